@@ -29,6 +29,38 @@ pub async fn tcp_connect_in(addr: SocketAddr, pool: PoolRef) -> Result<Io> {
     Ok(Io::with_memory_pool(TcpStream(sock), pool))
 }
 
+/// Opens a TCP connection to a remote host using bound socket and
+/// specified memory pool.
+///
+/// Socket is connected in non-blocking mode, connection completion is
+/// awaited on async-std reactor.
+pub async fn tcp_connect_bound_in(
+    sock: net::TcpStream,
+    addr: SocketAddr,
+    pool: PoolRef,
+) -> Result<Io> {
+    let sock = socket2::Socket::from(sock);
+    sock.set_nonblocking(true)?;
+    match sock.connect(&addr.into()) {
+        Ok(()) => (),
+        #[cfg(unix)]
+        Err(e) if e.raw_os_error() == Some(libc::EINPROGRESS) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => (),
+        Err(e) => return Err(e),
+    }
+
+    // wait for connect completion
+    let sock = async_io::Async::new(net::TcpStream::from(sock))?;
+    sock.writable().await?;
+    if let Some(e) = sock.get_ref().take_error()? {
+        return Err(e);
+    }
+
+    let sock = async_std::net::TcpStream::from(sock.into_inner()?);
+    sock.set_nodelay(true)?;
+    Ok(Io::with_memory_pool(TcpStream(sock), pool))
+}
+
 #[cfg(unix)]
 /// Opens a unix stream connection.
 pub async fn unix_connect<P>(addr: P) -> Result<Io>
@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, net::IpAddr};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ConnectError {
@@ -18,6 +18,10 @@ pub enum ConnectError {
     #[error("Connector received `Connect` method with unresolved host")]
     Unresolved,
 
+    /// Failed to bind socket to the local address
+    #[error("Cannot bind socket to local address {0}: {1}")]
+    Bind(IpAddr, io::Error),
+
     /// Connection io error
     #[error("{0}")]
     Io(#[from] io::Error),
@@ -32,6 +36,9 @@ impl Clone for ConnectError {
             ConnectError::NoRecords => ConnectError::NoRecords,
             ConnectError::InvalidInput => ConnectError::InvalidInput,
             ConnectError::Unresolved => ConnectError::Unresolved,
+            ConnectError::Bind(addr, err) => {
+                ConnectError::Bind(*addr, io::Error::new(err.kind(), format!("{}", err)))
+            }
             ConnectError::Io(err) => {
                 ConnectError::Io(io::Error::new(err.kind(), format!("{}", err)))
             }
@@ -51,6 +58,11 @@ mod tests {
         let _ = ConnectError::NoRecords.clone();
         let _ = ConnectError::InvalidInput.clone();
         let _ = ConnectError::Unresolved.clone();
+        let _ = ConnectError::Bind(
+            IpAddr::from([127, 0, 0, 1]),
+            io::Error::new(io::ErrorKind::AddrNotAvailable, "test"),
+        )
+        .clone();
         let _ = ConnectError::Io(io::Error::new(io::ErrorKind::Other, "test")).clone();
     }
 }
@@ -84,6 +84,24 @@ pub mod net {
         ))
     }
 
+    #[cfg(all(
+        not(feature = "tokio"),
+        not(feature = "async-std"),
+        not(feature = "glommio")
+    ))]
+    /// Opens a TCP connection to a remote host using bound socket and
+    /// specified memory pool.
+    pub async fn tcp_connect_bound_in(
+        _: std::net::TcpStream,
+        _: std::net::SocketAddr,
+        _: ntex_bytes::PoolRef,
+    ) -> std::io::Result<Io> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "runtime is not configure",
+        ))
+    }
+
     #[cfg(unix)]
     #[cfg(all(
         not(feature = "tokio"),
@@ -1,5 +1,5 @@
 use std::collections::{vec_deque, VecDeque};
-use std::{fmt, iter::FusedIterator, net::SocketAddr};
+use std::{fmt, iter::FusedIterator, net::IpAddr, net::SocketAddr};
 
 use ntex_util::future::Either;
 
@@ -57,6 +57,7 @@ pub struct Connect<T> {
     pub(super) req: T,
     pub(super) port: u16,
     pub(super) addr: Option<Either<SocketAddr, VecDeque<SocketAddr>>>,
+    pub(super) local: Option<IpAddr>,
 }
 
 impl<T: Address> Connect<T> {
@@ -67,6 +68,7 @@ impl<T: Address> Connect<T> {
             req,
             port: port.unwrap_or(0),
             addr: None,
+            local: None,
         }
     }
 
@@ -76,6 +78,7 @@ impl<T: Address> Connect<T> {
             req,
             port: 0,
             addr: Some(Either::Left(addr)),
+            local: None,
         }
     }
 
@@ -109,6 +112,14 @@ impl<T: Address> Connect<T> {
         self
     }
 
+    /// Bind socket to the local address before connecting.
+    ///
+    /// By default socket is bound by operating system.
+    pub fn set_local_addr(mut self, addr: Option<IpAddr>) -> Self {
+        self.local = addr;
+        self
+    }
+
     /// Host name
     pub fn host(&self) -> &str {
         self.req.host()
@@ -119,6 +130,11 @@ impl<T: Address> Connect<T> {
         self.req.port().unwrap_or(self.port)
     }
 
+    /// Local address of the request
+    pub fn local_addr(&self) -> Option<IpAddr> {
+        self.local
+    }
+
     /// Preresolved addresses of the request.
     pub fn addrs(&self) -> ConnectAddrsIter<'_> {
         if let Some(addr) = self.req.addr() {
@@ -299,6 +315,11 @@ mod tests {
         connect = connect.set_addrs(vec![addr]);
         assert_eq!(format!("{}", connect), "www.rust-lang.org:80");
 
+        assert_eq!(connect.local_addr(), None);
+        let local: IpAddr = "127.0.0.2".parse().unwrap();
+        connect = connect.set_local_addr(Some(local));
+        assert_eq!(connect.local_addr(), Some(local));
+
         let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
         let mut connect = Connect::new(addr);
         assert_eq!(connect.host(), "");
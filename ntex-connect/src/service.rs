@@ -1,12 +1,15 @@
+use std::net::{IpAddr, SocketAddr, TcpStream};
 use std::task::{Context, Poll};
-use std::{collections::VecDeque, fmt, future::Future, io, net::SocketAddr, pin::Pin};
+use std::{collections::VecDeque, fmt, future::Future, io, pin::Pin};
 
 use ntex_bytes::{PoolId, PoolRef};
 use ntex_io::{types, Io};
 use ntex_service::{Service, ServiceCtx, ServiceFactory};
 use ntex_util::future::{BoxFuture, Either, Ready};
+use socket2::{Domain, Socket, Type};
 
-use crate::{net::tcp_connect_in, Address, Connect, ConnectError, Resolver};
+use crate::net::{tcp_connect_bound_in, tcp_connect_in};
+use crate::{Address, Connect, ConnectError, Resolver};
 
 pub struct Connector<T> {
     resolver: Resolver<T>,
@@ -131,11 +134,13 @@ impl<'f, T: Address> Future for ConnectServiceResponse<'f, T> {
                 Poll::Pending => Poll::Pending,
                 Poll::Ready(address) => {
                     let port = address.port();
-                    let Connect { req, addr, .. } = address;
+                    let Connect {
+                        req, addr, local, ..
+                    } = address;
 
                     if let Some(addr) = addr {
                         self.state = ConnectState::Connect(TcpConnectorResponse::new(
-                            req, port, addr, self.pool,
+                            req, port, addr, local, self.pool,
                         ));
                         self.poll(cx)
                     } else if let Some(addr) = req.addr() {
@@ -143,6 +148,7 @@ impl<'f, T: Address> Future for ConnectServiceResponse<'f, T> {
                             req,
                             addr.port(),
                             Either::Left(addr),
+                            local,
                             self.pool,
                         ));
                         self.poll(cx)
@@ -162,8 +168,9 @@ struct TcpConnectorResponse<T> {
     req: Option<T>,
     port: u16,
     addrs: Option<VecDeque<SocketAddr>>,
+    local: Option<IpAddr>,
     #[allow(clippy::type_complexity)]
-    stream: Option<BoxFuture<'static, Result<Io, io::Error>>>,
+    stream: Option<BoxFuture<'static, Result<Io, ConnectError>>>,
     pool: PoolRef,
 }
 
@@ -172,6 +179,7 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: Either<SocketAddr, VecDeque<SocketAddr>>,
+        local: Option<IpAddr>,
         pool: PoolRef,
     ) -> TcpConnectorResponse<T> {
         trace!(
@@ -185,13 +193,15 @@ impl<T: Address> TcpConnectorResponse<T> {
             Either::Left(addr) => TcpConnectorResponse {
                 req: Some(req),
                 addrs: None,
-                stream: Some(Box::pin(tcp_connect_in(addr, pool))),
+                stream: Some(connect(addr, local, pool)),
+                local,
                 pool,
                 port,
             },
             Either::Right(addrs) => TcpConnectorResponse {
                 port,
                 pool,
+                local,
                 req: Some(req),
                 addrs: Some(addrs),
                 stream: None,
@@ -199,7 +209,7 @@ impl<T: Address> TcpConnectorResponse<T> {
         }
     }
 
-    fn can_continue(&self, err: &io::Error) -> bool {
+    fn can_continue(&self, err: &ConnectError) -> bool {
         trace!(
             "TCP connector - failed to connect to {:?} port: {} err: {:?}",
             self.req.as_ref().unwrap().host(),
@@ -232,7 +242,7 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
                     Poll::Pending => return Poll::Pending,
                     Poll::Ready(Err(err)) => {
                         if !this.can_continue(&err) {
-                            return Poll::Ready(Err(err.into()));
+                            return Poll::Ready(Err(err));
                         }
                     }
                 }
@@ -240,11 +250,37 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
 
             // try to connect
             let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(Box::pin(tcp_connect_in(addr, this.pool)));
+            this.stream = Some(connect(addr, this.local, this.pool));
         }
     }
 }
 
+/// Open connection, socket is bound to the local address if it is provided
+fn connect(
+    addr: SocketAddr,
+    local: Option<IpAddr>,
+    pool: PoolRef,
+) -> BoxFuture<'static, Result<Io, ConnectError>> {
+    if let Some(local) = local {
+        match bind(&addr, local) {
+            Ok(sock) => {
+                Box::pin(async move { Ok(tcp_connect_bound_in(sock, addr, pool).await?) })
+            }
+            Err(err) => Box::pin(async move { Err(ConnectError::Bind(local, err)) }),
+        }
+    } else {
+        Box::pin(async move { Ok(tcp_connect_in(addr, pool).await?) })
+    }
+}
+
+/// Create non-blocking socket bound to the local address
+fn bind(addr: &SocketAddr, local: IpAddr) -> io::Result<TcpStream> {
+    let sock = Socket::new(Domain::for_address(*addr), Type::STREAM, None)?;
+    sock.set_nonblocking(true)?;
+    sock.bind(&SocketAddr::new(local, 0).into())?;
+    Ok(sock.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,4 +314,29 @@ mod tests {
         let result = crate::connect(msg).await;
         assert!(result.is_ok());
     }
+
+    #[ntex::test]
+    async fn test_connect_bound() {
+        let server = ntex::server::test_server(|| {
+            ntex_service::fn_service(|_| async { Ok::<_, ()>(()) })
+        });
+
+        let srv = Connector::default();
+        let local: IpAddr = "127.0.0.1".parse().unwrap();
+        let msg = Connect::new(server.addr()).set_local_addr(Some(local));
+        let result = srv.connect(msg).await;
+        assert!(result.is_ok());
+
+        // address of different family
+        let local: IpAddr = "::1".parse().unwrap();
+        let msg = Connect::new(server.addr()).set_local_addr(Some(local));
+        let result = srv.connect(msg).await;
+        assert!(matches!(result, Err(ConnectError::Bind(addr, _)) if addr == local));
+
+        // address does not belong to the host
+        let local: IpAddr = "192.0.2.1".parse().unwrap();
+        let msg = Connect::new(server.addr()).set_local_addr(Some(local));
+        let err = srv.connect(msg).await.err().unwrap();
+        assert!(format!("{}", err).contains("192.0.2.1"));
+    }
 }
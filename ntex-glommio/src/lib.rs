@@ -46,6 +46,31 @@ mod net_impl {
         Ok(Io::with_memory_pool(TcpStream::new(sock), pool))
     }
 
+    /// Opens a TCP connection to a remote host using bound socket and
+    /// specified memory pool.
+    ///
+    /// Socket is connected on glommio's blocking thread pool, glommio does
+    /// not provide readiness notifications for foreign sockets. Each pending
+    /// connect occupies a pool thread until connection is established or
+    /// fails, so connect timeout is bound by the OS tcp settings.
+    pub async fn tcp_connect_bound_in(
+        sock: net::TcpStream,
+        addr: SocketAddr,
+        pool: PoolRef,
+    ) -> Result<Io> {
+        let sock = glommio::executor()
+            .spawn_blocking(move || {
+                let sock = socket2::Socket::from(sock);
+                sock.set_nonblocking(false)?;
+                sock.connect(&addr.into())?;
+                Ok::<_, std::io::Error>(sock)
+            })
+            .await?;
+        let sock = unsafe { glommio::net::TcpStream::from_raw_fd(sock.into_raw_fd()) };
+        sock.set_nodelay(true)?;
+        Ok(Io::with_memory_pool(TcpStream::new(sock), pool))
+    }
+
     /// Opens a unix stream connection.
     pub async fn unix_connect<P>(addr: P) -> Result<Io>
     where
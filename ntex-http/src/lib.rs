@@ -33,6 +33,7 @@ pub mod header {
 
     #[doc(hidden)]
     pub use crate::map::{AsName, Either, GetAll, Iter, Value};
+    pub use crate::map::{Drain, Entry, OccupiedEntry, VacantEntry};
     pub use crate::value::{HeaderValue, InvalidHeaderValue, ToStrError};
 
     pub use http::header::{HeaderName, InvalidHeaderName};
@@ -1,4 +1,4 @@
-use std::collections::{self, hash_map, hash_map::Entry, VecDeque};
+use std::collections::{self, hash_map, VecDeque};
 
 use crate::{HeaderName, HeaderValue};
 
@@ -321,13 +321,52 @@ impl HeaderMap {
     /// identical.
     pub fn append(&mut self, key: HeaderName, value: HeaderValue) {
         match self.inner.entry(key) {
-            Entry::Occupied(mut entry) => entry.get_mut().append(value),
-            Entry::Vacant(entry) => {
+            hash_map::Entry::Occupied(mut entry) => entry.get_mut().append(value),
+            hash_map::Entry::Vacant(entry) => {
                 entry.insert(Value::One(value));
             }
         }
     }
 
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    pub fn entry(&mut self, key: HeaderName) -> Entry<'_> {
+        match self.inner.entry(key) {
+            hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry(entry)),
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry(entry)),
+        }
+    }
+
+    /// Retains only the headers specified by the predicate.
+    ///
+    /// Each value of multi-value header is evaluated independently,
+    /// header is removed if none of its values are retained.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&HeaderName, &HeaderValue) -> bool,
+    {
+        self.inner.retain(|name, value| match value {
+            Value::One(ref val) => f(name, val),
+            Value::Multi(ref mut vec) => {
+                vec.retain(|val| f(name, val));
+                !vec.is_empty()
+            }
+        });
+    }
+
+    /// Clears the map, returning all headers as an iterator.
+    ///
+    /// Header names are yielded in arbitrary order, the same as with
+    /// [`iter()`](Self::iter). All values of a header are yielded one after
+    /// another, in insertion order. If the iterator is dropped before it is
+    /// fully consumed, remaining headers are dropped. Map keeps allocated
+    /// memory for reuse.
+    pub fn drain(&mut self) -> Drain<'_> {
+        Drain {
+            iter: self.inner.drain(),
+            current: None,
+        }
+    }
+
     /// Removes all headers for a particular header name from the map.
     pub fn remove<N: AsName>(&mut self, key: N) {
         match key.as_name() {
@@ -409,8 +448,8 @@ where
             })
             .fold(HashMap::default(), |mut map: HashMap<_, Value>, (n, v)| {
                 match map.entry(n) {
-                    Entry::Occupied(mut oc) => oc.get_mut().extend(v),
-                    Entry::Vacant(va) => {
+                    hash_map::Entry::Occupied(mut oc) => oc.get_mut().extend(v),
+                    hash_map::Entry::Vacant(va) => {
                         let _ = va.insert(v);
                     }
                 }
@@ -445,6 +484,157 @@ impl TryFrom<&str> for Value {
     }
 }
 
+/// A view into a single entry in a map, which may either be vacant or occupied.
+#[derive(Debug)]
+pub enum Entry<'a> {
+    /// An occupied entry
+    Occupied(OccupiedEntry<'a>),
+    /// A vacant entry
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        match self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty.
+    ///
+    /// Returns a mutable reference to the first value in the entry.
+    pub fn or_insert(self, default: HeaderValue) -> &'a mut HeaderValue {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the
+    /// default function if empty.
+    pub fn or_insert_with<F: FnOnce() -> HeaderValue>(
+        self,
+        default: F,
+    ) -> &'a mut HeaderValue {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Appends value to the entry.
+    ///
+    /// If entry is occupied, value is pushed to the end of the list
+    /// of the entry's values.
+    pub fn append(self, value: HeaderValue) {
+        match self {
+            Entry::Occupied(mut entry) => entry.append(value),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+}
+
+/// A view into an occupied entry in a `HeaderMap`.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a>(hash_map::OccupiedEntry<'a, HeaderName, Value>);
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        self.0.key()
+    }
+
+    /// Returns a reference to the first value in the entry.
+    pub fn get(&self) -> &HeaderValue {
+        self.0.get().get()
+    }
+
+    /// Returns a mutable reference to the first value in the entry.
+    pub fn get_mut(&mut self) -> &mut HeaderValue {
+        self.0.get_mut().get_mut()
+    }
+
+    /// Converts the entry into a mutable reference to its first value.
+    pub fn into_mut(self) -> &'a mut HeaderValue {
+        self.0.into_mut().get_mut()
+    }
+
+    /// Returns an iterator visiting all values associated with the entry.
+    pub fn iter(&self) -> GetAll<'_> {
+        GetAll {
+            idx: 0,
+            item: Some(self.0.get()),
+        }
+    }
+
+    /// Sets the value of the entry, all previous values are removed.
+    pub fn insert(&mut self, value: HeaderValue) {
+        let _ = self.0.insert(Value::One(value));
+    }
+
+    /// Pushes value to the end of the list of the entry's values.
+    pub fn append(&mut self, value: HeaderValue) {
+        self.0.get_mut().append(value)
+    }
+
+    /// Removes the entry from the map, returns all values.
+    pub fn remove(self) -> ValueIntoIter {
+        self.0.remove().into_iter()
+    }
+}
+
+/// A view into a vacant entry in a `HeaderMap`.
+#[derive(Debug)]
+pub struct VacantEntry<'a>(hash_map::VacantEntry<'a, HeaderName, Value>);
+
+impl<'a> VacantEntry<'a> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &HeaderName {
+        self.0.key()
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> HeaderName {
+        self.0.into_key()
+    }
+
+    /// Sets the value of the entry, returns a mutable reference to it.
+    pub fn insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        self.0.insert(Value::One(value)).get_mut()
+    }
+}
+
+/// A draining iterator over the headers of a `HeaderMap`.
+#[derive(Debug)]
+pub struct Drain<'a> {
+    iter: hash_map::Drain<'a, HeaderName, Value>,
+    current: Option<(HeaderName, ValueIntoIter)>,
+}
+
+impl<'a> Iterator for Drain<'a> {
+    type Item = (HeaderName, HeaderValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((ref name, ref mut values)) = self.current {
+                if let Some(value) = values.next() {
+                    return Some((name.clone(), value));
+                }
+                self.current = None;
+            }
+
+            match self.iter.next()? {
+                (name, Value::One(value)) => return Some((name, value)),
+                (name, value) => self.current = Some((name, value.into_iter())),
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GetAll<'a> {
     idx: usize,
@@ -642,6 +832,108 @@ mod tests {
         assert_eq!(map.get(ACCEPT_ENCODING), None);
     }
 
+    #[test]
+    fn test_entry() {
+        let mut map = HeaderMap::new();
+
+        let val = map
+            .entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("text"));
+        assert_eq!(val, "text");
+        *map.entry(CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static("json")) = HeaderValue::from_static("html");
+        assert_eq!(map.get(CONTENT_TYPE).unwrap(), "html");
+
+        map.entry(ACCEPT_ENCODING)
+            .append(HeaderValue::from_static("gzip"));
+        map.entry(ACCEPT_ENCODING)
+            .append(HeaderValue::from_static("br"));
+        assert_eq!(
+            map.get_all(ACCEPT_ENCODING).collect::<Vec<_>>(),
+            vec![
+                &HeaderValue::from_static("gzip"),
+                &HeaderValue::from_static("br"),
+            ]
+        );
+
+        match map.entry(ACCEPT_ENCODING) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), ACCEPT_ENCODING);
+                assert_eq!(entry.get(), "gzip");
+                assert_eq!(entry.iter().count(), 2);
+                entry.append(HeaderValue::from_static("deflate"));
+                assert_eq!(entry.iter().count(), 3);
+                entry.insert(HeaderValue::from_static("zstd"));
+                assert_eq!(entry.iter().collect::<Vec<_>>(), vec!["zstd"]);
+                assert_eq!(entry.remove().collect::<Vec<_>>(), vec!["zstd"]);
+            }
+            Entry::Vacant(_) => panic!(),
+        }
+        assert!(!map.contains_key(ACCEPT_ENCODING));
+
+        match map.entry(ACCEPT_ENCODING) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), ACCEPT_ENCODING);
+                entry.insert(HeaderValue::from_static("br"));
+            }
+            Entry::Occupied(_) => panic!(),
+        }
+        assert_eq!(map.get(ACCEPT_ENCODING).unwrap(), "br");
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut map = HeaderMap::new();
+        map.insert(CONTENT_TYPE, HeaderValue::from_static("text"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("br"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("deflate"));
+
+        map.retain(|_, val| val != "br");
+        assert_eq!(map.get(CONTENT_TYPE).unwrap(), "text");
+        assert_eq!(
+            map.get_all(ACCEPT_ENCODING).collect::<Vec<_>>(),
+            vec!["gzip", "deflate"]
+        );
+
+        map.retain(|name, val| name == CONTENT_TYPE || val == "zstd");
+        assert!(!map.contains_key(ACCEPT_ENCODING));
+        assert_eq!(map.get(ACCEPT_ENCODING), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut map = HeaderMap::new();
+        map.insert(CONTENT_TYPE, HeaderValue::from_static("text"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("br"));
+
+        let mut items = map.drain().collect::<Vec<_>>();
+        assert!(map.is_empty());
+        // values of a header are not interleaved with other headers
+        let pos = items.iter().position(|i| i.0 == ACCEPT_ENCODING).unwrap();
+        assert_eq!(items[pos + 1].0, ACCEPT_ENCODING);
+        items.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        assert_eq!(
+            items,
+            vec![
+                (ACCEPT_ENCODING, HeaderValue::from_static("gzip")),
+                (ACCEPT_ENCODING, HeaderValue::from_static("br")),
+                (CONTENT_TYPE, HeaderValue::from_static("text")),
+            ]
+        );
+
+        // partially consumed drain clears the map
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        map.append(ACCEPT_ENCODING, HeaderValue::from_static("br"));
+        assert_eq!(
+            map.drain().next(),
+            Some((ACCEPT_ENCODING, HeaderValue::from_static("gzip")))
+        );
+        assert!(map.is_empty());
+    }
+
     #[test]
     fn test_from_http() {
         let mut map = http::HeaderMap::new();
@@ -147,13 +147,13 @@ impl Stack {
         let item = self.get_last_level();
         let mut rb = item.0.take();
         if rb.is_none() {
-            rb = Some(io.memory_pool().get_read_buf());
+            rb = Some(io.get_read_buf());
         }
 
         let result = f(rb.as_mut().unwrap());
         if let Some(b) = rb {
             if b.is_empty() {
-                io.memory_pool().release_read_buf(b);
+                io.release_read_buf(b);
             } else {
                 item.0.set(Some(b));
             }
@@ -168,7 +168,7 @@ impl Stack {
         let item = self.get_first_level();
         let mut rb = item.0.take();
         if rb.is_none() {
-            rb = Some(io.memory_pool().get_read_buf());
+            rb = Some(io.get_read_buf());
         }
 
         let result = f(rb.as_mut().unwrap());
@@ -181,7 +181,7 @@ impl Stack {
 
         if let Some(b) = rb {
             if b.is_empty() {
-                io.memory_pool().release_read_buf(b);
+                io.release_read_buf(b);
             } else {
                 item.0.set(Some(b));
             }
@@ -196,13 +196,13 @@ impl Stack {
         let item = self.get_first_level();
         let mut wb = item.1.take();
         if wb.is_none() {
-            wb = Some(io.memory_pool().get_write_buf());
+            wb = Some(io.get_write_buf());
         }
 
         let result = f(wb.as_mut().unwrap());
         if let Some(b) = wb {
             if b.is_empty() {
-                io.memory_pool().release_write_buf(b);
+                io.release_write_buf(b);
             } else {
                 item.1.set(Some(b));
             }
@@ -227,7 +227,7 @@ impl Stack {
 
         if let Some(b) = wb {
             if b.is_empty() {
-                io.memory_pool().release_write_buf(b);
+                io.release_write_buf(b);
             } else {
                 item.1.set(Some(b));
             }
@@ -267,6 +267,40 @@ impl Stack {
         }
     }
 
+    pub(crate) fn shrink(&self, io: &IoRef) {
+        let items = match &self.buffers {
+            Either::Left(b) => &b[..],
+            Either::Right(b) => &b[..],
+        };
+        let (read_hw, _) = io.read_params();
+        let (write_hw, _) = io.write_params();
+
+        for item in items {
+            if let Some(buf) = item.0.take() {
+                if buf.is_empty() {
+                    io.release_read_buf(buf);
+                } else if buf.capacity() > read_hw && buf.len() < read_hw {
+                    let mut b = io.get_read_buf();
+                    b.extend_from_slice(&buf);
+                    item.0.set(Some(b));
+                } else {
+                    item.0.set(Some(buf));
+                }
+            }
+            if let Some(buf) = item.1.take() {
+                if buf.is_empty() {
+                    io.release_write_buf(buf);
+                } else if buf.capacity() > write_hw && buf.len() < write_hw {
+                    let mut b = io.get_write_buf();
+                    b.extend_from_slice(&buf);
+                    item.1.set(Some(b));
+                } else {
+                    item.1.set(Some(buf));
+                }
+            }
+        }
+    }
+
     pub(crate) fn set_memory_pool(&self, pool: PoolRef) {
         let items = match &self.buffers {
             Either::Left(b) => &b[..],
@@ -310,7 +344,7 @@ impl<'a> ReadBuf<'a> {
     #[inline]
     /// Make sure buffer has enough free space
     pub fn resize_buf(&self, buf: &mut BytesVec) {
-        self.io.memory_pool().resize_read_buf(buf);
+        self.io.resize_read_buf(buf);
     }
 
     #[inline]
@@ -324,7 +358,7 @@ impl<'a> ReadBuf<'a> {
 
         if let Some(b) = item {
             if b.is_empty() {
-                self.io.memory_pool().release_read_buf(b);
+                self.io.release_read_buf(b);
             } else {
                 self.next.0.set(Some(b));
             }
@@ -340,12 +374,12 @@ impl<'a> ReadBuf<'a> {
     {
         let mut item = self.curr.0.take();
         if item.is_none() {
-            item = Some(self.io.memory_pool().get_read_buf());
+            item = Some(self.io.get_read_buf());
         }
         let result = f(item.as_mut().unwrap());
         if let Some(b) = item {
             if b.is_empty() {
-                self.io.memory_pool().release_read_buf(b);
+                self.io.release_read_buf(b);
             } else {
                 self.curr.0.set(Some(b));
             }
@@ -358,7 +392,7 @@ impl<'a> ReadBuf<'a> {
     pub fn take_src(&self) -> Option<BytesVec> {
         self.next.0.take().and_then(|b| {
             if b.is_empty() {
-                self.io.memory_pool().release_read_buf(b);
+                self.io.release_read_buf(b);
                 None
             } else {
                 Some(b)
@@ -371,11 +405,11 @@ impl<'a> ReadBuf<'a> {
     pub fn set_src(&self, src: Option<BytesVec>) {
         if let Some(src) = src {
             if src.is_empty() {
-                self.io.memory_pool().release_read_buf(src);
+                self.io.release_read_buf(src);
             } else if let Some(mut buf) = self.next.0.take() {
                 buf.extend_from_slice(&src);
                 self.next.0.set(Some(buf));
-                self.io.memory_pool().release_read_buf(src);
+                self.io.release_read_buf(src);
             } else {
                 self.next.0.set(Some(src));
             }
@@ -385,10 +419,7 @@ impl<'a> ReadBuf<'a> {
     #[inline]
     /// Take destination read buffer
     pub fn take_dst(&self) -> BytesVec {
-        self.curr
-            .0
-            .take()
-            .unwrap_or_else(|| self.io.memory_pool().get_read_buf())
+        self.curr.0.take().unwrap_or_else(|| self.io.get_read_buf())
     }
 
     #[inline]
@@ -396,11 +427,11 @@ impl<'a> ReadBuf<'a> {
     pub fn set_dst(&self, dst: Option<BytesVec>) {
         if let Some(dst) = dst {
             if dst.is_empty() {
-                self.io.memory_pool().release_read_buf(dst);
+                self.io.release_read_buf(dst);
             } else if let Some(mut buf) = self.curr.0.take() {
                 buf.extend_from_slice(&dst);
                 self.curr.0.set(Some(buf));
-                self.io.memory_pool().release_read_buf(dst);
+                self.io.release_read_buf(dst);
             } else {
                 self.curr.0.set(Some(dst));
             }
@@ -442,7 +473,7 @@ impl<'a> WriteBuf<'a> {
     #[inline]
     /// Make sure buffer has enough free space
     pub fn resize_buf(&self, buf: &mut BytesVec) {
-        self.io.memory_pool().resize_write_buf(buf);
+        self.io.resize_write_buf(buf);
     }
 
     #[inline]
@@ -455,7 +486,7 @@ impl<'a> WriteBuf<'a> {
         let result = f(&mut item);
         if let Some(b) = item {
             if b.is_empty() {
-                self.io.memory_pool().release_write_buf(b);
+                self.io.release_write_buf(b);
             } else {
                 self.curr.1.set(Some(b));
             }
@@ -471,14 +502,14 @@ impl<'a> WriteBuf<'a> {
     {
         let mut item = self.next.1.take();
         if item.is_none() {
-            item = Some(self.io.memory_pool().get_write_buf());
+            item = Some(self.io.get_write_buf());
         }
         let buf = item.as_mut().unwrap();
         let total = buf.len();
         let result = f(buf);
 
         if buf.is_empty() {
-            self.io.memory_pool().release_write_buf(item.unwrap());
+            self.io.release_write_buf(item.unwrap());
         } else {
             self.need_write
                 .set(self.need_write.get() | (total != buf.len()));
@@ -492,7 +523,7 @@ impl<'a> WriteBuf<'a> {
     pub fn take_src(&self) -> Option<BytesVec> {
         self.curr.1.take().and_then(|b| {
             if b.is_empty() {
-                self.io.memory_pool().release_write_buf(b);
+                self.io.release_write_buf(b);
                 None
             } else {
                 Some(b)
@@ -505,11 +536,11 @@ impl<'a> WriteBuf<'a> {
     pub fn set_src(&self, src: Option<BytesVec>) {
         if let Some(src) = src {
             if src.is_empty() {
-                self.io.memory_pool().release_write_buf(src);
+                self.io.release_write_buf(src);
             } else if let Some(mut buf) = self.curr.1.take() {
                 buf.extend_from_slice(&src);
                 self.curr.1.set(Some(buf));
-                self.io.memory_pool().release_write_buf(src);
+                self.io.release_write_buf(src);
             } else {
                 self.curr.1.set(Some(src));
             }
@@ -522,7 +553,7 @@ impl<'a> WriteBuf<'a> {
         self.next
             .1
             .take()
-            .unwrap_or_else(|| self.io.memory_pool().get_write_buf())
+            .unwrap_or_else(|| self.io.get_write_buf())
     }
 
     #[inline]
@@ -530,14 +561,14 @@ impl<'a> WriteBuf<'a> {
     pub fn set_dst(&self, dst: Option<BytesVec>) {
         if let Some(dst) = dst {
             if dst.is_empty() {
-                self.io.memory_pool().release_write_buf(dst);
+                self.io.release_write_buf(dst);
             } else {
                 self.need_write.set(true);
 
                 if let Some(mut buf) = self.next.1.take() {
                     buf.extend_from_slice(&dst);
                     self.next.1.set(Some(buf));
-                    self.io.memory_pool().release_write_buf(dst);
+                    self.io.release_write_buf(dst);
                 } else {
                     self.next.1.set(Some(dst));
                 }
@@ -1,19 +1,26 @@
 //! Framed transport dispatcher
-use std::{cell::Cell, future, pin::Pin, rc::Rc, task::Context, task::Poll, time};
+use std::{cell::Cell, cell::RefCell, fmt, future, pin::Pin, rc::Rc, task::Context};
+use std::{task::Poll, time};
 
 use ntex_bytes::Pool;
 use ntex_codec::{Decoder, Encoder};
 use ntex_service::{IntoService, Pipeline, Service};
+use ntex_util::channel::condition::{Condition, Waiter};
 use ntex_util::time::Seconds;
-use ntex_util::{future::Either, ready, spawn};
+use ntex_util::{future::poll_fn, future::Either, ready, spawn};
 
-use crate::{DispatchItem, IoBoxed, IoStatusUpdate, RecvError};
+use crate::{DispatchItem, IoBoxed, IoRef, IoStatusUpdate, RecvError};
 
 type Response<U> = <U as Encoder>::Item;
 
 pin_project_lite::pin_project! {
     /// Dispatcher - is a future that reads frames from bytes stream
     /// and pass then to the service.
+    ///
+    /// Dispatcher stops reading new frames while write buffer is over
+    /// the high watermark. Keep-alive timer is restarted by incoming
+    /// frames and by outgoing items, connection without traffic in
+    /// either direction is closed.
     pub struct Dispatcher<S, U>
     where
         S: Service<DispatchItem<U>, Response = Option<Response<U>>>,
@@ -37,7 +44,6 @@ where
     U: Encoder + Decoder,
 {
     st: Cell<DispatcherState>,
-    ka_timeout: Cell<time::Duration>,
     error: Cell<Option<S::Error>>,
     flags: Cell<Flags>,
     shared: Rc<DispatcherShared<S, U>>,
@@ -50,12 +56,18 @@ where
     U: Encoder + Decoder,
 {
     io: IoBoxed,
-    codec: U,
+    sink: Rc<SinkShared<U>>,
     service: Pipeline<S>,
     error: Cell<Option<DispatcherError<S::Error, <U as Encoder>::Error>>>,
     inflight: Cell<usize>,
 }
 
+struct SinkShared<U> {
+    codec: U,
+    ka_timeout: Cell<time::Duration>,
+    ready: Condition,
+}
+
 #[derive(Copy, Clone, Debug)]
 enum DispatcherState {
     Processing,
@@ -105,7 +117,11 @@ where
         let pool = io.memory_pool().pool();
         let shared = Rc::new(DispatcherShared {
             io,
-            codec,
+            sink: Rc::new(SinkShared {
+                codec,
+                ka_timeout,
+                ready: Condition::new(),
+            }),
             error: Cell::new(None),
             inflight: Cell::new(0),
             service: Pipeline::new(service.into_service()),
@@ -115,7 +131,6 @@ where
             inner: DispatcherInner {
                 pool,
                 shared,
-                ka_timeout,
                 error: Cell::new(None),
                 flags: Cell::new(Flags::empty()),
                 st: Cell::new(DispatcherState::Processing),
@@ -139,7 +154,7 @@ where
 
         // register keepalive timer
         self.inner.shared.io.start_keepalive_timer(ka_timeout);
-        self.inner.ka_timeout.set(ka_timeout);
+        self.inner.shared.sink.ka_timeout.set(ka_timeout);
 
         self
     }
@@ -156,6 +171,19 @@ where
         self.inner.shared.io.set_disconnect_timeout(val.into());
         self
     }
+
+    /// Get sink for sending items to the peer.
+    ///
+    /// Sink could be used by other tasks for server-initiated items,
+    /// it respects write back-pressure of the dispatcher.
+    pub fn sink(&self) -> DispatcherSink<U> {
+        let sink = self.inner.shared.sink.clone();
+        DispatcherSink {
+            io: self.inner.shared.io.get_ref(),
+            waiter: RefCell::new(sink.ready.wait()),
+            sink,
+        }
+    }
 }
 
 impl<S, U> DispatcherShared<S, U>
@@ -167,8 +195,10 @@ where
         self.inflight.set(self.inflight.get() - 1);
         match item {
             Ok(Some(val)) => {
-                if let Err(err) = io.encode(val, &self.codec) {
+                if let Err(err) = io.encode(val, &self.sink.codec) {
                     self.error.set(Some(DispatcherError::Encoder(err)))
+                } else {
+                    io.start_keepalive_timer(self.sink.ka_timeout.get());
                 }
             }
             Err(err) => self.error.set(Some(DispatcherError::Service(err))),
@@ -206,7 +236,7 @@ where
                     )) {
                         PollService::Ready => {
                             // decode incoming bytes if buffer is ready
-                            match ready!(io.poll_recv(&slf.shared.codec, cx)) {
+                            match ready!(io.poll_recv(&slf.shared.sink.codec, cx)) {
                                 Ok(el) => {
                                     slf.update_keepalive();
                                     DispatchItem::Item(el)
@@ -264,6 +294,7 @@ where
                         PollService::Ready => {
                             if slf.shared.io.poll_flush(cx, false).is_ready() {
                                 slf.st.set(DispatcherState::Processing);
+                                slf.shared.sink.ready.notify();
                                 DispatchItem::WBackPressureDisabled
                             } else {
                                 return Poll::Pending;
@@ -284,6 +315,7 @@ where
                 // drain service responses and shutdown io
                 DispatcherState::Stop => {
                     slf.unregister_keepalive();
+                    slf.shared.sink.ready.notify();
 
                     // service may relay on poll_ready for response results
                     if !slf.flags.get().contains(Flags::READY_ERR) {
@@ -409,13 +441,88 @@ where
 
     /// update keep-alive timer
     fn update_keepalive(&self) {
-        self.shared.io.start_keepalive_timer(self.ka_timeout.get());
+        self.shared
+            .io
+            .start_keepalive_timer(self.shared.sink.ka_timeout.get());
     }
 
     /// unregister keep-alive timer
     fn unregister_keepalive(&self) {
         self.shared.io.stop_keepalive_timer();
-        self.ka_timeout.set(time::Duration::ZERO);
+        self.shared.sink.ka_timeout.set(time::Duration::ZERO);
+    }
+}
+
+/// Sink for sending items to the peer from outside of the dispatcher's service.
+///
+/// Sink is ready if write buffer is not over the high watermark, otherwise
+/// it waits until dispatcher flushes write buffer.
+pub struct DispatcherSink<U> {
+    io: IoRef,
+    sink: Rc<SinkShared<U>>,
+    waiter: RefCell<Waiter>,
+}
+
+impl<U> Clone for DispatcherSink<U> {
+    fn clone(&self) -> Self {
+        DispatcherSink {
+            io: self.io.clone(),
+            sink: self.sink.clone(),
+            waiter: RefCell::new(self.sink.ready.wait()),
+        }
+    }
+}
+
+impl<U> fmt::Debug for DispatcherSink<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatcherSink")
+            .field("io", &self.io)
+            .finish()
+    }
+}
+
+impl<U: Encoder> DispatcherSink<U> {
+    /// Io reference
+    pub fn io(&self) -> &IoRef {
+        &self.io
+    }
+
+    /// Check if sink is ready to accept items
+    pub fn is_ready(&self) -> bool {
+        self.io.is_closed() || !self.io.is_write_buf_full()
+    }
+
+    /// Wait until sink is ready to accept items.
+    ///
+    /// Resolves immediately if connection is closed.
+    pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if self.is_ready() {
+                return Poll::Ready(());
+            }
+
+            // dispatcher notifies waiters when write buffer get flushed
+            self.io.wake();
+            if self.waiter.borrow().poll_ready(cx).is_pending() {
+                return Poll::Pending;
+            }
+            *self.waiter.borrow_mut() = self.sink.ready.wait();
+        }
+    }
+
+    /// Wait until sink is ready to accept items.
+    pub async fn ready(&self) {
+        poll_fn(|cx| self.poll_ready(cx)).await
+    }
+
+    /// Encode and send item to the peer.
+    ///
+    /// Item is written to the write buffer regardless of sink readiness,
+    /// use `ready()` to wait for back-pressure.
+    pub fn send(&self, item: <U as Encoder>::Item) -> Result<(), <U as Encoder>::Error> {
+        self.io.encode(item, &self.sink.codec)?;
+        self.io.start_keepalive_timer(self.sink.ka_timeout.get());
+        Ok(())
     }
 }
 
@@ -428,7 +535,8 @@ mod tests {
     use ntex_bytes::{Bytes, PoolId, PoolRef};
     use ntex_codec::BytesCodec;
     use ntex_service::ServiceCtx;
-    use ntex_util::{future::Ready, time::sleep, time::Millis, time::Seconds};
+    use ntex_util::future::{lazy, Ready};
+    use ntex_util::{time::sleep, time::Millis, time::Seconds};
 
     use super::*;
     use crate::{io::Flags, testing::IoTest, Io, IoRef, IoStream};
@@ -473,7 +581,11 @@ mod tests {
             state.start_keepalive_timer(Duration::from_millis(500));
 
             let shared = Rc::new(DispatcherShared {
-                codec,
+                sink: Rc::new(SinkShared {
+                    codec,
+                    ka_timeout,
+                    ready: Condition::new(),
+                }),
                 io: state.into(),
                 error: Cell::new(None),
                 inflight: Cell::new(0),
@@ -488,7 +600,6 @@ mod tests {
                         st: Cell::new(DispatcherState::Processing),
                         pool,
                         shared,
-                        ka_timeout,
                     },
                 },
                 inner,
@@ -567,6 +678,102 @@ mod tests {
         //assert!(client.is_server_dropped());
     }
 
+    #[ntex::test]
+    async fn test_dispatcher_sink() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            ntex_service::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    Ok(None)
+                }
+            }),
+        );
+        state.0 .0.disconnect_timeout.set(Millis::ONE_SEC);
+        let disp = disp.keepalive_timeout(Seconds(1));
+        let sink = disp.sink();
+        assert!(format!("{:?}", sink).contains("DispatcherSink"));
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        // outgoing items keep connection alive
+        for _ in 0..4 {
+            sink.ready().await;
+            sink.send(Bytes::from_static(b"push")).unwrap();
+            let buf = client.read().await.unwrap();
+            assert_eq!(buf, Bytes::from_static(b"push"));
+            sleep(Millis(400)).await;
+        }
+        assert!(!state.flags().contains(Flags::IO_STOPPING));
+
+        // idle connection
+        sleep(Millis(1500)).await;
+        assert!(state.flags().contains(Flags::IO_STOPPING));
+        assert!(client.is_closed());
+
+        // sink is always ready for closed connection
+        assert!(sink.is_ready());
+        sink.ready().await;
+    }
+
+    #[ntex::test]
+    async fn test_dispatcher_sink_backpressure() {
+        let (client, server) = IoTest::create();
+        // do not allow to write to socket
+        client.remote_buffer_cap(0);
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            ntex_service::fn_service(|_: DispatchItem<BytesCodec>| async move {
+                Ok::<_, ()>(None)
+            }),
+        );
+        let pool = PoolId::P10.pool_ref();
+        pool.set_write_params(16 * 1024, 1024);
+        state.set_memory_pool(pool);
+
+        let disp = disp.keepalive_timeout(Seconds::ZERO);
+        let sink = disp.sink();
+        let sink2 = sink.clone();
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        assert!(sink.is_ready());
+        // over high watermark, below back-pressure limit
+        sink.send(Bytes::from(vec![b'a'; 20 * 1024])).unwrap();
+        assert!(sink.is_ready());
+        assert!(!sink.io().is_write_buf_full());
+        sink.send(Bytes::from(vec![b'a'; 20 * 1024])).unwrap();
+        assert!(!sink.is_ready());
+        assert!(sink.io().is_write_buf_full());
+        assert!(lazy(|cx| sink.poll_ready(cx)).await.is_pending());
+
+        let ready = Rc::new(Cell::new(false));
+        let ready2 = ready.clone();
+        spawn(async move {
+            sink2.ready().await;
+            ready2.set(true);
+        });
+        sleep(Millis(50)).await;
+        assert!(!ready.get());
+
+        // flush write buffer
+        client.remote_buffer_cap(40 * 1024);
+        sleep(Millis(50)).await;
+        assert!(ready.get());
+        assert!(sink.is_ready());
+        assert!(!sink.io().is_write_buf_full());
+        assert_eq!(client.read_any().len(), 40 * 1024);
+    }
+
     #[ntex::test]
     async fn test_err_in_service() {
         let (client, server) = IoTest::create();
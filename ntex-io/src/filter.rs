@@ -134,7 +134,7 @@ impl Filter for Base {
                     self.0 .0.remove_flags(Flags::WR_PAUSED);
                     self.0 .0.write_task.wake();
                 }
-                if len >= self.0.memory_pool().write_params_high() {
+                if len >= self.0.write_high_watermark() {
                     self.0 .0.insert_flags(Flags::WR_BACKPRESSURE);
                 }
             }
@@ -27,6 +27,43 @@ impl<U> Framed<U> {
         }
     }
 
+    #[inline]
+    /// Provides an interface for reading and writing to `Io` object
+    /// with specified read and write buffer capacities.
+    pub fn with_capacity<Io>(
+        io: Io,
+        codec: U,
+        read_cap: usize,
+        write_cap: usize,
+    ) -> Framed<U>
+    where
+        IoBoxed: From<Io>,
+    {
+        let io = IoBoxed::from(io);
+        io.set_buffer_capacity(read_cap, write_cap);
+        Framed { codec, io }
+    }
+
+    #[inline]
+    /// Set write buffer high watermark.
+    ///
+    /// By default write buffer capacity is used.
+    pub fn set_write_high_watermark(&self, size: usize) {
+        self.io.set_write_high_watermark(size)
+    }
+
+    #[inline]
+    /// Check if write buffer size reached twice the high watermark
+    pub fn is_write_buf_full(&self) -> bool {
+        self.io.is_write_buf_full()
+    }
+
+    #[inline]
+    /// Release empty buffers and shrink oversized buffers to its capacity
+    pub fn shrink_buffers(&self) {
+        self.io.shrink_buffers()
+    }
+
     #[inline]
     /// Returns a reference to the underlying I/O stream wrapped by `Framed`.
     pub fn get_io(&self) -> &IoBoxed {
@@ -40,10 +77,31 @@ impl<U> Framed<U> {
     }
 
     #[inline]
-    /// Return inner types of framed object
+    /// Return inner types of framed object.
+    ///
+    /// Read and write buffers are owned by io object, buffered data
+    /// is available to new `Framed` instance created with the same io.
     pub fn into_inner(self) -> (IoBoxed, U) {
         (self.io, self.codec)
     }
+
+    #[inline]
+    /// Replace codec, read and write buffers are preserved
+    pub fn into_framed<U2>(self, codec: U2) -> Framed<U2> {
+        Framed { codec, io: self.io }
+    }
+
+    #[inline]
+    /// Map io object, for example to add filter layer
+    pub fn map_io<F>(self, f: F) -> Framed<U>
+    where
+        F: FnOnce(IoBoxed) -> IoBoxed,
+    {
+        Framed {
+            codec: self.codec,
+            io: f(self.io),
+        }
+    }
 }
 
 impl<U> Framed<U>
@@ -103,7 +161,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use ntex_bytes::Bytes;
+    use ntex_bytes::{Bytes, BytesMut, PoolId};
     use ntex_codec::BytesCodec;
 
     use super::*;
@@ -131,4 +189,82 @@ mod tests {
         server.shutdown().await.unwrap();
         assert!(client.is_closed());
     }
+
+    #[derive(Debug)]
+    struct LineCodec;
+
+    impl Decoder for LineCodec {
+        type Item = Bytes;
+        type Error = io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+            Ok(src
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|idx| src.split_to(idx + 1).freeze()))
+        }
+    }
+
+    #[ntex::test]
+    async fn framed_into_framed() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(0);
+        client.write(b"line-0\nline-1\nrest");
+
+        let server = Framed::new(Io::new(server), LineCodec);
+        let item = server.recv().await.unwrap().unwrap();
+        assert_eq!(item, b"line-0\n".as_ref());
+
+        // unflushed data
+        server.get_io().write(b"out").unwrap();
+
+        let (io, _) = server.into_inner();
+        let server = Framed::new(io, LineCodec).into_framed(BytesCodec);
+        let server = server.map_io(|io| io);
+        let item = server.recv().await.unwrap().unwrap();
+        assert_eq!(item, b"line-1\nrest".as_ref());
+
+        client.remote_buffer_cap(1024);
+        server.flush(true).await.unwrap();
+        assert_eq!(client.read_any(), b"out".as_ref());
+    }
+
+    #[ntex::test]
+    async fn framed_buffers() {
+        let pool = PoolId::P10.pool_ref();
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(0);
+
+        let server =
+            Framed::with_capacity(Io::with_memory_pool(server, pool), BytesCodec, 256, 512);
+        server.set_write_high_watermark(1024);
+        assert!(!server.is_write_buf_full());
+
+        // write burst
+        server
+            .get_io()
+            .encode(Bytes::from(vec![1; 8192]), &BytesCodec)
+            .unwrap();
+        assert!(server.is_write_buf_full());
+        assert!(pool.allocated() >= 8192);
+        client.remote_buffer_cap(65536);
+        server.flush(true).await.unwrap();
+        assert_eq!(client.read_any().len(), 8192);
+        assert!(!server.is_write_buf_full());
+        assert_eq!(pool.allocated(), 0);
+
+        // large frame is accumulated in read buffer and partially consumed
+        client.write(vec![2; 10]);
+        server.get_io().read_ready().await.unwrap();
+        server.get_io().with_read_buf(|buf| {
+            buf.extend_from_slice(&[3; 8192]);
+            buf.truncate(10);
+        });
+        assert!(pool.allocated() >= 8192);
+
+        server.shrink_buffers();
+        assert!(pool.allocated() < 1024);
+        let item = server.recv().await.unwrap().unwrap();
+        assert_eq!(item, vec![2; 10]);
+    }
 }
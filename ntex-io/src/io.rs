@@ -1,6 +1,8 @@
 use std::cell::Cell;
 use std::task::{Context, Poll};
-use std::{fmt, future::Future, hash, io, marker, mem, ops, pin::Pin, ptr, rc::Rc, time};
+use std::{
+    cmp, fmt, future::Future, hash, io, marker, mem, ops, pin::Pin, ptr, rc::Rc, time,
+};
 
 use ntex_bytes::{PoolId, PoolRef};
 use ntex_codec::{Decoder, Encoder};
@@ -68,6 +70,9 @@ pub(crate) struct IoState {
     #[allow(clippy::box_collection)]
     pub(super) on_disconnect: Cell<Option<Box<Vec<LocalWaker>>>>,
     pub(super) keepalive: Cell<time::Instant>,
+    pub(super) read_params: Cell<Option<(usize, usize)>>,
+    pub(super) write_params: Cell<Option<(usize, usize)>>,
+    pub(super) write_hw: Cell<Option<usize>>,
 }
 
 impl IoState {
@@ -193,6 +198,9 @@ impl Io {
             handle: Cell::new(None),
             on_disconnect: Cell::new(None),
             keepalive: Cell::new(now()),
+            read_params: Cell::new(None),
+            write_params: Cell::new(None),
+            write_hw: Cell::new(None),
         });
 
         let filter = Box::new(Base::new(IoRef(inner.clone())));
@@ -226,6 +234,37 @@ impl<F> Io<F> {
         self.0 .0.disconnect_timeout.set(timeout);
     }
 
+    #[inline]
+    /// Set read and write buffer capacities.
+    ///
+    /// By default capacities are defined by memory pool read and write params.
+    /// Buffers with custom capacity are not cached by memory pool, empty
+    /// buffers get deallocated.
+    ///
+    /// Panics if capacity is less than 2 bytes.
+    pub fn set_buffer_capacity(&self, read: usize, write: usize) {
+        self.0 .0.read_params.set(Some(buf_params(read)));
+        self.0 .0.write_params.set(Some(buf_params(write)));
+    }
+
+    #[inline]
+    /// Set write buffer high watermark.
+    ///
+    /// Write back-pressure is enabled if size of write buffer
+    /// reaches high watermark. By default write buffer capacity is used.
+    pub fn set_write_high_watermark(&self, size: usize) {
+        self.0 .0.write_hw.set(Some(size));
+    }
+
+    #[inline]
+    /// Release empty buffers and shrink oversized buffers to its capacity.
+    ///
+    /// Empty buffers are released automatically after each io operation,
+    /// this method is useful after large bursts of data.
+    pub fn shrink_buffers(&self) {
+        self.0 .0.buffer.shrink(&self.0);
+    }
+
     #[inline]
     /// Clone current io object.
     ///
@@ -249,6 +288,9 @@ impl<F> Io<F> {
             handle: Cell::new(None),
             on_disconnect: Cell::new(None),
             keepalive: Cell::new(now()),
+            read_params: Cell::new(None),
+            write_params: Cell::new(None),
+            write_hw: Cell::new(None),
         });
 
         let state = mem::replace(&mut self.0, IoRef(inner));
@@ -519,7 +561,7 @@ impl<F> Io<F> {
                     inner.insert_flags(Flags::WR_WAIT);
                     inner.dispatch_task.register(cx.waker());
                     return Poll::Pending;
-                } else if len >= inner.pool.get().write_params_high() << 1 {
+                } else if len >= self.0.write_high_watermark() << 1 {
                     inner.insert_flags(Flags::WR_BACKPRESSURE);
                     inner.dispatch_task.register(cx.waker());
                     return Poll::Pending;
@@ -852,6 +894,12 @@ impl Future for OnDisconnect {
     }
 }
 
+/// Buffer params (high, low) for specified capacity
+fn buf_params(cap: usize) -> (usize, usize) {
+    assert!(cap > 1, "Buffer capacity must be larger than 1 byte");
+    (cap, cmp::max(cap / 4, 1))
+}
+
 #[cfg(test)]
 mod tests {
     use ntex_codec::BytesCodec;
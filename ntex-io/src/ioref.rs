@@ -1,6 +1,6 @@
 use std::{any, fmt, hash, io, time};
 
-use ntex_bytes::{BytesVec, PoolRef};
+use ntex_bytes::{BufMut, BytesVec, PoolRef};
 use ntex_codec::{Decoder, Encoder};
 
 use super::{io::Flags, timer, types, Filter, IoRef, OnDisconnect, WriteBuf};
@@ -106,7 +106,7 @@ impl IoRef {
         if !flags.contains(Flags::IO_STOPPING) {
             self.with_write_buf(|buf| {
                 // make sure we've got room
-                self.memory_pool().resize_write_buf(buf);
+                self.resize_write_buf(buf);
 
                 // encode item and wake write task
                 codec.encode_vec(item, buf)
@@ -177,6 +177,21 @@ impl IoRef {
         Ok(result)
     }
 
+    #[inline]
+    /// Get size of write buffer, data that is not yet flushed to the peer
+    pub fn write_buf_size(&self) -> usize {
+        self.0.buffer.write_destination_size()
+    }
+
+    #[inline]
+    /// Check if write buffer is full
+    ///
+    /// Write buffer is full if its size reached twice the high watermark,
+    /// the same limit is used for dispatcher write back-pressure.
+    pub fn is_write_buf_full(&self) -> bool {
+        self.write_buf_size() >= self.write_high_watermark() << 1
+    }
+
     #[inline]
     /// Get mut access to source read buffer
     pub fn with_read_buf<F, R>(&self, f: F) -> R
@@ -217,6 +232,82 @@ impl IoRef {
     }
 }
 
+impl IoRef {
+    /// Read buffer params (high, low)
+    pub(crate) fn read_params(&self) -> (usize, usize) {
+        self.0
+            .read_params
+            .get()
+            .unwrap_or_else(|| self.memory_pool().read_params().unpack())
+    }
+
+    /// Write buffer params (high, low)
+    pub(crate) fn write_params(&self) -> (usize, usize) {
+        self.0
+            .write_params
+            .get()
+            .unwrap_or_else(|| self.memory_pool().write_params().unpack())
+    }
+
+    /// Write buffer high watermark
+    pub(crate) fn write_high_watermark(&self) -> usize {
+        self.0
+            .write_hw
+            .get()
+            .unwrap_or_else(|| self.write_params().0)
+    }
+
+    pub(crate) fn get_read_buf(&self) -> BytesVec {
+        if let Some((hw, _)) = self.0.read_params.get() {
+            BytesVec::with_capacity_in(hw, self.memory_pool())
+        } else {
+            self.memory_pool().get_read_buf()
+        }
+    }
+
+    pub(crate) fn get_write_buf(&self) -> BytesVec {
+        if let Some((hw, _)) = self.0.write_params.get() {
+            BytesVec::with_capacity_in(hw, self.memory_pool())
+        } else {
+            self.memory_pool().get_write_buf()
+        }
+    }
+
+    pub(crate) fn resize_read_buf(&self, buf: &mut BytesVec) {
+        if let Some((hw, lw)) = self.0.read_params.get() {
+            let remaining = buf.remaining_mut();
+            if remaining < lw {
+                buf.reserve(hw - remaining);
+            }
+        } else {
+            self.memory_pool().resize_read_buf(buf);
+        }
+    }
+
+    pub(crate) fn resize_write_buf(&self, buf: &mut BytesVec) {
+        if let Some((hw, lw)) = self.0.write_params.get() {
+            let remaining = buf.remaining_mut();
+            if remaining < lw {
+                buf.reserve(hw - remaining);
+            }
+        } else {
+            self.memory_pool().resize_write_buf(buf);
+        }
+    }
+
+    pub(crate) fn release_read_buf(&self, buf: BytesVec) {
+        if self.0.read_params.get().is_none() {
+            self.memory_pool().release_read_buf(buf);
+        }
+    }
+
+    pub(crate) fn release_write_buf(&self, buf: BytesVec) {
+        if self.0.write_params.get().is_none() {
+            self.memory_pool().release_write_buf(buf);
+        }
+    }
+}
+
 impl Eq for IoRef {}
 
 impl PartialEq for IoRef {
@@ -318,6 +409,23 @@ mod tests {
         assert!(state.flags().contains(Flags::IO_STOPPING));
     }
 
+    #[ntex::test]
+    async fn write_buf_size() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(0);
+        let state = Io::new(server);
+        assert_eq!(state.write_buf_size(), 0);
+
+        state.write(BIN).unwrap();
+        sleep(Millis(50)).await;
+        assert_eq!(state.write_buf_size(), BIN.len());
+
+        client.remote_buffer_cap(1024);
+        sleep(Millis(50)).await;
+        assert_eq!(state.write_buf_size(), 0);
+        assert_eq!(client.read_any(), BIN);
+    }
+
     #[ntex::test]
     async fn read_readiness() {
         let (client, server) = IoTest::create();
@@ -24,7 +24,7 @@ use ntex_codec::{Decoder, Encoder};
 use ntex_util::time::Millis;
 
 pub use self::buf::{ReadBuf, WriteBuf};
-pub use self::dispatcher::Dispatcher;
+pub use self::dispatcher::{Dispatcher, DispatcherSink};
 pub use self::filter::{Base, Filter, Layer};
 pub use self::framed::Framed;
 pub use self::io::{Io, IoRef, OnDisconnect};
@@ -25,7 +25,7 @@ impl ReadContext {
         F: FnOnce(&mut BytesVec, usize, usize) -> Poll<io::Result<()>>,
     {
         let inner = &self.0 .0;
-        let (hw, lw) = self.0.memory_pool().read_params().unpack();
+        let (hw, lw) = self.0.read_params();
         let (result, nbytes, total) = inner.buffer.with_read_source(&self.0, |buf| {
             let total = buf.len();
 
@@ -136,7 +136,7 @@ impl WriteContext {
         let mut flags = inner.flags.get();
         let mut wake_dispatcher = false;
         if flags.contains(Flags::WR_BACKPRESSURE)
-            && len < inner.pool.get().write_params_high() << 1
+            && len < self.0.write_high_watermark() << 1
         {
             flags.remove(Flags::WR_BACKPRESSURE);
             wake_dispatcher = true;
@@ -44,11 +44,23 @@ macro_rules! parse_single_value {
 #[derive(Debug)]
 pub struct PathDeserializer<'de, T: ResourcePath> {
     path: &'de Path<T>,
+    insensitive: bool,
 }
 
 impl<'de, T: ResourcePath + 'de> PathDeserializer<'de, T> {
     pub fn new(path: &'de Path<T>) -> Self {
-        PathDeserializer { path }
+        PathDeserializer {
+            path,
+            insensitive: false,
+        }
+    }
+
+    /// Match enum variant names ascii case-insensitively.
+    ///
+    /// By default variant names are case-sensitive.
+    pub fn case_insensitive_variants(mut self) -> Self {
+        self.insensitive = true;
+        self
     }
 }
 
@@ -62,6 +74,7 @@ impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T>
         visitor.visit_map(ParamsDeserializer {
             params: self.path.iter(),
             current: None,
+            insensitive: self.insensitive,
         })
     }
 
@@ -110,20 +123,14 @@ impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T>
     where
         V: Visitor<'de>,
     {
-        if self.path.len() < len {
-            Err(de::value::Error::custom(
-                format!(
-                    "wrong number of parameters: {} expected {}",
-                    self.path.len(),
-                    len
-                )
-                .as_str(),
-            ))
-        } else {
-            visitor.visit_seq(ParamsSeq {
-                params: self.path.iter(),
-            })
-        }
+        // missing trailing segments could be deserialized to `None`
+        visitor.visit_seq(ParamsSeq {
+            params: self.path.iter(),
+            missing: len.saturating_sub(self.path.len()),
+            len: self.path.len(),
+            expected: len,
+            insensitive: self.insensitive,
+        })
     }
 
     fn deserialize_tuple_struct<V>(
@@ -135,39 +142,33 @@ impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T>
     where
         V: Visitor<'de>,
     {
-        if self.path.len() < len {
-            Err(de::value::Error::custom(
-                format!(
-                    "wrong number of parameters: {} expected {}",
-                    self.path.len(),
-                    len
-                )
-                .as_str(),
-            ))
-        } else {
-            visitor.visit_seq(ParamsSeq {
-                params: self.path.iter(),
-            })
-        }
+        // missing trailing segments could be deserialized to `None`
+        visitor.visit_seq(ParamsSeq {
+            params: self.path.iter(),
+            missing: len.saturating_sub(self.path.len()),
+            len: self.path.len(),
+            expected: len,
+            insensitive: self.insensitive,
+        })
     }
 
     fn deserialize_enum<V>(
         self,
         _: &'static str,
-        _: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        if self.path.is_empty() {
+        if let Some((name, value)) = self.path.iter().next() {
+            visitor.visit_enum(ValueEnum {
+                value: variant(name, value, variants, self.insensitive)?,
+            })
+        } else {
             Err(de::value::Error::custom(
                 "expeceted at least one parameters",
             ))
-        } else {
-            visitor.visit_enum(ValueEnum {
-                value: &self.path[0],
-            })
         }
     }
 
@@ -191,6 +192,10 @@ impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T>
     {
         visitor.visit_seq(ParamsSeq {
             params: self.path.iter(),
+            missing: 0,
+            len: self.path.len(),
+            expected: 0,
+            insensitive: self.insensitive,
         })
     }
 
@@ -219,6 +224,7 @@ impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T>
 struct ParamsDeserializer<'de, T: ResourcePath> {
     params: PathIter<'de, T>,
     current: Option<(&'de str, &'de str)>,
+    insensitive: bool,
 }
 
 impl<'de, T: ResourcePath> de::MapAccess<'de> for ParamsDeserializer<'de, T> {
@@ -239,8 +245,12 @@ impl<'de, T: ResourcePath> de::MapAccess<'de> for ParamsDeserializer<'de, T> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        if let Some((_, value)) = self.current.take() {
-            seed.deserialize(Value { value })
+        if let Some((name, value)) = self.current.take() {
+            seed.deserialize(Value {
+                name,
+                value,
+                insensitive: self.insensitive,
+            })
         } else {
             Err(de::value::Error::custom("unexpected item"))
         }
@@ -293,7 +303,9 @@ macro_rules! parse_value {
 }
 
 struct Value<'de> {
+    name: &'de str,
     value: &'de str,
+    insensitive: bool,
 }
 
 impl<'de> Deserializer<'de> for Value<'de> {
@@ -363,13 +375,15 @@ impl<'de> Deserializer<'de> for Value<'de> {
     fn deserialize_enum<V>(
         self,
         _: &'static str,
-        _: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(ValueEnum { value: self.value })
+        visitor.visit_enum(ValueEnum {
+            value: variant(self.name, self.value, variants, self.insensitive)?,
+        })
     }
 
     fn deserialize_newtype_struct<V>(
@@ -422,6 +436,11 @@ impl<'de> Deserializer<'de> for Value<'de> {
 
 struct ParamsSeq<'de, T: ResourcePath> {
     params: PathIter<'de, T>,
+    /// Number of trailing elements without matched segment
+    missing: usize,
+    len: usize,
+    expected: usize,
+    insensitive: bool,
 }
 
 impl<'de, T: ResourcePath> de::SeqAccess<'de> for ParamsSeq<'de, T> {
@@ -432,12 +451,90 @@ impl<'de, T: ResourcePath> de::SeqAccess<'de> for ParamsSeq<'de, T> {
         U: de::DeserializeSeed<'de>,
     {
         match self.params.next() {
-            Some(item) => Ok(Some(seed.deserialize(Value { value: item.1 })?)),
+            Some(item) => Ok(Some(seed.deserialize(Value {
+                name: item.0,
+                value: item.1,
+                insensitive: self.insensitive,
+            })?)),
+            None if self.missing > 0 => {
+                self.missing -= 1;
+                Ok(Some(seed.deserialize(MissingValue {
+                    len: self.len,
+                    expected: self.expected,
+                })?))
+            }
             None => Ok(None),
         }
     }
 }
 
+/// Element without matched segment, deserializes only to `None`
+struct MissingValue {
+    len: usize,
+    expected: usize,
+}
+
+impl<'de> Deserializer<'de> for MissingValue {
+    type Error = de::value::Error;
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::value::Error::custom(format!(
+            "wrong number of parameters: {} expected {}",
+            self.len, self.expected
+        )))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+            byte_buf unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Find enum variant for segment value
+fn variant<'de>(
+    name: &str,
+    value: &'de str,
+    variants: &'static [&'static str],
+    insensitive: bool,
+) -> Result<&'de str, de::value::Error> {
+    if variants.contains(&value) {
+        return Ok(value);
+    }
+    if insensitive {
+        if let Some(variant) = variants.iter().find(|v| v.eq_ignore_ascii_case(value)) {
+            return Ok(variant);
+        }
+    }
+
+    let expected = match variants {
+        [] => "there are no variants".to_string(),
+        [variant] => format!("`{}`", variant),
+        _ => format!(
+            "one of {}",
+            variants
+                .iter()
+                .map(|v| format!("`{}`", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    Err(de::value::Error::custom(format!(
+        "unknown variant `{}` for segment `{}`, expected {}",
+        value, name, expected
+    )))
+}
+
 struct ValueEnum<'de> {
     value: &'de str,
 }
@@ -674,6 +771,73 @@ mod tests {
         assert!(format!("{:?}", i).contains("unknown variant"));
     }
 
+    #[test]
+    fn test_extract_enum_case_insensitive() {
+        let mut path = Path::new("/VAL2/");
+        path.segments = vec![("val", PathItem::Static("VAL2"))];
+        let i: Result<Test3, de::value::Error> =
+            de::Deserialize::deserialize(PathDeserializer::new(&path));
+        assert_eq!(
+            i.unwrap_err().to_string(),
+            "unknown variant `VAL2` for segment `val`, expected one of `val1`, `val2`"
+        );
+
+        let i: Test3 = de::Deserialize::deserialize(
+            PathDeserializer::new(&path).case_insensitive_variants(),
+        )
+        .unwrap();
+        assert_eq!(i.val, TestEnum::Val2);
+
+        let i: (TestEnum,) = de::Deserialize::deserialize(
+            PathDeserializer::new(&path).case_insensitive_variants(),
+        )
+        .unwrap();
+        assert_eq!(i, (TestEnum::Val2,));
+
+        let i: TestEnum = de::Deserialize::deserialize(
+            PathDeserializer::new(&path).case_insensitive_variants(),
+        )
+        .unwrap();
+        assert_eq!(i, TestEnum::Val2);
+    }
+
+    #[test]
+    fn test_extract_optional() {
+        #[derive(Debug, Deserialize)]
+        struct Archive {
+            year: u16,
+            month: Option<u8>,
+        }
+
+        let mut path = Path::new("/archive/2023/05");
+        path.segments = vec![
+            ("year", PathItem::Static("2023")),
+            ("month", PathItem::Static("05")),
+        ];
+        let s: Archive =
+            de::Deserialize::deserialize(PathDeserializer::new(&path)).unwrap();
+        assert_eq!((s.year, s.month), (2023, Some(5)));
+        let s: (u16, Option<u8>) =
+            de::Deserialize::deserialize(PathDeserializer::new(&path)).unwrap();
+        assert_eq!(s, (2023, Some(5)));
+
+        let mut path = Path::new("/archive/2023");
+        path.segments = vec![("year", PathItem::Static("2023"))];
+        let s: Archive =
+            de::Deserialize::deserialize(PathDeserializer::new(&path)).unwrap();
+        assert_eq!((s.year, s.month), (2023, None));
+        let s: (u16, Option<u8>) =
+            de::Deserialize::deserialize(PathDeserializer::new(&path)).unwrap();
+        assert_eq!(s, (2023, None));
+
+        let s: Result<(u16, u8), de::value::Error> =
+            de::Deserialize::deserialize(PathDeserializer::new(&path));
+        assert_eq!(
+            s.unwrap_err().to_string(),
+            "wrong number of parameters: 1 expected 2"
+        );
+    }
+
     #[test]
     fn test_extract_errors() {
         let mut path = Path::new("/name/");
@@ -15,7 +15,8 @@ mod tree;
 
 pub use self::de::PathDeserializer;
 pub use self::path::{Path, PathIter};
-pub use self::resource::ResourceDef;
+pub use self::quoter::is_valid_path;
+pub use self::resource::{BuildError, ResourceDef, ResourceRank};
 pub use self::router::{ResourceId, Router, RouterBuilder};
 
 #[doc(hidden)]
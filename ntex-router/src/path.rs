@@ -8,7 +8,8 @@ use crate::{Resource, ResourcePath};
 #[derive(Debug, Clone)]
 pub(super) enum PathItem {
     Static(&'static str),
-    Segment(String),
+    /// Decoded value and position of encoded value
+    Segment(String, u16, u16),
     IdxSegment(u16, u16),
 }
 
@@ -123,7 +124,7 @@ impl<T: ResourcePath> Path<T> {
             if key == item.0 {
                 return match item.1 {
                     PathItem::Static(s) => Some(s),
-                    PathItem::Segment(ref s) => Some(s),
+                    PathItem::Segment(ref s, _, _) => Some(s),
                     PathItem::IdxSegment(s, e) => {
                         Some(&self.path.path()[(s as usize)..(e as usize)])
                     }
@@ -137,6 +138,25 @@ impl<T: ResourcePath> Path<T> {
         }
     }
 
+    /// Get matched parameter by name without percent-decoding
+    pub fn get_raw(&self, key: &str) -> Option<&str> {
+        for item in self.segments.iter() {
+            if key == item.0 {
+                return match item.1 {
+                    PathItem::Static(s) => Some(s),
+                    PathItem::Segment(_, s, e) | PathItem::IdxSegment(s, e) => {
+                        Some(&self.path.path()[(s as usize)..(e as usize)])
+                    }
+                };
+            }
+        }
+        if key == "tail" {
+            Some(&self.path.path()[(self.skip as usize)..])
+        } else {
+            None
+        }
+    }
+
     /// Get unprocessed part of the path
     pub fn unprocessed(&self) -> &str {
         &self.path.path()[(self.skip as usize)..]
@@ -184,7 +204,7 @@ impl<'a, T: ResourcePath> Iterator for PathIter<'a, T> {
             let idx = self.idx;
             let res = match self.params.segments[idx].1 {
                 PathItem::Static(s) => s,
-                PathItem::Segment(ref s) => s.as_str(),
+                PathItem::Segment(ref s, _, _) => s.as_str(),
                 PathItem::IdxSegment(s, e) => {
                     &self.params.path.path()[(s as usize)..(e as usize)]
                 }
@@ -211,7 +231,7 @@ impl<T: ResourcePath> Index<usize> for Path<T> {
     fn index(&self, idx: usize) -> &str {
         match self.segments[idx].1 {
             PathItem::Static(s) => s,
-            PathItem::Segment(ref s) => s,
+            PathItem::Segment(ref s, _, _) => s,
             PathItem::IdxSegment(s, e) => &self.path.path()[(s as usize)..(e as usize)],
         }
     }
@@ -1,4 +1,37 @@
+/// Decode percent-encoded sequences, returns `None` if path does not
+/// contain encoded sequences or if decoded path is not valid utf-8
 pub(super) fn requote(val: &[u8]) -> Option<String> {
+    decode(val).and_then(|data| String::from_utf8(data).ok())
+}
+
+/// Check if percent-encoded sequences of the path decode to valid utf-8
+pub fn is_valid_path(path: &str) -> bool {
+    match decode(path.as_bytes()) {
+        Some(data) => std::str::from_utf8(&data).is_ok(),
+        None => true,
+    }
+}
+
+/// Offset in encoded value for offset in decoded value
+pub(super) fn raw_offset(raw: &str, decoded: usize) -> usize {
+    let raw = raw.as_bytes();
+    let mut idx = 0;
+    let mut pos = 0;
+    while pos < decoded && idx < raw.len() {
+        if raw[idx] == b'%'
+            && idx + 2 < raw.len()
+            && restore_ch(raw[idx + 1], raw[idx + 2]).is_some()
+        {
+            idx += 3;
+        } else {
+            idx += 1;
+        }
+        pos += 1;
+    }
+    idx
+}
+
+fn decode(val: &[u8]) -> Option<Vec<u8>> {
     let mut has_pct = 0;
     let mut pct = [b'%', 0, 0];
     let mut idx = 0;
@@ -40,9 +73,7 @@ pub(super) fn requote(val: &[u8]) -> Option<String> {
         if has_pct > 0 {
             data.extend(&pct[..has_pct]);
         }
-        // Unsafe: we get data from http::Uri, which does utf-8 checks already
-        // this code only decodes valid pct encoded values
-        Some(unsafe { String::from_utf8_unchecked(data) })
+        Some(data)
     } else {
         None
     }
@@ -65,3 +96,33 @@ fn from_hex(v: u8) -> Option<u8> {
 fn restore_ch(d1: u8, d2: u8) -> Option<u8> {
     from_hex(d1).and_then(|d1| from_hex(d2).map(move |d2| d1 << 4 | d2))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requote() {
+        assert_eq!(requote(b"/test"), None);
+        assert_eq!(requote(b"/a%20b%2F%zz").unwrap(), "/a b/%zz");
+        assert_eq!(requote(b"/a%FF"), None);
+    }
+
+    #[test]
+    fn test_is_valid_path() {
+        assert!(is_valid_path("/test"));
+        assert!(is_valid_path("/a%20b%zz%"));
+        assert!(is_valid_path("/%D1%84"));
+        assert!(!is_valid_path("/a%FF"));
+        assert!(!is_valid_path("/%D1"));
+    }
+
+    #[test]
+    fn test_raw_offset() {
+        assert_eq!(raw_offset("abc", 2), 2);
+        assert_eq!(raw_offset("a%20b", 2), 4);
+        assert_eq!(raw_offset("a%20b", 3), 5);
+        assert_eq!(raw_offset("a%zzb", 2), 2);
+        assert_eq!(raw_offset("a%2", 3), 3);
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use regex::{escape, Regex};
@@ -21,15 +22,123 @@ pub struct ResourceDef {
     name: String,
     pattern: String,
     elements: Vec<PathElement>,
+    rank: ResourceRank,
+    /// Index of the first optional dynamic segment
+    optional: usize,
     pub(super) prefix: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Specificity rank of resource definition.
+///
+/// Fully static patterns rank higher than dynamic, then patterns with
+/// longer static prefix, then patterns with more regex constrained
+/// dynamic segments. Greater rank is more specific.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceRank {
+    is_static: bool,
+    static_prefix: usize,
+    constrained: usize,
+}
+
+impl ResourceRank {
+    fn new(path: &str, segments: &Segments, elems: &[PathElement], prefix: bool) -> Self {
+        let is_static =
+            !prefix && segments.tp.iter().all(|s| matches!(s, Segment::Static(_)));
+        let static_prefix = if is_static {
+            path.len()
+        } else {
+            path.find(['{', '*']).unwrap_or(path.len())
+        };
+        let constrained = elems
+            .iter()
+            .filter(|el| matches!(el, PathElement::Var(_, Constraint::Regex(..))))
+            .count();
+
+        ResourceRank {
+            is_static,
+            static_prefix,
+            constrained,
+        }
+    }
+
+    /// Pattern does not contain dynamic segments and is not a prefix
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Length of static part at the start of the pattern
+    pub fn static_prefix(&self) -> usize {
+        self.static_prefix
+    }
+
+    /// Number of dynamic segments with custom regex
+    pub fn constrained(&self) -> usize {
+        self.constrained
+    }
+}
+
+#[derive(Debug, Clone)]
 enum PathElement {
     Str(String),
-    Var(String),
+    Var(String, Constraint),
+}
+
+/// Constraint for value of dynamic segment
+#[derive(Debug, Clone)]
+enum Constraint {
+    /// Default segment pattern, any non-empty value
+    Segment,
+    /// Default tail pattern, any value
+    Tail,
+    /// Custom pattern, flag is set for tail segment
+    Regex(Regex, bool),
+}
+
+impl Constraint {
+    fn is_tail(&self) -> bool {
+        matches!(self, Constraint::Tail | Constraint::Regex(_, true))
+    }
+
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            Constraint::Segment => !value.is_empty(),
+            Constraint::Tail => true,
+            Constraint::Regex(re, _) => re.is_match(value),
+        }
+    }
+}
+
+/// Error returned when resource path cannot be built from supplied values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Value for dynamic segment is not supplied
+    MissingValue(String),
+    /// Value does not match pattern of dynamic segment
+    InvalidValue { name: String, value: String },
+    /// Supplied parameter is not defined by resource pattern
+    UnknownParameter(String),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingValue(name) => {
+                write!(f, "Value for parameter {:?} is missing", name)
+            }
+            BuildError::InvalidValue { name, value } => write!(
+                f,
+                "Value {:?} does not match pattern of parameter {:?}",
+                value, name
+            ),
+            BuildError::UnknownParameter(name) => {
+                write!(f, "Parameter {:?} is not defined by pattern", name)
+            }
+        }
+    }
 }
 
+impl std::error::Error for BuildError {}
+
 impl PathElement {
     fn is_str(&self) -> bool {
         matches!(self, PathElement::Str(_))
@@ -45,7 +154,7 @@ impl PathElement {
     fn as_str(&self) -> &str {
         match self {
             PathElement::Str(s) => s.as_str(),
-            PathElement::Var(s) => s.as_str(),
+            PathElement::Var(s, _) => s.as_str(),
         }
     }
 }
@@ -55,6 +164,8 @@ pub(crate) enum Segment {
     Static(String),
     Dynamic {
         pattern: Regex,
+        /// Regex with case insensitive static parts
+        insensitive: String,
         names: Vec<&'static str>,
         tail: bool,
     },
@@ -92,28 +203,23 @@ impl ResourceDef {
     /// with segment separator. Static segments could be
     /// case insensitive.
     ///
-    /// Panics if path pattern is malformed.
+    /// Dynamic segment could be constrained with custom regex,
+    /// `{name:regex}`, for example `/items/{id:\d+}`. Regex must match
+    /// whole segment. Resources are matched in registration order, unless
+    /// router ranks resources by specificity.
+    ///
+    /// Last segment could match rest of the path, `{tail}*` or `{tail:.*}`
+    /// captures remaining segments including slashes, tail could be empty.
+    /// Custom regex could be used for tail match, `{tail:regex}*`.
+    ///
+    /// Trailing dynamic segments could be optional, `{name?}` or
+    /// `{name?:regex}`, for example `/archive/{year}/{month?}` matches
+    /// both `/archive/2023` and `/archive/2023/05`.
+    ///
+    /// Panics if path pattern is malformed, segment regex is not valid or
+    /// optional segment is followed by required one.
     pub fn new<T: IntoPattern>(path: T) -> Self {
-        let set = path.patterns();
-        let mut p = String::new();
-        let mut tp = Vec::new();
-        let mut elements = Vec::new();
-
-        for path in set {
-            p = path.clone();
-            let (pelems, elems) = ResourceDef::parse(&path);
-            tp.push(pelems);
-            elements = elems;
-        }
-
-        ResourceDef {
-            tp,
-            elements,
-            id: 0,
-            name: String::new(),
-            pattern: p,
-            prefix: false,
-        }
+        ResourceDef::from_patterns(path.patterns(), false)
     }
 
     /// Parse path pattern and create new `ResourceDef` instance.
@@ -151,26 +257,45 @@ impl ResourceDef {
 
     /// Parse path pattern and create new `Pattern` instance with custom prefix
     fn with_prefix<T: IntoPattern>(path: T) -> Self {
-        let patterns = path.patterns();
+        ResourceDef::from_patterns(path.patterns(), true)
+    }
 
+    fn from_patterns(patterns: Vec<String>, prefix: bool) -> Self {
         let mut p = String::new();
         let mut tp = Vec::new();
         let mut elements = Vec::new();
+        let mut rank = ResourceRank::default();
+        let mut optional = 0;
 
         for path in patterns {
-            p = path.clone();
-            let (pelems, elems) = ResourceDef::parse(&path);
-            tp.push(pelems);
-            elements = elems;
+            // pattern with optional segments is matched as a set of patterns
+            let (paths, num) = expand_optional(&path);
+            for (idx, expanded) in paths.iter().enumerate() {
+                let (pelems, elems) = ResourceDef::parse(expanded);
+                rank = rank.max(ResourceRank::new(expanded, &pelems, &elems, prefix));
+                tp.push(pelems);
+                if idx == 0 {
+                    elements = elems;
+                }
+            }
+            optional = num;
+            p = path;
         }
 
+        let vars = elements
+            .iter()
+            .filter(|el| matches!(el, PathElement::Var(..)))
+            .count();
+
         ResourceDef {
             tp,
             elements,
+            rank,
+            optional: vars - optional,
             id: 0,
             name: String::new(),
             pattern: p,
-            prefix: true,
+            prefix,
         }
     }
 
@@ -189,61 +314,161 @@ impl ResourceDef {
         &self.pattern
     }
 
+    /// Specificity rank of the resource
+    ///
+    /// Highest rank is used for resource with multiple patterns.
+    pub fn rank(&self) -> ResourceRank {
+        self.rank
+    }
+
     /// Build resource path from elements. Returns `true` on success.
+    ///
+    /// See [`ResourceDef::build_path`].
     pub fn resource_path<U, I>(&self, path: &mut String, elements: &mut U) -> bool
     where
         U: Iterator<Item = I>,
         I: AsRef<str>,
     {
+        self.build_path(path, elements).is_ok()
+    }
+
+    /// Build resource path from elements. Returns `true` on success.
+    ///
+    /// See [`ResourceDef::build_path_named`].
+    pub fn resource_path_named<K, V, S>(
+        &self,
+        path: &mut String,
+        elements: &HashMap<K, V, S>,
+    ) -> bool
+    where
+        K: std::borrow::Borrow<str> + Eq + Hash,
+        V: AsRef<str>,
+        S: std::hash::BuildHasher,
+    {
+        self.build_path_named(path, elements).is_ok()
+    }
+
+    /// Build resource path from elements.
+    ///
+    /// Values are percent-encoded, so path matches the same resource and
+    /// matched values are equal to supplied values. Tail values could
+    /// contain slashes. Value must match pattern of dynamic segment.
+    ///
+    /// ```rust
+    /// use ntex_router::{BuildError, ResourceDef};
+    ///
+    /// let res = ResourceDef::new("/user/{id:\\d+}/{tail}*");
+    ///
+    /// let mut path = String::new();
+    /// res.build_path(&mut path, &mut ["1", "a b/c"].iter()).unwrap();
+    /// assert_eq!(path, "/user/1/a%20b/c");
+    ///
+    /// assert_eq!(
+    ///     res.build_path(&mut String::new(), &mut ["x", ""].iter()),
+    ///     Err(BuildError::InvalidValue { name: "id".to_string(), value: "x".to_string() })
+    /// );
+    /// ```
+    pub fn build_path<U, I>(
+        &self,
+        path: &mut String,
+        elements: &mut U,
+    ) -> Result<(), BuildError>
+    where
+        U: Iterator<Item = I>,
+        I: AsRef<str>,
+    {
+        let mut idx = 0;
         for el in &self.elements {
-            match *el {
-                PathElement::Str(ref s) => path.push_str(s),
-                PathElement::Var(_) => {
+            match el {
+                PathElement::Str(s) => path.push_str(s),
+                PathElement::Var(name, constraint) => {
                     if let Some(val) = elements.next() {
-                        path.push_str(val.as_ref())
+                        push_value(path, name, val.as_ref(), constraint)?;
+                    } else if idx >= self.optional {
+                        trim_optional(path);
+                        return Ok(());
                     } else {
-                        return false;
+                        return Err(BuildError::MissingValue(name.clone()));
                     }
+                    idx += 1;
                 }
             }
         }
-        true
+        Ok(())
     }
 
-    /// Build resource path from elements. Returns `true` on success.
-    pub fn resource_path_named<K, V, S>(
+    /// Build resource path from named elements.
+    ///
+    /// Same as [`ResourceDef::build_path`], also fails if elements contain
+    /// parameter which is not defined by resource pattern.
+    pub fn build_path_named<K, V, S>(
         &self,
         path: &mut String,
         elements: &HashMap<K, V, S>,
-    ) -> bool
+    ) -> Result<(), BuildError>
     where
         K: std::borrow::Borrow<str> + Eq + Hash,
         V: AsRef<str>,
         S: std::hash::BuildHasher,
     {
+        for key in elements.keys() {
+            let key = key.borrow();
+            if !self
+                .elements
+                .iter()
+                .any(|el| matches!(el, PathElement::Var(name, _) if name == key))
+            {
+                return Err(BuildError::UnknownParameter(key.to_string()));
+            }
+        }
+
+        let mut idx = 0;
         for el in &self.elements {
-            match *el {
-                PathElement::Str(ref s) => path.push_str(s),
-                PathElement::Var(ref name) => {
-                    if let Some(val) = elements.get(name) {
-                        path.push_str(val.as_ref())
+            match el {
+                PathElement::Str(s) => path.push_str(s),
+                PathElement::Var(name, constraint) => {
+                    if let Some(val) = elements.get(name.as_str()) {
+                        push_value(path, name, val.as_ref(), constraint)?;
+                    } else if idx >= self.optional {
+                        trim_optional(path);
+                        return Ok(());
                     } else {
-                        return false;
+                        return Err(BuildError::MissingValue(name.clone()));
                     }
+                    idx += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make path patterns of dynamic segments case insensitive.
+    ///
+    /// Only static parts of the segments are case insensitive.
+    pub(super) fn case_insensitive(&mut self) {
+        for segments in &mut self.tp {
+            for segment in &mut segments.tp {
+                if let Segment::Dynamic {
+                    ref mut pattern,
+                    ref insensitive,
+                    ..
+                } = segment
+                {
+                    *pattern = Regex::new(insensitive).unwrap();
                 }
             }
         }
-        true
     }
 
     fn parse_segment<'a>(
         pattern: &'a str,
         elems: &mut Vec<PathElement>,
-    ) -> (String, &'a str, bool) {
+    ) -> (String, String, &'a str, bool) {
         const DEFAULT_PATTERN: &str = ".+";
         const DEFAULT_PATTERN_TAIL: &str = ".*";
 
         let mut re = "^".to_string();
+        let mut ire = "^".to_string();
         let mut end = None;
         let mut tail = false;
         let mut rem = pattern;
@@ -260,6 +485,7 @@ impl ResourceDef {
             let p = pattern.split_at(start_idx);
             pattern = p.1;
             re.push_str(&escape(p.0));
+            ire.push_str(&escape_insensitive(p.0));
             elems.push(PathElement::Str(p.0.to_string()));
 
             // find closing }
@@ -283,18 +509,22 @@ impl ResourceDef {
             let param = &p.0[1..p.0.len() - 1]; // Remove outer brackets
             tail = rem == "*"; // tail match (should match regardless of segments)
 
+            if tail {
+                rem = &rem[1..];
+            }
             let (name, pat) = match param.find(':') {
                 Some(idx) => {
-                    if tail {
-                        panic!("Custom regex is not supported for remainder match");
-                    }
                     let (name, pattern) = param.split_at(idx);
-                    (name, &pattern[1..])
+                    let pattern = &pattern[1..];
+                    // `{name:.*}` as last segment matches rest of the path
+                    if pattern == DEFAULT_PATTERN_TAIL && rem.is_empty() {
+                        tail = true;
+                    }
+                    (name, pattern)
                 }
                 None => (
                     param,
                     if tail {
-                        rem = &rem[1..];
                         DEFAULT_PATTERN_TAIL
                     } else {
                         DEFAULT_PATTERN
@@ -303,15 +533,27 @@ impl ResourceDef {
             };
 
             re = format!(r"{}(?P<{}>{})", re, &escape(name), pat);
-
-            elems.push(PathElement::Var(name.to_string()));
+            ire = format!(r"{}(?P<{}>{})", ire, &escape(name), pat);
+
+            let constraint = match (pat, tail) {
+                (DEFAULT_PATTERN, false) => Constraint::Segment,
+                (DEFAULT_PATTERN_TAIL, true) => Constraint::Tail,
+                // invalid pattern is reported by segment regex
+                _ => match Regex::new(&format!("^(?:{})$", pat)) {
+                    Ok(re) => Constraint::Regex(re, tail),
+                    Err(_) => Constraint::Tail,
+                },
+            };
+            elems.push(PathElement::Var(name.to_string(), constraint));
 
             if let Some(idx) = rem.find(|c| c == '{' || c == '/') {
                 end = Some(idx);
                 pattern = rem;
                 continue;
             } else {
-                re += rem;
+                re.push_str(&escape(rem));
+                ire.push_str(&escape_insensitive(rem));
+                elems.push(PathElement::Str(rem.to_string()));
                 rem = "";
                 break;
             }
@@ -320,17 +562,23 @@ impl ResourceDef {
         // find end of segment
         if let Some(idx) = rem.find('/') {
             re.push_str(&escape(&rem[..idx]));
+            ire.push_str(&escape_insensitive(&rem[..idx]));
+            elems.push(PathElement::Str(rem[..idx].to_string()));
             rem = &rem[idx..];
         } else {
             re.push_str(&escape(rem));
+            ire.push_str(&escape_insensitive(rem));
+            elems.push(PathElement::Str(rem.to_string()));
             rem = "";
         };
         re.push('$');
+        ire.push('$');
 
-        (re, rem, tail)
+        (re, ire, rem, tail)
     }
 
     fn parse(mut pattern: &str) -> (Segments, Vec<PathElement>) {
+        let path = pattern;
         let mut elems = Vec::new();
         let mut pelems = Vec::new();
 
@@ -361,8 +609,11 @@ impl ResourceDef {
             }
 
             // dynamic segment
-            let (re_part, rem, tail) = Self::parse_segment(pattern, &mut elems);
-            let re = Regex::new(&re_part).unwrap();
+            let (re_part, insensitive, rem, tail) =
+                Self::parse_segment(pattern, &mut elems);
+            let re = Regex::new(&re_part).unwrap_or_else(|e| {
+                panic!("Invalid regex in path pattern {:?}: {}", path, e)
+            });
             let names: Vec<_> = re
                 .capture_names()
                 .filter_map(|name| {
@@ -372,6 +623,7 @@ impl ResourceDef {
             pelems.push(Segment::Dynamic {
                 names,
                 tail,
+                insensitive,
                 pattern: re,
             });
 
@@ -393,9 +645,10 @@ impl ResourceDef {
         if !pattern.is_empty() {
             // handle tail expression for static segment
             if let Some(stripped) = pattern.strip_suffix('*') {
-                let pattern = Regex::new(&format!("^{}(.+)", stripped)).unwrap();
+                let pattern = Regex::new(&format!("^{}(.+)", escape(stripped))).unwrap();
                 pelems.push(Segment::Dynamic {
                     pattern,
+                    insensitive: format!("^{}(.+)", escape_insensitive(stripped)),
                     names: Vec::new(),
                     tail: true,
                 });
@@ -456,6 +709,138 @@ impl From<String> for ResourceDef {
     }
 }
 
+/// Escape static part of the pattern for ascii case insensitive match
+fn escape_insensitive(s: &str) -> String {
+    if s.bytes().any(|b| b.is_ascii_alphabetic()) {
+        format!("(?i-u:{})", escape(s))
+    } else {
+        escape(s)
+    }
+}
+
+/// Remove separator of omitted optional segment
+fn trim_optional(path: &mut String) {
+    if path.len() > 1 && path.ends_with('/') {
+        path.pop();
+    }
+}
+
+/// Expand trailing optional segments to the set of patterns.
+///
+/// Pattern with all segments is the first one, every next pattern omits
+/// one more segment. Returns patterns and number of optional segments.
+fn expand_optional(path: &str) -> (Vec<String>, usize) {
+    // segment separators outside of dynamic segments
+    let mut separators = Vec::new();
+    let mut depth = 0usize;
+    for (idx, b) in path.bytes().enumerate() {
+        match b {
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            b'/' if depth == 0 => separators.push(idx),
+            _ => (),
+        }
+    }
+
+    // positions of `?` and separators of optional segments
+    let mut markers = Vec::new();
+    let mut starts = Vec::new();
+    for (i, start) in separators.iter().enumerate() {
+        let end = separators.get(i + 1).copied().unwrap_or(path.len());
+        let segment = &path[start + 1..end];
+        let marker = segment
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .and_then(|param| {
+                let name = &param[..param.find(':').unwrap_or(param.len())];
+                name.ends_with('?').then(|| start + 1 + name.len())
+            });
+        match marker {
+            Some(marker) => {
+                markers.push(marker);
+                starts.push(*start);
+            }
+            None if !markers.is_empty() => panic!(
+                "Optional segment must be at the end of path pattern {:?}",
+                path
+            ),
+            None => (),
+        }
+    }
+
+    if markers.is_empty() {
+        return (vec![path.to_string()], 0);
+    }
+
+    let strip = |s: &str| -> String {
+        let p: String = s
+            .char_indices()
+            .filter(|(idx, _)| !markers.contains(idx))
+            .map(|(_, c)| c)
+            .collect();
+        if p.is_empty() {
+            "/".to_string()
+        } else {
+            p
+        }
+    };
+
+    let mut paths = vec![strip(path)];
+    for start in starts.iter().rev() {
+        paths.push(strip(&path[..*start]));
+    }
+    (paths, markers.len())
+}
+
+/// Validate value of dynamic segment and push percent-encoded value to path
+fn push_value(
+    path: &mut String,
+    name: &str,
+    value: &str,
+    constraint: &Constraint,
+) -> Result<(), BuildError> {
+    if !constraint.is_match(value) {
+        return Err(BuildError::InvalidValue {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    let tail = constraint.is_tail();
+    for b in value.bytes() {
+        match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'!'
+            | b'$'
+            | b'&'
+            | b'\''
+            | b'('
+            | b')'
+            | b'*'
+            | b'+'
+            | b','
+            | b';'
+            | b'='
+            | b':'
+            | b'@' => path.push(b as char),
+            b'/' if tail => path.push('/'),
+            _ => {
+                const HEX: &[u8; 16] = b"0123456789ABCDEF";
+                path.push('%');
+                path.push(HEX[(b >> 4) as usize] as char);
+                path.push(HEX[(b & 0xf) as usize] as char);
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn insert_slash(path: &str) -> String {
     let mut path = path.to_owned();
     if !path.is_empty() && !path.starts_with('/') {
@@ -670,6 +1055,39 @@ mod tests {
             resource.get("id").unwrap(),
             "http://localhost:80/file//var/log/syslog"
         );
+
+        // raw values
+        let tree = Tree::new(&ResourceDef::new("/files/v{version}/{name}"), 1);
+        let uri = Uri::try_from("/files/v%31/my%20doc%2Ftxt").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("version").unwrap(), "1");
+        assert_eq!(resource.get_raw("version").unwrap(), "%31");
+        assert_eq!(resource.get("name").unwrap(), "my doc/txt");
+        assert_eq!(resource.get_raw("name").unwrap(), "my%20doc%2Ftxt");
+
+        let uri = Uri::try_from("/files/v2/doc").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get_raw("name").unwrap(), "doc");
+
+        // invalid utf-8 is not decoded
+        let uri = Uri::try_from("/files/v2/doc%FF").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("name").unwrap(), "doc%FF");
+
+        // tail is decoded as plain segments
+        let tree = Tree::new(&ResourceDef::new("/assets/{tail}*"), 1);
+        let uri = Uri::try_from("/assets/dir/qwe%25rty/a%20b.txt").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("tail").unwrap(), "dir/qwe%rty/a b.txt");
+
+        let uri = Uri::try_from("/assets/qwe%25rty/dir").unwrap();
+        let mut resource = Path::new(uri);
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("tail").unwrap(), "qwe%rty/dir");
     }
 
     #[cfg(feature = "http")]
@@ -724,6 +1142,7 @@ mod tests {
 
         let seg2 = Segment::Dynamic {
             pattern: Regex::new("test").unwrap(),
+            insensitive: "test".to_string(),
             names: Vec::new(),
             tail: false,
         };
@@ -751,6 +1170,39 @@ mod tests {
         let mut resource = Path::new("/user/-2345/sdg");
         assert_eq!(tree.find(&mut resource), Some(1));
         assert_eq!(resource.get("id").unwrap(), "2345/sdg");
+
+        let tree = Tree::new(&ResourceDef::new("/assets/{tail}*"), 1);
+        assert_eq!(tree.find(&mut Path::new("/assets")), None);
+
+        let mut resource = Path::new("/assets/");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("tail").unwrap(), "");
+
+        // custom regex for tail
+        let tree = Tree::new(&ResourceDef::new("/assets/{path:.+\\.css}*"), 1);
+        assert_eq!(tree.find(&mut Path::new("/assets/")), None);
+        assert_eq!(tree.find(&mut Path::new("/assets/main.js")), None);
+
+        let mut resource = Path::new("/assets/css/main.css");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "css/main.css");
+
+        // `{name:.*}` as last segment
+        let re = ResourceDef::new("/assets/{path:.*}");
+        let tree = Tree::new(&re, 1);
+        assert_eq!(tree.find(&mut Path::new("/assets")), None);
+
+        let mut resource = Path::new("/assets/");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "");
+
+        let mut resource = Path::new("/assets/css/main.css");
+        assert_eq!(tree.find(&mut resource), Some(1));
+        assert_eq!(resource.get("path").unwrap(), "css/main.css");
+
+        let mut s = String::new();
+        assert!(re.resource_path(&mut s, &mut ["css/main.css"].iter()));
+        assert_eq!(s, "/assets/css/main.css");
     }
 
     #[test]
@@ -997,6 +1449,202 @@ mod tests {
         assert_eq!(s, "/user/item/item2/");
     }
 
+    #[test]
+    fn test_parse_optional() {
+        let re = ResourceDef::new("/archive/{year}/{month?:\\d+}");
+        assert_eq!(re.pattern(), "/archive/{year}/{month?:\\d+}");
+        let tree = Tree::new(&re, 1);
+
+        let mut path = Path::new("/archive/2023/05");
+        assert_eq!(tree.find(&mut path), Some(1));
+        assert_eq!(path.get("year").unwrap(), "2023");
+        assert_eq!(path.get("month").unwrap(), "05");
+
+        let mut path = Path::new("/archive/2023");
+        assert_eq!(tree.find(&mut path), Some(1));
+        assert_eq!(path.get("year").unwrap(), "2023");
+        assert!(path.get("month").is_none());
+
+        assert_eq!(tree.find(&mut Path::new("/archive/2023/may")), None);
+        assert_eq!(tree.find(&mut Path::new("/archive")), None);
+
+        let tree = Tree::new(&ResourceDef::new("/{lang?}/{page?}"), 1);
+        assert_eq!(tree.find(&mut Path::new("/")), Some(1));
+        let mut path = Path::new("/en");
+        assert_eq!(tree.find(&mut path), Some(1));
+        assert_eq!(path.get("lang").unwrap(), "en");
+        let mut path = Path::new("/en/index");
+        assert_eq!(tree.find(&mut path), Some(1));
+        assert_eq!(path.get("page").unwrap(), "index");
+
+        let re = ResourceDef::new("/archive/{year}/{month?}");
+        let mut s = String::new();
+        re.build_path(&mut s, &mut ["2023", "05"].iter()).unwrap();
+        assert_eq!(s, "/archive/2023/05");
+        let mut s = String::new();
+        re.build_path(&mut s, &mut ["2023"].iter()).unwrap();
+        assert_eq!(s, "/archive/2023");
+        assert_eq!(
+            re.build_path(&mut String::new(), &mut [""; 0].iter()),
+            Err(BuildError::MissingValue("year".to_string()))
+        );
+
+        let mut map = HashMap::new();
+        map.insert("year", "2023");
+        let mut s = String::new();
+        re.build_path_named(&mut s, &map).unwrap();
+        assert_eq!(s, "/archive/2023");
+
+        let mut s = String::new();
+        ResourceDef::new("/{id?}")
+            .build_path(&mut s, &mut [""; 0].iter())
+            .unwrap();
+        assert_eq!(s, "/");
+    }
+
+    #[test]
+    #[should_panic(expected = "Optional segment must be at the end of path pattern")]
+    fn test_parse_optional_not_last() {
+        ResourceDef::new("/archive/{year?}/{month}");
+    }
+
+    #[test]
+    fn test_build_path() {
+        let resource = ResourceDef::new("/user/{id:\\d+}/{name}.{ext}");
+        let mut s = String::new();
+        resource
+            .build_path(&mut s, &mut ["12", "a b/c", "tar.gz"].iter())
+            .unwrap();
+        assert_eq!(s, "/user/12/a%20b%2Fc.tar.gz");
+
+        assert_eq!(
+            resource.build_path(&mut String::new(), &mut ["12", "a"].iter()),
+            Err(BuildError::MissingValue("ext".to_string()))
+        );
+        let err = resource
+            .build_path(&mut String::new(), &mut ["1a", "a", "b"].iter())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BuildError::InvalidValue {
+                name: "id".to_string(),
+                value: "1a".to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Value \"1a\" does not match pattern of parameter \"id\""
+        );
+        assert!(resource
+            .build_path(&mut String::new(), &mut ["1", "", "b"].iter())
+            .is_err());
+
+        let resource = ResourceDef::new("/static/{tail}*");
+        let mut s = String::new();
+        resource
+            .build_path(&mut s, &mut ["css/my style.css"].iter())
+            .unwrap();
+        assert_eq!(s, "/static/css/my%20style.css");
+        let mut s = String::new();
+        resource.build_path(&mut s, &mut [""].iter()).unwrap();
+        assert_eq!(s, "/static/");
+
+        let resource = ResourceDef::new("/{lang:[a-z]{2}}/{tail:[a-z/]+}*");
+        let mut s = String::new();
+        resource
+            .build_path(&mut s, &mut ["en", "docs/intro"].iter())
+            .unwrap();
+        assert_eq!(s, "/en/docs/intro");
+        assert!(resource
+            .build_path(&mut String::new(), &mut ["eng", "docs"].iter())
+            .is_err());
+
+        let resource = ResourceDef::new("/user/{id}/{page}");
+        let mut map = HashMap::new();
+        map.insert("id", "1");
+        assert_eq!(
+            resource.build_path_named(&mut String::new(), &map),
+            Err(BuildError::MissingValue("page".to_string()))
+        );
+        map.insert("page", "2");
+        map.insert("sort", "asc");
+        let err = resource
+            .build_path_named(&mut String::new(), &map)
+            .unwrap_err();
+        assert_eq!(err, BuildError::UnknownParameter("sort".to_string()));
+        assert_eq!(
+            err.to_string(),
+            "Parameter \"sort\" is not defined by pattern"
+        );
+        map.remove("sort");
+        let mut s = String::new();
+        resource.build_path_named(&mut s, &map).unwrap();
+        assert_eq!(s, "/user/1/2");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_build_path_roundtrip() {
+        use http::Uri;
+
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '-', '.', '_', '~', '!', '$', '&', '\'', '(', ')', '*', '+',
+            ',', ';', '=', ':', '@', '/', '%', '?', '#', ' ', '[', ']', '{', '}', '"',
+            '\\', '^', '|', 'ж', '€', '😀',
+        ];
+
+        // simple deterministic generator
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        let value = |next: &mut dyn FnMut(usize) -> usize, min: usize| {
+            let len = min + next(8);
+            (0..len)
+                .map(|_| CHARS[next(CHARS.len())])
+                .collect::<String>()
+        };
+
+        let patterns = [
+            "/user/{id}",
+            "/user/{id}/{name}/test",
+            "/files/v{version}/{name}.txt",
+            "/assets/{tail}*",
+            "/user/{id}/{tail:.*}",
+        ];
+        for pattern in patterns {
+            let resource = ResourceDef::new(pattern);
+            let tree = Tree::new(&resource, 1);
+            let names: Vec<_> = resource
+                .elements
+                .iter()
+                .filter_map(|el| match el {
+                    PathElement::Var(name, _) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+
+            for _ in 0..500 {
+                let values: Vec<_> = names
+                    .iter()
+                    .map(|name| value(&mut next, usize::from(name != "tail")))
+                    .collect();
+                let mut path = String::new();
+                resource.build_path(&mut path, &mut values.iter()).unwrap();
+
+                let uri = Uri::try_from(path.as_str()).unwrap();
+                let mut res = Path::new(uri);
+                assert_eq!(tree.find(&mut res), Some(1), "{}", path);
+                for (name, value) in names.iter().zip(values.iter()) {
+                    assert_eq!(res.get(name).unwrap(), value, "{}", path);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_non_rooted() {
         let tree = Tree::new(&ResourceDef::new("name"), 1);
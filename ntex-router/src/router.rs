@@ -17,6 +17,7 @@ impl<T, U> Router<T, U> {
         RouterBuilder {
             resources: Vec::new(),
             insensitive: false,
+            rank: false,
         }
     }
 
@@ -114,18 +115,31 @@ impl<T, U> Router<T, U> {
 #[derive(Debug)]
 pub struct RouterBuilder<T, U = ()> {
     insensitive: bool,
+    rank: bool,
     resources: Vec<(ResourceDef, T, Option<U>)>,
 }
 
 impl<T, U> RouterBuilder<T, U> {
-    /// Make router case insensitive. Only static segments
-    /// could be case insensitive.
+    /// Make router case insensitive.
+    ///
+    /// Static segments and static parts of dynamic segments are matched
+    /// ascii case insensitively, values of dynamic segments are not changed.
     ///
     /// By default router is case sensitive.
     pub fn case_insensitive(&mut self) {
         self.insensitive = true;
     }
 
+    /// Match resources in order of specificity.
+    ///
+    /// Resources are sorted by [`ResourceDef::rank`], resources with the same
+    /// rank are matched in registration order.
+    ///
+    /// By default resources are matched in registration order.
+    pub fn rank_by_specificity(&mut self) {
+        self.rank = true;
+    }
+
     /// Register resource for specified path.
     pub fn path<P: IntoPattern>(
         &mut self,
@@ -159,7 +173,18 @@ impl<T, U> RouterBuilder<T, U> {
     }
 
     /// Finish configuration and create router instance.
-    pub fn finish(self) -> Router<T, U> {
+    pub fn finish(mut self) -> Router<T, U> {
+        if self.insensitive {
+            for (rdef, _, _) in &mut self.resources {
+                rdef.case_insensitive();
+            }
+        }
+        if self.rank {
+            // stable sort keeps registration order for resources with the same rank
+            self.resources
+                .sort_by_key(|r| std::cmp::Reverse(r.0.rank()));
+        }
+
         let tree = if self.resources.is_empty() {
             Tree::default()
         } else {
@@ -182,6 +207,7 @@ impl<T, U> RouterBuilder<T, U> {
 mod tests {
     use crate::path::Path;
     use crate::router::{ResourceId, Router};
+    use crate::ResourceDef;
 
     #[test]
     fn test_recognizer_1() {
@@ -267,6 +293,120 @@ mod tests {
         assert_eq!(path.get("custom").unwrap(), "blah-blah");
     }
 
+    #[test]
+    fn test_recognizer_regex() {
+        let mut router = Router::<usize>::build();
+        router.path("/items/{id:\\d+}", 10).0.set_id(0);
+        router.path("/items/{slug:[a-z-]+}", 11).0.set_id(1);
+        router
+            .path("/items/{id:\\d+}/{slug:[a-z-]+}.html", 12)
+            .0
+            .set_id(2);
+        router.path("/items/{any}/{name}", 13).0.set_id(3);
+        router.path("/items/{any}", 14).0.set_id(4);
+        let mut router = router.finish();
+
+        let mut path = Path::new("/items/123");
+        let (h, info) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 10);
+        assert_eq!(info, ResourceId(0));
+        assert_eq!(path.get("id").unwrap(), "123");
+
+        let mut path = Path::new("/items/some-item");
+        let (h, info) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 11);
+        assert_eq!(info, ResourceId(1));
+        assert_eq!(path.get("slug").unwrap(), "some-item");
+        assert!(path.get("id").is_none());
+
+        // regex must match whole segment
+        let mut path = Path::new("/items/123abc");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 14);
+        assert_eq!(path.get("any").unwrap(), "123abc");
+
+        let mut path = Path::new("/items/12/item-name.html");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 12);
+        assert_eq!(path.get("id").unwrap(), "12");
+        assert_eq!(path.get("slug").unwrap(), "item-name");
+
+        let mut path = Path::new("/items/ab/item-name.html");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 13);
+        assert_eq!(path.get("any").unwrap(), "ab");
+        assert_eq!(path.get("name").unwrap(), "item-name.html");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid regex in path pattern \"/items/{id:[0-9}\"")]
+    fn test_recognizer_invalid_regex() {
+        let mut router = Router::<usize>::build();
+        router.path("/items/{id:[0-9}", 10);
+    }
+
+    #[test]
+    fn test_recognizer_rank() {
+        // registration order
+        let mut router = Router::<usize>::build();
+        router.path("/users/{id}", 10);
+        router.path("/users/me", 11);
+        let router = router.finish();
+        let mut path = Path::new("/users/me");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 10);
+
+        let mut router = Router::<usize>::build();
+        router.rank_by_specificity();
+        router.path("/{tail}*", 10);
+        router.path("/users/{id}", 11);
+        router.path("/users/{id:\\d+}", 12);
+        router.path("/users/me", 13);
+        router.path("/users/v{version}", 14);
+        router.path("/users/{name}", 15);
+        let router = router.finish();
+
+        let mut path = Path::new("/users/me");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 13);
+        let mut path = Path::new("/users/v1");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 14);
+        let mut path = Path::new("/users/123");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 12);
+        let mut path = Path::new("/users/john");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 11);
+        let mut path = Path::new("/index.html");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 10);
+    }
+
+    #[test]
+    fn test_rank() {
+        let rank = ResourceDef::new("/users/me").rank();
+        assert!(rank.is_static());
+        assert_eq!(rank.static_prefix(), 9);
+        assert_eq!(rank.constrained(), 0);
+
+        let rank = ResourceDef::new("/users/{id:\\d+}/{name}").rank();
+        assert!(!rank.is_static());
+        assert_eq!(rank.static_prefix(), 7);
+        assert_eq!(rank.constrained(), 1);
+
+        assert!(!ResourceDef::prefix("/users").rank().is_static());
+        assert!(
+            ResourceDef::new("/users/me").rank() > ResourceDef::new("/users/{id}").rank()
+        );
+        assert!(
+            ResourceDef::new("/users/{id:\\d+}").rank()
+                > ResourceDef::new("/users/{id}").rank()
+        );
+        assert!(
+            ResourceDef::new("/users/v{id}").rank()
+                > ResourceDef::new("/users/{id}").rank()
+        );
+        assert_eq!(
+            ResourceDef::new(["/{id}", "/users/me"]).rank(),
+            ResourceDef::new("/users/me").rank()
+        );
+    }
+
     #[test]
     fn test_recognizer_2() {
         let mut router = Router::<usize>::build();
@@ -299,8 +439,11 @@ mod tests {
         let (h, _) = router.recognize_mut(&mut path).unwrap();
         assert_eq!(*h, 10);
 
-        let mut path = Path::new("/test.jsoN");
-        assert!(router.recognize_mut(&mut path).is_none());
+        // static parts of dynamic segment are case insensitive
+        let mut path = Path::new("/tesT.jsoN");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 11);
+        assert_eq!(path.get("source").unwrap(), "tesT");
     }
 
     #[test]
@@ -411,6 +554,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_recognizer_insensitive() {
+        let mut router = Router::<usize>::build();
+        router.case_insensitive();
+        router.path("/api/v1/users", 10);
+        router.path("/api/v{version}/items/{id}", 11);
+        router.path("/files/{name}.json", 12);
+        router.path("/static/img*", 13);
+        let mut router = router.finish();
+
+        let mut path = Path::new("/API/V1/Users");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 10);
+        assert_eq!(*path.get_ref(), "/API/V1/Users");
+
+        let mut path = Path::new("/Api/V2/ITEMS/AbC");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 11);
+        assert_eq!(path.get("version").unwrap(), "2");
+        assert_eq!(path.get("id").unwrap(), "AbC");
+
+        let mut path = Path::new("/FILES/Report.JSON");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 12);
+        assert_eq!(path.get("name").unwrap(), "Report");
+
+        let mut path = Path::new("/Static/IMG/Logo.png");
+        let (h, _) = router.recognize_mut(&mut path).unwrap();
+        assert_eq!(*h, 13);
+
+        let mut path = Path::new("/files/report.txt");
+        assert!(router.recognize_mut(&mut path).is_none());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_recognizer_insensitive_urlencoded() {
+        use http::Uri;
+
+        let mut router = Router::<usize>::build();
+        router.case_insensitive();
+        router.path("/api/{name}", 10);
+        router.path("/api/v1/users", 11);
+        let router = router.finish();
+
+        let mut path = Path::new(Uri::try_from("/API/%56%31/Users").unwrap());
+        let (h, _) = router.recognize(&mut path).unwrap();
+        assert_eq!(*h, 11);
+
+        // encoded slash is not a segment separator
+        let mut path = Path::new(Uri::try_from("/API/V1%2FUsers").unwrap());
+        let (h, _) = router.recognize(&mut path).unwrap();
+        assert_eq!(*h, 10);
+        assert_eq!(path.get("name").unwrap(), "V1/Users");
+    }
+
     #[test]
     fn test_recognizer_checked_insensitive() {
         let mut router = Router::<usize, usize>::build();
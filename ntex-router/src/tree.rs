@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::mem;
 
 use super::path::PathItem;
+use super::quoter::raw_offset;
 use super::resource::{ResourceDef, Segment};
 use super::{Resource, ResourcePath};
 
@@ -416,14 +417,27 @@ impl Tree {
                     ..
                 } => {
                     // special treatment for tail, it matches regardless of sleshes
-                    let seg = if tail { path } else { segment.as_ref() };
+                    let (seg, quoted) = if tail {
+                        let seg = T::unquote(path);
+                        let quoted = matches!(seg, Cow::Owned(_));
+                        (seg, quoted)
+                    } else {
+                        (Cow::Borrowed(segment.as_ref()), quoted)
+                    };
 
-                    if let Some(captures) = pattern.captures(seg) {
+                    if let Some(captures) = pattern.captures(seg.as_ref()) {
                         let mut is_match = true;
                         for name in names.iter() {
                             if let Some(m) = captures.name(name) {
                                 let item = if quoted {
-                                    PathItem::Segment(m.as_str().to_string())
+                                    let raw = if tail { path } else { &path[..idx] };
+                                    let start = base_skip + skip as isize;
+                                    PathItem::Segment(
+                                        m.as_str().to_string(),
+                                        (start + raw_offset(raw, m.start()) as isize)
+                                            as u16,
+                                        (start + raw_offset(raw, m.end()) as isize) as u16,
+                                    )
                                 } else {
                                     PathItem::IdxSegment(
                                         (base_skip + (skip + m.start()) as isize) as u16,
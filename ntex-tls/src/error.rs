@@ -0,0 +1,98 @@
+use std::{error::Error, fmt, io, sync::Arc};
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+type ErrorFn = Arc<dyn Fn(&HandshakeError) + Send + Sync>;
+
+/// Tls handshake error
+///
+/// Acceptor services return handshake error as a source of service error,
+/// i.e. openssl acceptor returns boxed `HandshakeError`, rustls acceptor
+/// returns `io::Error` that wraps `HandshakeError`.
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum HandshakeError {
+    /// Handshake is not completed within configured timeout
+    Timeout,
+    /// Peer certificate is rejected
+    CertRejected(BoxedError),
+    /// Tls protocol error
+    Protocol(BoxedError),
+    /// Io error, i.e. peer is disconnected during handshake
+    Io(io::Error),
+}
+
+impl HandshakeError {
+    /// Get handshake error from acceptor service error
+    pub fn from_error<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a HandshakeError> {
+        err.downcast_ref::<HandshakeError>().or_else(|| {
+            err.downcast_ref::<io::Error>()
+                .and_then(|e| e.get_ref())
+                .and_then(|e| e.downcast_ref::<HandshakeError>())
+        })
+    }
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Timeout => write!(f, "tls handshake timeout"),
+            HandshakeError::CertRejected(e) => {
+                write!(f, "peer certificate is rejected: {}", e)
+            }
+            HandshakeError::Protocol(e) => write!(f, "tls protocol error: {}", e),
+            HandshakeError::Io(e) => write!(f, "io error during tls handshake: {}", e),
+        }
+    }
+}
+
+impl Error for HandshakeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HandshakeError::Timeout => None,
+            HandshakeError::CertRejected(e) | HandshakeError::Protocol(e) => {
+                Some(e.as_ref())
+            }
+            HandshakeError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<HandshakeError> for io::Error {
+    fn from(err: HandshakeError) -> io::Error {
+        let kind = match err {
+            HandshakeError::Timeout => io::ErrorKind::TimedOut,
+            HandshakeError::Io(ref e) => e.kind(),
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Handshake error callback
+#[derive(Clone, Default)]
+pub(crate) struct OnError(Option<ErrorFn>);
+
+impl OnError {
+    pub(crate) fn new<U>(f: U) -> Self
+    where
+        U: Fn(&HandshakeError) + Send + Sync + 'static,
+    {
+        OnError(Some(Arc::new(f)))
+    }
+
+    pub(crate) fn call<T>(
+        &self,
+        res: Result<T, HandshakeError>,
+    ) -> Result<T, HandshakeError> {
+        if let (Err(err), Some(f)) = (&res, &self.0) {
+            f(err);
+        }
+        res
+    }
+}
+
+impl fmt::Debug for OnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OnError").field(&self.0.is_some()).finish()
+    }
+}
@@ -15,6 +15,11 @@ pub mod rustls;
 
 mod counter;
 
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod error;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::error::HandshakeError;
+
 /// Sets the maximum per-worker concurrent ssl connection establish process.
 ///
 /// All listeners will stop accepting connections when this limit is
@@ -1,13 +1,15 @@
 use std::task::{Context, Poll};
 use std::{error::Error, future::Future, marker::PhantomData, pin::Pin};
 
-use ntex_io::{Filter, FilterFactory, Io, Layer};
+use ntex_io::{Filter, Io, Layer};
 use ntex_service::{Service, ServiceCtx, ServiceFactory};
-use ntex_util::{future::Ready, time::Millis};
+use ntex_util::future::{BoxFuture, Ready};
+use ntex_util::time::Millis;
 use tls_openssl::ssl::SslAcceptor;
 
 use crate::counter::{Counter, CounterGuard};
-use crate::MAX_SSL_ACCEPT_COUNTER;
+use crate::error::OnError;
+use crate::{HandshakeError, MAX_SSL_ACCEPT_COUNTER};
 
 use super::{SslAcceptor as IoSslAcceptor, SslFilter};
 
@@ -17,6 +19,7 @@ use super::{SslAcceptor as IoSslAcceptor, SslFilter};
 /// `openssl` feature enables `Acceptor` type
 pub struct Acceptor<F> {
     acceptor: IoSslAcceptor,
+    on_error: OnError,
     _t: PhantomData<F>,
 }
 
@@ -25,17 +28,31 @@ impl<F> Acceptor<F> {
     pub fn new(acceptor: SslAcceptor) -> Self {
         Acceptor {
             acceptor: IoSslAcceptor::new(acceptor),
+            on_error: OnError::default(),
             _t: PhantomData,
         }
     }
 
     /// Set handshake timeout.
     ///
-    /// Default is set to 5 seconds.
+    /// Connection is dropped if handshake is not completed within timeout.
+    /// Timeout is disabled if it is set to 0. Default is set to 5 seconds.
     pub fn timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
         self.acceptor.timeout(timeout);
         self
     }
+
+    /// Set handshake error callback.
+    ///
+    /// Callback is called for every failed handshake, including timed out
+    /// handshakes. It could be used for updating metrics.
+    pub fn on_error<U>(mut self, f: U) -> Self
+    where
+        U: Fn(&HandshakeError) + Send + Sync + 'static,
+    {
+        self.on_error = OnError::new(f);
+        self
+    }
 }
 
 impl<F> From<SslAcceptor> for Acceptor<F> {
@@ -48,6 +65,7 @@ impl<F> Clone for Acceptor<F> {
     fn clone(&self) -> Self {
         Self {
             acceptor: self.acceptor.clone(),
+            on_error: self.on_error.clone(),
             _t: PhantomData,
         }
     }
@@ -65,6 +83,7 @@ impl<F: Filter, C: 'static> ServiceFactory<Io<F>, C> for Acceptor<F> {
         MAX_SSL_ACCEPT_COUNTER.with(|conns| {
             Ready::Ok(AcceptorService {
                 acceptor: self.acceptor.clone(),
+                on_error: self.on_error.clone(),
                 conns: conns.clone(),
                 _t: PhantomData,
             })
@@ -78,6 +97,7 @@ impl<F: Filter, C: 'static> ServiceFactory<Io<F>, C> for Acceptor<F> {
 /// `openssl` feature enables `Acceptor` type
 pub struct AcceptorService<F> {
     acceptor: IoSslAcceptor,
+    on_error: OnError,
     conns: Counter,
     _t: PhantomData<F>,
 }
@@ -100,7 +120,8 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
     fn call<'a>(&'a self, req: Io<F>, _: ServiceCtx<'a, Self>) -> Self::Future<'a> {
         AcceptorServiceResponse {
             _guard: self.conns.get(),
-            fut: self.acceptor.clone().create(req),
+            fut: self.acceptor.clone().accept(req),
+            on_error: self.on_error.clone(),
         }
     }
 }
@@ -111,7 +132,8 @@ pin_project_lite::pin_project! {
         F: Filter,
     {
         #[pin]
-        fut: <IoSslAcceptor as FilterFactory<F>>::Future,
+        fut: BoxFuture<'static, Result<Io<Layer<SslFilter, F>>, HandshakeError>>,
+        on_error: OnError,
         _guard: CounterGuard,
     }
 }
@@ -120,6 +142,7 @@ impl<F: Filter> Future for AcceptorServiceResponse<F> {
     type Output = Result<Io<Layer<SslFilter, F>>, Box<dyn Error>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().fut.poll(cx)
+        let this = self.project();
+        this.fut.poll(cx).map(|res| Ok(this.on_error.call(res)?))
     }
 }
@@ -6,9 +6,9 @@ use ntex_bytes::{BufMut, BytesVec};
 use ntex_io::{types, Filter, FilterFactory, FilterLayer, Io, Layer, ReadBuf, WriteBuf};
 use ntex_util::{future::poll_fn, future::BoxFuture, ready, time, time::Millis};
 use tls_openssl::ssl::{self, NameType, SslStream};
-use tls_openssl::x509::X509;
+use tls_openssl::x509::{X509VerifyResult, X509};
 
-use crate::{PskIdentity, Servername};
+use crate::{HandshakeError, PskIdentity, Servername};
 
 mod accept;
 pub use self::accept::{Acceptor, AcceptorService};
@@ -228,7 +228,7 @@ impl SslAcceptor {
 
     /// Set handshake timeout.
     ///
-    /// Default is set to 5 seconds.
+    /// Timeout is disabled if it is set to 0. Default is set to 5 seconds.
     pub fn timeout<U: Into<Millis>>(&mut self, timeout: U) -> &mut Self {
         self.timeout = timeout.into();
         self
@@ -252,26 +252,27 @@ impl fmt::Debug for SslAcceptor {
     }
 }
 
-impl<F: Filter> FilterFactory<F> for SslAcceptor {
-    type Filter = SslFilter;
-
-    type Error = Box<dyn Error>;
-    type Future = BoxFuture<'static, Result<Io<Layer<Self::Filter, F>>, Self::Error>>;
-
-    fn create(self, io: Io<F>) -> Self::Future {
+impl SslAcceptor {
+    pub(crate) fn accept<F: Filter>(
+        self,
+        io: Io<F>,
+    ) -> BoxFuture<'static, Result<Io<Layer<SslFilter, F>>, HandshakeError>> {
         let timeout = self.timeout;
         let ctx_result = ssl::Ssl::new(self.acceptor.context());
 
         Box::pin(async move {
-            time::timeout(timeout, async {
-                let ssl = ctx_result.map_err(map_to_ioerr)?;
+            time::timeout_checked(timeout, async {
+                let ssl = ctx_result.map_err(|e| HandshakeError::Protocol(e.into()))?;
                 let inner = IoInner {
                     source: None,
                     destination: None,
                 };
                 let filter = SslFilter {
                     handshake: Cell::new(true),
-                    inner: RefCell::new(ssl::SslStream::new(ssl, inner)?),
+                    inner: RefCell::new(
+                        ssl::SslStream::new(ssl, inner)
+                            .map_err(|e| HandshakeError::Protocol(e.into()))?,
+                    ),
                 };
                 let io = io.add_filter(filter);
 
@@ -288,20 +289,51 @@ impl<F: Filter> FilterFactory<F> for SslAcceptor {
                         })?;
                     handle_result(result, &io, cx)
                 })
-                .await?;
+                .await
+                .map_err(|err| handshake_error(err, io.filter().inner.borrow().ssl()))?;
 
                 io.filter().handshake.set(false);
                 Ok(io)
             })
             .await
-            .map_err(|_| {
-                io::Error::new(io::ErrorKind::TimedOut, "ssl handshake timeout").into()
-            })
+            .map_err(|_| HandshakeError::Timeout)
             .and_then(|item| item)
         })
     }
 }
 
+impl<F: Filter> FilterFactory<F> for SslAcceptor {
+    type Filter = SslFilter;
+
+    type Error = Box<dyn Error>;
+    type Future = BoxFuture<'static, Result<Io<Layer<Self::Filter, F>>, Self::Error>>;
+
+    fn create(self, io: Io<F>) -> Self::Future {
+        let fut = self.accept(io);
+        Box::pin(async move { Ok(fut.await?) })
+    }
+}
+
+/// Classify acceptor handshake error
+fn handshake_error(err: Box<dyn Error>, ssl: &ssl::SslRef) -> HandshakeError {
+    let err = match err.downcast::<io::Error>() {
+        Ok(err) => return HandshakeError::Io(*err),
+        Err(err) => err,
+    };
+    match err.downcast::<ssl::Error>() {
+        Ok(err) => {
+            if ssl.verify_result() != X509VerifyResult::OK {
+                HandshakeError::CertRejected(err)
+            } else if err.io_error().is_some() {
+                HandshakeError::Io(err.into_io_error().unwrap())
+            } else {
+                HandshakeError::Protocol(err)
+            }
+        }
+        Err(err) => HandshakeError::Protocol(err.to_string().into()),
+    }
+}
+
 #[derive(Debug)]
 pub struct SslConnector {
     ssl: ssl::Ssl,
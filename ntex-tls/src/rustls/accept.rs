@@ -3,12 +3,14 @@ use std::{future::Future, io, marker::PhantomData, pin::Pin, sync::Arc};
 
 use tls_rust::ServerConfig;
 
-use ntex_io::{Filter, FilterFactory, Io, Layer};
+use ntex_io::{Filter, Io, Layer};
 use ntex_service::{Service, ServiceCtx, ServiceFactory};
-use ntex_util::{future::Ready, time::Millis};
+use ntex_util::future::{BoxFuture, Ready};
+use ntex_util::time::Millis;
 
 use super::{TlsAcceptor, TlsFilter};
-use crate::{counter::Counter, counter::CounterGuard, MAX_SSL_ACCEPT_COUNTER};
+use crate::{counter::Counter, counter::CounterGuard, error::OnError};
+use crate::{HandshakeError, MAX_SSL_ACCEPT_COUNTER};
 
 #[derive(Debug)]
 /// Support `SSL` connections via rustls package
@@ -16,6 +18,7 @@ use crate::{counter::Counter, counter::CounterGuard, MAX_SSL_ACCEPT_COUNTER};
 /// `rust-tls` feature enables `RustlsAcceptor` type
 pub struct Acceptor<F> {
     inner: TlsAcceptor,
+    on_error: OnError,
     _t: PhantomData<F>,
 }
 
@@ -24,17 +27,31 @@ impl<F> Acceptor<F> {
     pub fn new(config: Arc<ServerConfig>) -> Self {
         Acceptor {
             inner: TlsAcceptor::new(config),
+            on_error: OnError::default(),
             _t: PhantomData,
         }
     }
 
     /// Set handshake timeout.
     ///
-    /// Default is set to 5 seconds.
+    /// Connection is dropped if handshake is not completed within timeout.
+    /// Timeout is disabled if it is set to 0. Default is set to 5 seconds.
     pub fn timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
         self.inner.timeout(timeout.into());
         self
     }
+
+    /// Set handshake error callback.
+    ///
+    /// Callback is called for every failed handshake, including timed out
+    /// handshakes. It could be used for updating metrics.
+    pub fn on_error<U>(mut self, f: U) -> Self
+    where
+        U: Fn(&HandshakeError) + Send + Sync + 'static,
+    {
+        self.on_error = OnError::new(f);
+        self
+    }
 }
 
 impl<F> From<ServerConfig> for Acceptor<F> {
@@ -47,6 +64,7 @@ impl<F> Clone for Acceptor<F> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            on_error: self.on_error.clone(),
             _t: PhantomData,
         }
     }
@@ -65,6 +83,7 @@ impl<F: Filter, C: 'static> ServiceFactory<Io<F>, C> for Acceptor<F> {
         MAX_SSL_ACCEPT_COUNTER.with(|conns| {
             Ready::Ok(AcceptorService {
                 acceptor: self.inner.clone(),
+                on_error: self.on_error.clone(),
                 conns: conns.clone(),
                 io: PhantomData,
             })
@@ -77,6 +96,7 @@ impl<F: Filter, C: 'static> ServiceFactory<Io<F>, C> for Acceptor<F> {
 pub struct AcceptorService<F> {
     acceptor: TlsAcceptor,
     io: PhantomData<F>,
+    on_error: OnError,
     conns: Counter,
 }
 
@@ -98,7 +118,8 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
     fn call<'a>(&'a self, req: Io<F>, _: ServiceCtx<'a, Self>) -> Self::Future<'a> {
         AcceptorServiceFut {
             _guard: self.conns.get(),
-            fut: self.acceptor.clone().create(req),
+            fut: self.acceptor.clone().accept(req),
+            on_error: self.on_error.clone(),
         }
     }
 }
@@ -109,7 +130,8 @@ pin_project_lite::pin_project! {
         F: Filter,
     {
         #[pin]
-        fut: <TlsAcceptor as FilterFactory<F>>::Future,
+        fut: BoxFuture<'static, Result<Io<Layer<TlsFilter, F>>, HandshakeError>>,
+        on_error: OnError,
         _guard: CounterGuard,
     }
 }
@@ -118,6 +140,7 @@ impl<F: Filter> Future for AcceptorServiceFut<F> {
     type Output = Result<Io<Layer<TlsFilter, F>>, io::Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().fut.poll(cx)
+        let this = self.project();
+        this.fut.poll(cx).map(|res| Ok(this.on_error.call(res)?))
     }
 }
@@ -9,6 +9,8 @@ use ntex_io::{
 use ntex_util::{future::BoxFuture, time::Millis};
 use tls_rust::{Certificate, ClientConfig, ServerConfig, ServerName};
 
+use crate::HandshakeError;
+
 mod accept;
 mod client;
 mod server;
@@ -129,11 +131,18 @@ impl TlsAcceptor {
 
     /// Set handshake timeout.
     ///
-    /// Default is set to 5 seconds.
+    /// Timeout is disabled if it is set to 0. Default is set to 5 seconds.
     pub fn timeout<U: Into<Millis>>(&mut self, timeout: U) -> &mut Self {
         self.timeout = timeout.into();
         self
     }
+
+    pub(crate) fn accept<F: Filter>(
+        self,
+        io: Io<F>,
+    ) -> BoxFuture<'static, Result<Io<Layer<TlsFilter, F>>, HandshakeError>> {
+        Box::pin(TlsServerFilter::create(io, self.cfg, self.timeout))
+    }
 }
 
 impl From<ServerConfig> for TlsAcceptor {
@@ -158,10 +167,8 @@ impl<F: Filter> FilterFactory<F> for TlsAcceptor {
     type Future = BoxFuture<'static, Result<Io<Layer<Self::Filter, F>>, io::Error>>;
 
     fn create(self, st: Io<F>) -> Self::Future {
-        let cfg = self.cfg.clone();
-        let timeout = self.timeout;
-
-        Box::pin(async move { TlsServerFilter::create(st, cfg, timeout).await })
+        let fut = self.accept(st);
+        Box::pin(async move { Ok(fut.await?) })
     }
 }
 
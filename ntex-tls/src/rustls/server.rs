@@ -5,10 +5,10 @@ use std::{any, cell::Cell, cell::RefCell, sync::Arc, task::Poll};
 use ntex_bytes::BufMut;
 use ntex_io::{types, Filter, FilterLayer, Io, Layer, ReadBuf, WriteBuf};
 use ntex_util::{future::poll_fn, ready, time, time::Millis};
-use tls_rust::{ServerConfig, ServerConnection};
+use tls_rust::{Error, ServerConfig, ServerConnection};
 
 use crate::rustls::{IoInner, TlsFilter, Wrapper};
-use crate::Servername;
+use crate::{HandshakeError, Servername};
 
 use super::{PeerCert, PeerCertChain};
 
@@ -126,8 +126,8 @@ impl TlsServerFilter {
         io: Io<F>,
         cfg: Arc<ServerConfig>,
         timeout: Millis,
-    ) -> Result<Io<Layer<TlsFilter, F>>, io::Error> {
-        time::timeout(timeout, async {
+    ) -> Result<Io<Layer<TlsFilter, F>>, HandshakeError> {
+        time::timeout_checked(timeout, async {
             let session = ServerConnection::new(cfg)
                 .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
             let filter = TlsFilter::new_server(TlsServerFilter {
@@ -193,7 +193,18 @@ impl TlsServerFilter {
             }
         })
         .await
-        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "rustls handshake timeout"))
-        .and_then(|item| item)
+        .map_err(|_| HandshakeError::Timeout)
+        .and_then(|item| item.map_err(handshake_error))
+    }
+}
+
+/// Classify acceptor handshake error
+fn handshake_error(err: io::Error) -> HandshakeError {
+    match err.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+        Some(e @ (Error::InvalidCertificate(_) | Error::NoCertificatesPresented)) => {
+            HandshakeError::CertRejected(Box::new(e.clone()))
+        }
+        Some(e) => HandshakeError::Protocol(Box::new(e.clone())),
+        None => HandshakeError::Io(err),
     }
 }
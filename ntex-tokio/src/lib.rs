@@ -28,6 +28,22 @@ pub async fn tcp_connect_in(addr: SocketAddr, pool: PoolRef) -> Result<Io> {
     Ok(Io::with_memory_pool(TcpStream(sock), pool))
 }
 
+/// Opens a TCP connection to a remote host using bound socket and
+/// specified memory pool.
+///
+/// Socket must be in non-blocking mode.
+pub async fn tcp_connect_bound_in(
+    sock: net::TcpStream,
+    addr: SocketAddr,
+    pool: PoolRef,
+) -> Result<Io> {
+    let sock = tokio::net::TcpSocket::from_std_stream(sock)
+        .connect(addr)
+        .await?;
+    sock.set_nodelay(true)?;
+    Ok(Io::with_memory_pool(TcpStream(sock), pool))
+}
+
 #[cfg(unix)]
 /// Opens a unix stream connection.
 pub async fn unix_connect<'a, P>(addr: P) -> Result<Io>
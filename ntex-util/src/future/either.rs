@@ -11,7 +11,7 @@ pub enum Either<A, B> {
 }
 
 impl<A, B> Either<A, B> {
-    fn project(self: Pin<&mut Self>) -> Either<Pin<&mut A>, Pin<&mut B>> {
+    pub(crate) fn project(self: Pin<&mut Self>) -> Either<Pin<&mut A>, Pin<&mut B>> {
         unsafe {
             match self.get_unchecked_mut() {
                 Either::Left(a) => Either::Left(Pin::new_unchecked(a)),
@@ -0,0 +1,192 @@
+//! `Service` and `ServiceFactory` implementations for `Either`.
+//!
+//! `Either` allows to choose service implementation at runtime without
+//! boxing, both services must handle same request type and must have
+//! same response and error types.
+//!
+//! ```rust
+//! use ntex_service::{fn_service, ServiceFactory};
+//! use ntex_util::future::Either;
+//!
+//! #[ntex::main]
+//! async fn main() {
+//!     let factory = if std::env::var("DOUBLE").is_ok() {
+//!         Either::Left(fn_service(|n: usize| async move { Ok::<_, ()>(n * 2) }))
+//!     } else {
+//!         Either::Right(fn_service(|n: usize| async move { Ok::<_, ()>(n) }))
+//!     };
+//!     let srv = factory.pipeline(()).await.unwrap();
+//!     println!("{:?}", srv.call(1).await);
+//! }
+//! ```
+use std::{future::Future, pin::Pin, task::Context, task::Poll};
+
+use ntex_service::{Service, ServiceCall, ServiceCtx, ServiceFactory};
+
+use crate::future::Either;
+
+impl<A, B, R> Service<R> for Either<A, B>
+where
+    A: Service<R>,
+    B: Service<R, Response = A::Response, Error = A::Error>,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future<'f> =
+        Either<ServiceCall<'f, A, R>, ServiceCall<'f, B, R>> where Self: 'f, R: 'f;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Either::Left(svc) => svc.poll_ready(cx),
+            Either::Right(svc) => svc.poll_ready(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match self {
+            Either::Left(svc) => svc.poll_shutdown(cx),
+            Either::Right(svc) => svc.poll_shutdown(cx),
+        }
+    }
+
+    #[inline]
+    fn call<'a>(&'a self, req: R, ctx: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+        match self {
+            Either::Left(svc) => Either::Left(ctx.call(svc, req)),
+            Either::Right(svc) => Either::Right(ctx.call(svc, req)),
+        }
+    }
+}
+
+impl<A, B, R, C> ServiceFactory<R, C> for Either<A, B>
+where
+    A: ServiceFactory<R, C>,
+    B: ServiceFactory<
+        R,
+        C,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Service = Either<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future<'f> =
+        EitherFactoryResponse<A::Future<'f>, B::Future<'f>> where Self: 'f, C: 'f;
+
+    #[inline]
+    fn create(&self, cfg: C) -> Self::Future<'_> {
+        let fut = match self {
+            Either::Left(factory) => Either::Left(factory.create(cfg)),
+            Either::Right(factory) => Either::Right(factory.create(cfg)),
+        };
+        EitherFactoryResponse { fut }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Response future of `Either` service factory
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless polled"]
+    pub struct EitherFactoryResponse<A, B> {
+        #[pin]
+        fut: Either<A, B>,
+    }
+}
+
+impl<A, B, SA, SB, E> Future for EitherFactoryResponse<A, B>
+where
+    A: Future<Output = Result<SA, E>>,
+    B: Future<Output = Result<SB, E>>,
+{
+    type Output = Result<Either<SA, SB>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().fut.project() {
+            Either::Left(fut) => fut.poll(cx).map(|res| res.map(Either::Left)),
+            Either::Right(fut) => fut.poll(cx).map(|res| res.map(Either::Right)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_service::{fn_factory, fn_service, Pipeline};
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::future::lazy;
+
+    struct Srv(Rc<Cell<bool>>);
+
+    impl Service<usize> for Srv {
+        type Response = usize;
+        type Error = ();
+        type Future<'f> = crate::future::Ready<usize, ()> where Self: 'f;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn poll_shutdown(&self, _: &mut Context<'_>) -> Poll<()> {
+            Poll::Pending
+        }
+
+        fn call<'a>(&'a self, req: usize, _: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+            crate::future::Ready::Ok(req + 10)
+        }
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_service() {
+        let ready = Rc::new(Cell::new(false));
+        let srv =
+            Pipeline::new(Either::<_, Srv>::Left(fn_service(|n: usize| async move {
+                Ok::<_, ()>(n + 1)
+            })));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx)).await.is_ready());
+        assert_eq!(srv.call(1).await, Ok(2));
+
+        let srv = Pipeline::new(Either::<Srv, _>::Right(Srv(ready.clone())));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        assert!(lazy(|cx| srv.poll_shutdown(cx)).await.is_pending());
+        ready.set(true);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(1).await, Ok(11));
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_factory() {
+        let ready = Rc::new(Cell::new(true));
+        let factory = |left: bool| {
+            let ready = ready.clone();
+            if left {
+                Either::Left(fn_factory(|| async {
+                    Ok::<_, ()>(Srv(Rc::new(Cell::new(true))))
+                }))
+            } else {
+                Either::Right(fn_factory(move || {
+                    let ready = ready.clone();
+                    async move { Ok::<_, ()>(Srv(ready)) }
+                }))
+            }
+        };
+
+        let srv = factory(true).pipeline(&()).await.unwrap();
+        assert!(srv.get_ref().is_left());
+        assert_eq!(srv.call(1).await, Ok(11));
+
+        let srv = factory(false).pipeline(&()).await.unwrap();
+        assert!(srv.get_ref().is_right());
+        assert_eq!(srv.call(2).await, Ok(12));
+    }
+}
@@ -8,7 +8,23 @@ use super::counter::{Counter, CounterGuard};
 /// InFlight - service factory for service that can limit number of in-flight
 /// async requests.
 ///
-/// Default number of in-flight requests is 15
+/// Default number of in-flight requests is 15. Readiness of wrapped service
+/// is pending while limit is reached, slot is released when response future
+/// completes or gets dropped.
+///
+/// ```rust
+/// use ntex::util::inflight::InFlight;
+/// use ntex::web::{self, App, HttpResponse};
+///
+/// fn main() {
+///     // upstream tolerates only 8 parallel calls
+///     let app = App::new().service(
+///         web::resource("/upstream")
+///             .wrap(InFlight::new(8))
+///             .to(|| async { HttpResponse::Ok() }),
+///     );
+/// }
+/// ```
 #[derive(Copy, Clone, Debug)]
 pub struct InFlight {
     max_inflight: usize,
@@ -110,8 +126,10 @@ impl<'f, T: Service<R>, R> Future for InFlightServiceResponse<'f, T, R> {
 
 #[cfg(test)]
 mod tests {
-    use ntex_service::{apply, fn_factory, Pipeline, Service, ServiceCtx, ServiceFactory};
-    use std::{cell::RefCell, task::Poll, time::Duration};
+    use ntex_service::{
+        apply, fn_factory, fn_service, Pipeline, ServiceCtx, ServiceFactory,
+    };
+    use std::{cell::Cell, cell::RefCell, rc::Rc, task::Poll, time::Duration};
 
     use super::*;
     use crate::{channel::oneshot, future::lazy, future::BoxFuture};
@@ -183,4 +201,67 @@ mod tests {
         crate::time::sleep(Duration::from_millis(25)).await;
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
     }
+
+    #[ntex_macros::rt_test2]
+    async fn test_drop_before_complete() {
+        let (tx, rx) = oneshot::channel();
+        let srv = Pipeline::new(InFlightService::new(1, SleepService(rx)));
+
+        let mut fut = Box::pin(srv.call(()));
+        assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        let done = Rc::new(Cell::new(false));
+        let (srv2, done2) = (srv.clone(), done.clone());
+        ntex::rt::spawn(async move {
+            let _ = srv2.call(()).await;
+            done2.set(true);
+        });
+        crate::time::sleep(Duration::from_millis(25)).await;
+        assert!(!done.get());
+
+        // dropped response future releases slot and wakes waiting task
+        drop(fut);
+        let _ = tx.send(());
+        crate::time::sleep(Duration::from_millis(25)).await;
+        assert!(done.get());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_contention() {
+        let active = Rc::new(Cell::new(0));
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let act = active.clone();
+        let srv = Pipeline::new(InFlightService::new(
+            2,
+            fn_service(move |idx: usize| {
+                let act = act.clone();
+                async move {
+                    act.set(act.get() + 1);
+                    assert!(act.get() <= 2);
+                    crate::time::sleep(Duration::from_millis(10)).await;
+                    act.set(act.get() - 1);
+                    Ok::<_, ()>(idx)
+                }
+            }),
+        ));
+
+        for idx in 0..6 {
+            let (srv, order) = (srv.clone(), order.clone());
+            ntex::rt::spawn(async move {
+                let idx = srv.call(idx).await.unwrap();
+                order.borrow_mut().push(idx);
+            });
+        }
+        crate::time::sleep(Duration::from_millis(200)).await;
+
+        // all waiting callers are woken up and complete
+        let mut order = order.borrow().clone();
+        assert_eq!(&order[..2], &[0, 1]);
+        order.sort_unstable();
+        assert_eq!(order, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(active.get(), 0);
+    }
 }
@@ -0,0 +1,468 @@
+//! Service that runs requests concurrently and releases responses in call order.
+use std::{cell::Cell, cell::RefCell, fmt, future::Future, pin::Pin};
+use std::{task::Context, task::Poll, task::Waker};
+
+use ntex_service::{IntoService, Middleware, Service, ServiceCall, ServiceCtx};
+
+use super::counter::{Counter, CounterGuard};
+use crate::{HashMap, HashSet};
+
+/// InOrder - service factory for service that runs requests concurrently
+/// and resolves responses strictly in call order.
+///
+/// Completed responses are buffered until all previous calls are resolved.
+/// Default number of concurrent calls, including buffered responses, is 16.
+/// Readiness of the service is pending while limit is reached.
+///
+/// If call fails, responses of following calls are released as usual.
+/// With `cancel_on_error()` calls that are made before failed call is
+/// resolved, resolve with `InOrderError::Canceled`.
+///
+/// ```rust
+/// use ntex::service::{fn_service, Middleware, Pipeline};
+/// use ntex::util::inorder::InOrder;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let srv = Pipeline::new(InOrder::new(8).create(fn_service(|n: u32| async move {
+///         ntex::time::sleep(ntex::time::Millis(10 - n)).await;
+///         Ok::<_, ()>(n)
+///     })));
+///
+///     let (r1, r2) = ntex::util::join(srv.call(1), srv.call(2)).await;
+///     assert_eq!((r1, r2), (Ok(1), Ok(2)));
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct InOrder {
+    max: usize,
+    cancel_on_error: bool,
+}
+
+impl InOrder {
+    /// Create `InOrder` with max number of concurrent calls.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            cancel_on_error: false,
+        }
+    }
+
+    /// Cancel pending calls if previous call fails.
+    pub fn cancel_on_error(mut self) -> Self {
+        self.cancel_on_error = true;
+        self
+    }
+}
+
+impl Default for InOrder {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl<S> Middleware<S> for InOrder {
+    type Service = InOrderService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        InOrderService {
+            service,
+            count: Counter::new(self.max),
+            state: State::new(self.cancel_on_error),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InOrderService<S> {
+    service: S,
+    count: Counter,
+    state: State,
+}
+
+impl<S> InOrderService<S> {
+    pub fn new<U, R>(max: usize, service: U) -> Self
+    where
+        S: Service<R>,
+        U: IntoService<S, R>,
+    {
+        Self {
+            count: Counter::new(max),
+            service: service.into_service(),
+            state: State::new(false),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    /// sequence number of next call
+    next: Cell<u64>,
+    /// sequence number of call that can be released
+    current: Cell<u64>,
+    /// calls before this sequence number are canceled
+    canceled: Cell<u64>,
+    cancel_on_error: bool,
+    dropped: RefCell<HashSet<u64>>,
+    waiters: RefCell<HashMap<u64, Waker>>,
+}
+
+impl State {
+    fn new(cancel_on_error: bool) -> Self {
+        Self {
+            cancel_on_error,
+            next: Cell::new(0),
+            current: Cell::new(0),
+            canceled: Cell::new(0),
+            dropped: RefCell::new(HashSet::default()),
+            waiters: RefCell::new(HashMap::default()),
+        }
+    }
+
+    fn next(&self) -> u64 {
+        let seq = self.next.get();
+        self.next.set(seq + 1);
+        seq
+    }
+
+    fn release(&self, seq: u64, failed: bool) {
+        if failed && self.cancel_on_error {
+            self.canceled.set(self.next.get());
+        }
+
+        // skip dropped calls
+        let mut current = seq + 1;
+        let mut dropped = self.dropped.borrow_mut();
+        while dropped.remove(&current) {
+            current += 1;
+        }
+        self.current.set(current);
+
+        if let Some(waker) = self.waiters.borrow_mut().remove(&current) {
+            waker.wake();
+        }
+    }
+}
+
+impl<T, R> Service<R> for InOrderService<T>
+where
+    T: Service<R>,
+{
+    type Response = T::Response;
+    type Error = InOrderError<T::Error>;
+    type Future<'f> = InOrderServiceResponse<'f, T, R> where Self: 'f, R: 'f;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.service.poll_ready(cx)?.is_pending() {
+            Poll::Pending
+        } else if !self.count.available(cx) {
+            log::trace!("InOrder limit exceeded");
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[inline]
+    fn call<'a>(&'a self, req: R, ctx: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+        InOrderServiceResponse {
+            fut: Some(ctx.call(&self.service, req)),
+            result: None,
+            slot: Slot {
+                seq: self.state.next(),
+                state: &self.state,
+                released: false,
+            },
+            _guard: self.count.get(),
+        }
+    }
+
+    ntex_service::forward_poll_shutdown!(service);
+}
+
+struct Slot<'f> {
+    seq: u64,
+    state: &'f State,
+    released: bool,
+}
+
+impl<'f> Slot<'f> {
+    fn release(&mut self, failed: bool) {
+        self.released = true;
+        self.state.release(self.seq, failed);
+    }
+}
+
+impl<'f> Drop for Slot<'f> {
+    fn drop(&mut self) {
+        if !self.released {
+            if self.state.current.get() == self.seq {
+                self.state.release(self.seq, false);
+            } else {
+                self.state.dropped.borrow_mut().insert(self.seq);
+                self.state.waiters.borrow_mut().remove(&self.seq);
+            }
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct InOrderServiceResponse<'f, T: Service<R>, R>
+    where T: 'f, R: 'f
+    {
+        #[pin]
+        fut: Option<ServiceCall<'f, T, R>>,
+        result: Option<Result<T::Response, T::Error>>,
+        slot: Slot<'f>,
+        _guard: CounterGuard,
+    }
+}
+
+impl<'f, T: Service<R>, R> Future for InOrderServiceResponse<'f, T, R> {
+    type Output = Result<T::Response, InOrderError<T::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        let state = this.slot.state;
+        let ready = state.current.get() == this.slot.seq;
+
+        if ready && this.slot.seq < state.canceled.get() {
+            this.fut.set(None);
+            this.slot.release(false);
+            return Poll::Ready(Err(InOrderError::Canceled));
+        }
+
+        if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+            if let Poll::Ready(result) = fut.poll(cx) {
+                this.fut.set(None);
+                *this.result = Some(result);
+            }
+        }
+
+        if ready {
+            if let Some(result) = this.result.take() {
+                this.slot.release(result.is_err());
+                return Poll::Ready(result.map_err(InOrderError::Service));
+            }
+        } else {
+            state
+                .waiters
+                .borrow_mut()
+                .insert(this.slot.seq, cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<'f, T: Service<R>, R> fmt::Debug for InOrderServiceResponse<'f, T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InOrderServiceResponse")
+            .field("seq", &self.slot.seq)
+            .field("completed", &self.result.is_some())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InOrderError<E> {
+    Service(E),
+    Canceled,
+}
+
+impl<E> From<E> for InOrderError<E> {
+    fn from(err: E) -> Self {
+        InOrderError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for InOrderError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InOrderError::Service(e) => fmt::Display::fmt(e, f),
+            InOrderError::Canceled => f.write_str("previous call failed, call canceled"),
+        }
+    }
+}
+
+impl<E: fmt::Display + fmt::Debug> std::error::Error for InOrderError<E> {}
+
+#[cfg(test)]
+mod tests {
+    use ntex_service::{fn_service, Pipeline};
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::future::{lazy, poll_fn, BoxFuture};
+    use crate::{channel::oneshot, time::sleep, time::Millis};
+
+    struct Srv;
+
+    impl Service<oneshot::Receiver<Result<usize, ()>>> for Srv {
+        type Response = usize;
+        type Error = ();
+        type Future<'f> = BoxFuture<'f, Result<usize, ()>>;
+
+        fn call<'a>(
+            &'a self,
+            rx: oneshot::Receiver<Result<usize, ()>>,
+            _: ServiceCtx<'a, Self>,
+        ) -> Self::Future<'a> {
+            Box::pin(async move { rx.await.unwrap() })
+        }
+    }
+
+    /// Simple xorshift generator for reproducible shuffles
+    fn shuffle(items: &mut [usize], mut seed: u64) {
+        for i in (1..items.len()).rev() {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            items.swap(i, (seed % (i as u64 + 1)) as usize);
+        }
+    }
+
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_order() {
+        for seed in 1..25u64 {
+            let srv = Pipeline::new(InOrder::new(32).create(Srv));
+            let results = Rc::new(RefCell::new(Vec::new()));
+
+            let mut senders = Vec::new();
+            for idx in 0..16 {
+                let (tx, rx) = oneshot::channel();
+                senders.push(Some(tx));
+                let srv = srv.clone();
+                let results = results.clone();
+                crate::spawn(async move {
+                    let res = srv.call(rx).await;
+                    results.borrow_mut().push((idx, res));
+                });
+            }
+            sleep(Millis(1)).await;
+
+            let mut order: Vec<_> = (0..16).collect();
+            shuffle(&mut order, seed);
+            for idx in order {
+                let _ = senders[idx].take().unwrap().send(Ok(idx));
+                if seed % 2 == 0 {
+                    yield_now().await;
+                }
+            }
+            sleep(Millis(1)).await;
+
+            let results = results.borrow();
+            assert_eq!(results.len(), 16);
+            for (pos, (idx, res)) in results.iter().enumerate() {
+                assert_eq!(pos, *idx);
+                assert_eq!(*res, Ok(pos));
+            }
+        }
+    }
+
+    /// Start service call and poll it once, sequence number is assigned by `call()`
+    async fn start<'a, F: Future + 'a>(
+        fut: F,
+    ) -> Pin<Box<dyn Future<Output = F::Output> + 'a>> {
+        let mut fut = Box::pin(fut);
+        assert!(lazy(|cx| fut.as_mut().poll(cx)).await.is_pending());
+        fut
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_backpressure() {
+        let srv = Pipeline::new(InOrderService::new(2, Srv));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx)).await.is_ready());
+
+        let (tx1, rx1) = oneshot::channel();
+        let (tx2, rx2) = oneshot::channel();
+        let fut1 = start(srv.call(rx1)).await;
+        let mut fut2 = start(srv.call(rx2)).await;
+
+        // second response is completed but buffered
+        let _ = tx2.send(Ok(2));
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+
+        let _ = tx1.send(Ok(1));
+        assert_eq!(fut1.await, Ok(1));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(fut2.await, Ok(2));
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_error() {
+        // responses after failed call are released
+        let srv = Pipeline::new(InOrder::default().create(Srv));
+        let (tx1, rx1) = oneshot::channel();
+        let (tx2, rx2) = oneshot::channel();
+        let fut1 = start(srv.call(rx1)).await;
+        let fut2 = start(srv.call(rx2)).await;
+        let _ = tx2.send(Ok(2));
+        let _ = tx1.send(Err(()));
+        assert_eq!(fut1.await, Err(InOrderError::Service(())));
+        assert_eq!(fut2.await, Ok(2));
+
+        // pending calls are canceled
+        let srv = Pipeline::new(InOrder::default().cancel_on_error().create(Srv));
+        let (tx1, rx1) = oneshot::channel();
+        let (_tx2, rx2) = oneshot::channel();
+        let (tx3, rx3) = oneshot::channel();
+        let fut1 = start(srv.call(rx1)).await;
+        let fut2 = start(srv.call(rx2)).await;
+        let _ = tx1.send(Err(()));
+        assert_eq!(fut1.await, Err(InOrderError::Service(())));
+
+        // calls made after failed call is resolved are not canceled
+        let fut3 = start(srv.call(rx3)).await;
+        assert_eq!(fut2.await, Err(InOrderError::Canceled));
+        let _ = tx3.send(Ok(3));
+        assert_eq!(fut3.await, Ok(3));
+        assert_eq!(
+            format!("{}", InOrderError::<&str>::Canceled),
+            "previous call failed, call canceled"
+        );
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_dropped() {
+        let srv = Pipeline::new(InOrder::default().create(fn_service(
+            |rx: oneshot::Receiver<usize>| async move { Ok::<_, ()>(rx.await.unwrap()) },
+        )));
+
+        let (_tx1, rx1) = oneshot::channel();
+        let (tx2, rx2) = oneshot::channel();
+        let (tx3, rx3) = oneshot::channel();
+        let (tx4, rx4) = oneshot::channel();
+        let fut1 = start(srv.call(rx1)).await;
+        let mut fut2 = start(srv.call(rx2)).await;
+        let fut3 = start(srv.call(rx3)).await;
+        let fut4 = start(srv.call(rx4)).await;
+        let _ = tx4.send(4);
+        let _ = tx3.send(3);
+        let _ = tx2.send(2);
+        assert!(lazy(|cx| fut2.as_mut().poll(cx)).await.is_pending());
+
+        // dropped calls do not block following calls
+        drop(fut3);
+        drop(fut1);
+        assert_eq!(fut2.await, Ok(2));
+        assert_eq!(fut4.await, Ok(4));
+    }
+}
@@ -1,7 +1,9 @@
 pub mod buffer;
 pub mod counter;
+pub mod either;
 mod extensions;
 pub mod inflight;
+pub mod inorder;
 pub mod keepalive;
 pub mod onerequest;
 pub mod timeout;
@@ -0,0 +1,67 @@
+//! Server-sent events chat
+//!
+//! Open `http://127.0.0.1:8080/events` in a few browser tabs, then post messages:
+//!
+//! ```sh
+//! curl -d 'hello' http://127.0.0.1:8080/send
+//! ```
+use std::cell::RefCell;
+
+use ntex::channel::mpsc;
+use ntex::time::Seconds;
+use ntex::web::{self, middleware, sse, App, HttpResponse, HttpServer, Responder};
+
+#[derive(Default)]
+struct Chat {
+    clients: RefCell<Vec<mpsc::Sender<sse::Event>>>,
+    last_id: RefCell<u64>,
+}
+
+async fn events(
+    chat: web::types::State<Chat>,
+    last_id: sse::LastEventId,
+) -> impl Responder {
+    let (tx, rx) = mpsc::channel();
+    let greeting = match last_id.as_str() {
+        Some(id) => format!("welcome back, last seen message {}", id),
+        None => "welcome".to_string(),
+    };
+    let _ = tx.send(sse::Event::new(greeting).event("system"));
+    chat.clients.borrow_mut().push(tx);
+
+    sse::SseStream::new(rx).heartbeat(Seconds(15))
+}
+
+async fn send(chat: web::types::State<Chat>, msg: String) -> HttpResponse {
+    let id = {
+        let mut last_id = chat.last_id.borrow_mut();
+        *last_id += 1;
+        *last_id
+    };
+
+    // disconnected clients drop their receivers
+    chat.clients.borrow_mut().retain(|tx| {
+        tx.send(sse::Event::new(msg.as_str()).id(id.to_string()))
+            .is_ok()
+    });
+    HttpResponse::Ok().finish()
+}
+
+#[ntex::main]
+async fn main() -> std::io::Result<()> {
+    std::env::set_var("RUST_LOG", "ntex=info");
+    env_logger::init();
+
+    HttpServer::new(|| {
+        App::new()
+            .state(Chat::default())
+            .wrap(middleware::Logger::default())
+            .route("/events", web::get().to(events))
+            .route("/send", web::post().to(send))
+    })
+    .bind("127.0.0.1:8080")?
+    // chat state is per worker
+    .workers(1)
+    .run()
+    .await
+}
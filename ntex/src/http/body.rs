@@ -2,6 +2,8 @@ use std::{
     error::Error, fmt, marker::PhantomData, mem, pin::Pin, task::Context, task::Poll,
 };
 
+use futures_io::AsyncRead;
+
 use crate::util::{Bytes, BytesMut, Stream};
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -81,6 +83,7 @@ impl<B> ResponseBody<B> {
         ResponseBody::Body(body)
     }
 
+    /// Extract body, `Body::None` is left in place
     pub fn take_body(&mut self) -> ResponseBody<B> {
         std::mem::replace(self, ResponseBody::Other(Body::None))
     }
@@ -94,6 +97,11 @@ impl<B: MessageBody> ResponseBody<B> {
             None
         }
     }
+
+    /// Size of the body
+    pub fn size(&self) -> BodySize {
+        MessageBody::size(self)
+    }
 }
 
 impl<B: MessageBody> MessageBody for ResponseBody<B> {
@@ -278,6 +286,15 @@ where
     }
 }
 
+impl<R> From<BodyReader<R>> for Body
+where
+    R: AsyncRead + Unpin + 'static,
+{
+    fn from(r: BodyReader<R>) -> Body {
+        Body::from_message(r)
+    }
+}
+
 impl MessageBody for Bytes {
     fn size(&self) -> BodySize {
         BodySize::Sized(self.len() as u64)
@@ -540,6 +557,91 @@ where
     }
 }
 
+/// Default chunk size of [`BodyReader`]
+const READER_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Type represent streaming body produced by `AsyncRead` reader.
+///
+/// Reader is polled only when response body is polled, i.e. when connection
+/// is ready to accept more data. Read errors terminate response body,
+/// connection gets closed because response headers are already sent.
+///
+/// Data is read into the same `BytesMut` buffer, chunks are split off the buffer
+/// without copying.
+pub struct BodyReader<R> {
+    reader: R,
+    buf: BytesMut,
+    chunk_size: usize,
+    eof: bool,
+}
+
+impl<R> BodyReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Create body reader with default chunk size (8Kb).
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, READER_CHUNK_SIZE)
+    }
+
+    /// Create body reader with specified chunk size.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunk size must be greater than zero");
+        BodyReader {
+            reader,
+            chunk_size,
+            buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+}
+
+impl<R> fmt::Debug for BodyReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyReader")
+            .field("reader", &std::any::type_name::<R>())
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+impl<R> MessageBody for BodyReader<R>
+where
+    R: AsyncRead + Unpin + 'static,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.eof {
+            return Poll::Ready(None);
+        }
+
+        // buffer gets reused if previous chunks are already released
+        if self.buf.len() < self.chunk_size {
+            self.buf.resize(self.chunk_size, 0);
+        }
+        match Pin::new(&mut self.reader).poll_read(cx, &mut self.buf[..self.chunk_size]) {
+            Poll::Ready(Ok(0)) => {
+                self.eof = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(n)) => Poll::Ready(Some(Ok(self.buf.split_to(n).freeze()))),
+            Poll::Ready(Err(e)) => {
+                self.eof = true;
+                Poll::Ready(Some(Err(e.into())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use futures_util::stream;
@@ -787,4 +889,45 @@ mod tests {
             Some(Bytes::from("2")),
         );
     }
+
+    #[crate::rt_test]
+    async fn body_reader() {
+        let mut body = BodyReader::with_chunk_size(&b"0123456789"[..], 4);
+        assert!(format!("{:?}", body).contains("BodyReader"));
+        assert_eq!(body.size(), BodySize::Stream);
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk, Bytes::from("0123"));
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("4567")),
+        );
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("89")),
+        );
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+        // chunks are not affected by buffer reuse
+        assert_eq!(chunk, Bytes::from("0123"));
+
+        struct Failing;
+
+        impl AsyncRead for Failing {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                _: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "failed")))
+            }
+        }
+
+        let mut body: Body = BodyReader::new(Failing).into();
+        let err = poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap();
+        assert_eq!(err.unwrap_err().to_string(), "failed");
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+    }
 }
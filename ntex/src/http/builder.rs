@@ -1,11 +1,11 @@
-use std::{error::Error, fmt, marker::PhantomData};
+use std::{error::Error, fmt, marker::PhantomData, time::SystemTime};
 
 use ntex_h2::{self as h2};
 
 use crate::http::body::MessageBody;
 use crate::http::config::{KeepAlive, OnRequest, ServiceConfig};
-use crate::http::error::ResponseError;
-use crate::http::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
+use crate::http::error::{H2SettingsError, ResponseError};
+use crate::http::h1::{self, Codec, ExpectHandler, H1Service, UpgradeHandler};
 use crate::http::h2::H2Service;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -27,6 +27,12 @@ pub struct HttpServiceBuilder<F, S, X = ExpectHandler, U = UpgradeHandler<F>> {
     upgrade: Option<U>,
     on_request: Option<OnRequest>,
     h2config: h2::Config,
+    headers_max_size: usize,
+    headers_max_count: usize,
+    payload_max_size: usize,
+    pipeline_max: usize,
+    date_header: bool,
+    date_source: Option<fn() -> SystemTime>,
     _t: PhantomData<(F, S)>,
 }
 
@@ -42,6 +48,12 @@ impl<F, S> HttpServiceBuilder<F, S, ExpectHandler, UpgradeHandler<F>> {
             upgrade: None,
             on_request: None,
             h2config: h2::Config::server(),
+            headers_max_size: h1::MAX_BUFFER_SIZE,
+            headers_max_count: h1::MAX_HEADERS,
+            payload_max_size: 0,
+            pipeline_max: 1,
+            date_header: true,
+            date_source: None,
             _t: PhantomData,
         }
     }
@@ -72,7 +84,8 @@ where
     ///
     /// Defines a timeout for reading client request header. If a client does not transmit
     /// the entire set headers within this time, the request is terminated with
-    /// the 408 (Request Time-out) error.
+    /// the 408 (Request Time-out) error. If nothing is received, connection get dropped.
+    /// For keep-alive connections timeout starts with first byte of next request.
     ///
     /// To disable timeout set value to 0.
     ///
@@ -86,7 +99,8 @@ where
     /// Set server connection disconnect timeout in seconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
-    /// within this time, the connection get dropped.
+    /// within this time, the connection get dropped. Also defines a timeout for reading
+    /// the rest of request payload after response is sent.
     ///
     /// To disable timeout set value to 0.
     ///
@@ -109,6 +123,117 @@ where
         self
     }
 
+    /// Set max size of request head in bytes.
+    ///
+    /// Requests with larger head get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max size is set to 32Kb.
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        self.headers_max_size = size;
+        self
+    }
+
+    /// Set max number of request headers.
+    ///
+    /// Requests with more headers get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.headers_max_count = count;
+        self
+    }
+
+    /// Set max size of request payload in bytes.
+    ///
+    /// Requests with larger declared content-length get rejected with
+    /// the 413 (Payload Too Large) error before payload is read. Streaming
+    /// payloads that exceed the limit are aborted and connection is closed
+    /// after response.
+    ///
+    /// By default payload size is not limited. To disable limit set value to 0.
+    pub fn max_payload_size(mut self, size: usize) -> Self {
+        self.payload_max_size = size;
+        self
+    }
+
+    /// Set max number of pipelined requests handled concurrently.
+    ///
+    /// Responses are always sent in the order of requests. Requests with
+    /// payload, upgrade requests and requests with `Expect` header are not
+    /// handled concurrently.
+    ///
+    /// By default requests are handled one by one.
+    pub fn max_pipelined_requests(mut self, max: usize) -> Self {
+        self.pipeline_max = max;
+        self
+    }
+
+    /// Enable or disable automatic `Date` response header.
+    ///
+    /// `Date` header set by the service is always sent as is.
+    ///
+    /// By default date header is enabled.
+    pub fn date_header(mut self, enabled: bool) -> Self {
+        self.date_header = enabled;
+        self
+    }
+
+    #[doc(hidden)]
+    /// Set source of the current time for `Date` header.
+    ///
+    /// Date is not cached if source is set, this is useful for tests only.
+    pub fn date_source(mut self, clock: fn() -> SystemTime) -> Self {
+        self.date_source = Some(clock);
+        self
+    }
+
+    /// Set http/2 initial window size (in octets) for stream-level flow control.
+    ///
+    /// By default initial window size is set to 65,535.
+    ///
+    /// Returns error if `size` is larger than 2^31-1.
+    pub fn initial_window_size(self, size: u32) -> Result<Self, H2SettingsError> {
+        let size = H2SettingsError::check_window_size(size)?;
+        self.h2config.initial_window_size(size);
+        Ok(self)
+    }
+
+    /// Set http/2 initial window size (in octets) for connection-level flow control.
+    ///
+    /// By default initial connection window size is set to 1Mb.
+    ///
+    /// Returns error if `size` is larger than 2^31-1.
+    pub fn initial_connection_window_size(
+        self,
+        size: u32,
+    ) -> Result<Self, H2SettingsError> {
+        let size = H2SettingsError::check_window_size(size)?;
+        self.h2config.initial_connection_window_size(size);
+        Ok(self)
+    }
+
+    /// Set max number of concurrent http/2 streams that client is allowed to open.
+    ///
+    /// Streams over the limit get reset by server.
+    /// By default max number of streams is set to 256.
+    pub fn max_concurrent_streams(self, max: u32) -> Self {
+        self.h2config.max_concurrent_streams(max);
+        self
+    }
+
+    /// Set the largest http/2 frame payload (in octets) that server accepts.
+    ///
+    /// By default max frame size is set to 16,384.
+    ///
+    /// Returns error if `size` is not within 16,384 and 16,777,215 range.
+    pub fn max_frame_size(self, size: u32) -> Result<Self, H2SettingsError> {
+        let size = H2SettingsError::check_max_frame_size(size)?;
+        self.h2config.max_frame_size(size);
+        Ok(self)
+    }
+
     #[doc(hidden)]
     /// Configure http2 connection settings
     pub fn configure_http2<O, R>(self, f: O) -> Self
@@ -139,6 +264,12 @@ where
             upgrade: self.upgrade,
             on_request: self.on_request,
             h2config: self.h2config,
+            headers_max_size: self.headers_max_size,
+            headers_max_count: self.headers_max_count,
+            payload_max_size: self.payload_max_size,
+            pipeline_max: self.pipeline_max,
+            date_header: self.date_header,
+            date_source: self.date_source,
             _t: PhantomData,
         }
     }
@@ -163,6 +294,12 @@ where
             upgrade: Some(upgrade.into_factory()),
             on_request: self.on_request,
             h2config: self.h2config,
+            headers_max_size: self.headers_max_size,
+            headers_max_count: self.headers_max_count,
+            payload_max_size: self.payload_max_size,
+            pipeline_max: self.pipeline_max,
+            date_header: self.date_header,
+            date_source: self.date_source,
             _t: PhantomData,
         }
     }
@@ -179,6 +316,27 @@ where
         self
     }
 
+    fn service_config(&self) -> ServiceConfig {
+        let cfg = ServiceConfig::new(
+            self.keep_alive,
+            self.client_timeout,
+            self.client_disconnect,
+            self.handshake_timeout,
+            self.h2config.clone(),
+        )
+        .max_header_size(self.headers_max_size)
+        .max_headers(self.headers_max_count)
+        .max_payload_size(self.payload_max_size)
+        .max_pipelined_requests(self.pipeline_max)
+        .date_header(self.date_header);
+
+        if let Some(clock) = self.date_source {
+            cfg.date_source(clock)
+        } else {
+            cfg
+        }
+    }
+
     /// Finish service configuration and create *http service* for HTTP/1 protocol.
     pub fn h1<B, SF>(self, service: SF) -> H1Service<F, S, B, X, U>
     where
@@ -188,13 +346,7 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-            self.h2config,
-        );
+        let cfg = self.service_config();
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -210,13 +362,7 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>> + 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-            self.h2config,
-        );
+        let cfg = self.service_config();
 
         H2Service::with_config(cfg, service.into_factory())
     }
@@ -230,13 +376,7 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>> + 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_timeout,
-            self.client_disconnect,
-            self.handshake_timeout,
-            self.h2config,
-        );
+        let cfg = self.service_config();
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -2,13 +2,15 @@ use std::{fmt, rc::Rc};
 
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
 
-use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{error::HttpError, h1};
 use crate::{service::Service, time::Millis};
 
 use super::connect::ConnectorWrapper;
 use super::error::ConnectError;
-use super::{Client, ClientConfig, Connect, Connection, Connector};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use super::TlsConfig;
+use super::{Client, ClientConfig, Connect, Connection, Connector, RetryPolicy};
 
 /// An HTTP Client builder
 ///
@@ -18,8 +20,7 @@ use super::{Client, ClientConfig, Connect, Connection, Connector};
 pub struct ClientBuilder {
     config: ClientConfig,
     default_headers: bool,
-    allow_redirects: bool,
-    max_redirects: usize,
+    custom_connector: bool,
 }
 
 impl Default for ClientBuilder {
@@ -32,11 +33,17 @@ impl ClientBuilder {
     pub fn new() -> Self {
         ClientBuilder {
             default_headers: true,
-            allow_redirects: true,
-            max_redirects: 10,
+            custom_connector: false,
             config: ClientConfig {
                 headers: HeaderMap::new(),
                 timeout: Millis(5_000),
+                body_timeout: Millis::ZERO,
+                headers_max_size: h1::MAX_BUFFER_SIZE,
+                headers_max_count: h1::MAX_HEADERS,
+                max_redirects: 0,
+                retry: None,
+                #[cfg(feature = "cookie")]
+                cookie_store: None,
                 connector: Box::new(ConnectorWrapper(Connector::default().finish().into())),
             },
         }
@@ -50,9 +57,26 @@ impl ClientBuilder {
             + 'static,
     {
         self.config.connector = Box::new(ConnectorWrapper(connector.into()));
+        self.custom_connector = true;
         self
     }
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Use tls settings for secure connections.
+    ///
+    /// Settings are used for default connector, they are not used if custom
+    /// connector is set with `connector()` method. Returns error if root
+    /// certificates or client certificate could not be used.
+    ///
+    /// See [`Connector::tls_config()`].
+    pub fn tls_config(mut self, config: TlsConfig) -> std::io::Result<Self> {
+        let connector = Connector::default().tls_config(config)?;
+        if !self.custom_connector {
+            self.config.connector = Box::new(ConnectorWrapper(connector.finish().into()));
+        }
+        Ok(self)
+    }
+
     /// Set request timeout.
     ///
     /// Request timeout is the total time before a response must be received.
@@ -62,25 +86,99 @@ impl ClientBuilder {
         self
     }
 
+    /// Set max size of response head in bytes.
+    ///
+    /// By default max size is set to 32Kb.
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        self.config.headers_max_size = size;
+        self
+    }
+
+    /// Set max number of response headers.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.config.headers_max_count = count;
+        self
+    }
+
     /// Disable request timeout.
     pub fn disable_timeout(mut self) -> Self {
         self.config.timeout = Millis::ZERO;
         self
     }
 
+    /// Set response body timeout.
+    ///
+    /// Body timeout is the max period between chunks of response payload,
+    /// if no data arrives within this period the payload stream yields
+    /// `PayloadError::Timeout`. It also bounds `body()` and `json()` collectors.
+    /// Body timeout is disabled by default.
+    pub fn body_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.config.body_timeout = timeout.into();
+        self
+    }
+
+    /// Disable response body timeout.
+    pub fn disable_body_timeout(mut self) -> Self {
+        self.config.body_timeout = Millis::ZERO;
+        self
+    }
+
     /// Do not follow redirects.
     ///
-    /// Redirects are allowed by default.
+    /// Redirects are not followed by default.
     pub fn disable_redirects(mut self) -> Self {
-        self.allow_redirects = false;
+        self.config.max_redirects = 0;
         self
     }
 
-    /// Set max number of redirects.
+    /// Follow up to `num` redirects.
+    ///
+    /// `301`, `302` and `303` redirects of `POST` requests are followed with
+    /// `GET` request, `307` and `308` redirects preserve method and body.
+    /// Redirect that requires to send streaming body again fails with
+    /// `SendRequestError::RedirectStream` error. `Authorization` and `Cookie`
+    /// headers are not sent to other origins.
     ///
-    /// Max redirects is set to 10 by default.
+    /// Redirects are not followed by default.
     pub fn max_redirects(mut self, num: usize) -> Self {
-        self.max_redirects = num;
+        self.config.max_redirects = num;
+        self
+    }
+
+    /// Retry failed requests according to the policy.
+    ///
+    /// Requests with streaming body cannot be sent again, such request fails
+    /// with `SendRequestError::RetryStream` error if it needs to be retried.
+    /// Retries can be disabled for single request with `ClientRequest::no_retry()`.
+    ///
+    /// Requests are not retried by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = Some(policy);
+        self
+    }
+
+    /// Do not retry failed requests.
+    pub fn disable_retry(mut self) -> Self {
+        self.config.retry = None;
+        self
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Enable cookie store.
+    ///
+    /// Cookies from `Set-Cookie` response headers get stored and sent
+    /// with subsequent requests of the client that match cookie's domain,
+    /// path and secure attributes. Expired cookies are removed from the store.
+    ///
+    /// Cookie store is disabled by default.
+    pub fn cookie_store(mut self, enabled: bool) -> Self {
+        self.config.cookie_store = if enabled {
+            Some(super::CookieStore::new())
+        } else {
+            None
+        };
         self
     }
 
@@ -113,6 +211,9 @@ impl ClientBuilder {
     }
 
     /// Set client wide HTTP basic authorization header
+    ///
+    /// Replaces previously set client wide authorization header. Every request
+    /// of the client carries it, unless request sets its own authorization.
     pub fn basic_auth<U>(self, username: U, password: Option<&str>) -> Self
     where
         U: fmt::Display,
@@ -121,18 +222,27 @@ impl ClientBuilder {
             Some(password) => format!("{}:{}", username, password),
             None => format!("{}:", username),
         };
-        self.header(
-            header::AUTHORIZATION,
-            format!("Basic {}", base64.encode(auth)),
-        )
+        self.authorization(format!("Basic {}", base64.encode(auth)))
     }
 
     /// Set client wide HTTP bearer authentication header
+    ///
+    /// Replaces previously set client wide authorization header.
     pub fn bearer_auth<T>(self, token: T) -> Self
     where
         T: fmt::Display,
     {
-        self.header(header::AUTHORIZATION, format!("Bearer {}", token))
+        self.authorization(format!("Bearer {}", token))
+    }
+
+    fn authorization(mut self, value: String) -> Self {
+        match HeaderValue::try_from(value) {
+            Ok(value) => {
+                self.config.headers.insert(header::AUTHORIZATION, value);
+            }
+            Err(e) => log::error!("Header value error: {:?}", e),
+        }
+        self
     }
 
     /// Finish build process and create `Client` instance.
@@ -149,12 +259,34 @@ mod tests {
     async fn basics() {
         let builder = ClientBuilder::new()
             .disable_timeout()
-            .disable_redirects()
             .max_redirects(10)
             .no_default_headers();
-        assert!(!builder.allow_redirects);
         assert!(!builder.default_headers);
-        assert_eq!(builder.max_redirects, 10);
+        assert_eq!(builder.config.max_redirects, 10);
+
+        let builder = builder.disable_redirects();
+        assert_eq!(builder.config.max_redirects, 0);
+
+        let builder = builder.retry(RetryPolicy::default());
+        assert!(builder.config.retry.is_some());
+        let builder = builder.disable_retry();
+        assert!(builder.config.retry.is_none());
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    #[crate::rt_test]
+    async fn tls_config() {
+        use crate::http::client::Certificate;
+
+        let config = TlsConfig::new().danger_accept_invalid_hostnames(true);
+        let _ = ClientBuilder::new().tls_config(config).unwrap().finish();
+
+        let config = TlsConfig::new().add_root_certificate(Certificate::from_der(b"cert"));
+        assert!(ClientBuilder::new().tls_config(config.clone()).is_err());
+
+        let builder = ClientBuilder::new().connector(Connector::default().finish());
+        assert!(builder.custom_connector);
+        assert!(builder.tls_config(config).is_err());
     }
 
     #[crate::rt_test]
@@ -197,5 +329,16 @@ mod tests {
                 .unwrap(),
             "Bearer someS3cr3tAutht0k3n"
         );
+
+        // replaces basic auth
+        let client = ClientBuilder::new()
+            .basic_auth("username", None)
+            .bearer_auth("token");
+        let values: Vec<_> = client
+            .config
+            .headers
+            .get_all(header::AUTHORIZATION)
+            .collect();
+        assert_eq!(values, vec!["Bearer token"]);
     }
 }
@@ -1,11 +1,11 @@
 use std::{fmt, net};
 
-use crate::http::{body::Body, RequestHeadType};
+use crate::http::{body::Body, h1, RequestHeadType};
 use crate::{service::Pipeline, service::Service, util::BoxFuture};
 
 use super::error::{ConnectError, SendRequestError};
 use super::response::ClientResponse;
-use super::{Connect as ClientConnect, Connection};
+use super::{ClientConfig, Connect as ClientConnect, Connection};
 
 // #[derive(Debug)]
 pub(super) struct ConnectorWrapper<T>(pub(crate) Pipeline<T>);
@@ -27,6 +27,7 @@ pub(super) trait Connect: fmt::Debug {
         head: RequestHeadType,
         body: Body,
         addr: Option<net::SocketAddr>,
+        config: &ClientConfig,
     ) -> BoxFuture<'_, Result<ClientResponse, SendRequestError>>;
 }
 
@@ -39,7 +40,11 @@ where
         head: RequestHeadType,
         body: Body,
         addr: Option<net::SocketAddr>,
+        config: &ClientConfig,
     ) -> BoxFuture<'_, Result<ClientResponse, SendRequestError>> {
+        let codec = h1::ClientCodec::default()
+            .headers_limits(config.headers_max_size, config.headers_max_count);
+
         Box::pin(async move {
             // connect to the host
             let fut = self.0.call(ClientConnect {
@@ -51,7 +56,7 @@ where
 
             // send request
             connection
-                .send_request(head, body)
+                .send_request(head, body, codec)
                 .await
                 .map(|(head, payload)| ClientResponse::new(head, payload))
         })
@@ -1,11 +1,11 @@
 use std::{fmt, time};
 
-use crate::http::body::MessageBody;
 use crate::http::message::{RequestHeadType, ResponseHead};
 use crate::http::payload::Payload;
+use crate::http::{body::MessageBody, h1};
 use crate::io::{types::HttpProtocol, IoBoxed};
 
-use super::{error::SendRequestError, h1proto, h2proto, pool::Acquired};
+use super::{error::SendRequestError, h1proto, h2proto, pool::Acquired, proxy::Proxy};
 
 pub(super) enum ConnectionType {
     H1(IoBoxed),
@@ -27,6 +27,7 @@ pub struct Connection {
     io: Option<ConnectionType>,
     created: time::Instant,
     pool: Option<Acquired>,
+    proxy: Option<Proxy>,
 }
 
 impl fmt::Debug for Connection {
@@ -49,9 +50,15 @@ impl Connection {
             pool,
             created,
             io: Some(io),
+            proxy: None,
         }
     }
 
+    /// Requests are sent to the http proxy
+    pub(super) fn set_proxy(&mut self, proxy: Proxy) {
+        self.proxy = Some(proxy);
+    }
+
     pub(super) fn release(self, close: bool) {
         if let Some(mut pool) = self.pool {
             pool.release(
@@ -59,6 +66,7 @@ impl Connection {
                     io: self.io,
                     created: self.created,
                     pool: None,
+                    proxy: None,
                 },
                 close,
             );
@@ -81,10 +89,16 @@ impl Connection {
         mut self,
         head: H,
         body: B,
+        codec: h1::ClientCodec,
     ) -> Result<(ResponseHead, Payload), SendRequestError> {
         match self.io.take().unwrap() {
             ConnectionType::H1(io) => {
-                h1proto::send_request(io, head.into(), body, self.created, self.pool).await
+                let head = if let Some(ref proxy) = self.proxy {
+                    proxy.request_head(head.into())
+                } else {
+                    head.into()
+                };
+                h1proto::send_request(io, head, body, codec, self.created, self.pool).await
             }
             ConnectionType::H2(io) => h2proto::send_request(io, head.into(), body).await,
         }
@@ -0,0 +1,336 @@
+//! Client cookie store
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use coo_kie::{time::OffsetDateTime, Cookie};
+
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::http::Uri;
+
+/// Cookie store
+///
+/// Stores cookies received with `Set-Cookie` response headers and
+/// provides matching cookies for subsequent requests, as described in
+/// [RFC 6265](https://tools.ietf.org/html/rfc6265#section-5.3). Store is
+/// cheap to clone, all clones share same cookies.
+#[derive(Clone, Default)]
+pub struct CookieStore(Rc<RefCell<Vec<StoredCookie>>>);
+
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    path: String,
+    host_only: bool,
+    secure: bool,
+    expires: Option<OffsetDateTime>,
+}
+
+impl CookieStore {
+    /// Create empty cookie store
+    pub fn new() -> Self {
+        CookieStore::default()
+    }
+
+    /// Cookies that would be sent with request to the `uri`
+    ///
+    /// Cookies with longer paths are listed first.
+    pub fn cookies_for(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        let now = OffsetDateTime::now_utc();
+        let mut cookies = self.0.borrow_mut();
+        cookies.retain(|c| !c.is_expired(now));
+
+        let mut matched: Vec<_> = cookies.iter().filter(|c| c.matches(uri)).collect();
+        matched.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        matched.into_iter().map(|c| c.cookie.clone()).collect()
+    }
+
+    /// Number of stored cookies
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Check if store is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Remove all cookies
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear()
+    }
+
+    /// Store cookies from `Set-Cookie` headers of the response to the `uri`
+    pub(crate) fn store(&self, uri: &Uri, headers: &HeaderMap) {
+        let host = if let Some(host) = uri.host() {
+            host.to_ascii_lowercase()
+        } else {
+            return;
+        };
+        let now = OffsetDateTime::now_utc();
+
+        for hdr in headers.get_all(header::SET_COOKIE) {
+            let cookie = match hdr.to_str().map(Cookie::parse) {
+                Ok(Ok(cookie)) => cookie.into_owned(),
+                _ => {
+                    log::trace!("Cannot parse set-cookie header {:?}", hdr);
+                    continue;
+                }
+            };
+
+            let (domain, host_only) = match cookie.domain() {
+                Some(domain) if !domain.is_empty() => {
+                    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+                    if !domain.contains('.') {
+                        // single-label domain (i.e. top level domain) is treated
+                        // as public suffix, RFC 6265 section 5.3 step 5
+                        if domain == host {
+                            (host.clone(), true)
+                        } else {
+                            log::trace!("Cookie domain {:?} is public suffix", domain);
+                            continue;
+                        }
+                    } else if !domain_match(&host, &domain) {
+                        log::trace!("Cookie domain {:?} does not match {:?}", domain, host);
+                        continue;
+                    } else {
+                        (domain, false)
+                    }
+                }
+                _ => (host.clone(), true),
+            };
+            let path = match cookie.path() {
+                Some(path) if path.starts_with('/') => path.to_string(),
+                _ => default_path(uri),
+            };
+            let expires = if let Some(max_age) = cookie.max_age() {
+                Some(now.saturating_add(max_age))
+            } else {
+                cookie.expires_datetime()
+            };
+
+            let stored = StoredCookie {
+                host_only,
+                expires,
+                secure: cookie.secure().unwrap_or(false),
+                cookie,
+                domain,
+                path,
+            };
+
+            let mut cookies = self.0.borrow_mut();
+            cookies.retain(|c| {
+                !(c.cookie.name() == stored.cookie.name()
+                    && c.domain == stored.domain
+                    && c.path == stored.path)
+            });
+            if !stored.is_expired(now) {
+                cookies.push(stored);
+            }
+        }
+    }
+
+    /// `Cookie` header value for request to the `uri`
+    ///
+    /// Cookies from the store are appended to the `existing` header value.
+    pub(crate) fn header(
+        &self,
+        uri: &Uri,
+        existing: Option<&HeaderValue>,
+    ) -> Option<HeaderValue> {
+        let cookies = self.cookies_for(uri);
+        if cookies.is_empty() {
+            return None;
+        }
+
+        let mut value = existing
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_string())
+            .unwrap_or_default();
+        for cookie in cookies {
+            if !value.is_empty() {
+                value.push_str("; ");
+            }
+            value.push_str(cookie.name());
+            value.push('=');
+            value.push_str(cookie.value());
+        }
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires.map(|exp| exp <= now).unwrap_or(false)
+    }
+
+    fn matches(&self, uri: &Uri) -> bool {
+        let host = if let Some(host) = uri.host() {
+            host.to_ascii_lowercase()
+        } else {
+            return false;
+        };
+
+        if self.host_only {
+            if host != self.domain {
+                return false;
+            }
+        } else if !domain_match(&host, &self.domain) {
+            return false;
+        }
+        if self.secure && !matches!(uri.scheme_str(), Some("https") | Some("wss")) {
+            return false;
+        }
+        path_match(uri.path(), &self.path)
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieStore")
+            .field("cookies", &self.len())
+            .finish()
+    }
+}
+
+/// Domain matching, RFC 6265 section 5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        true
+    } else {
+        host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+            && host.parse::<std::net::IpAddr>().is_err()
+    }
+}
+
+/// Path matching, RFC 6265 section 5.1.4
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/')
+                || path.as_bytes().get(cookie_path.len()) == Some(&b'/')))
+}
+
+/// Default cookie path, RFC 6265 section 5.1.4
+fn default_path(uri: &Uri) -> String {
+    let path = uri.path();
+    match path.rfind('/') {
+        Some(idx) if idx > 0 && path.starts_with('/') => path[..idx].to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(uri: &'static str, cookies: &[&'static str]) -> CookieStore {
+        let store = CookieStore::new();
+        let mut headers = HeaderMap::new();
+        for cookie in cookies {
+            headers.append(header::SET_COOKIE, HeaderValue::from_static(cookie));
+        }
+        store.store(&Uri::from_static(uri), &headers);
+        store
+    }
+
+    fn names(store: &CookieStore, uri: &'static str) -> Vec<String> {
+        store
+            .cookies_for(&Uri::from_static(uri))
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_domain() {
+        let jar = store(
+            "http://www.example.com/",
+            &[
+                "host=1",
+                "domain=2; Domain=.example.com",
+                "other=3; Domain=rust-lang.org",
+            ],
+        );
+        assert_eq!(jar.len(), 2);
+        assert_eq!(
+            names(&jar, "http://www.example.com/"),
+            vec!["host", "domain"]
+        );
+        assert_eq!(names(&jar, "http://example.com/"), vec!["domain"]);
+        assert_eq!(names(&jar, "http://sub.www.example.com/"), vec!["domain"]);
+        assert!(names(&jar, "http://notexample.com/").is_empty());
+        assert!(names(&jar, "http://rust-lang.org/").is_empty());
+
+        jar.clear();
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn test_public_suffix_domain() {
+        let jar = store(
+            "http://www.example.com/",
+            &["tld=1; Domain=com", "dot=2; Domain=.com"],
+        );
+        assert!(jar.is_empty());
+        assert!(names(&jar, "http://rust-lang.com/").is_empty());
+
+        // single-label host, cookie is host-only
+        let jar = store("http://localhost/", &["local=1; Domain=localhost"]);
+        assert_eq!(names(&jar, "http://localhost/"), vec!["local"]);
+        assert!(names(&jar, "http://sub.localhost/").is_empty());
+    }
+
+    #[test]
+    fn test_path() {
+        let jar = store(
+            "http://localhost/a/b",
+            &["default=1", "root=2; Path=/", "deep=3; Path=/a/b/c"],
+        );
+        assert_eq!(names(&jar, "http://localhost/"), vec!["root"]);
+        assert_eq!(names(&jar, "http://localhost/ab"), vec!["root"]);
+        assert_eq!(names(&jar, "http://localhost/a"), vec!["default", "root"]);
+        assert_eq!(
+            names(&jar, "http://localhost/a/b/c/d"),
+            vec!["deep", "default", "root"]
+        );
+    }
+
+    #[test]
+    fn test_secure_and_expire() {
+        let jar = store(
+            "https://localhost/",
+            &[
+                "secure=1; Secure",
+                "expired=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+                "session=3",
+            ],
+        );
+        assert_eq!(names(&jar, "https://localhost/"), vec!["secure", "session"]);
+        assert_eq!(names(&jar, "wss://localhost/"), vec!["secure", "session"]);
+        assert_eq!(names(&jar, "http://localhost/"), vec!["session"]);
+
+        // replace and delete
+        let mut headers = HeaderMap::new();
+        headers.append(header::SET_COOKIE, HeaderValue::from_static("session=4"));
+        headers.append(
+            header::SET_COOKIE,
+            HeaderValue::from_static("secure=; Max-Age=0"),
+        );
+        jar.store(&Uri::from_static("https://localhost/"), &headers);
+        let cookies = jar.cookies_for(&Uri::from_static("https://localhost/"));
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].value(), "4");
+
+        let value = HeaderValue::from_static("a=b");
+        assert_eq!(
+            jar.header(&Uri::from_static("http://localhost/"), Some(&value))
+                .unwrap(),
+            "a=b; session=4"
+        );
+        assert!(jar
+            .header(&Uri::from_static("http://example.com/"), None)
+            .is_none());
+    }
+}
@@ -0,0 +1,310 @@
+//! Resumable downloads
+use std::{fs, io, io::Seek, io::Write};
+
+use crate::http::error::PayloadError;
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::http::StatusCode;
+use crate::time::{sleep, Millis};
+use crate::util::{stream_recv, BytesMut};
+
+use super::error::{DownloadError, SendRequestError};
+use super::FrozenClientRequest;
+
+/// Number of attempts if client has no retry policy
+const DEFAULT_ATTEMPTS: usize = 3;
+
+/// Destination for downloaded data
+///
+/// Implemented for `Vec<u8>`, `BytesMut` and `std::fs::File`.
+pub trait DownloadWriter {
+    /// Write chunk of downloaded data
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Discard all written data
+    ///
+    /// It is called if server sends whole resource instead of requested range.
+    fn reset(&mut self) -> io::Result<()>;
+}
+
+impl<T: DownloadWriter + ?Sized> DownloadWriter for &mut T {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        (**self).write(data)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        (**self).reset()
+    }
+}
+
+impl DownloadWriter for Vec<u8> {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.clear();
+        Ok(())
+    }
+}
+
+impl DownloadWriter for BytesMut {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.clear();
+        Ok(())
+    }
+}
+
+impl DownloadWriter for fs::File {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_all(data)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.set_len(0)?;
+        self.rewind()
+    }
+}
+
+/// Validators of downloaded resource
+struct Validators {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    length: Option<u64>,
+    resumable: bool,
+}
+
+impl Validators {
+    fn new(headers: &HeaderMap) -> Self {
+        // weak etags could not be used for byte ranges
+        let etag = headers
+            .get(header::ETAG)
+            .filter(|v| !v.as_bytes().starts_with(b"W/"))
+            .cloned();
+        let last_modified = headers.get(header::LAST_MODIFIED).cloned();
+        let length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let accept_ranges = headers
+            .get(header::ACCEPT_RANGES)
+            .map(|v| v.as_bytes().eq_ignore_ascii_case(b"bytes"))
+            .unwrap_or(false);
+
+        Validators {
+            resumable: accept_ranges && (etag.is_some() || last_modified.is_some()),
+            etag,
+            last_modified,
+            length,
+        }
+    }
+
+    /// Check if partial response belongs to the same resource
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        if self.etag.is_some() {
+            self.etag.as_ref() == headers.get(header::ETAG)
+        } else {
+            self.last_modified.as_ref() == headers.get(header::LAST_MODIFIED)
+        }
+    }
+}
+
+/// Parse `Content-Range: bytes <start>-<end>/<length>` header
+fn content_range(headers: &HeaderMap) -> Option<(u64, u64, Option<u64>)> {
+    let value = headers.get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let (unit, range) = value.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    let (range, length) = range.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+    let length = if length == "*" {
+        None
+    } else {
+        Some(length.parse().ok()?)
+    };
+    if start > end || length.map(|len| end >= len).unwrap_or(false) {
+        None
+    } else {
+        Some((start, end, length))
+    }
+}
+
+fn is_retryable(err: &SendRequestError) -> bool {
+    matches!(
+        err,
+        SendRequestError::Connect(_)
+            | SendRequestError::Send(_)
+            | SendRequestError::Timeout
+            | SendRequestError::H2(_)
+    )
+}
+
+fn is_retryable_payload(err: &PayloadError) -> bool {
+    matches!(
+        err,
+        PayloadError::Incomplete(_)
+            | PayloadError::Timeout
+            | PayloadError::Http2Payload(_)
+            | PayloadError::Io(_)
+    )
+}
+
+/// Download resource, resume interrupted transfer from current offset
+pub(super) async fn download<W: DownloadWriter>(
+    req: FrozenClientRequest,
+    writer: &mut W,
+) -> Result<u64, DownloadError> {
+    let policy = req.config.retry.clone();
+    let max_attempts = policy
+        .as_ref()
+        .map(|p| p.max_attempts())
+        .unwrap_or(DEFAULT_ATTEMPTS);
+
+    let mut offset = 0;
+    let mut validators: Option<Validators> = None;
+    let mut attempt = 1;
+    loop {
+        let result = if offset == 0 {
+            req.send().await
+        } else {
+            req.extra_header(header::RANGE, format!("bytes={}-", offset))
+                .send()
+                .await
+        };
+
+        match result {
+            Ok(mut res) => {
+                match res.status() {
+                    StatusCode::OK => {
+                        // server sends whole resource
+                        if offset != 0 {
+                            log::trace!("Server ignored range request, restart download");
+                            writer.reset().map_err(DownloadError::Write)?;
+                            offset = 0;
+                        }
+                        validators = Some(Validators::new(res.headers()));
+                    }
+                    StatusCode::PARTIAL_CONTENT if offset != 0 => {
+                        let v = validators.as_ref().unwrap();
+                        if !v.matches(res.headers()) {
+                            return Err(DownloadError::Modified);
+                        }
+                        match content_range(res.headers()) {
+                            Some((start, _, length))
+                                if start == offset
+                                    && (v.length.is_none() || length == v.length) => {}
+                            _ => return Err(DownloadError::InvalidRange),
+                        }
+                    }
+                    status => return Err(DownloadError::Status(status)),
+                }
+
+                loop {
+                    match stream_recv(&mut res).await {
+                        Some(Ok(chunk)) => {
+                            writer.write(&chunk).map_err(DownloadError::Write)?;
+                            offset += chunk.len() as u64;
+                        }
+                        Some(Err(err)) => {
+                            let resumable = validators.as_ref().unwrap().resumable;
+                            if resumable
+                                && attempt < max_attempts
+                                && is_retryable_payload(&err)
+                            {
+                                log::trace!("Download interrupted at {}: {}", offset, err);
+                                break;
+                            }
+                            return Err(err.into());
+                        }
+                        None => return Ok(offset),
+                    }
+                }
+            }
+            // failed resume request is retried as well
+            Err(err) if offset != 0 && attempt < max_attempts && is_retryable(&err) => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let delay = policy
+            .as_ref()
+            .map(|p| p.delay(attempt))
+            .unwrap_or(Millis::ZERO);
+        log::trace!("Resume download in {:?}, attempt {}", delay, attempt + 1);
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_range() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(content_range(&headers), None);
+
+        let mut check = |val: &'static str| {
+            headers.insert(header::CONTENT_RANGE, HeaderValue::from_static(val));
+            content_range(&headers)
+        };
+        assert_eq!(check("bytes 0-9/10"), Some((0, 9, Some(10))));
+        assert_eq!(check("bytes 5-9/*"), Some((5, 9, None)));
+        assert_eq!(check("bytes 5-10/10"), None);
+        assert_eq!(check("bytes 9-5/10"), None);
+        assert_eq!(check("items 0-9/10"), None);
+        assert_eq!(check("bytes */10"), None);
+    }
+
+    #[test]
+    fn test_validators() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        assert!(!Validators::new(&headers).resumable);
+
+        headers.insert(header::ETAG, HeaderValue::from_static("W/\"1\""));
+        assert!(!Validators::new(&headers).resumable);
+
+        headers.insert(header::ETAG, HeaderValue::from_static("\"1\""));
+        let v = Validators::new(&headers);
+        assert!(v.resumable);
+        assert!(v.matches(&headers));
+        headers.insert(header::ETAG, HeaderValue::from_static("\"2\""));
+        assert!(!v.matches(&headers));
+
+        headers.remove(header::ETAG);
+        headers.insert(
+            header::LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let v = Validators::new(&headers);
+        assert!(v.resumable);
+        assert!(v.matches(&headers));
+
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("none"));
+        assert!(!Validators::new(&headers).resumable);
+    }
+
+    #[test]
+    fn test_writers() {
+        let mut buf = Vec::new();
+        DownloadWriter::write(&mut buf, b"data").unwrap();
+        DownloadWriter::reset(&mut buf).unwrap();
+        DownloadWriter::write(&mut buf, b"new").unwrap();
+        assert_eq!(buf, b"new");
+
+        let mut buf = BytesMut::new();
+        let mut w = &mut buf;
+        DownloadWriter::write(&mut w, b"data").unwrap();
+        DownloadWriter::reset(&mut w).unwrap();
+        DownloadWriter::write(&mut w, b"new").unwrap();
+        assert_eq!(&buf[..], b"new");
+    }
+}
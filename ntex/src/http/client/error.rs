@@ -1,5 +1,5 @@
 //! Http client errors
-use std::{error::Error, io};
+use std::{error::Error, io, net::IpAddr};
 
 use serde_json::error::Error as JsonError;
 use thiserror::Error;
@@ -8,6 +8,7 @@ use thiserror::Error;
 use crate::connect::openssl::{HandshakeError, SslError};
 
 use crate::http::error::{HttpError, ParseError, PayloadError};
+use crate::http::StatusCode;
 use crate::util::Either;
 
 /// A set of errors that can occur during parsing json payloads
@@ -37,7 +38,7 @@ pub enum ConnectError {
     SslError(std::rc::Rc<SslError>),
 
     /// SSL Handshake error
-    #[cfg(feature = "openssl")]
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
     #[error("{0}")]
     SslHandshakeError(String),
 
@@ -60,6 +61,34 @@ pub enum ConnectError {
     /// Unresolved host name
     #[error("Connector received `Connect` method with unresolved host")]
     Unresolved,
+
+    /// Failed to bind socket to the local address
+    #[error("Cannot bind socket to local address {0}: {1}")]
+    Bind(IpAddr, io::Error),
+
+    /// Proxy refused to establish tunnel
+    #[error("Proxy refused to establish tunnel: {0}")]
+    ProxyTunnel(StatusCode),
+
+    /// Socks proxy does not accept offered authentication methods
+    #[cfg(feature = "socks")]
+    #[error("Socks proxy does not accept offered authentication methods")]
+    SocksMethod,
+
+    /// Socks proxy rejected username and password
+    #[cfg(feature = "socks")]
+    #[error("Socks proxy authentication failed")]
+    SocksAuth,
+
+    /// Target host refused connection from socks proxy
+    #[cfg(feature = "socks")]
+    #[error("Target host refused connection from socks proxy")]
+    SocksConnectionRefused,
+
+    /// Socks proxy failed to connect to target host
+    #[cfg(feature = "socks")]
+    #[error("Socks proxy failed to connect to target host, reply code: {0}")]
+    SocksReply(u8),
 }
 
 impl Clone for ConnectError {
@@ -68,7 +97,7 @@ impl Clone for ConnectError {
             ConnectError::SslIsNotSupported => ConnectError::SslIsNotSupported,
             #[cfg(feature = "openssl")]
             ConnectError::SslError(e) => ConnectError::SslError(e.clone()),
-            #[cfg(feature = "openssl")]
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
             ConnectError::SslHandshakeError(e) => {
                 ConnectError::SslHandshakeError(e.clone())
             }
@@ -88,6 +117,18 @@ impl Clone for ConnectError {
                 }
             }
             ConnectError::Unresolved => ConnectError::Unresolved,
+            ConnectError::Bind(addr, e) => {
+                ConnectError::Bind(*addr, io::Error::new(e.kind(), format!("{}", e)))
+            }
+            ConnectError::ProxyTunnel(status) => ConnectError::ProxyTunnel(*status),
+            #[cfg(feature = "socks")]
+            ConnectError::SocksMethod => ConnectError::SocksMethod,
+            #[cfg(feature = "socks")]
+            ConnectError::SocksAuth => ConnectError::SocksAuth,
+            #[cfg(feature = "socks")]
+            ConnectError::SocksConnectionRefused => ConnectError::SocksConnectionRefused,
+            #[cfg(feature = "socks")]
+            ConnectError::SocksReply(code) => ConnectError::SocksReply(*code),
         }
     }
 }
@@ -106,6 +147,7 @@ impl From<crate::connect::ConnectError> for ConnectError {
             crate::connect::ConnectError::NoRecords => ConnectError::NoRecords,
             crate::connect::ConnectError::InvalidInput => panic!(),
             crate::connect::ConnectError::Unresolved => ConnectError::Unresolved,
+            crate::connect::ConnectError::Bind(addr, e) => ConnectError::Bind(addr, e),
             crate::connect::ConnectError::Io(e) => ConnectError::Disconnected(Some(e)),
         }
     }
@@ -157,6 +199,15 @@ pub enum SendRequestError {
     /// Tunnels are not supported for http2 connection
     #[error("Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
+    /// Redirect requires to send streaming request body again
+    #[error("Cannot follow redirect, streaming request body cannot be sent again")]
+    RedirectStream,
+    /// Retry requires to send streaming request body again
+    #[error("Cannot retry request, streaming request body cannot be sent again")]
+    RetryStream,
+    /// Request body stream returned error
+    #[error("Request body stream error: {0}")]
+    Stream(Box<dyn Error>),
     /// Error sending request body
     #[error("Error sending request body {0}")]
     Error(#[from] Box<dyn Error>),
@@ -180,6 +231,29 @@ impl From<Either<ParseError, io::Error>> for SendRequestError {
     }
 }
 
+/// A set of errors that can occur during resumable download
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    /// Error sending request
+    #[error("{0}")]
+    Send(#[from] SendRequestError),
+    /// Error reading response payload
+    #[error("Error reading response payload: {0}")]
+    Payload(#[from] PayloadError),
+    /// Server responded with unexpected status
+    #[error("Unexpected response status: {0}")]
+    Status(StatusCode),
+    /// Partial response does not match requested range
+    #[error("Invalid content range of partial response")]
+    InvalidRange,
+    /// Resource has been modified between download attempts
+    #[error("Resource has been modified during download")]
+    Modified,
+    /// Writer returned error
+    #[error("Cannot write downloaded data: {0}")]
+    Write(io::Error),
+}
+
 /// A set of errors that can occur during freezing a request
 #[derive(Error, Debug)]
 pub enum FreezeRequestError {
@@ -17,6 +17,7 @@ pub struct FrozenClientRequest {
     pub(super) addr: Option<net::SocketAddr>,
     pub(super) response_decompress: bool,
     pub(super) timeout: Millis,
+    pub(super) body_timeout: Option<Millis>,
     pub(super) config: Rc<ClientConfig>,
 }
 
@@ -45,6 +46,7 @@ impl FrozenClientRequest {
             self.addr,
             self.response_decompress,
             self.timeout,
+            self.body_timeout,
             self.config.clone(),
             body,
         )
@@ -56,6 +58,7 @@ impl FrozenClientRequest {
             self.addr,
             self.response_decompress,
             self.timeout,
+            self.body_timeout,
             self.config.clone(),
             value,
         )
@@ -67,6 +70,7 @@ impl FrozenClientRequest {
             self.addr,
             self.response_decompress,
             self.timeout,
+            self.body_timeout,
             self.config.clone(),
             value,
         )
@@ -82,6 +86,7 @@ impl FrozenClientRequest {
             self.addr,
             self.response_decompress,
             self.timeout,
+            self.body_timeout,
             self.config.clone(),
             stream,
         )
@@ -93,6 +98,7 @@ impl FrozenClientRequest {
             self.addr,
             self.response_decompress,
             self.timeout,
+            self.body_timeout,
             self.config.clone(),
         )
     }
@@ -182,6 +188,7 @@ impl FrozenSendBuilder {
             self.req.addr,
             self.req.response_decompress,
             self.req.timeout,
+            self.req.body_timeout,
             self.req.config,
             body,
         )
@@ -197,6 +204,7 @@ impl FrozenSendBuilder {
             self.req.addr,
             self.req.response_decompress,
             self.req.timeout,
+            self.req.body_timeout,
             self.req.config,
             value,
         )
@@ -212,6 +220,7 @@ impl FrozenSendBuilder {
             self.req.addr,
             self.req.response_decompress,
             self.req.timeout,
+            self.req.body_timeout,
             self.req.config,
             value,
         )
@@ -231,6 +240,7 @@ impl FrozenSendBuilder {
             self.req.addr,
             self.req.response_decompress,
             self.req.timeout,
+            self.req.body_timeout,
             self.req.config,
             stream,
         )
@@ -246,6 +256,7 @@ impl FrozenSendBuilder {
             self.req.addr,
             self.req.response_decompress,
             self.req.timeout,
+            self.req.body_timeout,
             self.req.config,
         )
     }
@@ -12,11 +12,13 @@ use crate::util::{poll_fn, ready, BufMut, Bytes, BytesMut, Stream};
 use super::connection::{Connection, ConnectionType};
 use super::error::{ConnectError, SendRequestError};
 use super::pool::Acquired;
+use super::response::Trailers;
 
 pub(super) async fn send_request<B>(
     io: IoBoxed,
     mut head: RequestHeadType,
     body: B,
+    codec: h1::ClientCodec,
     created: Instant,
     pool: Option<Acquired>,
 ) -> Result<(ResponseHead, Payload), SendRequestError>
@@ -57,7 +59,6 @@ where
     );
 
     // send request
-    let codec = h1::ClientCodec::default();
     io.send((head, body.size()).into(), &codec).await?;
 
     log::trace!("http1 request has been sent");
@@ -90,7 +91,10 @@ where
             Ok((head, Payload::None))
         }
         _ => {
-            let pl: PayloadStream = Box::pin(PlStream::new(io, codec, created, pool));
+            let trailers = Trailers::default();
+            head.extensions_mut().insert(trailers.clone());
+            let pl: PayloadStream =
+                Box::pin(PlStream::new(io, codec, trailers, created, pool));
             Ok((head, pl.into()))
         }
     }
@@ -108,7 +112,8 @@ where
     loop {
         match poll_fn(|cx| body.poll_next_chunk(cx)).await {
             Some(result) => {
-                io.encode(h1::Message::Chunk(Some(result?)), codec)?;
+                let chunk = result.map_err(SendRequestError::Stream)?;
+                io.encode(h1::Message::Chunk(Some(chunk)), codec)?;
                 io.flush(false).await?;
             }
             None => {
@@ -125,6 +130,7 @@ where
 pub(super) struct PlStream {
     io: Option<IoBoxed>,
     codec: h1::ClientPayloadCodec,
+    trailers: Trailers,
     created: Instant,
     pool: Option<Acquired>,
 }
@@ -133,12 +139,14 @@ impl PlStream {
     fn new(
         io: IoBoxed,
         codec: h1::ClientCodec,
+        trailers: Trailers,
         created: Instant,
         pool: Option<Acquired>,
     ) -> Self {
         PlStream {
             io: Some(io),
             codec: codec.into_payload_codec(),
+            trailers,
             created,
             pool,
         }
@@ -160,6 +168,9 @@ impl Stream for PlStream {
                         if let Some(chunk) = chunk {
                             Ok(chunk)
                         } else {
+                            if let Some(trailers) = this.codec.take_trailers() {
+                                this.trailers.set(trailers);
+                            }
                             release_connection(
                                 this.io.take().unwrap(),
                                 !this.codec.keepalive(),
@@ -182,7 +193,13 @@ impl Stream for PlStream {
                     }
                     Err(RecvError::Decoder(err)) => Err(err),
                     Err(RecvError::PeerGone(Some(err))) => Err(err.into()),
-                    Err(RecvError::PeerGone(None)) => return Poll::Ready(None),
+                    Err(RecvError::PeerGone(None)) => {
+                        if this.codec.is_incomplete() {
+                            Err(PayloadError::Incomplete(None))
+                        } else {
+                            return Poll::Ready(None);
+                        }
+                    }
                 },
             ));
         }
@@ -10,6 +10,7 @@ use crate::util::{poll_fn, ByteString, Bytes, HashMap, Ready};
 use crate::{channel::oneshot, Service, ServiceCtx};
 
 use super::error::SendRequestError;
+use super::response::Trailers;
 
 pub(super) async fn send_request<B>(
     mut client: H2Client,
@@ -106,7 +107,7 @@ async fn send_body<B: MessageBody>(
                 log::debug!("{:?} sending chunk, {} bytes", stream.id(), b.len());
                 stream.send_payload(b, false).await?
             }
-            Some(Err(e)) => return Err(e.into()),
+            Some(Err(e)) => return Err(SendRequestError::Stream(e)),
             None => {
                 log::debug!("{:?} eof of send stream ", stream.id());
                 stream.send_payload(Bytes::new(), true).await?;
@@ -177,6 +178,7 @@ impl H2Client {
             tx: Some(tx),
             stream: None,
             payload: None,
+            trailers: Trailers::default(),
         };
         self.wait_id = Some(id);
         self.inner.streams.borrow_mut().insert(id, info);
@@ -211,6 +213,7 @@ struct StreamInfo {
     tx: Option<oneshot::Sender<Result<(ResponseHead, Payload), SendRequestError>>>,
     stream: Option<h2::Stream>,
     payload: Option<payload::PayloadSender>,
+    trailers: Trailers,
 }
 
 pub(super) struct H2PublishService(Rc<H2ClientInner>);
@@ -273,6 +276,7 @@ impl Service<h2::Message> for H2PublishService {
                             payload::Payload::create(msg.stream().empty_capacity());
                         sender.set_stream(stream);
                         info.payload = Some(sender);
+                        head.extensions_mut().insert(info.trailers.clone());
                         Payload::H2(payload)
                     } else {
                         Payload::None
@@ -303,8 +307,9 @@ impl Service<h2::Message> for H2PublishService {
                             h2::StreamEof::Data(data) => {
                                 pl.feed_eof(data);
                             }
-                            h2::StreamEof::Trailers(_) => {
-                                pl.feed_eof(Bytes::new());
+                            h2::StreamEof::Trailers(trailers) => {
+                                info.trailers.set(trailers.clone());
+                                pl.feed_trailers(trailers);
                             }
                             h2::StreamEof::Error(err) => pl.set_error(err.into()),
                         }
@@ -22,27 +22,44 @@ mod builder;
 mod connect;
 mod connection;
 mod connector;
+#[cfg(feature = "cookie")]
+mod cookies;
+mod download;
 pub mod error;
 mod frozen;
 mod h1proto;
 mod h2proto;
+pub mod multipart;
 mod pool;
+mod proxy;
+mod redirect;
 mod request;
+mod resolver;
 mod response;
+mod retry;
 mod sender;
+#[cfg(feature = "socks")]
+mod socks;
 mod test;
+mod tls;
 
 pub use self::builder::ClientBuilder;
 pub use self::connection::Connection;
 pub use self::connector::Connector;
+#[cfg(feature = "cookie")]
+pub use self::cookies::CookieStore;
+pub use self::download::DownloadWriter;
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
+pub use self::pool::{HostStats, PoolEvent, PoolStats};
 pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
+pub use self::retry::RetryPolicy;
 pub use self::sender::SendClientRequest;
 pub use self::test::TestResponse;
+pub use self::tls::{Certificate, PrivateKey, TlsConfig};
 
 use crate::http::error::HttpError;
-use crate::http::{HeaderMap, Method, RequestHead, Uri};
+use crate::http::{h1, HeaderMap, Method, RequestHead, Uri};
 use crate::time::Millis;
 
 use self::connect::{Connect as HttpConnect, ConnectorWrapper};
@@ -78,6 +95,13 @@ struct ClientConfig {
     pub(self) connector: Box<dyn HttpConnect>,
     pub(self) headers: HeaderMap,
     pub(self) timeout: Millis,
+    pub(self) body_timeout: Millis,
+    pub(self) headers_max_size: usize,
+    pub(self) headers_max_count: usize,
+    pub(self) max_redirects: usize,
+    pub(self) retry: Option<RetryPolicy>,
+    #[cfg(feature = "cookie")]
+    pub(self) cookie_store: Option<CookieStore>,
 }
 
 impl Default for Client {
@@ -86,6 +110,13 @@ impl Default for Client {
             connector: Box::new(ConnectorWrapper(Connector::default().finish().into())),
             headers: HeaderMap::new(),
             timeout: Millis(5_000),
+            body_timeout: Millis::ZERO,
+            headers_max_size: h1::MAX_BUFFER_SIZE,
+            headers_max_count: h1::MAX_HEADERS,
+            max_redirects: 0,
+            retry: None,
+            #[cfg(feature = "cookie")]
+            cookie_store: None,
         }))
     }
 }
@@ -193,4 +224,32 @@ impl Client {
     {
         self.request(Method::OPTIONS, url)
     }
+
+    #[cfg(feature = "cookie")]
+    /// Client's cookie store.
+    ///
+    /// Store is available if it is enabled with `ClientBuilder::cookie_store()`.
+    pub fn cookie_store(&self) -> Option<&CookieStore> {
+        self.0.cookie_store.as_ref()
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Stored cookies that would be sent with request to the `url`.
+    pub fn cookies_for<U>(&self, url: U) -> Vec<coo_kie::Cookie<'static>>
+    where
+        Uri: TryFrom<U>,
+    {
+        match (&self.0.cookie_store, Uri::try_from(url)) {
+            (Some(store), Ok(uri)) => store.cookies_for(&uri),
+            _ => Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Remove all stored cookies.
+    pub fn clear_cookies(&self) {
+        if let Some(ref store) = self.0.cookie_store {
+            store.clear()
+        }
+    }
 }
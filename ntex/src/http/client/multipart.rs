@@ -0,0 +1,290 @@
+//! Multipart form-data request body
+use std::{collections::VecDeque, error::Error, fmt, task::Context, task::Poll};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::body::{BodySize, BodyStream, MessageBody};
+use crate::util::{Bytes, BytesMut, Stream};
+
+/// Multipart `form-data` request body, [RFC 7578](https://tools.ietf.org/html/rfc7578)
+///
+/// Form is sent with `content-length` header if all parts are sized,
+/// otherwise chunked transfer encoding is used.
+///
+/// ```rust,no_run
+/// use ntex::http::client::{multipart::Form, Client};
+/// use ntex::util::Bytes;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let form = Form::new()
+///         .text("name", "value")
+///         .bytes("file", "data.txt", mime::TEXT_PLAIN, Bytes::from_static(b"data"));
+///
+///     let res = Client::new()
+///         .post("http://www.rust-lang.org")
+///         .send_multipart(form)
+///         .await;
+/// }
+/// ```
+pub struct Form {
+    boundary: String,
+    parts: VecDeque<Chunk>,
+    eof: bool,
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Stream(Box<dyn MessageBody>),
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Form::new()
+    }
+}
+
+impl Form {
+    /// Create empty form with random boundary
+    pub fn new() -> Self {
+        Form {
+            boundary: format!("{:032x}", WyRand::new().generate::<u128>()),
+            parts: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    /// Form boundary
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// `Content-Type` header value for the form
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Add text field
+    pub fn text<V: AsRef<str>>(mut self, name: &str, value: V) -> Self {
+        self.push_header(name, None, None);
+        self.push(Chunk::Bytes(Bytes::copy_from_slice(
+            value.as_ref().as_bytes(),
+        )));
+        self.push(Chunk::Bytes(Bytes::from_static(b"\r\n")));
+        self
+    }
+
+    /// Add file part with in-memory content
+    pub fn bytes(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: mime::Mime,
+        data: Bytes,
+    ) -> Self {
+        self.push_header(name, Some(filename), Some(content_type));
+        self.push(Chunk::Bytes(data));
+        self.push(Chunk::Bytes(Bytes::from_static(b"\r\n")));
+        self
+    }
+
+    /// Add file part with streaming content
+    ///
+    /// Form with streaming part is sent with chunked transfer encoding.
+    pub fn stream<S, E>(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: mime::Mime,
+        stream: S,
+    ) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        self.push_header(name, Some(filename), Some(content_type));
+        self.push(Chunk::Stream(Box::new(BodyStream::new(stream))));
+        self.push(Chunk::Bytes(Bytes::from_static(b"\r\n")));
+        self
+    }
+
+    fn push_header(&mut self, name: &str, filename: Option<&str>, ct: Option<mime::Mime>) {
+        let mut buf = BytesMut::with_capacity(128);
+        buf.extend_from_slice(b"--");
+        buf.extend_from_slice(self.boundary.as_bytes());
+        buf.extend_from_slice(b"\r\ncontent-disposition: form-data; name=\"");
+        escape(name, &mut buf);
+        buf.extend_from_slice(b"\"");
+        if let Some(filename) = filename {
+            buf.extend_from_slice(b"; filename=\"");
+            escape(filename, &mut buf);
+            buf.extend_from_slice(b"\"");
+        }
+        if let Some(ct) = ct {
+            buf.extend_from_slice(b"\r\ncontent-type: ");
+            buf.extend_from_slice(ct.as_ref().as_bytes());
+        }
+        buf.extend_from_slice(b"\r\n\r\n");
+        self.push(Chunk::Bytes(buf.freeze()));
+    }
+
+    fn push(&mut self, chunk: Chunk) {
+        self.parts.push_back(chunk);
+    }
+
+    fn closing(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.boundary.len() + 6);
+        buf.extend_from_slice(b"--");
+        buf.extend_from_slice(self.boundary.as_bytes());
+        buf.extend_from_slice(b"--\r\n");
+        buf.freeze()
+    }
+}
+
+/// Escape field name or file name the way browsers do
+///
+/// Value is sent as utf-8, quote and line breaks are percent-encoded.
+fn escape(value: &str, buf: &mut BytesMut) {
+    for ch in value.chars() {
+        match ch {
+            '"' => buf.extend_from_slice(b"%22"),
+            '\r' => buf.extend_from_slice(b"%0D"),
+            '\n' => buf.extend_from_slice(b"%0A"),
+            ch => {
+                let mut tmp = [0; 4];
+                buf.extend_from_slice(ch.encode_utf8(&mut tmp).as_bytes());
+            }
+        }
+    }
+}
+
+impl MessageBody for Form {
+    fn size(&self) -> BodySize {
+        let mut size = self.closing().len() as u64;
+        for part in &self.parts {
+            match part {
+                Chunk::Bytes(bytes) => size += bytes.len() as u64,
+                Chunk::Stream(_) => return BodySize::Stream,
+            }
+        }
+        BodySize::Sized(size)
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        loop {
+            match self.parts.front_mut() {
+                Some(Chunk::Bytes(_)) => {
+                    // empty chunk would terminate chunked body
+                    if let Some(Chunk::Bytes(bytes)) = self.parts.pop_front() {
+                        if !bytes.is_empty() {
+                            return Poll::Ready(Some(Ok(bytes)));
+                        }
+                    }
+                }
+                Some(Chunk::Stream(stream)) => match stream.poll_next_chunk(cx) {
+                    Poll::Ready(None) => {
+                        self.parts.pop_front();
+                    }
+                    res => return res,
+                },
+                None => {
+                    return if self.eof {
+                        Poll::Ready(None)
+                    } else {
+                        self.eof = true;
+                        Poll::Ready(Some(Ok(self.closing())))
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::poll_fn;
+
+    async fn body(mut form: Form) -> Bytes {
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| form.poll_next_chunk(cx)).await {
+            let chunk = chunk.unwrap();
+            assert!(!chunk.is_empty());
+            buf.extend_from_slice(&chunk);
+        }
+        buf.freeze()
+    }
+
+    #[crate::rt_test]
+    async fn test_form() {
+        let form = Form::new().text("name", "value").text("empty", "").bytes(
+            "file",
+            "data.txt",
+            mime::TEXT_PLAIN,
+            Bytes::from_static(b"data"),
+        );
+        let boundary = form.boundary().to_string();
+        assert_eq!(boundary.len(), 32);
+        assert_eq!(
+            form.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+
+        let size = form.size();
+        let body = body(form).await;
+        assert_eq!(size, BodySize::Sized(body.len() as u64));
+        assert_eq!(
+            body,
+            format!(
+                "--{0}\r\ncontent-disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n\
+                 --{0}\r\ncontent-disposition: form-data; name=\"empty\"\r\n\r\n\r\n\
+                 --{0}\r\ncontent-disposition: form-data; name=\"file\"; \
+                 filename=\"data.txt\"\r\ncontent-type: text/plain\r\n\r\ndata\r\n\
+                 --{0}--\r\n",
+                boundary
+            )
+        );
+
+        let form = Form::new();
+        assert_eq!(form.size(), BodySize::Sized(38));
+        assert_ne!(form.boundary(), Form::default().boundary());
+    }
+
+    #[crate::rt_test]
+    async fn test_stream() {
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, std::io::Error>>();
+        tx.send(Ok(Bytes::from_static(b"ab"))).unwrap();
+        tx.send(Ok(Bytes::from_static(b"cd"))).unwrap();
+        drop(tx);
+
+        let form = Form::new().stream(
+            "file\"\r\n",
+            "тест.txt",
+            mime::APPLICATION_OCTET_STREAM,
+            rx,
+        );
+        assert_eq!(form.size(), BodySize::Stream);
+        let boundary = form.boundary().to_string();
+        assert_eq!(
+            body(form).await,
+            format!(
+                "--{0}\r\ncontent-disposition: form-data; name=\"file%22%0D%0A\"; \
+                 filename=\"тест.txt\"\r\ncontent-type: application/octet-stream\
+                 \r\n\r\nabcd\r\n--{0}--\r\n",
+                boundary
+            )
+        );
+    }
+}
@@ -1,13 +1,14 @@
+use std::rc::Weak;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use std::{cell::RefCell, collections::VecDeque, future::Future, pin::Pin, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, fmt, future::Future, pin::Pin, rc::Rc};
 
 use ntex_h2::{self as h2};
 
 use crate::http::uri::{Authority, Scheme, Uri};
 use crate::io::{types::HttpProtocol, IoBoxed};
 use crate::service::{Pipeline, PipelineCall, Service, ServiceCtx};
-use crate::time::{now, Millis};
+use crate::time::{now, sleep, timeout_checked, Millis};
 use crate::util::{ready, BoxFuture, ByteString, HashMap, HashSet};
 use crate::{channel::pool, rt::spawn, task::LocalWaker};
 
@@ -35,6 +36,97 @@ enum Acquire {
     NotAvailable,
 }
 
+/// Connection pool event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// New connection is opened
+    Open,
+    /// Idle connection is reused
+    Reuse,
+    /// Connection is closed by the pool
+    Close,
+}
+
+/// Connection pool statistics of a host
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostStats {
+    /// Number of connections in use
+    pub active: usize,
+    /// Number of idle connections
+    pub idle: usize,
+    /// Number of requests waiting for a connection
+    pub waiters: usize,
+    /// Total number of acquired connections
+    pub acquired: u64,
+    /// Total number of released connections
+    pub released: u64,
+}
+
+/// Connection pool statistics
+///
+/// Statistics handle is created by `Connector::stats()`, it reports pools
+/// of the connector after `Connector::finish()` is called. Host is removed
+/// from statistics once it has no active and idle connections.
+#[derive(Clone, Default)]
+pub struct PoolStats(Rc<RefCell<Vec<Weak<RefCell<Inner>>>>>);
+
+impl PoolStats {
+    /// Statistics of all known hosts
+    pub fn hosts(&self) -> Vec<(Authority, HostStats)> {
+        let mut hosts: HashMap<Key, HostStats> = HashMap::default();
+        for inner in self.0.borrow().iter().filter_map(Weak::upgrade) {
+            if let Ok(inner) = inner.try_borrow() {
+                inner.stats(&mut hosts);
+            }
+        }
+        hosts
+            .into_iter()
+            .map(|(key, stats)| (key.authority, stats))
+            .collect()
+    }
+
+    /// Statistics of the host
+    pub fn host(&self, authority: &Authority) -> Option<HostStats> {
+        self.hosts()
+            .into_iter()
+            .find(|(auth, _)| auth == authority)
+            .map(|(_, stats)| stats)
+    }
+
+    fn register(&self, inner: &Rc<RefCell<Inner>>) {
+        let mut pools = self.0.borrow_mut();
+        pools.retain(|pool| pool.strong_count() > 0);
+        pools.push(Rc::downgrade(inner));
+    }
+}
+
+impl fmt::Debug for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolStats")
+            .field("hosts", &self.hosts())
+            .finish()
+    }
+}
+
+/// Pool events hook
+#[derive(Clone)]
+pub(super) struct PoolEventHook(Rc<dyn Fn(&Authority, PoolEvent)>);
+
+impl PoolEventHook {
+    pub(super) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Authority, PoolEvent) + 'static,
+    {
+        PoolEventHook(Rc::new(f))
+    }
+}
+
+impl fmt::Debug for PoolEventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolEventHook").finish()
+    }
+}
+
 #[derive(Debug)]
 struct AvailableConnection {
     io: ConnectionType,
@@ -42,6 +134,20 @@ struct AvailableConnection {
     created: Instant,
 }
 
+impl AvailableConnection {
+    fn is_expired(&self, now: Instant, keep_alive: Duration, lifetime: Duration) -> bool {
+        (now - self.used) > keep_alive || (now - self.created) > lifetime
+    }
+
+    fn close(self) {
+        if let ConnectionType::H1(io) = self.io {
+            spawn(async move {
+                let _ = io.shutdown().await;
+            });
+        }
+    }
+}
+
 /// Connections pool
 #[derive(Debug)]
 pub(super) struct ConnectionPool<T> {
@@ -54,28 +160,37 @@ impl<T> ConnectionPool<T>
 where
     T: Service<Connect, Response = IoBoxed, Error = ConnectError> + 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         connector: T,
         conn_lifetime: Duration,
         conn_keep_alive: Duration,
         disconnect_timeout: Millis,
+        timeout: Millis,
         limit: usize,
+        limit_per_host: usize,
         h2config: h2::Config,
     ) -> Self {
         let connector = Pipeline::new(connector);
         let waiters = Rc::new(RefCell::new(Waiters {
             waiters: HashMap::default(),
             pool: pool::new(),
+            seq: 0,
         }));
         let inner = Rc::new(RefCell::new(Inner {
             conn_lifetime,
             conn_keep_alive,
             disconnect_timeout,
+            timeout,
             limit,
+            limit_per_host,
             h2config,
             acquired: 0,
+            acquired_per_host: HashMap::default(),
             available: HashMap::default(),
             connecting: HashSet::default(),
+            counters: HashMap::default(),
+            hook: None,
             waker: LocalWaker::new(),
             waiters: waiters.clone(),
         }));
@@ -87,6 +202,22 @@ where
             waiters: waiters.clone(),
         });
 
+        // close expired idle connections
+        let interval = conn_keep_alive
+            .min(conn_lifetime)
+            .clamp(Duration::from_millis(10), Duration::from_secs(1));
+        let pool = Rc::downgrade(&inner);
+        crate::rt::spawn(async move {
+            loop {
+                sleep(interval).await;
+                if let Some(inner) = pool.upgrade() {
+                    inner.borrow_mut().evict();
+                } else {
+                    break;
+                }
+            }
+        });
+
         ConnectionPool {
             connector,
             inner,
@@ -95,6 +226,15 @@ where
     }
 }
 
+impl<T> ConnectionPool<T> {
+    /// Report pool statistics and events
+    pub(super) fn monitor(self, stats: &PoolStats, hook: Option<PoolEventHook>) -> Self {
+        stats.register(&self.inner);
+        self.inner.borrow_mut().hook = hook;
+        self
+    }
+}
+
 impl<T> Drop for ConnectionPool<T> {
     fn drop(&mut self) {
         self.inner.borrow().waker.wake();
@@ -164,10 +304,15 @@ where
                         "Pool is full, waiting for available connections for {:?}",
                         req.uri
                     );
+                    // guard must be dropped after receiver, so cancelled
+                    // waiter get removed from the queue
+                    let _guard = WaitGuard(waiters.clone());
                     let rx = waiters.borrow_mut().wait_for(req);
-                    match rx.await {
-                        Err(_) => Err(ConnectError::Disconnected(None)),
-                        Ok(res) => res,
+                    let timeout = inner.borrow().timeout;
+                    match timeout_checked(timeout, rx).await {
+                        Ok(Err(_)) => Err(ConnectError::Disconnected(None)),
+                        Ok(Ok(res)) => res,
+                        Err(_) => Err(ConnectError::Timeout),
                     }
                 }
             }
@@ -180,19 +325,25 @@ pub(super) struct Inner {
     conn_lifetime: Duration,
     conn_keep_alive: Duration,
     disconnect_timeout: Millis,
+    timeout: Millis,
     limit: usize,
+    limit_per_host: usize,
     h2config: h2::Config,
     acquired: usize,
+    acquired_per_host: HashMap<Key, usize>,
     available: HashMap<Key, VecDeque<AvailableConnection>>,
     connecting: HashSet<Key>,
+    counters: HashMap<Key, (u64, u64)>,
+    hook: Option<PoolEventHook>,
     waker: LocalWaker,
     waiters: Rc<RefCell<Waiters>>,
 }
 
 #[derive(Debug)]
 struct Waiters {
-    waiters: HashMap<Key, VecDeque<(Connect, Waiter)>>,
+    waiters: HashMap<Key, VecDeque<(Connect, Waiter, usize)>>,
     pool: pool::Pool<Result<Connection, ConnectError>>,
+    seq: usize,
 }
 
 impl Waiters {
@@ -200,37 +351,49 @@ impl Waiters {
     fn wait_for(&mut self, connect: Connect) -> WaiterReceiver {
         let (tx, rx) = self.pool.channel();
         let key: Key = connect.uri.authority().unwrap().clone().into();
+        self.seq = self.seq.wrapping_add(1);
         self.waiters
             .entry(key)
             .or_insert_with(VecDeque::new)
-            .push_back((connect, tx));
+            .push_back((connect, tx, self.seq));
         rx
     }
 
     /// cleanup dropped waiters
     fn cleanup(&mut self) {
-        let mut keys = Vec::new();
-
-        // cleanup waiters
-        for (key, waiters) in &mut self.waiters {
-            while !waiters.is_empty() {
-                let (req, tx) = waiters.front().unwrap();
+        self.waiters.retain(|_, waiters| {
+            waiters.retain(|(req, tx, _)| {
                 // check if waiter is still alive
                 if tx.is_canceled() {
                     trace!("Waiter for {:?} is gone, remove waiter", req.uri);
-                    waiters.pop_front();
-                    continue;
-                };
-                break;
-            }
+                    false
+                } else {
+                    true
+                }
+            });
+            !waiters.is_empty()
+        });
+    }
 
-            if waiters.is_empty() {
-                keys.push(key.clone());
-            }
-        }
+    /// hosts with waiters, ordered by the oldest waiter
+    fn hosts(&self) -> Vec<Key> {
+        let mut keys: Vec<_> = self
+            .waiters
+            .iter()
+            .filter_map(|(key, waiters)| waiters.front().map(|w| (w.2, key.clone())))
+            .collect();
+        keys.sort_unstable_by_key(|(seq, _)| *seq);
+        keys.into_iter().map(|(_, key)| key).collect()
+    }
+}
 
-        for key in keys {
-            self.waiters.remove(&key);
+/// Removes cancelled waiters when waiting future is dropped
+struct WaitGuard(Rc<RefCell<Waiters>>);
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        if let Ok(mut waiters) = self.0.try_borrow_mut() {
+            waiters.cleanup();
         }
     }
 }
@@ -241,6 +404,11 @@ impl Inner {
         if self.limit > 0 && self.acquired >= self.limit {
             return Acquire::NotAvailable;
         }
+        if self.limit_per_host > 0
+            && self.acquired_per_host.get(key).copied().unwrap_or(0) >= self.limit_per_host
+        {
+            return Acquire::NotAvailable;
+        }
 
         // check if open connection is available
         // cleanup stale connections at the same time
@@ -248,14 +416,9 @@ impl Inner {
             let now = now();
             while let Some(conn) = connections.pop_back() {
                 // check if it still usable
-                if (now - conn.used) > self.conn_keep_alive
-                    || (now - conn.created) > self.conn_lifetime
-                {
-                    if let ConnectionType::H1(io) = conn.io {
-                        spawn(async move {
-                            let _ = io.shutdown().await;
-                        });
-                    }
+                if conn.is_expired(now, self.conn_keep_alive, self.conn_lifetime) {
+                    conn.close();
+                    emit(&self.hook, key, PoolEvent::Close);
                     continue;
                 }
 
@@ -263,6 +426,7 @@ impl Inner {
                 match io {
                     ConnectionType::H1(ref s) => {
                         if s.is_closed() {
+                            emit(&self.hook, key, PoolEvent::Close);
                             continue;
                         }
                         let is_valid = s.with_read_buf(|buf| {
@@ -274,11 +438,13 @@ impl Inner {
                             }
                         });
                         if !is_valid {
+                            emit(&self.hook, key, PoolEvent::Close);
                             continue;
                         }
                     }
                     ConnectionType::H2(ref s) => {
                         if s.is_closed() {
+                            emit(&self.hook, key, PoolEvent::Close);
                             continue;
                         }
                         let conn = AvailableConnection {
@@ -289,8 +455,10 @@ impl Inner {
                         connections.push_front(conn);
                     }
                 }
+                emit(&self.hook, key, PoolEvent::Reuse);
                 return Acquire::Acquired(io, conn.created);
             }
+            self.cleanup_host(key);
         }
 
         if self.connecting.contains(key) {
@@ -303,10 +471,89 @@ impl Inner {
     fn check_availibility(&mut self) {
         let mut waiters = self.waiters.borrow_mut();
         waiters.cleanup();
-        if !waiters.waiters.is_empty() && self.acquired < self.limit {
+        if !waiters.waiters.is_empty() && (self.limit == 0 || self.acquired < self.limit) {
             self.waker.wake();
         }
     }
+
+    /// Close idle connections that exceeded keep-alive or lifetime period
+    fn evict(&mut self) {
+        let now = now();
+        let (keep_alive, lifetime) = (self.conn_keep_alive, self.conn_lifetime);
+        let mut closed = Vec::new();
+        self.available.retain(|key, connections| {
+            for conn in std::mem::take(connections) {
+                if conn.is_expired(now, keep_alive, lifetime) {
+                    trace!("Close expired connection for {:?}", key.authority);
+                    conn.close();
+                    closed.push(key.clone());
+                } else {
+                    connections.push_back(conn);
+                }
+            }
+            !connections.is_empty()
+        });
+        for key in closed {
+            self.event(&key, PoolEvent::Close);
+            self.cleanup_host(&key);
+        }
+    }
+
+    fn event(&self, key: &Key, event: PoolEvent) {
+        emit(&self.hook, key, event)
+    }
+
+    fn stats(&self, hosts: &mut HashMap<Key, HostStats>) {
+        for (key, cnt) in &self.acquired_per_host {
+            hosts.entry(key.clone()).or_default().active += cnt;
+        }
+        for (key, connections) in &self.available {
+            hosts.entry(key.clone()).or_default().idle += connections.len();
+        }
+        for (key, (acquired, released)) in &self.counters {
+            let stats = hosts.entry(key.clone()).or_default();
+            stats.acquired += acquired;
+            stats.released += released;
+        }
+        if let Ok(waiters) = self.waiters.try_borrow() {
+            for (key, queue) in &waiters.waiters {
+                hosts.entry(key.clone()).or_default().waiters += queue.len();
+            }
+        }
+    }
+
+    fn acquire_host(&mut self, key: &Key) {
+        self.acquired += 1;
+        *self.acquired_per_host.entry(key.clone()).or_insert(0) += 1;
+        self.counters.entry(key.clone()).or_default().0 += 1;
+    }
+
+    fn release_host(&mut self, key: &Key) {
+        self.acquired -= 1;
+        self.counters.entry(key.clone()).or_default().1 += 1;
+        if let Some(cnt) = self.acquired_per_host.get_mut(key) {
+            *cnt -= 1;
+            if *cnt == 0 {
+                self.acquired_per_host.remove(key);
+            }
+        }
+    }
+
+    /// Remove state of the host without active and idle connections
+    fn cleanup_host(&mut self, key: &Key) {
+        if !self.acquired_per_host.contains_key(key)
+            && self.available.get(key).map(|c| c.is_empty()).unwrap_or(true)
+        {
+            self.available.remove(key);
+            self.counters.remove(key);
+        }
+    }
+}
+
+fn emit(hook: &Option<PoolEventHook>, key: &Key, event: PoolEvent) {
+    if let Some(ref hook) = hook {
+        (hook.0)(&key.authority, event);
+    }
 }
 
 struct ConnectionPoolSupport<T> {
@@ -333,20 +580,24 @@ where
         let mut waiters = this.waiters.borrow_mut();
         this.inner.borrow_mut().waker.register(cx.waker());
 
-        // check waiters
-        for (key, waiters) in &mut waiters.waiters {
-            while let Some((req, tx)) = waiters.front() {
+        // check waiters, hosts with the oldest waiters go first. host that
+        // reached its limit does not block waiters of other hosts
+        'outer: loop {
+            for key in waiters.hosts() {
+                let queue = waiters.waiters.get_mut(&key).unwrap();
+                let (req, tx, _) = queue.front().unwrap();
+
                 // is waiter still alive
                 if tx.is_canceled() {
                     trace!("Waiter for {:?} is gone, cleanup", req.uri);
                     cleanup = true;
-                    waiters.pop_front();
-                    continue;
+                    queue.pop_front();
+                    continue 'outer;
                 };
 
-                let result = this.inner.borrow_mut().acquire(key);
+                let result = this.inner.borrow_mut().acquire(&key);
                 match result {
-                    Acquire::NotAvailable => break,
+                    Acquire::NotAvailable => continue,
                     Acquire::Acquired(io, created) => {
                         trace!(
                             "Use existing {:?} connection for {:?}, wake up waiter",
@@ -354,7 +605,7 @@ where
                             req.uri
                         );
                         cleanup = true;
-                        let (_, tx) = waiters.pop_front().unwrap();
+                        let (_, tx, _) = queue.pop_front().unwrap();
                         let _ = tx.send(Ok(Connection::new(
                             io,
                             created,
@@ -364,7 +615,7 @@ where
                     Acquire::Available => {
                         trace!("Connecting to {:?} and wake up waiter", req.uri);
                         cleanup = true;
-                        let (connect, tx) = waiters.pop_front().unwrap();
+                        let (connect, tx, _) = queue.pop_front().unwrap();
                         let uri = connect.uri.clone();
                         OpenConnection::spawn(
                             key.clone(),
@@ -376,7 +627,9 @@ where
                         );
                     }
                 }
+                continue 'outer;
             }
+            break;
         }
 
         if cleanup {
@@ -490,6 +743,7 @@ where
                         );
                     });
 
+                    this.inner.borrow().event(this.key, PoolEvent::Open);
                     let guard = this.guard.take().unwrap().consume();
                     let conn = Connection::new(
                         ConnectionType::H2(client.clone()),
@@ -514,6 +768,7 @@ where
                         "Connection for {:?} is established, init http1 connection",
                         &this.key.authority
                     );
+                    this.inner.borrow().event(this.key, PoolEvent::Open);
                     let conn = Connection::new(
                         ConnectionType::H1(io),
                         now(),
@@ -566,7 +821,7 @@ pub(super) struct Acquired(Key, Option<Rc<RefCell<Inner>>>);
 
 impl Acquired {
     fn new(key: Key, inner: Rc<RefCell<Inner>>) -> Self {
-        inner.borrow_mut().acquired += 1;
+        inner.borrow_mut().acquire_host(&key);
         Acquired(key, Some(inner))
     }
 
@@ -578,7 +833,7 @@ impl Acquired {
         if let Some(inner) = self.1.take() {
             let (io, created, _) = conn.into_inner();
             let mut inner = inner.borrow_mut();
-            inner.acquired -= 1;
+            inner.release_host(&self.0);
             if close {
                 log::trace!(
                     "Releasing and closing connection for {:?}",
@@ -592,6 +847,7 @@ impl Acquired {
                     }
                     ConnectionType::H2(io) => io.close(),
                 }
+                inner.event(&self.0, PoolEvent::Close);
             } else {
                 log::trace!("Releasing connection for {:?}", self.0.authority);
                 inner
@@ -604,6 +860,7 @@ impl Acquired {
                         used: now(),
                     });
             }
+            inner.cleanup_host(&self.0);
             inner.check_availibility();
         }
     }
@@ -613,7 +870,8 @@ impl Drop for Acquired {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
             let mut inner = inner.borrow_mut();
-            inner.acquired -= 1;
+            inner.release_host(&self.0);
+            inner.cleanup_host(&self.0);
             inner.check_availibility();
         }
     }
@@ -643,7 +901,9 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_secs(10),
                 Millis::ZERO,
+                Millis::ZERO,
                 1,
+                0,
                 h2::Config::client(),
             )
             .clone(),
@@ -736,4 +996,200 @@ mod tests {
         assert!(lazy(|cx| pool.poll_ready(cx)).await.is_ready());
         assert!(lazy(|cx| pool.poll_shutdown(cx)).await.is_ready());
     }
+
+    #[crate::rt_test]
+    async fn test_limit_per_host() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+
+        let pool = Pipeline::new(ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Millis::ZERO,
+            Millis::ZERO,
+            0,
+            1,
+            h2::Config::client(),
+        ));
+
+        let req1 = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+        let req2 = Connect {
+            uri: Uri::try_from("http://localhost2/test").unwrap(),
+            addr: None,
+        };
+        let conn = pool.call(req1.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 1);
+
+        // host limit is reached, requests are queued
+        let mut fut1 = pool.call(req1.clone());
+        let mut fut2 = pool.call(req1.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut1).poll(cx)).await.is_pending());
+        assert!(lazy(|cx| Pin::new(&mut fut2).poll(cx)).await.is_pending());
+        assert_eq!(pool.get_ref().waiters.borrow().waiters.len(), 1);
+
+        // other host is not blocked
+        let conn2 = pool.call(req2.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+        assert_eq!(pool.get_ref().inner.borrow().acquired, 2);
+
+        // released connection goes to the first waiter
+        conn.release(false);
+        let conn = fut1.await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+        assert!(lazy(|cx| Pin::new(&mut fut2).poll(cx)).await.is_pending());
+
+        conn.release(false);
+        let conn = fut2.await.unwrap();
+        assert_eq!(store.borrow().len(), 2);
+        assert!(pool.get_ref().waiters.borrow().waiters.is_empty());
+
+        conn.release(false);
+        conn2.release(false);
+        assert_eq!(pool.get_ref().inner.borrow().acquired, 0);
+        assert!(pool.get_ref().inner.borrow().acquired_per_host.is_empty());
+    }
+
+    #[crate::rt_test]
+    async fn test_limit_per_host_waiters() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+
+        let pool = Pipeline::new(ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Millis::ZERO,
+            Millis(100),
+            0,
+            1,
+            h2::Config::client(),
+        ));
+
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+        let conn = pool.call(req.clone()).await.unwrap();
+
+        // dropped waiter is removed from the queue
+        let mut fut = pool.call(req.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut).poll(cx)).await.is_pending());
+        assert_eq!(pool.get_ref().waiters.borrow().waiters.len(), 1);
+        drop(fut);
+        assert!(pool.get_ref().waiters.borrow().waiters.is_empty());
+
+        // connect timeout applies to waiting
+        match pool.call(req.clone()).await {
+            Err(ConnectError::Timeout) => (),
+            _ => panic!(),
+        }
+        assert!(pool.get_ref().waiters.borrow().waiters.is_empty());
+
+        conn.release(false);
+        let conn = pool.call(req.clone()).await.unwrap();
+        assert_eq!(store.borrow().len(), 1);
+        drop(conn);
+        assert_eq!(pool.get_ref().inner.borrow().acquired, 0);
+    }
+
+    #[crate::rt_test]
+    async fn test_stats() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events2 = events.clone();
+        let stats = PoolStats::default();
+
+        let pool = Pipeline::new(
+            ConnectionPool::new(
+                fn_service(move |_| {
+                    let (client, server) = Io::create();
+                    store2.borrow_mut().push(server);
+                    Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+                }),
+                Duration::from_secs(10),
+                Duration::from_millis(50),
+                Millis::ZERO,
+                Millis::ZERO,
+                1,
+                0,
+                h2::Config::client(),
+            )
+            .monitor(
+                &stats,
+                Some(PoolEventHook::new(move |auth, ev| {
+                    events2.borrow_mut().push((auth.to_string(), ev))
+                })),
+            ),
+        );
+        assert!(stats.hosts().is_empty());
+        assert!(format!("{:?}", stats).contains("PoolStats"));
+
+        let req = Connect {
+            uri: Uri::try_from("http://localhost/test").unwrap(),
+            addr: None,
+        };
+        let auth = req.uri.authority().unwrap().clone();
+        let conn = pool.call(req.clone()).await.unwrap();
+        let mut fut = pool.call(req.clone());
+        assert!(lazy(|cx| Pin::new(&mut fut).poll(cx)).await.is_pending());
+        assert_eq!(
+            stats.host(&auth).unwrap(),
+            HostStats {
+                active: 1,
+                idle: 0,
+                waiters: 1,
+                acquired: 1,
+                released: 0
+            }
+        );
+
+        // released connection is reused by waiter
+        conn.release(false);
+        let conn = fut.await.unwrap();
+        conn.release(false);
+        assert_eq!(
+            stats.host(&auth).unwrap(),
+            HostStats {
+                active: 0,
+                idle: 1,
+                waiters: 0,
+                acquired: 2,
+                released: 2
+            }
+        );
+
+        // idle connection is closed in background,
+        // host without connections is removed
+        sleep(Millis(150)).await;
+        assert!(stats.host(&auth).is_none());
+        assert!(pool.get_ref().inner.borrow().available.is_empty());
+        assert!(pool.get_ref().inner.borrow().counters.is_empty());
+        assert_eq!(
+            events.borrow()[..],
+            [
+                ("localhost".to_string(), PoolEvent::Open),
+                ("localhost".to_string(), PoolEvent::Reuse),
+                ("localhost".to_string(), PoolEvent::Close)
+            ]
+        );
+
+        // closed connection
+        let conn = pool.call(req.clone()).await.unwrap();
+        assert_eq!(stats.host(&auth).unwrap().active, 1);
+        conn.release(true);
+        assert!(stats.host(&auth).is_none());
+    }
 }
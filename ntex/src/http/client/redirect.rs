@@ -0,0 +1,187 @@
+//! Redirects handling
+use std::net;
+
+use crate::http::body::Body;
+use crate::http::header;
+use crate::http::{Method, RequestHead, RequestHeadType, StatusCode, Uri};
+
+use super::{error::SendRequestError, sender::send_request, ClientConfig, ClientResponse};
+
+/// Send request and follow redirects
+///
+/// Up to `ClientConfig::max_redirects` redirects are followed, after that
+/// last redirect response is returned as is.
+pub(super) async fn send(
+    mut head: RequestHeadType,
+    mut body: Body,
+    mut addr: Option<net::SocketAddr>,
+    config: &ClientConfig,
+) -> Result<ClientResponse, SendRequestError> {
+    let mut redirects = 0;
+
+    loop {
+        let replay = replay_body(&body);
+        let req = head.as_ref();
+        let (uri, method, version, flags) =
+            (req.uri.clone(), req.method.clone(), req.version, req.flags);
+        let mut headers = req.headers.clone();
+        if let Some(extra) = head.extra_headers() {
+            for (key, value) in extra.iter() {
+                headers.insert(key.clone(), value.clone());
+            }
+        }
+
+        let res = send_request(head, body, addr, config).await?;
+        if redirects >= config.max_redirects {
+            return Ok(res);
+        }
+
+        let status = res.status();
+        let location = if is_redirect(status) {
+            res.headers()
+                .get(header::LOCATION)
+                .and_then(|val| val.to_str().ok())
+                .and_then(|val| resolve(&uri, val))
+        } else {
+            None
+        };
+        let location = if let Some(location) = location {
+            location
+        } else {
+            return Ok(res);
+        };
+        log::trace!("Follow redirect {:?} to {:?}", status, location);
+
+        // 301, 302 switch POST to GET, 303 switches any method except HEAD
+        let next_method = match status {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND if method == Method::POST => {
+                Method::GET
+            }
+            StatusCode::SEE_OTHER if method != Method::HEAD => Method::GET,
+            _ => method.clone(),
+        };
+        body = if next_method == method {
+            match replay {
+                Some(body) => body,
+                None => return Err(SendRequestError::RedirectStream),
+            }
+        } else {
+            headers.remove(header::CONTENT_TYPE);
+            headers.remove(header::CONTENT_LENGTH);
+            headers.remove(header::TRANSFER_ENCODING);
+            Body::None
+        };
+
+        // do not leak credentials to other origins
+        if !same_origin(&uri, &location) {
+            headers.remove(header::AUTHORIZATION);
+            headers.remove(header::COOKIE);
+            addr = None;
+        }
+
+        head = RequestHeadType::Owned(RequestHead {
+            uri: location,
+            method: next_method,
+            version,
+            headers,
+            flags,
+            ..Default::default()
+        });
+        redirects += 1;
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+/// Copy of request body, streaming bodies can not be copied
+pub(super) fn replay_body(body: &Body) -> Option<Body> {
+    match body {
+        Body::None => Some(Body::None),
+        Body::Empty => Some(Body::Empty),
+        Body::Bytes(bytes) => Some(Body::Bytes(bytes.clone())),
+        Body::Message(_) => None,
+    }
+}
+
+/// Resolve `Location` header value against request uri
+fn resolve(base: &Uri, location: &str) -> Option<Uri> {
+    if let Ok(uri) = Uri::try_from(location) {
+        if uri.scheme().is_some() && uri.authority().is_some() {
+            return Some(uri);
+        }
+    }
+
+    let scheme = base.scheme_str()?;
+    let authority = base.authority()?;
+    let uri = if location.starts_with("//") {
+        format!("{}:{}", scheme, location)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else {
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map(|idx| idx + 1).unwrap_or(0)];
+        let dir = if dir.is_empty() { "/" } else { dir };
+        format!("{}://{}{}{}", scheme, authority, dir, location)
+    };
+    Uri::try_from(uri).ok()
+}
+
+fn same_origin(a: &Uri, b: &Uri) -> bool {
+    let host = |uri: &Uri| uri.host().map(|h| h.to_ascii_lowercase());
+    a.scheme() == b.scheme() && host(a) == host(b) && port(a) == port(b)
+}
+
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("http") | Some("ws") => Some(80),
+        Some("https") | Some("wss") => Some(443),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base = Uri::from_static("http://localhost:8080/a/b?q=1");
+        assert_eq!(
+            resolve(&base, "https://example.com/x").unwrap(),
+            "https://example.com/x"
+        );
+        assert_eq!(
+            resolve(&base, "//example.com/x").unwrap(),
+            "http://example.com/x"
+        );
+        assert_eq!(
+            resolve(&base, "/x?y=1").unwrap(),
+            "http://localhost:8080/x?y=1"
+        );
+        assert_eq!(resolve(&base, "c").unwrap(), "http://localhost:8080/a/c");
+        assert!(resolve(&Uri::from_static("/a"), "c").is_none());
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let origin = |s: &'static str| {
+            same_origin(
+                &Uri::from_static("http://localhost/a"),
+                &Uri::from_static(s),
+            )
+        };
+        assert!(origin("http://LOCALHOST:80/b"));
+        assert!(!origin("https://localhost/a"));
+        assert!(!origin("http://localhost:8080/a"));
+        assert!(!origin("http://example.com/a"));
+    }
+}
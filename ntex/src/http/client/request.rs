@@ -13,15 +13,23 @@ use crate::http::{
 };
 use crate::{time::Millis, util::Bytes, util::Stream};
 
-use super::error::{FreezeRequestError, InvalidUrl};
+use super::download::{self, DownloadWriter};
+use super::error::{DownloadError, FreezeRequestError, InvalidUrl, SendRequestError};
 use super::sender::{PrepForSendingError, SendClientRequest};
-use super::{frozen::FrozenClientRequest, ClientConfig};
+use super::{frozen::FrozenClientRequest, multipart::Form, ClientConfig};
 
-#[cfg(feature = "compress")]
+#[cfg(feature = "zstd")]
+const HTTPS_ENCODING: &str = "br, zstd, gzip, deflate";
+#[cfg(all(feature = "compress", not(feature = "zstd")))]
 const HTTPS_ENCODING: &str = "br, gzip, deflate";
 #[cfg(not(feature = "compress"))]
 const HTTPS_ENCODING: &str = "br";
 
+#[cfg(feature = "zstd")]
+const HTTP_ENCODING: &str = "zstd, gzip, deflate";
+#[cfg(all(feature = "compress", not(feature = "zstd")))]
+const HTTP_ENCODING: &str = "gzip, deflate";
+
 /// An HTTP Client request builder
 ///
 /// This type can be used to construct an instance of `ClientRequest` through a
@@ -52,6 +60,7 @@ pub struct ClientRequest {
     cookies: Option<CookieJar>,
     response_decompress: bool,
     timeout: Millis,
+    body_timeout: Option<Millis>,
     config: Rc<ClientConfig>,
 }
 
@@ -70,6 +79,7 @@ impl ClientRequest {
             #[cfg(feature = "cookie")]
             cookies: None,
             timeout: Millis::ZERO,
+            body_timeout: None,
             response_decompress: true,
         }
         .method(method)
@@ -247,12 +257,16 @@ impl ClientRequest {
     }
 
     /// Set content length
+    ///
+    /// Streaming body is sent with this length instead of chunked transfer encoding.
     #[inline]
     pub fn content_length(self, len: u64) -> Self {
         self.header(header::CONTENT_LENGTH, len)
     }
 
-    /// Set HTTP basic authorization header
+    /// Set HTTP basic authorization header, replaces existing header.
+    ///
+    /// Credentials are encoded as utf-8, [RFC 7617](https://tools.ietf.org/html/rfc7617).
     pub fn basic_auth<U>(self, username: U, password: Option<&str>) -> Self
     where
         U: fmt::Display,
@@ -261,18 +275,18 @@ impl ClientRequest {
             Some(password) => format!("{}:{}", username, password),
             None => format!("{}:", username),
         };
-        self.header(
+        self.set_header(
             header::AUTHORIZATION,
             format!("Basic {}", base64.encode(auth)),
         )
     }
 
-    /// Set HTTP bearer authentication header
+    /// Set HTTP bearer authentication header, replaces existing header.
     pub fn bearer_auth<T>(self, token: T) -> Self
     where
         T: fmt::Display,
     {
-        self.header(header::AUTHORIZATION, format!("Bearer {}", token))
+        self.set_header(header::AUTHORIZATION, format!("Bearer {}", token))
     }
 
     #[cfg(feature = "cookie")]
@@ -325,6 +339,45 @@ impl ClientRequest {
         self
     }
 
+    /// Set response body timeout. Overrides client wide body timeout setting.
+    ///
+    /// If no payload data arrives within this period, the response payload
+    /// stream yields `PayloadError::Timeout`.
+    pub fn body_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.body_timeout = Some(timeout.into());
+        self
+    }
+
+    /// Disable response body timeout for this request.
+    ///
+    /// Overrides client wide body timeout setting.
+    pub fn disable_body_timeout(mut self) -> Self {
+        self.body_timeout = Some(Millis::ZERO);
+        self
+    }
+
+    /// Request byte range of the resource.
+    ///
+    /// Sets `Range: bytes=<start>-<end>` header, `end` is inclusive.
+    /// If `end` is `None`, rest of the resource starting from `start`
+    /// is requested.
+    pub fn range(self, start: u64, end: Option<u64>) -> Self {
+        let value = if let Some(end) = end {
+            format!("bytes={}-{}", start, end)
+        } else {
+            format!("bytes={}-", start)
+        };
+        self.set_header(header::RANGE, value)
+    }
+
+    /// Do not retry this request.
+    ///
+    /// Overrides client wide retry policy.
+    pub fn no_retry(mut self) -> Self {
+        self.head.set_no_retry();
+        self
+    }
+
     /// This method calls provided closure with builder reference if
     /// value is `true`.
     pub fn if_true<F>(self, value: bool, f: F) -> Self
@@ -385,12 +438,52 @@ impl ClientRequest {
             addr: slf.addr,
             response_decompress: slf.response_decompress,
             timeout: slf.timeout,
+            body_timeout: slf.body_timeout,
             config: slf.config,
         };
 
         Ok(request)
     }
 
+    /// Download response body to the writer, resume interrupted transfer.
+    ///
+    /// If response body stream fails and server advertised `Accept-Ranges: bytes`
+    /// along with strong `ETag` or `Last-Modified` validator, request is sent
+    /// again with `Range` header starting from current offset. Partial response
+    /// must have the same validator and matching `Content-Range`, otherwise
+    /// download fails with `DownloadError::Modified` or `DownloadError::InvalidRange`.
+    /// If server ignores `Range` header and sends whole resource, writer gets
+    /// reset and download starts from the beginning.
+    ///
+    /// Number of attempts and delays between them follow client retry policy,
+    /// without policy up to 3 attempts are made. Response is not decompressed,
+    /// `Range` header of the request is ignored. Returns number of downloaded bytes.
+    ///
+    /// ```rust
+    /// use ntex::http::client::Client;
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let mut buf = Vec::new();
+    ///     let res = Client::new()
+    ///         .get("http://www.rust-lang.org")
+    ///         .download_to(&mut buf)
+    ///         .await;
+    /// }
+    /// ```
+    pub async fn download_to<W: DownloadWriter>(
+        mut self,
+        mut writer: W,
+    ) -> Result<u64, DownloadError> {
+        self.head.headers.remove(header::RANGE);
+        let req = self
+            .set_header(header::ACCEPT_ENCODING, "identity")
+            .no_decompress()
+            .freeze()
+            .map_err(SendRequestError::from)?;
+        download::download(req, &mut writer).await
+    }
+
     /// Complete request construction and send body.
     pub fn send_body<B>(self, body: B) -> SendClientRequest
     where
@@ -405,6 +498,7 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
+            slf.body_timeout,
             slf.config,
             body,
         )
@@ -421,6 +515,7 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
+            slf.body_timeout,
             slf.config,
             value,
         )
@@ -439,12 +534,27 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
+            slf.body_timeout,
             slf.config,
             value,
         )
     }
 
+    /// Set a multipart form-data body and generate `ClientRequest`
+    ///
+    /// `Content-Type` header is set to `multipart/form-data` with the form boundary.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        let content_type = form.content_type();
+        self.set_header(header::CONTENT_TYPE, content_type)
+            .send_body(Body::from_message(form))
+    }
+
     /// Set an streaming body and generate `ClientRequest`.
+    ///
+    /// Body is sent with chunked transfer encoding over http/1, unless content
+    /// length is set with `content_length()` method, in that case stream must
+    /// produce exactly that many bytes. Request is aborted with
+    /// `SendRequestError::Stream` error if stream returns error.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
         S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
@@ -459,6 +569,7 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
+            slf.body_timeout,
             slf.config,
             stream,
         )
@@ -475,6 +586,7 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
+            slf.body_timeout,
             slf.config,
         )
     }
@@ -539,7 +651,7 @@ impl ClientRequest {
             } else {
                 #[cfg(feature = "compress")]
                 {
-                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, "gzip, deflate")
+                    slf = slf.set_header_if_none(header::ACCEPT_ENCODING, HTTP_ENCODING)
                 }
             };
         }
@@ -685,6 +797,16 @@ mod tests {
                 .unwrap(),
             "Bearer someS3cr3tAutht0k3n"
         );
+
+        // request auth replaces client wide auth
+        let client = Client::build().bearer_auth("token").finish();
+        let req = client.get("/").basic_auth("ü", Some("pass"));
+        let values: Vec<_> = req.head.headers.get_all(header::AUTHORIZATION).collect();
+        assert_eq!(values, vec!["Basic w7w6cGFzcw=="]);
+
+        let req = client.get("/").bearer_auth("token2");
+        let values: Vec<_> = req.head.headers.get_all(header::AUTHORIZATION).collect();
+        assert_eq!(values, vec!["Bearer token2"]);
     }
 
     #[crate::rt_test]
@@ -0,0 +1,169 @@
+//! Host name resolution for http client connector
+use std::{collections::HashMap, fmt, net::IpAddr, net::SocketAddr};
+
+use crate::connect::{self, Connect as TcpConnect};
+use crate::service::{boxed, Pipeline};
+
+use super::{error::ConnectError, Uri};
+
+pub(super) type BoxedResolver =
+    boxed::BoxService<TcpConnect<Uri>, TcpConnect<Uri>, connect::ConnectError>;
+
+#[derive(Default)]
+/// Resolves host names before connecting to remote host
+///
+/// Static host overrides get checked first, then custom resolver is used.
+/// If none of them is configured, tcp connector resolves host name itself.
+pub(super) struct HostResolver {
+    hosts: HashMap<String, Vec<SocketAddr>>,
+    resolver: Option<Pipeline<BoxedResolver>>,
+    prefer_ipv4: bool,
+}
+
+impl HostResolver {
+    pub(super) fn set_resolver(&mut self, resolver: BoxedResolver) {
+        self.resolver = Some(Pipeline::new(resolver));
+    }
+
+    pub(super) fn set_host(&mut self, host: &str, addrs: Vec<SocketAddr>) {
+        self.hosts.insert(host.to_lowercase(), addrs);
+    }
+
+    pub(super) fn set_prefer_ipv4(&mut self) {
+        self.prefer_ipv4 = true;
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        !self.hosts.is_empty() || self.resolver.is_some() || self.prefer_ipv4
+    }
+
+    /// Resolve host name of the request, preresolved requests are returned as is
+    pub(super) async fn lookup(
+        &self,
+        req: TcpConnect<Uri>,
+    ) -> Result<TcpConnect<Uri>, ConnectError> {
+        let host = req.host().trim_start_matches('[').trim_end_matches(']');
+        if req.addrs().next().is_some() || host.parse::<IpAddr>().is_ok() {
+            return Ok(req);
+        }
+
+        let mut req = if let Some(addrs) = self.hosts.get(&req.host().to_lowercase()) {
+            let port = req.port();
+            trace!("Use static addresses {:?} for host {:?}", addrs, req.host());
+            let addrs = addrs.iter().map(|addr| {
+                let mut addr = *addr;
+                if addr.port() == 0 {
+                    addr.set_port(port);
+                }
+                addr
+            });
+            req.set_addrs(addrs.collect::<Vec<_>>())
+        } else if let Some(ref resolver) = self.resolver {
+            resolver.call(req).await?
+        } else {
+            connect::Resolver::new().lookup(req).await?
+        };
+
+        if self.prefer_ipv4 {
+            let mut addrs: Vec<_> = req.take_addrs().collect();
+            addrs.sort_by_key(|addr| !addr.is_ipv4());
+            req = req.set_addrs(addrs);
+        }
+        if req.addrs().next().is_none() {
+            Err(ConnectError::NoRecords)
+        } else {
+            Ok(req)
+        }
+    }
+}
+
+impl fmt::Debug for HostResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostResolver")
+            .field("hosts", &self.hosts)
+            .field("resolver", &self.resolver.is_some())
+            .field("prefer_ipv4", &self.prefer_ipv4)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::fn_service;
+
+    #[crate::rt_test]
+    async fn test_static_hosts() {
+        let mut resolver = HostResolver::default();
+        assert!(!resolver.is_enabled());
+
+        resolver.set_host(
+            "Example.com",
+            vec![
+                "[::1]:0".parse().unwrap(),
+                "127.0.0.1:8080".parse().unwrap(),
+            ],
+        );
+        assert!(resolver.is_enabled());
+
+        let req = TcpConnect::new(Uri::from_static("http://example.com/"));
+        let req = resolver.lookup(req).await.unwrap();
+        let addrs: Vec<SocketAddr> = req.addrs().collect();
+        assert_eq!(
+            addrs,
+            vec![
+                "[::1]:80".parse().unwrap(),
+                "127.0.0.1:8080".parse().unwrap()
+            ]
+        );
+
+        resolver.set_prefer_ipv4();
+        let req = TcpConnect::new(Uri::from_static("https://example.com/"));
+        let req = resolver.lookup(req).await.unwrap();
+        let addrs: Vec<SocketAddr> = req.addrs().collect();
+        assert_eq!(
+            addrs,
+            vec![
+                "127.0.0.1:8080".parse().unwrap(),
+                "[::1]:443".parse().unwrap()
+            ]
+        );
+
+        resolver.set_host("empty.com", Vec::new());
+        let req = TcpConnect::new(Uri::from_static("http://empty.com/"));
+        assert!(matches!(
+            resolver.lookup(req).await,
+            Err(ConnectError::NoRecords)
+        ));
+    }
+
+    #[crate::rt_test]
+    async fn test_custom_resolver() {
+        let mut resolver = HostResolver::default();
+        resolver.set_resolver(boxed::service(fn_service(
+            |req: TcpConnect<Uri>| async move {
+                if req.host() == "service" {
+                    Ok(req.set_addr(Some("127.0.0.1:9000".parse().unwrap())))
+                } else {
+                    Err(connect::ConnectError::Unresolved)
+                }
+            },
+        )));
+
+        let req = TcpConnect::new(Uri::from_static("http://service/"));
+        let req = resolver.lookup(req).await.unwrap();
+        assert_eq!(
+            req.addrs().collect::<Vec<_>>(),
+            vec!["127.0.0.1:9000".parse::<SocketAddr>().unwrap()]
+        );
+
+        let req = TcpConnect::new(Uri::from_static("http://other/"));
+        assert!(resolver.lookup(req).await.is_err());
+
+        // ip addresses are not resolved
+        let req = TcpConnect::new(Uri::from_static("http://127.0.0.1:8080/"));
+        assert!(resolver.lookup(req).await.is_ok());
+        let req = TcpConnect::new(Uri::from_static("http://[::1]:8080/"));
+        assert!(resolver.lookup(req).await.is_ok());
+    }
+}
@@ -1,6 +1,6 @@
-use std::cell::{Ref, RefMut};
+use std::cell::{Ref, RefCell, RefMut};
 use std::task::{Context, Poll};
-use std::{fmt, future::Future, marker::PhantomData, mem, pin::Pin};
+use std::{fmt, future::Future, marker::PhantomData, mem, pin::Pin, rc::Rc};
 
 use serde::de::DeserializeOwned;
 
@@ -9,7 +9,9 @@ use coo_kie::{Cookie, ParseError as CookieParseError};
 
 use crate::http::error::PayloadError;
 use crate::http::header::{AsName, HeaderValue, CONTENT_LENGTH};
-use crate::http::{HeaderMap, HttpMessage, Payload, ResponseHead, StatusCode, Version};
+use crate::http::{
+    HeaderMap, HttpMessage, Payload, ResponseHead, StatusCode, Uri, Version,
+};
 use crate::time::{Deadline, Millis};
 use crate::util::{Bytes, BytesMut, Extensions, Stream};
 
@@ -19,6 +21,18 @@ use super::error::JsonPayloadError;
 pub struct ClientResponse {
     pub(crate) head: ResponseHead,
     pub(crate) payload: Payload,
+    url: Option<Uri>,
+    attempts: usize,
+}
+
+/// Trailer fields of response payload, set once payload is received
+#[derive(Clone, Default, Debug)]
+pub(super) struct Trailers(Rc<RefCell<Option<HeaderMap>>>);
+
+impl Trailers {
+    pub(super) fn set(&self, trailers: HeaderMap) {
+        *self.0.borrow_mut() = Some(trailers);
+    }
 }
 
 impl HttpMessage for ClientResponse {
@@ -59,7 +73,12 @@ impl HttpMessage for ClientResponse {
 impl ClientResponse {
     /// Create new client response instance
     pub(crate) fn new(head: ResponseHead, payload: Payload) -> Self {
-        ClientResponse { head, payload }
+        ClientResponse {
+            head,
+            payload,
+            url: None,
+            attempts: 1,
+        }
     }
 
     pub(crate) fn with_empty_payload(head: ResponseHead) -> Self {
@@ -82,6 +101,30 @@ impl ClientResponse {
         self.head().version
     }
 
+    /// Url of the request.
+    ///
+    /// If redirects are followed, this is url of the last request.
+    #[inline]
+    pub fn url(&self) -> Option<&Uri> {
+        self.url.as_ref()
+    }
+
+    pub(crate) fn set_url(&mut self, url: Uri) {
+        self.url = Some(url);
+    }
+
+    /// Number of attempts made to get the response.
+    ///
+    /// It is greater than one if request was retried by client retry policy.
+    #[inline]
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    pub(crate) fn set_attempts(&mut self, attempts: usize) {
+        self.attempts = attempts;
+    }
+
     /// Get the status from the server.
     #[inline]
     pub fn status(&self) -> StatusCode {
@@ -106,6 +149,16 @@ impl ClientResponse {
         &mut self.head_mut().headers
     }
 
+    /// Trailer fields of the response
+    ///
+    /// Trailers are available only after payload reached eof,
+    /// only http/1 chunked and http/2 responses could contain trailers.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.extensions()
+            .get::<Trailers>()
+            .and_then(|t| t.0.borrow().clone())
+    }
+
     /// Set a body and return previous body value
     pub fn set_payload(&mut self, payload: Payload) {
         self.payload = payload;
@@ -198,6 +251,8 @@ impl MessageBody {
     }
 
     /// Change max size of payload. By default max size is 256Kb
+    ///
+    /// Larger payload fails with `PayloadError::LimitExceeded` error.
     pub fn limit(mut self, limit: usize) -> Self {
         if let Some(ref mut fut) = self.fut {
             fut.limit = limit;
@@ -236,8 +291,9 @@ impl Future for MessageBody {
         }
 
         if let Some(len) = this.length.take() {
-            if len > this.fut.as_ref().unwrap().limit {
-                return Poll::Ready(Err(PayloadError::Overflow));
+            let limit = this.fut.as_ref().unwrap().limit;
+            if len > limit {
+                return Poll::Ready(Err(PayloadError::LimitExceeded { limit, size: len }));
             }
         }
 
@@ -250,8 +306,8 @@ impl Future for MessageBody {
 ///
 /// Returns error:
 ///
-/// * content type is not `application/json`
-/// * content length is greater than 64k
+/// * content type is not `application/json` or `+json`
+/// * content length is greater than limit, 64Kb by default
 pub struct JsonBody<U> {
     length: Option<usize>,
     err: Option<JsonPayloadError>,
@@ -298,6 +354,8 @@ where
     }
 
     /// Change max size of payload. By default max size is 64Kb
+    ///
+    /// Larger payload fails with `PayloadError::LimitExceeded` error.
     pub fn limit(mut self, limit: usize) -> Self {
         if let Some(ref mut fut) = self.fut {
             fut.limit = limit;
@@ -331,8 +389,11 @@ where
         }
 
         if let Some(len) = self.length.take() {
-            if len > self.fut.as_ref().unwrap().limit {
-                return Poll::Ready(Err(JsonPayloadError::Payload(PayloadError::Overflow)));
+            let limit = self.fut.as_ref().unwrap().limit;
+            if len > limit {
+                return Poll::Ready(Err(JsonPayloadError::Payload(
+                    PayloadError::LimitExceeded { limit, size: len },
+                )));
             }
         }
 
@@ -372,8 +433,12 @@ impl Future for ReadBody {
         loop {
             return match Pin::new(&mut this.stream).poll_next(cx)? {
                 Poll::Ready(Some(chunk)) => {
-                    if (this.buf.len() + chunk.len()) > this.limit {
-                        Poll::Ready(Err(PayloadError::Overflow))
+                    let size = this.buf.len() + chunk.len();
+                    if size > this.limit {
+                        Poll::Ready(Err(PayloadError::LimitExceeded {
+                            limit: this.limit,
+                            size,
+                        }))
                     } else {
                         this.buf.extend_from_slice(&chunk);
                         continue;
@@ -414,8 +479,16 @@ mod tests {
         }
 
         let mut req = TestResponse::with_header(header::CONTENT_LENGTH, "1000000").finish();
-        match req.body().await.err().unwrap() {
-            PayloadError::Overflow => (),
+        let err = req.body().await.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "A payload size 1000000 exceeds limit of 262144 bytes"
+        );
+        match err {
+            PayloadError::LimitExceeded {
+                limit: 262_144,
+                size: 1_000_000,
+            } => (),
             _ => unreachable!("error"),
         }
 
@@ -428,7 +501,7 @@ mod tests {
             .set_payload(Bytes::from_static(b"11111111111111"))
             .finish();
         match req.body().limit(5).await.err().unwrap() {
-            PayloadError::Overflow => (),
+            PayloadError::LimitExceeded { limit: 5, size: 14 } => (),
             _ => unreachable!("error"),
         }
     }
@@ -440,8 +513,16 @@ mod tests {
 
     fn json_eq(err: JsonPayloadError, other: JsonPayloadError) -> bool {
         match err {
-            JsonPayloadError::Payload(PayloadError::Overflow) => {
-                matches!(other, JsonPayloadError::Payload(PayloadError::Overflow))
+            JsonPayloadError::Payload(PayloadError::LimitExceeded { limit, size }) => {
+                if let JsonPayloadError::Payload(PayloadError::LimitExceeded {
+                    limit: l,
+                    size: s,
+                }) = other
+                {
+                    l == limit && s == size
+                } else {
+                    false
+                }
             }
             JsonPayloadError::ContentType => matches!(other, JsonPayloadError::ContentType),
             _ => false,
@@ -477,7 +558,26 @@ mod tests {
         let json = JsonBody::<MyObject>::new(&mut req).limit(100).await;
         assert!(json_eq(
             json.err().unwrap(),
-            JsonPayloadError::Payload(PayloadError::Overflow)
+            JsonPayloadError::Payload(PayloadError::LimitExceeded {
+                limit: 100,
+                size: 10000
+            })
+        ));
+
+        let mut req = TestResponse::default()
+            .header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json"),
+            )
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .finish();
+        let json = JsonBody::<MyObject>::new(&mut req).limit(10).await;
+        assert!(json_eq(
+            json.err().unwrap(),
+            JsonPayloadError::Payload(PayloadError::LimitExceeded {
+                limit: 10,
+                size: 16
+            })
         ));
 
         let mut req = TestResponse::default()
@@ -499,5 +599,16 @@ mod tests {
                 name: "test".to_owned()
             }
         );
+
+        // content type with parameters
+        let mut req = TestResponse::default()
+            .header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json; charset=utf-8"),
+            )
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .finish();
+        let json = JsonBody::<MyObject>::new(&mut req).await;
+        assert_eq!(json.ok().unwrap().name, "test");
     }
 }
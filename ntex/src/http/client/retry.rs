@@ -0,0 +1,285 @@
+//! Retries of failed requests
+use std::net;
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::body::Body;
+use crate::http::{Method, RequestHead, RequestHeadType, StatusCode};
+use crate::time::{sleep, timeout_checked, Millis};
+
+use super::error::{ConnectError, SendRequestError};
+use super::{redirect, sender, ClientConfig, ClientResponse};
+
+/// Retry policy for client requests
+///
+/// By default, up to 3 attempts are made. Requests are retried if connection
+/// could not be established, if response is not received within request
+/// timeout or if server responds with `502`, `503` or `504` status. Request
+/// timeout is applied to each attempt. Only idempotent requests are retried. Delay between attempts is
+/// doubled after each attempt, starting from 100 milliseconds and up to 10
+/// seconds, random jitter is added to each delay.
+///
+/// ```rust
+/// use ntex::http::client::{Client, RetryPolicy};
+/// use ntex::time::Millis;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = Client::build()
+///         .retry(RetryPolicy::new(5).backoff(Millis(50), Millis(1_000)))
+///         .finish();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    statuses: Vec<StatusCode>,
+    connect_errors: bool,
+    all_methods: bool,
+    backoff: Millis,
+    max_backoff: Millis,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            connect_errors: true,
+            all_methods: false,
+            backoff: Millis(100),
+            max_backoff: Millis(10_000),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create retry policy with max number of attempts, including first one
+    pub fn new(max_attempts: usize) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Set response statuses that are retried
+    pub fn statuses<I>(mut self, statuses: I) -> Self
+    where
+        I: IntoIterator<Item = StatusCode>,
+    {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Retry requests that failed to establish connection.
+    ///
+    /// Enabled by default.
+    pub fn connect_errors(mut self, enabled: bool) -> Self {
+        self.connect_errors = enabled;
+        self
+    }
+
+    /// Retry requests with any method.
+    ///
+    /// By default only idempotent requests are retried.
+    pub fn all_methods(mut self) -> Self {
+        self.all_methods = true;
+        self
+    }
+
+    /// Set initial and max delay between attempts
+    pub fn backoff<T: Into<Millis>>(mut self, initial: T, max: T) -> Self {
+        self.backoff = initial.into();
+        self.max_backoff = max.into();
+        self
+    }
+
+    /// Do not add random jitter to delays
+    pub fn disable_jitter(mut self) -> Self {
+        self.jitter = false;
+        self
+    }
+
+    pub(super) fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    fn is_retryable(
+        &self,
+        method: &Method,
+        res: &Result<ClientResponse, SendRequestError>,
+    ) -> bool {
+        match res {
+            Ok(res) => self.statuses.contains(&res.status()),
+            Err(SendRequestError::Connect(ConnectError::Timeout))
+            | Err(SendRequestError::Timeout) => is_idempotent(method),
+            Err(SendRequestError::Connect(_)) => self.connect_errors,
+            Err(_) => false,
+        }
+    }
+
+    /// Delay before the next attempt
+    pub(super) fn delay(&self, attempt: usize) -> Millis {
+        let exp = attempt.saturating_sub(1).min(31) as u32;
+        let delay = self
+            .backoff
+            .0
+            .saturating_mul(1 << exp)
+            .min(self.max_backoff.0);
+        if self.jitter && delay > 1 {
+            // half of the delay is randomized
+            Millis(delay / 2 + WyRand::new().generate_range(0..=delay / 2))
+        } else {
+            Millis(delay)
+        }
+    }
+}
+
+/// Send request and retry it according to the client retry policy
+///
+/// Timeout is applied to each attempt.
+pub(super) async fn send(
+    mut head: RequestHeadType,
+    mut body: Body,
+    addr: Option<net::SocketAddr>,
+    timeout: Millis,
+    config: &ClientConfig,
+) -> Result<ClientResponse, SendRequestError> {
+    let req = head.as_ref();
+    let policy = match config.retry {
+        Some(ref policy)
+            if !req.no_retry() && (policy.all_methods || is_idempotent(&req.method)) =>
+        {
+            policy
+        }
+        _ => return send_once(head, body, addr, timeout, config).await,
+    };
+    let method = req.method.clone();
+
+    let mut attempt = 1;
+    loop {
+        let replay = redirect::replay_body(&body).map(|body| (copy_head(&head), body));
+        let result = send_once(head, body, addr, timeout, config).await;
+
+        if attempt >= policy.max_attempts || !policy.is_retryable(&method, &result) {
+            return result.map(|mut res| {
+                res.set_attempts(attempt);
+                res
+            });
+        }
+        if let Some((next_head, next_body)) = replay {
+            head = next_head;
+            body = next_body;
+        } else {
+            return Err(SendRequestError::RetryStream);
+        }
+        drop(result);
+
+        let delay = policy.delay(attempt);
+        log::trace!("Retry request in {:?}, attempt {}", delay, attempt + 1);
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Send request, follow redirects if it is enabled
+async fn send_once(
+    head: RequestHeadType,
+    body: Body,
+    addr: Option<net::SocketAddr>,
+    timeout: Millis,
+    config: &ClientConfig,
+) -> Result<ClientResponse, SendRequestError> {
+    let fut = async {
+        if config.max_redirects > 0 {
+            redirect::send(head, body, addr, config).await
+        } else {
+            sender::send_request(head, body, addr, config).await
+        }
+    };
+    timeout_checked(timeout, fut)
+        .await
+        .unwrap_or(Err(SendRequestError::Timeout))
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET
+            | Method::HEAD
+            | Method::PUT
+            | Method::DELETE
+            | Method::OPTIONS
+            | Method::TRACE
+    )
+}
+
+fn copy_head(head: &RequestHeadType) -> RequestHeadType {
+    match head {
+        RequestHeadType::Rc(head, extra) => {
+            RequestHeadType::Rc(head.clone(), extra.clone())
+        }
+        RequestHeadType::Owned(head) => RequestHeadType::Owned(RequestHead {
+            uri: head.uri.clone(),
+            method: head.method.clone(),
+            version: head.version,
+            headers: head.headers.clone(),
+            flags: head.flags,
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(5)
+            .backoff(Millis(100), Millis(350))
+            .disable_jitter();
+        assert_eq!(policy.delay(1), Millis(100));
+        assert_eq!(policy.delay(2), Millis(200));
+        assert_eq!(policy.delay(3), Millis(350));
+        assert_eq!(policy.delay(100), Millis(350));
+
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Millis(100) && delay <= Millis(200), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_retryable() {
+        let policy = RetryPolicy::default().all_methods();
+        let timeout = Err(SendRequestError::Timeout);
+        assert!(policy.is_retryable(&Method::GET, &timeout));
+        assert!(!policy.is_retryable(&Method::POST, &timeout));
+
+        let pool_timeout = Err(SendRequestError::Connect(ConnectError::Timeout));
+        assert!(policy.is_retryable(&Method::PUT, &pool_timeout));
+        assert!(!policy.is_retryable(&Method::PATCH, &pool_timeout));
+
+        let err = Err(SendRequestError::Connect(ConnectError::Unresolved));
+        assert!(policy.is_retryable(&Method::POST, &err));
+        assert!(!policy
+            .connect_errors(false)
+            .is_retryable(&Method::GET, &err));
+    }
+
+    #[test]
+    fn test_idempotent() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+}
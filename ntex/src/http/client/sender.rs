@@ -1,23 +1,21 @@
 use std::task::{Context, Poll};
-use std::{error::Error, future::Future, net, pin::Pin, rc::Rc};
+use std::{error::Error, future::Future, io, net, pin::Pin, rc::Rc};
 
 use serde::Serialize;
 
-use crate::http::body::{Body, BodyStream};
-use crate::http::error::HttpError;
+use crate::http::body::{Body, BodySize, BodyStream, MessageBody};
+use crate::http::error::{HttpError, PayloadError};
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::http::RequestHeadType;
-use crate::time::{sleep, Millis, Sleep};
+use crate::http::{Payload, RequestHeadType};
+use crate::time::{Deadline, Millis, Sleep};
 use crate::util::{BoxFuture, Bytes, Stream};
 
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
-#[cfg(feature = "compress")]
-use crate::http::Payload;
 
 use super::error::{FreezeRequestError, InvalidUrl, SendRequestError};
 use super::response::ClientResponse;
-use super::ClientConfig;
+use super::{retry, ClientConfig};
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum PrepForSendingError {
@@ -60,9 +58,8 @@ impl SendClientRequest {
     pub(crate) fn new(
         send: BoxFuture<'static, Result<ClientResponse, SendRequestError>>,
         response_decompress: bool,
-        timeout: Millis,
     ) -> SendClientRequest {
-        SendClientRequest::Fut(send, timeout.map(sleep), response_decompress)
+        SendClientRequest::Fut(send, None, response_decompress)
     }
 }
 
@@ -126,12 +123,149 @@ impl From<PrepForSendingError> for SendClientRequest {
     }
 }
 
+/// Response payload that fails if no data arrives within timeout period
+struct BodyTimeout {
+    payload: Payload,
+    timeout: Millis,
+    deadline: Option<Deadline>,
+}
+
+impl BodyTimeout {
+    fn new(payload: Payload, timeout: Millis) -> Self {
+        BodyTimeout {
+            payload,
+            timeout,
+            deadline: Some(Deadline::new(timeout)),
+        }
+    }
+}
+
+impl Stream for BodyTimeout {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let deadline = if let Some(ref mut deadline) = this.deadline {
+            deadline
+        } else {
+            return Poll::Ready(None);
+        };
+
+        match this.payload.poll_recv(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                deadline.reset(this.timeout);
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(res) => {
+                this.deadline = None;
+                Poll::Ready(res)
+            }
+            Poll::Pending => {
+                if deadline.poll_elapsed(cx).is_ready() {
+                    this.deadline = None;
+                    Poll::Ready(Some(Err(PayloadError::Timeout)))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+/// Streaming request body with known size
+///
+/// Stream must produce exactly `size` bytes.
+struct SizedBody<B> {
+    body: B,
+    size: u64,
+    sent: u64,
+}
+
+impl<B: MessageBody> MessageBody for SizedBody<B> {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.size)
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match self.body.poll_next_chunk(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.sent += chunk.len() as u64;
+                if self.sent > self.size {
+                    Poll::Ready(Some(Err(Box::new(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Stream is longer than content-length",
+                    )))))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            Poll::Ready(None) if self.sent < self.size => {
+                Poll::Ready(Some(Err(Box::new(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Stream is shorter than content-length",
+                )))))
+            }
+            res => res,
+        }
+    }
+}
+
+/// Send request, cookie store is used if it is enabled
+#[allow(unused_mut)]
+pub(super) async fn send_request(
+    mut head: RequestHeadType,
+    body: Body,
+    addr: Option<net::SocketAddr>,
+    config: &ClientConfig,
+) -> Result<ClientResponse, SendRequestError> {
+    let uri = head.as_ref().uri.clone();
+
+    #[cfg(feature = "cookie")]
+    if let Some(ref store) = config.cookie_store {
+        match head {
+            RequestHeadType::Owned(ref mut head) => {
+                if let Some(value) = store.header(&uri, head.headers.get(header::COOKIE)) {
+                    head.headers.insert(header::COOKIE, value);
+                }
+            }
+            RequestHeadType::Rc(ref head, ref mut extra_headers) => {
+                let existing = extra_headers
+                    .as_ref()
+                    .and_then(|h| h.get(header::COOKIE))
+                    .or_else(|| head.headers.get(header::COOKIE));
+                if let Some(value) = store.header(&uri, existing) {
+                    extra_headers
+                        .get_or_insert_with(HeaderMap::new)
+                        .insert(header::COOKIE, value);
+                }
+            }
+        }
+    }
+
+    let mut res = config
+        .connector
+        .send_request(head, body, addr, config)
+        .await?;
+
+    #[cfg(feature = "cookie")]
+    if let Some(ref store) = config.cookie_store {
+        store.store(&uri, res.headers());
+    }
+    res.set_url(uri);
+    Ok(res)
+}
+
 impl RequestHeadType {
     pub(super) fn send_body<B>(
         self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
         mut timeout: Millis,
+        body_timeout: Option<Millis>,
         config: Rc<ClientConfig>,
         body: B,
     ) -> SendClientRequest
@@ -141,12 +275,22 @@ impl RequestHeadType {
         if timeout.is_zero() {
             timeout = config.timeout;
         }
+        let body_timeout = body_timeout.unwrap_or(config.body_timeout);
         let body = body.into();
 
-        let fut =
-            Box::pin(async move { config.connector.send_request(self, body, addr).await });
+        let fut = Box::pin(async move {
+            let mut res = retry::send(self, body, addr, timeout, &config).await?;
+            if body_timeout.non_zero() {
+                let payload = res.take_payload();
+                res.set_payload(Payload::from_stream(BodyTimeout::new(
+                    payload,
+                    body_timeout,
+                )));
+            }
+            Ok(res)
+        });
 
-        SendClientRequest::new(fut, response_decompress, timeout)
+        SendClientRequest::new(fut, response_decompress)
     }
 
     pub(super) fn send_json<T: Serialize>(
@@ -154,6 +298,7 @@ impl RequestHeadType {
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
         timeout: Millis,
+        body_timeout: Option<Millis>,
         config: Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
@@ -170,6 +315,7 @@ impl RequestHeadType {
             addr,
             response_decompress,
             timeout,
+            body_timeout,
             config,
             Body::Bytes(Bytes::from(body)),
         )
@@ -180,6 +326,7 @@ impl RequestHeadType {
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
         timeout: Millis,
+        body_timeout: Option<Millis>,
         config: Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
@@ -199,6 +346,7 @@ impl RequestHeadType {
             addr,
             response_decompress,
             timeout,
+            body_timeout,
             config,
             Body::Bytes(Bytes::from(body)),
         )
@@ -209,6 +357,7 @@ impl RequestHeadType {
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
         timeout: Millis,
+        body_timeout: Option<Millis>,
         config: Rc<ClientConfig>,
         stream: S,
     ) -> SendClientRequest
@@ -216,23 +365,50 @@ impl RequestHeadType {
         S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
         E: Error + 'static,
     {
+        let body = if let Some(size) = self.content_length() {
+            Body::from_message(SizedBody {
+                size,
+                body: BodyStream::new(stream),
+                sent: 0,
+            })
+        } else {
+            Body::from_message(BodyStream::new(stream))
+        };
         self.send_body(
             addr,
             response_decompress,
             timeout,
+            body_timeout,
             config,
-            Body::from_message(BodyStream::new(stream)),
+            body,
         )
     }
 
+    /// Value of `Content-Length` header
+    fn content_length(&self) -> Option<u64> {
+        self.extra_headers()
+            .and_then(|h| h.get(header::CONTENT_LENGTH))
+            .or_else(|| self.as_ref().headers.get(header::CONTENT_LENGTH))
+            .and_then(|val| val.to_str().ok())
+            .and_then(|val| val.trim().parse().ok())
+    }
+
     pub(super) fn send(
         self,
         addr: Option<net::SocketAddr>,
         response_decompress: bool,
         timeout: Millis,
+        body_timeout: Option<Millis>,
         config: Rc<ClientConfig>,
     ) -> SendClientRequest {
-        self.send_body(addr, response_decompress, timeout, config, Body::None)
+        self.send_body(
+            addr,
+            response_decompress,
+            timeout,
+            body_timeout,
+            config,
+            Body::None,
+        )
     }
 
     fn set_header_if_none<V>(&mut self, key: HeaderName, value: V) -> Result<(), HttpError>
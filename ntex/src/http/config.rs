@@ -2,7 +2,7 @@ use std::{cell::Cell, ptr::copy_nonoverlapping, rc::Rc, time, time::Duration};
 
 use ntex_h2::{self as h2};
 
-use crate::http::{Request, Response};
+use crate::http::{h1, Request, Response};
 use crate::service::{boxed::BoxService, Pipeline};
 use crate::time::{sleep, Millis, Seconds};
 use crate::{io::IoRef, util::BytesMut};
@@ -44,7 +44,7 @@ impl From<Option<usize>> for KeepAlive {
 /// Http service configuration
 pub struct ServiceConfig(pub(super) Rc<Inner>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(super) struct Inner {
     pub(super) keep_alive: Millis,
     pub(super) client_timeout: Millis,
@@ -53,6 +53,10 @@ pub(super) struct Inner {
     pub(super) timer: DateService,
     pub(super) ssl_handshake_timeout: Millis,
     pub(super) h2config: h2::Config,
+    pub(super) headers_max_size: usize,
+    pub(super) headers_max_count: usize,
+    pub(super) payload_max_size: usize,
+    pub(super) pipeline_max: usize,
 }
 
 impl Clone for ServiceConfig {
@@ -97,8 +101,80 @@ impl ServiceConfig {
             ssl_handshake_timeout,
             h2config,
             timer: DateService::new(),
+            headers_max_size: h1::MAX_BUFFER_SIZE,
+            headers_max_count: h1::MAX_HEADERS,
+            payload_max_size: 0,
+            pipeline_max: 1,
         }))
     }
+
+    /// Set max size of request head in bytes.
+    ///
+    /// Requests with larger head get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max size is set to 32Kb.
+    pub fn max_header_size(mut self, size: usize) -> Self {
+        Rc::make_mut(&mut self.0).headers_max_size = size;
+        self
+    }
+
+    /// Set max number of request headers.
+    ///
+    /// Requests with more headers get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn max_headers(mut self, count: usize) -> Self {
+        Rc::make_mut(&mut self.0).headers_max_count = count;
+        self
+    }
+
+    /// Set max size of request payload in bytes.
+    ///
+    /// Requests with larger declared content-length get rejected with
+    /// the 413 (Payload Too Large) error before payload is read. Streaming
+    /// payloads that exceed the limit are aborted and connection is closed
+    /// after response.
+    ///
+    /// By default payload size is not limited. To disable limit set value to 0.
+    pub fn max_payload_size(mut self, size: usize) -> Self {
+        Rc::make_mut(&mut self.0).payload_max_size = size;
+        self
+    }
+
+    /// Set max number of pipelined requests handled concurrently.
+    ///
+    /// Responses are always sent in the order of requests. Requests with
+    /// payload, upgrade requests and requests with `Expect` header are not
+    /// handled concurrently.
+    ///
+    /// By default requests are handled one by one.
+    pub fn max_pipelined_requests(mut self, max: usize) -> Self {
+        Rc::make_mut(&mut self.0).pipeline_max = max;
+        self
+    }
+
+    /// Enable or disable automatic `Date` response header.
+    ///
+    /// `Date` header set by the service is always sent as is.
+    ///
+    /// By default date header is enabled.
+    pub fn date_header(mut self, enabled: bool) -> Self {
+        let clock = self.0.timer.0.clock;
+        Rc::make_mut(&mut self.0).timer = DateService::with_config(enabled, clock);
+        self
+    }
+
+    #[doc(hidden)]
+    /// Set source of the current time for `Date` header.
+    ///
+    /// Date is not cached if source is set, this is useful for tests only.
+    pub fn date_source(mut self, clock: fn() -> time::SystemTime) -> Self {
+        let enabled = self.0.timer.0.enabled;
+        Rc::make_mut(&mut self.0).timer = DateService::with_config(enabled, Some(clock));
+        self
+    }
 }
 
 pub(super) type OnRequest = BoxService<(Request, IoRef), Request, Response>;
@@ -114,6 +190,10 @@ pub(super) struct DispatcherConfig<S, X, U> {
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
     pub(super) on_request: Option<Pipeline<OnRequest>>,
+    pub(super) headers_max_size: usize,
+    pub(super) headers_max_count: usize,
+    pub(super) payload_max_size: usize,
+    pub(super) pipeline_max: usize,
 }
 
 impl<S, X, U> DispatcherConfig<S, X, U> {
@@ -135,6 +215,10 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
             ka_enabled: cfg.0.ka_enabled,
             h2config: cfg.0.h2config.clone(),
             timer: cfg.0.timer.clone(),
+            headers_max_size: cfg.0.headers_max_size,
+            headers_max_count: cfg.0.headers_max_count,
+            payload_max_size: cfg.0.payload_max_size,
+            pipeline_max: cfg.0.pipeline_max,
         }
     }
 
@@ -156,20 +240,24 @@ pub struct DateService(Rc<DateServiceInner>);
 
 impl Default for DateService {
     fn default() -> Self {
-        DateService(Rc::new(DateServiceInner::new()))
+        DateService(Rc::new(DateServiceInner::new(true, None)))
     }
 }
 
 #[derive(Debug)]
 struct DateServiceInner {
+    enabled: bool,
+    clock: Option<fn() -> time::SystemTime>,
     current: Cell<bool>,
     current_time: Cell<time::Instant>,
     current_date: Cell<[u8; DATE_VALUE_LENGTH_HDR]>,
 }
 
 impl DateServiceInner {
-    fn new() -> Self {
+    fn new(enabled: bool, clock: Option<fn() -> time::SystemTime>) -> Self {
         DateServiceInner {
+            enabled,
+            clock,
             current: Cell::new(false),
             current_time: Cell::new(time::Instant::now()),
             current_date: Cell::new(DATE_VALUE_DEFAULT),
@@ -181,7 +269,12 @@ impl DateServiceInner {
         self.current_time.set(time::Instant::now());
 
         let mut bytes = DATE_VALUE_DEFAULT;
-        let dt = httpdate::HttpDate::from(time::SystemTime::now()).to_string();
+        let now = if let Some(clock) = self.clock {
+            clock()
+        } else {
+            time::SystemTime::now()
+        };
+        let dt = httpdate::HttpDate::from(now).to_string();
         bytes[6..35].copy_from_slice(dt.as_ref());
         self.current_date.set(bytes);
     }
@@ -189,11 +282,23 @@ impl DateServiceInner {
 
 impl DateService {
     fn new() -> Self {
-        DateService(Rc::new(DateServiceInner::new()))
+        DateService(Rc::new(DateServiceInner::new(true, None)))
+    }
+
+    fn with_config(enabled: bool, clock: Option<fn() -> time::SystemTime>) -> Self {
+        DateService(Rc::new(DateServiceInner::new(enabled, clock)))
+    }
+
+    /// Check if automatic `Date` header is enabled
+    pub(super) fn enabled(&self) -> bool {
+        self.0.enabled
     }
 
     fn check_date(&self) {
-        if !self.0.current.get() {
+        if self.0.clock.is_some() {
+            // custom time source, do not cache date
+            self.0.update();
+        } else if !self.0.current.get() {
             self.0.update();
 
             // periodic date update
@@ -213,6 +318,11 @@ impl DateService {
 
     #[doc(hidden)]
     pub fn set_date_header(&self, dst: &mut BytesMut) {
+        if !self.0.enabled {
+            // msg eof
+            dst.extend_from_slice(b"\r\n");
+            return;
+        }
         self.check_date();
 
         // SAFETY: reserves exact size
@@ -243,6 +353,21 @@ mod tests {
         assert_eq!(buf1, buf2);
     }
 
+    #[crate::rt_test]
+    async fn test_date_config() {
+        let cfg = ServiceConfig::default().date_header(false);
+        let mut buf = BytesMut::new();
+        cfg.0.timer.set_date_header(&mut buf);
+        assert_eq!(&buf[..], b"\r\n");
+
+        let cfg = ServiceConfig::default()
+            .date_source(|| time::SystemTime::UNIX_EPOCH)
+            .date_header(true);
+        let mut buf = BytesMut::new();
+        cfg.0.timer.set_date_header(&mut buf);
+        assert_eq!(&buf[..], b"date: Thu, 01 Jan 1970 00:00:00 GMT\r\n\r\n");
+    }
+
     #[test]
     fn keep_alive() {
         assert_eq!(KeepAlive::Disabled, Option::<usize>::None.into());
@@ -1,11 +1,13 @@
 //! Stream encoder
-use std::{fmt, future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
+use std::{cmp, fmt, future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 
 use brotli2::write::BrotliEncoder;
 use flate2::write::{GzEncoder, ZlibEncoder};
+#[cfg(feature = "zstd")]
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING};
+use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING, VARY};
 use crate::http::{ResponseHead, StatusCode};
 use crate::rt::{spawn_blocking, JoinHandle};
 use crate::util::Bytes;
@@ -22,10 +24,24 @@ pub struct Encoder<B> {
 }
 
 impl<B: MessageBody + 'static> Encoder<B> {
+    /// Encode response body with default compression level
     pub fn response(
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        Encoder::response_with_level(encoding, None, head, body)
+    }
+
+    /// Encode response body with specific compression level
+    ///
+    /// Level is clamped to the supported range of selected encoding,
+    /// `0-9` for gzip and deflate, `0-11` for brotli and `1-22` for zstd.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<B> {
         let can_encode = ContentEncoder::can_encode(encoding)
             && !(head.headers().contains_key(&CONTENT_ENCODING)
@@ -36,7 +52,7 @@ impl<B: MessageBody + 'static> Encoder<B> {
 
         if !can_encode {
             body
-        } else {
+        } else if let Some(encoder) = ContentEncoder::encoder(encoding, level) {
             let body = match body {
                 ResponseBody::Other(b) => match b {
                     Body::None => return ResponseBody::Other(Body::None),
@@ -47,8 +63,6 @@ impl<B: MessageBody + 'static> Encoder<B> {
                 ResponseBody::Body(stream) => EncoderBody::Stream(stream),
             };
 
-            // Modify response body only if encoder is not None
-            let encoder = ContentEncoder::encoder(encoding).unwrap();
             update_head(encoding, head);
             head.no_chunking(false);
             ResponseBody::Other(Body::from_message(Encoder {
@@ -57,6 +71,9 @@ impl<B: MessageBody + 'static> Encoder<B> {
                 fut: None,
                 encoder: Some(encoder),
             }))
+        } else {
+            log::warn!("Cannot create {:?} encoder, send body as is", encoding);
+            body
         }
     }
 }
@@ -187,12 +204,30 @@ fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
         CONTENT_ENCODING,
         HeaderValue::from_static(encoding.as_str()),
     );
+
+    // do not duplicate vary header if handler already set it
+    let vary = head.headers().get_all(VARY).any(|hdr| {
+        hdr.to_str()
+            .map(|s| {
+                s.split(',').any(|v| {
+                    let v = v.trim();
+                    v == "*" || v.eq_ignore_ascii_case("accept-encoding")
+                })
+            })
+            .unwrap_or(false)
+    });
+    if !vary {
+        head.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
 }
 
 enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdEncoder<'static, Writer>),
 }
 
 impl ContentEncoder {
@@ -200,21 +235,35 @@ impl ContentEncoder {
         matches!(
             encoding,
             ContentEncoding::Deflate | ContentEncoding::Gzip | ContentEncoding::Br
-        )
+        ) || (cfg!(feature = "zstd") && encoding == ContentEncoding::Zstd)
     }
 
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
+        let flate_level = || {
+            level
+                .map(|l| flate2::Compression::new(cmp::min(l, 9)))
+                .unwrap_or_else(flate2::Compression::fast)
+        };
+
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
+            ))),
+            ContentEncoding::Br => Some(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.map(|l| cmp::min(l, 11)).unwrap_or(3),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => {
+                let level = level.map(|l| l.clamp(1, 22)).unwrap_or(3);
+                ZstdEncoder::new(Writer::new(), level as i32)
+                    .ok()
+                    .map(ContentEncoder::Zstd)
             }
             _ => None,
         }
@@ -225,6 +274,8 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
         }
     }
 
@@ -242,6 +293,11 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -268,6 +324,14 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error decoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
@@ -278,6 +342,8 @@ impl fmt::Debug for ContentEncoder {
             ContentEncoder::Deflate(_) => write!(f, "ContentEncoder::Deflate"),
             ContentEncoder::Gzip(_) => write!(f, "ContentEncoder::Gzip"),
             ContentEncoder::Br(_) => write!(f, "ContentEncoder::Br"),
+            #[cfg(feature = "zstd")]
+            ContentEncoder::Zstd(_) => write!(f, "ContentEncoder::Zstd"),
         }
     }
 }
@@ -14,11 +14,18 @@ use crate::util::{BytesMut, Either};
 
 /// Error that can be converted to `Response`
 pub trait ResponseError: fmt::Display + fmt::Debug {
-    /// Create response for error
+    /// Response's status code
     ///
     /// Internal server error is generated by default.
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    /// Create response for error
+    ///
+    /// Response with `status_code()` status is generated by default.
     fn error_response(&self) -> Response {
-        let mut resp = Response::new(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut resp = Response::new(self.status_code());
         let mut buf = BytesMut::new();
         let _ = write!(crate::http::helpers::Writer(&mut buf), "{}", self);
         resp.headers_mut().insert(
@@ -30,6 +37,10 @@ pub trait ResponseError: fmt::Display + fmt::Debug {
 }
 
 impl<'a, T: ResponseError> ResponseError for &'a T {
+    fn status_code(&self) -> StatusCode {
+        (*self).status_code()
+    }
+
     fn error_response(&self) -> Response {
         (*self).error_response()
     }
@@ -125,9 +136,18 @@ pub enum PayloadError {
     /// A payload reached size limit.
     #[error("A payload reached size limit.")]
     Overflow,
+    /// A payload is larger than collector's size limit.
+    ///
+    /// `size` is the content length or number of bytes received
+    /// before limit is reached.
+    #[error("A payload size {size} exceeds limit of {limit} bytes")]
+    LimitExceeded { limit: usize, size: usize },
     /// A payload length is unknown.
     #[error("A payload length is unknown.")]
     UnknownLength,
+    /// No payload data received within timeout period
+    #[error("Timeout while waiting for payload data")]
+    Timeout,
     /// Http2 payload error
     #[error("{0}")]
     Http2Payload(#[from] h2::StreamError),
@@ -187,6 +207,10 @@ pub enum DispatchError {
     #[error("Task is completed but request's payload is not consumed")]
     PayloadIsNotConsumed,
 
+    /// Request payload exceeds configured limit
+    #[error("Request payload is too large")]
+    PayloadOverflow,
+
     /// Malformed request
     #[error("Malformed request")]
     MalformedRequest,
@@ -227,6 +251,37 @@ pub enum H2Error {
     Stream(#[from] Box<dyn error::Error>),
 }
 
+/// Invalid http/2 setting
+#[derive(thiserror::Error, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum H2SettingsError {
+    /// Window size exceeds 2^31-1
+    #[error("Window size {0} exceeds 2^31-1")]
+    WindowSize(u32),
+    /// Max frame size is not within 16,384 and 16,777,215 range
+    #[error("Max frame size {0} is not within 16,384 and 16,777,215 range")]
+    MaxFrameSize(u32),
+}
+
+impl H2SettingsError {
+    pub(crate) fn check_window_size(size: u32) -> Result<u32, Self> {
+        if size as usize <= h2::frame::MAX_INITIAL_WINDOW_SIZE {
+            Ok(size)
+        } else {
+            Err(H2SettingsError::WindowSize(size))
+        }
+    }
+
+    pub(crate) fn check_max_frame_size(size: u32) -> Result<u32, Self> {
+        if (h2::frame::DEFAULT_MAX_FRAME_SIZE..=h2::frame::MAX_MAX_FRAME_SIZE)
+            .contains(&size)
+        {
+            Ok(size)
+        } else {
+            Err(H2SettingsError::MaxFrameSize(size))
+        }
+    }
+}
+
 /// A set of error that can occure during parsing content type
 #[derive(thiserror::Error, PartialEq, Eq, Debug)]
 pub enum ContentTypeError {
@@ -251,6 +306,8 @@ pub enum BlockingError<E: fmt::Debug> {
     Error(E),
     #[error("Thread pool is gone")]
     Canceled,
+    #[error("Thread pool is overloaded")]
+    Overloaded,
 }
 
 impl From<crate::rt::JoinError> for PayloadError {
@@ -270,6 +327,10 @@ impl From<BlockingError<io::Error>> for PayloadError {
                 io::ErrorKind::Other,
                 "Operation is canceled",
             )),
+            BlockingError::Overloaded => PayloadError::Io(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "Thread pool is overloaded",
+            )),
         }
     }
 }
@@ -287,6 +348,23 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    #[test]
+    fn test_status_code() {
+        #[derive(thiserror::Error, Debug)]
+        #[error("Not found")]
+        struct NotFound;
+
+        impl ResponseError for NotFound {
+            fn status_code(&self) -> StatusCode {
+                StatusCode::NOT_FOUND
+            }
+        }
+
+        let resp: Response = NotFound.into();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(NotFound.status_code(), StatusCode::NOT_FOUND);
+    }
+
     #[test]
     fn test_error_http_response() {
         let orig = io::Error::new(io::ErrorKind::Other, "other");
@@ -7,7 +7,7 @@ use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::{ParseError, PayloadError};
 use crate::http::message::{ConnectionType, RequestHeadType, ResponseHead};
-use crate::http::{Method, Version};
+use crate::http::{HeaderMap, Method, Version};
 use crate::util::{Bytes, BytesMut};
 
 use super::decoder::{PayloadDecoder, PayloadItem, PayloadType};
@@ -38,6 +38,7 @@ struct ClientCodecInner {
     timer: DateService,
     decoder: decoder::MessageDecoder<ResponseHead>,
     payload: RefCell<Option<PayloadDecoder>>,
+    trailers: RefCell<Option<HeaderMap>>,
     version: Cell<Version>,
     ctype: Cell<ConnectionType>,
 
@@ -67,6 +68,7 @@ impl ClientCodec {
                 timer,
                 decoder: decoder::MessageDecoder::default(),
                 payload: RefCell::new(None),
+                trailers: RefCell::new(None),
                 version: Cell::new(Version::HTTP_11),
                 ctype: Cell::new(ConnectionType::Close),
                 flags: Cell::new(flags),
@@ -75,6 +77,15 @@ impl ClientCodec {
         }
     }
 
+    /// Set limits for response head.
+    ///
+    /// `max_size` is the max size of response head in bytes, `max_headers` is
+    /// the max number of response headers. By default it is 32Kb and 96 headers.
+    pub fn headers_limits(mut self, max_size: usize, max_headers: usize) -> Self {
+        self.inner.decoder = decoder::MessageDecoder::new(max_size, max_headers);
+        self
+    }
+
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
         self.inner.ctype.get() == ConnectionType::Upgrade
@@ -108,6 +119,22 @@ impl ClientPayloadCodec {
         self.inner.ctype.get() == ConnectionType::KeepAlive
     }
 
+    /// Check if payload is not fully received and could not be
+    /// completed by connection close
+    pub(crate) fn is_incomplete(&self) -> bool {
+        self.inner
+            .payload
+            .borrow()
+            .as_ref()
+            .map(|pl| !pl.is_eof())
+            .unwrap_or(false)
+    }
+
+    /// Take trailer fields of the last response payload
+    pub fn take_trailers(&self) -> Option<HeaderMap> {
+        self.inner.trailers.borrow_mut().take()
+    }
+
     /// Transform payload codec to a message codec
     pub fn into_message_codec(self) -> ClientCodec {
         ClientCodec { inner: self.inner }
@@ -166,25 +193,32 @@ impl Decoder for ClientPayloadCodec {
             "Payload decoder is not specified"
         );
 
-        let item = self
-            .inner
-            .payload
-            .borrow_mut()
-            .as_mut()
-            .unwrap()
-            .decode(src)?;
-
-        Ok(match item {
-            Some(PayloadItem::Chunk(chunk)) => {
-                reserve_readbuf(src);
-                Some(Some(chunk))
-            }
-            Some(PayloadItem::Eof) => {
-                self.inner.payload.borrow_mut().take();
-                Some(None)
-            }
-            None => None,
-        })
+        loop {
+            let item = self
+                .inner
+                .payload
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .decode(src)?;
+
+            return Ok(match item {
+                Some(PayloadItem::Chunk(chunk)) => {
+                    reserve_readbuf(src);
+                    Some(Some(chunk))
+                }
+                Some(PayloadItem::Trailers(trailers)) => {
+                    // eof always follows trailers
+                    *self.inner.trailers.borrow_mut() = Some(trailers);
+                    continue;
+                }
+                Some(PayloadItem::Eof) => {
+                    self.inner.payload.borrow_mut().take();
+                    Some(None)
+                }
+                None => None,
+            });
+        }
     }
 }
 
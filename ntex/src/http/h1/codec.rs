@@ -6,6 +6,7 @@ use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::ParseError;
+use crate::http::header::CONTENT_LENGTH;
 use crate::http::message::ConnectionType;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -19,6 +20,7 @@ bitflags! {
         const HEAD              = 0b0000_0001;
         const STREAM            = 0b0000_0010;
         const KEEPALIVE_ENABLED = 0b0000_0100;
+        const CONNECT           = 0b0000_1000;
     }
 }
 
@@ -86,6 +88,15 @@ impl Codec {
         }
     }
 
+    /// Set limits for request head.
+    ///
+    /// `max_size` is the max size of request head in bytes, `max_headers` is
+    /// the max number of request headers. By default it is 32Kb and 96 headers.
+    pub fn headers_limits(mut self, max_size: usize, max_headers: usize) -> Self {
+        self.decoder = decoder::MessageDecoder::new(max_size, max_headers);
+        self
+    }
+
     #[inline]
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
@@ -136,6 +147,7 @@ impl Decoder for Codec {
             let head = req.head();
             let mut flags = self.flags.get();
             flags.set(Flags::HEAD, head.method == Method::HEAD);
+            flags.set(Flags::CONNECT, head.method == Method::CONNECT);
             self.flags.set(flags);
             self.version.set(head.version);
             self.ctype.set(head.connection_type());
@@ -161,10 +173,25 @@ impl Encoder for Codec {
 
     fn encode(&self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Item((mut res, length)) => {
+            Message::Item((mut res, mut length)) => {
                 // set response version
                 res.head_mut().version = self.version.get();
 
+                // successful response to CONNECT request switches connection
+                // to tunnel mode, it cannot have a body
+                if self.flags.get().contains(Flags::CONNECT) && res.status().is_success() {
+                    length = BodySize::None;
+                }
+
+                // http/1.0 peers do not support chunked transfer encoding,
+                // streaming body without content-length is delimited by connection close
+                if length == BodySize::Stream && self.version.get() < Version::HTTP_11 {
+                    res.head_mut().no_chunking(true);
+                    if !res.headers().contains_key(CONTENT_LENGTH) {
+                        self.ctype.set(ConnectionType::Close);
+                    }
+                }
+
                 // connection status
                 if let Some(ct) = res.head().ctype() {
                     if ct != ConnectionType::KeepAlive {
@@ -173,6 +200,7 @@ impl Encoder for Codec {
                 }
 
                 // encode message
+                let trailers = res.head_mut().take_trailers();
                 self.encoder.encode(
                     dst,
                     &mut res,
@@ -183,6 +211,7 @@ impl Encoder for Codec {
                     self.ctype.get(),
                     &self.timer,
                 )?;
+                self.encoder.set_trailers(trailers);
                 // self.headers_size = (dst.len() - len) as u32;
             }
             Message::Chunk(Some(bytes)) => {
@@ -250,4 +279,33 @@ mod tests {
         assert!(codec.upgrade());
         assert!(!codec.keepalive_enabled());
     }
+
+    #[crate::rt_test]
+    async fn test_connect_response() {
+        let codec = Codec::default();
+        let mut buf = BytesMut::from("CONNECT example.com:443 HTTP/1.1\r\n\r\n");
+        let _item = codec.decode(&mut buf).unwrap().unwrap();
+
+        let mut buf = BytesMut::new();
+        let res = Response::Ok()
+            .header("content-length", "10")
+            .finish()
+            .drop_body();
+        codec
+            .encode(Message::Item((res, BodySize::Sized(10))), &mut buf)
+            .unwrap();
+        let data = String::from_utf8(buf.to_vec()).unwrap();
+        assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!data.contains("content-length"));
+        assert!(!data.contains("transfer-encoding"));
+
+        let mut buf = BytesMut::new();
+        let res = Response::Forbidden().finish().drop_body();
+        codec
+            .encode(Message::Item((res, BodySize::Empty)), &mut buf)
+            .unwrap();
+        let data = String::from_utf8(buf.to_vec()).unwrap();
+        assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert!(data.contains("content-length: 0\r\n"));
+    }
 }
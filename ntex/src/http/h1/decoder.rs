@@ -12,11 +12,15 @@ use crate::util::{Buf, Bytes, BytesMut};
 
 use super::MAX_BUFFER_SIZE;
 
-const MAX_HEADERS: usize = 96;
+pub(crate) const MAX_HEADERS: usize = 96;
 
 #[derive(Debug)]
 /// Incoming messagd decoder
-pub(super) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(super) struct MessageDecoder<T: MessageType> {
+    max_size: usize,
+    max_headers: usize,
+    _t: PhantomData<T>,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 /// Incoming request type
@@ -28,13 +32,24 @@ pub enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(MAX_BUFFER_SIZE, MAX_HEADERS)
     }
 }
 
 impl<T: MessageType> Clone for MessageDecoder<T> {
     fn clone(&self) -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(self.max_size, self.max_headers)
+    }
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    /// Create decoder with limits for message head size and number of headers
+    pub(super) fn new(max_size: usize, max_headers: usize) -> Self {
+        MessageDecoder {
+            max_size,
+            max_headers,
+            _t: PhantomData,
+        }
     }
 }
 
@@ -43,7 +58,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, self.max_size, self.max_headers)
     }
 }
 
@@ -78,13 +93,18 @@ pub(super) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
         slice: &Bytes,
         version: Version,
         raw_headers: &[HeaderIndex],
+        max_headers: usize,
     ) -> Result<PayloadLength, ParseError> {
         let mut ka = None;
         let mut has_upgrade = false;
@@ -198,7 +218,7 @@ pub(super) trait MessageType: Sized {
         if chunked {
             // Chunked encoding
             Ok(PayloadLength::Payload(PayloadType::Payload(
-                PayloadDecoder::chunked(),
+                PayloadDecoder::chunked(max_headers),
             )))
         } else if let Some(len) = content_length {
             // Content-Length
@@ -228,16 +248,28 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut headers_buf: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
+        let mut headers_vec = Vec::new();
+        let headers = headers_slice(&mut headers_buf, &mut headers_vec, max_headers);
 
         let (len, method, uri, ver, headers) = {
-            let mut parsed: [mem::MaybeUninit<httparse::Header<'_>>; MAX_HEADERS] =
+            let mut parsed_buf: [mem::MaybeUninit<httparse::Header<'_>>; MAX_HEADERS] =
                 uninit_array();
+            let mut parsed_vec = Vec::new();
+            let parsed = headers_slice(&mut parsed_buf, &mut parsed_vec, max_headers);
 
             let mut req = httparse::Request::new(&mut []);
 
-            match req.parse_with_uninit_headers(src, &mut parsed)? {
+            match req.parse_with_uninit_headers(src, parsed)? {
+                httparse::Status::Complete(len) if len > max_size => {
+                    trace!("Message head is larger than {} bytes, closing", max_size);
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Complete(len) => {
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
@@ -253,12 +285,12 @@ impl MessageType for Request {
                         method,
                         uri,
                         version,
-                        HeaderIndex::record(src, req.headers, &mut headers),
+                        HeaderIndex::record(src, req.headers, headers),
                     )
                 }
                 httparse::Status::Partial => {
-                    if src.len() >= MAX_BUFFER_SIZE {
-                        trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    if src.len() >= max_size {
+                        trace!("{} bytes of unprocessed data reached, closing", max_size);
                         return Err(ParseError::TooLarge);
                     }
                     return Ok(None);
@@ -269,7 +301,8 @@ impl MessageType for Request {
         let mut msg = Request::new();
 
         // convert headers
-        let mut length = msg.set_headers(&src.split_to(len).freeze(), ver, headers)?;
+        let mut length =
+            msg.set_headers(&src.split_to(len).freeze(), ver, headers, max_headers)?;
 
         // disallow HTTP/1.0 POST requests that do not contain a Content-Length headers
         // see https://datatracker.ietf.org/doc/html/rfc1945#section-7.2.2
@@ -325,19 +358,29 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
+    fn decode(
+        src: &mut BytesMut,
+        max_size: usize,
+        max_headers: usize,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut headers_buf: [mem::MaybeUninit<HeaderIndex>; MAX_HEADERS] = uninit_array();
+        let mut headers_vec = Vec::new();
+        let headers = headers_slice(&mut headers_buf, &mut headers_vec, max_headers);
 
         let (len, ver, status, headers) = {
-            let mut parsed: [mem::MaybeUninit<httparse::Header<'_>>; MAX_HEADERS] =
+            let mut parsed_buf: [mem::MaybeUninit<httparse::Header<'_>>; MAX_HEADERS] =
                 uninit_array();
+            let mut parsed_vec = Vec::new();
+            let parsed = headers_slice(&mut parsed_buf, &mut parsed_vec, max_headers);
 
             let mut res = httparse::Response::new(&mut []);
-            match httparse::ParserConfig::default().parse_response_with_uninit_headers(
-                &mut res,
-                src,
-                &mut parsed,
-            )? {
+            match httparse::ParserConfig::default()
+                .parse_response_with_uninit_headers(&mut res, src, parsed)?
+            {
+                httparse::Status::Complete(len) if len > max_size => {
+                    log::error!("Message head is larger than {} bytes, closing", max_size);
+                    return Err(ParseError::TooLarge);
+                }
                 httparse::Status::Complete(len) => {
                     let version = if res.version.unwrap() == 1 {
                         Version::HTTP_11
@@ -351,12 +394,15 @@ impl MessageType for ResponseHead {
                         len,
                         version,
                         status,
-                        HeaderIndex::record(src, res.headers, &mut headers),
+                        HeaderIndex::record(src, res.headers, headers),
                     )
                 }
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        log::error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return if src.len() >= max_size {
+                        log::error!(
+                            "{} bytes of unprocessed data reached, closing",
+                            max_size
+                        );
                         Err(ParseError::TooLarge)
                     } else {
                         Ok(None)
@@ -369,7 +415,8 @@ impl MessageType for ResponseHead {
         msg.version = ver;
 
         // convert headers
-        let mut length = msg.set_headers(&src.split_to(len).freeze(), ver, headers)?;
+        let mut length =
+            msg.set_headers(&src.split_to(len).freeze(), ver, headers, max_headers)?;
 
         // Remove CL value if 0 now that all headers and HTTP/1.0 special cases are processed.
         // Protects against some request smuggling attacks.
@@ -442,6 +489,8 @@ impl HeaderIndex {
 /// Http payload item
 pub enum PayloadItem {
     Chunk(Bytes),
+    /// Trailer fields of chunked payload, always followed by `Eof`
+    Trailers(HeaderMap),
     Eof,
 }
 
@@ -461,9 +510,9 @@ impl PayloadDecoder {
         }
     }
 
-    pub(super) fn chunked() -> PayloadDecoder {
+    pub(super) fn chunked(max_headers: usize) -> PayloadDecoder {
         PayloadDecoder {
-            kind: Cell::new(Kind::Chunked(ChunkedState::Size, 0)),
+            kind: Cell::new(Kind::Chunked(ChunkedState::Size, 0, max_headers)),
         }
     }
 
@@ -472,6 +521,20 @@ impl PayloadDecoder {
             kind: Cell::new(Kind::Eof),
         }
     }
+
+    /// Check if payload is delimited by connection close
+    pub(super) fn is_eof(&self) -> bool {
+        self.kind.get() == Kind::Eof
+    }
+
+    /// Remaining payload length, if it is known
+    pub(super) fn length_hint(&self) -> Option<u64> {
+        if let Kind::Length(x) = self.kind.get() {
+            Some(x)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -479,8 +542,9 @@ enum Kind {
     /// A Reader used when a Content-Length header is passed with a positive
     /// integer.
     Length(u64),
-    /// A Reader used when Transfer-Encoding is `chunked`.
-    Chunked(ChunkedState, u64),
+    /// A Reader used when Transfer-Encoding is `chunked`,
+    /// contains max number of trailer fields.
+    Chunked(ChunkedState, u64, usize),
     /// A Reader used for responses that don't indicate a length or chunked.
     ///
     /// Note: This should only used for `Response`s. It is illegal for a
@@ -507,8 +571,7 @@ enum ChunkedState {
     Body,
     BodyCr,
     BodyLf,
-    EndCr,
-    EndLf,
+    Trailers,
     End,
 }
 
@@ -541,16 +604,22 @@ impl Decoder for PayloadDecoder {
                     Ok(Some(PayloadItem::Chunk(buf.freeze())))
                 }
             }
-            Kind::Chunked(ref mut state, ref mut size) => {
+            Kind::Chunked(ref mut state, ref mut size, max_headers) => {
                 let result = loop {
                     let mut buf = None;
+                    let mut trailers = None;
                     // advances the chunked state
-                    *state = match state.step(src, size, &mut buf) {
-                        Poll::Pending => break Ok(None),
-                        Poll::Ready(Ok(state)) => state,
-                        Poll::Ready(Err(e)) => break Err(e),
-                    };
+                    *state =
+                        match state.step(src, size, max_headers, &mut buf, &mut trailers) {
+                            Poll::Pending => break Ok(None),
+                            Poll::Ready(Ok(state)) => state,
+                            Poll::Ready(Err(e)) => break Err(e),
+                        };
 
+                    if let Some(trailers) = trailers {
+                        log::trace!("Chunked stream trailers: {:?}", trailers);
+                        break Ok(Some(PayloadItem::Trailers(trailers)));
+                    }
                     if *state == ChunkedState::End {
                         log::trace!("End of chunked stream");
                         break Ok(Some(PayloadItem::Eof));
@@ -594,7 +663,9 @@ impl ChunkedState {
         &self,
         body: &mut BytesMut,
         size: &mut u64,
+        max_headers: usize,
         buf: &mut Option<Bytes>,
+        trailers: &mut Option<HeaderMap>,
     ) -> Poll<Result<ChunkedState, ParseError>> {
         use self::ChunkedState::*;
         match *self {
@@ -605,8 +676,7 @@ impl ChunkedState {
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
-            EndCr => ChunkedState::read_end_cr(body),
-            EndLf => ChunkedState::read_end_lf(body),
+            Trailers => ChunkedState::read_trailers(body, max_headers, trailers),
             End => Poll::Ready(Ok(ChunkedState::End)),
         }
     }
@@ -673,7 +743,7 @@ impl ChunkedState {
     ) -> Poll<Result<ChunkedState, ParseError>> {
         match byte!(rdr) {
             b'\n' if *size > 0 => Poll::Ready(Ok(ChunkedState::Body)),
-            b'\n' if *size == 0 => Poll::Ready(Ok(ChunkedState::EndCr)),
+            b'\n' if *size == 0 => Poll::Ready(Ok(ChunkedState::Trailers)),
             _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk size LF"))),
         }
     }
@@ -718,16 +788,58 @@ impl ChunkedState {
             _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk body LF"))),
         }
     }
-    fn read_end_cr(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, ParseError>> {
-        match byte!(rdr) {
-            b'\r' => Poll::Ready(Ok(ChunkedState::EndLf)),
-            _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk end CR"))),
+    fn read_trailers(
+        rdr: &mut BytesMut,
+        max_headers: usize,
+        trailers: &mut Option<HeaderMap>,
+    ) -> Poll<Result<ChunkedState, ParseError>> {
+        // fast path, message without trailer fields
+        if rdr.len() >= 2 && &rdr[..2] == b"\r\n" {
+            rdr.advance(2);
+            return Poll::Ready(Ok(ChunkedState::End));
         }
-    }
-    fn read_end_lf(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, ParseError>> {
-        match byte!(rdr) {
-            b'\n' => Poll::Ready(Ok(ChunkedState::End)),
-            _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk end LF"))),
+
+        let mut parsed_buf = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed_vec = Vec::new();
+        let parsed = if max_headers <= MAX_HEADERS {
+            &mut parsed_buf[..max_headers]
+        } else {
+            parsed_vec.resize(max_headers, httparse::EMPTY_HEADER);
+            parsed_vec.as_mut_slice()
+        };
+        match httparse::parse_headers(rdr, parsed)? {
+            httparse::Status::Complete((len, parsed)) => {
+                let mut map = HeaderMap::with_capacity(parsed.len());
+                for hdr in parsed {
+                    let name = HeaderName::from_bytes(hdr.name.as_bytes())
+                        .map_err(|_| ParseError::Header)?;
+                    match name {
+                        // framing headers are not allowed in trailers
+                        header::CONTENT_LENGTH | header::TRANSFER_ENCODING => {
+                            log::debug!("Ignore {:?} trailer field", name);
+                            continue;
+                        }
+                        _ => (),
+                    }
+                    let value = HeaderValue::from_bytes(hdr.value)
+                        .map_err(|_| ParseError::Header)?;
+                    map.append(name, value);
+                }
+                rdr.advance(len);
+
+                if !map.is_empty() {
+                    *trailers = Some(map);
+                }
+                Poll::Ready(Ok(ChunkedState::End))
+            }
+            httparse::Status::Partial => {
+                if rdr.len() >= MAX_BUFFER_SIZE {
+                    log::trace!("MAX_BUFFER_SIZE unprocessed trailers data reached");
+                    Poll::Ready(Err(ParseError::TooLarge))
+                } else {
+                    Poll::Pending
+                }
+            }
         }
     }
 }
@@ -737,6 +849,20 @@ fn uninit_array<T, const LEN: usize>() -> [mem::MaybeUninit<T>; LEN] {
     unsafe { mem::MaybeUninit::uninit().assume_init() }
 }
 
+/// Use stack buffer for default number of headers, allocate otherwise
+fn headers_slice<'a, T: Copy>(
+    buf: &'a mut [mem::MaybeUninit<T>; MAX_HEADERS],
+    vec: &'a mut Vec<mem::MaybeUninit<T>>,
+    max_headers: usize,
+) -> &'a mut [mem::MaybeUninit<T>] {
+    if max_headers <= MAX_HEADERS {
+        &mut buf[..max_headers]
+    } else {
+        vec.resize(max_headers, mem::MaybeUninit::uninit());
+        vec.as_mut_slice()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -803,6 +929,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_headers_limits() {
+        let req = "GET /test HTTP/1.1\r\nx-1: 1\r\nx-2: 2\r\nx-3: 3\r\n\r\n";
+
+        // number of headers
+        let reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, 2);
+        let mut buf = BytesMut::from(req);
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        let reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, 3);
+        let mut buf = BytesMut::from(req);
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 3);
+
+        // more than default number of headers
+        let mut data = "GET /test HTTP/1.1\r\n".to_string();
+        for idx in 0..MAX_HEADERS + 10 {
+            data.push_str(&format!("x-{}: {}\r\n", idx, idx));
+        }
+        data.push_str("\r\n");
+        let mut buf = BytesMut::from(data.as_str());
+        assert!(MessageDecoder::<Request>::default()
+            .decode(&mut buf)
+            .is_err());
+
+        let reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, MAX_HEADERS + 10);
+        let mut buf = BytesMut::from(data.as_str());
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), MAX_HEADERS + 10);
+
+        // size of head, complete and partial
+        let reader = MessageDecoder::<Request>::new(32, MAX_HEADERS);
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-1: 1\r\nx-2: 2\r\n\r\n");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\nx-1: 1\r\nx-2: 2");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        // response head
+        let reader = MessageDecoder::<ResponseHead>::new(MAX_BUFFER_SIZE, 1);
+        let mut buf = BytesMut::from("HTTP/1.1 200 OK\r\nx-1: 1\r\nx-2: 2\r\n\r\n");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+    }
+
     #[test]
     fn test_parse_partial() {
         let mut buf = BytesMut::from("PUT /test HTTP/1");
@@ -1118,6 +1288,20 @@ mod tests {
         assert!(req.upgrade());
     }
 
+    #[test]
+    fn test_connect_authority_form() {
+        let mut buf = BytesMut::from(
+            "CONNECT example.com:443 HTTP/1.1\r\n\
+             host: example.com:443\r\n\r\n",
+        );
+        let req = parse_ready!(&mut buf);
+
+        assert!(req.upgrade());
+        assert_eq!(*req.method(), Method::CONNECT);
+        assert_eq!(req.uri().host(), Some("example.com"));
+        assert_eq!(req.uri().port_u16(), Some(443));
+    }
+
     #[test]
     fn test_request_chunked() {
         let mut buf = BytesMut::from(
@@ -1356,6 +1540,79 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n",
+        );
+
+        let reader = MessageDecoder::<Request>::default();
+        let (msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+        assert!(msg.chunked().unwrap());
+
+        buf.extend(b"4\r\ndata\r\n0\r\nx-checksum: 1234\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(
+            b"content-length: 10\r\nx-status: done\r\n\r\n\
+              GET /test2 HTTP/1.1\r\n\r\n",
+        );
+        let trailers = match pl.decode(&mut buf).unwrap().unwrap() {
+            PayloadItem::Trailers(trailers) => trailers,
+            _ => panic!("expect trailers"),
+        };
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers.get("x-checksum").unwrap(), "1234");
+        assert_eq!(trailers.get("x-status").unwrap(), "done");
+        assert!(!trailers.contains_key(header::CONTENT_LENGTH));
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.path(), "/test2");
+
+        // malformed trailer section
+        let mut buf = BytesMut::from(
+            "GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n",
+        );
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+        buf.extend(b"0\r\nx-checksum\r\n\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_parse_chunked_payload_trailers_limit() {
+        let mut trailers = BytesMut::from("0\r\n");
+        for idx in 0..MAX_HEADERS + 10 {
+            trailers.extend(format!("x-trailer-{}: {}\r\n", idx, idx).as_bytes());
+        }
+        trailers.extend(b"\r\n");
+
+        let reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, MAX_HEADERS + 10);
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n");
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+        buf.extend(&trailers);
+        match pl.decode(&mut buf).unwrap().unwrap() {
+            PayloadItem::Trailers(trailers) => assert_eq!(trailers.len(), MAX_HEADERS + 10),
+            _ => panic!("expect trailers"),
+        }
+
+        let reader = MessageDecoder::<Request>::new(MAX_BUFFER_SIZE, 4);
+        let mut buf =
+            BytesMut::from("GET /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n");
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+        buf.extend(&trailers);
+        assert!(matches!(pl.decode(&mut buf), Err(ParseError::TooLarge)));
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from("HTTP/1.0 200 Ok\r\n\r\ntest data");
@@ -1,8 +1,9 @@
 //! Framed transport dispatcher
-use std::task::{Context, Poll};
-use std::{cell::RefCell, error::Error, future::Future, io, marker, pin::Pin, rc::Rc};
+use std::{cell::RefCell, error::Error, future::Future, io, marker, pin::Pin};
+use std::{collections::VecDeque, rc::Rc, task::Context, task::Poll};
 
 use crate::io::{Filter, Io, IoBoxed, IoRef, IoStatusUpdate, RecvError};
+use crate::server::ShutdownSignal;
 use crate::service::{Pipeline, PipelineCall, Service};
 use crate::util::{ready, Bytes};
 
@@ -10,9 +11,10 @@ use crate::http;
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
 use crate::http::config::{DispatcherConfig, OnRequest};
 use crate::http::error::{DispatchError, ParseError, PayloadError, ResponseError};
-use crate::http::message::{ConnectionType, CurrentIo};
+use crate::http::message::{ConnectionType, CurrentIo, H1Response, TakeIo};
 use crate::http::request::Request;
 use crate::http::response::Response;
+use crate::http::Method;
 
 use super::decoder::{PayloadDecoder, PayloadItem, PayloadType};
 use super::payload::{Payload, PayloadSender, PayloadStatus};
@@ -32,6 +34,8 @@ bitflags::bitflags! {
         const UPGRADE_HND          = 0b0001_0000;
         /// Stop after sending payload
         const SENDPAYLOAD_AND_STOP = 0b0010_0000;
+        /// Client timeout is registered for next request
+        const READ_HEAD            = 0b0100_0000;
     }
 }
 
@@ -81,17 +85,63 @@ pin_project_lite::pin_project! {
         Service { #[pin] fut: PipelineCall<S, Request> },
         ServiceUpgrade { #[pin] fut: PipelineCall<S, Request>  },
         Expect { #[pin] fut: PipelineCall<X, Request> },
-        Filter { fut: PipelineCall<OnRequest, (Request, IoRef)> }
+        Filter { fut: PipelineCall<OnRequest, (Request, IoRef)> },
+        Pipelined { fut: PipelinedCall<S> },
     }
 }
 
-struct DispatcherInner<F, S, B, X, U> {
+/// Request that is read ahead of current request
+struct PipelinedRequest<S: Service<Request> + 'static> {
+    codec: Codec,
+    kind: PipelinedKind<S>,
+}
+
+enum PipelinedKind<S: Service<Request> + 'static> {
+    /// Service is called for the request
+    Call(PipelinedCall<S>, Rc<H1Response>),
+    /// Request is not handled yet
+    Request(Request, PayloadType),
+    /// Malformed request
+    Error(ParseError),
+}
+
+/// Service call that stores result until response could be sent
+struct PipelinedCall<S: Service<Request> + 'static> {
+    fut: Option<Pin<Box<PipelineCall<S, Request>>>>,
+    result: Option<Result<S::Response, S::Error>>,
+}
+
+impl<S: Service<Request>> PipelinedCall<S> {
+    fn poll_result(&mut self, cx: &mut Context<'_>) {
+        if let Some(ref mut fut) = self.fut {
+            if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+                self.fut = None;
+                self.result = Some(result);
+            }
+        }
+    }
+
+    fn poll_call(&mut self, cx: &mut Context<'_>) -> Poll<Result<S::Response, S::Error>> {
+        self.poll_result(cx);
+        if let Some(result) = self.result.take() {
+            Poll::Ready(result)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+struct DispatcherInner<F, S: Service<Request> + 'static, B, X, U> {
     io: Io<F>,
     flags: Flags,
     codec: Codec,
     config: Rc<DispatcherConfig<S, X, U>>,
     error: Option<DispatchError>,
-    payload: Option<(PayloadDecoder, PayloadSender)>,
+    payload: Option<(PayloadDecoder, PayloadSender, usize)>,
+    pipeline: VecDeque<PipelinedRequest<S>>,
+    connect_io: Option<Rc<RefCell<Option<Io<F>>>>>,
+    response_started: Rc<H1Response>,
+    shutdown: ShutdownSignal,
     _t: marker::PhantomData<(S, B)>,
 }
 
@@ -108,7 +158,8 @@ where
 {
     /// Construct new `Dispatcher` instance with outgoing messages stream.
     pub(in crate::http) fn new(io: Io<F>, config: Rc<DispatcherConfig<S, X, U>>) -> Self {
-        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled())
+            .headers_limits(config.headers_max_size, config.headers_max_count);
         io.set_disconnect_timeout(config.client_disconnect.into());
 
         // slow-request timer
@@ -118,6 +169,7 @@ where
             io.start_keepalive_timer(config.client_timeout);
             Flags::KEEPALIVE_REG
         };
+        let response_started = Rc::new(H1Response::new(io.get_ref()));
 
         Dispatcher {
             call: CallState::None,
@@ -129,6 +181,10 @@ where
                 config,
                 error: None,
                 payload: None,
+                pipeline: VecDeque::new(),
+                connect_io: None,
+                response_started,
+                shutdown: ShutdownSignal::current(),
                 _t: marker::PhantomData,
             },
         }
@@ -156,31 +212,22 @@ where
                 State::Call => {
                     let next = match this.call.project() {
                         CallStateProject::Service { fut } => {
-                            match fut.poll(cx) {
-                                Poll::Ready(result) => match result {
-                                    Ok(res) => {
-                                        let (res, body) = res.into().into_parts();
-                                        *this.st = this.inner.send_response(res, body);
-                                    }
-                                    Err(e) => *this.st = this.inner.handle_error(e, false),
-                                },
-                                Poll::Pending => {
-                                    // we might need to read more data into a request payload
-                                    // (ie service future can wait for payload data)
-                                    if this.inner.payload.is_some() {
-                                        if let Err(e) =
-                                            ready!(this.inner.poll_request_payload(cx))
-                                        {
-                                            *this.st = State::Stop;
-                                            this.inner.error = Some(e);
-                                        }
-                                    } else if this.inner.poll_io_closed(cx) {
-                                        // check if io is closed
-                                        *this.st = State::Stop;
-                                    } else {
-                                        return Poll::Pending;
-                                    }
-                                }
+                            // error closes connection if next requests are pipelined
+                            let critical = !this.inner.pipeline.is_empty();
+                            let result = fut.poll(cx);
+                            if let Some(st) =
+                                ready!(this.inner.handle_call_result(result, critical, cx))
+                            {
+                                *this.st = st;
+                            }
+                            None
+                        }
+                        CallStateProject::Pipelined { fut } => {
+                            let result = fut.poll_call(cx);
+                            if let Some(st) =
+                                ready!(this.inner.handle_call_result(result, true, cx))
+                            {
+                                *this.st = st;
                             }
                             None
                         }
@@ -193,6 +240,12 @@ where
                             match ready!(fut.poll(cx)) {
                                 Ok(res) => {
                                     let (msg, body) = res.into().into_parts();
+                                    if this.inner.restore_io(&msg) {
+                                        *this.st = this.inner.send_response(msg, body);
+                                        this = self.as_mut().project();
+                                        continue;
+                                    }
+
                                     let io = if let Some(item) = msg.head().take_io() {
                                         item
                                     } else {
@@ -305,7 +358,7 @@ where
                     if let Err(e) = ready!(this.inner.poll_request_payload(cx)) {
                         *this.st = State::Stop;
                         this.inner.error = Some(e);
-                    } else {
+                    } else if this.inner.payload.is_none() {
                         *this.st = this.inner.switch_to_read_request();
                     }
                 }
@@ -338,9 +391,12 @@ where
                     if io.0.is_closed() {
                         *this.st = State::Stop;
                     } else {
-                        if let Poll::Ready(Err(err)) =
-                            _poll_request_payload(&io.0, &mut this.inner.payload, cx)
-                        {
+                        if let Poll::Ready(Err(err)) = _poll_request_payload(
+                            &io.0,
+                            &mut this.inner.payload,
+                            this.inner.config.payload_max_size,
+                            cx,
+                        ) {
                             this.inner.error = Some(err);
                         }
                         loop {
@@ -447,6 +503,33 @@ where
         }
     }
 
+    fn switch_to_read_payload(&mut self) -> State<B> {
+        // response is sent, limit time for reading the rest of request payload
+        if !self.config.client_disconnect.is_zero() {
+            self.flags.insert(Flags::KEEPALIVE_REG);
+            self.io
+                .start_keepalive_timer(self.config.client_disconnect.into());
+        }
+        State::ReadPayload
+    }
+
+    /// Replace keep-alive timer with client timeout
+    /// when next request starts arriving
+    fn start_slow_request_timer(&mut self) {
+        if self.flags.contains(Flags::STARTED | Flags::KEEPALIVE_REG)
+            && !self.flags.contains(Flags::READ_HEAD)
+            && self.io.with_read_buf(|buf| !buf.is_empty())
+        {
+            self.flags.insert(Flags::READ_HEAD);
+            if self.config.client_timeout.is_zero() {
+                self.flags.remove(Flags::KEEPALIVE_REG);
+                self.io.stop_keepalive_timer();
+            } else {
+                self.io.start_keepalive_timer(self.config.client_timeout);
+            }
+        }
+    }
+
     fn unregister_keepalive(&mut self) {
         if self.flags.contains(Flags::KEEPALIVE_REG) {
             self.io.stop_keepalive_timer();
@@ -454,6 +537,94 @@ where
         }
     }
 
+    /// Handle result of service call, returns next state if it changes
+    fn handle_call_result(
+        &mut self,
+        result: Poll<Result<S::Response, S::Error>>,
+        critical: bool,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<State<B>>> {
+        match result {
+            Poll::Ready(Ok(res)) => {
+                let (res, body) = res.into().into_parts();
+                Poll::Ready(Some(self.send_response(res, body)))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Some(self.handle_error(e, critical))),
+            Poll::Pending => {
+                // we might need to read more data into a request payload
+                // (ie service future can wait for payload data)
+                if self.payload.is_some() {
+                    if let Err(e) = ready!(self.poll_request_payload(cx)) {
+                        self.error = Some(e);
+                        Poll::Ready(Some(State::Stop))
+                    } else {
+                        Poll::Ready(None)
+                    }
+                } else if self.poll_io_closed(cx) {
+                    // check if io is closed
+                    Poll::Ready(Some(State::Stop))
+                } else {
+                    self.poll_pipeline(cx);
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    /// Start service calls for pipelined requests
+    ///
+    /// Requests are decoded with a copy of the codec, so current request's
+    /// response is not affected. Only safe requests (GET, HEAD, OPTIONS, TRACE)
+    /// without payload are handled concurrently, any other request stops
+    /// reading of pipelined requests and waits until previous responses are sent.
+    fn poll_pipeline(&mut self, cx: &mut Context<'_>) {
+        for item in self.pipeline.iter_mut() {
+            if let PipelinedKind::Call(ref mut call, _) = item.kind {
+                call.poll_result(cx);
+            }
+        }
+
+        if self.config.pipeline_max <= 1
+            || self.config.on_request.is_some()
+            || self.flags.intersects(Flags::UPGRADE | Flags::UPGRADE_HND)
+        {
+            return;
+        }
+
+        while self.pipeline.len() + 1 < self.config.pipeline_max {
+            let codec = match self.pipeline.back() {
+                Some(item) if matches!(item.kind, PipelinedKind::Call(..)) => &item.codec,
+                Some(_) => break,
+                None => &self.codec,
+            };
+            if !codec.keepalive() {
+                break;
+            }
+            let codec = codec.clone();
+
+            let kind = match self.io.decode(&codec) {
+                Ok(Some((mut req, PayloadType::None)))
+                    if !req.upgrade() && !req.head().expect() && is_safe(req.method()) =>
+                {
+                    log::trace!("pipelined http message is received: {:?}", req);
+                    let started = Rc::new(H1Response::pipelined(self.io.get_ref()));
+                    req.head_mut().io = CurrentIo::H1(started.clone());
+
+                    let mut call = PipelinedCall {
+                        fut: Some(Box::pin(self.config.service.call_nowait(req))),
+                        result: None,
+                    };
+                    call.poll_result(cx);
+                    PipelinedKind::Call(call, started)
+                }
+                Ok(Some((req, pl))) => PipelinedKind::Request(req, pl),
+                Ok(None) => break,
+                Err(err) => PipelinedKind::Error(err),
+            };
+            self.pipeline.push_back(PipelinedRequest { codec, kind });
+        }
+    }
+
     fn handle_error<E>(&mut self, err: E, critical: bool) -> State<B>
     where
         E: ResponseError + 'static,
@@ -498,82 +669,111 @@ where
     }
 
     fn service_upgrade(&mut self, mut req: Request) -> CallState<S, X> {
-        // Move io into request
-        let io: IoBoxed = self.io.take().into();
-        req.head_mut().io = CurrentIo::Io(Rc::new((
-            io.get_ref(),
-            RefCell::new(Some(Box::new((io, self.codec.clone())))),
-        )));
+        // Move io into request, io get boxed only if handler takes it
+        let io = self.io.take();
+        let io_ref = io.get_ref();
+        let io = Rc::new(RefCell::new(Some(io)));
+        if req.head().method == Method::CONNECT {
+            self.connect_io = Some(io.clone());
+        }
+
+        let codec = self.codec.clone();
+        let take: TakeIo = Box::new(move || {
+            let io: IoBoxed = io.borrow_mut().take()?.into();
+            Some(Box::new((io, codec)))
+        });
+        req.head_mut().io = CurrentIo::Io(Rc::new((io_ref, RefCell::new(Some(take)))));
         // Handle upgrade requests
         CallState::ServiceUpgrade {
             fut: self.config.service.call_nowait(req),
         }
     }
 
+    /// Final response state for next request
+    fn next_response_started(&mut self) -> Rc<H1Response> {
+        // previous request could be still alive
+        if Rc::strong_count(&self.response_started) > 1 {
+            self.response_started = Rc::new(H1Response::new(self.io.get_ref()));
+        } else {
+            self.response_started.started.set(false);
+        }
+        self.response_started.clone()
+    }
+
+    /// Get io back if CONNECT request is rejected and handler did not take io
+    fn restore_io(&mut self, msg: &Response<()>) -> bool {
+        if let Some(io) = self.connect_io.take() {
+            if !msg.status().is_success() {
+                if let Some(io) = io.borrow_mut().take() {
+                    // CONNECT requests do not have payload
+                    self.io = io;
+                    self.payload = None;
+                    self.codec.unset_streaming();
+                    if self.codec.upgrade() {
+                        self.codec.set_ctype(if self.codec.keepalive_enabled() {
+                            ConnectionType::KeepAlive
+                        } else {
+                            ConnectionType::Close
+                        });
+                    }
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
     fn read_request(
         &mut self,
         cx: &mut Context<'_>,
         call_state: &mut std::pin::Pin<&mut CallState<S, X>>,
     ) -> Poll<State<B>> {
+        // pipelined requests are already decoded
+        if let Some(item) = self.pipeline.pop_front() {
+            log::trace!("handle pipelined http message");
+            self.codec = item.codec;
+            return Poll::Ready(match item.kind {
+                PipelinedKind::Call(call, started) => {
+                    self.stop_keepalive_timer();
+                    // previous responses are sent, write queued interim responses
+                    if let Some(buf) = started.queued.borrow_mut().take() {
+                        if !buf.is_empty() {
+                            let _ = self.io.with_write_buf(|w| w.extend_from_slice(&buf));
+                        }
+                    }
+                    self.response_started = started;
+                    call_state.set(CallState::Pipelined { fut: call });
+                    State::Call
+                }
+                PipelinedKind::Request(req, pl) => self.handle_request(req, pl, call_state),
+                PipelinedKind::Error(err) => self.handle_parse_error(err),
+            });
+        }
         log::trace!("trying to read http message");
 
         loop {
-            let result = ready!(self.io.poll_recv(&self.codec, cx));
+            let result = match self.io.poll_recv(&self.codec, cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => {
+                    // worker is shutting down, close idle keep-alive connection
+                    if self.flags.contains(Flags::STARTED)
+                        && self.shutdown.poll_shutdown(cx).is_ready()
+                        && self.io.with_read_buf(|buf| buf.is_empty())
+                    {
+                        log::trace!("worker is shutting down, close idle connection");
+                        self.io.close();
+                        return Poll::Ready(State::Stop);
+                    }
+                    self.start_slow_request_timer();
+                    return Poll::Pending;
+                }
+            };
 
             // decode incoming bytes stream
             return match result {
-                Ok((mut req, pl)) => {
+                Ok((req, pl)) => {
                     log::trace!("http message is received: {:?} and payload {:?}", req, pl);
-
-                    // keep-alive timer
-                    if self.flags.contains(Flags::KEEPALIVE_REG) {
-                        self.flags.remove(Flags::KEEPALIVE_REG);
-                        self.io.stop_keepalive_timer();
-                    }
-
-                    // configure request payload
-                    let upgrade = match pl {
-                        PayloadType::None => false,
-                        PayloadType::Payload(decoder) => {
-                            let (ps, pl) = Payload::create(false);
-                            req.replace_payload(http::Payload::H1(pl));
-                            self.payload = Some((decoder, ps));
-                            false
-                        }
-                        PayloadType::Stream(decoder) => {
-                            if self.config.upgrade.is_none() {
-                                let (ps, pl) = Payload::create(false);
-                                req.replace_payload(http::Payload::H1(pl));
-                                self.payload = Some((decoder, ps));
-                                false
-                            } else {
-                                self.flags.insert(Flags::UPGRADE);
-                                true
-                            }
-                        }
-                    };
-
-                    if upgrade {
-                        // Handle UPGRADE request
-                        log::trace!("prep io for upgrade handler");
-                        Poll::Ready(State::Upgrade(Some(req)))
-                    } else {
-                        if req.upgrade() {
-                            self.flags.insert(Flags::UPGRADE_HND);
-                        } else {
-                            req.head_mut().io = CurrentIo::Ref(self.io.get_ref());
-                        }
-                        call_state.set(if let Some(ref f) = self.config.on_request {
-                            self.service_filter(req, f)
-                        } else if req.head().expect() {
-                            self.service_expect(req)
-                        } else if self.flags.contains(Flags::UPGRADE_HND) {
-                            self.service_upgrade(req)
-                        } else {
-                            self.service_call(req)
-                        });
-                        Poll::Ready(State::Call)
-                    }
+                    Poll::Ready(self.handle_request(req, pl, call_state))
                 }
                 Err(RecvError::WriteBackpressure) => {
                     if let Err(err) = ready!(self.io.poll_flush(cx, false)) {
@@ -584,13 +784,7 @@ where
                         continue;
                     }
                 }
-                Err(RecvError::Decoder(err)) => {
-                    // Malformed requests, respond with 400
-                    log::trace!("malformed request: {:?}", err);
-                    let (res, body) = Response::BadRequest().finish().into_parts();
-                    self.error = Some(DispatchError::Parse(err));
-                    Poll::Ready(self.send_response(res, body.into_body()))
-                }
+                Err(RecvError::Decoder(err)) => Poll::Ready(self.handle_parse_error(err)),
                 Err(RecvError::PeerGone(err)) => {
                     log::trace!("peer is gone with {:?}", err);
                     self.error = Some(DispatchError::PeerGone(err));
@@ -602,11 +796,14 @@ where
                 }
                 Err(RecvError::KeepAlive) => {
                     // keep-alive timeout
-                    if !self.flags.contains(Flags::STARTED) {
+                    if self.io.with_read_buf(|buf| !buf.is_empty()) {
                         log::trace!("slow request timeout");
                         let (req, body) = Response::RequestTimeout().finish().into_parts();
                         let _ = self.send_response(req, body.into_body());
                         self.error = Some(DispatchError::SlowRequestTimeout);
+                    } else if !self.flags.contains(Flags::STARTED) {
+                        log::trace!("slow request timeout, no data received");
+                        self.error = Some(DispatchError::SlowRequestTimeout);
                     } else {
                         log::trace!("keep-alive timeout, close connection");
                     }
@@ -616,14 +813,103 @@ where
         }
     }
 
+    /// Malformed requests, respond with 400, or 431 if head is too large
+    fn handle_parse_error(&mut self, err: ParseError) -> State<B> {
+        log::trace!("malformed request: {:?}", err);
+        let mut res = if let ParseError::TooLarge = err {
+            Response::RequestHeaderFieldsTooLarge()
+        } else {
+            Response::BadRequest()
+        };
+        let (res, body) = res.finish().into_parts();
+        self.error = Some(DispatchError::Parse(err));
+        self.send_response(res, body.into_body())
+    }
+
+    /// Request is received, stop keep-alive timer
+    fn stop_keepalive_timer(&mut self) {
+        self.flags.remove(Flags::READ_HEAD);
+        if self.flags.contains(Flags::KEEPALIVE_REG) {
+            self.flags.remove(Flags::KEEPALIVE_REG);
+            self.io.stop_keepalive_timer();
+        }
+    }
+
+    /// Configure request payload and start service call
+    fn handle_request(
+        &mut self,
+        mut req: Request,
+        pl: PayloadType,
+        call_state: &mut std::pin::Pin<&mut CallState<S, X>>,
+    ) -> State<B> {
+        self.stop_keepalive_timer();
+
+        // configure request payload
+        let upgrade = match pl {
+            PayloadType::None => false,
+            PayloadType::Payload(decoder) => {
+                // reject payloads with declared size over the limit
+                let max = self.config.payload_max_size;
+                if max != 0 && decoder.length_hint().unwrap_or(0) > max as u64 {
+                    log::trace!("request payload is too large");
+                    self.codec.set_ctype(ConnectionType::Close);
+                    let (res, body) = Response::PayloadTooLarge().finish().into_parts();
+                    return self.send_response(res, body.into_body());
+                }
+                let (ps, pl) = Payload::create(false);
+                req.replace_payload(http::Payload::H1(pl));
+                self.payload = Some((decoder, ps, 0));
+                false
+            }
+            PayloadType::Stream(decoder) => {
+                if self.config.upgrade.is_none() {
+                    let (ps, pl) = Payload::create(false);
+                    req.replace_payload(http::Payload::H1(pl));
+                    self.payload = Some((decoder, ps, 0));
+                    false
+                } else {
+                    self.flags.insert(Flags::UPGRADE);
+                    true
+                }
+            }
+        };
+
+        if upgrade {
+            // Handle UPGRADE request
+            log::trace!("prep io for upgrade handler");
+            State::Upgrade(Some(req))
+        } else {
+            if req.upgrade() {
+                self.flags.insert(Flags::UPGRADE_HND);
+            } else {
+                req.head_mut().io = CurrentIo::H1(self.next_response_started());
+            }
+            call_state.set(if let Some(ref f) = self.config.on_request {
+                self.service_filter(req, f)
+            } else if req.head().expect() {
+                self.service_expect(req)
+            } else if self.flags.contains(Flags::UPGRADE_HND) {
+                self.service_upgrade(req)
+            } else {
+                self.service_call(req)
+            });
+            State::Call
+        }
+    }
+
     fn send_response(&mut self, msg: Response<()>, body: ResponseBody<B>) -> State<B> {
         trace!("sending response: {:?} body: {:?}", msg, body.size());
+        self.response_started.started.set(true);
         // we dont need to process responses if socket is disconnected
         // but we still want to handle requests with app service
         // so we skip response processing for droppped connection
         if self.io.is_closed() {
             State::Stop
         } else {
+            // worker is shutting down, do not keep connection alive
+            if self.codec.keepalive() && self.shutdown.is_shutdown() {
+                self.codec.set_ctype(ConnectionType::Close);
+            }
             let result = self
                 .io
                 .encode(Message::Item((msg, body.size())), &self.codec)
@@ -644,7 +930,7 @@ where
                         if self.error.is_some() {
                             State::Stop
                         } else if self.payload.is_some() {
-                            State::ReadPayload
+                            self.switch_to_read_payload()
                         } else {
                             self.switch_to_read_request()
                         }
@@ -678,7 +964,7 @@ where
                 } else if self.flags.contains(Flags::SENDPAYLOAD_AND_STOP) {
                     Some(State::Stop)
                 } else if self.payload.is_some() {
-                    Some(State::ReadPayload)
+                    Some(self.switch_to_read_payload())
                 } else {
                     Some(self.switch_to_read_request())
                 }
@@ -696,7 +982,20 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Result<(), DispatchError>> {
-        _poll_request_payload(&self.io, &mut self.payload, cx)
+        match ready!(_poll_request_payload(
+            &self.io,
+            &mut self.payload,
+            self.config.payload_max_size,
+            cx
+        )) {
+            Err(DispatchError::PayloadOverflow) => {
+                // payload is aborted, close connection after response
+                self.codec.set_ctype(ConnectionType::Close);
+                self.flags.remove(Flags::KEEPALIVE);
+                Poll::Ready(Ok(()))
+            }
+            result => Poll::Ready(result),
+        }
     }
 
     /// check for io changes, could close while waiting for service call
@@ -713,10 +1012,19 @@ where
     }
 }
 
+/// Check if request method is safe and could be handled ahead of previous requests
+fn is_safe(method: &Method) -> bool {
+    method == Method::GET
+        || method == Method::HEAD
+        || method == Method::OPTIONS
+        || method == Method::TRACE
+}
+
 /// Process request's payload
 fn _poll_request_payload<F>(
     io: &Io<F>,
-    slf_payload: &mut Option<(PayloadDecoder, PayloadSender)>,
+    slf_payload: &mut Option<(PayloadDecoder, PayloadSender, usize)>,
+    limit: usize,
     cx: &mut Context<'_>,
 ) -> Poll<Result<(), DispatchError>> {
     // check if payload data is required
@@ -733,8 +1041,18 @@ fn _poll_request_payload<F>(
                 match io.poll_recv(&payload.0, cx) {
                     Poll::Ready(Ok(PayloadItem::Chunk(chunk))) => {
                         updated = true;
+                        payload.2 += chunk.len();
+                        if limit != 0 && payload.2 > limit {
+                            payload.1.set_error(PayloadError::Overflow);
+                            *slf_payload = None;
+                            return Poll::Ready(Err(DispatchError::PayloadOverflow));
+                        }
                         payload.1.feed_data(chunk);
                     }
+                    Poll::Ready(Ok(PayloadItem::Trailers(trailers))) => {
+                        updated = true;
+                        payload.1.feed_trailers(trailers);
+                    }
                     Poll::Ready(Ok(PayloadItem::Eof)) => {
                         updated = true;
                         payload.1.feed_eof();
@@ -809,7 +1127,7 @@ mod tests {
     use super::*;
     use crate::http::config::{DispatcherConfig, ServiceConfig};
     use crate::http::h1::{ClientCodec, ExpectHandler, UpgradeHandler};
-    use crate::http::{body, Request, ResponseHead, StatusCode};
+    use crate::http::{body, HeaderMap, Request, ResponseHead, StatusCode};
     use crate::io::{self as nio, Base};
     use crate::service::{boxed, fn_service, IntoService};
     use crate::util::{lazy, poll_fn, stream_recv, Bytes, BytesMut};
@@ -1040,6 +1358,190 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    #[crate::rt_test]
+    async fn test_pipeline_concurrent() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+
+        let inflight = Rc::new(Cell::new(0));
+        let max_inflight = Rc::new(Cell::new(0));
+        let (inflight2, max_inflight2) = (inflight.clone(), max_inflight.clone());
+        let config = ServiceConfig::default().max_pipelined_requests(3);
+        crate::rt::spawn(Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(move |req: Request| {
+                    let (inflight, max_inflight) =
+                        (inflight2.clone(), max_inflight2.clone());
+                    async move {
+                        inflight.set(inflight.get() + 1);
+                        max_inflight.set(std::cmp::max(inflight.get(), max_inflight.get()));
+
+                        // first request completes last
+                        let path = req.path().to_string();
+                        let delay = match path.as_str() {
+                            "/1" => 150,
+                            "/2" => 100,
+                            _ => 50,
+                        };
+                        sleep(Millis(delay)).await;
+                        inflight.set(inflight.get() - 1);
+                        Ok::<_, io::Error>(Response::Ok().body(path))
+                    }
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        ));
+
+        client
+            .write("GET /1 HTTP/1.1\r\n\r\nGET /2 HTTP/1.1\r\n\r\nGET /3 HTTP/1.1\r\n\r\n");
+        sleep(Millis(250)).await;
+        assert_eq!(max_inflight.get(), 3);
+
+        let data = client.read_any();
+        let data = String::from_utf8(data.to_vec()).unwrap();
+        assert_eq!(data.matches("HTTP/1.1 200 OK").count(), 3);
+        let p1 = data.find("\r\n\r\n/1").unwrap();
+        let p2 = data.find("\r\n\r\n/2").unwrap();
+        let p3 = data.find("\r\n\r\n/3").unwrap();
+        assert!(p1 < p2 && p2 < p3);
+        assert!(!client.is_server_dropped());
+
+        // connection is still usable
+        client.write("GET /4 HTTP/1.1\r\n\r\n");
+        let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
+        assert!(load(&mut ClientCodec::default(), &mut buf)
+            .status
+            .is_success());
+
+        client.close().await;
+        assert!(client.is_server_dropped());
+    }
+
+    #[crate::rt_test]
+    async fn test_pipeline_concurrent_error() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+
+        let config = ServiceConfig::default().max_pipelined_requests(3);
+        crate::rt::spawn(Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(|_| async {
+                    sleep(Millis(50)).await;
+                    Ok::<_, io::Error>(Response::Ok().finish())
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        ));
+
+        client.write("GET /1 HTTP/1.1\r\n\r\nGET /2 HTTP/1.1\r\n\r\nGET /3 H\r\n\r\n");
+        sleep(Millis(150)).await;
+
+        let mut buf = BytesMut::from(&client.read_any()[..]);
+        let mut decoder = ClientCodec::default();
+        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::OK);
+        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::OK);
+        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::BAD_REQUEST);
+        assert!(client.is_server_dropped());
+    }
+
+    #[crate::rt_test]
+    async fn test_pipeline_concurrent_unsafe_method() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+
+        let inflight = Rc::new(Cell::new(0));
+        let max_inflight = Rc::new(Cell::new(0));
+        let (inflight2, max_inflight2) = (inflight.clone(), max_inflight.clone());
+        let config = ServiceConfig::default().max_pipelined_requests(3);
+        crate::rt::spawn(Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(move |req: Request| {
+                    let (inflight, max_inflight) =
+                        (inflight2.clone(), max_inflight2.clone());
+                    async move {
+                        inflight.set(inflight.get() + 1);
+                        max_inflight.set(std::cmp::max(inflight.get(), max_inflight.get()));
+                        sleep(Millis(50)).await;
+                        inflight.set(inflight.get() - 1);
+                        Ok::<_, io::Error>(Response::Ok().body(req.path().to_string()))
+                    }
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        ));
+
+        // DELETE is not started until response for first request is sent
+        client.write("GET /1 HTTP/1.1\r\n\r\nDELETE /2 HTTP/1.1\r\n\r\n");
+        sleep(Millis(250)).await;
+        assert_eq!(max_inflight.get(), 1);
+
+        let data = client.read_any();
+        let data = String::from_utf8(data.to_vec()).unwrap();
+        let p1 = data.find("\r\n\r\n/1").unwrap();
+        let p2 = data.find("\r\n\r\n/2").unwrap();
+        assert!(p1 < p2);
+
+        client.close().await;
+        assert!(client.is_server_dropped());
+    }
+
+    #[crate::rt_test]
+    async fn test_pipeline_concurrent_informational() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+
+        let config = ServiceConfig::default().max_pipelined_requests(2);
+        crate::rt::spawn(Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(|req: Request| async move {
+                    let path = req.path().to_string();
+                    if path == "/1" {
+                        sleep(Millis(100)).await;
+                    } else {
+                        req.head()
+                            .send_informational(
+                                StatusCode::from_u16(103).unwrap(),
+                                &HeaderMap::new(),
+                            )
+                            .unwrap();
+                    }
+                    Ok::<_, io::Error>(Response::Ok().body(path))
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        ));
+
+        client.write("GET /1 HTTP/1.1\r\n\r\nGET /2 HTTP/1.1\r\n\r\n");
+        sleep(Millis(200)).await;
+
+        // interim response of second request is sent after first response
+        let data = client.read_any();
+        let data = String::from_utf8(data.to_vec()).unwrap();
+        let p1 = data.find("\r\n\r\n/1").unwrap();
+        let p103 = data.find("HTTP/1.1 103").unwrap();
+        let p2 = data.find("\r\n\r\n/2").unwrap();
+        assert!(p1 < p103 && p103 < p2);
+
+        client.close().await;
+        assert!(client.is_server_dropped());
+    }
+
     #[crate::rt_test]
     /// h1 dispatcher still processes all incoming requests
     /// but it does not write any data to socket
@@ -1101,7 +1603,127 @@ mod tests {
         assert!(h1.inner.io.is_closed());
 
         let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
-        assert_eq!(load(&mut decoder, &mut buf).status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            load(&mut decoder, &mut buf).status,
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_headers_limits() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1.1\r\nx-1: 1\r\nx-2: 2\r\n\r\n");
+
+        let config = ServiceConfig::default().max_headers(1);
+        let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(|_| {
+                    Box::pin(async { Ok::<_, io::Error>(Response::Ok().finish()) })
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        );
+        sleep(Millis(50)).await;
+        let _ = lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_ready();
+        sleep(Millis(50)).await;
+
+        assert!(poll_fn(|cx| Pin::new(&mut h1).poll(cx)).await.is_ok());
+        assert!(h1.inner.io.is_closed());
+
+        let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
+        assert_eq!(
+            load(&mut ClientCodec::default(), &mut buf).status,
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_payload_limit() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("POST /test HTTP/1.1\r\ncontent-length: 11\r\n\r\n");
+
+        let called = Rc::new(Cell::new(false));
+        let called2 = called.clone();
+        let config = ServiceConfig::default().max_payload_size(10);
+        let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(move |_| {
+                    called2.set(true);
+                    Box::pin(async { Ok::<_, io::Error>(Response::Ok().finish()) })
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        );
+        sleep(Millis(50)).await;
+        let _ = lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_ready();
+        sleep(Millis(50)).await;
+
+        assert!(poll_fn(|cx| Pin::new(&mut h1).poll(cx)).await.is_ok());
+        assert!(h1.inner.io.is_closed());
+        assert!(!called.get());
+
+        let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
+        assert_eq!(
+            load(&mut ClientCodec::default(), &mut buf).status,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_payload_limit_chunked() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let err = Rc::new(RefCell::new(None));
+        let err2 = err.clone();
+        let config = ServiceConfig::default().max_payload_size(10);
+        let h1 = Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config,
+                fn_service(move |mut req: Request| {
+                    let err = err2.clone();
+                    Box::pin(async move {
+                        let mut pl = req.take_payload();
+                        while let Some(item) = stream_recv(&mut pl).await {
+                            if let Err(e) = item {
+                                *err.borrow_mut() = Some(e);
+                                break;
+                            }
+                        }
+                        Ok::<_, io::Error>(Response::PayloadTooLarge().finish())
+                    })
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        );
+        crate::rt::spawn(h1);
+
+        client.write(
+            "POST /test HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n\
+             8\r\n12345678\r\n8\r\n12345678\r\n0\r\n\r\n",
+        );
+        sleep(Millis(50)).await;
+
+        assert!(matches!(*err.borrow(), Some(PayloadError::Overflow)));
+        let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
+        let head = load(&mut ClientCodec::default(), &mut buf);
+        assert_eq!(head.status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(head.headers.get(http::header::CONNECTION).unwrap(), "close");
+        client.close().await;
+        assert!(client.is_server_dropped());
     }
 
     #[crate::rt_test]
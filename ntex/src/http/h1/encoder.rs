@@ -1,5 +1,5 @@
-use std::marker::PhantomData;
-use std::{cell::Cell, cmp, io, io::Write, mem, ptr, ptr::copy_nonoverlapping, slice};
+use std::{cell::Cell, cell::RefCell, marker::PhantomData};
+use std::{cmp, io, io::Write, mem, ptr, ptr::copy_nonoverlapping, slice};
 
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
@@ -8,7 +8,7 @@ use crate::http::helpers;
 use crate::http::message::{ConnectionType, RequestHeadType};
 use crate::http::response::Response;
 use crate::http::{HeaderMap, StatusCode, Version};
-use crate::util::{BufMut, BytesMut};
+use crate::util::{BufMut, BytesMut, Either};
 
 const AVERAGE_HEADER_SIZE: usize = 30;
 
@@ -16,6 +16,7 @@ const AVERAGE_HEADER_SIZE: usize = 30;
 pub(super) struct MessageEncoder<T: MessageType> {
     pub(super) length: BodySize,
     pub(super) te: Cell<TransferEncoding>,
+    trailers: RefCell<Option<HeaderMap>>,
     _t: PhantomData<T>,
 }
 
@@ -24,6 +25,7 @@ impl<T: MessageType> Default for MessageEncoder<T> {
         MessageEncoder {
             length: BodySize::None,
             te: Cell::new(TransferEncoding::empty()),
+            trailers: RefCell::new(None),
             _t: PhantomData,
         }
     }
@@ -34,6 +36,7 @@ impl<T: MessageType> Clone for MessageEncoder<T> {
         MessageEncoder {
             length: self.length,
             te: self.te.clone(),
+            trailers: self.trailers.clone(),
             _t: PhantomData,
         }
     }
@@ -153,7 +156,9 @@ pub(super) trait MessageType: Sized {
                     remaining -= len;
                 }
                 Value::Multi(ref vec) => {
-                    for val in vec {
+                    // only one date header is allowed
+                    let count = if *key == DATE { 1 } else { vec.len() };
+                    for val in vec.iter().take(count) {
                         let v = val.as_ref();
                         let v_len = v.len();
                         let k_len = k.len();
@@ -247,11 +252,16 @@ impl MessageType for RequestHeadType {
     fn encode_status(&self, dst: &mut BytesMut) -> io::Result<()> {
         let head = self.as_ref();
         dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
+        let target = if head.absolute_form() {
+            Either::Left(&head.uri)
+        } else {
+            Either::Right(head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"))
+        };
         write!(
             helpers::Writer(dst),
             "{} {} {}",
             head.method,
-            head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
+            target,
             // only HTTP-0.9/1.1
             match head.version {
                 Version::HTTP_09 => "HTTP/0.9",
@@ -277,11 +287,24 @@ impl<T: MessageType> MessageEncoder<T> {
     /// Encode eof
     pub(super) fn encode_eof(&self, buf: &mut BytesMut) -> io::Result<()> {
         let mut te = self.te.get();
-        let result = te.encode_eof(buf);
+        let trailers = self.trailers.borrow_mut().take();
+        let result = te.encode_eof(trailers.as_ref(), buf);
         self.te.set(te);
         result
     }
 
+    /// Set trailers for current message
+    ///
+    /// Trailers could be sent only with chunked transfer encoding
+    pub(super) fn set_trailers(&self, trailers: Option<HeaderMap>) {
+        if trailers.is_some() && !self.te.get().is_chunked() {
+            log::debug!("Transfer encoding is not chunked, drop trailers");
+            *self.trailers.borrow_mut() = None;
+        } else {
+            *self.trailers.borrow_mut() = trailers;
+        }
+    }
+
     pub(super) fn encode(
         &self,
         dst: &mut BytesMut,
@@ -293,6 +316,8 @@ impl<T: MessageType> MessageEncoder<T> {
         ctype: ConnectionType,
         timer: &DateService,
     ) -> io::Result<()> {
+        self.trailers.borrow_mut().take();
+
         // transfer encoding
         if !head {
             self.te.set(match length {
@@ -316,6 +341,31 @@ impl<T: MessageType> MessageEncoder<T> {
     }
 }
 
+/// Encode informational (1xx) response
+pub(in crate::http) fn encode_informational(
+    status: StatusCode,
+    headers: &HeaderMap,
+    dst: &mut BytesMut,
+) {
+    let reason = match status.as_u16() {
+        103 => "Early Hints",
+        _ => status.canonical_reason().unwrap_or(""),
+    }
+    .as_bytes();
+    dst.reserve(256 + headers.len() * AVERAGE_HEADER_SIZE + reason.len());
+
+    write_status_line(Version::HTTP_11, status.as_u16(), dst);
+    dst.extend_from_slice(reason);
+    dst.extend_from_slice(b"\r\n");
+    for (key, value) in headers {
+        dst.extend_from_slice(key.as_str().as_bytes());
+        dst.extend_from_slice(b": ");
+        dst.extend_from_slice(value.as_ref());
+        dst.extend_from_slice(b"\r\n");
+    }
+    dst.extend_from_slice(b"\r\n");
+}
+
 /// Encoders to handle different Transfer-Encodings.
 #[derive(Debug, Copy, Clone)]
 pub(super) struct TransferEncoding {
@@ -365,6 +415,11 @@ impl TransferEncoding {
         }
     }
 
+    #[inline]
+    pub(super) fn is_chunked(&self) -> bool {
+        matches!(self.kind, TransferEncodingKind::Chunked(_))
+    }
+
     /// Encode message. Return `EOF` state of encoder
     #[inline]
     pub(super) fn encode(&mut self, msg: &[u8], buf: &mut BytesMut) -> io::Result<bool> {
@@ -415,7 +470,11 @@ impl TransferEncoding {
 
     /// Encode eof. Return `EOF` state of encoder
     #[inline]
-    pub(super) fn encode_eof(&mut self, buf: &mut BytesMut) -> io::Result<()> {
+    pub(super) fn encode_eof(
+        &mut self,
+        trailers: Option<&HeaderMap>,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
         match self.kind {
             TransferEncodingKind::Eof => Ok(()),
             TransferEncodingKind::Length(rem) => {
@@ -427,7 +486,19 @@ impl TransferEncoding {
             }
             TransferEncodingKind::Chunked(eof) => {
                 if !eof {
-                    buf.extend_from_slice(b"0\r\n\r\n");
+                    if let Some(trailers) = trailers {
+                        buf.extend_from_slice(b"0\r\n");
+                        for (key, value) in trailers {
+                            buf.reserve(key.as_str().len() + value.len() + 4);
+                            buf.extend_from_slice(key.as_str().as_bytes());
+                            buf.extend_from_slice(b": ");
+                            buf.extend_from_slice(value.as_ref());
+                            buf.extend_from_slice(b"\r\n");
+                        }
+                        buf.extend_from_slice(b"\r\n");
+                    } else {
+                        buf.extend_from_slice(b"0\r\n\r\n");
+                    }
                     self.kind = TransferEncodingKind::Chunked(true);
                 }
                 Ok(())
@@ -585,7 +656,7 @@ mod tests {
 
     use super::*;
     use crate::http::header::{HeaderValue, AUTHORIZATION};
-    use crate::http::RequestHead;
+    use crate::http::{RequestHead, Uri};
     use crate::util::Bytes;
 
     #[test]
@@ -599,6 +670,29 @@ mod tests {
         assert_eq!(bytes.split(), Bytes::from_static(b"4\r\ntest\r\n0\r\n\r\n"));
     }
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            crate::http::header::HeaderName::from_static("x-checksum"),
+            HeaderValue::from_static("1234"),
+        );
+        assert!(!enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_eof(Some(&trailers), &mut bytes).unwrap();
+        assert_eq!(
+            bytes.split(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\nx-checksum: 1234\r\n\r\n")
+        );
+
+        // trailers are ignored for non chunked encoding
+        let mut enc = TransferEncoding::eof();
+        assert!(!enc.encode(b"test", &mut bytes).ok().unwrap());
+        enc.encode_eof(Some(&trailers), &mut bytes).unwrap();
+        assert_eq!(bytes.split(), Bytes::from_static(b"test"));
+    }
+
     #[test]
     fn test_extra_headers() {
         let mut bytes = BytesMut::with_capacity(2048);
@@ -632,6 +726,47 @@ mod tests {
         assert!(data.contains("date: date\r\n"));
     }
 
+    #[test]
+    fn test_absolute_form() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let head = || RequestHead {
+            uri: Uri::from_static("http://example.com:8080/path?q=1"),
+            ..Default::default()
+        };
+        let _ = RequestHeadType::Owned(head()).encode_status(&mut bytes);
+        assert_eq!(bytes.split(), Bytes::from_static(b"GET /path?q=1 HTTP/1.1"));
+
+        let mut head = head();
+        head.set_absolute_form();
+        let _ = RequestHeadType::Owned(head).encode_status(&mut bytes);
+        assert_eq!(
+            bytes.split(),
+            Bytes::from_static(b"GET http://example.com:8080/path?q=1 HTTP/1.1")
+        );
+    }
+
+    #[test]
+    fn test_date_header() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let mut head = RequestHead::default();
+        head.headers.append(DATE, HeaderValue::from_static("date1"));
+        head.headers.append(DATE, HeaderValue::from_static("date2"));
+        let head = RequestHeadType::Owned(head);
+
+        let _ = head.encode_headers(
+            &mut bytes,
+            Version::HTTP_11,
+            BodySize::Empty,
+            ConnectionType::Close,
+            &DateService::default(),
+        );
+        let data = String::from_utf8(Vec::from(bytes.split().as_ref())).unwrap();
+        assert_eq!(data.matches("date: ").count(), 1);
+        assert!(data.contains("date: date1\r\n"));
+    }
+
     #[test]
     fn test_write_content_length() {
         let mut bytes = BytesMut::new();
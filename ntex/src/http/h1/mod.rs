@@ -19,9 +19,11 @@ pub use self::payload::Payload;
 pub use self::service::{H1Service, H1ServiceHandler};
 pub use self::upgrade::UpgradeHandler;
 
+pub(crate) use self::decoder::MAX_HEADERS;
 pub(super) use self::dispatcher::Dispatcher;
+pub(super) use self::encoder::encode_informational;
 
-const MAX_BUFFER_SIZE: usize = 32_768;
+pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
 
 #[derive(Debug)]
 /// Codec message
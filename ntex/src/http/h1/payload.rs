@@ -3,7 +3,7 @@ use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 use std::{cell::RefCell, collections::VecDeque, pin::Pin};
 
-use crate::http::error::PayloadError;
+use crate::http::{error::PayloadError, header::HeaderMap};
 use crate::{task::LocalWaker, util::Bytes, util::Stream};
 
 /// max buffer size 32k
@@ -62,6 +62,14 @@ impl Payload {
         self.inner.borrow_mut().unread_data(data);
     }
 
+    /// Trailer fields of chunked payload
+    ///
+    /// Trailers are available only after payload reached eof.
+    #[inline]
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.inner.borrow().trailers.clone()
+    }
+
     #[inline]
     pub fn readany(
         &mut self,
@@ -115,6 +123,12 @@ impl PayloadSender {
         }
     }
 
+    pub fn feed_trailers(&mut self, trailers: HeaderMap) {
+        if let Some(shared) = self.inner.upgrade() {
+            shared.borrow_mut().trailers = Some(trailers);
+        }
+    }
+
     pub(super) fn poll_data_required(&self, cx: &mut Context<'_>) -> PayloadStatus {
         // we check only if Payload (other side) is alive,
         // otherwise always return true (consume payload)
@@ -138,6 +152,7 @@ struct Inner {
     err: Option<PayloadError>,
     need_read: bool,
     items: VecDeque<Bytes>,
+    trailers: Option<HeaderMap>,
     task: LocalWaker,
     io_task: LocalWaker,
 }
@@ -149,6 +164,7 @@ impl Inner {
             len: 0,
             err: None,
             items: VecDeque::new(),
+            trailers: None,
             need_read: true,
             task: LocalWaker::new(),
             io_task: LocalWaker::new(),
@@ -220,4 +236,29 @@ mod tests {
             poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
         );
     }
+
+    #[crate::rt_test]
+    async fn test_trailers() {
+        let (mut sender, mut payload) = Payload::create(false);
+        assert!(payload.trailers().is_none());
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            crate::http::header::HeaderName::from_static("x-checksum"),
+            crate::http::header::HeaderValue::from_static("1234"),
+        );
+        sender.feed_data(Bytes::from("data"));
+        sender.feed_trailers(trailers);
+        sender.feed_eof();
+
+        assert_eq!(
+            Bytes::from("data"),
+            poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
+        );
+        assert!(poll_fn(|cx| payload.readany(cx)).await.is_none());
+        assert_eq!(
+            payload.trailers().unwrap().get("x-checksum").unwrap(),
+            "1234"
+        );
+    }
 }
@@ -5,4 +5,4 @@ mod service;
 pub use self::payload::Payload;
 pub use self::service::H2Service;
 
-pub(in crate::http) use self::service::handle;
+pub(in crate::http) use self::service::{encode_informational, handle};
@@ -4,8 +4,9 @@ use std::{cell::RefCell, collections::VecDeque, pin::Pin, rc::Rc, rc::Weak};
 
 use ntex_h2::{self as h2};
 
+use crate::http::{error::PayloadError, header::HeaderMap};
+use crate::task::LocalWaker;
 use crate::util::{poll_fn, Bytes, Stream};
-use crate::{http::error::PayloadError, task::LocalWaker};
 
 /// Buffered stream of byte chunks
 ///
@@ -39,6 +40,14 @@ impl Payload {
         )
     }
 
+    /// Trailer fields of the stream
+    ///
+    /// Trailers are available only after payload reached eof.
+    #[inline]
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.inner.borrow().trailers.clone()
+    }
+
     #[inline]
     pub async fn read(&self) -> Option<Result<Bytes, PayloadError>> {
         poll_fn(|cx| self.poll_read(cx)).await
@@ -91,6 +100,15 @@ impl PayloadSender {
         }
     }
 
+    pub fn feed_trailers(&mut self, trailers: HeaderMap) {
+        if let Some(shared) = self.inner.upgrade() {
+            let mut inner = shared.borrow_mut();
+            inner.trailers = Some(trailers);
+            inner.feed_eof(Bytes::new());
+            self.inner = Weak::new();
+        }
+    }
+
     pub fn feed_data(&mut self, data: Bytes, cap: h2::Capacity) {
         if let Some(shared) = self.inner.upgrade() {
             shared.borrow_mut().feed_data(data, cap)
@@ -110,6 +128,7 @@ struct Inner {
     cap: h2::Capacity,
     err: Option<PayloadError>,
     items: VecDeque<Bytes>,
+    trailers: Option<HeaderMap>,
     task: LocalWaker,
     io_task: LocalWaker,
     stream: Option<h2::Stream>,
@@ -122,6 +141,7 @@ impl Inner {
             eof: false,
             err: None,
             stream: None,
+            trailers: None,
             items: VecDeque::new(),
             task: LocalWaker::new(),
             io_task: LocalWaker::new(),
@@ -1,13 +1,13 @@
 use std::{cell::RefCell, io, task::Context, task::Poll};
 use std::{marker::PhantomData, mem, rc::Rc};
 
-use ntex_h2::{self as h2, frame::StreamId, server};
+use ntex_h2::{self as h2, frame::Reason, frame::StreamId, server};
 
 use crate::http::body::{BodySize, MessageBody};
 use crate::http::config::{DispatcherConfig, ServiceConfig};
-use crate::http::error::{DispatchError, H2Error, ResponseError};
+use crate::http::error::{DispatchError, H2Error, PayloadError, ResponseError};
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::http::message::{CurrentIo, ResponseHead};
+use crate::http::message::{CurrentIo, H2Response, ResponseHead};
 use crate::http::{DateService, Method, Request, Response, StatusCode, Uri, Version};
 use crate::io::{types, Filter, Io, IoBoxed, IoRef};
 use crate::service::{IntoServiceFactory, Service, ServiceCtx, ServiceFactory};
@@ -241,7 +241,7 @@ impl Service<h2::ControlMessage<H2Error>> for ControlService {
 struct PublishService<S: Service<Request>, B, X, U> {
     io: IoRef,
     config: Rc<DispatcherConfig<S, X, U>>,
-    streams: RefCell<HashMap<StreamId, PayloadSender>>,
+    streams: RefCell<HashMap<StreamId, (PayloadSender, usize)>>,
     _t: PhantomData<B>,
 }
 
@@ -292,7 +292,7 @@ where
                 let pl = if !eof {
                     log::debug!("Creating local payload stream for {:?}", msg.id());
                     let (sender, payload) = Payload::create(msg.stream().empty_capacity());
-                    self.streams.borrow_mut().insert(msg.id(), sender);
+                    self.streams.borrow_mut().insert(msg.id(), (sender, 0));
                     Some(payload)
                 } else {
                     None
@@ -301,8 +301,19 @@ where
             }
             h2::MessageKind::Data(data, cap) => {
                 log::debug!("Got data chunk for {:?}: {:?}", msg.id(), data.len());
-                if let Some(sender) = self.streams.borrow_mut().get_mut(&msg.id()) {
-                    sender.feed_data(data, cap)
+                let mut streams = self.streams.borrow_mut();
+                if let Some((sender, size)) = streams.get_mut(&msg.id()) {
+                    let max = self.config.payload_max_size;
+                    *size += data.len();
+                    if max != 0 && *size > max {
+                        // abort payload and reset stream, peer must stop sending data
+                        log::debug!("Payload size is over limit for {:?}", msg.id());
+                        sender.set_error(PayloadError::Overflow);
+                        streams.remove(&msg.id());
+                        msg.stream().reset(Reason::CANCEL);
+                    } else {
+                        sender.feed_data(data, cap)
+                    }
                 } else {
                     log::error!("Payload stream does not exists for {:?}", msg.id());
                 };
@@ -310,13 +321,13 @@ where
             }
             h2::MessageKind::Eof(item) => {
                 log::debug!("Got payload eof for {:?}: {:?}", msg.id(), item);
-                if let Some(mut sender) = self.streams.borrow_mut().remove(&msg.id()) {
+                if let Some((mut sender, _)) = self.streams.borrow_mut().remove(&msg.id()) {
                     match item {
                         h2::StreamEof::Data(data) => {
                             sender.feed_eof(data);
                         }
-                        h2::StreamEof::Trailers(_) => {
-                            sender.feed_eof(Bytes::new());
+                        h2::StreamEof::Trailers(trailers) => {
+                            sender.feed_trailers(trailers);
                         }
                         h2::StreamEof::Error(err) => sender.set_error(err.into()),
                     }
@@ -325,7 +336,7 @@ where
             }
             h2::MessageKind::Disconnect(err) => {
                 log::debug!("Connection is disconnected {:?}", err);
-                if let Some(mut sender) = self.streams.borrow_mut().remove(&msg.id()) {
+                if let Some((mut sender, _)) = self.streams.borrow_mut().remove(&msg.id()) {
                     sender.set_error(io::Error::new(io::ErrorKind::Other, err).into());
                 }
                 return Either::Right(Ready::Ok(()));
@@ -363,13 +374,29 @@ where
             head.version = Version::HTTP_2;
             head.method = method;
             head.headers = headers;
-            head.io = CurrentIo::Ref(io);
-
-            let (mut res, mut body) = match cfg.service.call(req).await {
-                Ok(res) => res.into().into_parts(),
-                Err(err) => {
-                    let (res, body) = Response::from(&err).into_parts();
-                    (res, body.into_body())
+            let state = Rc::new(H2Response::new(io, msg.stream().clone()));
+            head.io = CurrentIo::H2(state.clone());
+
+            // reject payloads with declared size over the limit
+            let too_large = cfg.payload_max_size != 0
+                && req
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(|len| len > cfg.payload_max_size as u64)
+                    .unwrap_or(false);
+
+            let (mut res, mut body) = if too_large {
+                let (res, body) = Response::PayloadTooLarge().finish().into_parts();
+                (res, body.into_body())
+            } else {
+                match cfg.service.call(req).await {
+                    Ok(res) => res.into().into_parts(),
+                    Err(err) => {
+                        let (res, body) = Response::from(&err).into_parts();
+                        (res, body.into_body())
+                    }
                 }
             };
 
@@ -380,6 +407,8 @@ where
             log::debug!("Received service response: {:?} payload: {:?}", head, size);
 
             let hdrs = mem::replace(&mut head.headers, HeaderMap::new());
+            let trailers = head.take_trailers();
+            state.started.set(true);
             if size.is_eof() || is_head_req {
                 msg.stream().send_response(head.status, hdrs, true)?;
             } else {
@@ -389,7 +418,11 @@ where
                     match poll_fn(|cx| body.poll_next_chunk(cx)).await {
                         None => {
                             log::debug!("{:?} closing payload stream", msg.id());
-                            msg.stream().send_payload(Bytes::new(), true).await?;
+                            if let Some(trailers) = trailers {
+                                msg.stream().send_trailers(trailers);
+                            } else {
+                                msg.stream().send_payload(Bytes::new(), true).await?;
+                            }
                             break;
                         }
                         Some(Ok(chunk)) => {
@@ -456,7 +489,7 @@ fn prepare_response(timer: &DateService, head: &mut ResponseHead, size: &mut Bod
     head.headers.remove(PROXY_CONNECTION);
 
     // set date header
-    if !head.headers.contains_key(header::DATE) {
+    if timer.enabled() && !head.headers.contains_key(header::DATE) {
         let mut bytes = BytesMut::with_capacity(29);
         timer.set_date(|date| bytes.extend_from_slice(date));
         head.headers.insert(header::DATE, unsafe {
@@ -464,3 +497,31 @@ fn prepare_response(timer: &DateService, head: &mut ResponseHead, size: &mut Bod
         });
     }
 }
+
+/// Encode informational (1xx) response as headers frame of the stream
+pub(in crate::http) fn encode_informational(
+    id: StreamId,
+    status: StatusCode,
+    headers: &HeaderMap,
+    dst: &mut BytesMut,
+) {
+    let mut headers = headers.clone();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::CONNECTION);
+    headers.remove(header::TRANSFER_ENCODING);
+    headers.remove(header::UPGRADE);
+    headers.remove(KEEP_ALIVE);
+    headers.remove(PROXY_CONNECTION);
+
+    let pseudo = h2::frame::PseudoHeaders::response(status);
+    let mut hdrs = h2::frame::Headers::new(id, pseudo, headers, false);
+    hdrs.set_end_headers();
+
+    // encoder without dynamic table, connection's hpack state is not affected
+    let mut encoder = h2::hpack::Encoder::new(0, 0);
+    hdrs.encode(
+        &mut encoder,
+        dst,
+        h2::frame::DEFAULT_MAX_FRAME_SIZE as usize,
+    );
+}
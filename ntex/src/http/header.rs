@@ -1,5 +1,13 @@
 //! Various http headers
 
+use std::{borrow::Cow, fmt};
+
+use percent_encoding::{
+    percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC,
+};
+
+use crate::http::error::ParseError;
+
 pub use ntex_http::header::{HeaderName, HeaderValue, InvalidHeaderValue};
 
 pub use ntex_http::header::*;
@@ -18,6 +26,8 @@ pub enum ContentEncoding {
     Deflate,
     /// Gzip algorithm
     Gzip,
+    /// A format using the Zstandard algorithm
+    Zstd,
     /// Indicates the identity function (i.e. no compression, nor modification)
     Identity,
 }
@@ -36,6 +46,7 @@ impl ContentEncoding {
             ContentEncoding::Br => "br",
             ContentEncoding::Gzip => "gzip",
             ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Zstd => "zstd",
             ContentEncoding::Identity | ContentEncoding::Auto => "identity",
         }
     }
@@ -45,6 +56,7 @@ impl ContentEncoding {
     pub fn quality(self) -> f64 {
         match self {
             ContentEncoding::Br => 1.1,
+            ContentEncoding::Zstd => 1.05,
             ContentEncoding::Gzip => 1.0,
             ContentEncoding::Deflate => 0.9,
             ContentEncoding::Identity | ContentEncoding::Auto => 0.1,
@@ -62,12 +74,533 @@ impl<'a> From<&'a str> for ContentEncoding {
             ContentEncoding::Gzip
         } else if s.eq_ignore_ascii_case("deflate") {
             ContentEncoding::Deflate
+        } else if s.eq_ignore_ascii_case("zstd") {
+            ContentEncoding::Zstd
         } else {
             ContentEncoding::Identity
         }
     }
 }
 
+/// `Content-Disposition` disposition type
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DispositionType {
+    /// Content may be displayed inline
+    Inline,
+    /// Content should be downloaded and saved locally
+    Attachment,
+    /// Part of a `multipart/form-data` body
+    FormData,
+    /// Extension type, stored lowercased
+    Ext(String),
+}
+
+impl DispositionType {
+    fn as_str(&self) -> &str {
+        match self {
+            DispositionType::Inline => "inline",
+            DispositionType::Attachment => "attachment",
+            DispositionType::FormData => "form-data",
+            DispositionType::Ext(ref s) => s.as_str(),
+        }
+    }
+}
+
+/// `Content-Disposition` parameter
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DispositionParam {
+    /// `name` parameter
+    Name(String),
+    /// `filename` parameter
+    Filename(String),
+    /// Decoded value of `filename*` extended parameter
+    FilenameExt(String),
+    /// Any other parameter, name is stored lowercased
+    Unknown(String, String),
+}
+
+/// Typed `Content-Disposition` header, see RFC 6266 and RFC 7578
+///
+/// ```rust
+/// use ntex::http::header::{ContentDisposition, HeaderValue};
+///
+/// let cd = ContentDisposition::attachment("résumé.pdf");
+/// let value = HeaderValue::from(&cd);
+///
+/// let cd = ContentDisposition::parse(&value).unwrap();
+/// assert_eq!(cd.get_filename(), Some("résumé.pdf"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContentDisposition {
+    /// Disposition type
+    pub disposition: DispositionType,
+    /// Disposition parameters
+    pub parameters: Vec<DispositionParam>,
+}
+
+/// Characters that must be percent-encoded in RFC 5987 `ext-value`
+const EXT_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+impl ContentDisposition {
+    /// Create `Content-Disposition` without parameters
+    pub fn new(disposition: DispositionType) -> Self {
+        ContentDisposition {
+            disposition,
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Create `attachment` disposition with filename.
+    ///
+    /// Non-ASCII filenames are sent as `filename*` extended parameter
+    /// with an ASCII `filename` fallback.
+    pub fn attachment<T: Into<String>>(filename: T) -> Self {
+        ContentDisposition::new(DispositionType::Attachment).filename(filename)
+    }
+
+    /// Create `inline` disposition with filename.
+    pub fn inline<T: Into<String>>(filename: T) -> Self {
+        ContentDisposition::new(DispositionType::Inline).filename(filename)
+    }
+
+    /// Set `name` parameter
+    pub fn name<T: Into<String>>(mut self, name: T) -> Self {
+        self.parameters
+            .retain(|p| !matches!(p, DispositionParam::Name(_)));
+        self.parameters.push(DispositionParam::Name(name.into()));
+        self
+    }
+
+    /// Set `filename` parameter
+    pub fn filename<T: Into<String>>(mut self, filename: T) -> Self {
+        self.parameters.retain(|p| {
+            !matches!(
+                p,
+                DispositionParam::Filename(_) | DispositionParam::FilenameExt(_)
+            )
+        });
+        self.parameters
+            .push(DispositionParam::Filename(filename.into()));
+        self
+    }
+
+    /// Parse `Content-Disposition` header value.
+    ///
+    /// Parser accepts unquoted values, raw UTF-8 values and parameters
+    /// without value, as sent by browsers.
+    pub fn parse(value: &HeaderValue) -> Result<Self, ParseError> {
+        let raw = value.as_bytes();
+        let s = match std::str::from_utf8(raw) {
+            Ok(s) => Cow::Borrowed(s),
+            Err(_) => Cow::Owned(raw.iter().map(|b| *b as char).collect::<String>()),
+        };
+
+        let (disposition, mut rest) = match s.find(';') {
+            Some(idx) => (s[..idx].trim(), &s[idx + 1..]),
+            None => (s.trim(), ""),
+        };
+        if disposition.is_empty() {
+            return Err(ParseError::Header);
+        }
+        let disposition = if disposition.eq_ignore_ascii_case("inline") {
+            DispositionType::Inline
+        } else if disposition.eq_ignore_ascii_case("attachment") {
+            DispositionType::Attachment
+        } else if disposition.eq_ignore_ascii_case("form-data") {
+            DispositionType::FormData
+        } else {
+            DispositionType::Ext(disposition.to_ascii_lowercase())
+        };
+
+        let mut parameters = Vec::new();
+        while !rest.is_empty() {
+            let (name, value, tail) = split_param(rest);
+            rest = tail;
+
+            let name = name.to_ascii_lowercase();
+            match name.as_str() {
+                "" => continue,
+                "name" => parameters.push(DispositionParam::Name(value)),
+                "filename" => parameters.push(DispositionParam::Filename(value)),
+                "filename*" => {
+                    if let Some(value) = decode_ext_value(&value) {
+                        parameters.push(DispositionParam::FilenameExt(value))
+                    }
+                }
+                _ => parameters.push(DispositionParam::Unknown(name, value)),
+            }
+        }
+
+        Ok(ContentDisposition {
+            disposition,
+            parameters,
+        })
+    }
+
+    /// Disposition type is `attachment`
+    pub fn is_attachment(&self) -> bool {
+        self.disposition == DispositionType::Attachment
+    }
+
+    /// Disposition type is `inline`
+    pub fn is_inline(&self) -> bool {
+        self.disposition == DispositionType::Inline
+    }
+
+    /// Disposition type is `form-data`
+    pub fn is_form_data(&self) -> bool {
+        self.disposition == DispositionType::FormData
+    }
+
+    /// Value of `name` parameter
+    pub fn get_name(&self) -> Option<&str> {
+        self.parameters.iter().find_map(|p| match p {
+            DispositionParam::Name(ref name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Filename, `filename*` is preferred over `filename`
+    pub fn get_filename(&self) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find_map(|p| match p {
+                DispositionParam::FilenameExt(ref name) => Some(name.as_str()),
+                _ => None,
+            })
+            .or_else(|| {
+                self.parameters.iter().find_map(|p| match p {
+                    DispositionParam::Filename(ref name) => Some(name.as_str()),
+                    _ => None,
+                })
+            })
+    }
+}
+
+/// Split first parameter, returns name, value and rest of the input
+fn split_param(s: &str) -> (&str, String, &str) {
+    let s = s.trim_start();
+    let end = s.find(['=', ';']).unwrap_or(s.len());
+    let name = s[..end].trim();
+    if !s[end..].starts_with('=') {
+        // parameter without value
+        return (name, String::new(), s.get(end + 1..).unwrap_or(""));
+    }
+
+    let s = s[end + 1..].trim_start();
+    if let Some(s) = s.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = s.char_indices();
+        let mut rest = "";
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, c)) = chars.next() {
+                        value.push(c);
+                    }
+                }
+                '"' => {
+                    rest = &s[idx + 1..];
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+        // skip garbage after closing quote
+        let rest = rest.find(';').map(|idx| &rest[idx + 1..]).unwrap_or("");
+        (name, value, rest)
+    } else {
+        match s.find(';') {
+            Some(idx) => (name, s[..idx].trim().to_string(), &s[idx + 1..]),
+            None => (name, s.trim().to_string(), ""),
+        }
+    }
+}
+
+/// Decode RFC 5987 `ext-value`, `charset'[language]'value-chars`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _lang = parts.next()?;
+    let bytes: Vec<u8> = percent_decode_str(parts.next()?).collect();
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else {
+        let encoding = encoding_rs::Encoding::for_label(charset.as_bytes())?;
+        Some(encoding.decode_without_bom_handling(&bytes).0.into_owned())
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, s: &str, ascii: bool) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{}", c)?,
+            c if c.is_ascii_control() || (ascii && !c.is_ascii()) => f.write_str("_")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for ContentDisposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.disposition.as_str())?;
+
+        let has_ext = self
+            .parameters
+            .iter()
+            .any(|p| matches!(p, DispositionParam::FilenameExt(_)));
+
+        for param in &self.parameters {
+            match param {
+                DispositionParam::Name(ref name) => {
+                    f.write_str("; name=")?;
+                    write_quoted(f, name, false)?;
+                }
+                DispositionParam::Filename(ref name) => {
+                    f.write_str("; filename=")?;
+                    write_quoted(f, name, true)?;
+                    if !has_ext && !name.is_ascii() {
+                        write!(
+                            f,
+                            "; filename*=UTF-8''{}",
+                            utf8_percent_encode(name, EXT_VALUE)
+                        )?;
+                    }
+                }
+                DispositionParam::FilenameExt(ref name) => {
+                    write!(
+                        f,
+                        "; filename*=UTF-8''{}",
+                        utf8_percent_encode(name, EXT_VALUE)
+                    )?;
+                }
+                DispositionParam::Unknown(ref name, ref value) => {
+                    write!(f, "; {}=", name)?;
+                    write_quoted(f, value, false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a ContentDisposition> for HeaderValue {
+    fn from(cd: &'a ContentDisposition) -> HeaderValue {
+        // control characters are replaced during formatting
+        HeaderValue::try_from(cd.to_string()).unwrap()
+    }
+}
+
+impl From<ContentDisposition> for HeaderValue {
+    fn from(cd: ContentDisposition) -> HeaderValue {
+        HeaderValue::from(&cd)
+    }
+}
+
+/// Single element of `Forwarded` header
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ForwardedElement {
+    /// `by` parameter, interface where the request came in to the proxy
+    pub by: Option<String>,
+    /// `for` parameter, client that initiated the request
+    ///
+    /// Value is either ip address with optional port (IPv6 addresses are
+    /// enclosed in brackets), obfuscated identifier or `unknown`.
+    pub for_: Option<String>,
+    /// `host` parameter, original `Host` request header
+    pub host: Option<String>,
+    /// `proto` parameter, protocol used to make the request
+    pub proto: Option<String>,
+}
+
+/// Typed `Forwarded` header, see RFC 7239
+///
+/// ```rust
+/// use ntex::http::header::{Forwarded, HeaderValue};
+///
+/// let value = HeaderValue::from_static(r#"for="[2001:db8::1]:4711";proto=https, for=_hidden"#);
+/// let fwd = Forwarded::parse(&value);
+/// assert_eq!(fwd.elements[0].for_.as_deref(), Some("[2001:db8::1]:4711"));
+/// assert_eq!(fwd.elements[1].for_.as_deref(), Some("_hidden"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Forwarded {
+    /// Forwarded elements, from the client to the last proxy
+    pub elements: Vec<ForwardedElement>,
+}
+
+impl Forwarded {
+    /// Parse `Forwarded` header value.
+    ///
+    /// Malformed elements are skipped.
+    pub fn parse(value: &HeaderValue) -> Self {
+        let mut fwd = Forwarded::default();
+        fwd.extend(value);
+        fwd
+    }
+
+    /// Parse all `Forwarded` headers from header map.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut fwd = Forwarded::default();
+        for value in headers.get_all(&FORWARDED) {
+            fwd.extend(value);
+        }
+        fwd
+    }
+
+    fn extend(&mut self, value: &HeaderValue) {
+        if let Ok(s) = value.to_str() {
+            self.elements
+                .extend(split_quoted(s, ',').into_iter().filter_map(parse_element));
+        }
+    }
+}
+
+/// Split by separator, ignoring separators inside of quoted strings
+fn split_quoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if !quoted && c == sep {
+            parts.push(&s[start..idx]);
+            start = idx + 1;
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn parse_element(s: &str) -> Option<ForwardedElement> {
+    let mut el = ForwardedElement::default();
+    let mut empty = true;
+
+    for pair in split_quoted(s, ';') {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+        let (name, value) = pair.split_once('=')?;
+        let value = parse_value(value)?;
+
+        let slot = match name.to_ascii_lowercase().as_str() {
+            "by" => &mut el.by,
+            "for" => &mut el.for_,
+            "host" => &mut el.host,
+            "proto" => &mut el.proto,
+            "" => return None,
+            _ => continue,
+        };
+        // parameter must not occur more than once
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(value);
+        empty = false;
+    }
+
+    if empty {
+        None
+    } else {
+        Some(el)
+    }
+}
+
+fn parse_value(s: &str) -> Option<String> {
+    if let Some(s) = s.strip_prefix('"') {
+        let s = s.strip_suffix('"')?;
+        let mut value = String::with_capacity(s.len());
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => value.push(chars.next()?),
+                '"' => return None,
+                c => value.push(c),
+            }
+        }
+        Some(value)
+    } else if !s.is_empty() && s.bytes().all(is_token) {
+        Some(s.to_string())
+    } else {
+        None
+    }
+}
+
+fn is_token(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, name: &str, value: &str) -> fmt::Result {
+    if !value.is_empty() && value.bytes().all(is_token) {
+        write!(f, "{}={}", name, value)
+    } else {
+        write!(f, "{}=", name)?;
+        write_quoted(f, value, false)
+    }
+}
+
+impl fmt::Display for Forwarded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, el) in self.elements.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            let params = [
+                ("for", &el.for_),
+                ("proto", &el.proto),
+                ("host", &el.host),
+                ("by", &el.by),
+            ];
+            let mut first = true;
+            for (name, value) in params {
+                if let Some(ref value) = value {
+                    if !first {
+                        f.write_str(";")?;
+                    }
+                    first = false;
+                    write_value(f, name, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a Forwarded> for HeaderValue {
+    fn from(fwd: &'a Forwarded) -> HeaderValue {
+        // control characters are replaced during formatting
+        HeaderValue::try_from(fwd.to_string()).unwrap()
+    }
+}
+
+impl From<Forwarded> for HeaderValue {
+    fn from(fwd: Forwarded) -> HeaderValue {
+        HeaderValue::from(&fwd)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,8 +608,142 @@ mod tests {
     #[test]
     fn encoding() {
         assert!(ContentEncoding::Br.is_compressed());
+        assert!(ContentEncoding::Zstd.is_compressed());
+        assert_eq!(ContentEncoding::from("zstd"), ContentEncoding::Zstd);
+        assert_eq!(ContentEncoding::Zstd.as_str(), "zstd");
         assert!(!ContentEncoding::Identity.is_compressed());
         assert!(!ContentEncoding::Auto.is_compressed());
         assert_eq!(format!("{:?}", ContentEncoding::Identity), "Identity");
     }
+
+    #[test]
+    fn content_disposition_parse() {
+        let cd = ContentDisposition::parse(&HeaderValue::from_static(
+            "form-data; name=\"upload\"; filename=\"a;b \\\"c\\\".txt\"",
+        ))
+        .unwrap();
+        assert!(cd.is_form_data());
+        assert_eq!(cd.get_name(), Some("upload"));
+        assert_eq!(cd.get_filename(), Some("a;b \"c\".txt"));
+
+        // unquoted values and missing values
+        let cd = ContentDisposition::parse(&HeaderValue::from_static(
+            "Attachment; filename=report.pdf ; size; creation-date=",
+        ))
+        .unwrap();
+        assert!(cd.is_attachment());
+        assert_eq!(cd.get_filename(), Some("report.pdf"));
+        assert_eq!(
+            cd.parameters[1],
+            DispositionParam::Unknown("size".to_string(), String::new())
+        );
+        assert_eq!(
+            cd.parameters[2],
+            DispositionParam::Unknown("creation-date".to_string(), String::new())
+        );
+
+        // filename* is preferred
+        let cd = ContentDisposition::parse(&HeaderValue::from_static(
+            "attachment; filename*=UTF-8''%E4%B8%AD%E6%96%87.txt; filename=\"fallback.txt\"",
+        ))
+        .unwrap();
+        assert_eq!(cd.get_filename(), Some("中文.txt"));
+
+        let cd = ContentDisposition::parse(&HeaderValue::from_static(
+            "attachment; filename*=iso-8859-1'en'%A3%20rates.txt",
+        ))
+        .unwrap();
+        assert_eq!(cd.get_filename(), Some("£ rates.txt"));
+
+        // raw utf-8 as sent by browsers
+        let cd = ContentDisposition::parse(
+            &HeaderValue::from_str("form-data; name=\"file\"; filename=\"文件.txt\"")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(cd.get_filename(), Some("文件.txt"));
+
+        assert!(ContentDisposition::parse(&HeaderValue::from_static("")).is_err());
+        assert!(ContentDisposition::parse(&HeaderValue::from_static("; name=a")).is_err());
+    }
+
+    #[test]
+    fn content_disposition_roundtrip() {
+        let cd = ContentDisposition::attachment("plain.txt");
+        let value = HeaderValue::from(&cd);
+        assert_eq!(value, "attachment; filename=\"plain.txt\"");
+        assert_eq!(ContentDisposition::parse(&value).unwrap(), cd);
+
+        for name in ["中文 文件.txt", "😀;\"quoted\".png", "a\\b"] {
+            let value = HeaderValue::from(ContentDisposition::attachment(name));
+            assert!(value.to_str().is_ok());
+            let cd = ContentDisposition::parse(&value).unwrap();
+            assert_eq!(cd.get_filename(), Some(name));
+        }
+
+        let value = HeaderValue::from(ContentDisposition::attachment("😀.png"));
+        assert_eq!(
+            value,
+            "attachment; filename=\"_.png\"; filename*=UTF-8''%F0%9F%98%80.png"
+        );
+
+        let cd = ContentDisposition::new(DispositionType::FormData)
+            .name("field")
+            .filename("f.txt");
+        let value = HeaderValue::from(&cd);
+        assert_eq!(value, "form-data; name=\"field\"; filename=\"f.txt\"");
+        assert_eq!(
+            ContentDisposition::parse(&value).unwrap().get_name(),
+            Some("field")
+        );
+    }
+
+    #[test]
+    fn forwarded_parse() {
+        let fwd = Forwarded::parse(&HeaderValue::from_static(
+            "for=1.2.3.4;proto=https;host=example.com, for=10.0.0.1",
+        ));
+        assert_eq!(fwd.elements.len(), 2);
+        assert_eq!(fwd.elements[0].for_.as_deref(), Some("1.2.3.4"));
+        assert_eq!(fwd.elements[0].proto.as_deref(), Some("https"));
+        assert_eq!(fwd.elements[0].host.as_deref(), Some("example.com"));
+        assert_eq!(fwd.elements[0].by, None);
+        assert_eq!(fwd.elements[1].for_.as_deref(), Some("10.0.0.1"));
+
+        // quoted values, ipv6 and obfuscated identifiers
+        let fwd = Forwarded::parse(&HeaderValue::from_static(
+            "For=\"[2001:db8:cafe::17]:4711\"; by=\"_hidden\", for=\"a\\\"b;c,d\"",
+        ));
+        assert_eq!(fwd.elements.len(), 2);
+        assert_eq!(
+            fwd.elements[0].for_.as_deref(),
+            Some("[2001:db8:cafe::17]:4711")
+        );
+        assert_eq!(fwd.elements[0].by.as_deref(), Some("_hidden"));
+        assert_eq!(fwd.elements[1].for_.as_deref(), Some("a\"b;c,d"));
+
+        // malformed elements are skipped
+        let fwd = Forwarded::parse(&HeaderValue::from_static(
+            "for, for=[::1], for=1.1.1.1;for=2.2.2.2, , for=3.3.3.3, for=\"unterminated",
+        ));
+        assert_eq!(fwd.elements.len(), 1);
+        assert_eq!(fwd.elements[0].for_.as_deref(), Some("3.3.3.3"));
+
+        let mut headers = HeaderMap::new();
+        headers.append(FORWARDED, HeaderValue::from_static("for=1.1.1.1"));
+        headers.append(FORWARDED, HeaderValue::from_static("for=2.2.2.2"));
+        let fwd = Forwarded::from_headers(&headers);
+        assert_eq!(fwd.elements.len(), 2);
+        assert_eq!(fwd.elements[1].for_.as_deref(), Some("2.2.2.2"));
+    }
+
+    #[test]
+    fn forwarded_roundtrip() {
+        let value = HeaderValue::from_static(
+            "for=\"[2001:db8::1]:80\";proto=https;host=example.com, for=unknown;by=_proxy",
+        );
+        let fwd = Forwarded::parse(&value);
+        assert_eq!(HeaderValue::from(&fwd), value);
+        assert_eq!(Forwarded::parse(&HeaderValue::from(fwd.clone())), fwd);
+    }
 }
@@ -1,11 +1,11 @@
-use std::{cell::Ref, cell::RefCell, cell::RefMut, net, rc::Rc};
+use std::{cell::Cell, cell::Ref, cell::RefCell, cell::RefMut, fmt, io, net, rc::Rc};
 
 use bitflags::bitflags;
 
 use crate::http::header::HeaderMap;
-use crate::http::{h1::Codec, Method, StatusCode, Uri, Version};
+use crate::http::{h1, h1::Codec, h2, Method, StatusCode, Uri, Version};
 use crate::io::{types, IoBoxed, IoRef};
-use crate::util::Extensions;
+use crate::util::{BytesMut, Extensions};
 
 /// Represents various types of connection
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -25,6 +25,8 @@ bitflags! {
         const UPGRADE     = 0b0000_0100;
         const EXPECT      = 0b0000_1000;
         const NO_CHUNKING = 0b0001_0000;
+        const ABSOLUTE    = 0b0010_0000;
+        const NO_RETRY    = 0b0100_0000;
     }
 }
 
@@ -36,17 +38,80 @@ pub(crate) trait Head: Default + 'static {
         F: FnOnce(&MessagePool<Self>) -> R;
 }
 
-#[derive(Clone, Debug)]
+/// Io and codec get boxed only if handler requests them
+pub(crate) type TakeIo = Box<dyn FnOnce() -> Option<Box<(IoBoxed, Codec)>>>;
+
+/// h1 request io and response state
+pub(crate) struct H1Response {
+    pub(crate) io: IoRef,
+    /// Final response is started
+    pub(crate) started: Cell<bool>,
+    /// Interim responses of pipelined request, written once
+    /// previous responses are sent
+    pub(crate) queued: RefCell<Option<BytesMut>>,
+}
+
+impl H1Response {
+    pub(crate) fn new(io: IoRef) -> Self {
+        H1Response {
+            io,
+            started: Cell::new(false),
+            queued: RefCell::new(None),
+        }
+    }
+
+    /// Create response state for request that waits for previous responses
+    pub(crate) fn pipelined(io: IoRef) -> Self {
+        H1Response {
+            io,
+            started: Cell::new(false),
+            queued: RefCell::new(Some(BytesMut::new())),
+        }
+    }
+}
+
+/// h2 request stream and response state
+pub(crate) struct H2Response {
+    pub(crate) io: IoRef,
+    pub(crate) stream: ntex_h2::StreamRef,
+    /// Final response is started
+    pub(crate) started: Cell<bool>,
+}
+
+impl H2Response {
+    pub(crate) fn new(io: IoRef, stream: ntex_h2::StreamRef) -> Self {
+        H2Response {
+            io,
+            stream,
+            started: Cell::new(false),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub(crate) enum CurrentIo {
-    Ref(IoRef),
-    Io(Rc<(IoRef, RefCell<Option<Box<(IoBoxed, Codec)>>>)>),
+    H1(Rc<H1Response>),
+    H2(Rc<H2Response>),
+    Io(Rc<(IoRef, RefCell<Option<TakeIo>>)>),
     None,
 }
 
+impl fmt::Debug for CurrentIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurrentIo::H1(ref io) => f.debug_tuple("CurrentIo::H1").field(&io.io).finish(),
+            CurrentIo::H2(ref io) => f.debug_tuple("CurrentIo::H2").field(&io.io).finish(),
+            CurrentIo::Io(ref io) => f.debug_tuple("CurrentIo::Io").field(&io.0).finish(),
+            CurrentIo::None => write!(f, "CurrentIo::None"),
+        }
+    }
+}
+
 impl CurrentIo {
     pub(crate) fn as_ref(&self) -> Option<&IoRef> {
         match self {
-            CurrentIo::Ref(ref io) => Some(io),
+            CurrentIo::H1(ref io) => Some(&io.io),
+            CurrentIo::H2(ref io) => Some(&io.io),
             CurrentIo::Io(ref io) => Some(&io.0),
             CurrentIo::None => None,
         }
@@ -62,6 +127,7 @@ pub struct RequestHead {
     pub extensions: RefCell<Extensions>,
     pub(crate) io: CurrentIo,
     pub(crate) flags: Flags,
+    pub(crate) peer_addr: Option<net::SocketAddr>,
 }
 
 impl Default for RequestHead {
@@ -73,6 +139,7 @@ impl Default for RequestHead {
             version: Version::HTTP_11,
             headers: HeaderMap::with_capacity(16),
             flags: Flags::empty(),
+            peer_addr: None,
             extensions: RefCell::new(Extensions::new()),
         }
     }
@@ -81,6 +148,7 @@ impl Default for RequestHead {
 impl Head for RequestHead {
     fn clear(&mut self) {
         self.io = CurrentIo::None;
+        self.peer_addr = None;
         self.flags = Flags::empty();
         self.headers.clear();
         self.extensions.get_mut().clear();
@@ -180,17 +248,112 @@ impl RequestHead {
         self.flags.insert(Flags::UPGRADE);
     }
 
+    #[inline]
+    /// Request target is sent in absolute form, i.e. for proxy
+    pub(crate) fn absolute_form(&self) -> bool {
+        self.flags.contains(Flags::ABSOLUTE)
+    }
+
+    #[inline]
+    pub(crate) fn set_absolute_form(&mut self) {
+        self.flags.insert(Flags::ABSOLUTE);
+    }
+
+    #[inline]
+    /// Client retry policy is not applied to the request
+    pub(crate) fn no_retry(&self) -> bool {
+        self.flags.contains(Flags::NO_RETRY)
+    }
+
+    #[inline]
+    pub(crate) fn set_no_retry(&mut self) {
+        self.flags.insert(Flags::NO_RETRY);
+    }
+
     /// Peer socket address
     ///
     /// Peer address is actual socket address, if proxy is used in front of
     /// ntex http server, then peer address would be address of this proxy.
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.io.as_ref().and_then(|io| {
-            io.query::<types::PeerAddr>()
-                .get()
-                .map(types::PeerAddr::into_inner)
-        })
+        self.io
+            .as_ref()
+            .and_then(|io| {
+                io.query::<types::PeerAddr>()
+                    .get()
+                    .map(types::PeerAddr::into_inner)
+            })
+            .or(self.peer_addr)
+    }
+
+    #[inline]
+    /// Set peer socket address
+    ///
+    /// Address from the connection's io object takes precedence,
+    /// this value is used if io does not provide peer address.
+    pub fn set_peer_addr(&mut self, addr: net::SocketAddr) {
+        self.peer_addr = Some(addr);
+    }
+
+    /// Send informational (1xx) response, i.e. `103 Early Hints`
+    ///
+    /// Interim response is written to the connection immediately, it is possible
+    /// to send several interim responses before final response. Interim responses
+    /// of pipelined requests are written after responses of previous requests. Interim responses
+    /// are skipped for HTTP/1.0 peers.
+    ///
+    /// For http/2 requests interim response is sent as headers frame
+    /// on the request's stream.
+    ///
+    /// Returns error if status is not informational or if final response
+    /// is already started.
+    pub fn send_informational(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> io::Result<()> {
+        if !status.is_informational() || status == StatusCode::SWITCHING_PROTOCOLS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Status is not informational",
+            ));
+        }
+        match self.io {
+            CurrentIo::H1(ref io) => {
+                if io.started.get() {
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "Final response is already started",
+                    ))
+                } else if self.version < Version::HTTP_11 {
+                    Ok(())
+                } else {
+                    let mut dst = BytesMut::new();
+                    h1::encode_informational(status, headers, &mut dst);
+                    // pipelined request, previous responses are not sent yet
+                    if let Some(ref mut queued) = *io.queued.borrow_mut() {
+                        queued.extend_from_slice(&dst);
+                        return Ok(());
+                    }
+                    io.io.with_write_buf(|buf| buf.extend_from_slice(&dst))
+                }
+            }
+            CurrentIo::H2(ref io) => {
+                if io.started.get() {
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "Final response is already started",
+                    ))
+                } else if io.stream.is_failed() {
+                    Err(io::Error::new(io::ErrorKind::Other, "Stream is closed"))
+                } else {
+                    let mut dst = BytesMut::new();
+                    h2::encode_informational(io.stream.id(), status, headers, &mut dst);
+                    io.io.with_write_buf(|buf| buf.extend_from_slice(&dst))
+                }
+            }
+            _ => Ok(()),
+        }
     }
 
     /// Take io and codec for current request
@@ -198,7 +361,7 @@ impl RequestHead {
     /// This objects are set only for upgrade requests
     pub fn take_io(&self) -> Option<Box<(IoBoxed, Codec)>> {
         match self.io {
-            CurrentIo::Io(ref inner) => inner.1.borrow_mut().take(),
+            CurrentIo::Io(ref inner) => inner.1.borrow_mut().take().and_then(|f| f()),
             _ => None,
         }
     }
@@ -242,6 +405,7 @@ pub struct ResponseHead {
     pub reason: Option<&'static str>,
     pub(crate) io: CurrentIo,
     pub(crate) extensions: RefCell<Extensions>,
+    trailers: Option<HeaderMap>,
     flags: Flags,
 }
 
@@ -254,6 +418,7 @@ impl ResponseHead {
             version: Version::default(),
             headers: HeaderMap::with_capacity(12),
             reason: None,
+            trailers: None,
             flags: Flags::empty(),
             io: CurrentIo::None,
             extensions: RefCell::new(Extensions::new()),
@@ -284,6 +449,26 @@ impl ResponseHead {
         &mut self.headers
     }
 
+    #[inline]
+    /// Read the message trailer fields.
+    pub fn trailers(&self) -> Option<&HeaderMap> {
+        self.trailers.as_ref()
+    }
+
+    #[inline]
+    /// Mutable reference to the message trailer fields.
+    ///
+    /// Trailers are sent only for streaming bodies, with chunked
+    /// transfer encoding for http/1.1 and as trailing headers for http/2.
+    pub fn trailers_mut(&mut self) -> &mut HeaderMap {
+        self.trailers.get_or_insert_with(HeaderMap::new)
+    }
+
+    #[inline]
+    pub(crate) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take().filter(|t| !t.is_empty())
+    }
+
     #[inline]
     /// Set connection type of the message
     pub fn set_connection_type(&mut self, ctype: ConnectionType) {
@@ -368,7 +553,7 @@ impl ResponseHead {
 
     pub(crate) fn take_io(&self) -> Option<Box<(IoBoxed, Codec)>> {
         match self.io {
-            CurrentIo::Io(ref inner) => inner.1.borrow_mut().take(),
+            CurrentIo::Io(ref inner) => inner.1.borrow_mut().take().and_then(|f| f()),
             _ => None,
         }
     }
@@ -383,7 +568,9 @@ impl Default for ResponseHead {
 impl Head for ResponseHead {
     fn clear(&mut self) {
         self.reason = None;
+        self.trailers = None;
         self.headers.clear();
+        self.extensions.get_mut().clear();
         self.io = CurrentIo::None;
         self.flags = Flags::empty();
     }
@@ -1,6 +1,6 @@
 use std::{fmt, mem, pin::Pin, task::Context, task::Poll};
 
-use super::{error::PayloadError, h1, h2};
+use super::{error::PayloadError, h1, h2, header::HeaderMap};
 use crate::util::{poll_fn, Bytes, Stream};
 
 /// Type represent boxed payload
@@ -59,6 +59,18 @@ impl Payload {
         Payload::Stream(Box::pin(stream))
     }
 
+    /// Trailer fields of the payload
+    ///
+    /// Trailers are available only after payload reached eof,
+    /// only http/1 chunked and http/2 payloads could contain trailers.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        match self {
+            Payload::H1(ref pl) => pl.trailers(),
+            Payload::H2(ref pl) => pl.trailers(),
+            Payload::None | Payload::Stream(_) => None,
+        }
+    }
+
     #[inline]
     /// Attempt to pull out the next value of this payload.
     pub async fn recv(&mut self) -> Option<Result<Bytes, PayloadError>> {
@@ -1,10 +1,10 @@
-use std::{cell::Ref, cell::RefMut, fmt, mem, net};
+use std::{cell::Ref, cell::RefMut, fmt, io, mem, net};
 
 use crate::http::header::{self, HeaderMap};
 use crate::http::httpmessage::HttpMessage;
 use crate::http::message::{Message, RequestHead};
-use crate::http::{payload::Payload, Method, Uri, Version};
-use crate::io::{types, IoRef};
+use crate::http::{payload::Payload, Method, StatusCode, Uri, Version};
+use crate::io::IoRef;
 use crate::util::Extensions;
 
 /// Request
@@ -137,11 +137,18 @@ impl Request {
     /// ntex http server, then peer address would be address of this proxy.
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.head().io.as_ref().and_then(|io| {
-            io.query::<types::PeerAddr>()
-                .get()
-                .map(types::PeerAddr::into_inner)
-        })
+        self.head().peer_addr()
+    }
+
+    /// Send informational (1xx) response, i.e. `103 Early Hints`
+    ///
+    /// See [`RequestHead::send_informational()`] for details.
+    pub fn send_informational(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> io::Result<()> {
+        self.head().send_informational(status, headers)
     }
 
     /// Get request's payload
@@ -223,4 +230,19 @@ mod tests {
         let s = format!("{:?}", req);
         assert!(s.contains("Request HTTP/1.1 GET:/index.html"));
     }
+
+    #[test]
+    fn test_peer_addr() {
+        let addr: net::SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let mut req = Request::new();
+        assert_eq!(req.peer_addr(), None);
+
+        req.head_mut().set_peer_addr(addr);
+        assert_eq!(req.peer_addr(), Some(addr));
+
+        let req = crate::http::test::TestRequest::default()
+            .peer_addr(addr)
+            .finish();
+        assert_eq!(req.peer_addr(), Some(addr));
+    }
 }
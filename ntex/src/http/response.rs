@@ -1,15 +1,19 @@
 //! Http response
-use std::{cell::Ref, cell::RefMut, error::Error, fmt, str};
+use std::{cell::Ref, cell::RefMut, error::Error, fmt, io, str};
 
 use serde::Serialize;
 
 #[cfg(feature = "cookie")]
-use coo_kie::{Cookie, CookieJar};
+use coo_kie::{Cookie, CookieJar, SameSite};
 
-use crate::http::body::{Body, BodyStream, MessageBody, ResponseBody};
+use crate::http::body::{
+    Body, BodyReader, BodySize, BodyStream, MessageBody, ResponseBody,
+};
 use crate::http::error::{HttpError, ResponseError};
-use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::http::message::{ConnectionType, Message, ResponseHead};
+use crate::http::header::{
+    self, ContentDisposition, DispositionType, HeaderMap, HeaderName, HeaderValue,
+};
+use crate::http::message::{ConnectionType, Message, RequestHead, ResponseHead};
 use crate::http::StatusCode;
 use crate::util::{Bytes, BytesMut, Extensions, Stream};
 
@@ -113,6 +117,7 @@ impl<B> Response<B> {
     /// Add a cookie to this response
     #[inline]
     pub fn add_cookie(&mut self, cookie: &Cookie<'_>) -> Result<(), HttpError> {
+        check_same_site(cookie);
         let h = &mut self.head.headers;
         HeaderValue::from_str(&cookie.to_string())
             .map(|c| {
@@ -204,7 +209,10 @@ impl<B> Response<B> {
         }
     }
 
-    /// Set a body and return previous body value
+    /// Map current body to a new one
+    ///
+    /// Closure gets mutable access to response head, so it is possible
+    /// to update headers that depend on the new body.
     pub fn map_body<F, B2>(mut self, f: F) -> Response<B2>
     where
         F: FnOnce(&mut ResponseHead, ResponseBody<B>) -> ResponseBody<B2>,
@@ -217,7 +225,7 @@ impl<B> Response<B> {
         }
     }
 
-    /// Extract response body
+    /// Extract response body, `Body::None` is left in place
     pub fn take_body(&mut self) -> ResponseBody<B> {
         self.body.take_body()
     }
@@ -241,6 +249,17 @@ impl<B: MessageBody> fmt::Debug for Response<B> {
     }
 }
 
+#[cfg(feature = "cookie")]
+/// Browsers reject `SameSite=None` cookies without `Secure` attribute
+fn check_same_site(cookie: &Cookie<'_>) {
+    if cookie.same_site() == Some(SameSite::None) && cookie.secure() != Some(true) {
+        log::warn!(
+            "Cookie {:?} has SameSite=None attribute but is not Secure, browsers may reject it",
+            cookie.name()
+        );
+    }
+}
+
 #[cfg(feature = "cookie")]
 #[derive(Debug)]
 pub struct CookieIter<'a> {
@@ -327,6 +346,32 @@ impl ResponseBuilder {
         self
     }
 
+    /// Append a trailer field.
+    ///
+    /// Trailers are sent after streaming body. Response with trailers and
+    /// sized body or with disabled chunking for http/1.1 is rejected
+    /// with internal server error.
+    pub fn trailer<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        HeaderName: TryFrom<K>,
+        HeaderValue: TryFrom<V>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            match HeaderName::try_from(key) {
+                Ok(key) => match HeaderValue::try_from(value) {
+                    Ok(value) => {
+                        parts.trailers_mut().append(key, value);
+                    }
+                    Err(e) => self.err = Some(log_error(e)),
+                },
+                Err(e) => self.err = Some(log_error(e)),
+            };
+        }
+        self
+    }
+
     /// Set a header.
     ///
     /// ```rust
@@ -433,6 +478,54 @@ impl ResponseBuilder {
         self.header(header::CONTENT_LENGTH, len)
     }
 
+    /// Set `Content-Disposition: attachment` header with filename.
+    ///
+    /// Browser saves response body to a file instead of displaying it.
+    /// Non-ASCII filenames are sent with `filename*` parameter, path
+    /// separators and control characters are replaced with `_`.
+    ///
+    /// ```rust
+    /// use ntex::http::Response;
+    ///
+    /// let res = Response::Ok()
+    ///     .content_type("text/csv")
+    ///     .attachment("report 2023.csv")
+    ///     .body("id,name\n1,test\n");
+    /// assert_eq!(
+    ///     res.headers().get("content-disposition").unwrap(),
+    ///     "attachment; filename=\"report 2023.csv\""
+    /// );
+    /// ```
+    pub fn attachment<T: AsRef<str>>(&mut self, filename: T) -> &mut Self {
+        self.disposition(DispositionType::Attachment, filename.as_ref())
+    }
+
+    /// Set `Content-Disposition: inline` header with filename.
+    ///
+    /// Browser displays response body, filename is used if user saves it.
+    /// Filename is sanitized same way as with
+    /// [`attachment()`](Self::attachment) method.
+    pub fn inline<T: AsRef<str>>(&mut self, filename: T) -> &mut Self {
+        self.disposition(DispositionType::Inline, filename.as_ref())
+    }
+
+    fn disposition(&mut self, disposition: DispositionType, filename: &str) -> &mut Self {
+        let filename: String = filename
+            .chars()
+            .map(|c| {
+                if c == '/' || c == '\\' || c.is_control() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        self.set_header(
+            header::CONTENT_DISPOSITION,
+            ContentDisposition::new(disposition).filename(filename),
+        )
+    }
+
     #[cfg(feature = "cookie")]
     /// Set a cookie
     ///
@@ -454,6 +547,7 @@ impl ResponseBuilder {
     /// }
     /// ```
     pub fn cookie(&mut self, cookie: Cookie<'_>) -> &mut Self {
+        check_same_site(&cookie);
         if self.cookies.is_none() {
             let mut jar = CookieJar::new();
             jar.add(cookie.into_owned());
@@ -534,7 +628,21 @@ impl ResponseBuilder {
     ///
     /// `ResponseBuilder` can not be used after this call.
     pub fn body<B: Into<Body>>(&mut self, body: B) -> Response {
-        self.message_body(body.into())
+        let body = body.into();
+
+        // trailers could be sent only after streaming body
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            let has_trailers = parts.trailers().map(|t| !t.is_empty()).unwrap_or(false);
+            if has_trailers && (body.size() != BodySize::Stream || !parts.chunked()) {
+                self.head.take();
+                return io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Trailers are allowed only for chunked streaming body",
+                )
+                .into();
+            }
+        }
+        self.message_body(body)
     }
 
     /// Set a body and generate `Response`.
@@ -578,27 +686,96 @@ impl ResponseBuilder {
         self.body(Body::from_message(BodyStream::new(stream)))
     }
 
+    #[inline]
+    /// Set a streaming body produced by `AsyncRead` reader and generate `Response`.
+    ///
+    /// Reader is read in chunks of `chunk_size` bytes, see [`BodyReader`].
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    pub fn streaming_reader<R>(&mut self, reader: R, chunk_size: usize) -> Response
+    where
+        R: futures_io::AsyncRead + Unpin + 'static,
+    {
+        self.body(BodyReader::with_chunk_size(reader, chunk_size))
+    }
+
     /// Set a json body and generate `Response`
     ///
     /// `ResponseBuilder` can not be used after this call.
     pub fn json<T: Serialize>(&mut self, value: &T) -> Response {
         match serde_json::to_string(value) {
             Ok(body) => {
-                let contains = if let Some(parts) = parts(&mut self.head, &self.err) {
-                    parts.headers.contains_key(header::CONTENT_TYPE)
-                } else {
-                    true
-                };
-                if !contains {
-                    self.header(header::CONTENT_TYPE, "application/json");
-                }
+                self.default_content_type("application/json");
+                self.body(Body::from(body))
+            }
+            Err(e) => e.into(),
+        }
+    }
 
+    /// Set a pretty-printed json body and generate `Response`
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    pub fn json_pretty<T: Serialize>(&mut self, value: &T) -> Response {
+        let mut body = Vec::new();
+        match serde_json::to_writer_pretty(&mut body, value) {
+            Ok(_) => {
+                self.default_content_type("application/json");
                 self.body(Body::from(body))
             }
             Err(e) => e.into(),
         }
     }
 
+    /// Set a JSONP body and generate `Response`
+    ///
+    /// Callback name is read from `callback_param` query parameter of the request,
+    /// payload is wrapped as `/**/callback(...);`. Callback name must be a valid
+    /// javascript identifier or dot separated identifiers, otherwise
+    /// `400 Bad Request` response is generated. If query parameter is missing,
+    /// plain json response is generated.
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, HttpRequest, HttpResponse};
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     HttpResponse::Ok().jsonp("callback", req.head(), &vec!["v1", "v2"])
+    /// }
+    /// ```
+    pub fn jsonp<T: Serialize>(
+        &mut self,
+        callback_param: &str,
+        req: &RequestHead,
+        value: &T,
+    ) -> Response {
+        let callback = req.uri.query().and_then(|query| {
+            form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == callback_param)
+                .map(|(_, value)| value)
+        });
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return self.json(value),
+        };
+        if !is_valid_callback(&callback) {
+            return Response::BadRequest().body("Invalid JSONP callback name");
+        }
+
+        match serde_json::to_string(value) {
+            Ok(body) => {
+                // line separators are not allowed in javascript strings
+                let body = body
+                    .replace('\u{2028}', "\\u2028")
+                    .replace('\u{2029}', "\\u2029");
+                self.set_header(header::CONTENT_TYPE, "application/javascript")
+                    .set_header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+                self.body(Body::from(format!("/**/{}({});", callback, body)))
+            }
+            Err(e) => e.into(),
+        }
+    }
+
     #[inline]
     /// Set an empty body and generate `Response`
     ///
@@ -616,6 +793,28 @@ impl ResponseBuilder {
             cookies: self.cookies.take(),
         }
     }
+
+    /// Set content type if it is not set yet
+    fn default_content_type(&mut self, ctype: &'static str) {
+        let contains = if let Some(parts) = parts(&mut self.head, &self.err) {
+            parts.headers.contains_key(header::CONTENT_TYPE)
+        } else {
+            true
+        };
+        if !contains {
+            self.header(header::CONTENT_TYPE, ctype);
+        }
+    }
+}
+
+/// Check if JSONP callback is a javascript identifier or dot separated identifiers
+fn is_valid_callback(callback: &str) -> bool {
+    callback.len() <= 128
+        && callback.split('.').all(|ident| {
+            let mut chars = ident.chars();
+            matches!(chars.next(), Some(ch) if ch.is_ascii_alphabetic() || ch == '_' || ch == '$')
+                && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '$')
+        })
 }
 
 #[inline]
@@ -671,6 +870,9 @@ impl<'a> From<&'a ResponseHead> for ResponseBuilder {
         for (k, v) in &head.headers {
             msg.headers.append(k.clone(), v.clone());
         }
+        if let Some(trailers) = head.trailers() {
+            *msg.trailers_mut() = trailers.clone();
+        }
         msg.no_chunking(!head.chunked());
 
         #[cfg(feature = "cookie")]
@@ -801,9 +1003,12 @@ impl From<BytesMut> for Response {
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::Cell, rc::Rc};
+
     use super::*;
     use crate::http::body::Body;
     use crate::http::header::{HeaderValue, CONTENT_TYPE, COOKIE};
+    use crate::util::Bytes;
 
     #[test]
     fn test_debug() {
@@ -858,6 +1063,37 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "cookie")]
+    #[test]
+    fn test_same_site_cookie() {
+        let resp = Response::Ok()
+            .cookie(
+                coo_kie::Cookie::build("name", "value")
+                    .same_site(SameSite::Strict)
+                    .finish(),
+            )
+            .cookie(
+                coo_kie::Cookie::build("name2", "value")
+                    .same_site(SameSite::None)
+                    .secure(true)
+                    .finish(),
+            )
+            .finish();
+
+        let mut val: Vec<_> = resp
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .map(|v| v.to_str().unwrap().to_owned())
+            .collect();
+        val.sort();
+        assert_eq!(val[0], "name2=value; SameSite=None; Secure");
+        assert_eq!(val[1], "name=value; SameSite=Strict");
+
+        let same_site: Vec<_> = resp.cookies().map(|c| c.same_site()).collect();
+        assert!(same_site.contains(&Some(SameSite::Strict)));
+        assert!(same_site.contains(&Some(SameSite::None)));
+    }
+
     #[cfg(feature = "cookie")]
     #[test]
     fn test_update_response_cookies() {
@@ -932,6 +1168,122 @@ mod tests {
         assert_eq!(resp.body().get_ref(), b"[\"v1\",\"v2\",\"v3\"]");
     }
 
+    #[test]
+    fn test_attachment() {
+        let cases = [
+            ("report.csv", "attachment; filename=\"report.csv\""),
+            (
+                "my \"quoted\" file.txt",
+                "attachment; filename=\"my \\\"quoted\\\" file.txt\"",
+            ),
+            (
+                "résumé.pdf",
+                "attachment; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf",
+            ),
+            (
+                "../../etc/passwd",
+                "attachment; filename=\".._.._etc_passwd\"",
+            ),
+            ("a\\b\r\nc.txt", "attachment; filename=\"a_b__c.txt\""),
+        ];
+        for (name, expected) in cases {
+            let resp = Response::Ok()
+                .content_type("text/csv")
+                .attachment(name)
+                .body("data");
+            assert_eq!(
+                resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+                expected
+            );
+            assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+            assert_eq!(resp.body().get_ref(), b"data");
+        }
+
+        let resp = Response::Ok().inline("image.png").finish();
+        let cd = ContentDisposition::parse(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        )
+        .unwrap();
+        assert!(cd.is_inline());
+        assert_eq!(cd.get_filename(), Some("image.png"));
+
+        // previous disposition is replaced
+        let resp = Response::Ok()
+            .attachment("first.txt")
+            .inline("second.txt")
+            .finish();
+        let mut cd = resp.headers().get_all(header::CONTENT_DISPOSITION);
+        assert_eq!(cd.next().unwrap(), "inline; filename=\"second.txt\"");
+        assert!(cd.next().is_none());
+    }
+
+    #[test]
+    fn test_json_pretty() {
+        let resp = Response::build(StatusCode::OK).json_pretty(&vec!["v1", "v2"]);
+        let ct = resp.headers().get(CONTENT_TYPE).unwrap();
+        assert_eq!(ct, HeaderValue::from_static("application/json"));
+        assert_eq!(resp.body().get_ref(), b"[\n  \"v1\",\n  \"v2\"\n]");
+    }
+
+    #[test]
+    fn test_jsonp() {
+        let req = |uri: &str| RequestHead {
+            uri: uri.parse().unwrap(),
+            ..Default::default()
+        };
+
+        let resp = Response::build(StatusCode::OK).jsonp(
+            "callback",
+            &req("/?a=1&callback=app.handle_$1"),
+            &vec!["v1", "v\u{2028}2"],
+        );
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/javascript"
+        );
+        assert_eq!(
+            resp.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(
+            resp.body().get_ref(),
+            b"/**/app.handle_$1([\"v1\",\"v\\u20282\"]);"
+        );
+
+        // existing headers are replaced
+        let resp = Response::build(StatusCode::OK)
+            .content_type("text/plain")
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .jsonp("cb", &req("/?cb=f"), &1);
+        let ct: Vec<_> = resp.headers().get_all(CONTENT_TYPE).collect();
+        assert_eq!(ct, vec!["application/javascript"]);
+        assert_eq!(
+            resp.headers().get_all(header::X_CONTENT_TYPE_OPTIONS).count(),
+            1
+        );
+
+        // no callback
+        let resp = Response::build(StatusCode::OK).jsonp("cb", &req("/?callback=f"), &1);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(resp.body().get_ref(), b"1");
+
+        // invalid callback is not reflected
+        for cb in ["alert(1)//", "", "1a", "a..b", "a.", "%3Cscript%3E"] {
+            let uri = format!("/?cb={}", cb);
+            let resp = Response::build(StatusCode::OK).jsonp("cb", &req(&uri), &1);
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            assert!(!resp.headers().contains_key(header::X_CONTENT_TYPE_OPTIONS));
+            assert_eq!(resp.body().get_ref(), b"Invalid JSONP callback name");
+        }
+        let uri = format!("/?cb={}", "a".repeat(129));
+        let resp = Response::build(StatusCode::OK).jsonp("cb", &req(&uri), &1);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_serde_json_in_body() {
         use serde_json::json;
@@ -1061,4 +1413,61 @@ mod tests {
             assert_eq!((cookie.name(), cookie.value()), ("cookie1", "val100"));
         }
     }
+
+    struct CountBody(Body, Rc<Cell<usize>>);
+
+    impl MessageBody for CountBody {
+        fn size(&self) -> BodySize {
+            self.0.size()
+        }
+
+        fn poll_next_chunk(
+            &mut self,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Result<Bytes, Box<dyn std::error::Error>>>> {
+            let res = self.0.poll_next_chunk(cx);
+            if let std::task::Poll::Ready(Some(Ok(ref chunk))) = res {
+                self.1.set(self.1.get() + chunk.len());
+            }
+            res
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_map_body() {
+        let counter = Rc::new(Cell::new(0));
+        let stream = futures_util::stream::iter(vec![
+            Ok::<_, std::io::Error>(Bytes::from_static(b"test")),
+            Ok(Bytes::from_static(b"body")),
+        ]);
+        let cnt = counter.clone();
+        let resp = Response::Ok().streaming(stream);
+        let mut resp = resp.map_body(move |_, body| {
+            let (ResponseBody::Body(b) | ResponseBody::Other(b)) = body;
+            ResponseBody::Body(CountBody(b, cnt))
+        });
+        assert_eq!(resp.body().size(), BodySize::Stream);
+
+        let mut body = resp.take_body();
+        assert!(matches!(resp.body(), ResponseBody::Other(Body::None)));
+        while let Some(chunk) = crate::util::poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            chunk.unwrap();
+        }
+        assert_eq!(counter.get(), 8);
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_map_body_encoder() {
+        use crate::http::encoding::Encoder;
+        use crate::http::header::{ContentEncoding, CONTENT_ENCODING};
+
+        let resp = Response::Ok()
+            .body("test body")
+            .map_body(|head, body| Encoder::response(ContentEncoding::Gzip, head, body))
+            .map_body(|head, body| Encoder::response(ContentEncoding::Br, head, body));
+        assert_eq!(resp.headers().get_all(CONTENT_ENCODING).count(), 1);
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(resp.body().size(), BodySize::Stream);
+    }
 }
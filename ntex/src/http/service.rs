@@ -142,7 +142,7 @@ where
 
 #[cfg(feature = "openssl")]
 mod openssl {
-    use ntex_tls::openssl::{Acceptor, SslFilter};
+    use ntex_tls::{openssl::Acceptor, openssl::SslFilter, HandshakeError};
     use tls_openssl::ssl::SslAcceptor;
 
     use super::*;
@@ -174,7 +174,36 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(acceptor)
+            self.openssl_acceptor(Acceptor::new(acceptor))
+        }
+
+        /// Create openssl based service with handshake error callback
+        pub(crate) fn openssl_on_error<E>(
+            self,
+            acceptor: SslAcceptor,
+            on_error: E,
+        ) -> impl ServiceFactory<
+            Io<F>,
+            Response = (),
+            Error = SslError<DispatchError>,
+            InitError = (),
+        >
+        where
+            E: Fn(&HandshakeError) + Send + Sync + 'static,
+        {
+            self.openssl_acceptor(Acceptor::new(acceptor).on_error(on_error))
+        }
+
+        fn openssl_acceptor(
+            self,
+            acceptor: Acceptor<F>,
+        ) -> impl ServiceFactory<
+            Io<F>,
+            Response = (),
+            Error = SslError<DispatchError>,
+            InitError = (),
+        > {
+            acceptor
                 .timeout(self.cfg.0.ssl_handshake_timeout)
                 .chain()
                 .map_err(SslError::Ssl)
@@ -186,7 +215,7 @@ mod openssl {
 
 #[cfg(feature = "rustls")]
 mod rustls {
-    use ntex_tls::rustls::{Acceptor, TlsFilter};
+    use ntex_tls::{rustls::Acceptor, rustls::TlsFilter, HandshakeError};
     use tls_rustls::ServerConfig;
 
     use super::*;
@@ -208,7 +237,7 @@ mod rustls {
         U::Error: fmt::Display + error::Error,
         U::InitError: fmt::Debug,
     {
-        /// Create openssl based service
+        /// Create rustls based service
         pub fn rustls(
             self,
             mut config: ServerConfig,
@@ -221,7 +250,39 @@ mod rustls {
             let protos = vec!["h2".to_string().into(), "http/1.1".to_string().into()];
             config.alpn_protocols = protos;
 
-            Acceptor::from(config)
+            self.rustls_acceptor(Acceptor::from(config))
+        }
+
+        /// Create rustls based service with handshake error callback
+        pub(crate) fn rustls_on_error<E>(
+            self,
+            mut config: ServerConfig,
+            on_error: E,
+        ) -> impl ServiceFactory<
+            Io<F>,
+            Response = (),
+            Error = SslError<DispatchError>,
+            InitError = (),
+        >
+        where
+            E: Fn(&HandshakeError) + Send + Sync + 'static,
+        {
+            let protos = vec!["h2".to_string().into(), "http/1.1".to_string().into()];
+            config.alpn_protocols = protos;
+
+            self.rustls_acceptor(Acceptor::from(config).on_error(on_error))
+        }
+
+        fn rustls_acceptor(
+            self,
+            acceptor: Acceptor<F>,
+        ) -> impl ServiceFactory<
+            Io<F>,
+            Response = (),
+            Error = SslError<DispatchError>,
+            InitError = (),
+        > {
+            acceptor
                 .timeout(self.cfg.0.ssl_handshake_timeout)
                 .chain()
                 .map_err(|e| SslError::Ssl(Box::new(e)))
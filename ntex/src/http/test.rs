@@ -7,7 +7,7 @@ use coo_kie::{Cookie, CookieJar};
 use crate::io::{Filter, Io};
 use crate::ws::{error::WsClientError, WsClient, WsConnection};
 use crate::{rt::System, server::Server, service::ServiceFactory};
-use crate::{time::Millis, time::Seconds, util::Bytes};
+use crate::{time::Millis, time::Seconds, util::Bytes, util::Stream};
 
 use super::client::{Client, ClientRequest, ClientResponse, Connector};
 use super::error::{HttpError, PayloadError};
@@ -49,6 +49,7 @@ struct Inner {
     #[cfg(feature = "cookie")]
     cookies: CookieJar,
     payload: Option<Payload>,
+    peer_addr: Option<net::SocketAddr>,
 }
 
 impl Default for TestRequest {
@@ -61,6 +62,7 @@ impl Default for TestRequest {
             #[cfg(feature = "cookie")]
             cookies: CookieJar::new(),
             payload: None,
+            peer_addr: None,
         }))
     }
 }
@@ -115,6 +117,12 @@ impl TestRequest {
         panic!("Cannot create header");
     }
 
+    /// Set peer socket address of this request
+    pub fn peer_addr(&mut self, addr: net::SocketAddr) -> &mut Self {
+        parts(&mut self.0).peer_addr = Some(addr);
+        self
+    }
+
     #[cfg(feature = "cookie")]
     /// Set cookie for this request
     pub fn cookie(&mut self, cookie: Cookie<'_>) -> &mut Self {
@@ -130,6 +138,24 @@ impl TestRequest {
         self
     }
 
+    /// Set streaming request payload
+    ///
+    /// Request is sent with `Transfer-Encoding: chunked` header, payload
+    /// is delivered chunk by chunk as it gets produced by the stream.
+    pub fn set_payload_stream<S>(&mut self, stream: S) -> &mut Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        let inner = parts(&mut self.0);
+        inner.headers.remove(header::CONTENT_LENGTH);
+        inner.headers.insert(
+            header::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+        inner.payload = Some(Payload::Stream(Box::pin(stream)));
+        self
+    }
+
     /// Take test request
     pub fn take(&mut self) -> TestRequest {
         TestRequest(self.0.take())
@@ -150,6 +176,7 @@ impl TestRequest {
         head.method = inner.method;
         head.version = inner.version;
         head.headers = inner.headers;
+        head.peer_addr = inner.peer_addr;
 
         if let Some(conn) = head.headers.get(header::CONNECTION) {
             if let Ok(s) = conn.to_str() {
@@ -171,6 +198,15 @@ impl TestRequest {
                 let _ = write!(cookie, "; {}={}", name, value);
             }
             if !cookie.is_empty() {
+                // keep cookies from explicitly set headers
+                let mut existing = String::new();
+                for hdr in head.headers.get_all(super::header::COOKIE) {
+                    if let Ok(s) = hdr.to_str() {
+                        let _ = write!(existing, "; {}", s);
+                    }
+                }
+                cookie.insert_str(0, &existing);
+
                 head.headers.insert(
                     super::header::COOKIE,
                     HeaderValue::from_str(&cookie.as_str()[2..]).unwrap(),
@@ -5,6 +5,7 @@
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
 //! * `compress` - enables compression support in http and web modules
+//! * `zstd` - enables zstd compression support, implies `compress`
 //! * `cookie` - enables cookie support in http and web modules
 #![warn(
     rust_2018_idioms,
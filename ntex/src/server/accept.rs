@@ -7,6 +7,7 @@ use polling::{Event, Poller};
 use crate::rt::System;
 use crate::time::{sleep, Millis};
 
+use super::metrics::Metrics;
 use super::socket::{Listener, SocketAddr};
 use super::worker::{Connection, WorkerClient};
 use super::{Server, ServerStatus, Token};
@@ -20,6 +21,7 @@ pub(super) enum Command {
     Pause,
     Resume,
     Worker(WorkerClient),
+    StopWorker(usize),
     Timer,
     WorkerAvailable,
 }
@@ -29,7 +31,9 @@ struct ServerSocketInfo {
     addr: SocketAddr,
     token: Token,
     sock: Listener,
+    workers: Option<usize>,
     registered: Cell<bool>,
+    throttled: Cell<bool>,
     timeout: Cell<Option<Instant>>,
 }
 
@@ -89,8 +93,9 @@ impl AcceptLoop {
 
     pub(super) fn start(
         &mut self,
-        socks: Vec<(Token, Listener)>,
+        socks: Vec<(Token, Listener, Option<usize>)>,
         workers: Vec<WorkerClient>,
+        metrics: Metrics,
     ) {
         let (rx, poll, srv) = self
             .inner
@@ -106,6 +111,7 @@ impl AcceptLoop {
             workers,
             self.notify.clone(),
             status_handler,
+            metrics,
         );
     }
 }
@@ -128,19 +134,22 @@ struct Accept {
     srv: Server,
     notify: AcceptNotify,
     next: usize,
+    paused: bool,
     backpressure: bool,
     status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+    metrics: Metrics,
 }
 
 impl Accept {
     fn start(
         rx: mpsc::Receiver<Command>,
         poller: Arc<Poller>,
-        socks: Vec<(Token, Listener)>,
+        socks: Vec<(Token, Listener, Option<usize>)>,
         srv: Server,
         workers: Vec<WorkerClient>,
         notify: AcceptNotify,
         status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+        metrics: Metrics,
     ) {
         let sys = System::current();
 
@@ -149,26 +158,39 @@ impl Accept {
             .name("ntex-server accept loop".to_owned())
             .spawn(move || {
                 System::set_current(sys);
-                Accept::new(rx, poller, socks, workers, srv, notify, status_handler).poll()
+                Accept::new(
+                    rx,
+                    poller,
+                    socks,
+                    workers,
+                    srv,
+                    notify,
+                    status_handler,
+                    metrics,
+                )
+                .poll()
             });
     }
 
     fn new(
         rx: mpsc::Receiver<Command>,
         poller: Arc<Poller>,
-        socks: Vec<(Token, Listener)>,
+        socks: Vec<(Token, Listener, Option<usize>)>,
         workers: Vec<WorkerClient>,
         srv: Server,
         notify: AcceptNotify,
         status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+        metrics: Metrics,
     ) -> Accept {
         let mut sockets = Vec::new();
-        for (hnd_token, lst) in socks.into_iter() {
+        for (hnd_token, lst, workers) in socks.into_iter() {
             sockets.push(ServerSocketInfo {
                 addr: lst.local_addr(),
                 sock: lst,
                 token: hnd_token,
+                workers,
                 registered: Cell::new(false),
+                throttled: Cell::new(false),
                 timeout: Cell::new(None),
             });
         }
@@ -181,7 +203,9 @@ impl Accept {
             notify,
             srv,
             status_handler,
+            metrics,
             next: 0,
+            paused: false,
             backpressure: false,
         }
     }
@@ -285,7 +309,7 @@ impl Accept {
         for key in 0..self.sockets.len() {
             let info = &mut self.sockets[key];
             if let Some(inst) = info.timeout.get() {
-                if now > inst && !self.backpressure {
+                if now > inst && !self.backpressure && !self.paused {
                     log::info!("Resuming socket listener on {} after timeout", info.addr);
                     info.timeout.take();
                     self.add_source(key);
@@ -309,6 +333,7 @@ impl Accept {
                     }
                     Command::Pause => {
                         log::trace!("Pausing accept loop");
+                        self.paused = true;
                         for (key, info) in self.sockets.iter().enumerate() {
                             log::info!("Stopping socket listener on {}", info.addr);
                             self.remove_source(key);
@@ -317,23 +342,40 @@ impl Accept {
                     }
                     Command::Resume => {
                         log::trace!("Resuming accept loop");
-                        for (key, info) in self.sockets.iter().enumerate() {
-                            log::info!("Resuming socket listener on {}", info.addr);
-                            self.add_source(key);
+                        self.paused = false;
+                        if !self.backpressure {
+                            for (key, info) in self.sockets.iter().enumerate() {
+                                info.throttled.set(false);
+                                if info.timeout.get().is_none() {
+                                    log::info!("Resuming socket listener on {}", info.addr);
+                                    self.add_source(key);
+                                }
+                            }
+                            self.update_status(ServerStatus::Ready);
                         }
-                        self.update_status(ServerStatus::Ready);
                     }
                     Command::Worker(worker) => {
                         log::trace!("Adding new worker to accept loop");
                         self.backpressure(false);
                         self.workers.push(worker);
                     }
+                    Command::StopWorker(idx) => {
+                        if let Some(pos) = self.workers.iter().position(|w| w.idx == idx) {
+                            log::trace!("Removing worker {} from accept loop", idx);
+                            // worker finishes processing open connections
+                            self.workers.swap_remove(pos).stop(true);
+                            if self.workers.len() <= self.next {
+                                self.next = 0;
+                            }
+                        }
+                    }
                     Command::Timer => {
                         self.process_timer();
                     }
                     Command::WorkerAvailable => {
                         log::trace!("Worker is available");
                         self.backpressure(false);
+                        self.unthrottle();
                     }
                 },
                 Err(err) => match err {
@@ -352,16 +394,24 @@ impl Accept {
     }
 
     fn backpressure(&mut self, on: bool) {
-        self.update_status(if on {
-            ServerStatus::NotReady
-        } else {
-            ServerStatus::Ready
-        });
+        if !self.paused {
+            self.update_status(if on {
+                ServerStatus::NotReady
+            } else {
+                ServerStatus::Ready
+            });
+        }
 
         if self.backpressure {
             if !on {
                 self.backpressure = false;
+                self.metrics.paused(false);
+                if self.paused {
+                    // sockets get re-registered on resume
+                    return;
+                }
                 for (key, info) in self.sockets.iter().enumerate() {
+                    info.throttled.set(false);
                     if info.timeout.get().is_none() {
                         // socket with timeout will re-register itself after timeout
                         log::info!(
@@ -374,6 +424,7 @@ impl Accept {
             }
         } else if on {
             self.backpressure = true;
+            self.metrics.paused(true);
             for key in 0..self.sockets.len() {
                 // disable err timeout
                 let info = &mut self.sockets[key];
@@ -385,17 +436,58 @@ impl Accept {
         }
     }
 
-    fn accept_one(&mut self, mut msg: Connection) {
+    /// Stop listeners of the service that is not ready on any worker
+    ///
+    /// Other listeners continue to accept connections, throttled listeners
+    /// are resumed when worker reports service readiness.
+    fn throttle(&mut self, token: Token) {
+        for (key, info) in self.sockets.iter().enumerate() {
+            if info.token == token && !info.throttled.get() {
+                log::trace!("Service is not ready, throttling {}", info.addr);
+                info.throttled.set(true);
+                self.remove_source(key);
+            }
+        }
+    }
+
+    fn unthrottle(&mut self) {
+        if self.paused || self.backpressure {
+            return;
+        }
+        for (key, info) in self.sockets.iter().enumerate() {
+            if info.throttled.get() {
+                info.throttled.set(false);
+                if info.timeout.get().is_none() {
+                    log::trace!("Resuming throttled socket listener on {}", info.addr);
+                    self.add_source(key);
+                }
+            }
+        }
+    }
+
+    fn accept_one(&mut self, mut msg: Connection, limit: Option<usize>, queue: bool) {
         log::trace!(
             "Accepting connection: {:?} bp: {}",
             msg.io,
             self.backpressure
         );
 
-        if self.backpressure {
-            while !self.workers.is_empty() {
-                match self.workers[self.next].send(msg) {
-                    Ok(_) => (),
+        // connection could be sent only to workers running its service,
+        // in queue mode connection is queued to next worker even if
+        // worker or service is not ready
+        let mut idx = 0;
+        while idx < self.workers.len() {
+            idx += 1;
+            let worker = &self.workers[self.next];
+            if limit.map(|n| worker.idx < n).unwrap_or(true)
+                && (queue || (worker.available() && worker.service_available(msg.token)))
+            {
+                match worker.send(msg) {
+                    Ok(_) => {
+                        log::trace!("Sent to worker {:?}", self.next);
+                        self.next = (self.next + 1) % self.workers.len();
+                        return;
+                    }
                     Err(tmp) => {
                         log::trace!("Worker failed while processing connection");
                         self.update_status(ServerStatus::WorkerFailed);
@@ -404,6 +496,8 @@ impl Accept {
                         self.workers.swap_remove(self.next);
                         if self.workers.is_empty() {
                             log::error!("No workers");
+                            self.metrics.closed(msg.token, Duration::ZERO, true);
+                            self.backpressure(true);
                             return;
                         } else if self.workers.len() <= self.next {
                             self.next = 0;
@@ -411,54 +505,69 @@ impl Accept {
                         continue;
                     }
                 }
-                self.next = (self.next + 1) % self.workers.len();
-                break;
             }
+            self.next = (self.next + 1) % self.workers.len();
+        }
+
+        if queue {
+            log::error!("No workers for {:?}, dropping connection", msg.token);
+            self.metrics.closed(msg.token, Duration::ZERO, true);
         } else {
-            let mut idx = 0;
-            while idx < self.workers.len() {
-                idx += 1;
-                if self.workers[self.next].available() {
-                    match self.workers[self.next].send(msg) {
-                        Ok(_) => {
-                            log::trace!("Sent to worker {:?}", self.next);
-                            self.next = (self.next + 1) % self.workers.len();
-                            return;
-                        }
-                        Err(tmp) => {
-                            log::trace!("Worker failed while processing connection");
-                            self.update_status(ServerStatus::WorkerFailed);
-                            self.srv.worker_faulted(self.workers[self.next].idx);
-                            msg = tmp;
-                            self.workers.swap_remove(self.next);
-                            if self.workers.is_empty() {
-                                log::error!("No workers");
-                                self.backpressure(true);
-                                return;
-                            } else if self.workers.len() <= self.next {
-                                self.next = 0;
-                            }
-                            continue;
-                        }
-                    }
+            // worker got busy after readiness check
+            self.is_ready(msg.token, limit);
+            self.accept_one(msg, limit, true);
+        }
+    }
+
+    /// Check if any worker could process connection for the service
+    ///
+    /// Listener gets throttled if service is not ready on any of available
+    /// workers, i.e. tls handshake limit is reached. Back-pressure is enabled
+    /// if all workers are busy.
+    fn is_ready(&mut self, token: Token, limit: Option<usize>) -> bool {
+        let mut available = false;
+        for worker in &self.workers {
+            if limit.map(|n| worker.idx < n).unwrap_or(true) && worker.available() {
+                if worker.service_available(token) {
+                    return true;
                 }
-                self.next = (self.next + 1) % self.workers.len();
+                available = true;
             }
-            // enable backpressure
+        }
+
+        if available {
+            self.throttle(token);
+        } else {
             log::trace!("No available workers, enable back-pressure");
             self.backpressure(true);
-            self.accept_one(msg);
         }
+        false
     }
 
     fn accept(&mut self, token: usize) -> bool {
         loop {
-            let msg = if let Some(info) = self.sockets.get_mut(token) {
+            // pending connections stay in listen backlog
+            let (srv, limit) = if let Some(info) = self.sockets.get(token) {
+                if self.paused || self.backpressure || info.throttled.get() {
+                    return false;
+                }
+                (info.token, info.workers)
+            } else {
+                return false;
+            };
+            if !self.is_ready(srv, limit) {
+                return false;
+            }
+
+            let (msg, limit) = if let Some(info) = self.sockets.get_mut(token) {
                 match info.sock.accept() {
-                    Ok(Some(io)) => Connection {
-                        io,
-                        token: info.token,
-                    },
+                    Ok(Some(io)) => (
+                        Connection {
+                            io,
+                            token: info.token,
+                        },
+                        info.workers,
+                    ),
                     Ok(None) => return true,
                     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return true,
                     Err(ref e) if connection_error(e) => continue,
@@ -480,7 +589,8 @@ impl Accept {
                 return false;
             };
 
-            self.accept_one(msg);
+            self.metrics.accepted(msg.token);
+            self.accept_one(msg, limit, false);
         }
     }
 }
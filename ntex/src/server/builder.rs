@@ -8,16 +8,18 @@ use socket2::{Domain, SockAddr, Socket, Type};
 use crate::rt::{spawn, Signal, System};
 use crate::{
     io::Io, service::ServiceFactory, time::sleep, time::Millis, util::join_all,
-    util::Stream,
+    util::BoxFuture, util::Stream,
 };
 
 use super::accept::{AcceptLoop, AcceptNotify, Command};
 use super::config::{
     Config, ConfigWrapper, ConfiguredService, ServiceConfig, ServiceRuntime,
 };
+use super::metrics::{Metrics, ServerMetrics};
+use super::multiplex::Multiplex;
 use super::service::{Factory, InternalServiceFactory};
-use super::socket::Listener;
-use super::worker::{self, Worker, WorkerAvailability, WorkerClient};
+use super::socket::{Listener, SocketAddr};
+use super::worker::{Worker, WorkerAvailability, WorkerClient};
 use super::{Server, ServerCommand, ServerStatus, Token};
 
 const STOP_DELAY: Millis = Millis(300);
@@ -27,9 +29,12 @@ pub struct ServerBuilder {
     threads: usize,
     token: Token,
     backlog: i32,
+    reuse_port: bool,
+    max_connections: usize,
     workers: Vec<(usize, WorkerClient)>,
-    services: Vec<Box<dyn InternalServiceFactory>>,
-    sockets: Vec<(Token, String, Listener)>,
+    services: Vec<(Box<dyn InternalServiceFactory>, Option<usize>)>,
+    sockets: Vec<(Token, String, Listener, Option<usize>)>,
+    last_bind: (usize, usize),
     accept: AcceptLoop,
     exit: bool,
     shutdown_timeout: Millis,
@@ -37,6 +42,11 @@ pub struct ServerBuilder {
     cmd: Receiver<ServerCommand>,
     server: Server,
     notify: Vec<oneshot::Sender<()>>,
+    on_shutdown: Vec<Box<dyn FnOnce() -> BoxFuture<'static, ()>>>,
+    metrics: Metrics,
+    metrics_hooks: Option<Box<dyn ServerMetrics>>,
+    #[cfg(all(unix, feature = "systemd"))]
+    systemd: Option<super::systemd::Notify>,
 }
 
 impl Default for ServerBuilder {
@@ -57,13 +67,21 @@ impl ServerBuilder {
             workers: Vec::new(),
             services: Vec::new(),
             sockets: Vec::new(),
+            last_bind: (0, 0),
             accept: AcceptLoop::new(server.clone()),
             backlog: 2048,
+            reuse_port: false,
+            max_connections: 25600,
             exit: false,
             shutdown_timeout: Millis::from_secs(30),
             no_signals: false,
             cmd: rx,
             notify: Vec::new(),
+            on_shutdown: Vec::new(),
+            metrics: Metrics::default(),
+            metrics_hooks: None,
+            #[cfg(all(unix, feature = "systemd"))]
+            systemd: None,
             server,
         }
     }
@@ -86,23 +104,97 @@ impl ServerBuilder {
     ///
     /// Generally set in the 64-2048 range. Default value is 2048.
     ///
-    /// This method should be called before `bind()` method call.
+    /// This method should be called before `bind()` method call. Value
+    /// applies to subsequent `bind()` and `configure()` calls, so different
+    /// binds could use different backlog.
     pub fn backlog(mut self, num: i32) -> Self {
         self.backlog = num;
         self
     }
 
+    /// Set `SO_REUSEPORT` option for bound sockets.
+    ///
+    /// Allows multiple independent processes to bind the same address,
+    /// i.e. for zero-downtime restarts. Option is not supported on all
+    /// platforms, on unsupported platforms warning is logged and option
+    /// is ignored. By default option is not set.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    /// Set number of workers for services added by the last `bind()`,
+    /// `listen()` or `configure()` call.
+    ///
+    /// Connections for these services are distributed only between first
+    /// `num` workers, other workers do not run these services. It is useful
+    /// for low-traffic listeners, i.e. admin or metrics endpoints. If `num` is
+    /// greater than total number of workers, services run on all workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num` is 0 or if method is called before any bind call.
+    pub fn bind_workers(mut self, num: usize) -> Self {
+        assert!(num > 0, "Number of workers must be greater than 0");
+        let (services, sockets) = self.last_bind;
+        assert!(
+            sockets < self.sockets.len(),
+            "bind_workers() must be called after bind()"
+        );
+        self.services[services..]
+            .iter_mut()
+            .for_each(|item| item.1 = Some(num));
+        self.sockets[sockets..]
+            .iter_mut()
+            .for_each(|item| item.3 = Some(num));
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
-    /// All socket listeners will stop accepting connections when this limit is
-    /// reached for each worker.
+    /// Worker stops receiving new connections when this limit is reached. If
+    /// all workers are at the limit, socket listeners stop accepting
+    /// connections, pending connections wait in listen backlog. Accepting
+    /// resumes as soon as any worker closes connection, new connections are
+    /// distributed between available workers in round-robin order.
     ///
     /// By default max connections is set to a 25k per worker.
+    pub fn max_connections(mut self, num: usize) -> Self {
+        self.max_connections = num;
+        self
+    }
+
+    /// Sets the maximum per-worker number of concurrent connections.
+    ///
+    /// Same as [`ServerBuilder::max_connections()`].
     pub fn maxconn(self, num: usize) -> Self {
-        worker::max_concurrent_connections(num);
+        self.max_connections(num)
+    }
+
+    /// Sets the maximum per-worker number of concurrent tls handshakes.
+    ///
+    /// Tls acceptor service becomes unavailable when this limit is reached,
+    /// listeners of such services stop accepting connections until one of
+    /// in-flight handshakes completes, pending connections wait in listen
+    /// backlog. Listeners of other services are not affected. Limit is
+    /// shared by all servers in the process and is applied to workers
+    /// started after this call.
+    ///
+    /// By default max connection rate is set to 256.
+    pub fn max_connection_rate(self, num: usize) -> Self {
+        ntex_tls::max_concurrent_ssl_accept(num);
         self
     }
 
+    /// Sets the maximum per-worker number of concurrent tls handshakes.
+    ///
+    /// Same as [`ServerBuilder::max_connection_rate()`].
+    pub fn maxconnrate(self, num: usize) -> Self {
+        self.max_connection_rate(num)
+    }
+
     /// Stop ntex runtime when server get dropped.
     ///
     /// By default "stop runtime" is disabled.
@@ -131,6 +223,54 @@ impl ServerBuilder {
         self
     }
 
+    /// Register graceful shutdown hook.
+    ///
+    /// Hook is called on graceful shutdown before workers start draining
+    /// connections, i.e. to fail load balancer health checks. Server
+    /// accepts new connections until future returned by hook resolves.
+    /// Hooks are not called on forced shutdown.
+    pub fn on_shutdown<F, R>(mut self, f: F) -> Self
+    where
+        F: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.on_shutdown.push(Box::new(move || Box::pin(f())));
+        self
+    }
+
+    /// Register server metrics hooks.
+    ///
+    /// Hooks are called on connection accept and close, and when accept loop
+    /// pauses because all workers are busy. Built-in counters are maintained
+    /// regardless of hooks, see [`Server::metrics()`].
+    pub fn metrics<M>(mut self, hooks: M) -> Self
+    where
+        M: ServerMetrics + 'static,
+    {
+        self.metrics_hooks = Some(Box::new(hooks));
+        self
+    }
+
+    /// Enable systemd service manager notifications.
+    ///
+    /// Server sends `READY=1` notification when workers are started and
+    /// `STOPPING=1` when server starts shutting down. If watchdog is enabled
+    /// for service, `WATCHDOG=1` notification is sent every half of
+    /// `$WATCHDOG_USEC` period, but only if event loops of all workers are
+    /// responsive, so systemd restarts service with blocked worker.
+    ///
+    /// Notifications are sent to `$NOTIFY_SOCKET`, if server is not started by
+    /// systemd this setting has no effect. By default notifications are disabled.
+    #[cfg(all(unix, feature = "systemd"))]
+    pub fn systemd_notify(mut self, enabled: bool) -> Self {
+        self.systemd = if enabled {
+            super::systemd::Notify::from_env()
+        } else {
+            None
+        };
+        self
+    }
+
     /// Set server status handler.
     ///
     /// Server calls this handler on every inner status update.
@@ -151,18 +291,20 @@ impl ServerBuilder {
     where
         F: Fn(&mut ServiceConfig) -> io::Result<()>,
     {
-        let mut cfg = ServiceConfig::new(self.threads, self.backlog);
+        let mut cfg = ServiceConfig::new(self.threads, self.backlog, self.reuse_port);
 
         f(&mut cfg)?;
 
         let mut cfg = cfg.0.borrow_mut();
         let mut srv = ConfiguredService::new(cfg.apply.take().unwrap());
+        self.start_bind();
         for (name, lst) in mem::take(&mut cfg.services) {
             let token = self.token.next();
             srv.stream(token, name.clone(), lst.local_addr()?);
-            self.sockets.push((token, name, Listener::from_tcp(lst)));
+            self.sockets
+                .push((token, name, Listener::from_tcp(lst), None));
         }
-        self.services.push(Box::new(srv));
+        self.services.push((Box::new(srv), None));
         self.threads = cfg.threads;
 
         Ok(self)
@@ -178,19 +320,21 @@ impl ServerBuilder {
         F: Fn(ServiceConfig) -> R,
         R: Future<Output = io::Result<()>>,
     {
-        let cfg = ServiceConfig::new(self.threads, self.backlog);
+        let cfg = ServiceConfig::new(self.threads, self.backlog, self.reuse_port);
         let inner = cfg.0.clone();
 
         f(cfg).await?;
 
         let mut cfg = inner.borrow_mut();
         let mut srv = ConfiguredService::new(cfg.apply.take().unwrap());
+        self.start_bind();
         for (name, lst) in mem::take(&mut cfg.services) {
             let token = self.token.next();
             srv.stream(token, name.clone(), lst.local_addr()?);
-            self.sockets.push((token, name, Listener::from_tcp(lst)));
+            self.sockets
+                .push((token, name, Listener::from_tcp(lst), None));
         }
-        self.services.push(Box::new(srv));
+        self.services.push((Box::new(srv), None));
         self.threads = cfg.threads;
 
         Ok(self)
@@ -206,11 +350,14 @@ impl ServerBuilder {
         R: Future<Output = Result<(), E>> + 'static,
         E: fmt::Display + 'static,
     {
-        self.services
-            .push(Box::new(ConfiguredService::new(Box::new(ConfigWrapper {
+        self.services.push((
+            Box::new(ConfiguredService::new(Box::new(ConfigWrapper {
                 f,
                 _t: marker::PhantomData,
-            }))));
+            }))),
+            None,
+        ));
+        self.start_bind();
         self
     }
 
@@ -226,22 +373,48 @@ impl ServerBuilder {
         F: Fn(Config) -> R + Send + Clone + 'static,
         R: ServiceFactory<Io>,
     {
-        let sockets = bind_addr(addr, self.backlog)?;
+        let sockets = bind_addr(addr, self.backlog, self.reuse_port)?;
 
+        self.start_bind();
         for lst in sockets {
             let token = self.token.next();
-            self.services.push(Factory::create(
-                name.as_ref().to_string(),
+            self.services.push((
+                Factory::create(
+                    name.as_ref().to_string(),
+                    token,
+                    factory.clone(),
+                    lst.local_addr()?,
+                ),
+                None,
+            ));
+            self.sockets.push((
                 token,
-                factory.clone(),
-                lst.local_addr()?,
+                name.as_ref().to_string(),
+                Listener::from_tcp(lst),
+                None,
             ));
-            self.sockets
-                .push((token, name.as_ref().to_string(), Listener::from_tcp(lst)));
         }
         Ok(self)
     }
 
+    /// Add new multiplexed service to the server.
+    ///
+    /// Several services share the listener, service is selected for each
+    /// connection by peeking first bytes of the stream. See [`Multiplex`]
+    /// for details.
+    pub fn bind_multiplexed<F, U, N: AsRef<str>>(
+        self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        U: net::ToSocketAddrs,
+        F: Fn(Config) -> Multiplex + Send + Clone + 'static,
+    {
+        self.bind(name, addr, factory)
+    }
+
     #[cfg(unix)]
     /// Add new unix domain service to the server.
     pub fn bind_uds<F, U, N, R>(self, name: N, addr: U, factory: F) -> io::Result<Self>
@@ -283,14 +456,17 @@ impl ServerBuilder {
         use std::net::{IpAddr, Ipv4Addr, SocketAddr};
         let token = self.token.next();
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-        self.services.push(Factory::create(
-            name.as_ref().to_string(),
+        self.start_bind();
+        self.services.push((
+            Factory::create(name.as_ref().to_string(), token, factory, addr),
+            None,
+        ));
+        self.sockets.push((
             token,
-            factory,
-            addr,
+            name.as_ref().to_string(),
+            Listener::from_uds(lst),
+            None,
         ));
-        self.sockets
-            .push((token, name.as_ref().to_string(), Listener::from_uds(lst)));
         Ok(self)
     }
 
@@ -306,17 +482,35 @@ impl ServerBuilder {
         R: ServiceFactory<Io>,
     {
         let token = self.token.next();
-        self.services.push(Factory::create(
-            name.as_ref().to_string(),
+        self.start_bind();
+        self.services.push((
+            Factory::create(name.as_ref().to_string(), token, factory, lst.local_addr()?),
+            None,
+        ));
+        self.sockets.push((
             token,
-            factory,
-            lst.local_addr()?,
+            name.as_ref().to_string(),
+            Listener::from_tcp(lst),
+            None,
         ));
-        self.sockets
-            .push((token, name.as_ref().to_string(), Listener::from_tcp(lst)));
         Ok(self)
     }
 
+    /// Get addresses of tcp listeners.
+    ///
+    /// Addresses are reported in order of `bind()`, `listen()` and
+    /// `configure()` calls, unix domain sockets are skipped.
+    pub fn addrs(&self) -> Vec<net::SocketAddr> {
+        self.sockets
+            .iter()
+            .filter_map(|(_, _, lst, _)| match lst.local_addr() {
+                SocketAddr::Tcp(addr) => Some(addr),
+                #[cfg(unix)]
+                SocketAddr::Uds(_) => None,
+            })
+            .collect()
+    }
+
     /// Starts processing incoming connections and return server controller.
     pub fn run(mut self) -> Server {
         if self.sockets.is_empty() {
@@ -324,6 +518,14 @@ impl ServerBuilder {
         } else {
             info!("Starting {} workers", self.threads);
 
+            let mut names: Vec<_> =
+                self.sockets.iter().map(|s| (s.0 .0, s.1.clone())).collect();
+            names.sort_unstable();
+            self.metrics = Metrics::new(
+                names.into_iter().map(|(_, name)| name).collect(),
+                self.metrics_hooks.take(),
+            );
+
             // start workers
             let mut workers = Vec::new();
             for idx in 0..self.threads {
@@ -339,9 +541,10 @@ impl ServerBuilder {
             self.accept.start(
                 mem::take(&mut self.sockets)
                     .into_iter()
-                    .map(|t| (t.0, t.2))
+                    .map(|t| (t.0, t.2, t.3))
                     .collect(),
                 workers,
+                self.metrics.clone(),
             );
 
             // handle signals
@@ -349,6 +552,9 @@ impl ServerBuilder {
                 spawn(signals(self.server.clone()));
             }
 
+            #[cfg(all(unix, feature = "systemd"))]
+            self.systemd_ready();
+
             // start http server actor
             let server = self.server.clone();
             spawn(self);
@@ -356,12 +562,100 @@ impl ServerBuilder {
         }
     }
 
+    #[cfg(all(unix, feature = "systemd"))]
+    fn systemd_ready(&self) {
+        if let Some(ref notify) = self.systemd {
+            notify.ready();
+
+            if let Some(interval) = notify.watchdog() {
+                let srv = self.server.clone();
+                spawn(async move {
+                    loop {
+                        sleep(interval).await;
+                        if !srv.watchdog() {
+                            break;
+                        }
+                    }
+                });
+            }
+        }
+    }
+
     fn start_worker(&self, idx: usize, notify: AcceptNotify) -> WorkerClient {
-        let avail = WorkerAvailability::new(notify);
-        let services: Vec<Box<dyn InternalServiceFactory>> =
-            self.services.iter().map(|v| v.clone_factory()).collect();
+        // workers heartbeat twice per watchdog period
+        #[cfg(all(unix, feature = "systemd"))]
+        let heartbeat = self
+            .systemd
+            .as_ref()
+            .and_then(|n| n.watchdog())
+            .map(|t| Millis(t.0 / 2));
+        #[cfg(not(all(unix, feature = "systemd")))]
+        let heartbeat = None;
+
+        let avail = WorkerAvailability::new(notify, self.token.0);
+        let services: Vec<Box<dyn InternalServiceFactory>> = self
+            .services
+            .iter()
+            .filter(|(_, limit)| limit.map(|n| idx < n).unwrap_or(true))
+            .map(|(v, _)| v.clone_factory())
+            .collect();
+
+        Worker::start(
+            idx,
+            services,
+            avail,
+            self.shutdown_timeout,
+            self.max_connections,
+            self.metrics.clone(),
+            heartbeat,
+        )
+    }
+
+    fn start_bind(&mut self) {
+        self.last_bind = self.bind_mark();
+    }
+
+    /// Current position of binds, used for grouping several binds
+    pub(crate) fn bind_mark(&self) -> (usize, usize) {
+        (self.services.len(), self.sockets.len())
+    }
+
+    pub(crate) fn set_last_bind(mut self, mark: (usize, usize)) -> Self {
+        self.last_bind = mark;
+        self
+    }
 
-        Worker::start(idx, services, avail, self.shutdown_timeout)
+    /// Get lowest unused worker index
+    fn next_worker_idx(&self) -> usize {
+        let mut idx = 0;
+        while self.workers.iter().any(|(i, _)| *i == idx) {
+            idx += 1;
+        }
+        idx
+    }
+
+    fn resize_workers(&mut self, num: usize) {
+        if num == 0 {
+            error!("Number of workers must be greater than 0");
+            return;
+        }
+        info!("Resizing workers from {} to {}", self.workers.len(), num);
+        self.threads = num;
+
+        while self.workers.len() < num {
+            let idx = self.next_worker_idx();
+            let worker = self.start_worker(idx, self.accept.notify());
+            self.workers.push((idx, worker.clone()));
+            self.accept.send(Command::Worker(worker));
+        }
+        while self.workers.len() > num {
+            // drain worker with highest index
+            let pos = (0..self.workers.len())
+                .max_by_key(|pos| self.workers[*pos].0)
+                .unwrap();
+            let (idx, _) = self.workers.swap_remove(pos);
+            self.accept.send(Command::StopWorker(idx));
+        }
     }
 
     fn handle_cmd(&mut self, item: ServerCommand) {
@@ -408,10 +702,63 @@ impl ServerBuilder {
             ServerCommand::Notify(tx) => {
                 self.notify.push(tx);
             }
+            ServerCommand::Resize(num, mut tx) => {
+                self.resize_workers(num);
+                let _ = tx.send(());
+            }
+            ServerCommand::Workers(mut tx) => {
+                let _ = tx.send(self.workers.len());
+            }
+            ServerCommand::Metrics(mut tx) => {
+                let _ = tx.send(self.metrics.snapshot());
+            }
+            ServerCommand::Connections(mut tx) => {
+                let mut workers: Vec<_> = self
+                    .workers
+                    .iter()
+                    .map(|(idx, worker)| (*idx, worker.connections()))
+                    .collect();
+                workers.sort_unstable();
+                let _ = tx.send(workers.into_iter().map(|(_, num)| num).collect());
+            }
+            #[cfg(all(unix, feature = "systemd"))]
+            ServerCommand::Watchdog => {
+                if let Some(ref notify) = self.systemd {
+                    let mut alive = true;
+                    for (idx, worker) in &self.workers {
+                        if !worker.heartbeat() {
+                            log::warn!("Worker {} is not responding", idx);
+                            alive = false;
+                        }
+                    }
+                    if alive {
+                        notify.watchdog_ping();
+                    }
+                }
+            }
             ServerCommand::Stop {
                 graceful,
                 completion,
             } => {
+                #[cfg(all(unix, feature = "systemd"))]
+                if let Some(ref mut notify) = self.systemd {
+                    notify.stopping();
+                }
+
+                // run shutdown hooks, then stop server
+                if graceful && !self.on_shutdown.is_empty() {
+                    let hooks: Vec<_> = mem::take(&mut self.on_shutdown)
+                        .into_iter()
+                        .map(|f| f())
+                        .collect();
+                    let srv = self.server.clone();
+                    spawn(async move {
+                        let _ = join_all(hooks).await;
+                        srv.stop_cmd(graceful, completion);
+                    });
+                    return;
+                }
+
                 let exit = self.exit;
 
                 // stop accept thread
@@ -472,17 +819,7 @@ impl ServerBuilder {
                 if found {
                     error!("Worker has died {:?}, restarting", idx);
 
-                    let mut new_idx = self.workers.len();
-                    'found: loop {
-                        for i in 0..self.workers.len() {
-                            if self.workers[i].0 == new_idx {
-                                new_idx += 1;
-                                continue 'found;
-                            }
-                        }
-                        break;
-                    }
-
+                    let new_idx = self.next_worker_idx();
                     let worker = self.start_worker(new_idx, self.accept.notify());
                     self.workers.push((new_idx, worker.clone()));
                     self.accept.send(Command::Worker(worker));
@@ -524,12 +861,13 @@ async fn signals(srv: Server) {
 pub(super) fn bind_addr<S: net::ToSocketAddrs>(
     addr: S,
     backlog: i32,
+    reuse_port: bool,
 ) -> io::Result<Vec<net::TcpListener>> {
     let mut err = None;
     let mut succ = false;
     let mut sockets = Vec::new();
     for addr in addr.to_socket_addrs()? {
-        match create_tcp_listener(addr, backlog) {
+        match create_tcp_listener(addr, backlog, reuse_port) {
             Ok(lst) => {
                 succ = true;
                 sockets.push(lst);
@@ -555,6 +893,7 @@ pub(super) fn bind_addr<S: net::ToSocketAddrs>(
 pub(crate) fn create_tcp_listener(
     addr: net::SocketAddr,
     backlog: i32,
+    reuse_port: bool,
 ) -> io::Result<net::TcpListener> {
     let builder = match addr {
         net::SocketAddr::V4(_) => Socket::new(Domain::IPV4, Type::STREAM, None)?,
@@ -566,12 +905,35 @@ pub(crate) fn create_tcp_listener(
     // https://docs.microsoft.com/en-us/windows/win32/winsock/using-so-reuseaddr-and-so-exclusiveaddruse
     #[cfg(not(windows))]
     builder.set_reuse_address(true)?;
+    if reuse_port {
+        set_reuse_port(&builder)?;
+    }
 
     builder.bind(&SockAddr::from(addr))?;
     builder.listen(backlog)?;
     Ok(net::TcpListener::from(builder))
 }
 
+#[cfg(all(
+    unix,
+    not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+))]
+fn set_reuse_port(sock: &Socket) -> io::Result<()> {
+    sock.set_reuse_port(true)
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+)))]
+fn set_reuse_port(_: &Socket) -> io::Result<()> {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    WARNED.call_once(|| {
+        log::warn!("SO_REUSEPORT is not supported on current platform, option is ignored")
+    });
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -579,6 +941,31 @@ mod tests {
     #[test]
     fn test_bind_addr() {
         let addrs: Vec<net::SocketAddr> = Vec::new();
-        assert!(bind_addr(&addrs[..], 10).is_err());
+        assert!(bind_addr(&addrs[..], 10, false).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reuse_port() {
+        let lst = bind_addr("127.0.0.1:0", 10, true).unwrap();
+        let addr = lst[0].local_addr().unwrap();
+
+        assert!(bind_addr(addr, 10, false).is_err());
+        let lst2 = bind_addr(addr, 10, true).unwrap();
+        assert_eq!(lst2[0].local_addr().unwrap(), addr);
+
+        // builder applies option at bind time
+        assert!(ServerBuilder::new()
+            .bind("test", addr, |_| {
+                crate::service::fn_service(|_: Io| crate::util::Ready::Ok::<_, ()>(()))
+            })
+            .is_err());
+        let srv = ServerBuilder::new()
+            .reuse_port(true)
+            .bind("test", addr, |_| {
+                crate::service::fn_service(|_: Io| crate::util::Ready::Ok::<_, ()>(()))
+            })
+            .unwrap();
+        assert_eq!(srv.addrs(), vec![addr]);
     }
 }
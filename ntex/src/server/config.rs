@@ -1,3 +1,4 @@
+use std::time::Duration;
 use std::{cell::Cell, cell::RefCell, fmt, future::Future, io, marker, mem, net, rc::Rc};
 
 use log::error;
@@ -9,7 +10,7 @@ use crate::util::{BoxFuture, HashMap, PoolId, Ready};
 use super::service::{
     BoxedServerService, InternalServiceFactory, ServerMessage, StreamService,
 };
-use super::{builder::bind_addr, counter::CounterGuard, Token};
+use super::{builder::bind_addr, counter::CounterGuard, metrics::Metrics, Token};
 
 #[derive(Clone, Debug)]
 pub struct Config(Rc<InnerServiceConfig>);
@@ -17,17 +18,37 @@ pub struct Config(Rc<InnerServiceConfig>);
 #[derive(Debug)]
 pub(super) struct InnerServiceConfig {
     pub(super) pool: Cell<PoolId>,
+    pub(super) nodelay: Cell<bool>,
+    pub(super) keepalive: Cell<Option<Duration>>,
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub(super) metrics: Option<(Metrics, Token)>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self(Rc::new(InnerServiceConfig {
             pool: Cell::new(PoolId::DEFAULT),
+            nodelay: Cell::new(true),
+            keepalive: Cell::new(None),
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            metrics: None,
         }))
     }
 }
 
 impl Config {
+    #[allow(unused_variables)]
+    /// Create config for listener
+    pub(super) fn new(metrics: &Metrics, token: Token) -> Self {
+        Self(Rc::new(InnerServiceConfig {
+            pool: Cell::new(PoolId::DEFAULT),
+            nodelay: Cell::new(true),
+            keepalive: Cell::new(None),
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
+            metrics: Some((metrics.clone(), token)),
+        }))
+    }
+
     /// Set memory pool for the service.
     ///
     /// Use specified memory pool for memory allocations.
@@ -36,9 +57,50 @@ impl Config {
         self
     }
 
+    /// Set `TCP_NODELAY` option for accepted tcp streams.
+    ///
+    /// By default option is enabled.
+    pub fn tcp_nodelay(&self, enabled: bool) -> &Self {
+        self.0.nodelay.set(enabled);
+        self
+    }
+
+    /// Set tcp keep-alive time for accepted tcp streams.
+    ///
+    /// Keep-alive is enabled with specified idle time before probes are sent,
+    /// `None` leaves os defaults. By default keep-alive is not set.
+    pub fn tcp_keepalive(&self, time: Option<Duration>) -> &Self {
+        self.0.keepalive.set(time);
+        self
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Tls handshake error callback of the listener.
+    ///
+    /// Callback reports handshake errors to server metrics, it could be
+    /// passed to `on_error()` method of `ntex-tls` acceptors.
+    pub fn handshake_error_hook(
+        &self,
+    ) -> impl Fn(&crate::tls::HandshakeError) + Send + Sync + 'static {
+        let hook = self
+            .0
+            .metrics
+            .as_ref()
+            .map(|(metrics, token)| metrics.handshake_hook(*token));
+        move |err| {
+            if let Some(ref hook) = hook {
+                hook(err)
+            }
+        }
+    }
+
     pub(super) fn get_pool_id(&self) -> PoolId {
         self.0.pool.get()
     }
+
+    pub(super) fn get_tcp_options(&self) -> (bool, Option<Duration>) {
+        (self.0.nodelay.get(), self.0.keepalive.get())
+    }
 }
 
 #[derive(Debug)]
@@ -50,14 +112,16 @@ pub(super) struct ServiceConfigInner {
     pub(super) apply: Option<Box<dyn ServiceRuntimeConfiguration + Send>>,
     pub(super) threads: usize,
     pub(super) backlog: i32,
+    pub(super) reuse_port: bool,
     applied: bool,
 }
 
 impl ServiceConfig {
-    pub(super) fn new(threads: usize, backlog: i32) -> Self {
+    pub(super) fn new(threads: usize, backlog: i32, reuse_port: bool) -> Self {
         ServiceConfig(Rc::new(RefCell::new(ServiceConfigInner {
             threads,
             backlog,
+            reuse_port,
             services: Vec::new(),
             applied: false,
             apply: Some(Box::new(ConfigWrapper {
@@ -75,7 +139,11 @@ impl ServiceConfig {
     where
         U: net::ToSocketAddrs,
     {
-        let sockets = bind_addr(addr, self.0.borrow().backlog)?;
+        let (backlog, reuse_port) = {
+            let inner = self.0.borrow();
+            (inner.backlog, inner.reuse_port)
+        };
+        let sockets = bind_addr(addr, backlog, reuse_port)?;
 
         for lst in sockets {
             self.listen(name.as_ref(), lst);
@@ -159,7 +227,10 @@ impl InternalServiceFactory for ConfiguredService {
         })
     }
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+    fn create(
+        &self,
+        _: &Metrics,
+    ) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
         // configure services
         let rt = ServiceRuntime::new(self.topics.clone());
         let cfg_fut = self.rt.configure(ServiceRuntime(rt.0.clone()));
@@ -1,24 +1,26 @@
-use std::{cell::Cell, rc::Rc, task};
+use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
+use std::{rc::Rc, task};
 
 use crate::task::LocalWaker;
 
 /// Simple counter with ability to notify task on reaching specific number
 ///
 /// Counter could be cloned, total count is shared across all clones.
+/// Total count could be also observed from other threads.
 pub(super) struct Counter(Rc<CounterInner>);
 
 struct CounterInner {
-    count: Cell<usize>,
+    count: Arc<AtomicUsize>,
     capacity: usize,
     task: LocalWaker,
 }
 
 impl Counter {
     /// Create `Counter` instance and set max value.
-    pub(super) fn new(capacity: usize) -> Self {
+    pub(super) fn new(capacity: usize, count: Arc<AtomicUsize>) -> Self {
         Counter(Rc::new(CounterInner {
             capacity,
-            count: Cell::new(0),
+            count,
             task: LocalWaker::new(),
         }))
     }
@@ -36,11 +38,7 @@ impl Counter {
 
     /// Get total number of acquired counts
     pub(super) fn total(&self) -> usize {
-        self.0.count.get()
-    }
-
-    pub(super) fn priv_clone(&self) -> Self {
-        Counter(self.0.clone())
+        self.0.count.load(Ordering::Relaxed)
     }
 }
 
@@ -63,19 +61,18 @@ impl Drop for CounterGuard {
 
 impl CounterInner {
     fn inc(&self) {
-        self.count.set(self.count.get() + 1);
+        self.count.fetch_add(1, Ordering::Relaxed);
     }
 
     fn dec(&self) {
-        let num = self.count.get();
-        self.count.set(num - 1);
+        let num = self.count.fetch_sub(1, Ordering::Relaxed);
         if num == self.capacity {
             self.task.wake();
         }
     }
 
     fn available(&self, cx: &mut task::Context<'_>) -> bool {
-        if self.count.get() < self.capacity {
+        if self.count.load(Ordering::Relaxed) < self.capacity {
             true
         } else {
             self.task.register(cx.waker());
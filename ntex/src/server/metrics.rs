@@ -0,0 +1,342 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::{fmt, sync::Arc, time::Duration};
+
+use super::Token;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::tls::HandshakeError;
+
+/// Server metrics hooks.
+///
+/// Hooks are called from accept loop and worker threads on hot path,
+/// implementations must be cheap, i.e. update atomic counters. Listener
+/// id is assigned in order of bind calls, name is the service name
+/// passed to bind call.
+///
+/// Server always maintains built-in counters, snapshot of counters is
+/// available with [`Server::metrics()`](super::Server::metrics).
+pub trait ServerMetrics: Send + Sync {
+    /// New connection is accepted
+    fn accepted(&self, _id: usize, _name: &str) {}
+
+    /// Connection is closed
+    ///
+    /// `failed` is set if connection service returned error, i.e. on tls
+    /// handshake error, or if connection could not be processed.
+    fn closed(&self, _id: usize, _name: &str, _duration: Duration, _failed: bool) {}
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Tls handshake failed
+    ///
+    /// Called for connections handled by `ntex-tls` acceptors that use
+    /// [`Config::handshake_error_hook()`](super::Config::handshake_error_hook)
+    /// as error callback, tls listeners of web `HttpServer` use it by default.
+    /// Connection is reported as failed by `closed()` hook as well.
+    fn handshake_error(&self, _id: usize, _name: &str, _err: &HandshakeError) {}
+
+    /// Accept loop is paused, all workers are at capacity
+    fn paused(&self) {}
+
+    /// Accept loop is resumed after pause
+    fn resumed(&self) {}
+}
+
+impl<T: ServerMetrics> ServerMetrics for Arc<T> {
+    fn accepted(&self, id: usize, name: &str) {
+        self.as_ref().accepted(id, name)
+    }
+
+    fn closed(&self, id: usize, name: &str, duration: Duration, failed: bool) {
+        self.as_ref().closed(id, name, duration, failed)
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    fn handshake_error(&self, id: usize, name: &str, err: &HandshakeError) {
+        self.as_ref().handshake_error(id, name, err)
+    }
+
+    fn paused(&self) {
+        self.as_ref().paused()
+    }
+
+    fn resumed(&self) {
+        self.as_ref().resumed()
+    }
+}
+
+#[non_exhaustive]
+#[derive(Clone, Debug, Default)]
+/// Snapshot of server metrics
+pub struct MetricsSnapshot {
+    /// Number of accept loop pauses caused by busy workers
+    pub pauses: u64,
+    /// Accept loop is paused
+    pub paused: bool,
+    /// Per-listener metrics, ordered by listener id
+    pub listeners: Vec<ListenerMetrics>,
+}
+
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+/// Listener metrics
+pub struct ListenerMetrics {
+    /// Listener id
+    pub id: usize,
+    /// Service name
+    pub name: String,
+    /// Number of accepted connections
+    pub accepted: u64,
+    /// Number of currently open connections
+    pub active: u64,
+    /// Number of failed connections
+    pub failed: u64,
+    /// Number of tls handshake errors, failed connections include them
+    pub handshake_errors: u64,
+    /// Total duration of closed connections
+    pub duration: Duration,
+}
+
+#[derive(Clone, Default)]
+pub(super) struct Metrics(Arc<MetricsInner>);
+
+#[derive(Default)]
+struct MetricsInner {
+    listeners: Vec<ListenerCounters>,
+    pauses: AtomicU64,
+    paused: AtomicBool,
+    hooks: Option<Box<dyn ServerMetrics>>,
+}
+
+struct ListenerCounters {
+    name: String,
+    accepted: AtomicU64,
+    closed: AtomicU64,
+    failed: AtomicU64,
+    handshake_errors: AtomicU64,
+    duration: AtomicU64,
+}
+
+impl Metrics {
+    pub(super) fn new(names: Vec<String>, hooks: Option<Box<dyn ServerMetrics>>) -> Self {
+        let listeners = names
+            .into_iter()
+            .map(|name| ListenerCounters {
+                name,
+                accepted: AtomicU64::new(0),
+                closed: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+                handshake_errors: AtomicU64::new(0),
+                duration: AtomicU64::new(0),
+            })
+            .collect();
+
+        Metrics(Arc::new(MetricsInner {
+            listeners,
+            hooks,
+            pauses: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+        }))
+    }
+
+    pub(super) fn accepted(&self, token: Token) {
+        if let Some(l) = self.0.listeners.get(token.0) {
+            l.accepted.fetch_add(1, Ordering::Relaxed);
+            if let Some(ref hooks) = self.0.hooks {
+                hooks.accepted(token.0, &l.name);
+            }
+        }
+    }
+
+    pub(super) fn closed(&self, token: Token, duration: Duration, failed: bool) {
+        if let Some(l) = self.0.listeners.get(token.0) {
+            l.closed.fetch_add(1, Ordering::Relaxed);
+            l.duration
+                .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+            if failed {
+                l.failed.fetch_add(1, Ordering::Relaxed);
+            }
+            if let Some(ref hooks) = self.0.hooks {
+                hooks.closed(token.0, &l.name, duration, failed);
+            }
+        }
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    pub(super) fn handshake_error(&self, token: Token, err: &HandshakeError) {
+        if let Some(l) = self.0.listeners.get(token.0) {
+            l.handshake_errors.fetch_add(1, Ordering::Relaxed);
+            if let Some(ref hooks) = self.0.hooks {
+                hooks.handshake_error(token.0, &l.name, err);
+            }
+        }
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Handshake error hook of listener
+    pub(super) fn handshake_hook(
+        &self,
+        token: Token,
+    ) -> impl Fn(&HandshakeError) + Send + Sync + 'static {
+        let metrics = self.clone();
+        move |err| metrics.handshake_error(token, err)
+    }
+
+    pub(super) fn paused(&self, paused: bool) {
+        if self.0.paused.swap(paused, Ordering::Relaxed) != paused {
+            if paused {
+                self.0.pauses.fetch_add(1, Ordering::Relaxed);
+            }
+            if let Some(ref hooks) = self.0.hooks {
+                if paused {
+                    hooks.paused()
+                } else {
+                    hooks.resumed()
+                }
+            }
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            pauses: self.0.pauses.load(Ordering::Relaxed),
+            paused: self.0.paused.load(Ordering::Relaxed),
+            listeners: self
+                .0
+                .listeners
+                .iter()
+                .enumerate()
+                .map(|(id, l)| {
+                    let accepted = l.accepted.load(Ordering::Relaxed);
+                    let closed = l.closed.load(Ordering::Relaxed);
+                    ListenerMetrics {
+                        id,
+                        accepted,
+                        name: l.name.clone(),
+                        active: accepted.saturating_sub(closed),
+                        failed: l.failed.load(Ordering::Relaxed),
+                        handshake_errors: l.handshake_errors.load(Ordering::Relaxed),
+                        duration: Duration::from_micros(l.duration.load(Ordering::Relaxed)),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("listeners", &self.0.listeners.len())
+            .field("hooks", &self.0.hooks.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Hooks(Mutex<Vec<String>>);
+
+    impl ServerMetrics for Hooks {
+        fn accepted(&self, id: usize, name: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("accepted {} {}", id, name));
+        }
+
+        fn closed(&self, id: usize, name: &str, _: Duration, failed: bool) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("closed {} {} {}", id, name, failed));
+        }
+
+        #[cfg(any(feature = "openssl", feature = "rustls"))]
+        fn handshake_error(&self, id: usize, name: &str, err: &HandshakeError) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("handshake {} {} {}", id, name, err));
+        }
+
+        fn paused(&self) {
+            self.0.lock().unwrap().push("paused".to_string());
+        }
+
+        fn resumed(&self) {
+            self.0.lock().unwrap().push("resumed".to_string());
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let hooks = Arc::new(Hooks::default());
+        let m = Metrics::new(
+            vec!["main".to_string(), "admin".to_string()],
+            Some(Box::new(hooks.clone())),
+        );
+        assert!(format!("{:?}", m).contains("Metrics"));
+
+        m.accepted(Token(0));
+        m.accepted(Token(0));
+        m.accepted(Token(1));
+        m.closed(Token(0), Duration::from_millis(10), false);
+        m.closed(Token(1), Duration::from_millis(5), true);
+        m.paused(true);
+        m.paused(true);
+        m.paused(false);
+        // unknown listener is ignored
+        m.accepted(Token(5));
+
+        let snapshot = m.snapshot();
+        assert_eq!(snapshot.pauses, 1);
+        assert!(!snapshot.paused);
+        assert_eq!(snapshot.listeners.len(), 2);
+        let main = &snapshot.listeners[0];
+        assert_eq!((main.id, main.name.as_str()), (0, "main"));
+        assert_eq!((main.accepted, main.active, main.failed), (2, 1, 0));
+        assert_eq!(main.duration, Duration::from_millis(10));
+        let admin = &snapshot.listeners[1];
+        assert_eq!((admin.accepted, admin.active, admin.failed), (1, 0, 1));
+        assert_eq!((main.handshake_errors, admin.handshake_errors), (0, 0));
+
+        assert_eq!(
+            *hooks.0.lock().unwrap(),
+            vec![
+                "accepted 0 main",
+                "accepted 0 main",
+                "accepted 1 admin",
+                "closed 0 main false",
+                "closed 1 admin true",
+                "paused",
+                "resumed"
+            ]
+        );
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    #[test]
+    fn test_handshake_error() {
+        let hooks = Arc::new(Hooks::default());
+        let m = Metrics::new(
+            vec!["main".to_string(), "tls".to_string()],
+            Some(Box::new(hooks.clone())),
+        );
+
+        let hook = m.handshake_hook(Token(1));
+        hook(&HandshakeError::Timeout);
+        m.handshake_error(Token(5), &HandshakeError::Timeout);
+
+        let snapshot = m.snapshot();
+        assert_eq!(snapshot.listeners[0].handshake_errors, 0);
+        assert_eq!(snapshot.listeners[1].handshake_errors, 1);
+        assert_eq!(
+            *hooks.0.lock().unwrap(),
+            vec!["handshake 1 tls tls handshake timeout"]
+        );
+    }
+}
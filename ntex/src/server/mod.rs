@@ -8,8 +8,13 @@ mod accept;
 mod builder;
 mod config;
 mod counter;
+mod metrics;
+mod multiplex;
 mod service;
+mod shutdown;
 mod socket;
+#[cfg(all(unix, feature = "systemd"))]
+mod systemd;
 mod test;
 mod worker;
 
@@ -24,6 +29,11 @@ pub use ntex_tls::max_concurrent_ssl_accept;
 pub(crate) use self::builder::create_tcp_listener;
 pub use self::builder::ServerBuilder;
 pub use self::config::{Config, ServiceConfig, ServiceRuntime};
+pub use self::metrics::{ListenerMetrics, MetricsSnapshot, ServerMetrics};
+pub use self::multiplex::{Multiplex, MultiplexService};
+pub use self::shutdown::ShutdownSignal;
+#[cfg(unix)]
+pub use self::socket::{from_fd, listen_fds};
 pub use self::test::{build_test_server, test_server, TestServer};
 
 #[non_exhaustive]
@@ -72,6 +82,17 @@ enum ServerCommand {
     },
     /// Notify of server stop
     Notify(oneshot::Sender<()>),
+    /// Number of open connections per worker
+    Connections(oneshot::Sender<Vec<usize>>),
+    /// Change number of workers
+    Resize(usize, oneshot::Sender<()>),
+    /// Number of running workers
+    Workers(oneshot::Sender<usize>),
+    /// Server metrics
+    Metrics(oneshot::Sender<MetricsSnapshot>),
+    /// Systemd watchdog timer
+    #[cfg(all(unix, feature = "systemd"))]
+    Watchdog,
 }
 
 /// Server controller
@@ -92,14 +113,26 @@ impl Server {
         let _ = self.0.try_send(ServerCommand::Signal(sig));
     }
 
+    fn stop_cmd(&self, graceful: bool, completion: Option<oneshot::Sender<()>>) {
+        let _ = self.0.try_send(ServerCommand::Stop {
+            graceful,
+            completion,
+        });
+    }
+
     fn worker_faulted(&self, idx: usize) {
         let _ = self.0.try_send(ServerCommand::WorkerFaulted(idx));
     }
 
+    #[cfg(all(unix, feature = "systemd"))]
+    fn watchdog(&self) -> bool {
+        self.0.try_send(ServerCommand::Watchdog).is_ok()
+    }
+
     /// Pause accepting incoming connections
     ///
-    /// If socket contains some pending connection, they might be dropped.
-    /// All opened connection remains active.
+    /// Listening sockets stay open, pending connections wait in listen
+    /// backlog until server is resumed. All opened connection remains active.
     pub fn pause(&self) -> impl Future<Output = ()> {
         let (tx, rx) = oneshot::oneshot();
         let _ = self.0.try_send(ServerCommand::Pause(tx));
@@ -109,6 +142,8 @@ impl Server {
     }
 
     /// Resume accepting incoming connections
+    ///
+    /// Listeners stay inactive while all workers are at capacity.
     pub fn resume(&self) -> impl Future<Output = ()> {
         let (tx, rx) = oneshot::oneshot();
         let _ = self.0.try_send(ServerCommand::Resume(tx));
@@ -117,6 +152,49 @@ impl Server {
         }
     }
 
+    /// Get number of currently open connections for each worker.
+    ///
+    /// Workers are ordered by worker index.
+    pub fn connections(&self) -> impl Future<Output = Vec<usize>> {
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self.0.try_send(ServerCommand::Connections(tx));
+        async move { rx.await.unwrap_or_default() }
+    }
+
+    /// Get number of running workers.
+    ///
+    /// Workers that are draining connections after resize are not counted.
+    pub fn workers(&self) -> impl Future<Output = usize> {
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self.0.try_send(ServerCommand::Workers(tx));
+        async move { rx.await.unwrap_or_default() }
+    }
+
+    /// Get snapshot of server metrics.
+    ///
+    /// Snapshot contains built-in connection counters for each listener and
+    /// accept loop pause counters. See [`ServerBuilder::metrics()`].
+    pub fn metrics(&self) -> impl Future<Output = MetricsSnapshot> {
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self.0.try_send(ServerCommand::Metrics(tx));
+        async move { rx.await.unwrap_or_default() }
+    }
+
+    /// Change number of running workers.
+    ///
+    /// New workers are started if `num` is greater than current number of workers,
+    /// otherwise workers with highest index stop accepting new connections and
+    /// finish processing open connections, same as on graceful shutdown.
+    /// Existing connections are not dropped. Services limited with
+    /// [`ServerBuilder::bind_workers()`] run on new workers within the limit.
+    pub fn resize_workers(&self, num: usize) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self.0.try_send(ServerCommand::Resize(num, tx));
+        async move {
+            let _ = rx.await;
+        }
+    }
+
     /// Stop incoming connection processing, stop all workers and exit.
     ///
     /// If server starts with `spawn()` method, then spawned thread get terminated.
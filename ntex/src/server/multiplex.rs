@@ -0,0 +1,290 @@
+//! Multiple services on one listener
+use std::{fmt, rc::Rc, task::Context, task::Poll};
+
+use crate::io::{Base, Filter, Io, IoRef};
+use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::{Service, ServiceCtx, ServiceFactory};
+use crate::time::{timeout_checked, Millis};
+use crate::util::{join_all, BoxFuture};
+
+type Predicate = Rc<dyn Fn(&[u8], &IoRef) -> bool>;
+
+/// Multiplexed service factory.
+///
+/// Multiplex service selects service per connection, it peeks first bytes of
+/// the stream and checks predicates in order of registration. Peeked bytes
+/// are kept in io read buffer, so selected service gets whole stream. Predicate
+/// also receives io reference, it could be used to query tls negotiated
+/// protocol if multiplex service is used after tls acceptor.
+///
+/// Predicates are checked once `peek_size` bytes are received. If peer sends
+/// less data, predicates are checked with received bytes after timeout, i.e.
+/// empty slice for protocols where server speaks first. Connection is closed
+/// if no predicate matches.
+///
+/// Selected service must read data from io read buffer before waiting for
+/// read readiness, services based on `Io::recv()` do that.
+///
+/// ```rust,no_run
+/// use ntex::{io::Io, server::Multiplex, service::fn_service};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     ntex::server::build()
+///         .bind_multiplexed("main", "127.0.0.1:8080", |_| {
+///             Multiplex::new()
+///                 .service(
+///                     |buf, _| buf.starts_with(b"METRICS"),
+///                     fn_service(|io: Io| async move { Ok::<_, ()>(()) }),
+///                 )
+///                 .service(
+///                     |_, _| true,
+///                     fn_service(|io: Io| async move { Ok::<_, ()>(()) }),
+///                 )
+///         })?
+///         .run()
+///         .await
+/// }
+/// ```
+pub struct Multiplex<F = Base> {
+    services: Vec<(Predicate, BoxServiceFactory<(), Io<F>, (), (), ()>)>,
+    peek_size: usize,
+    timeout: Millis,
+}
+
+impl<F: Filter> Default for Multiplex<F> {
+    fn default() -> Self {
+        Multiplex {
+            services: Vec::new(),
+            peek_size: 8,
+            timeout: Millis(5_000),
+        }
+    }
+}
+
+impl<F: Filter> Multiplex<F> {
+    /// Create multiplex service factory
+    pub fn new() -> Self {
+        Multiplex::default()
+    }
+
+    /// Register service with predicate.
+    ///
+    /// Predicate receives peeked bytes, up to `peek_size` bytes.
+    pub fn service<P, T>(mut self, predicate: P, factory: T) -> Self
+    where
+        P: Fn(&[u8], &IoRef) -> bool + 'static,
+        T: ServiceFactory<Io<F>> + 'static,
+        T::Service: 'static,
+    {
+        let factory = factory.map(|_| ()).map_err(|_| ()).map_init_err(|_| ());
+        self.services
+            .push((Rc::new(predicate), boxed::factory(factory)));
+        self
+    }
+
+    /// Set number of bytes to peek before checking predicates.
+    ///
+    /// By default 8 bytes are peeked. If size is set to 0, predicates
+    /// are checked immediately.
+    pub fn peek_size(mut self, size: usize) -> Self {
+        self.peek_size = size;
+        self
+    }
+
+    /// Set max time for receiving peeked bytes.
+    ///
+    /// Predicates are checked with received bytes after timeout, unmatched
+    /// connection is closed. Timeout is disabled if it is set to 0.
+    ///
+    /// By default timeout is set to 5 seconds.
+    pub fn timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+}
+
+impl<F> fmt::Debug for Multiplex<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multiplex")
+            .field("services", &self.services.len())
+            .field("peek_size", &self.peek_size)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<F: Filter> ServiceFactory<Io<F>> for Multiplex<F> {
+    type Response = ();
+    type Error = ();
+    type Service = MultiplexService<F>;
+    type InitError = ();
+    type Future<'f> = BoxFuture<'f, Result<Self::Service, Self::InitError>>;
+
+    fn create(&self, _: ()) -> Self::Future<'_> {
+        Box::pin(async move {
+            let services = join_all(self.services.iter().map(|(_, f)| f.create(())))
+                .await
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(MultiplexService {
+                services: self
+                    .services
+                    .iter()
+                    .map(|(p, _)| p.clone())
+                    .zip(services)
+                    .collect(),
+                peek_size: self.peek_size,
+                timeout: self.timeout,
+            })
+        })
+    }
+}
+
+/// Multiplexed service
+pub struct MultiplexService<F> {
+    services: Vec<(Predicate, BoxService<Io<F>, (), ()>)>,
+    peek_size: usize,
+    timeout: Millis,
+}
+
+impl<F> fmt::Debug for MultiplexService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiplexService")
+            .field("services", &self.services.len())
+            .field("peek_size", &self.peek_size)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<F: Filter> Service<Io<F>> for MultiplexService<F> {
+    type Response = ();
+    type Error = ();
+    type Future<'f> = BoxFuture<'f, Result<(), ()>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // service is selected per connection, so multiplex service is ready
+        // if any of services is ready, selected service readiness is checked
+        // in `call()`
+        let mut ready = self.services.is_empty();
+        for (_, srv) in &self.services {
+            ready = srv.poll_ready(cx)?.is_ready() || ready;
+        }
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut ready = true;
+        for (_, srv) in &self.services {
+            ready = srv.poll_shutdown(cx).is_ready() && ready;
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call<'a>(&'a self, io: Io<F>, ctx: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+        Box::pin(async move {
+            let peek = async {
+                while io.with_read_buf(|buf| buf.len()) < self.peek_size {
+                    match io.read_ready().await {
+                        Ok(Some(_)) => continue,
+                        Ok(None) => return Ok(false),
+                        Err(_) => return Err(()),
+                    }
+                }
+                Ok(true)
+            };
+            if let Ok(res) = timeout_checked(self.timeout, peek).await {
+                if !res? {
+                    log::trace!("Peer is disconnected before service is selected");
+                    return Ok(());
+                }
+            }
+
+            let peeked =
+                io.with_read_buf(|buf| buf[..buf.len().min(self.peek_size)].to_vec());
+            let io_ref = io.get_ref();
+            let idx = self.services.iter().position(|(p, _)| p(&peeked, &io_ref));
+            if let Some(idx) = idx {
+                // waits for selected service readiness
+                ctx.call(&self.services[idx].1, io).await
+            } else {
+                log::trace!("No service matches connection, closing");
+                io.close();
+                Ok(())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::Pipeline;
+    use crate::util::{lazy, Ready};
+
+    #[derive(Clone, Copy)]
+    struct Srv(bool);
+
+    impl ServiceFactory<Io> for Srv {
+        type Response = ();
+        type Error = ();
+        type Service = Srv;
+        type InitError = ();
+        type Future<'f> = Ready<Srv, ()>;
+
+        fn create(&self, _: ()) -> Self::Future<'_> {
+            Ready::Ok(*self)
+        }
+    }
+
+    impl Service<Io> for Srv {
+        type Response = ();
+        type Error = ();
+        type Future<'f> = Ready<(), ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            if self.0 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call<'a>(&'a self, _: Io, _: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+            Ready::Ok(())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_poll_ready() {
+        let srv = Pipeline::new(
+            Multiplex::new()
+                .service(|_, _| true, Srv(false))
+                .create(())
+                .await
+                .unwrap(),
+        );
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+
+        // ready if any service is ready
+        let srv = Pipeline::new(
+            Multiplex::new()
+                .service(|_, _| true, Srv(false))
+                .service(|_, _| true, Srv(true))
+                .create(())
+                .await
+                .unwrap(),
+        );
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+    }
+}
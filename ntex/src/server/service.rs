@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, rc::Rc, task::Context, task::Poll};
+use std::{net::SocketAddr, rc::Rc, task::Context, task::Poll, time::Duration};
 
 use log::error;
 
@@ -6,7 +6,7 @@ use crate::service::{boxed, Service, ServiceCtx, ServiceFactory};
 use crate::util::{BoxFuture, Pool, PoolId};
 use crate::{io::Io, time::Millis};
 
-use super::{counter::CounterGuard, socket::Stream, Config, Token};
+use super::{counter::CounterGuard, metrics::Metrics, socket::Stream, Config, Token};
 
 /// Server message
 pub(super) enum ServerMessage {
@@ -29,7 +29,10 @@ pub(super) trait InternalServiceFactory: Send {
 
     fn clone_factory(&self) -> Box<dyn InternalServiceFactory>;
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>>;
+    fn create(
+        &self,
+        metrics: &Metrics,
+    ) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>>;
 }
 
 pub(super) type BoxedServerService =
@@ -39,6 +42,8 @@ pub(super) type BoxedServerService =
 pub(super) struct StreamService<T> {
     service: Rc<T>,
     pool: Pool,
+    nodelay: bool,
+    keepalive: Option<Duration>,
 }
 
 impl<T> StreamService<T> {
@@ -46,6 +51,18 @@ impl<T> StreamService<T> {
         StreamService {
             pool: pid.pool(),
             service: Rc::new(service),
+            nodelay: true,
+            keepalive: None,
+        }
+    }
+
+    pub(super) fn with_config(service: T, cfg: &Config) -> Self {
+        let (nodelay, keepalive) = cfg.get_tcp_options();
+        StreamService {
+            nodelay,
+            keepalive,
+            pool: cfg.get_pool_id().pool(),
+            service: Rc::new(service),
         }
     }
 }
@@ -79,16 +96,16 @@ where
         Box::pin(async move {
             match req {
                 ServerMessage::Connect(stream) => {
-                    let stream = stream.try_into().map_err(|e| {
-                        error!("Cannot convert to an async io stream: {}", e);
-                    });
+                    let stream =
+                        stream.into_io(self.nodelay, self.keepalive).map_err(|e| {
+                            error!("Cannot convert to an async io stream: {}", e);
+                        });
 
                     if let Ok(stream) = stream {
-                        let stream: Io<_> = stream;
                         stream.set_memory_pool(self.pool.pool_ref());
-                        let _ = ctx.call(self.service.as_ref(), stream).await;
+                        let res = ctx.call(self.service.as_ref(), stream).await;
                         drop(guard);
-                        Ok(())
+                        res.map(|_| ()).map_err(|_| ())
                     } else {
                         Err(())
                     }
@@ -142,16 +159,18 @@ where
         })
     }
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+    fn create(
+        &self,
+        metrics: &Metrics,
+    ) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
         let token = self.token;
-        let cfg = Config::default();
-        let pool = cfg.get_pool_id();
-        let factory = self.inner.create(cfg);
+        let cfg = Config::new(metrics, token);
+        let factory = self.inner.create(cfg.clone());
 
         Box::pin(async move {
             match factory.create(()).await {
                 Ok(inner) => {
-                    let service = boxed::service(StreamService::new(inner, pool));
+                    let service = boxed::service(StreamService::with_config(inner, &cfg));
                     Ok(vec![(token, service)])
                 }
                 Err(_) => Err(()),
@@ -169,8 +188,11 @@ impl InternalServiceFactory for Box<dyn InternalServiceFactory> {
         self.as_ref().clone_factory()
     }
 
-    fn create(&self) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
-        self.as_ref().create()
+    fn create(
+        &self,
+        metrics: &Metrics,
+    ) -> BoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+        self.as_ref().create(metrics)
     }
 }
 
@@ -0,0 +1,147 @@
+use std::{cell::Cell, fmt, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::channel::condition::{Condition, Waiter};
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+thread_local! {
+    static SIGNAL: ShutdownSignal = ShutdownSignal::new();
+}
+
+/// Worker shutdown signal.
+///
+/// Signal resolves when current worker starts graceful or forced shutdown.
+/// Each worker has its own signal, handlers and tasks spawned in the worker
+/// could wait for the signal to finish their work before shutdown timeout
+/// expires. Signal resolves immediately if shutdown is already started.
+/// Signal could be used as web handler argument.
+///
+/// ```rust
+/// use ntex::{server::ShutdownSignal, util::select, util::Either, web};
+///
+/// async fn index(shutdown: ShutdownSignal) -> &'static str {
+///     let work = async { "done" };
+///     match select(work, shutdown).await {
+///         Either::Left(res) => res,
+///         Either::Right(_) => "server is shutting down",
+///     }
+/// }
+/// ```
+pub struct ShutdownSignal {
+    inner: Rc<Inner>,
+    waiter: Waiter,
+}
+
+struct Inner {
+    stopping: Cell<bool>,
+    cond: Condition,
+}
+
+impl ShutdownSignal {
+    fn new() -> Self {
+        let cond = Condition::new();
+        ShutdownSignal {
+            waiter: cond.wait(),
+            inner: Rc::new(Inner {
+                cond,
+                stopping: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Get shutdown signal of current worker.
+    pub fn current() -> Self {
+        SIGNAL.with(|s| s.clone())
+    }
+
+    /// Check if shutdown is started.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.stopping.get()
+    }
+
+    /// Check if shutdown is started, register current task for wakeup
+    /// if shutdown is not started yet.
+    pub fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.inner.stopping.get() {
+            Poll::Ready(())
+        } else {
+            let _ = self.waiter.poll_ready(cx);
+            if self.inner.stopping.get() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Start shutdown of current worker
+    pub(super) fn notify() {
+        SIGNAL.with(|s| {
+            s.inner.stopping.set(true);
+            s.inner.cond.notify();
+        })
+    }
+}
+
+impl Clone for ShutdownSignal {
+    fn clone(&self) -> Self {
+        ShutdownSignal {
+            inner: self.inner.clone(),
+            waiter: self.inner.cond.wait(),
+        }
+    }
+}
+
+impl Future for ShutdownSignal {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.poll_shutdown(cx)
+    }
+}
+
+impl fmt::Debug for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSignal")
+            .field("stopping", &self.inner.stopping.get())
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for ShutdownSignal {
+    type Error = Err::Container;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(_: &HttpRequest, _: &mut Payload) -> Self::Future {
+        Ready::Ok(ShutdownSignal::current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::lazy;
+
+    #[crate::rt_test]
+    async fn test_shutdown_signal() {
+        let signal = ShutdownSignal::current();
+        assert!(!signal.is_shutdown());
+        assert!(format!("{:?}", signal).contains("stopping: false"));
+
+        let signal2 = signal.clone();
+        let waiter = crate::rt::spawn(signal2);
+        assert!(lazy(|cx| signal.poll_shutdown(cx)).await.is_pending());
+
+        ShutdownSignal::notify();
+        assert!(signal.is_shutdown());
+        waiter.await.unwrap();
+        ShutdownSignal::current().await;
+
+        // signal is per worker thread
+        std::thread::spawn(|| assert!(!ShutdownSignal::current().is_shutdown()))
+            .join()
+            .unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt, io, net};
+use std::{fmt, io, net, time::Duration};
 
 use crate::{io::Io, rt};
 
@@ -119,6 +119,119 @@ mod listener_impl {
     }
 }
 
+/// First file descriptor passed by systemd
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: std::os::unix::io::RawFd = 3;
+
+#[cfg(unix)]
+/// Create tcp listener from file descriptor.
+///
+/// File descriptor must be bound and listening tcp stream socket, this
+/// function does not change socket options. Returns error if
+/// file descriptor is not a listening tcp socket, file descriptor is
+/// closed in this case.
+///
+/// This function is available on unix platforms only.
+///
+/// # Safety
+///
+/// File descriptor must be open and must not be owned by anything else,
+/// listener takes ownership of the file descriptor.
+pub unsafe fn from_fd(fd: std::os::unix::io::RawFd) -> io::Result<net::TcpListener> {
+    use socket2::{Socket, Type};
+    use std::os::unix::io::FromRawFd;
+
+    let socket = Socket::from_raw_fd(fd);
+    if socket.r#type()? != Type::STREAM {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("File descriptor {} is not a stream socket", fd),
+        ));
+    }
+    if socket.local_addr()?.as_socket().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("File descriptor {} is not a tcp socket", fd),
+        ));
+    }
+    #[cfg(any(target_os = "android", target_os = "freebsd", target_os = "linux"))]
+    if !socket.is_listener()? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Socket {} is not listening", fd),
+        ));
+    }
+    Ok(net::TcpListener::from(socket))
+}
+
+#[cfg(unix)]
+/// Create tcp listeners from sockets passed by systemd socket activation.
+///
+/// Sockets are passed with `LISTEN_PID` and `LISTEN_FDS` environment variables,
+/// variables are removed, so sockets are consumed only once and are not
+/// inherited by child processes. Returns empty list if process was not
+/// started with socket activation. All passed sockets must be listening
+/// tcp stream sockets.
+///
+/// This function is available on unix platforms only.
+///
+/// ```rust,no_run
+/// use ntex::web::{self, App, HttpResponse, HttpServer};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     let mut srv = HttpServer::new(|| {
+///         App::new().service(web::resource("/").to(|| async { HttpResponse::Ok() }))
+///     });
+///     for lst in ntex::server::listen_fds()? {
+///         srv = srv.listen(lst)?;
+///     }
+///     srv.run().await
+/// }
+/// ```
+pub fn listen_fds() -> io::Result<Vec<net::TcpListener>> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let mut listeners = Vec::new();
+    for fd in listen_fds_range(pid.as_deref(), fds.as_deref(), std::process::id())? {
+        // fds are passed to current process and are not used by anything else
+        listeners.push(unsafe { from_fd(fd)? });
+    }
+    Ok(listeners)
+}
+
+#[cfg(unix)]
+fn listen_fds_range(
+    pid: Option<&str>,
+    fds: Option<&str>,
+    current: u32,
+) -> io::Result<std::ops::Range<std::os::unix::io::RawFd>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+
+    let (pid, fds) = match (pid, fds) {
+        (Some(pid), Some(fds)) => (pid, fds),
+        _ => return Ok(0..0),
+    };
+    let pid: u32 = pid
+        .parse()
+        .map_err(|_| invalid("Invalid LISTEN_PID value"))?;
+    if pid != current {
+        // sockets are passed to different process
+        return Ok(0..0);
+    }
+    let fds: std::os::unix::io::RawFd = fds
+        .parse()
+        .map_err(|_| invalid("Invalid LISTEN_FDS value"))?;
+    if fds < 0 {
+        return Err(invalid("Invalid LISTEN_FDS value"));
+    }
+    Ok(SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + fds)
+}
+
 #[cfg(windows)]
 mod listener_impl {
     use super::*;
@@ -140,6 +253,35 @@ pub enum Stream {
     Uds(std::os::unix::net::UnixStream),
 }
 
+impl Stream {
+    /// Convert to async io stream, tcp socket options are applied to tcp stream
+    pub(super) fn into_io(
+        self,
+        nodelay: bool,
+        keepalive: Option<Duration>,
+    ) -> io::Result<Io> {
+        match self {
+            Stream::Tcp(stream) => {
+                if let Some(time) = keepalive {
+                    let params = socket2::TcpKeepalive::new().with_time(time);
+                    socket2::SockRef::from(&stream).set_tcp_keepalive(&params)?;
+                }
+                if nodelay {
+                    rt::from_tcp_stream(stream)
+                } else {
+                    // runtimes enable TCP_NODELAY during conversion
+                    let sock = stream.try_clone()?;
+                    let io = rt::from_tcp_stream(stream)?;
+                    sock.set_nodelay(false)?;
+                    Ok(io)
+                }
+            }
+            #[cfg(unix)]
+            Stream::Uds(_) => self.try_into(),
+        }
+    }
+}
+
 impl TryFrom<Stream> for Io {
     type Error = io::Error;
 
@@ -173,6 +315,68 @@ mod tests {
         assert!(format!("{}", lst).contains("127.0.0.1"));
     }
 
+    #[crate::rt_test]
+    async fn test_stream_options() {
+        let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = lst.local_addr().unwrap();
+
+        let _client = net::TcpStream::connect(addr).unwrap();
+        let (stream, _) = lst.accept().unwrap();
+        let sock = stream.try_clone().unwrap();
+        let _io = Stream::Tcp(stream)
+            .into_io(false, Some(Duration::from_secs(30)))
+            .unwrap();
+        assert!(!sock.nodelay().unwrap());
+        assert!(socket2::SockRef::from(&sock).keepalive().unwrap());
+
+        let _client = net::TcpStream::connect(addr).unwrap();
+        let (stream, _) = lst.accept().unwrap();
+        let sock = stream.try_clone().unwrap();
+        let _io = Stream::Tcp(stream).into_io(true, None).unwrap();
+        assert!(sock.nodelay().unwrap());
+        assert!(!socket2::SockRef::from(&sock).keepalive().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_from_fd() {
+        use std::os::unix::io::IntoRawFd;
+
+        let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = lst.local_addr().unwrap();
+        let lst = unsafe { from_fd(lst.into_raw_fd()) }.unwrap();
+        assert_eq!(lst.local_addr().unwrap(), addr);
+
+        let sock = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let err = unsafe { from_fd(sock.into_raw_fd()) }.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // bound but not listening socket
+        #[cfg(target_os = "linux")]
+        {
+            use socket2::{Domain, Socket, Type};
+
+            let socket = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+            socket
+                .bind(&"127.0.0.1:0".parse::<net::SocketAddr>().unwrap().into())
+                .unwrap();
+            let err = unsafe { from_fd(socket.into_raw_fd()) }.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_listen_fds_range() {
+        assert_eq!(listen_fds_range(None, None, 10).unwrap(), 0..0);
+        assert_eq!(listen_fds_range(Some("10"), None, 10).unwrap(), 0..0);
+        assert_eq!(listen_fds_range(Some("11"), Some("2"), 10).unwrap(), 0..0);
+        assert_eq!(listen_fds_range(Some("10"), Some("2"), 10).unwrap(), 3..5);
+        assert!(listen_fds_range(Some("pid"), Some("2"), 10).is_err());
+        assert!(listen_fds_range(Some("10"), Some("-1"), 10).is_err());
+        assert!(listen_fds_range(Some("10"), Some("x"), 10).is_err());
+    }
+
     #[test]
     #[cfg(unix)]
     fn uds() {
@@ -0,0 +1,178 @@
+//! Systemd service manager notifications
+use std::{env, io, os::unix::net::UnixDatagram, path::PathBuf, process};
+
+use crate::time::Millis;
+
+/// Notification socket from `$NOTIFY_SOCKET` environment variable
+#[derive(Debug)]
+pub(super) struct Notify {
+    sock: UnixDatagram,
+    addr: NotifyAddr,
+    watchdog: Option<Millis>,
+    stopping: bool,
+}
+
+#[derive(Debug)]
+enum NotifyAddr {
+    Path(PathBuf),
+    #[cfg(target_os = "linux")]
+    Abstract(Vec<u8>),
+}
+
+impl Notify {
+    /// Create notification socket from environment
+    ///
+    /// Returns `None` if service is not started by systemd with
+    /// `Type=notify` setting.
+    pub(super) fn from_env() -> Option<Notify> {
+        let path = env::var_os("NOTIFY_SOCKET").filter(|p| !p.is_empty())?;
+        let addr = match path.to_str().and_then(|p| p.strip_prefix('@')) {
+            #[cfg(target_os = "linux")]
+            Some(name) => NotifyAddr::Abstract(name.as_bytes().to_vec()),
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => {
+                log::error!("Abstract notify socket is not supported: {:?}", path);
+                return None;
+            }
+            None => NotifyAddr::Path(path.into()),
+        };
+
+        let sock = match UnixDatagram::unbound() {
+            Ok(sock) => sock,
+            Err(e) => {
+                log::error!("Cannot create notify socket: {}", e);
+                return None;
+            }
+        };
+
+        Some(Notify {
+            sock,
+            addr,
+            watchdog: watchdog_interval(),
+            stopping: false,
+        })
+    }
+
+    /// Interval between watchdog notifications
+    ///
+    /// Watchdog is notified twice per `$WATCHDOG_USEC` period.
+    pub(super) fn watchdog(&self) -> Option<Millis> {
+        self.watchdog
+    }
+
+    pub(super) fn ready(&self) {
+        self.send("READY=1");
+    }
+
+    pub(super) fn stopping(&mut self) {
+        if !self.stopping {
+            self.stopping = true;
+            self.send("STOPPING=1");
+        }
+    }
+
+    pub(super) fn watchdog_ping(&self) {
+        if !self.stopping {
+            self.send("WATCHDOG=1");
+        }
+    }
+
+    fn send(&self, msg: &str) {
+        if let Err(e) = self.send_to(msg.as_bytes()) {
+            log::error!("Cannot send {:?} notification: {}", msg, e);
+        }
+    }
+
+    fn send_to(&self, msg: &[u8]) -> io::Result<usize> {
+        match self.addr {
+            NotifyAddr::Path(ref path) => self.sock.send_to(msg, path),
+            #[cfg(target_os = "linux")]
+            NotifyAddr::Abstract(ref name) => {
+                use std::os::linux::net::SocketAddrExt;
+
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                self.sock.send_to_addr(msg, &addr)
+            }
+        }
+    }
+}
+
+fn watchdog_interval() -> Option<Millis> {
+    // watchdog could be enabled for different process
+    if let Some(pid) = env::var_os("WATCHDOG_PID") {
+        if pid.to_str().and_then(|p| p.parse::<u32>().ok()) != Some(process::id()) {
+            return None;
+        }
+    }
+
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    let msecs = u32::try_from(usec / 2_000).unwrap_or(u32::MAX);
+    if msecs == 0 {
+        None
+    } else {
+        Some(Millis(msecs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env() {
+        env::remove_var("NOTIFY_SOCKET");
+        assert!(Notify::from_env().is_none());
+        env::set_var("NOTIFY_SOCKET", "");
+        assert!(Notify::from_env().is_none());
+
+        let path = env::temp_dir().join(format!("ntex-systemd-{}.sock", process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sock = UnixDatagram::bind(&path).unwrap();
+        env::set_var("NOTIFY_SOCKET", &path);
+        env::set_var("WATCHDOG_USEC", "3000000");
+        env::remove_var("WATCHDOG_PID");
+
+        let mut notify = Notify::from_env().unwrap();
+        assert_eq!(notify.watchdog(), Some(Millis(1500)));
+        notify.ready();
+        notify.watchdog_ping();
+        notify.stopping();
+        notify.stopping();
+        notify.watchdog_ping();
+
+        let mut buf = [0u8; 16];
+        sock.set_nonblocking(true).unwrap();
+        for msg in ["READY=1", "WATCHDOG=1", "STOPPING=1"] {
+            let n = sock.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..n], msg.as_bytes());
+        }
+        assert!(sock.recv(&mut buf).is_err());
+
+        // watchdog of different process
+        env::set_var("WATCHDOG_PID", "1");
+        assert_eq!(Notify::from_env().unwrap().watchdog(), None);
+        env::set_var("WATCHDOG_PID", process::id().to_string());
+        assert!(Notify::from_env().unwrap().watchdog().is_some());
+        env::set_var("WATCHDOG_USEC", "invalid");
+        assert_eq!(Notify::from_env().unwrap().watchdog(), None);
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let name = format!("ntex-systemd-{}", process::id());
+            let addr = SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+            let sock = UnixDatagram::bind_addr(&addr).unwrap();
+            env::set_var("NOTIFY_SOCKET", format!("@{}", name));
+            Notify::from_env().unwrap().ready();
+            let n = sock.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..n], b"READY=1");
+        }
+
+        env::remove_var("NOTIFY_SOCKET");
+        env::remove_var("WATCHDOG_USEC");
+        env::remove_var("WATCHDOG_PID");
+        let _ = std::fs::remove_file(&path);
+    }
+}
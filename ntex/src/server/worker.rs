@@ -1,18 +1,20 @@
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::{future::Future, pin::Pin, sync::Arc, task::Context, task::Poll};
+use std::{future::Future, pin::Pin, sync::Arc, task::Context, task::Poll, time::Duration};
 
 use async_channel::{unbounded, Receiver, Sender};
 use async_oneshot as oneshot;
 
 use crate::rt::{spawn, Arbiter};
 use crate::service::Pipeline;
-use crate::time::{sleep, Millis, Sleep};
+use crate::time::{now, sleep, Millis, Sleep};
 use crate::util::{
-    join_all, ready, select, stream_recv, BoxFuture, Either, Stream as FutStream,
+    join_all, ready, select, stream_recv, BoxFuture, Either, HashMap, Stream as FutStream,
 };
 
 use super::accept::{AcceptNotify, Command};
+use super::metrics::Metrics;
 use super::service::{BoxedServerService, InternalServiceFactory, ServerMessage};
+use super::shutdown::ShutdownSignal;
 use super::{counter::Counter, socket::Stream, Token};
 
 #[derive(Debug)]
@@ -33,26 +35,6 @@ pub(super) struct Connection {
 }
 
 const STOP_TIMEOUT: Millis = Millis::ONE_SEC;
-static MAX_CONNS: AtomicUsize = AtomicUsize::new(25600);
-
-/// Sets the maximum per-worker number of concurrent connections.
-///
-/// All socket listeners will stop accepting connections when this limit is
-/// reached for each worker.
-///
-/// By default max connections is set to a 25k per worker.
-pub(super) fn max_concurrent_connections(num: usize) {
-    MAX_CONNS.store(num, Ordering::Relaxed);
-}
-
-pub(super) fn num_connections() -> usize {
-    MAX_CONNS_COUNTER.with(|conns| conns.total())
-}
-
-thread_local! {
-    static MAX_CONNS_COUNTER: Counter =
-        Counter::new(MAX_CONNS.load(Ordering::Relaxed));
-}
 
 #[derive(Clone, Debug)]
 pub(super) struct WorkerClient {
@@ -87,6 +69,22 @@ impl WorkerClient {
         self.avail.available()
     }
 
+    /// Service is ready to accept connections
+    pub(super) fn service_available(&self, token: Token) -> bool {
+        self.avail.service_available(token)
+    }
+
+    /// Number of currently open connections
+    pub(super) fn connections(&self) -> usize {
+        self.avail.connections.load(Ordering::Relaxed)
+    }
+
+    /// Check and reset worker heartbeat
+    #[cfg(all(unix, feature = "systemd"))]
+    pub(super) fn heartbeat(&self) -> bool {
+        self.avail.heartbeat.swap(false, Ordering::Relaxed)
+    }
+
     pub(super) fn stop(&self, graceful: bool) -> oneshot::Receiver<bool> {
         let (result, rx) = oneshot::oneshot();
         let _ = self.tx2.try_send(StopCommand { graceful, result });
@@ -98,13 +96,19 @@ impl WorkerClient {
 pub(super) struct WorkerAvailability {
     notify: AcceptNotify,
     available: Arc<AtomicBool>,
+    connections: Arc<AtomicUsize>,
+    services: Arc<[AtomicBool]>,
+    heartbeat: Arc<AtomicBool>,
 }
 
 impl WorkerAvailability {
-    pub(super) fn new(notify: AcceptNotify) -> Self {
+    pub(super) fn new(notify: AcceptNotify, services: usize) -> Self {
         WorkerAvailability {
             notify,
             available: Arc::new(AtomicBool::new(false)),
+            connections: Arc::new(AtomicUsize::new(0)),
+            services: (0..services).map(|_| AtomicBool::new(false)).collect(),
+            heartbeat: Arc::new(AtomicBool::new(true)),
         }
     }
 
@@ -118,6 +122,27 @@ impl WorkerAvailability {
             self.notify.send(Command::WorkerAvailable)
         }
     }
+
+    fn service_available(&self, token: Token) -> bool {
+        self.services
+            .get(token.0)
+            .map(|srv| srv.load(Ordering::Acquire))
+            .unwrap_or(false)
+    }
+
+    fn beat(&self) {
+        self.heartbeat.store(true, Ordering::Relaxed);
+    }
+
+    fn set_service(&self, token: Token, val: bool) {
+        if let Some(srv) = self.services.get(token.0) {
+            let old = srv.swap(val, Ordering::Release);
+            // accept loop is notified by `set()` if worker is not available
+            if !old && val && self.available() {
+                self.notify.send(Command::WorkerAvailable)
+            }
+        }
+    }
 }
 
 /// Service worker
@@ -127,12 +152,13 @@ impl WorkerAvailability {
 pub(super) struct Worker {
     rx: Receiver<WorkerCommand>,
     rx2: Receiver<StopCommand>,
-    services: Vec<WorkerService>,
+    services: HashMap<Token, WorkerService>,
     availability: WorkerAvailability,
     conns: Counter,
     factories: Vec<Box<dyn InternalServiceFactory>>,
     state: WorkerState,
     shutdown_timeout: Millis,
+    metrics: Metrics,
 }
 
 struct WorkerService {
@@ -164,6 +190,9 @@ impl Worker {
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: Millis,
+        max_connections: usize,
+        metrics: Metrics,
+        heartbeat: Option<Millis>,
     ) -> WorkerClient {
         let (tx1, rx1) = unbounded();
         let (tx2, rx2) = unbounded();
@@ -171,10 +200,28 @@ impl Worker {
 
         Arbiter::default().exec_fn(move || {
             spawn(async move {
-                match Worker::create(rx1, rx2, factories, availability, shutdown_timeout)
-                    .await
+                match Worker::create(
+                    rx1,
+                    rx2,
+                    factories,
+                    availability,
+                    shutdown_timeout,
+                    max_connections,
+                    metrics,
+                )
+                .await
                 {
                     Ok(wrk) => {
+                        // heartbeat stops if worker's event loop is blocked
+                        if let Some(interval) = heartbeat {
+                            let avail = wrk.availability.clone();
+                            spawn(async move {
+                                loop {
+                                    avail.beat();
+                                    sleep(interval).await;
+                                }
+                            });
+                        }
                         spawn(wrk);
                     }
                     Err(e) => {
@@ -194,22 +241,25 @@ impl Worker {
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: Millis,
+        max_connections: usize,
+        metrics: Metrics,
     ) -> Result<Worker, ()> {
         availability.set(false);
-        let mut wrk = MAX_CONNS_COUNTER.with(move |conns| Worker {
+        let mut wrk = Worker {
             rx,
             rx2,
+            conns: Counter::new(max_connections, availability.connections.clone()),
             availability,
             factories,
             shutdown_timeout,
-            services: Vec::new(),
-            conns: conns.priv_clone(),
+            metrics,
+            services: HashMap::default(),
             state: WorkerState::Unavailable,
-        });
+        };
 
         let mut fut: Vec<BoxFuture<'static, _>> = Vec::new();
         for (idx, factory) in wrk.factories.iter().enumerate() {
-            let f = factory.create();
+            let f = factory.create(&wrk.metrics);
             fut.push(Box::pin(async move {
                 let r = f.await?;
 
@@ -236,12 +286,14 @@ impl Worker {
             Ok(services) => {
                 for item in services {
                     for (factory, token, service) in item {
-                        assert_eq!(token.0, wrk.services.len());
-                        wrk.services.push(WorkerService {
-                            factory,
-                            service: service.into(),
-                            status: WorkerServiceStatus::Unavailable,
-                        });
+                        wrk.services.insert(
+                            token,
+                            WorkerService {
+                                factory,
+                                service: service.into(),
+                                status: WorkerServiceStatus::Unavailable,
+                            },
+                        );
                     }
                 }
                 Ok(wrk)
@@ -252,7 +304,7 @@ impl Worker {
 
     fn shutdown(&mut self, force: bool) {
         if force {
-            self.services.iter_mut().for_each(|srv| {
+            self.services.values_mut().for_each(|srv| {
                 if srv.status == WorkerServiceStatus::Available {
                     srv.status = WorkerServiceStatus::Stopped;
                     let fut = srv
@@ -265,7 +317,7 @@ impl Worker {
             });
         } else {
             let timeout = self.shutdown_timeout;
-            self.services.iter_mut().for_each(move |srv| {
+            self.services.values_mut().for_each(move |srv| {
                 if srv.status == WorkerServiceStatus::Available {
                     srv.status = WorkerServiceStatus::Stopping;
 
@@ -280,30 +332,37 @@ impl Worker {
         }
     }
 
+    /// Check services readiness
+    ///
+    /// Worker is ready if it has connection capacity and at least one
+    /// service is ready, accept loop could skip workers with unavailable
+    /// service for specific connection.
     fn check_readiness(&mut self, cx: &mut Context<'_>) -> Result<bool, (Token, usize)> {
-        let mut ready = self.conns.available(cx);
+        let available = self.conns.available(cx);
+        let mut ready = false;
         let mut failed = None;
-        for (idx, srv) in &mut self.services.iter_mut().enumerate() {
+        for (token, srv) in self.services.iter_mut() {
             if srv.status == WorkerServiceStatus::Available
                 || srv.status == WorkerServiceStatus::Unavailable
             {
                 match srv.service.poll_ready(cx) {
                     Poll::Ready(Ok(_)) => {
+                        ready = true;
+                        self.availability.set_service(*token, true);
                         if srv.status == WorkerServiceStatus::Unavailable {
                             trace!(
                                 "Service {:?} is available",
-                                self.factories[srv.factory].name(Token(idx))
+                                self.factories[srv.factory].name(*token)
                             );
                             srv.status = WorkerServiceStatus::Available;
                         }
                     }
                     Poll::Pending => {
-                        ready = false;
-
+                        self.availability.set_service(*token, false);
                         if srv.status == WorkerServiceStatus::Available {
                             trace!(
                                 "Service {:?} is unavailable",
-                                self.factories[srv.factory].name(Token(idx))
+                                self.factories[srv.factory].name(*token)
                             );
                             srv.status = WorkerServiceStatus::Unavailable;
                         }
@@ -311,9 +370,10 @@ impl Worker {
                     Poll::Ready(Err(_)) => {
                         error!(
                             "Service {:?} readiness check returned error, restarting",
-                            self.factories[srv.factory].name(Token(idx))
+                            self.factories[srv.factory].name(*token)
                         );
-                        failed = Some((Token(idx), srv.factory));
+                        self.availability.set_service(*token, false);
+                        failed = Some((*token, srv.factory));
                         srv.status = WorkerServiceStatus::Failed;
                     }
                 }
@@ -322,7 +382,13 @@ impl Worker {
         if let Some(idx) = failed {
             Err(idx)
         } else {
-            Ok(ready)
+            Ok(available && ready)
+        }
+    }
+
+    fn set_status(&mut self, token: Token, status: WorkerServiceStatus) {
+        if let Some(srv) = self.services.get_mut(&token) {
+            srv.status = status;
         }
     }
 }
@@ -350,14 +416,16 @@ impl Future for Worker {
         })) = stop
         {
             self.availability.set(false);
-            let num = num_connections();
+            ShutdownSignal::notify();
+            let num = self.conns.total();
             if num == 0 {
                 info!("Shutting down worker, 0 connections");
                 let _ = result.send(true);
+                Arbiter::current().stop();
                 return Poll::Ready(());
             } else if graceful {
                 self.shutdown(false);
-                let num = num_connections();
+                let num = self.conns.total();
                 if num != 0 {
                     info!("Graceful worker shutdown, {} connections", num);
                     self.state = WorkerState::Shutdown(
@@ -367,6 +435,7 @@ impl Future for Worker {
                     );
                 } else {
                     let _ = result.send(true);
+                    Arbiter::current().stop();
                     return Poll::Ready(());
                 }
             } else {
@@ -377,6 +446,7 @@ impl Future for Worker {
             }
         }
 
+        let num_connections = self.conns.total();
         match self.state {
             WorkerState::Unavailable => {
                 match self.check_readiness(cx) {
@@ -392,11 +462,11 @@ impl Future for Worker {
                             "Service {:?} failed, restarting",
                             self.factories[idx].name(token)
                         );
-                        self.services[token.0].status = WorkerServiceStatus::Restarting;
+                        self.set_status(token, WorkerServiceStatus::Restarting);
                         self.state = WorkerState::Restarting(
                             idx,
                             token,
-                            self.factories[idx].create(),
+                            self.factories[idx].create(&self.metrics),
                         );
                         self.poll(cx)
                     }
@@ -411,7 +481,9 @@ impl Future for Worker {
                                 "Service {:?} has been restarted",
                                 self.factories[idx].name(token)
                             );
-                            self.services[token.0].created(service);
+                            if let Some(srv) = self.services.get_mut(&token) {
+                                srv.created(service);
+                            }
                             // service is restarted, now wait for readiness
                             self.state = WorkerState::Unavailable;
                             return self.poll(cx);
@@ -428,8 +500,7 @@ impl Future for Worker {
                 self.poll(cx)
             }
             WorkerState::Shutdown(ref mut t1, ref mut t2, ref mut tx) => {
-                let num = num_connections();
-                if num == 0 {
+                if num_connections == 0 {
                     let _ = tx.take().unwrap().send(true);
                     Arbiter::current().stop();
                     return Poll::Ready(());
@@ -472,11 +543,11 @@ impl Future for Worker {
                                 self.factories[idx].name(token)
                             );
                             self.availability.set(false);
-                            self.services[token.0].status = WorkerServiceStatus::Restarting;
+                            self.set_status(token, WorkerServiceStatus::Restarting);
                             self.state = WorkerState::Restarting(
                                 idx,
                                 token,
-                                self.factories[idx].create(),
+                                self.factories[idx].create(&self.metrics),
                             );
                             return self.poll(cx);
                         }
@@ -485,8 +556,14 @@ impl Future for Worker {
                     let next = ready!(Pin::new(&mut self.rx).poll_next(cx));
                     if let Some(WorkerCommand(msg)) = next {
                         // handle incoming io stream
+                        let srv = if let Some(srv) = self.services.get(&msg.token) {
+                            srv
+                        } else {
+                            error!("Worker does not run service for {:?}", msg.token);
+                            self.metrics.closed(msg.token, Duration::ZERO, true);
+                            continue;
+                        };
                         let guard = self.conns.get();
-                        let srv = &self.services[msg.token.0];
 
                         if log::log_enabled!(log::Level::Trace) {
                             trace!(
@@ -497,8 +574,11 @@ impl Future for Worker {
                         let fut = srv
                             .service
                             .call_static((Some(guard), ServerMessage::Connect(msg.io)));
+                        let (token, metrics, start) =
+                            (msg.token, self.metrics.clone(), now());
                         spawn(async move {
-                            let _ = fut.await;
+                            let res = fut.await;
+                            metrics.closed(token, now() - start, res.is_err());
                         });
                     } else {
                         return Poll::Ready(());
@@ -590,7 +670,7 @@ mod tests {
         let poll = Arc::new(polling::Poller::new().unwrap());
         let waker = poll.clone();
         let avail =
-            WorkerAvailability::new(AcceptNotify::new(waker.clone(), sync_tx.clone()));
+            WorkerAvailability::new(AcceptNotify::new(waker.clone(), sync_tx.clone()), 1);
 
         let st = Arc::new(Mutex::new(St::Pending));
         let counter = Arc::new(Mutex::new(0));
@@ -611,6 +691,8 @@ mod tests {
             )],
             avail.clone(),
             Millis(5_000),
+            25_600,
+            Metrics::default(),
         )
         .await
         .unwrap();
@@ -625,10 +707,13 @@ mod tests {
         *st.lock().unwrap() = St::Ready;
         let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
         assert!(avail.available());
+        assert!(avail.service_available(Token(0)));
+        assert!(!avail.service_available(Token(1)));
 
         *st.lock().unwrap() = St::Pending;
         let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
         assert!(!avail.available());
+        assert!(!avail.service_available(Token(0)));
 
         *st.lock().unwrap() = St::Ready;
         let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
@@ -648,7 +733,8 @@ mod tests {
         assert!(avail.available());
 
         // shutdown
-        let g = MAX_CONNS_COUNTER.with(|conns| conns.get());
+        let g = worker.conns.get();
+        assert_eq!(avail.connections.load(Ordering::Relaxed), 1);
 
         let (tx, rx) = oneshot::oneshot();
         tx2.try_send(StopCommand {
@@ -666,7 +752,7 @@ mod tests {
         // force shutdown
         let (_tx1, rx1) = unbounded();
         let (tx2, rx2) = unbounded();
-        let avail = WorkerAvailability::new(AcceptNotify::new(waker, sync_tx.clone()));
+        let avail = WorkerAvailability::new(AcceptNotify::new(waker, sync_tx.clone()), 1);
         let f = SrvFactory {
             st: st.clone(),
             counter: counter.clone(),
@@ -683,12 +769,14 @@ mod tests {
             )],
             avail.clone(),
             Millis(5_000),
+            25_600,
+            Metrics::default(),
         )
         .await
         .unwrap();
 
         // shutdown
-        let _g = MAX_CONNS_COUNTER.with(|conns| conns.get());
+        let _g = worker.conns.get();
 
         *st.lock().unwrap() = St::Ready;
         let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
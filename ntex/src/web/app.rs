@@ -11,11 +11,15 @@ use crate::util::{BoxFuture, Extensions, Ready};
 
 use super::app_service::{AppFactory, AppService};
 use super::config::{AppConfig, ServiceConfig};
+use super::info::TrustedProxies;
+use super::middleware::{Next, WrapFn};
 use super::request::WebRequest;
-use super::resource::Resource;
+use super::resource::{AutoOptions, Resource};
 use super::response::WebResponse;
 use super::route::Route;
-use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
+use super::service::{
+    insert_keyed_state, AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory,
+};
 use super::{DefaultError, ErrorRenderer};
 
 type HttpNewService<Err: ErrorRenderer> =
@@ -34,6 +38,7 @@ pub struct App<M, F, Err: ErrorRenderer = DefaultError> {
     state_factories: Vec<FnStateFactory>,
     error_renderer: Err,
     case_insensitive: bool,
+    rank_by_specificity: bool,
 }
 
 impl App<Identity, Filter<DefaultError>, DefaultError> {
@@ -49,6 +54,7 @@ impl App<Identity, Filter<DefaultError>, DefaultError> {
             extensions: Extensions::new(),
             error_renderer: DefaultError,
             case_insensitive: false,
+            rank_by_specificity: false,
         }
     }
 }
@@ -66,6 +72,7 @@ impl<Err: ErrorRenderer> App<Identity, Filter<Err>, Err> {
             extensions: Extensions::new(),
             error_renderer: err,
             case_insensitive: false,
+            rank_by_specificity: false,
         }
     }
 }
@@ -117,6 +124,95 @@ where
         self
     }
 
+    /// Set keyed application state.
+    ///
+    /// Keyed state allows to store multiple values of the same type,
+    /// values are accessed by key with `KeyedState<K, T>` extractor or
+    /// `HttpRequest::app_state_keyed()` method.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, types::KeyedState, types::StateKey, App};
+    ///
+    /// struct Primary;
+    /// impl StateKey for Primary {
+    ///     const KEY: &'static str = "primary";
+    /// }
+    ///
+    /// async fn index(db: KeyedState<Primary, String>) -> String {
+    ///     db.to_string()
+    /// }
+    ///
+    /// let app = App::new()
+    ///     .state_keyed("primary", "db1".to_string())
+    ///     .state_keyed("replica", "db2".to_string())
+    ///     .service(web::resource("/index.html").to(index));
+    /// ```
+    pub fn state_keyed<U: 'static>(mut self, key: &'static str, state: U) -> Self {
+        insert_keyed_state(&mut self.extensions, key, state);
+        self
+    }
+
+    /// Respond to `OPTIONS` requests automatically.
+    ///
+    /// If enabled, resources that do not have route for `OPTIONS` request
+    /// respond with `204 No Content` response and `Allow` header, which
+    /// contains methods accepted by resource routes. Resource's default
+    /// service is not called for such requests.
+    ///
+    /// By default automatic responses are disabled.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// let app = App::new()
+    ///     .auto_options(true)
+    ///     .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }));
+    /// ```
+    pub fn auto_options(mut self, enabled: bool) -> Self {
+        self.extensions.insert(AutoOptions(enabled));
+        self
+    }
+
+    /// Set trusted proxy networks.
+    ///
+    /// `Forwarded`, `X-Forwarded-For`, `X-Forwarded-Host` and `X-Forwarded-Proto`
+    /// headers are used by `ConnectionInfo` only if peer address belongs to one
+    /// of trusted networks. Real client address is available with
+    /// `ConnectionInfo::real_ip()` method and `RealIp` extractor.
+    ///
+    /// Networks are set in CIDR notation or as a single address. If trusted
+    /// proxies are not set, forwarded headers are always used by `ConnectionInfo`,
+    /// but real ip is always peer address.
+    ///
+    /// This method panics if network is not valid.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, types::RealIp, App};
+    ///
+    /// async fn index(ip: RealIp) -> String {
+    ///     ip.to_string()
+    /// }
+    ///
+    /// let app = App::new()
+    ///     .trusted_proxies(["10.0.0.0/8", "::1"])
+    ///     .service(web::resource("/index.html").to(index));
+    /// ```
+    pub fn trusted_proxies<I, P>(mut self, proxies: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut trusted = TrustedProxies::default();
+        for proxy in proxies {
+            let proxy = proxy.as_ref();
+            if trusted.add(proxy).is_err() {
+                panic!("Invalid trusted proxy network: {:?}", proxy);
+            }
+        }
+        self.extensions.insert(trusted);
+        self
+    }
+
     /// Set application state factory. This function is
     /// similar to `.state()` but it accepts state factory. State object get
     /// constructed asynchronously during application initialization.
@@ -366,6 +462,7 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         }
     }
 
@@ -406,16 +503,56 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         }
     }
 
+    /// Register a application middleware function.
+    ///
+    /// Function receives request and wrapped service, see
+    /// [`WrapFn`](super::middleware::WrapFn). Middleware functions get called
+    /// in the same order as middlewares.
+    pub fn wrap_fn<F, R>(self, f: F) -> App<Stack<M, WrapFn<F, Err>>, T, Err>
+    where
+        F: Fn(WebRequest<Err>, Next<Err>) -> R,
+        R: Future<Output = Result<WebResponse, Err::Container>>,
+    {
+        self.wrap(WrapFn::new(f))
+    }
+
     /// Use ascii case-insensitive routing.
     ///
-    /// Only static segments could be case-insensitive.
+    /// Static segments and static parts of dynamic segments are matched
+    /// case-insensitively, percent-encoded characters are decoded before
+    /// matching. Request path and values of dynamic segments keep
+    /// original case. Nested scopes use case-insensitive routing as well.
     pub fn case_insensitive_routing(mut self) -> Self {
         self.case_insensitive = true;
         self
     }
+
+    /// Match routes by specificity instead of registration order.
+    ///
+    /// Static patterns are matched before dynamic, patterns with longer
+    /// static prefix before patterns with shorter one, regex constrained
+    /// segments before unconstrained. Routes with the same rank are matched
+    /// in registration order. Nested scopes use the same ordering.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .rank_routes_by_specificity()
+    ///         .route("/users/{id}", web::get().to(|| async { HttpResponse::Ok() }))
+    ///         // not shadowed by `/users/{id}`
+    ///         .route("/users/me", web::get().to(|| async { HttpResponse::Ok() }));
+    /// }
+    /// ```
+    pub fn rank_routes_by_specificity(mut self) -> Self {
+        self.rank_by_specificity = true;
+        self
+    }
 }
 
 impl<M, F, Err> App<M, F, Err>
@@ -498,6 +635,7 @@ where
             default: self.default,
             extensions: RefCell::new(Some(self.extensions)),
             case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         };
         map_config(app, move |_| cfg.clone())
     }
@@ -526,6 +664,7 @@ where
             default: self.default,
             extensions: RefCell::new(Some(self.extensions)),
             case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         }
     }
 }
@@ -552,6 +691,7 @@ where
             default: self.default,
             extensions: RefCell::new(Some(self.extensions)),
             case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         }
     }
 }
@@ -721,6 +861,31 @@ mod tests {
         assert!(filter.get());
     }
 
+    #[crate::rt_test]
+    async fn test_wrap_fn() {
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|req, srv| async move {
+                    let mut res = srv.call(req).await?;
+                    res.headers_mut()
+                        .insert(header::CONTENT_TYPE, HeaderValue::from_static("0001"));
+                    Ok(res)
+                })
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "0001");
+
+        // middleware function is applied to default service
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "0001");
+    }
+
     #[crate::rt_test]
     async fn test_wrap() {
         let srv = init_service(
@@ -761,6 +926,62 @@ mod tests {
         );
     }
 
+    #[crate::rt_test]
+    async fn test_rank_routes_by_specificity() {
+        let srv = init_service(
+            App::new()
+                .route(
+                    "/users/{id}",
+                    web::get().to(|| async { HttpResponse::Ok() }),
+                )
+                .route(
+                    "/users/me",
+                    web::get().to(|| async { HttpResponse::Created() }),
+                ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/users/me").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let srv = init_service(
+            App::new()
+                .rank_routes_by_specificity()
+                .route(
+                    "/users/{id}",
+                    web::get().to(|| async { HttpResponse::Ok() }),
+                )
+                .route(
+                    "/users/me",
+                    web::get().to(|| async { HttpResponse::Created() }),
+                )
+                .service(
+                    web::scope("/api")
+                        .route("/{name}", web::get().to(|| async { HttpResponse::Ok() }))
+                        .route(
+                            "/{id:\\d+}",
+                            web::get().to(|| async { HttpResponse::Accepted() }),
+                        ),
+                ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/users/me").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let req = TestRequest::with_uri("/users/1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/api/1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let req = TestRequest::with_uri("/api/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[crate::rt_test]
     async fn test_case_insensitive_router() {
         let srv = init_service(
@@ -776,6 +997,58 @@ mod tests {
         let req = TestRequest::with_uri("/Test").to_request();
         let resp = call_service(&srv, req).await;
         assert_eq!(resp.status(), StatusCode::OK);
+
+        let srv = init_service(App::new().case_insensitive_routing().service(
+            web::scope("/api").service(web::resource("/v{version}/users/{id}").to(
+                |req: HttpRequest| async move {
+                    HttpResponse::Ok().body(format!(
+                        "{} {} {}",
+                        req.path(),
+                        req.match_info().get("version").unwrap(),
+                        req.match_info().get("id").unwrap(),
+                    ))
+                },
+            )),
+        ))
+        .await;
+        let req = TestRequest::with_uri("/API/V1/Users/AbC").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"/API/V1/Users/AbC 1 AbC")
+        );
+
+        let req = TestRequest::with_uri("/api/v1/accounts/AbC").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_encoded_path() {
+        let srv = init_service(App::new().service(web::resource("/files/{name}").to(
+            |req: HttpRequest| async move {
+                let info = req.match_info();
+                HttpResponse::Ok().body(format!(
+                    "{} {}",
+                    info.get("name").unwrap(),
+                    info.get_raw("name").unwrap()
+                ))
+            },
+        )))
+        .await;
+        let req = TestRequest::with_uri("/files/my%20doc%2Ftxt").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(b"my doc/txt my%20doc%2Ftxt")
+        );
+
+        // invalid utf-8
+        let req = TestRequest::with_uri("/files/doc%FF").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
     #[cfg(feature = "url")]
@@ -2,7 +2,7 @@ use std::task::{Context, Poll};
 use std::{cell::RefCell, future::Future, marker::PhantomData, pin::Pin, rc::Rc};
 
 use crate::http::{Request, Response};
-use crate::router::{Path, ResourceDef, Router};
+use crate::router::{is_valid_path, Path, ResourceDef, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::service::dev::ServiceChainFactory;
 use crate::service::{
@@ -14,6 +14,7 @@ use super::config::AppConfig;
 use super::error::ErrorRenderer;
 use super::guard::Guard;
 use super::httprequest::{HttpRequest, HttpRequestPool};
+use super::info::TrustedProxies;
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
@@ -48,6 +49,7 @@ where
     pub(super) default: Option<Rc<HttpNewService<Err>>>,
     pub(super) external: RefCell<Vec<ResourceDef>>,
     pub(super) case_insensitive: bool,
+    pub(super) rank_by_specificity: bool,
 }
 
 impl<T, F, Err> ServiceFactory<Request> for AppFactory<T, F, Err>
@@ -113,10 +115,15 @@ where
         let middleware = self.middleware.clone();
         let external = std::mem::take(&mut *self.external.borrow_mut());
 
+        let case_insensitive = self.case_insensitive;
+        let rank_by_specificity = self.rank_by_specificity;
         let mut router = Router::build();
-        if self.case_insensitive {
+        if case_insensitive {
             router.case_insensitive();
         }
+        if rank_by_specificity {
+            router.rank_by_specificity();
+        }
 
         Box::pin(async move {
             // app state factories
@@ -127,6 +134,12 @@ where
 
             // App config
             let mut config = WebServiceConfig::new(state.clone(), default.clone());
+            if case_insensitive {
+                config.set_case_insensitive();
+            }
+            if rank_by_specificity {
+                config.set_rank_by_specificity();
+            }
 
             // register services
             services
@@ -152,6 +165,10 @@ where
             // complete ResourceMap tree creation
             let rmap = Rc::new(rmap);
             rmap.finish(rmap.clone());
+            if let Err(e) = rmap.check_names() {
+                log::error!("Cannot construct resource map: {}", e);
+                return Err(());
+            }
 
             // create http services
             for (path, factory, guards) in &mut services.iter() {
@@ -215,6 +232,7 @@ where
             inner.head = head;
             inner.payload = payload;
             inner.app_state = self.state.clone();
+            inner.match_name = None;
             req
         } else {
             HttpRequest::new(
@@ -256,6 +274,19 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for AppRouting<Err> {
         mut req: WebRequest<Err>,
         ctx: ServiceCtx<'a, Self>,
     ) -> Self::Future<'a> {
+        // percent-encoded path must be valid utf-8
+        if !is_valid_path(req.path()) {
+            let req = req.into_parts().0;
+            return Either::Right(Box::pin(async {
+                Ok(WebResponse::new(Response::BadRequest().finish(), req))
+            }));
+        }
+
+        // guards use connection info, it must respect trusted proxies
+        if req.app_state::<TrustedProxies>().is_some() {
+            let _ = req.connection_info();
+        }
+
         let res = self.router.recognize_checked(&mut req, |req, guards| {
             if let Some(guards) = guards {
                 for f in guards {
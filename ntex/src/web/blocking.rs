@@ -0,0 +1,197 @@
+//! Thread pool for blocking operations
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{fmt, panic, thread};
+
+use crate::http::error::BlockingError;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Thread pool for blocking operations
+///
+/// Pool runs fixed number of threads and keeps limited number of
+/// pending operations. If all threads are busy and queue is full,
+/// new operations get rejected with `BlockingError::Overloaded` error,
+/// default error renderer responds with `503 Service Unavailable`.
+///
+/// Pool could be registered as application state and used from handlers.
+///
+/// ```rust
+/// use ntex::web::{self, types::State, App, BlockingPool, Error};
+///
+/// async fn index(pool: State<BlockingPool>) -> Result<String, Error> {
+///     let value = pool
+///         .execute(|| Ok::<_, std::io::Error>("value".to_string()))
+///         .await?;
+///     Ok(value)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .state(BlockingPool::new(4, 32))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BlockingPool {
+    tx: mpsc::SyncSender<Job>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+}
+
+impl BlockingPool {
+    /// Create thread pool with `threads` threads and max `queue` pending operations
+    ///
+    /// Pool threads stop after all pool instances are dropped.
+    pub fn new(threads: usize, queue: usize) -> Self {
+        assert!(threads > 0, "Number of threads must be more than 0");
+
+        let (tx, rx) = mpsc::sync_channel::<Job>(queue);
+        let rx = Arc::new(Mutex::new(rx));
+        let counters = Arc::new(Counters::default());
+
+        for idx in 0..threads {
+            let rx = rx.clone();
+            let counters = counters.clone();
+            thread::Builder::new()
+                .name(format!("ntex-blocking:{}", idx))
+                .spawn(move || loop {
+                    let job = rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => {
+                            counters.queued.fetch_sub(1, Ordering::Relaxed);
+                            counters.busy.fetch_add(1, Ordering::Relaxed);
+                            let _ = panic::catch_unwind(panic::AssertUnwindSafe(job));
+                            counters.busy.fetch_sub(1, Ordering::Relaxed);
+                        }
+                        Err(_) => return,
+                    }
+                })
+                .expect("Cannot spawn blocking pool thread");
+        }
+
+        BlockingPool { tx, counters }
+    }
+
+    /// Number of operations waiting for a free thread
+    pub fn queued(&self) -> usize {
+        self.counters.queued.load(Ordering::Relaxed)
+    }
+
+    /// Number of threads running operations
+    pub fn busy(&self) -> usize {
+        self.counters.busy.load(Ordering::Relaxed)
+    }
+
+    /// Execute blocking function on the pool, returns future that resolves
+    /// to result of the function execution.
+    ///
+    /// Returns `BlockingError::Overloaded` immediately if pool queue is full.
+    pub async fn execute<F, I, E>(&self, f: F) -> Result<I, BlockingError<E>>
+    where
+        F: FnOnce() -> Result<I, E> + Send + 'static,
+        I: Send + 'static,
+        E: Send + fmt::Debug + 'static,
+    {
+        let (tx, rx) = async_channel::bounded(1);
+        let job = Box::new(move || {
+            let _ = tx.try_send(f());
+        });
+
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        if let Err(err) = self.tx.try_send(job) {
+            self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            return match err {
+                mpsc::TrySendError::Full(_) => Err(BlockingError::Overloaded),
+                mpsc::TrySendError::Disconnected(_) => Err(BlockingError::Canceled),
+            };
+        }
+
+        match rx.recv().await {
+            Ok(res) => res.map_err(BlockingError::Error),
+            Err(_) => Err(BlockingError::Canceled),
+        }
+    }
+}
+
+impl fmt::Debug for BlockingPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingPool")
+            .field("queued", &self.queued())
+            .field("busy", &self.busy())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::time::{sleep, Millis};
+    use crate::web::{DefaultError, WebResponseError};
+
+    #[crate::rt_test]
+    async fn test_execute() {
+        let pool = BlockingPool::new(2, 2);
+        let res = pool.execute(|| Ok::<_, &'static str>(10)).await;
+        assert_eq!(res.unwrap(), 10);
+
+        let res = pool.execute(|| Err::<usize, _>("error")).await;
+        assert!(matches!(res, Err(BlockingError::Error("error"))));
+
+        let res = pool
+            .execute(|| -> Result<usize, &'static str> { panic!() })
+            .await;
+        assert!(matches!(res, Err(BlockingError::Canceled)));
+
+        // pool still works after panic
+        let res = pool.execute(|| Ok::<_, &'static str>(1)).await;
+        assert_eq!(res.unwrap(), 1);
+        assert!(format!("{:?}", pool).contains("BlockingPool"));
+    }
+
+    #[crate::rt_test]
+    async fn test_overloaded() {
+        let pool = BlockingPool::new(1, 1);
+        let (tx, rx) = mpsc::channel::<()>();
+        let rx = Arc::new(Mutex::new(rx));
+
+        // occupy thread and queue
+        for _ in 0..2 {
+            let pool = pool.clone();
+            let rx = rx.clone();
+            crate::rt::spawn(async move {
+                let _ = pool
+                    .execute(move || {
+                        rx.lock().unwrap().recv_timeout(Duration::from_secs(5)).ok();
+                        Ok::<_, ()>(())
+                    })
+                    .await;
+            });
+            sleep(Millis(50)).await;
+        }
+        assert_eq!(pool.busy(), 1);
+        assert_eq!(pool.queued(), 1);
+
+        let err = pool.execute(|| Ok::<_, ()>(())).await.unwrap_err();
+        assert!(matches!(err, BlockingError::Overloaded));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        tx.send(()).unwrap();
+        tx.send(()).unwrap();
+        sleep(Millis(100)).await;
+        assert_eq!(pool.busy(), 0);
+        assert_eq!(pool.queued(), 0);
+        assert!(pool.execute(|| Ok::<_, ()>(())).await.is_ok());
+    }
+}
@@ -204,4 +204,46 @@ mod tests {
         let resp = call_service(&srv, req).await;
         assert_eq!(resp.status(), StatusCode::OK);
     }
+
+    #[crate::rt_test]
+    async fn test_configure_order_and_state() {
+        fn items(cfg: &mut ServiceConfig) {
+            cfg.state("items".to_string()).route(
+                "/items/new",
+                web::get().to(|| async { HttpResponse::Created() }),
+            );
+        }
+
+        fn item(cfg: &mut ServiceConfig) {
+            cfg.route(
+                "/items/{id}",
+                web::get().to(
+                    |st: web::types::State<String>, id: web::types::Path<String>| async move {
+                        HttpResponse::Ok().body(format!("{} {}", st.get_ref(), id))
+                    },
+                ),
+            );
+        }
+
+        let srv = init_service(
+            App::new()
+                .configure(items)
+                .configure(item)
+                .service(web::scope("/scope").configure(items).configure(item)),
+        )
+        .await;
+
+        for prefix in ["", "/scope"] {
+            // first registered route matches first
+            let req = TestRequest::with_uri(&format!("{}/items/new", prefix)).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            // state from another configure closure is visible
+            let req = TestRequest::with_uri(&format!("{}/items/5", prefix)).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(read_body(resp).await, Bytes::from_static(b"items 5"));
+        }
+    }
 }
@@ -1,5 +1,5 @@
 //! Web error
-use std::{cell::RefCell, fmt, io::Write, marker::PhantomData};
+use std::{any::TypeId, cell::RefCell, fmt, io::Write, marker::PhantomData};
 
 use thiserror::Error;
 
@@ -52,6 +52,19 @@ where
         );
         resp.set_body(Body::from(buf))
     }
+
+    #[doc(hidden)]
+    /// Type id of the error, used for downcasting.
+    ///
+    /// Do not implement, helper argument is not nameable outside of the crate.
+    fn __private_get_type_id__(&self, _: private::PrivateHelper) -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
+
+pub(crate) mod private {
+    #[derive(Debug)]
+    pub struct PrivateHelper(pub(crate) ());
 }
 
 impl<Err: ErrorRenderer> WebResponseError<Err> for std::convert::Infallible {}
@@ -77,26 +90,94 @@ where
     }
 }
 
+/// A set of errors that can occur in `Either` extractor
+#[derive(Error, Debug)]
+pub enum EitherError<A, B> {
+    /// Request payload cannot be buffered
+    #[error("Cannot buffer request payload: {0}")]
+    Payload(PayloadError),
+    /// Both extractors failed
+    #[error("Either extractor failed: {0}; {1}")]
+    Extract(A, B),
+}
+
+/// Response is created by the first extractor's error
+impl<A, B, Err> WebResponseError<Err> for EitherError<A, B>
+where
+    A: WebResponseError<Err>,
+    B: WebResponseError<Err>,
+    PayloadError: WebResponseError<Err>,
+    Err: ErrorRenderer,
+{
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EitherError::Payload(ref e) => e.status_code(),
+            EitherError::Extract(ref a, _) => a.status_code(),
+        }
+    }
+
+    fn error_response(&self, req: &HttpRequest) -> HttpResponse {
+        match self {
+            EitherError::Payload(ref e) => e.error_response(req),
+            EitherError::Extract(ref a, _) => a.error_response(req),
+        }
+    }
+}
+
 /// Errors which can occur when attempting to work with `State` extractor
 #[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum StateExtractorError {
-    #[error("App state is not configured, to configure use App::state()")]
-    NotConfigured,
+    #[error("App state of type {0} is not configured, to configure use App::state()")]
+    NotConfigured(&'static str),
+    #[error(
+        "App state for key {0:?} is not configured, to configure use App::state_keyed()"
+    )]
+    KeyNotConfigured(&'static str),
 }
 
 #[deprecated]
 #[doc(hidden)]
 pub type DataExtractorError = StateExtractorError;
 
-/// Errors which can occur when attempting to generate resource uri.
+/// Errors which can occur when attempting to work with `RequestId` extractor
 #[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RequestIdError {
+    #[error("Request id is not set, to configure use middleware::RequestId")]
+    NotConfigured,
+}
+
+/// Errors which can occur when attempting to work with `ReqData` extractor
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReqDataError {
+    #[error("Request data of type {0} is not set in request extensions")]
+    NotFound(&'static str),
+}
+
+/// Errors which can occur when attempting to work with `RealIp` extractor
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RealIpError {
+    #[error("Client ip address is unknown")]
+    Unknown,
+}
+
+/// Errors which can occur when attempting to generate resource uri.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum UrlGenerationError {
     /// Resource not found
     #[error("Resource not found")]
     ResourceNotFound,
     /// Not all path pattern covered
-    #[error("Not all path pattern covered")]
-    NotEnoughElements,
+    #[error("Not all path pattern covered for resource {0:?}")]
+    NotEnoughElements(String),
+    /// Value for dynamic segment of path pattern is missing
+    #[error("Value for parameter {1:?} of resource {0:?} is missing")]
+    MissingValue(String, String),
+    /// Not all elements are used by path pattern
+    #[error("Too many elements for resource {0:?}")]
+    TooManyElements(String),
+    /// Element does not match path pattern
+    #[error("Invalid element for resource {0:?}: {1}")]
+    InvalidElement(String, crate::router::BuildError),
     /// URL parse error
     #[cfg(feature = "url")]
     #[error("{0}")]
@@ -151,6 +232,47 @@ pub enum PathError {
     /// Deserialize error
     #[error("Path deserialize error: {0}")]
     Deserialize(#[from] serde::de::value::Error),
+    /// Path segment is missing
+    #[error("Path segment{} is missing", segment_name(*index, name))]
+    Missing {
+        index: Option<usize>,
+        name: Option<String>,
+    },
+    /// Path segment cannot be deserialized
+    #[error("Path segment `{name}` deserialize error: {error}")]
+    Segment {
+        index: usize,
+        name: String,
+        error: serde::de::value::Error,
+    },
+}
+
+fn segment_name(index: Option<usize>, name: &Option<String>) -> String {
+    match (name, index) {
+        (Some(name), _) => format!(" `{}`", name),
+        (None, Some(index)) => format!(" {}", index),
+        (None, None) => String::new(),
+    }
+}
+
+impl PathError {
+    /// Index of the failed path segment, if known
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            PathError::Missing { index, .. } => *index,
+            PathError::Segment { index, .. } => Some(*index),
+            PathError::Deserialize(_) => None,
+        }
+    }
+
+    /// Name of the failed path segment, if known
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            PathError::Missing { name, .. } => name.as_deref(),
+            PathError::Segment { name, .. } => Some(name),
+            PathError::Deserialize(_) => None,
+        }
+    }
 }
 
 /// A set of errors that can occur during parsing query strings
@@ -159,6 +281,22 @@ pub enum QueryPayloadError {
     /// Deserialize error
     #[error("Query deserialize error: {0}")]
     Deserialize(#[from] serde::de::value::Error),
+    /// Deserialize error of query parameter
+    #[error("Query deserialize error for field `{field}`: {error}")]
+    Field {
+        field: String,
+        error: serde::de::value::Error,
+    },
+}
+
+impl QueryPayloadError {
+    /// Name of the query parameter that failed to deserialize, if known
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            QueryPayloadError::Field { field, .. } => Some(field),
+            QueryPayloadError::Deserialize(_) => None,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -705,6 +843,28 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_downcast() {
+        fn payload() -> Result<(), Error> {
+            Err(PayloadError::Payload(error::PayloadError::Overflow))?;
+            Ok(())
+        }
+
+        let err = payload().unwrap_err();
+        assert!(err.is::<PayloadError>());
+        assert!(matches!(
+            err.downcast_ref::<PayloadError>(),
+            Some(PayloadError::Payload(error::PayloadError::Overflow))
+        ));
+        assert!(err.downcast_ref::<UrlencodedError>().is_none());
+
+        let err = err.downcast::<UrlencodedError>().unwrap_err();
+        match err.downcast::<PayloadError>() {
+            Ok(PayloadError::Payload(error::PayloadError::Overflow)) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn test_other_errors() {
         let req = TestRequest::default().to_http_request();
@@ -1,5 +1,5 @@
 //! Web error
-use std::{fmt, io, io::Write, str::Utf8Error};
+use std::{any::TypeId, fmt, io, io::Write, str::Utf8Error};
 
 use serde::de::value::Error as DeError;
 use serde_json::error::Error as JsonError;
@@ -39,6 +39,36 @@ impl Error {
     pub fn as_response_error(&self) -> &dyn WebResponseError<DefaultError> {
         self.cause.as_ref()
     }
+
+    /// Returns `true` if the underlying error is of type `T`.
+    pub fn is<T: WebResponseError<DefaultError>>(&self) -> bool {
+        self.cause
+            .__private_get_type_id__(error::private::PrivateHelper(()))
+            == TypeId::of::<T>()
+    }
+
+    /// Returns a reference to the underlying error if it is of type `T`.
+    pub fn downcast_ref<T: WebResponseError<DefaultError>>(&self) -> Option<&T> {
+        if self.is::<T>() {
+            // SAFETY: type id of the boxed error is checked above
+            unsafe { Some(&*(self.cause.as_ref() as *const _ as *const T)) }
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to downcast to the underlying error of type `T`.
+    ///
+    /// Returns the original error if it is of a different type.
+    pub fn downcast<T: WebResponseError<DefaultError>>(self) -> Result<T, Error> {
+        if self.is::<T>() {
+            // SAFETY: type id of the boxed error is checked above
+            let raw = Box::into_raw(self.cause);
+            Ok(*unsafe { Box::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
 }
 
 /// `Error` for any error which implements `WebResponseError<DefaultError>`
@@ -57,6 +87,10 @@ impl ErrorContainer for Error {
 }
 
 impl crate::http::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        self.cause.status_code()
+    }
+
     fn error_response(&self) -> HttpResponse {
         let mut resp = HttpResponse::new(self.cause.status_code());
         let mut buf = BytesMut::new();
@@ -94,6 +128,15 @@ impl<E: WebResponseError<DefaultError>> WebResponseError<DefaultError> for Timeo
 /// `InternalServerError` for `StateExtractorError`
 impl WebResponseError<DefaultError> for error::StateExtractorError {}
 
+/// `InternalServerError` for `RequestIdError`
+impl WebResponseError<DefaultError> for error::RequestIdError {}
+
+/// `InternalServerError` for `ReqDataError`
+impl WebResponseError<DefaultError> for error::ReqDataError {}
+
+/// `InternalServerError` for `RealIpError`
+impl WebResponseError<DefaultError> for error::RealIpError {}
+
 /// `InternalServerError` for `JsonError`
 impl WebResponseError<DefaultError> for JsonError {}
 
@@ -121,10 +164,18 @@ impl WebResponseError<DefaultError> for DeError {
 /// `InternalServerError` for `Canceled`
 impl WebResponseError<DefaultError> for crate::http::error::Canceled {}
 
-/// `InternalServerError` for `BlockingError`
+/// `InternalServerError` for `BlockingError`, `ServiceUnavailable` for overloaded pool
 impl<E: fmt::Debug + 'static> WebResponseError<DefaultError>
     for crate::http::error::BlockingError<E>
 {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            crate::http::error::BlockingError::Overloaded => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 /// Return `BAD_REQUEST` for `Utf8Error`
@@ -187,20 +238,38 @@ impl WebResponseError<DefaultError> for error::QueryPayloadError {
     }
 }
 
+/// `PayloadError` returns:
+///
+/// - `PayloadTooLarge` if payload exceeds configured limit
+/// - `UnsupportedMediaType` for unexpected content type or charset
+/// - `BadRequest` for other errors
 impl WebResponseError<DefaultError> for error::PayloadError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match self {
+            error::PayloadError::Payload(err) => {
+                WebResponseError::<DefaultError>::status_code(err)
+            }
+            error::PayloadError::ContentType(
+                http::error::ContentTypeError::Unexpected
+                | http::error::ContentTypeError::Expected
+                | http::error::ContentTypeError::UnknownEncoding,
+            ) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
 /// `PayloadError` returns two possible results:
 ///
-/// - `Overflow` returns `PayloadTooLarge`
+/// - `Overflow` and `LimitExceeded` returns `PayloadTooLarge`
 /// - Other errors returns `BadRequest`
 impl WebResponseError<DefaultError> for http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
-            http::error::PayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            http::error::PayloadError::Overflow
+            | http::error::PayloadError::LimitExceeded { .. } => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -253,6 +322,9 @@ impl WebResponseError<DefaultError> for HandshakeError {
             HandshakeError::UnsupportedVersion => HttpResponse::BadRequest()
                 .reason("Unsupported version")
                 .finish(),
+            HandshakeError::UnsupportedProtocol => HttpResponse::BadRequest()
+                .reason("No supported websocket protocol")
+                .finish(),
             HandshakeError::BadWebsocketKey => HttpResponse::BadRequest()
                 .reason("Handshake error")
                 .finish(),
@@ -1,9 +1,10 @@
 //! Request extractors
 use std::{future::Future, pin::Pin, task::Context, task::Poll};
 
-use super::error::ErrorRenderer;
+use super::error::{EitherError, ErrorRenderer, PayloadError};
 use super::httprequest::HttpRequest;
-use crate::{http::Payload, util::BoxFuture, util::Ready};
+use crate::http::{error, h1, Payload, Response};
+use crate::util::{stream_recv, BoxFuture, BytesMut, Either, Ready};
 
 /// Trait implemented by types that can be extracted from request.
 ///
@@ -17,8 +18,29 @@ pub trait FromRequest<Err>: Sized {
 
     /// Convert request to a Self
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future;
+
+    #[doc(hidden)]
+    /// Max size of request payload consumed by extractor
+    ///
+    /// Used by extractors that buffer payload, `None` means extractor
+    /// does not define own limit.
+    fn payload_limit(_: &HttpRequest) -> Option<usize> {
+        None
+    }
+
+    #[doc(hidden)]
+    /// Create response for extractor error
+    ///
+    /// Used by extractors with configurable error handler, response is
+    /// returned instead of rendered error for any error renderer.
+    fn error_response(_: &Self::Error, _: &HttpRequest) -> Option<Response> {
+        None
+    }
 }
 
+/// Response created by extractor error handler
+struct ErrorResponse(Response);
+
 /// Optionally extract a field from the request
 ///
 /// If the FromRequest for T fails, return None rather than returning an error response
@@ -86,6 +108,11 @@ where
             }
         })
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        T::payload_limit(req)
+    }
 }
 
 /// Optionally extract a field from the request or extract the Error if unsuccessful
@@ -150,6 +177,120 @@ where
             }
         })
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        T::payload_limit(req)
+    }
+}
+
+/// Extract one of two types from the request
+///
+/// `A` extractor is tried first, if it fails `B` extractor is used. If any of
+/// extractors consumes request payload, payload is buffered before extraction,
+/// so both extractors receive the same body. Max size of buffered payload is
+/// the larger of `A` and `B` payload limits, i.e.
+/// [**JsonConfig**](types/struct.JsonConfig.html) limit for `Json` extractor.
+/// Errors of payload buffering are returned without trying `B` extractor.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::web::{self, types::{Form, Json}, Either};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     name: String,
+/// }
+///
+/// /// accept json or form encoded body
+/// async fn index(info: Either<Json<Info>, Form<Info>>) -> String {
+///     match info {
+///         Either::Left(json) => format!("Json: {}", json.name),
+///         Either::Right(form) => format!("Form: {}", form.name),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/index.html").route(web::post().to(index))
+///     );
+/// }
+/// ```
+impl<A, B, Err> FromRequest<Err> for Either<A, B>
+where
+    A: FromRequest<Err> + 'static,
+    B: FromRequest<Err> + 'static,
+    Err: ErrorRenderer,
+{
+    type Error = EitherError<A::Error, B::Error>;
+    type Future = BoxFuture<'static, Result<Either<A, B>, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = if let Some(limit) = either_limit::<A, B, Err>(req) {
+            limit
+        } else {
+            // extractors do not use payload, it is left for other extractors
+            let left = A::from_request(req, payload);
+            let right = B::from_request(req, payload);
+            return Box::pin(async move {
+                let left = match left.await {
+                    Ok(a) => return Ok(Either::Left(a)),
+                    Err(e) => e,
+                };
+                match right.await {
+                    Ok(b) => Ok(Either::Right(b)),
+                    Err(right) => Err(EitherError::Extract(left, right)),
+                }
+            });
+        };
+        let req = req.clone();
+        let mut payload = payload.take();
+
+        Box::pin(async move {
+            let mut body = BytesMut::new();
+            while let Some(item) = stream_recv(&mut payload).await {
+                let chunk = item.map_err(|e| EitherError::Payload(e.into()))?;
+                if body.len() + chunk.len() > limit {
+                    return Err(EitherError::Payload(PayloadError::Payload(
+                        error::PayloadError::Overflow,
+                    )));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+
+            let mut pl = h1::Payload::empty();
+            pl.unread_data(body.clone());
+            let left = match A::from_request(&req, &mut Payload::from(pl)).await {
+                Ok(a) => return Ok(Either::Left(a)),
+                Err(e) => e,
+            };
+
+            let mut pl = h1::Payload::empty();
+            pl.unread_data(body);
+            match B::from_request(&req, &mut Payload::from(pl)).await {
+                Ok(b) => Ok(Either::Right(b)),
+                Err(right) => Err(EitherError::Extract(left, right)),
+            }
+        })
+    }
+
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        either_limit::<A, B, Err>(req)
+    }
+}
+
+/// Larger of `A` and `B` payload limits
+fn either_limit<A, B, Err>(req: &HttpRequest) -> Option<usize>
+where
+    A: FromRequest<Err>,
+    B: FromRequest<Err>,
+{
+    match (A::payload_limit(req), B::payload_limit(req)) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
 }
 
 #[doc(hidden)]
@@ -176,16 +317,22 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
 
         fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
             $fut_type {
+                req: req.clone(),
                 items: <($(Option<$T>,)+)>::default(),
                 $($T: $T::from_request(req, payload),)+
             }
         }
+
+        fn error_response(_: &Self::Error, req: &HttpRequest) -> Option<Response> {
+            req.extensions_mut().remove::<ErrorResponse>().map(|res| res.0)
+        }
     }
 
     pin_project_lite::pin_project! {
         #[doc(hidden)]
     pub struct $fut_type<Err: ErrorRenderer, $($T: FromRequest<Err>),+>
     {
+        req: HttpRequest,
         items: ($(Option<$T>,)+),
         $(#[pin] $T: $T::Future),+
     }
@@ -208,7 +355,13 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
                             this.items.$n = Some(item);
                         }
                         Poll::Pending => ready = false,
-                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                        Poll::Ready(Err(e)) => {
+                            // keep error handler response for handler
+                            if let Some(res) = $T::error_response(&e, this.req) {
+                                this.req.extensions_mut().insert(ErrorResponse(res));
+                            }
+                            return Poll::Ready(Err(e.into()));
+                        }
                     }
                 }
             )+
@@ -244,7 +397,7 @@ tuple_from_req!(TupleFromRequest10, (0, A), (1, B), (2, C), (3, D), (4, E), (5,
 #[cfg(test)]
 mod tests {
     use crate::http::header;
-    use crate::util::Bytes;
+    use crate::util::{stream_recv, Bytes};
     use crate::web::error::UrlencodedError;
     use crate::web::test::{from_request, TestRequest};
     use crate::web::types::{Form, FormConfig};
@@ -333,4 +486,85 @@ mod tests {
             .unwrap();
         assert!(r.is_err());
     }
+
+    #[crate::rt_test]
+    async fn test_either() {
+        use crate::util::Either;
+        use crate::web::error::EitherError;
+        use crate::web::types::{Json, JsonConfig, Path, PayloadConfig, Query};
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .set_payload(Bytes::from_static(b"{\"hello\": \"json\"}"))
+                .to_http_parts();
+        let r = from_request::<Either<Json<Info>, Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(matches!(r, Either::Left(ref j) if j.hello == "json"));
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .set_payload(Bytes::from_static(b"hello=form"))
+        .to_http_parts();
+        let r = from_request::<Either<Json<Info>, Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(matches!(r, Either::Right(ref f) if f.hello == "form"));
+
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .set_payload(Bytes::from_static(b"hello=form"))
+            .to_http_parts();
+        let r = from_request::<Either<Json<Info>, Form<Info>>>(&req, &mut pl).await;
+        let err = r.err().unwrap();
+        assert!(matches!(
+            err,
+            EitherError::Extract(_, UrlencodedError::ContentType)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Either extractor failed: Content type error; Content type error"
+        );
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .set_payload(Bytes::from_static(b"{\"hello\": \"json\"}"))
+                .state(JsonConfig::default().limit(8))
+                .state(FormConfig::default().limit(8))
+                .to_http_parts();
+        let r = from_request::<Either<Json<Info>, Form<Info>>>(&req, &mut pl).await;
+        assert!(matches!(r.err().unwrap(), EitherError::Payload(_)));
+
+        // extractors without own limit use payload config
+        let (req, mut pl) = TestRequest::default()
+            .set_payload(Bytes::from_static(b"{\"hello\": \"json\"}"))
+            .state(PayloadConfig::default().limit(8))
+            .to_http_parts();
+        let r = from_request::<Either<Bytes, String>>(&req, &mut pl).await;
+        assert!(matches!(r.err().unwrap(), EitherError::Payload(_)));
+
+        // limit of `A` extractor is larger than default payload limit
+        let body = format!("{{\"hello\": \"{}\"}}", "a".repeat(300_000));
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .set_payload(Bytes::from(body))
+                .state(JsonConfig::default().limit(512 * 1024))
+                .to_http_parts();
+        let r = from_request::<Either<Json<Info>, Form<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(matches!(r, Either::Left(ref j) if j.hello.len() == 300_000));
+
+        // payload is not buffered if extractors do not use it
+        let (req, mut pl) = TestRequest::with_uri("/?hello=query")
+            .set_payload(Bytes::from_static(b"{\"hello\": \"json\"}"))
+            .state(PayloadConfig::default().limit(8))
+            .to_http_parts();
+        let r = from_request::<Either<Path<Info>, Query<Info>>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert!(matches!(r, Either::Right(ref q) if q.hello == "query"));
+        assert!(stream_recv(&mut pl).await.unwrap().is_ok());
+    }
 }
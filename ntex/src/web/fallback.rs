@@ -0,0 +1,282 @@
+//! Fallback chain for default services
+use std::{cell::Cell, cell::RefCell, fmt, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::http::{error::PayloadError, Payload, StatusCode};
+use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::{IntoServiceFactory, Service, ServiceCtx, ServiceFactory};
+use crate::util::{BoxFuture, Bytes, Stream};
+
+use super::error::ErrorRenderer;
+use super::request::WebRequest;
+use super::response::WebResponse;
+
+type HttpService<Err: ErrorRenderer> =
+    BoxService<WebRequest<Err>, WebResponse, Err::Container>;
+type HttpNewService<Err: ErrorRenderer> =
+    BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
+
+/// Chain of services tried in registration order.
+///
+/// Service signals that request is not handled by returning *404 Not Found*
+/// response, in that case request is passed to the next service in chain.
+/// Response of the last service is returned as is.
+///
+/// Request is passed to the next service only if previous service did not
+/// read request payload and did not keep a copy of `HttpRequest`, otherwise
+/// *404 Not Found* response is returned. Request extensions are preserved
+/// between services, payload trailers are not available in chain services.
+///
+/// Use [`fallback()`](super::fallback) function to create a chain.
+pub struct Fallback<Err: ErrorRenderer> {
+    services: Vec<HttpNewService<Err>>,
+}
+
+impl<Err: ErrorRenderer> Fallback<Err> {
+    pub(super) fn new<F, U>(f: F) -> Self
+    where
+        F: IntoServiceFactory<U, WebRequest<Err>>,
+        U: ServiceFactory<WebRequest<Err>, Response = WebResponse, Error = Err::Container>
+            + 'static,
+        U::InitError: fmt::Debug,
+    {
+        Fallback {
+            services: Vec::new(),
+        }
+        .or(f)
+    }
+
+    /// Add service to the end of the chain.
+    pub fn or<F, U>(mut self, f: F) -> Self
+    where
+        F: IntoServiceFactory<U, WebRequest<Err>>,
+        U: ServiceFactory<WebRequest<Err>, Response = WebResponse, Error = Err::Container>
+            + 'static,
+        U::InitError: fmt::Debug,
+    {
+        self.services
+            .push(boxed::factory(f.into_factory().map_init_err(|e| {
+                log::error!("Cannot construct fallback service: {:?}", e)
+            })));
+        self
+    }
+}
+
+impl<Err: ErrorRenderer> fmt::Debug for Fallback<Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fallback")
+            .field("services", &self.services.len())
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for Fallback<Err> {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type InitError = ();
+    type Service = FallbackService<Err>;
+    type Future<'f> = BoxFuture<'f, Result<Self::Service, Self::InitError>>;
+
+    fn create(&self, _: ()) -> Self::Future<'_> {
+        Box::pin(async move {
+            let mut services = Vec::with_capacity(self.services.len());
+            for factory in &self.services {
+                services.push(factory.create(()).await?);
+            }
+            Ok(FallbackService { services })
+        })
+    }
+}
+
+pub struct FallbackService<Err: ErrorRenderer> {
+    services: Vec<HttpService<Err>>,
+}
+
+impl<Err: ErrorRenderer> fmt::Debug for FallbackService<Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackService")
+            .field("services", &self.services.len())
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> Service<WebRequest<Err>> for FallbackService<Err> {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut ready = true;
+        for srv in &self.services {
+            ready = srv.poll_ready(cx)?.is_ready() && ready;
+        }
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut ready = true;
+        for srv in &self.services {
+            ready = srv.poll_shutdown(cx).is_ready() && ready;
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        Box::pin(async move {
+            let mut req = req;
+            let payload = SharedPayload::new(req.take_payload());
+
+            let (last, services) = self.services.split_last().unwrap();
+            for srv in services {
+                if let Some(ref pl) = payload {
+                    req.set_payload(Payload::from_stream(pl.clone()));
+                }
+                let res = ctx.call(srv, req).await?;
+                if res.status() != StatusCode::NOT_FOUND {
+                    return Ok(res);
+                }
+                if payload
+                    .as_ref()
+                    .map(|pl| pl.consumed.get())
+                    .unwrap_or(false)
+                {
+                    log::debug!("Request payload is consumed, skip fallback services");
+                    return Ok(res);
+                }
+
+                let (hreq, res) = res.into_parts();
+                req = match WebRequest::from_request(hreq) {
+                    Ok(req) => req,
+                    Err(hreq) => {
+                        log::debug!("Request is in use, skip fallback services");
+                        return Ok(WebResponse::new(res, hreq));
+                    }
+                };
+            }
+            if let Some(pl) = payload {
+                req.set_payload(Payload::from_stream(pl));
+            }
+            ctx.call(last, req).await
+        })
+    }
+}
+
+/// Payload shared between services of the chain
+#[derive(Clone)]
+struct SharedPayload {
+    payload: Rc<RefCell<Payload>>,
+    consumed: Rc<Cell<bool>>,
+}
+
+impl SharedPayload {
+    fn new(payload: Payload) -> Option<Self> {
+        if let Payload::None = payload {
+            None
+        } else {
+            Some(SharedPayload {
+                payload: Rc::new(RefCell::new(payload)),
+                consumed: Rc::new(Cell::new(false)),
+            })
+        }
+    }
+}
+
+impl Stream for SharedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = self.payload.borrow_mut().poll_recv(cx);
+        if let Poll::Ready(Some(_)) = item {
+            self.consumed.set(true);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::{Method, StatusCode};
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_fallback() {
+        let srv = init_service(
+            App::new()
+                .service(web::resource("/api").to(|| async { HttpResponse::Ok() }))
+                .default_service(
+                    web::fallback(web::to(|req: HttpRequest| async move {
+                        if req.path().starts_with("/static/") {
+                            HttpResponse::Ok().body("static")
+                        } else {
+                            HttpResponse::NotFound().finish()
+                        }
+                    }))
+                    .or(web::to(|req: HttpRequest| async move {
+                        if req.method() == Method::GET {
+                            HttpResponse::Ok().body("index")
+                        } else {
+                            HttpResponse::NotFound().finish()
+                        }
+                    }))
+                    .or(web::to(|body: Bytes| async move {
+                        HttpResponse::NotFound().body(format!("not found {:?}", body))
+                    })),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static/app.js").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "static");
+
+        let req = TestRequest::with_uri("/users/1").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "index");
+
+        // payload is passed to the last service
+        let req = TestRequest::with_uri("/users/1")
+            .method(Method::POST)
+            .set_payload("data")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read_body(res).await, "not found b\"data\"");
+    }
+
+    #[crate::rt_test]
+    async fn test_fallback_payload_consumed() {
+        let srv = init_service(
+            App::new().default_service(
+                web::fallback(web::to(|_: Bytes| async { HttpResponse::NotFound() }))
+                    .or(web::to(|| async { HttpResponse::Ok() })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::default().set_payload("data").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -30,6 +30,20 @@
 use std::fmt;
 
 use crate::http::{header, Method, RequestHead, Uri};
+use crate::web::info::ConnectionInfo;
+
+/// Methods that are checked for `Allow` header of `405 Method Not Allowed` response
+pub(super) const ALLOW_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::CONNECT,
+    Method::OPTIONS,
+    Method::TRACE,
+    Method::PATCH,
+];
 
 /// Trait defines resource guards. Guards are used for route selection.
 ///
@@ -44,6 +58,20 @@ pub trait Guard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("Guard").finish()
     }
+
+    /// Host name and port of virtual host guard, used for url generation
+    #[doc(hidden)]
+    fn host(&self) -> Option<(&str, Option<u16>)> {
+        None
+    }
+
+    /// Http methods accepted by guard, used for `Allow` header
+    ///
+    /// Returns `None` if guard does not restrict request method.
+    #[doc(hidden)]
+    fn methods(&self) -> Option<Vec<Method>> {
+        None
+    }
 }
 
 /// Create guard object for supplied function.
@@ -135,6 +163,18 @@ impl Guard for AnyGuard {
         false
     }
 
+    fn methods(&self) -> Option<Vec<Method>> {
+        let mut methods = Vec::new();
+        for p in &self.0 {
+            for m in p.methods()? {
+                if !methods.contains(&m) {
+                    methods.push(m);
+                }
+            }
+        }
+        Some(methods)
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "AnyGuard(")?;
@@ -184,6 +224,14 @@ impl AllGuard {
     pub fn add<F: Guard + 'static>(&mut self, guard: F) {
         self.0.push(Box::new(guard));
     }
+
+    /// Check guards that do not restrict request method
+    pub(super) fn check_non_method(&self, request: &RequestHead) -> bool {
+        self.0
+            .iter()
+            .filter(|p| p.methods().is_none())
+            .all(|p| p.check(request))
+    }
 }
 
 impl Guard for AllGuard {
@@ -196,6 +244,13 @@ impl Guard for AllGuard {
         true
     }
 
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.0
+            .iter()
+            .filter_map(|p| p.methods())
+            .reduce(|a, b| a.into_iter().filter(|m| b.contains(m)).collect())
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "AllGuard(")?;
@@ -213,6 +268,18 @@ impl fmt::Debug for AllGuard {
 }
 
 /// Return guard that matches if supplied guard does not match.
+///
+/// ```rust
+/// use ntex::web::{self, guard, App, HttpResponse};
+///
+/// fn main() {
+///     App::new().service(web::resource("/index.html").route(
+///         web::route()
+///             .guard(guard::Not(guard::Any(guard::Options()).or(guard::Trace())))
+///             .to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
 pub fn Not<F: Guard + 'static>(guard: F) -> NotGuard {
     NotGuard(Box::new(guard))
 }
@@ -225,6 +292,16 @@ impl Guard for NotGuard {
         !self.0.check(request)
     }
 
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.0.methods().map(|methods| {
+            ALLOW_METHODS
+                .iter()
+                .filter(|m| !methods.contains(m))
+                .cloned()
+                .collect()
+        })
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "NotGuard(")?;
@@ -249,6 +326,10 @@ impl Guard for MethodGuard {
         request.method == self.0
     }
 
+    fn methods(&self) -> Option<Vec<Method>> {
+        Some(vec![self.0.clone()])
+    }
+
     /// Debug format
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(self, f)
@@ -334,39 +415,110 @@ impl Guard for HeaderGuard {
 
 /// Return predicate that matches if request contains specified Host name.
 ///
+/// Host is resolved with [`ConnectionInfo`](super::dev::ConnectionInfo), so
+/// forwarded host of trusted proxies is used. Host name is compared
+/// case-insensitively, port is ignored unless it is specified in guard host.
+///
+/// Host name could contain wildcard subdomain, i.e. `*.example.com`, it
+/// matches any subdomain but not `example.com` itself. Matched subdomain is
+/// stored in request extensions as [`Subdomain`].
+///
+/// Guard with host name without wildcard could be used for virtual hosts,
+/// urls generated with `HttpRequest::url_for()` for resources of a scope
+/// with host guard use guard host.
+///
 /// ```rust
 /// use ntex::web::{self, guard::Host, App, HttpResponse};
 ///
 /// fn main() {
-///     App::new().service(
-///         web::resource("/index.html")
-///             .guard(Host("www.rust-lang.org"))
-///             .to(|| async { HttpResponse::MethodNotAllowed() })
-///     );
+///     App::new()
+///         .service(
+///             web::scope("/")
+///                 .guard(Host("www.rust-lang.org"))
+///                 .route("/index.html", web::get().to(|| async { HttpResponse::Ok() })),
+///         )
+///         .service(
+///             web::scope("/")
+///                 .guard(Host("*.crates.io"))
+///                 .route("/index.html", web::get().to(|| async { HttpResponse::Ok() })),
+///         );
 /// }
 /// ```
 pub fn Host<H: AsRef<str>>(host: H) -> HostGuard {
-    HostGuard(host.as_ref().to_string(), None)
+    let host = host.as_ref().to_ascii_lowercase();
+    let (name, port) = split_port(&host);
+    HostGuard {
+        name: name.to_string(),
+        port,
+        scheme: None,
+    }
+}
+
+/// Split host to host name and port
+fn split_port(host: &str) -> (&str, Option<u16>) {
+    if let Some((name, port)) = host.rsplit_once(':') {
+        if !name.is_empty() && !name.ends_with(':') {
+            if let Ok(port) = port.parse() {
+                return (name, Some(port));
+            }
+        }
+    }
+    (host, None)
 }
 
 fn get_host_uri(req: &RequestHead) -> Option<Uri> {
     use core::str::FromStr;
-    req.headers
-        .get(header::HOST)
-        .and_then(|host_value| host_value.to_str().ok())
-        .or_else(|| req.uri.host())
-        .map(|host: &str| Uri::from_str(host).ok())
-        .and_then(|host_success| host_success)
+
+    let host = if let Some(info) = req.extensions().get::<ConnectionInfo>() {
+        info.host().to_string()
+    } else {
+        ConnectionInfo::resolve_host(req)?
+    };
+    Uri::from_str(&host).ok()
+}
+
+/// Subdomain matched by wildcard [`Host`] guard
+///
+/// Subdomain is stored in request extensions, it is available with
+/// [`ReqData`](super::types::ReqData) extractor.
+///
+/// ```rust
+/// use ntex::web::{self, guard, types::ReqData, App};
+///
+/// async fn index(sub: ReqData<guard::Subdomain>) -> String {
+///     format!("tenant: {}", sub.as_str())
+/// }
+///
+/// fn main() {
+///     App::new().service(
+///         web::resource("/")
+///             .guard(guard::Host("*.example.com"))
+///             .to(index),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subdomain(String);
+
+impl Subdomain {
+    /// Get subdomain
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 #[doc(hidden)]
 #[derive(Debug)]
-pub struct HostGuard(String, Option<String>);
+pub struct HostGuard {
+    name: String,
+    port: Option<u16>,
+    scheme: Option<String>,
+}
 
 impl HostGuard {
     /// Set request scheme to match
     pub fn scheme<H: AsRef<str>>(mut self, scheme: H) -> HostGuard {
-        self.1 = Some(scheme.as_ref().to_string());
+        self.scheme = Some(scheme.as_ref().to_string());
         self
     }
 }
@@ -379,21 +531,42 @@ impl Guard for HostGuard {
             return false;
         };
 
-        if let Some(uri_host) = req_host_uri.host() {
-            if self.0 != uri_host {
-                return false;
-            }
+        let req_host = if let Some(host) = req_host_uri.host() {
+            host.to_ascii_lowercase()
         } else {
             return false;
+        };
+        if self.port.is_some() && self.port != req_host_uri.port_u16() {
+            return false;
         }
 
-        if let Some(ref scheme) = self.1 {
-            if let Some(ref req_host_uri_scheme) = req_host_uri.scheme_str() {
-                return scheme == req_host_uri_scheme;
+        if let Some(ref scheme) = self.scheme {
+            if let Some(req_host_uri_scheme) = req_host_uri.scheme_str() {
+                if scheme != req_host_uri_scheme {
+                    return false;
+                }
             }
         }
 
-        true
+        if let Some(suffix) = self.name.strip_prefix('*') {
+            if req_host.len() > suffix.len() && req_host.ends_with(suffix) {
+                let sub = &req_host[..req_host.len() - suffix.len()];
+                req.extensions_mut().insert(Subdomain(sub.to_string()));
+                true
+            } else {
+                false
+            }
+        } else {
+            self.name == req_host
+        }
+    }
+
+    fn host(&self) -> Option<(&str, Option<u16>)> {
+        if self.name.starts_with('*') {
+            None
+        } else {
+            Some((&self.name, self.port))
+        }
     }
 
     /// Debug format
@@ -507,6 +680,70 @@ mod tests {
         assert!(!pred.check(req.head()));
     }
 
+    #[test]
+    fn test_host_port() {
+        let req = TestRequest::with_header(header::HOST, "www.Rust-Lang.org:8080")
+            .to_http_request();
+        assert!(Host("www.rust-lang.org").check(req.head()));
+        assert!(Host("WWW.rust-lang.org:8080").check(req.head()));
+        assert!(!Host("www.rust-lang.org:80").check(req.head()));
+
+        let req = TestRequest::with_header(header::HOST, "[::1]:8080").to_http_request();
+        assert!(Host("[::1]").check(req.head()));
+        assert!(Host("[::1]:8080").check(req.head()));
+        assert!(!Host("[::1]:80").check(req.head()));
+    }
+
+    #[test]
+    fn test_host_wildcard() {
+        let pred = Host("*.rust-lang.org");
+        assert!(pred.host().is_none());
+
+        let req = TestRequest::with_header(header::HOST, "blog.rust-lang.org:8080")
+            .to_http_request();
+        assert!(pred.check(req.head()));
+        assert_eq!(
+            req.extensions().get::<Subdomain>().unwrap().as_str(),
+            "blog"
+        );
+
+        let req =
+            TestRequest::with_header(header::HOST, "a.b.rust-lang.org").to_http_request();
+        assert!(pred.check(req.head()));
+        assert_eq!(req.extensions().get::<Subdomain>().unwrap().as_str(), "a.b");
+
+        for host in ["rust-lang.org", ".rust-lang.org", "blogrust-lang.org"] {
+            let req = TestRequest::with_header(header::HOST, host).to_http_request();
+            assert!(!pred.check(req.head()));
+            assert!(req.extensions().get::<Subdomain>().is_none());
+        }
+    }
+
+    #[test]
+    fn test_host_forwarded() {
+        let req = TestRequest::with_header(header::HOST, "internal:8080")
+            .header("x-forwarded-host", "www.rust-lang.org, proxy")
+            .to_http_request();
+        assert!(Host("www.rust-lang.org").check(req.head()));
+        assert!(!Host("internal").check(req.head()));
+
+        let req = TestRequest::with_header(header::HOST, "internal:8080")
+            .header(header::FORWARDED, "host=crates.io;proto=https")
+            .header("x-forwarded-host", "www.rust-lang.org")
+            .to_http_request();
+        assert!(Host("crates.io").check(req.head()));
+
+        // connection info of request is used
+        let req = TestRequest::with_header(header::HOST, "internal:8080")
+            .header("x-forwarded-host", "www.rust-lang.org")
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "www.rust-lang.org");
+        if let Some(info) = req.head().extensions_mut().get_mut::<ConnectionInfo>() {
+            *info = ConnectionInfo::default();
+        }
+        assert!(!Host("www.rust-lang.org").check(req.head()));
+    }
+
     #[test]
     fn test_methods() {
         let req = TestRequest::default().to_http_request();
@@ -587,4 +824,53 @@ mod tests {
         let g = |req: &RequestHead| req.headers().contains_key("content-type");
         assert!(g.check(req.head()));
     }
+
+    #[test]
+    fn test_nested() {
+        let get = TestRequest::default().to_http_request();
+        let head = TestRequest::default()
+            .method(Method::HEAD)
+            .to_http_request();
+        let options = TestRequest::default()
+            .method(Method::OPTIONS)
+            .to_http_request();
+
+        let g = Not(Any(Get()).or(Head()));
+        assert!(!g.check(get.head()));
+        assert!(!g.check(head.head()));
+        assert!(g.check(options.head()));
+
+        let g = All(Not(Options()))
+            .and(Any(Get()).or(fn_guard(|req| req.headers().contains_key("content-type"))));
+        assert!(g.check(get.head()));
+        assert!(!g.check(head.head()));
+        assert!(!g.check(options.head()));
+        let req = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .method(Method::HEAD)
+            .to_http_request();
+        assert!(g.check(req.head()));
+
+        assert!(Not(Not(Get())).check(get.head()));
+        assert!(format!("{:?}", Not(Any(Get()))).contains("NotGuard(AnyGuard("));
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        use std::{cell::Cell, rc::Rc};
+
+        let req = TestRequest::default().to_http_request();
+        let calls = Rc::new(Cell::new(0));
+        let counter = |result: bool| {
+            let calls = calls.clone();
+            fn_guard(move |_| {
+                calls.set(calls.get() + 1);
+                result
+            })
+        };
+
+        assert!(Any(counter(true)).or(counter(true)).check(req.head()));
+        assert_eq!(calls.get(), 1);
+        assert!(!All(counter(false)).and(counter(true)).check(req.head()));
+        assert_eq!(calls.get(), 2);
+    }
 }
@@ -78,7 +78,12 @@ where
             let (req, mut payload) = req.into_parts();
             let param = match T::from_request(&req, &mut payload).await {
                 Ok(param) => param,
-                Err(e) => return Ok(WebResponse::from_err::<Err, _>(e, req)),
+                Err(e) => {
+                    if let Some(res) = T::error_response(&e, &req) {
+                        return Ok(WebResponse::new(res, req));
+                    }
+                    return Ok(WebResponse::from_err::<Err, _>(e, req));
+                }
             };
 
             let result = self.hnd.call(param).await;
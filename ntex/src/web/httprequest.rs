@@ -1,16 +1,16 @@
-use std::{cell::Ref, cell::RefCell, cell::RefMut, fmt, net, rc::Rc};
+use std::{cell::Ref, cell::RefCell, cell::RefMut, fmt, io, net, rc::Rc};
 
 use crate::http::{
-    HeaderMap, HttpMessage, Message, Method, Payload, RequestHead, Uri, Version,
+    HeaderMap, HttpMessage, Message, Method, Payload, RequestHead, StatusCode, Uri, Version,
 };
-use crate::io::{types, IoRef};
+use crate::io::IoRef;
 use crate::router::Path;
 use crate::util::{Extensions, Ready};
 
 use super::config::AppConfig;
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
-use super::info::ConnectionInfo;
+use super::info::{ConnectionInfo, TrustedProxies};
 use super::rmap::ResourceMap;
 use super::service::AppState;
 
@@ -23,6 +23,7 @@ pub(crate) struct HttpRequestInner {
     pub(crate) path: Path<Uri>,
     pub(crate) payload: Payload,
     pub(crate) app_state: AppState,
+    pub(crate) match_name: Option<Rc<str>>,
     rmap: Rc<ResourceMap>,
     pool: &'static HttpRequestPool,
 }
@@ -44,6 +45,30 @@ impl HttpRequest {
             app_state,
             rmap,
             pool,
+            match_name: None,
+        }))
+    }
+
+    /// Create copy of the request that is not shared with the original.
+    ///
+    /// Copy contains request head without extensions, payload is empty.
+    pub(crate) fn detached(&self) -> HttpRequest {
+        let mut head = Message::<RequestHead>::new();
+        head.uri = self.0.head.uri.clone();
+        head.method = self.0.head.method.clone();
+        head.version = self.0.head.version;
+        head.headers = self.0.head.headers.clone();
+        head.flags = self.0.head.flags;
+        head.peer_addr = self.0.head.peer_addr;
+
+        HttpRequest(Rc::new(HttpRequestInner {
+            head,
+            path: self.0.path.clone(),
+            payload: Payload::None,
+            app_state: self.0.app_state.clone(),
+            rmap: self.0.rmap.clone(),
+            pool: self.0.pool,
+            match_name: self.0.match_name.clone(),
         }))
     }
 }
@@ -116,9 +141,18 @@ impl HttpRequest {
     /// ntex http server, then peer address would be address of this proxy.
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.io()
-            .map(|io| io.query::<types::PeerAddr>().get().map(|addr| addr.0))
-            .unwrap_or(None)
+        self.head().peer_addr()
+    }
+
+    /// Send informational (1xx) response, i.e. `103 Early Hints`
+    ///
+    /// See [`RequestHead::send_informational()`] for details.
+    pub fn send_informational(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> io::Result<()> {
+        self.head().send_informational(status, headers)
     }
 
     /// Get a reference to the Path parameters.
@@ -137,6 +171,16 @@ impl HttpRequest {
         &mut Rc::get_mut(&mut self.0).unwrap().path
     }
 
+    /// Name of matched resource.
+    ///
+    /// Name includes name prefixes of parent scopes, it could be used
+    /// with `url_for()` method. Returns `None` if matched resource is
+    /// not named or if no resource is matched yet.
+    #[inline]
+    pub fn match_name(&self) -> Option<&str> {
+        self.0.match_name.as_deref()
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -180,6 +224,57 @@ impl HttpRequest {
         self.0.rmap.url_for(self, name, elements)
     }
 
+    #[cfg(feature = "url")]
+    /// Generate url with query parameters for named resource
+    ///
+    /// Query keys and values get percent-encoded. Scheme and host are taken
+    /// from [`ConnectionInfo`](super::dev::ConnectionInfo), so `Forwarded` and
+    /// `X-Forwarded-*` headers set by proxy are honored.
+    ///
+    /// ```rust
+    /// # use ntex::web::{self, App, HttpRequest, HttpResponse};
+    /// #
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     let url = req.url_for_query("foo", ["1"], &[("page", "2"), ("q", "a b")]);
+    ///     HttpResponse::Ok().into()
+    /// }
+    /// ```
+    pub fn url_for_query<U, I, K, V>(
+        &self,
+        name: &str,
+        elements: U,
+        query: &[(K, V)],
+    ) -> Result<url_pkg::Url, super::error::UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.0.rmap.url_for_query(self, name, elements, query)
+    }
+
+    #[cfg(feature = "url")]
+    /// Generate relative url for named resource
+    ///
+    /// Generated url contains only path and query, without scheme and host,
+    /// it could be used for `Location` headers behind path-rewriting proxies.
+    /// Urls for external resources are always absolute.
+    pub fn url_for_relative<U, I, K, V>(
+        &self,
+        name: &str,
+        elements: U,
+        query: &[(K, V)],
+    ) -> Result<String, super::error::UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.0.rmap.url_for_relative(self, name, elements, query)
+    }
+
     #[cfg(feature = "url")]
     /// Generate url for named resource
     ///
@@ -205,7 +300,11 @@ impl HttpRequest {
     /// borrowed.
     #[inline]
     pub fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
-        ConnectionInfo::get(self.head(), self.app_config())
+        ConnectionInfo::get_with_proxies(
+            self.head(),
+            self.app_config(),
+            self.app_state::<TrustedProxies>(),
+        )
     }
 
     /// App config
@@ -222,9 +321,17 @@ impl HttpRequest {
     /// ```rust,ignore
     /// let opt_t = req.app_data::<State<T>>();
     /// ```
+    ///
+    /// State is resolved from the innermost level, resource state overrides
+    /// scope state and scope state overrides application state.
     pub fn app_state<T: 'static>(&self) -> Option<&T> {
         self.0.app_state.get::<T>()
     }
+
+    /// Get an application state object stored with `App::state_keyed()`
+    pub fn app_state_keyed<T: 'static>(&self, key: &str) -> Option<&T> {
+        self.0.app_state.get_keyed::<T>(key)
+    }
 }
 
 impl HttpMessage for HttpRequest {
@@ -413,7 +520,20 @@ mod tests {
         );
         assert_eq!(
             req.url_for("index", ["test"]),
-            Err(crate::web::error::UrlGenerationError::NotEnoughElements)
+            Err(crate::web::error::UrlGenerationError::MissingValue(
+                "index".to_string(),
+                "ext".to_string()
+            ))
+        );
+        assert_eq!(
+            req.url_for("index", ["test"]).unwrap_err().to_string(),
+            "Value for parameter \"ext\" of resource \"index\" is missing"
+        );
+        assert_eq!(
+            req.url_for("index", ["test", "html", "extra"]),
+            Err(crate::web::error::UrlGenerationError::TooManyElements(
+                "index".to_string()
+            ))
         );
         let url = req.url_for("index", ["test", "html"]);
         assert_eq!(
@@ -422,6 +542,101 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url_for_encoded() {
+        let mut res = ResourceDef::new("/user/{id:\\d+}/{name}");
+        *res.name_mut() = "index".to_string();
+
+        let mut rmap = ResourceMap::new(ResourceDef::new(""));
+        rmap.add(&mut res, None);
+
+        let req = TestRequest::with_header(header::HOST, "www.rust-lang.org")
+            .rmap(rmap)
+            .to_http_request();
+
+        let url = req.url_for("index", ["1", "a b/c"]).unwrap();
+        assert_eq!(url.as_str(), "http://www.rust-lang.org/user/1/a%20b%2Fc");
+
+        let err = req.url_for("index", ["a", "b"]).unwrap_err();
+        assert_eq!(
+            err,
+            crate::web::error::UrlGenerationError::InvalidElement(
+                "index".to_string(),
+                crate::router::BuildError::InvalidValue {
+                    name: "id".to_string(),
+                    value: "a".to_string()
+                }
+            )
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid element for resource \"index\": Value \"a\" does not match pattern of parameter \"id\""
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url_for_query() {
+        let mut res = ResourceDef::new("/user/{name}");
+        *res.name_mut() = "index".to_string();
+        let mut ext = ResourceDef::new("https://youtube.com/watch");
+        *ext.name_mut() = "youtube".to_string();
+        let mut ext2 = ResourceDef::new("https://youtube.com/channel/{id}");
+        *ext2.name_mut() = "channel".to_string();
+
+        let mut rmap = ResourceMap::new(ResourceDef::new(""));
+        rmap.add(&mut res, None);
+        rmap.add(&mut ext, None);
+        rmap.add(&mut ext2, None);
+
+        let req = TestRequest::with_header(header::HOST, "internal:8080")
+            .header("x-forwarded-proto", "https")
+            .header("x-forwarded-host", "www.rust-lang.org")
+            .rmap(rmap)
+            .to_http_request();
+
+        let url = req
+            .url_for_query("index", ["test"], &[("q", "a b&c"), ("ключ", "=")])
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://www.rust-lang.org/user/test?q=a+b%26c&%D0%BA%D0%BB%D1%8E%D1%87=%3D"
+        );
+        let url = req
+            .url_for_query::<_, _, &str, &str>("index", ["test"], &[])
+            .unwrap();
+        assert_eq!(url.as_str(), "https://www.rust-lang.org/user/test");
+
+        assert_eq!(
+            req.url_for_relative("index", ["test"], &[("page", "2")]),
+            Ok("/user/test?page=2".to_string())
+        );
+        assert_eq!(
+            req.url_for_relative::<_, _, &str, &str>("index", ["test"], &[]),
+            Ok("/user/test".to_string())
+        );
+        assert_eq!(
+            req.url_for_relative("youtube", [""; 0], &[("v", "oHg5SJYRHA0")]),
+            Ok("https://youtube.com/watch?v=oHg5SJYRHA0".to_string())
+        );
+        assert_eq!(
+            req.url_for_relative("channel", ["rust"], &[("page", "2")]),
+            Ok("https://youtube.com/channel/rust?page=2".to_string())
+        );
+        assert_eq!(
+            req.url_for_relative::<_, _, &str, &str>("channel", ["rust"], &[]),
+            Ok("https://youtube.com/channel/rust".to_string())
+        );
+        assert_eq!(
+            req.url_for_relative("index", [""; 0], &[("page", "2")]),
+            Err(crate::web::error::UrlGenerationError::MissingValue(
+                "index".to_string(),
+                "name".to_string()
+            ))
+        );
+    }
+
     #[cfg(feature = "url")]
     #[test]
     fn test_url_for_static() {
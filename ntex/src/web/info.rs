@@ -1,6 +1,6 @@
-use std::cell::Ref;
+use std::{cell::Ref, net::IpAddr, net::SocketAddr};
 
-use crate::http::header::{self, HeaderName};
+use crate::http::header::{self, Forwarded, HeaderName};
 use crate::http::RequestHead;
 use crate::web::config::AppConfig;
 
@@ -8,6 +8,79 @@ const X_FORWARDED_FOR: &[u8] = b"x-forwarded-for";
 const X_FORWARDED_HOST: &[u8] = b"x-forwarded-host";
 const X_FORWARDED_PROTO: &[u8] = b"x-forwarded-proto";
 
+/// Set of trusted proxy networks
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl TrustedProxies {
+    /// Add network in CIDR notation, i.e. `10.0.0.0/8`, or single ip address
+    pub(crate) fn add(&mut self, cidr: &str) -> Result<(), ()> {
+        let (addr, prefix) = match cidr.split_once('/') {
+            Some((addr, prefix)) => (
+                addr.parse().map_err(|_| ())?,
+                Some(prefix.parse().map_err(|_| ())?),
+            ),
+            None => (cidr.parse::<IpAddr>().map_err(|_| ())?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return Err(());
+        }
+        self.0.push((addr, prefix));
+        Ok(())
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+            addr => addr,
+        };
+        self.0.iter().any(|(net, prefix)| match (net, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        })
+    }
+}
+
+/// Parse ip address of forwarded header element, port is optional
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            value
+                .strip_prefix('[')
+                .and_then(|v| v.strip_suffix(']'))
+                .and_then(|v| v.parse().ok())
+        })
+}
+
+/// First host of `X-Forwarded-Host` header
+fn x_forwarded_host(req: &RequestHead) -> Option<&str> {
+    req.headers
+        .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.split(',').next().map(|v| v.trim()))
+}
+
+/// Host of `Host` header or request uri
+fn request_host(req: &RequestHead) -> Option<&str> {
+    req.headers
+        .get(&header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri.authority().map(|a| a.as_str()))
+}
+
 /// `HttpRequest` connection information
 #[derive(Debug, Clone, Default)]
 pub struct ConnectionInfo {
@@ -15,65 +88,80 @@ pub struct ConnectionInfo {
     host: String,
     remote: Option<String>,
     peer: Option<String>,
+    real_ip: Option<IpAddr>,
+    /// Info is computed with trusted proxies configuration
+    trusted: bool,
 }
 
 impl ConnectionInfo {
     /// Create *ConnectionInfo* instance for a request.
+    ///
+    /// Application's trusted proxies are not available to this method, if
+    /// info is not computed yet, forwarded headers are trusted. Use
+    /// `HttpRequest::connection_info()` to respect `App::trusted_proxies()`.
     pub fn get<'a>(req: &'a RequestHead, cfg: &AppConfig) -> Ref<'a, Self> {
-        if !req.extensions().contains::<ConnectionInfo>() {
-            req.extensions_mut().insert(ConnectionInfo::new(req, cfg));
+        Self::get_with_proxies(req, cfg, None)
+    }
+
+    pub(crate) fn get_with_proxies<'a>(
+        req: &'a RequestHead,
+        cfg: &AppConfig,
+        proxies: Option<&TrustedProxies>,
+    ) -> Ref<'a, Self> {
+        // info computed without trusted proxies is replaced
+        let cached = req
+            .extensions()
+            .get::<ConnectionInfo>()
+            .map(|info| info.trusted || proxies.is_none());
+        if cached != Some(true) {
+            req.extensions_mut()
+                .insert(ConnectionInfo::new(req, cfg, proxies));
         }
         Ref::map(req.extensions(), |e| e.get().unwrap())
     }
 
     #[allow(clippy::cognitive_complexity)]
-    fn new(req: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
+    fn new(
+        req: &RequestHead,
+        cfg: &AppConfig,
+        proxies: Option<&TrustedProxies>,
+    ) -> ConnectionInfo {
         let mut host = None;
         let mut scheme = None;
         let mut remote = None;
         let mut peer = None;
 
-        // load forwarded header
-        for hdr in req.headers.get_all(&header::FORWARDED) {
-            if let Ok(val) = hdr.to_str() {
-                for pair in val.split(';') {
-                    for el in pair.split(',') {
-                        let mut items = el.trim().splitn(2, '=');
-                        if let Some(name) = items.next() {
-                            if let Some(val) = items.next() {
-                                match &name.to_lowercase() as &str {
-                                    "for" => {
-                                        if remote.is_none() {
-                                            remote = Some(val.trim());
-                                        }
-                                    }
-                                    "proto" => {
-                                        if scheme.is_none() {
-                                            scheme = Some(val.trim());
-                                        }
-                                    }
-                                    "host" => {
-                                        if host.is_none() {
-                                            host = Some(val.trim());
-                                        }
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
-                }
+        let peer_ip = req.peer_addr().map(|addr| addr.ip());
+        let real_ip = peer_ip.map(|ip| real_ip(req, ip, proxies));
+
+        // forwarded headers are ignored if peer is not a trusted proxy
+        let forwarded = match (proxies, peer_ip) {
+            (None, _) => true,
+            (Some(proxies), Some(ip)) => proxies.contains(ip),
+            (Some(_), None) => false,
+        };
+
+        // load forwarded header, first element is closest to the client
+        let fwd;
+        if forwarded {
+            fwd = Forwarded::from_headers(&req.headers);
+            if let Some(el) = fwd.elements.first() {
+                remote = el.for_.as_deref();
+                scheme = el.proto.as_deref();
+                host = el.host.as_deref();
             }
         }
 
         // scheme
         if scheme.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    scheme = h.split(',').next().map(|v| v.trim());
+            if forwarded {
+                if let Some(h) = req
+                    .headers
+                    .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        scheme = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
             if scheme.is_none() {
@@ -86,35 +174,31 @@ impl ConnectionInfo {
 
         // host
         if host.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    host = h.split(',').next().map(|v| v.trim());
-                }
+            if forwarded {
+                host = x_forwarded_host(req);
             }
             if host.is_none() {
-                if let Some(h) = req.headers.get(&header::HOST) {
-                    host = h.to_str().ok();
-                }
+                host = request_host(req);
                 if host.is_none() {
-                    host = req.uri.authority().map(|a| a.as_str());
-                    if host.is_none() {
-                        host = Some(cfg.host());
-                    }
+                    host = Some(cfg.host());
                 }
             }
         }
 
         // remote addr
-        if remote.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    remote = h.split(',').next().map(|v| v.trim());
+        let real_remote = match (proxies, real_ip) {
+            (Some(_), Some(ip)) if forwarded => Some(ip.to_string()),
+            _ => None,
+        };
+        if real_remote.is_none() && remote.is_none() {
+            if forwarded && proxies.is_none() {
+                if let Some(h) = req
+                    .headers
+                    .get(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        remote = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
             if remote.is_none() {
@@ -125,12 +209,26 @@ impl ConnectionInfo {
 
         ConnectionInfo {
             peer,
+            real_ip,
             scheme: scheme.unwrap_or("http").to_owned(),
             host: host.unwrap_or("localhost").to_owned(),
-            remote: remote.map(|s| s.to_owned()),
+            remote: real_remote.or_else(|| remote.map(|s| s.to_owned())),
+            trusted: proxies.is_some(),
         }
     }
 
+    /// Resolve host of the request without application configuration,
+    /// forwarded headers are trusted.
+    pub(crate) fn resolve_host(req: &RequestHead) -> Option<String> {
+        let fwd = Forwarded::from_headers(&req.headers);
+        fwd.elements
+            .first()
+            .and_then(|el| el.host.as_deref())
+            .or_else(|| x_forwarded_host(req))
+            .or_else(|| request_host(req))
+            .map(|host| host.to_string())
+    }
+
     /// Scheme of the request.
     ///
     /// Scheme is resolved through the following headers, in this order:
@@ -179,6 +277,63 @@ impl ConnectionInfo {
             None
         }
     }
+
+    /// Real ip address of the client.
+    ///
+    /// Forwarded headers are used only if peer address belongs to
+    /// trusted proxies configured with `App::trusted_proxies()`,
+    /// otherwise peer address is used.
+    ///
+    /// Addresses of `Forwarded` header are checked first. If `Forwarded`
+    /// header does not contain addresses, `X-Forwarded-For` header is used.
+    /// Chain of addresses is walked from the right to the left, first
+    /// address that is not trusted proxy is the client address.
+    ///
+    /// Returns `None` if peer address is unknown, i.e. for unix sockets.
+    #[inline]
+    pub fn real_ip(&self) -> Option<IpAddr> {
+        self.real_ip
+    }
+}
+
+/// Find first untrusted address in forwarded chain
+fn real_ip(req: &RequestHead, peer: IpAddr, proxies: Option<&TrustedProxies>) -> IpAddr {
+    let proxies = match proxies {
+        Some(proxies) if proxies.contains(peer) => proxies,
+        _ => return peer,
+    };
+
+    let fwd = Forwarded::from_headers(&req.headers);
+    let mut chain: Vec<&str> = fwd
+        .elements
+        .iter()
+        .filter_map(|el| el.for_.as_deref())
+        .collect();
+    if chain.is_empty() {
+        for h in req
+            .headers
+            .get_all(HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+        {
+            if let Ok(h) = h.to_str() {
+                chain.extend(h.split(',').map(|v| v.trim()));
+            }
+        }
+    }
+
+    let mut ip = peer;
+    for addr in chain.iter().rev() {
+        // obfuscated or unknown addresses end the chain
+        match parse_ip(addr) {
+            Some(addr) => {
+                ip = addr;
+                if !proxies.contains(addr) {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    ip
 }
 
 #[cfg(test)]
@@ -205,6 +360,21 @@ mod tests {
         assert_eq!(info.host(), "rust-lang.org");
         assert_eq!(info.remote(), Some("192.0.2.60"));
 
+        // forwarded header takes precedence, malformed elements are skipped
+        let req = TestRequest::default()
+            .header(
+                header::FORWARDED,
+                "for, for=\"[2001:db8::1]:4711\";proto=https, for=10.0.0.1;host=proxy",
+            )
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .header(X_FORWARDED_HOST, "192.0.2.60")
+            .header(X_FORWARDED_PROTO, "http")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "192.0.2.60");
+        assert_eq!(info.remote(), Some("[2001:db8::1]:4711"));
+
         let req = TestRequest::default()
             .header(header::HOST, "rust-lang.org")
             .to_http_request();
@@ -233,4 +403,191 @@ mod tests {
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
     }
+
+    fn proxies(nets: &[&str]) -> TrustedProxies {
+        let mut proxies = TrustedProxies::default();
+        for net in nets {
+            proxies.add(net).unwrap();
+        }
+        proxies
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let proxies = proxies(&["10.0.0.0/8", "192.168.1.1", "2001:db8::/32", "::1"]);
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(proxies.contains("192.168.1.1".parse().unwrap()));
+        assert!(!proxies.contains("192.168.1.2".parse().unwrap()));
+        assert!(!proxies.contains("11.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(proxies.contains("::1".parse().unwrap()));
+        assert!(!proxies.contains("2001:db9::1".parse().unwrap()));
+        // ipv4-mapped ipv6 address
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+        let all = self::proxies(&["0.0.0.0/0"]);
+        assert!(all.contains("1.2.3.4".parse().unwrap()));
+        assert!(!all.contains("::2".parse().unwrap()));
+
+        let mut proxies = TrustedProxies::default();
+        assert!(proxies.add("10.0.0.0/33").is_err());
+        assert!(proxies.add("::/129").is_err());
+        assert!(proxies.add("10.0.0.0/").is_err());
+        assert!(proxies.add("localhost").is_err());
+
+        assert_eq!(parse_ip("192.0.2.60"), Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(
+            parse_ip("192.0.2.60:4711"),
+            Some("192.0.2.60".parse().unwrap())
+        );
+        assert_eq!(
+            parse_ip("\"[2001:db8::1]:4711\""),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(
+            parse_ip("[2001:db8::1]"),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(parse_ip("unknown"), None);
+        assert_eq!(parse_ip("_hidden"), None);
+    }
+
+    #[test]
+    fn test_real_ip() {
+        let peer: SocketAddr = "10.0.0.1:1234".parse().unwrap();
+
+        // no trusted proxies, peer address is used
+        let req = TestRequest::default()
+            .peer_addr(peer)
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some(peer.ip()));
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().real_ip(), None);
+
+        // untrusted peer, forwarded headers are ignored
+        let req = TestRequest::default()
+            .state(proxies(&["10.1.0.0/16"]))
+            .peer_addr(peer)
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60;proto=https;host=example.com",
+            )
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .header(header::HOST, "rust-lang.org")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some(peer.ip()));
+        assert_eq!(info.remote(), Some("10.0.0.1:1234"));
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "rust-lang.org");
+
+        // trusted peer, chain is walked from the right
+        let req = TestRequest::default()
+            .state(proxies(&["10.0.0.0/8"]))
+            .peer_addr(peer)
+            .header(X_FORWARDED_FOR, "1.1.1.1, 192.0.2.60, 10.0.0.2")
+            .header(X_FORWARDED_PROTO, "https")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some("192.0.2.60".parse().unwrap()));
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+        assert_eq!(info.scheme(), "https");
+
+        // forwarded header takes precedence over x-forwarded-for
+        let req = TestRequest::default()
+            .state(proxies(&["10.0.0.0/8"]))
+            .peer_addr(peer)
+            .header(
+                header::FORWARDED,
+                "for=\"[2001:db8::1]:4711\", for=10.0.0.3:80",
+            )
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some("2001:db8::1".parse().unwrap()));
+
+        // all hops are trusted, leftmost address is used
+        let req = TestRequest::default()
+            .state(proxies(&["10.0.0.0/8"]))
+            .peer_addr(peer)
+            .header(X_FORWARDED_FOR, "10.0.0.3, 10.0.0.2")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some("10.0.0.3".parse().unwrap()));
+
+        // obfuscated address ends the chain
+        let req = TestRequest::default()
+            .state(proxies(&["10.0.0.0/8"]))
+            .peer_addr(peer)
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60, for=_hidden, for=10.0.0.2",
+            )
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some("10.0.0.2".parse().unwrap()));
+
+        // trusted peer without forwarded headers
+        let req = TestRequest::default()
+            .state(proxies(&["10.0.0.0/8"]))
+            .peer_addr(peer)
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some(peer.ip()));
+        assert_eq!(info.remote(), Some("10.0.0.1"));
+
+        // info computed without trusted proxies is not reused
+        let req = TestRequest::default()
+            .state(proxies(&["10.1.0.0/16"]))
+            .peer_addr(peer)
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .to_http_request();
+        let info = ConnectionInfo::get(req.head(), &AppConfig::default());
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+        drop(info);
+        let info = req.connection_info();
+        assert_eq!(info.real_ip(), Some(peer.ip()));
+        assert_eq!(info.remote(), Some("10.0.0.1:1234"));
+        drop(info);
+        let info = ConnectionInfo::get(req.head(), &AppConfig::default());
+        assert_eq!(info.remote(), Some("10.0.0.1:1234"));
+    }
+
+    #[crate::rt_test]
+    async fn test_real_ip_extractor() {
+        use crate::web::{self, test, types::RealIp, App};
+
+        let app = test::init_service(App::new().trusted_proxies(["127.0.0.1"]).route(
+            "/",
+            web::get().to(|ip: RealIp| async move { ip.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "192.0.2.60");
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.1:8080".parse().unwrap())
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "192.0.2.1");
+
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), crate::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid trusted proxy network")]
+    fn test_invalid_trusted_proxy() {
+        let _ = crate::web::App::new().trusted_proxies(["10.0.0.0/40"]);
+    }
 }
@@ -1,18 +1,33 @@
 //! `Middleware` for compressing response body.
 use std::task::{Context, Poll};
-use std::{cmp, future::Future, marker, pin::Pin, str::FromStr};
+use std::{fmt, future::Future, marker, pin::Pin, rc::Rc, str::FromStr};
 
 use crate::http::encoding::Encoder;
-use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use crate::http::header::{
+    ContentEncoding, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE,
+};
+use crate::http::StatusCode;
 use crate::service::{Middleware, Service, ServiceCall, ServiceCtx};
-use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
+use crate::util::{Either, Ready};
+use crate::web::{BodyEncoding, ErrorRenderer, HttpResponse, WebRequest, WebResponse};
 
-#[derive(Debug, Clone)]
+type ContentTypeFilter = Rc<dyn Fn(&str) -> bool>;
+
+#[derive(Clone)]
 /// `Middleware` for compressing response body.
 ///
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// Encoding is negotiated with `Accept-Encoding` request header, supported
+/// encoding with highest quality value is used. If client explicitly forbids
+/// `identity` encoding and none of supported encodings is acceptable,
+/// middleware responds with `406 Not Acceptable`.
+///
+/// Responses with `204`, `304` and informational status codes, responses
+/// that are already encoded and responses with `image/*` (except svg),
+/// `audio/*` or `video/*` content types are not compressed.
+///
 /// ```rust
 /// use ntex::web::{self, middleware, App, HttpResponse};
 ///
@@ -28,12 +43,48 @@ use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
 /// ```
 pub struct Compress {
     enc: ContentEncoding,
+    level: Option<u32>,
+    filter: Option<ContentTypeFilter>,
 }
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress { enc: encoding }
+        Compress {
+            enc: encoding,
+            level: None,
+            filter: None,
+        }
+    }
+
+    /// Set compression level.
+    ///
+    /// Level is clamped to the supported range of negotiated encoding,
+    /// `0-9` for gzip and deflate, `0-11` for brotli and `1-22` for zstd.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Compress only responses with content types accepted by predicate.
+    ///
+    /// Predicate receives lowercased media type of the response without
+    /// parameters, i.e. `text/html`. Responses without `Content-Type` header
+    /// are not compressed if filter is set.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::Compress;
+    ///
+    /// let compress = Compress::default().content_type_filter(|ct| {
+    ///     ct.starts_with("text/") || ct == "application/json"
+    /// });
+    /// ```
+    pub fn content_type_filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.filter = Some(Rc::new(f));
+        self
     }
 }
 
@@ -43,6 +94,16 @@ impl Default for Compress {
     }
 }
 
+impl fmt::Debug for Compress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compress")
+            .field("enc", &self.enc)
+            .field("level", &self.level)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
 impl<S> Middleware<S> for Compress {
     type Service = CompressMiddleware<S>;
 
@@ -50,14 +111,31 @@ impl<S> Middleware<S> for Compress {
         CompressMiddleware {
             service,
             encoding: self.enc,
+            level: self.level,
+            filter: self.filter.clone(),
         }
     }
 }
 
-#[derive(Debug)]
 pub struct CompressMiddleware<S> {
     service: S,
     encoding: ContentEncoding,
+    level: Option<u32>,
+    filter: Option<ContentTypeFilter>,
+}
+
+impl<S> fmt::Debug for CompressMiddleware<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressMiddleware")
+            .field("service", &self.service)
+            .field("encoding", &self.encoding)
+            .field("level", &self.level)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
 }
 
 impl<S, E> Service<WebRequest<E>> for CompressMiddleware<S>
@@ -67,7 +145,7 @@ where
 {
     type Response = WebResponse;
     type Error = S::Error;
-    type Future<'f> = CompressResponse<'f, S, E> where S: 'f;
+    type Future<'f> = Either<CompressResponse<'f, S, E>, Ready<WebResponse, S::Error>> where S: 'f;
 
     crate::forward_poll_ready!(service);
     crate::forward_poll_shutdown!(service);
@@ -82,16 +160,24 @@ where
             if let Ok(enc) = val.to_str() {
                 AcceptEncoding::parse(enc, self.encoding)
             } else {
-                ContentEncoding::Identity
+                Some(ContentEncoding::Identity)
             }
         } else {
-            ContentEncoding::Identity
+            Some(ContentEncoding::Identity)
         };
 
-        CompressResponse {
-            encoding,
-            fut: ctx.call(&self.service, req),
-            _t: marker::PhantomData,
+        if let Some(encoding) = encoding {
+            Either::Left(CompressResponse {
+                encoding,
+                level: self.level,
+                filter: self.filter.clone(),
+                fut: ctx.call(&self.service, req),
+                _t: marker::PhantomData,
+            })
+        } else {
+            Either::Right(Ready::Ok(
+                req.into_response(HttpResponse::NotAcceptable().finish()),
+            ))
         }
     }
 }
@@ -104,6 +190,8 @@ pin_project_lite::pin_project! {
         #[pin]
         fut: ServiceCall<'f, S, WebRequest<E>>,
         encoding: ContentEncoding,
+        level: Option<u32>,
+        filter: Option<ContentTypeFilter>,
         _t: marker::PhantomData<E>,
     }
 }
@@ -122,81 +210,284 @@ where
             Poll::Ready(resp) => {
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
-                } else {
+                } else if is_compressible(resp.response(), this.filter) {
                     *this.encoding
+                } else {
+                    ContentEncoding::Identity
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let level = *this.level;
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_level(enc, level, head, body)
+                })))
             }
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
-struct AcceptEncoding {
-    encoding: ContentEncoding,
-    quality: f64,
-}
+/// Check if it makes sense to compress response
+fn is_compressible(res: &HttpResponse, filter: &Option<ContentTypeFilter>) -> bool {
+    let status = res.status();
+    if status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+        || res.headers().contains_key(&CONTENT_ENCODING)
+    {
+        return false;
+    }
 
-impl Eq for AcceptEncoding {}
+    let ctype = res
+        .headers()
+        .get(&CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase());
 
-impl Ord for AcceptEncoding {
-    #[allow(clippy::comparison_chain)]
-    fn cmp(&self, other: &AcceptEncoding) -> cmp::Ordering {
-        if self.quality > other.quality {
-            cmp::Ordering::Less
-        } else if self.quality < other.quality {
-            cmp::Ordering::Greater
+    if let Some(ctype) = ctype {
+        if (ctype.starts_with("image/") && ctype != "image/svg+xml")
+            || ctype.starts_with("audio/")
+            || ctype.starts_with("video/")
+        {
+            false
+        } else if let Some(ref filter) = filter {
+            filter(&ctype)
         } else {
-            cmp::Ordering::Equal
+            true
         }
+    } else {
+        filter.is_none()
     }
 }
 
-impl PartialOrd for AcceptEncoding {
-    fn partial_cmp(&self, other: &AcceptEncoding) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+/// `Accept-Encoding` header negotiation
+struct AcceptEncoding;
+
+impl AcceptEncoding {
+    /// Encodings supported with `ContentEncoding::Auto`, in order of preference
+    const SUPPORTED: &'static [ContentEncoding] = &[
+        ContentEncoding::Br,
+        #[cfg(feature = "zstd")]
+        ContentEncoding::Zstd,
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+    ];
+
+    /// Parse coding with optional quality value
+    fn item(item: &str) -> Option<(&str, f64)> {
+        let mut parts = item.split(';');
+        let coding = parts.next()?.trim();
+        if coding.is_empty() {
+            return None;
+        }
+        let quality = parts
+            .filter_map(|p| {
+                let p = p.trim();
+                p.strip_prefix("q=").or_else(|| p.strip_prefix("Q="))
+            })
+            .next()
+            .map(|q| f64::from_str(q.trim()).unwrap_or(0.0))
+            .unwrap_or(1.0);
+        Some((coding, quality))
     }
-}
 
-impl PartialEq for AcceptEncoding {
-    fn eq(&self, other: &AcceptEncoding) -> bool {
-        self.quality == other.quality
+    /// Select encoding for raw Accept-Encoding header value.
+    ///
+    /// Returns `None` if neither supported encodings nor identity
+    /// are acceptable.
+    fn parse(raw: &str, encoding: ContentEncoding) -> Option<ContentEncoding> {
+        let mut star = None;
+        let mut identity = None;
+        let mut codings = Vec::new();
+        for (coding, quality) in raw.split(',').filter_map(AcceptEncoding::item) {
+            if coding == "*" {
+                star = Some(quality);
+            } else if coding.eq_ignore_ascii_case("identity") {
+                identity = Some(quality);
+            } else {
+                match ContentEncoding::from(coding) {
+                    // unknown coding
+                    ContentEncoding::Identity => (),
+                    enc => codings.push((enc, quality)),
+                }
+            }
+        }
+
+        let supported: &[ContentEncoding] = if encoding == ContentEncoding::Auto {
+            Self::SUPPORTED
+        } else if encoding.is_compressed() {
+            std::slice::from_ref(&encoding)
+        } else {
+            &[]
+        };
+
+        // highest quality wins, server preference is used for equal qualities
+        let mut best: Option<(ContentEncoding, f64)> = None;
+        for enc in supported {
+            let quality = codings
+                .iter()
+                .find(|(e, _)| e == enc)
+                .map(|(_, q)| *q)
+                .or(star)
+                .unwrap_or(0.0);
+            if quality > 0.0 {
+                match best {
+                    Some((_, q)) if q >= quality => (),
+                    _ => best = Some((*enc, quality)),
+                }
+            }
+        }
+
+        // identity is acceptable unless it is explicitly forbidden
+        let identity = identity.or(star);
+        match best {
+            Some((enc, quality)) if !matches!(identity, Some(q) if q > quality) => {
+                Some(enc)
+            }
+            _ if matches!(identity, Some(q) if q <= 0.0) => None,
+            _ => Some(ContentEncoding::Identity),
+        }
     }
 }
 
-impl AcceptEncoding {
-    fn new(tag: &str) -> Option<AcceptEncoding> {
-        let parts: Vec<&str> = tag.split(';').collect();
-        let encoding = match parts.len() {
-            0 => return None,
-            _ => ContentEncoding::from(parts[0]),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::test::TestRequest;
+    use crate::web::{DefaultError, Error};
+
+    #[test]
+    fn test_accept_encoding() {
+        let enc = ContentEncoding::Auto;
+        let parse = |raw| AcceptEncoding::parse(raw, enc);
+        assert_eq!(parse("gzip, br"), Some(ContentEncoding::Br));
+        assert_eq!(parse("gzip;q=1.0, br;q=0.5"), Some(ContentEncoding::Gzip));
+        assert_eq!(parse("gzip;q=0.5, br;q=0.8"), Some(ContentEncoding::Br));
+        assert_eq!(parse("br;q=0, deflate"), Some(ContentEncoding::Deflate));
+        assert_eq!(
+            AcceptEncoding::parse("gzip, br", ContentEncoding::Gzip),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            AcceptEncoding::parse("br", ContentEncoding::Gzip),
+            Some(ContentEncoding::Identity)
+        );
+    }
+
+    #[test]
+    fn test_accept_encoding_quality() {
+        let enc = ContentEncoding::Auto;
+        let parse = |raw| AcceptEncoding::parse(raw, enc);
+        assert_eq!(parse("gzip;q=0, br;q=1"), Some(ContentEncoding::Br));
+        assert_eq!(
+            parse("gzip;q=0.2,deflate; q=0.9"),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(parse("unknown, gzip;q=0.1"), Some(ContentEncoding::Gzip));
+        assert_eq!(parse("*"), Some(ContentEncoding::Br));
+        #[cfg(feature = "zstd")]
+        assert_eq!(parse("*;q=0.5, br;q=0"), Some(ContentEncoding::Zstd));
+        #[cfg(not(feature = "zstd"))]
+        assert_eq!(parse("*;q=0.5, br;q=0"), Some(ContentEncoding::Gzip));
+        assert_eq!(parse(""), Some(ContentEncoding::Identity));
+        assert_eq!(parse("identity"), Some(ContentEncoding::Identity));
+        assert_eq!(
+            parse("identity;q=1, gzip;q=0.5"),
+            Some(ContentEncoding::Identity)
+        );
+        assert_eq!(parse("identity;q=0, gzip"), Some(ContentEncoding::Gzip));
+
+        // identity is forbidden
+        let gzip = ContentEncoding::Gzip;
+        assert_eq!(AcceptEncoding::parse("identity;q=0, br", gzip), None);
+        assert_eq!(AcceptEncoding::parse("*;q=0", gzip), None);
+        assert_eq!(
+            AcceptEncoding::parse("*;q=0, identity", gzip),
+            Some(ContentEncoding::Identity)
+        );
+        assert_eq!(parse("identity;q=0, *;q=0"), None);
+        assert_eq!(
+            AcceptEncoding::parse("gzip, *;q=0", ContentEncoding::Identity),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compressible() {
+        let check = |status, ctype: Option<&'static str>, filter| {
+            let mut res = HttpResponse::build(status);
+            if let Some(ctype) = ctype {
+                res.content_type(ctype);
+            }
+            is_compressible(&res.finish(), &filter)
         };
-        let quality = match parts.len() {
-            1 => encoding.quality(),
-            _ => f64::from_str(parts[1]).unwrap_or(0.0),
+        assert!(check(StatusCode::OK, None, None));
+        assert!(check(StatusCode::OK, Some("text/html"), None));
+        assert!(check(StatusCode::OK, Some("image/svg+xml"), None));
+        assert!(!check(StatusCode::NO_CONTENT, None, None));
+        assert!(!check(StatusCode::NOT_MODIFIED, None, None));
+        assert!(!check(StatusCode::OK, Some("image/png"), None));
+        assert!(!check(StatusCode::OK, Some("Video/MP4; codecs=avc1"), None));
+
+        let filter: Option<ContentTypeFilter> = Some(Rc::new(|ct| {
+            ct.starts_with("text/") || ct == "application/json"
+        }));
+        assert!(check(
+            StatusCode::OK,
+            Some("text/plain; charset=utf-8"),
+            filter.clone()
+        ));
+        assert!(check(
+            StatusCode::OK,
+            Some("application/json"),
+            filter.clone()
+        ));
+        assert!(!check(
+            StatusCode::OK,
+            Some("application/wasm"),
+            filter.clone()
+        ));
+        assert!(!check(StatusCode::OK, None, filter));
+
+        let res = HttpResponse::Ok().header(CONTENT_ENCODING, "gzip").finish();
+        assert!(!is_compressible(&res, &None));
+    }
+
+    #[crate::rt_test]
+    async fn test_compress() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let res = if req.path() == "/image" {
+                HttpResponse::Ok().content_type("image/png").body("IMAGE")
+            } else {
+                HttpResponse::Ok().content_type("text/plain").body("TEXT")
+            };
+            Ok::<_, Error>(req.into_response(res))
         };
-        Some(AcceptEncoding { encoding, quality })
-    }
-
-    /// Parse a raw Accept-Encoding header value into an ordered list.
-    fn parse(raw: &str, encoding: ContentEncoding) -> ContentEncoding {
-        let mut encodings: Vec<_> = raw
-            .replace(' ', "")
-            .split(',')
-            .map(AcceptEncoding::new)
-            .collect();
-        encodings.sort();
-
-        for enc in encodings.into_iter().flatten() {
-            if encoding == ContentEncoding::Auto {
-                return enc.encoding;
-            } else if encoding == enc.encoding {
-                return encoding;
-            }
-        }
-        ContentEncoding::Identity
+        let mw = Compress::default().content_type_filter(|ct| ct.starts_with("text/"));
+        assert!(format!("{:?}", mw).contains("filter: true"));
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let req = TestRequest::with_uri("/")
+            .header(ACCEPT_ENCODING, "gzip;q=0, br;q=1")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(
+            res.headers().get(CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("br"))
+        );
+
+        let req = TestRequest::with_uri("/image")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+
+        let req = TestRequest::with_uri("/")
+            .header(ACCEPT_ENCODING, "unknown, identity;q=0")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
     }
 }
@@ -0,0 +1,191 @@
+//! Middleware for conditional wrapping of services
+use std::task::{Context, Poll};
+use std::{fmt, rc::Rc};
+
+use crate::service::{Middleware, Service, ServiceCall, ServiceCtx};
+use crate::util::Either;
+
+/// `Middleware` for conditionally enabling other middleware.
+///
+/// If condition is `false` requests are passed to the wrapped service
+/// directly. Both cases produce the same service type, so application
+/// could be constructed once for any condition value.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::{Condition, Logger}, App, HttpResponse};
+///
+/// struct Config {
+///     access_log: bool,
+/// }
+///
+/// fn main() {
+///     let cfg = Config { access_log: true };
+///
+///     let app = App::new()
+///         .wrap(Condition::new(cfg.access_log, Logger::default()))
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct Condition<M> {
+    mw: M,
+    enabled: Rc<dyn Fn() -> bool>,
+}
+
+impl<M> Condition<M> {
+    /// Create `Condition` middleware, `mw` is used only if `enabled` is `true`.
+    pub fn new(enabled: bool, mw: M) -> Self {
+        Condition {
+            mw,
+            enabled: Rc::new(move || enabled),
+        }
+    }
+
+    /// Create `Condition` middleware with predicate.
+    ///
+    /// Predicate is called every time service gets constructed, i.e. once
+    /// for each worker thread.
+    pub fn with_predicate<F>(predicate: F, mw: M) -> Self
+    where
+        F: Fn() -> bool + 'static,
+    {
+        Condition {
+            mw,
+            enabled: Rc::new(predicate),
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for Condition<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condition").field("mw", &self.mw).finish()
+    }
+}
+
+impl<S, M> Middleware<S> for Condition<M>
+where
+    M: Middleware<S>,
+{
+    type Service = ConditionMiddleware<M::Service, S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        if (self.enabled)() {
+            ConditionMiddleware {
+                inner: Either::Left(self.mw.create(service)),
+            }
+        } else {
+            ConditionMiddleware {
+                inner: Either::Right(service),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConditionMiddleware<M, S> {
+    inner: Either<M, S>,
+}
+
+impl<M, S, R> Service<R> for ConditionMiddleware<M, S>
+where
+    M: Service<R>,
+    S: Service<R, Response = M::Response, Error = M::Error>,
+{
+    type Response = M::Response;
+    type Error = M::Error;
+    type Future<'f> =
+        Either<ServiceCall<'f, M, R>, ServiceCall<'f, S, R>> where Self: 'f, R: 'f;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.inner {
+            Either::Left(ref mw) => mw.poll_ready(cx),
+            Either::Right(ref srv) => srv.poll_ready(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.inner {
+            Either::Left(ref mw) => mw.poll_shutdown(cx),
+            Either::Right(ref srv) => srv.poll_shutdown(cx),
+        }
+    }
+
+    #[inline]
+    fn call<'a>(&'a self, req: R, ctx: ServiceCtx<'a, Self>) -> Self::Future<'a> {
+        match self.inner {
+            Either::Left(ref mw) => Either::Left(ctx.call(mw, req)),
+            Either::Right(ref srv) => Either::Right(ctx.call(srv, req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::service::{IntoService, Pipeline};
+    use crate::util::lazy;
+    use crate::web::middleware::DefaultHeaders;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::{DefaultError, Error, HttpResponse, WebRequest};
+
+    #[crate::rt_test]
+    async fn test_condition() {
+        let mw = Condition::new(true, DefaultHeaders::new().header(CONTENT_TYPE, "0001"));
+        assert!(format!("{:?}", mw).contains("Condition"));
+        let srv = Pipeline::new(Middleware::create(&mw, ok_service()));
+        assert!(lazy(|cx| srv.poll_ready(cx).is_ready()).await);
+        assert!(lazy(|cx| srv.poll_shutdown(cx).is_ready()).await);
+
+        let resp = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE),
+            Some(&HeaderValue::from_static("0001"))
+        );
+
+        let mw = Condition::new(false, DefaultHeaders::new().header(CONTENT_TYPE, "0001"));
+        let srv = Pipeline::new(Middleware::create(&mw, ok_service()));
+        assert!(lazy(|cx| srv.poll_ready(cx).is_ready()).await);
+        assert!(lazy(|cx| srv.poll_shutdown(cx).is_ready()).await);
+
+        let resp = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(resp.headers().get(CONTENT_TYPE).is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_predicate() {
+        let enabled = Rc::new(Cell::new(false));
+        let flag = enabled.clone();
+        let mw = Condition::with_predicate(
+            move || flag.get(),
+            DefaultHeaders::new().header(CONTENT_TYPE, "0001"),
+        );
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok()))
+        };
+
+        let srv1 = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+        enabled.set(true);
+        let srv2 = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let resp = srv1
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(resp.headers().get(CONTENT_TYPE).is_none());
+        let resp = srv2
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(resp.headers().contains_key(CONTENT_TYPE));
+    }
+}
@@ -0,0 +1,597 @@
+//! Cross-origin resource sharing (CORS) middleware
+use std::{fmt, rc::Rc};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, RequestHead};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{BoxFuture, HashSet};
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+type OriginFn = Rc<dyn Fn(&str, &RequestHead) -> bool>;
+
+/// `Middleware` for Cross-origin resource sharing support.
+///
+/// Preflight requests are answered by middleware, they never reach
+/// application handlers. Preflight response contains requested method and
+/// headers only if they are permitted. Preflight requests from not allowed
+/// origins get `403 Forbidden` response.
+///
+/// Cors headers are added to actual requests only if origin is allowed.
+/// `Vary: Origin` header is added to all responses, unless any origin
+/// is allowed without credentials.
+///
+/// By default no origins are allowed, all methods and all headers are
+/// allowed for allowed origins.
+///
+/// ```rust
+/// use ntex::http::{header, Method};
+/// use ntex::web::{self, middleware::Cors, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             Cors::new()
+///                 .allowed_origin("https://www.rust-lang.org")
+///                 .allowed_origin("https://*.example.com")
+///                 .allowed_methods([Method::GET, Method::POST])
+///                 .allowed_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+///                 .supports_credentials()
+///                 .max_age(3600),
+///         )
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    any_origin: bool,
+    origins: HashSet<String>,
+    wildcards: Vec<(String, String)>,
+    origin_fns: Vec<OriginFn>,
+    methods: Option<Vec<Method>>,
+    headers: Option<HashSet<HeaderName>>,
+    expose: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<u64>,
+}
+
+impl Cors {
+    /// Construct `Cors` middleware.
+    pub fn new() -> Self {
+        Cors::default()
+    }
+
+    /// Add allowed origin.
+    ///
+    /// Origin could be exact value like `https://example.com`, `*` for
+    /// any origin, or pattern with one wildcard host part like
+    /// `https://*.example.com`.
+    ///
+    /// Panics if any origin is allowed together with credentials.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        let inner = self.inner_mut();
+        let origin = origin.trim().to_ascii_lowercase();
+        if origin == "*" {
+            inner.any_origin = true;
+        } else if let Some((prefix, suffix)) = origin.split_once('*') {
+            inner
+                .wildcards
+                .push((prefix.to_string(), suffix.to_string()));
+        } else {
+            inner.origins.insert(origin);
+        }
+        inner.check();
+        self
+    }
+
+    /// Add predicate for checking `Origin` header value.
+    ///
+    /// Origin is allowed if any of configured origins or predicates accept it.
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &RequestHead) -> bool + 'static,
+    {
+        self.inner_mut().origin_fns.push(Rc::new(f));
+        self
+    }
+
+    /// Set allowed methods.
+    ///
+    /// By default all methods are allowed.
+    pub fn allowed_methods<I>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        self.inner_mut()
+            .methods
+            .get_or_insert_with(Vec::new)
+            .extend(methods);
+        self
+    }
+
+    /// Set allowed request headers.
+    ///
+    /// By default all headers are allowed.
+    pub fn allowed_headers<I, K>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        let allowed = self
+            .inner_mut()
+            .headers
+            .get_or_insert_with(HashSet::default);
+        for name in headers {
+            match HeaderName::try_from(name) {
+                Ok(name) => {
+                    allowed.insert(name);
+                }
+                Err(_) => panic!("Cannot create header name"),
+            }
+        }
+        self
+    }
+
+    /// Set headers that are exposed to the browser.
+    pub fn expose_headers<I, K>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        let expose = &mut self.inner_mut().expose;
+        for name in headers {
+            match HeaderName::try_from(name) {
+                Ok(name) => expose.push(name),
+                Err(_) => panic!("Cannot create header name"),
+            }
+        }
+        self
+    }
+
+    /// Allow requests with credentials.
+    ///
+    /// Panics if any origin is allowed.
+    pub fn supports_credentials(mut self) -> Self {
+        let inner = self.inner_mut();
+        inner.credentials = true;
+        inner.check();
+        self
+    }
+
+    /// Set max time in seconds for caching preflight responses.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.inner_mut().max_age = Some(seconds);
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+}
+
+impl fmt::Debug for Cors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cors")
+            .field("any_origin", &self.inner.any_origin)
+            .field("origins", &self.inner.origins)
+            .field("methods", &self.inner.methods)
+            .field("headers", &self.inner.headers)
+            .field("expose", &self.inner.expose)
+            .field("credentials", &self.inner.credentials)
+            .field("max_age", &self.inner.max_age)
+            .finish()
+    }
+}
+
+impl Inner {
+    fn check(&self) {
+        if self.any_origin && self.credentials {
+            panic!("Credentials are not allowed with wildcard origin");
+        }
+    }
+
+    /// Check if response does not depend on origin
+    fn is_wildcard(&self) -> bool {
+        self.any_origin && !self.credentials
+    }
+
+    fn is_origin_allowed(&self, origin: &str, head: &RequestHead) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.any_origin
+            || self.origins.contains(&origin)
+            || self.wildcards.iter().any(|(prefix, suffix)| {
+                origin.len() > prefix.len() + suffix.len()
+                    && origin.starts_with(prefix.as_str())
+                    && origin.ends_with(suffix.as_str())
+                    && origin[prefix.len()..origin.len() - suffix.len()]
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+            })
+            || self.origin_fns.iter().any(|f| f(&origin, head))
+    }
+
+    fn is_method_allowed(&self, method: &Method) -> bool {
+        self.methods
+            .as_ref()
+            .map(|methods| methods.contains(method))
+            .unwrap_or(true)
+    }
+
+    fn is_header_allowed(&self, name: &str) -> bool {
+        match self.headers {
+            Some(ref headers) => HeaderName::try_from(name)
+                .map(|name| headers.contains(&name))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    /// Set common cors headers
+    fn set_headers(&self, origin: &HeaderValue, headers: &mut header::HeaderMap) {
+        let origin = if self.is_wildcard() {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    fn set_vary(&self, headers: &mut header::HeaderMap) {
+        if !self.is_wildcard() {
+            let exists = headers.get_all(header::VARY).any(|v| {
+                v.to_str()
+                    .map(|v| {
+                        v.split(',')
+                            .any(|v| v.trim().eq_ignore_ascii_case("origin"))
+                    })
+                    .unwrap_or(false)
+            });
+            if !exists {
+                headers.append(header::VARY, HeaderValue::from_static("origin"));
+            }
+        }
+    }
+
+    /// Build response for preflight request
+    fn preflight(&self, origin: &HeaderValue, head: &RequestHead) -> HttpResponse {
+        let allowed = origin
+            .to_str()
+            .map(|o| self.is_origin_allowed(o, head))
+            .unwrap_or(false);
+        let mut res = if allowed {
+            HttpResponse::Ok().finish()
+        } else {
+            HttpResponse::Forbidden().finish()
+        };
+
+        if allowed {
+            let headers = res.headers_mut();
+            self.set_headers(origin, headers);
+
+            // echo requested method and headers only if they are permitted
+            if let Some(method) = head
+                .headers
+                .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+                .and_then(|v| Method::from_bytes(v.as_bytes()).ok())
+            {
+                if self.is_method_allowed(&method) {
+                    if let Ok(value) = HeaderValue::try_from(method.as_str()) {
+                        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+                    }
+                }
+            }
+            if let Some(requested) = head
+                .headers
+                .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+                .and_then(|v| v.to_str().ok())
+            {
+                let permitted: Vec<_> = requested
+                    .split(',')
+                    .map(|h| h.trim())
+                    .filter(|h| !h.is_empty() && self.is_header_allowed(h))
+                    .collect();
+                if !permitted.is_empty() {
+                    if let Ok(value) = HeaderValue::try_from(permitted.join(", ")) {
+                        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+                    }
+                }
+            }
+            if let Some(max_age) = self.max_age {
+                headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age));
+            }
+        }
+        self.set_vary(res.headers_mut());
+        res
+    }
+}
+
+impl<S> Middleware<S> for Cors {
+    type Service = CorsMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        CorsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct CorsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for CorsMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorsMiddleware")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for CorsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    E: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        Box::pin(async move {
+            let origin = req.headers().get(header::ORIGIN).cloned();
+
+            // preflight request
+            if let Some(ref origin) = origin {
+                if req.method() == Method::OPTIONS
+                    && req
+                        .headers()
+                        .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+                {
+                    let res = self.inner.preflight(origin, req.head());
+                    return Ok(req.into_response(res));
+                }
+            }
+
+            let allowed = origin.as_ref().and_then(|origin| {
+                origin
+                    .to_str()
+                    .ok()
+                    .filter(|o| self.inner.is_origin_allowed(o, req.head()))
+                    .map(|_| origin.clone())
+            });
+
+            let mut res = ctx.call(&self.service, req).await?;
+            if let Some(origin) = allowed {
+                let headers = res.headers_mut();
+                self.inner.set_headers(&origin, headers);
+                if !self.inner.expose.is_empty() {
+                    let expose: Vec<_> =
+                        self.inner.expose.iter().map(|h| h.as_str()).collect();
+                    if let Ok(value) = HeaderValue::try_from(expose.join(", ")) {
+                        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+                    }
+                }
+            }
+            self.inner.set_vary(res.headers_mut());
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::{DefaultError, Error};
+
+    fn preflight(origin: &'static str) -> TestRequest {
+        TestRequest::with_header(header::ORIGIN, origin)
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+    }
+
+    #[test]
+    fn test_origins() {
+        let cors = Cors::new()
+            .allowed_origin("https://Example.com")
+            .allowed_origin("https://*.example.org")
+            .allowed_origin_fn(|origin, _| origin.ends_with(".local"));
+        let head = RequestHead::default();
+        let inner = &cors.inner;
+        assert!(inner.is_origin_allowed("https://example.com", &head));
+        assert!(!inner.is_origin_allowed("https://example.com.evil", &head));
+        assert!(inner.is_origin_allowed("https://api.example.org", &head));
+        assert!(inner.is_origin_allowed("https://a.b.example.org", &head));
+        assert!(!inner.is_origin_allowed("https://.example.org", &head));
+        assert!(!inner.is_origin_allowed("https://evil.com/.example.org", &head));
+        assert!(inner.is_origin_allowed("http://app.local", &head));
+        assert!(!inner.is_origin_allowed("https://other.com", &head));
+        assert!(format!("{:?}", cors).contains("Cors"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Credentials are not allowed with wildcard origin")]
+    fn test_credentials_wildcard() {
+        let _ = Cors::new().allowed_origin("*").supports_credentials();
+    }
+
+    #[test]
+    #[should_panic(expected = "Credentials are not allowed with wildcard origin")]
+    fn test_wildcard_credentials() {
+        let _ = Cors::new().supports_credentials().allowed_origin("*");
+    }
+
+    #[crate::rt_test]
+    async fn test_preflight() {
+        // preflight requests must not reach handler
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::InternalServerError()))
+        };
+        let cors = Cors::new()
+            .allowed_origin("https://example.com")
+            .allowed_methods([Method::GET, Method::PUT])
+            .allowed_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+            .supports_credentials()
+            .max_age(3600);
+        let srv = Pipeline::new(Middleware::create(&cors, srv.into_service()));
+
+        let req = preflight("https://example.com")
+            .header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "Content-Type, X-Custom",
+            )
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://example.com"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "PUT"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "Content-Type"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+        assert_eq!(headers.get(header::VARY).unwrap(), "origin");
+
+        // method is not permitted
+        let req = TestRequest::with_header(header::ORIGIN, "https://example.com")
+            .method(Method::OPTIONS)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+
+        // origin is not allowed
+        let req = preflight("https://other.com").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "origin");
+    }
+
+    #[crate::rt_test]
+    async fn test_actual_request() {
+        let cors = Cors::new()
+            .allowed_origin("https://example.com")
+            .expose_headers(["x-total", "x-page"]);
+        let srv = Pipeline::new(Middleware::create(&cors, ok_service()));
+
+        let req = TestRequest::with_header(header::ORIGIN, "https://example.com")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        let headers = res.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://example.com"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap(),
+            "x-total, x-page"
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert_eq!(headers.get(header::VARY).unwrap(), "origin");
+
+        // not allowed origin
+        let req =
+            TestRequest::with_header(header::ORIGIN, "https://other.com").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "origin");
+
+        // not a cors request
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "origin");
+    }
+
+    #[crate::rt_test]
+    async fn test_any_origin() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::Ok()
+                        .header(header::VARY, "accept-encoding")
+                        .finish(),
+                ),
+            )
+        };
+        let cors = Cors::new().allowed_origin("*");
+        let srv = Pipeline::new(Middleware::create(&cors, srv.into_service()));
+
+        let req = TestRequest::with_header(header::ORIGIN, "https://example.com")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+        assert_eq!(res.headers().get_all(header::VARY).count(), 1);
+
+        let req = preflight("https://example.com").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                .unwrap(),
+            "PUT"
+        );
+        assert!(!res.headers().contains_key(header::VARY));
+    }
+}
@@ -1,16 +1,18 @@
 //! Middleware for setting default response headers
-use std::rc::Rc;
+use std::{fmt, rc::Rc};
 
 use crate::http::error::HttpError;
-use crate::http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::header::{AsName, HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
 use crate::service::{Middleware, Service, ServiceCtx};
 use crate::util::BoxFuture;
-use crate::web::{WebRequest, WebResponse};
+use crate::web::{HttpRequest, WebRequest, WebResponse};
 
 /// `Middleware` for setting default response headers.
 ///
 /// This middleware does not set header if response headers already contains it.
 ///
+/// Header values could be computed per request with `header_fn()` method.
+///
 /// ```rust
 /// use ntex::http;
 /// use ntex::web::{self, middleware, App, HttpResponse};
@@ -30,23 +32,72 @@ pub struct DefaultHeaders {
     inner: Rc<Inner>,
 }
 
-#[derive(Debug)]
 struct Inner {
     ct: bool,
     headers: HeaderMap,
+    computed: Vec<(HeaderName, HeaderFn)>,
 }
 
+type HeaderFn = Box<dyn Fn(&HttpRequest) -> Option<HeaderValue>>;
+
 impl Default for DefaultHeaders {
     fn default() -> Self {
         DefaultHeaders {
             inner: Rc::new(Inner {
                 ct: false,
                 headers: HeaderMap::new(),
+                computed: Vec::new(),
             }),
         }
     }
 }
 
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("ct", &self.ct)
+            .field("headers", &self.headers)
+            .field(
+                "computed",
+                &self.computed.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Header values computed by [`DefaultHeaders`] middleware for current request.
+///
+/// Values are stored in request extensions before request is passed to
+/// inner services, handlers could use them, for example, to read value of
+/// generated `Content-Security-Policy` header.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::ComputedHeaders, HttpRequest};
+///
+/// async fn index(req: HttpRequest) -> String {
+///     req.extensions()
+///         .get::<ComputedHeaders>()
+///         .and_then(|h| h.get("content-security-policy"))
+///         .and_then(|v| v.to_str().ok())
+///         .unwrap_or_default()
+///         .to_string()
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ComputedHeaders(HeaderMap);
+
+impl ComputedHeaders {
+    /// Get computed header value
+    pub fn get<K: AsName>(&self, key: K) -> Option<&HeaderValue> {
+        self.0.get(key)
+    }
+
+    /// Iterate over computed headers
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        self.0.iter()
+    }
+}
+
 impl DefaultHeaders {
     /// Construct `DefaultHeaders` middleware.
     pub fn new() -> DefaultHeaders {
@@ -78,6 +129,46 @@ impl DefaultHeaders {
         self
     }
 
+    /// Set a header with value computed per request.
+    ///
+    /// Function is called before request is passed to inner service,
+    /// computed value is stored in [`ComputedHeaders`] request extension and
+    /// is set on the response if response does not contain this header.
+    /// If function returns `None` or value is not a valid header value,
+    /// header is skipped.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, middleware, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .wrap(middleware::DefaultHeaders::new().header_fn(
+    ///             "Cache-Control",
+    ///             |req| req.path().starts_with("/static/").then_some("max-age=3600"),
+    ///         ))
+    ///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+    /// }
+    /// ```
+    pub fn header_fn<K, F, V>(mut self, key: K, f: F) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        F: Fn(&HttpRequest) -> Option<V> + 'static,
+        HeaderValue: TryFrom<V>,
+    {
+        let key = HeaderName::try_from(key)
+            .map_err(Into::into)
+            .expect("Cannot create header name");
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .computed
+            .push((
+                key,
+                Box::new(move |req| f(req).and_then(|v| HeaderValue::try_from(v).ok())),
+            ));
+        self
+    }
+
     /// Set *CONTENT-TYPE* header if response does not contain this header.
     pub fn content_type(mut self) -> Self {
         Rc::get_mut(&mut self.inner)
@@ -111,8 +202,7 @@ where
 {
     type Response = WebResponse;
     type Error = S::Error;
-    type Future<'f> =
-        BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
 
     crate::forward_poll_ready!(service);
     crate::forward_poll_shutdown!(service);
@@ -122,9 +212,34 @@ where
         req: WebRequest<E>,
         ctx: ServiceCtx<'a, Self>,
     ) -> Self::Future<'a> {
+        let mut computed = Vec::new();
+        if !self.inner.computed.is_empty() {
+            for (key, f) in &self.inner.computed {
+                if let Some(value) = f(req.http_request()) {
+                    computed.push((key.clone(), value));
+                } else {
+                    log::debug!("Skip computed header {:?}", key);
+                }
+            }
+            let mut ext = req.extensions_mut();
+            if !ext.contains::<ComputedHeaders>() {
+                ext.insert(ComputedHeaders::default());
+            }
+            let headers = ext.get_mut::<ComputedHeaders>().unwrap();
+            for (key, value) in &computed {
+                headers.0.insert(key.clone(), value.clone());
+            }
+        }
+
         Box::pin(async move {
             let mut res = ctx.call(&self.service, req).await?;
 
+            // set computed headers
+            for (key, value) in computed {
+                if !res.headers().contains_key(&key) {
+                    res.headers_mut().insert(key, value);
+                }
+            }
             // set response headers
             for (key, value) in self.inner.headers.iter() {
                 if !res.headers().contains_key(key) {
@@ -150,7 +265,7 @@ mod tests {
     use crate::service::{IntoService, Pipeline};
     use crate::util::lazy;
     use crate::web::request::WebRequest;
-    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::test::{ok_service, read_body, TestRequest};
     use crate::web::{DefaultError, Error, HttpResponse};
 
     #[crate::rt_test]
@@ -201,4 +316,40 @@ mod tests {
             "application/octet-stream"
         );
     }
+
+    #[crate::rt_test]
+    async fn test_header_fn() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let nonce = req
+                .extensions()
+                .get::<ComputedHeaders>()
+                .and_then(|h| h.get("x-nonce").cloned())
+                .unwrap();
+            Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::Ok()
+                        .header("x-path", "handler")
+                        .body(nonce.to_str().unwrap().to_string()),
+                ),
+            )
+        };
+        let mw = Pipeline::new(
+            DefaultHeaders::new()
+                .header_fn("x-nonce", |req| Some(req.path().len().to_string()))
+                .header_fn("x-path", |req| Some(req.path().to_string()))
+                .header_fn("x-skip", |_| None::<&str>)
+                .header_fn("x-invalid", |_| Some("\n"))
+                .create(srv.into_service()),
+        );
+        assert!(format!("{:?}", mw).contains("x-nonce"));
+
+        let req = TestRequest::with_uri("/test").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.headers().get("x-nonce").unwrap(), "5");
+        assert_eq!(resp.headers().get("x-path").unwrap(), "handler");
+        assert!(!resp.headers().contains_key("x-skip"));
+        assert!(!resp.headers().contains_key("x-invalid"));
+
+        assert_eq!(read_body(resp).await, "5");
+    }
 }
@@ -0,0 +1,319 @@
+//! Middleware for custom handling of error responses
+use std::ops::{Bound, RangeBounds};
+use std::{fmt, future::Future, rc::Rc};
+
+use crate::http::body::BodySize;
+use crate::http::header::{HeaderValue, CONTENT_LENGTH};
+use crate::http::StatusCode;
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{BoxFuture, HashMap};
+use crate::web::{WebRequest, WebResponse};
+
+type Handler = Rc<dyn Fn(WebResponse) -> BoxFuture<'static, WebResponse>>;
+
+/// `Middleware` for registering custom handlers for responses with specific
+/// status codes.
+///
+/// Handler receives original response and returns future that resolves
+/// to the response, handler could return original response untouched or
+/// replace it completely. Handlers for exact status codes get checked first,
+/// then handlers for ranges of status codes in registration order.
+///
+/// `Content-Length` header gets updated if handler replaces response body.
+/// Responses produced by handlers are not handled again, even by
+/// other `ErrorHandlers` middlewares.
+///
+/// ```rust
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, middleware::ErrorHandlers, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             ErrorHandlers::new()
+///                 .handler(StatusCode::NOT_FOUND, |res| async move {
+///                     res.into_response(
+///                         HttpResponse::NotFound()
+///                             .content_type("text/html")
+///                             .body("<h1>Page not found</h1>"),
+///                     )
+///                 })
+///                 .range(500..600, |res| async move {
+///                     let status = res.status();
+///                     res.into_response(HttpResponse::build(status).body("Server error"))
+///                 }),
+///         )
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ErrorHandlers {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    handlers: HashMap<StatusCode, Handler>,
+    ranges: Vec<(Bound<u16>, Bound<u16>, Handler)>,
+}
+
+impl Inner {
+    fn get(&self, status: StatusCode) -> Option<&Handler> {
+        self.handlers.get(&status).or_else(|| {
+            self.ranges
+                .iter()
+                .find(|(start, end, _)| (*start, *end).contains(&status.as_u16()))
+                .map(|(_, _, handler)| handler)
+        })
+    }
+}
+
+/// Marker for responses produced by error handlers
+struct Handled;
+
+impl ErrorHandlers {
+    /// Construct new `ErrorHandlers` instance
+    pub fn new() -> Self {
+        ErrorHandlers::default()
+    }
+
+    /// Register handler for specified status code
+    pub fn handler<F, R>(mut self, status: StatusCode, handler: F) -> Self
+    where
+        F: Fn(WebResponse) -> R + 'static,
+        R: Future<Output = WebResponse> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .handlers
+            .insert(status, Rc::new(move |res| Box::pin(handler(res))));
+        self
+    }
+
+    /// Register handler for range of status codes, i.e. `500..600`
+    pub fn range<T, F, R>(mut self, range: T, handler: F) -> Self
+    where
+        T: RangeBounds<u16>,
+        F: Fn(WebResponse) -> R + 'static,
+        R: Future<Output = WebResponse> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .ranges
+            .push((
+                range.start_bound().cloned(),
+                range.end_bound().cloned(),
+                Rc::new(move |res| Box::pin(handler(res))),
+            ));
+        self
+    }
+}
+
+impl fmt::Debug for ErrorHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorHandlers")
+            .field("handlers", &self.inner.handlers.keys())
+            .field("ranges", &self.inner.ranges.len())
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for ErrorHandlers {
+    type Service = ErrorHandlersMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ErrorHandlersMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ErrorHandlersMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for ErrorHandlersMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorHandlersMiddleware")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for ErrorHandlersMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    E: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> =
+        BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        Box::pin(async move {
+            let res = ctx.call(&self.service, req).await?;
+
+            let handler = if res.response().extensions().contains::<Handled>() {
+                None
+            } else {
+                self.inner.get(res.status())
+            };
+
+            if let Some(handler) = handler {
+                let size = res.response().body().size();
+                let mut res = handler(res).await;
+                res.response().extensions_mut().insert(Handled);
+
+                // body is replaced, update content length
+                let new_size = res.response().body().size();
+                if new_size != size {
+                    match new_size {
+                        BodySize::Sized(len) => {
+                            res.headers_mut()
+                                .insert(CONTENT_LENGTH, HeaderValue::from(len));
+                        }
+                        BodySize::Empty => {
+                            res.headers_mut()
+                                .insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
+                        }
+                        BodySize::None | BodySize::Stream => {
+                            res.headers_mut().remove(CONTENT_LENGTH);
+                        }
+                    }
+                }
+                Ok(res)
+            } else {
+                Ok(res)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::body::{Body, ResponseBody};
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{DefaultError, Error, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_handler() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let status = match req.path() {
+                "/missing" => StatusCode::NOT_FOUND,
+                "/error" => StatusCode::BAD_GATEWAY,
+                "/teapot" => StatusCode::IM_A_TEAPOT,
+                _ => StatusCode::OK,
+            };
+            Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::build(status)
+                        .header(CONTENT_LENGTH, "8")
+                        .body("ORIGINAL"),
+                ),
+            )
+        };
+        let mw = ErrorHandlers::new()
+            .handler(StatusCode::NOT_FOUND, |res| async move {
+                res.into_response(HttpResponse::NotFound().body("NOT FOUND PAGE"))
+            })
+            .range(500..=599, |res| async move {
+                let status = res.status();
+                res.into_response(HttpResponse::build(status).body("ERROR"))
+            })
+            .range(400.., |mut res| async move {
+                // pass through untouched
+                res.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from_static("8"));
+                res
+            });
+        assert!(format!("{:?}", mw).contains("ErrorHandlers"));
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let res = srv
+            .call(TestRequest::with_uri("/").to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(test::read_body(res).await, "ORIGINAL");
+
+        let res = srv
+            .call(TestRequest::with_uri("/missing").to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(test::read_body(res).await, "NOT FOUND PAGE");
+
+        let res = srv
+            .call(TestRequest::with_uri("/error").to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(test::read_body(res).await, "ERROR");
+
+        let res = srv
+            .call(TestRequest::with_uri("/teapot").to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "8");
+        assert_eq!(test::read_body(res).await, "ORIGINAL");
+    }
+
+    #[crate::rt_test]
+    async fn test_content_length() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::InternalServerError()
+                        .header(CONTENT_LENGTH, "8")
+                        .body("ORIGINAL"),
+                ),
+            )
+        };
+        let mw = ErrorHandlers::new().range(500..600, |res| async move {
+            res.map_body(|_, _| ResponseBody::Other(Body::from("REPLACED BODY")))
+        });
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "13");
+        assert_eq!(test::read_body(res).await, "REPLACED BODY");
+    }
+
+    #[crate::rt_test]
+    async fn test_no_recursion() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::NotFound().finish()))
+        };
+        let inner = ErrorHandlers::new().handler(StatusCode::NOT_FOUND, |res| async move {
+            res.into_response(HttpResponse::InternalServerError().body("INNER"))
+        });
+        let outer = ErrorHandlers::new().range(.., |res| async move {
+            res.into_response(HttpResponse::Ok().body("OUTER"))
+        });
+        let srv = Pipeline::new(Middleware::create(
+            &outer,
+            Middleware::create(&inner, srv.into_service()),
+        ));
+
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(test::read_body(res).await, "INNER");
+    }
+}
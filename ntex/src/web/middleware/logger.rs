@@ -1,15 +1,17 @@
 //! Request logging middleware
 use std::task::{ready, Context, Poll};
-use std::{env, error::Error, future::Future};
-use std::{fmt, fmt::Display, marker::PhantomData, pin::Pin, rc::Rc, time};
+use std::{cell::Ref, env, error::Error, future::Future, panic::AssertUnwindSafe};
+use std::{fmt, fmt::Display, marker::PhantomData, panic, pin::Pin, rc::Rc, time};
 
-use regex::Regex;
+use regex::{Regex, RegexSet};
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::header::HeaderName;
+use crate::http::{header::HeaderName, RequestHead, StatusCode};
 use crate::service::{Middleware, Service, ServiceCall, ServiceCtx};
 use crate::util::{Bytes, Either, HashSet};
-use crate::web::{HttpResponse, WebRequest, WebResponse};
+use crate::web::info::ConnectionInfo;
+use crate::web::types::RequestId;
+use crate::web::{HttpRequest, HttpResponse, WebRequest, WebResponse};
 
 /// `Middleware` for logging request and response info to the terminal.
 ///
@@ -44,7 +46,8 @@ use crate::web::{HttpResponse, WebRequest, WebResponse};
 ///
 /// `%%`  The percent sign
 ///
-/// `%a`  Remote IP-address (IP-address of proxy if using reverse proxy)
+/// `%a`  Remote IP-address, forwarded IP-address if request comes from trusted proxy,
+/// see `App::trusted_proxies()`
 ///
 /// `%t`  Time when the request was started to process (in rfc3339 format)
 ///
@@ -59,6 +62,8 @@ use crate::web::{HttpResponse, WebRequest, WebResponse};
 ///
 /// `%D`  Time taken to serve the request, in milliseconds
 ///
+/// `%M`  Time taken to serve the request, in microseconds
+///
 /// `%U`  Request URL
 ///
 /// `%{FOO}i`  request.headers['FOO']
@@ -67,25 +72,90 @@ use crate::web::{HttpResponse, WebRequest, WebResponse};
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
+/// `%{FOO}xi`  [custom request replacement](Logger::custom_request_replace) labelled "FOO"
+///
+/// `%{request_id}xi`  Request id set by [`RequestId`](super::RequestId) middleware
+///
+/// `%{original_method}xi`  Original method of request with method overridden by
+/// [`MethodOverride`](super::MethodOverride) middleware
+///
+/// `%{FOO}xo`  [custom response replacement](Logger::custom_response_replace) labelled "FOO"
+///
+/// ## Exclusion
+///
+/// Requests could be excluded from logging by path, with [`exclude`](Logger::exclude)
+/// and [`exclude_regex`](Logger::exclude_regex), or by response, with
+/// [`exclude_status`](Logger::exclude_status) and [`log_target`](Logger::log_target).
+/// Exclusion always wins, if any rule excludes request it is not logged
+/// even if `log_target` predicate accepts it. Excluded requests are not
+/// formatted at all.
+///
+/// If response rules are configured, request fields get rendered after
+/// response is ready.
 #[derive(Debug)]
 pub struct Logger {
     inner: Rc<Inner>,
 }
 
-#[derive(Debug)]
+type LogTarget = Rc<dyn Fn(&HttpRequest, &HttpResponse) -> bool>;
+
 struct Inner {
     format: Format,
     exclude: HashSet<String>,
+    exclude_regex: RegexSet,
+    exclude_status: Vec<StatusCode>,
+    log_target: Option<LogTarget>,
+}
+
+impl Inner {
+    fn new(format: Format) -> Self {
+        Inner {
+            format,
+            exclude: HashSet::default(),
+            exclude_regex: RegexSet::empty(),
+            exclude_status: Vec::new(),
+            log_target: None,
+        }
+    }
+
+    /// Check if request path is excluded
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.contains(path) || self.exclude_regex.is_match(path)
+    }
+
+    /// Check if logging depends on response
+    fn has_response_rules(&self) -> bool {
+        !self.exclude_status.is_empty() || self.log_target.is_some()
+    }
+
+    /// Check if request is excluded by response rules
+    fn is_excluded_response(&self, res: &WebResponse) -> bool {
+        self.exclude_status.contains(&res.status())
+            || self
+                .log_target
+                .as_ref()
+                .map(|f| !f(res.request(), res.response()))
+                .unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("format", &self.format)
+            .field("exclude", &self.exclude)
+            .field("exclude_regex", &self.exclude_regex.patterns())
+            .field("exclude_status", &self.exclude_status)
+            .field("log_target", &self.log_target.is_some())
+            .finish()
+    }
 }
 
 impl Logger {
     /// Create `Logger` middleware with the specified `format`.
     pub fn new(format: &str) -> Logger {
         Logger {
-            inner: Rc::new(Inner {
-                format: Format::new(format),
-                exclude: HashSet::default(),
-            }),
+            inner: Rc::new(Inner::new(Format::new(format))),
         }
     }
 
@@ -97,6 +167,104 @@ impl Logger {
             .insert(path.into());
         self
     }
+
+    /// Ignore and do not log access info for paths that match regex.
+    ///
+    /// Patterns are compiled once, when logger is constructed.
+    ///
+    /// Panics if `pattern` is not a valid regex.
+    pub fn exclude_regex<T: AsRef<str>>(mut self, pattern: T) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        let mut patterns = inner.exclude_regex.patterns().to_vec();
+        patterns.push(pattern.as_ref().to_string());
+        inner.exclude_regex = RegexSet::new(patterns).unwrap();
+        self
+    }
+
+    /// Ignore and do not log access info for responses with specified status.
+    pub fn exclude_status(mut self, status: StatusCode) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        if !inner.exclude_status.contains(&status) {
+            inner.exclude_status.push(status);
+        }
+        self
+    }
+
+    /// Set predicate that decides if request should be logged.
+    ///
+    /// Predicate is called after response is ready, if it returns `false`
+    /// access info is not logged. Predicate could not override other
+    /// exclusion rules.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::Logger;
+    ///
+    /// // log requests for static files only if they failed
+    /// Logger::default().log_target(|req, res| {
+    ///     !(req.path().starts_with("/static/") && res.status().is_success())
+    /// });
+    /// ```
+    pub fn log_target<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest, &HttpResponse) -> bool + 'static,
+    {
+        Rc::get_mut(&mut self.inner).unwrap().log_target = Some(Rc::new(f));
+        self
+    }
+
+    /// Register a function that receives a `HttpRequest` and returns a `String`
+    /// for use in the log line.
+    ///
+    /// The label passed as the first argument should match a replacement
+    /// substring in the logger format like `%{label}xi`. Function is called
+    /// after request get handled, if function panics `-` is logged.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::Logger;
+    ///
+    /// Logger::new("%{TENANT}xi").custom_request_replace("TENANT", |req| {
+    ///     req.headers()
+    ///         .get("x-tenant-id")
+    ///         .and_then(|v| v.to_str().ok())
+    ///         .unwrap_or("-")
+    ///         .to_string()
+    /// });
+    /// ```
+    pub fn custom_request_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> String + 'static,
+    {
+        let f = CustomFn(Rc::new(f));
+        for unit in &mut Rc::get_mut(&mut self.inner).unwrap().format.0 {
+            if let FormatText::CustomRequest(ref name, ref mut func) = unit {
+                if name == label {
+                    *func = Some(f.clone());
+                }
+            }
+        }
+        self
+    }
+
+    /// Register a function that receives a `HttpResponse` and returns a `String`
+    /// for use in the log line.
+    ///
+    /// The label passed as the first argument should match a replacement
+    /// substring in the logger format like `%{label}xo`. If function panics `-`
+    /// is logged.
+    pub fn custom_response_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&HttpResponse) -> String + 'static,
+    {
+        let f = CustomFn(Rc::new(f));
+        for unit in &mut Rc::get_mut(&mut self.inner).unwrap().format.0 {
+            if let FormatText::CustomResponse(ref name, ref mut func) = unit {
+                if name == label {
+                    *func = Some(f.clone());
+                }
+            }
+        }
+        self
+    }
 }
 
 impl Default for Logger {
@@ -107,10 +275,7 @@ impl Default for Logger {
     /// ```
     fn default() -> Self {
         Logger {
-            inner: Rc::new(Inner {
-                format: Format::default(),
-                exclude: HashSet::default(),
-            }),
+            inner: Rc::new(Inner::new(Format::default())),
         }
     }
 }
@@ -139,7 +304,8 @@ where
 {
     type Response = WebResponse;
     type Error = S::Error;
-    type Future<'f> = Either<LoggerResponse<'f, S, E>, ServiceCall<'f, S, WebRequest<E>>> where S: 'f, E: 'f;
+    type Future<'f> =
+        Either<LoggerResponse<'f, S, E>, ServiceCall<'f, S, WebRequest<E>>> where S: 'f, E: 'f;
 
     crate::forward_poll_ready!(service);
     crate::forward_poll_shutdown!(service);
@@ -150,18 +316,25 @@ where
         req: WebRequest<E>,
         ctx: ServiceCtx<'a, Self>,
     ) -> Self::Future<'a> {
-        if self.inner.exclude.contains(req.path()) {
+        if self.inner.is_excluded(req.path()) {
             Either::Right(ctx.call(&self.service, req))
         } else {
             let time = time::SystemTime::now();
-            let mut format = self.inner.format.clone();
 
-            for unit in &mut format.0 {
-                unit.render_request(time, &req);
-            }
+            // with response rules, request is rendered after response is ready
+            let format = if self.inner.has_response_rules() {
+                None
+            } else {
+                let mut format = self.inner.format.clone();
+                for unit in &mut format.0 {
+                    unit.render_request(time, &req);
+                }
+                Some(format)
+            };
             Either::Left(LoggerResponse {
                 time,
-                format: Some(format),
+                format,
+                inner: self.inner.clone(),
                 fut: ctx.call(&self.service, req),
                 _t: PhantomData,
             })
@@ -178,6 +351,7 @@ pin_project_lite::pin_project! {
         fut: ServiceCall<'f, S, WebRequest<E>>,
         time: time::SystemTime,
         format: Option<Format>,
+        inner: Rc<Inner>,
         _t: PhantomData<E>
     }
 }
@@ -192,14 +366,23 @@ where
         let this = self.project();
 
         let res = ready!(this.fut.poll(cx)?);
-        if let Some(ref mut format) = this.format {
+        let time = *this.time;
+        let mut format = if let Some(format) = this.format.take() {
+            format
+        } else if this.inner.is_excluded_response(&res) {
+            return Poll::Ready(Ok(res));
+        } else {
+            let mut format = this.inner.format.clone();
             for unit in &mut format.0 {
-                unit.render_response(res.response());
+                unit.render_request(time, res.request());
             }
+            format
+        };
+        for unit in &mut format.0 {
+            unit.render_response(res.response());
+            unit.render_custom(&res);
         }
-
-        let time = *this.time;
-        let format = this.format.take();
+        let format = Some(format);
 
         Poll::Ready(Ok(res.map_body(move |_, body| {
             ResponseBody::Other(Body::from_message(StreamLog {
@@ -271,7 +454,8 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrUsbTD]?)").unwrap();
+        let fmt =
+            Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe]|x[io])|[atPrUsbTDM]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -292,6 +476,17 @@ impl Format {
                         HeaderName::try_from(key.as_str()).unwrap(),
                     ),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "xi" => {
+                        let func = match key.as_str() {
+                            "request_id" => Some(CustomFn(Rc::new(request_id))),
+                            "original_method" => Some(CustomFn(Rc::new(
+                                super::method_override::original_method,
+                            ))),
+                            _ => None,
+                        };
+                        FormatText::CustomRequest(key.as_str().to_owned(), func)
+                    }
+                    "xo" => FormatText::CustomResponse(key.as_str().to_owned(), None),
                     _ => unreachable!(),
                 })
             } else {
@@ -306,6 +501,7 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "M" => FormatText::TimeMicros,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -331,11 +527,44 @@ enum FormatText {
     ResponseSize,
     Time,
     TimeMillis,
+    TimeMicros,
     RemoteAddr,
     UrlPath,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    CustomRequest(String, Option<CustomFn<HttpRequest>>),
+    CustomResponse(String, Option<CustomFn<HttpResponse>>),
+}
+
+/// Default replacement for `%{request_id}xi`
+fn request_id(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<RequestId>()
+        .map(|id| id.as_str().to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Custom replacement function
+struct CustomFn<T>(Rc<dyn Fn(&T) -> String>);
+
+impl<T> Clone for CustomFn<T> {
+    fn clone(&self) -> Self {
+        CustomFn(self.0.clone())
+    }
+}
+
+impl<T> CustomFn<T> {
+    fn call(&self, item: &T) -> String {
+        panic::catch_unwind(AssertUnwindSafe(|| (self.0)(item)))
+            .unwrap_or_else(|_| "-".to_string())
+    }
+}
+
+impl<T> fmt::Debug for CustomFn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomFn")
+    }
 }
 
 impl FormatText {
@@ -359,6 +588,11 @@ impl FormatText {
                 let rt = (rt.as_nanos() as f64) / 1_000_000.0;
                 fmt.write_fmt(format_args!("{:.6}", rt))
             }
+            FormatText::TimeMicros => {
+                let rt = entry_time.elapsed().unwrap();
+                rt.as_micros().fmt(fmt)
+            }
+            FormatText::CustomRequest(..) | FormatText::CustomResponse(..) => "-".fmt(fmt),
             FormatText::EnvironHeader(ref name) => {
                 if let Ok(val) = env::var(name) {
                     fmt.write_fmt(format_args!("{}", val))
@@ -391,33 +625,42 @@ impl FormatText {
         }
     }
 
-    fn render_request<E>(&mut self, now: time::SystemTime, req: &WebRequest<E>) {
+    fn render_custom(&mut self, res: &WebResponse) {
+        let s = match *self {
+            FormatText::CustomRequest(_, Some(ref f)) => f.call(res.request()),
+            FormatText::CustomResponse(_, Some(ref f)) => f.call(res.response()),
+            _ => return,
+        };
+        *self = FormatText::Str(s);
+    }
+
+    fn render_request<R: LogRequest>(&mut self, now: time::SystemTime, req: &R) {
+        let head = req.head();
         match *self {
             FormatText::RequestLine => {
-                let q = req.query_string();
-                *self = if q.is_empty() {
+                *self = if let Some(q) = head.uri.query() {
                     FormatText::Str(format!(
-                        "{} {} {:?}",
-                        req.method(),
-                        req.path(),
-                        req.version()
+                        "{} {}?{} {:?}",
+                        head.method,
+                        head.uri.path(),
+                        q,
+                        head.version
                     ))
                 } else {
                     FormatText::Str(format!(
-                        "{} {}?{} {:?}",
-                        req.method(),
-                        req.path(),
-                        q,
-                        req.version()
+                        "{} {} {:?}",
+                        head.method,
+                        head.uri.path(),
+                        head.version
                     ))
                 };
             }
-            FormatText::UrlPath => *self = FormatText::Str(req.path().to_string()),
+            FormatText::UrlPath => *self = FormatText::Str(head.uri.path().to_string()),
             FormatText::RequestTime => {
                 *self = FormatText::Str(httpdate::HttpDate::from(now).to_string())
             }
             FormatText::RequestHeader(ref name) => {
-                let s = if let Some(val) = req.headers().get(name) {
+                let s = if let Some(val) = head.headers.get(name) {
                     if let Ok(s) = val.to_str() {
                         s
                     } else {
@@ -429,8 +672,8 @@ impl FormatText {
                 *self = FormatText::Str(s.to_string());
             }
             FormatText::RemoteAddr => {
-                let s = if let Some(remote) = req.connection_info().remote() {
-                    FormatText::Str(remote.to_string())
+                let s = if let Some(ip) = req.connection_info().real_ip() {
+                    FormatText::Str(ip.to_string())
                 } else {
                     FormatText::Str("-".to_string())
                 };
@@ -441,6 +684,33 @@ impl FormatText {
     }
 }
 
+/// Request data used for rendering log line
+trait LogRequest {
+    fn head(&self) -> &RequestHead;
+
+    fn connection_info(&self) -> Ref<'_, ConnectionInfo>;
+}
+
+impl<E> LogRequest for WebRequest<E> {
+    fn head(&self) -> &RequestHead {
+        WebRequest::head(self)
+    }
+
+    fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
+        WebRequest::connection_info(self)
+    }
+}
+
+impl LogRequest for HttpRequest {
+    fn head(&self) -> &RequestHead {
+        HttpRequest::head(self)
+    }
+
+    fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
+        HttpRequest::connection_info(self)
+    }
+}
+
 pub(crate) struct FormatDisplay<'a>(
     &'a dyn Fn(&mut fmt::Formatter<'_>) -> Result<(), fmt::Error>,
 );
@@ -454,7 +724,7 @@ impl<'a> fmt::Display for FormatDisplay<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::http::{header, StatusCode};
+    use crate::http::{header, Method, StatusCode};
     use crate::service::{IntoService, Middleware, Pipeline};
     use crate::util::lazy;
     use crate::web::test::{self, TestRequest};
@@ -613,4 +883,138 @@ mod tests {
         let s = format!("{}", FormatDisplay(&render));
         assert!(s.contains(&httpdate::HttpDate::from(now).to_string()));
     }
+
+    #[crate::rt_test]
+    async fn test_custom_replace() {
+        let logger = Logger::new("%{TENANT}xi %{LEN}xo %{PANIC}xi %{UNKNOWN}xo %M")
+            .custom_request_replace("TENANT", |req| {
+                req.headers()
+                    .get("x-tenant")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("-")
+                    .to_string()
+            })
+            .custom_response_replace("LEN", |res| res.headers().len().to_string())
+            .custom_request_replace("PANIC", |_| panic!("custom replace"));
+        let mut format = logger.inner.format.clone();
+
+        let req = TestRequest::with_header("x-tenant", "tenant-1").to_srv_request();
+        let now = time::SystemTime::now();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+
+        let res = req.into_response(
+            HttpResponse::build(StatusCode::OK)
+                .header("X-Test", "ttt")
+                .finish(),
+        );
+        for unit in &mut format.0 {
+            unit.render_response(res.response());
+            unit.render_custom(&res);
+        }
+
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+        let s = format!("{}", FormatDisplay(&render));
+        let parts: Vec<_> = s.split(' ').collect();
+        assert_eq!(parts[..4], ["tenant-1", "1", "-", "-"]);
+        assert!(parts[4].parse::<u128>().is_ok());
+    }
+
+    #[crate::rt_test]
+    async fn test_request_id() {
+        let mut format = Format::new("%{request_id}xi");
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut()
+            .insert(RequestId(header::HeaderValue::from_static("id-1")));
+        let res = req.into_response(HttpResponse::Ok().finish());
+        for unit in &mut format.0 {
+            unit.render_custom(&res);
+        }
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 0, time::SystemTime::now())?;
+            }
+            Ok(())
+        };
+        assert_eq!(format!("{}", FormatDisplay(&render)), "id-1");
+    }
+
+    #[crate::rt_test]
+    async fn test_original_method() {
+        let mut format = Format::new("%{original_method}xi");
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut()
+            .insert(crate::web::middleware::OriginalMethod(Method::POST));
+        let res = req.into_response(HttpResponse::Ok().finish());
+        for unit in &mut format.0 {
+            unit.render_custom(&res);
+        }
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 0, time::SystemTime::now())?;
+            }
+            Ok(())
+        };
+        assert_eq!(format!("{}", FormatDisplay(&render)), "POST");
+    }
+
+    #[crate::rt_test]
+    async fn test_exclude() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let status = if req.path().ends_with("/missing") {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::OK
+            };
+            Ok::<_, Error>(req.into_response(HttpResponse::build(status).body("TEST")))
+        };
+        let logger = Logger::new("%r %s")
+            .exclude("/healthz")
+            .exclude_regex("^/assets/")
+            .exclude_regex(r"\.ico$")
+            .exclude_status(StatusCode::NOT_FOUND)
+            .log_target(|req, res| {
+                !(req.path().starts_with("/static/") && res.status().is_success())
+            });
+        assert!(format!("{:?}", logger).contains("exclude_regex"));
+        let srv = Pipeline::new(Middleware::create(&logger, srv.into_service()));
+
+        // logged responses get wrapped with logging body
+        let is_logged = |path: &'static str| {
+            let srv = srv.clone();
+            async move {
+                let req = TestRequest::with_uri(path).to_srv_request();
+                let res = srv.call(req).await.unwrap();
+                matches!(res.response().body(), ResponseBody::Other(_))
+            }
+        };
+        assert!(is_logged("/index.html").await);
+        assert!(!is_logged("/healthz").await);
+        assert!(!is_logged("/assets/app.js").await);
+        assert!(!is_logged("/favicon.ico").await);
+        assert!(!is_logged("/static/app.css").await);
+        assert!(!is_logged("/missing").await);
+        assert!(!is_logged("/static/missing").await);
+
+        // exclusion wins over log target
+        let logger = Logger::new("%r %s")
+            .exclude("/healthz")
+            .exclude_status(StatusCode::NOT_FOUND)
+            .log_target(|_, _| true);
+        assert!(logger.inner.is_excluded("/healthz"));
+
+        let req = TestRequest::with_uri("/missing").to_srv_request();
+        let res = req.into_response(HttpResponse::NotFound().finish());
+        assert!(logger.inner.is_excluded_response(&res));
+
+        let req = TestRequest::with_uri("/").to_srv_request();
+        let res = req.into_response(HttpResponse::Ok().finish());
+        assert!(!logger.inner.is_excluded_response(&res));
+    }
 }
@@ -0,0 +1,476 @@
+//! Middleware for http method override
+use std::{borrow::Cow, fmt, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::http::error::{HttpError, PayloadError};
+use crate::http::header::HeaderName;
+use crate::http::{HttpMessage, Method, Payload};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::{BoxFuture, Bytes, BytesMut, Stream};
+use crate::web::{WebRequest, WebResponse};
+
+/// Default method override header name
+const X_HTTP_METHOD_OVERRIDE: &str = "x-http-method-override";
+
+/// Method override query and form field name
+const METHOD_FIELD: &str = "_method";
+
+/// Default max size of form body inspected for method field
+const FORM_LIMIT: usize = 16_384;
+
+/// `Middleware` for http method override.
+///
+/// HTML forms can submit only `GET` and `POST` requests, middleware
+/// replaces method of `POST` request with method from `X-HTTP-Method-Override`
+/// header. Optionally, method could be read from `_method` query parameter
+/// or from `_method` field of urlencoded form. First found source wins,
+/// header is checked first, then query and then form.
+///
+/// Only methods from allow-list are accepted, by default `PUT`, `PATCH`
+/// and `DELETE`. Original method is stored in request extensions as
+/// [`OriginalMethod`], it is available for `%{original_method}xi`
+/// [`Logger`](super::Logger) format token.
+///
+/// Middleware must be registered on application level, so routing, route
+/// guards and *405 Method Not Allowed* responses use overridden method.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::MethodOverride::default().form())
+///         .service(
+///             web::resource("/post/{id}")
+///                 .route(web::put().to(|| async { HttpResponse::Ok() }))
+///                 .route(web::delete().to(|| async { HttpResponse::NoContent() })),
+///         );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MethodOverride {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    header: Option<HeaderName>,
+    query: bool,
+    form: bool,
+    form_limit: usize,
+    methods: Vec<Method>,
+}
+
+impl Default for MethodOverride {
+    fn default() -> Self {
+        MethodOverride {
+            inner: Rc::new(Inner {
+                header: Some(HeaderName::from_static(X_HTTP_METHOD_OVERRIDE)),
+                query: false,
+                form: false,
+                form_limit: FORM_LIMIT,
+                methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+            }),
+        }
+    }
+}
+
+impl MethodOverride {
+    /// Construct `MethodOverride` middleware.
+    pub fn new() -> MethodOverride {
+        MethodOverride::default()
+    }
+
+    /// Set method override header name.
+    ///
+    /// By default `X-HTTP-Method-Override` header is used.
+    pub fn header<K>(mut self, name: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        let name = HeaderName::try_from(name)
+            .map_err(Into::into)
+            .expect("Cannot create header name");
+        self.inner_mut().header = Some(name);
+        self
+    }
+
+    /// Do not read method from request header.
+    pub fn disable_header(mut self) -> Self {
+        self.inner_mut().header = None;
+        self
+    }
+
+    /// Read method from `_method` query parameter.
+    pub fn query(mut self) -> Self {
+        self.inner_mut().query = true;
+        self
+    }
+
+    /// Read method from `_method` field of urlencoded form.
+    ///
+    /// Middleware reads request body up to form limit, body is passed
+    /// to the application as is.
+    pub fn form(mut self) -> Self {
+        self.inner_mut().form = true;
+        self
+    }
+
+    /// Set max size of form body inspected for method field.
+    ///
+    /// Larger forms are not inspected. By default limit is 16Kb.
+    pub fn form_limit(mut self, limit: usize) -> Self {
+        self.inner_mut().form_limit = limit;
+        self
+    }
+
+    /// Set allowed target methods.
+    ///
+    /// By default `PUT`, `PATCH` and `DELETE` methods are allowed.
+    ///
+    /// Panics if `CONNECT` or `TRACE` method is specified.
+    pub fn methods<I: IntoIterator<Item = Method>>(mut self, methods: I) -> Self {
+        let methods: Vec<_> = methods.into_iter().collect();
+        for method in &methods {
+            assert!(
+                method != Method::CONNECT && method != Method::TRACE,
+                "Method override to {} is not allowed",
+                method
+            );
+        }
+        self.inner_mut().methods = methods;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+}
+
+impl fmt::Debug for MethodOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodOverride")
+            .field("header", &self.inner.header)
+            .field("query", &self.inner.query)
+            .field("form", &self.inner.form)
+            .field("form_limit", &self.inner.form_limit)
+            .field("methods", &self.inner.methods)
+            .finish()
+    }
+}
+
+impl Inner {
+    fn parse(&self, value: &[u8]) -> Option<Method> {
+        let method = Method::from_bytes(&value.to_ascii_uppercase()).ok()?;
+        if self.methods.contains(&method) {
+            Some(method)
+        } else {
+            None
+        }
+    }
+
+    async fn method<E>(&self, req: &mut WebRequest<E>) -> Option<Method> {
+        if let Some(ref name) = self.header {
+            if let Some(value) = req.headers().get(name) {
+                return self.parse(value.as_bytes());
+            }
+        }
+        if self.query {
+            if let Some(value) = field(req.query_string().as_bytes()) {
+                return self.parse(value.as_bytes());
+            }
+        }
+        if self.form && req.content_type() == "application/x-www-form-urlencoded" {
+            let body = self.read_form(req).await?;
+            if let Some(value) = field(&body) {
+                return self.parse(value.as_bytes());
+            }
+        }
+        None
+    }
+
+    /// Read form body, body is put back to the request
+    async fn read_form<E>(&self, req: &mut WebRequest<E>) -> Option<Bytes> {
+        let mut payload = req.take_payload();
+        let mut body = BytesMut::new();
+        let mut complete = false;
+        let mut error = None;
+        while body.len() <= self.form_limit {
+            match payload.recv().await {
+                Some(Ok(chunk)) => body.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    error = Some(e);
+                    break;
+                }
+                None => {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+
+        let body = body.freeze();
+        req.set_payload(Payload::from_stream(Replay {
+            body: Some(body.clone()),
+            error,
+            payload,
+        }));
+        if complete && body.len() <= self.form_limit {
+            Some(body)
+        } else {
+            None
+        }
+    }
+}
+
+/// Find method field in urlencoded data
+fn field(data: &[u8]) -> Option<String> {
+    serde_urlencoded::from_bytes::<Vec<(Cow<'_, str>, Cow<'_, str>)>>(data)
+        .ok()?
+        .into_iter()
+        .find(|(key, _)| key == METHOD_FIELD)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Payload with already read part of body
+struct Replay {
+    body: Option<Bytes>,
+    error: Option<PayloadError>,
+    payload: Payload,
+}
+
+impl Stream for Replay {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(body) = this.body.take() {
+            if !body.is_empty() {
+                return Poll::Ready(Some(Ok(body)));
+            }
+        }
+        if let Some(err) = this.error.take() {
+            return Poll::Ready(Some(Err(err)));
+        }
+        this.payload.poll_recv(cx)
+    }
+}
+
+/// Original method of request with overridden method
+///
+/// Middleware [`MethodOverride`] stores original method in request extensions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OriginalMethod(pub(crate) Method);
+
+impl OriginalMethod {
+    /// Get original method
+    pub fn method(&self) -> &Method {
+        &self.0
+    }
+}
+
+impl<S> Middleware<S> for MethodOverride {
+    type Service = MethodOverrideMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        MethodOverrideMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct MethodOverrideMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for MethodOverrideMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MethodOverrideMiddleware")
+            .field("service", &self.service)
+            .field("methods", &self.inner.methods)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for MethodOverrideMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    E: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        mut req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        Box::pin(async move {
+            if req.method() == Method::POST {
+                if let Some(method) = self.inner.method(&mut req).await {
+                    log::trace!("Override request method POST with {}", method);
+                    req.extensions_mut().insert(OriginalMethod(Method::POST));
+                    req.head_mut().method = method;
+                }
+            }
+            ctx.call(&self.service, req).await
+        })
+    }
+}
+
+/// Default replacement for `%{original_method}xi` logger format token
+pub(super) fn original_method(req: &crate::web::HttpRequest) -> String {
+    req.extensions()
+        .get::<OriginalMethod>()
+        .map(|m| m.0.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App, HttpRequest, HttpResponse};
+
+    #[test]
+    fn test_field() {
+        assert_eq!(field(b"a=1&_method=put"), Some("put".to_string()));
+        assert_eq!(field(b"a=1"), None);
+        assert_eq!(field(b"%"), None);
+    }
+
+    async fn handler(req: HttpRequest, body: Bytes) -> HttpResponse {
+        let original = req
+            .extensions()
+            .get::<OriginalMethod>()
+            .map(|m| m.method().to_string())
+            .unwrap_or_default();
+        HttpResponse::Ok().body(format!("{} {} {:?}", original, req.method(), body))
+    }
+
+    #[crate::rt_test]
+    async fn test_method_override() {
+        let app = test::init_service(
+            App::new()
+                .wrap(MethodOverride::default())
+                .service(
+                    web::resource("/")
+                        .route(web::put().to(handler))
+                        .route(web::post().to(handler)),
+                )
+                .service(web::resource("/get").route(web::get().to(handler))),
+        )
+        .await;
+
+        let req = TestRequest::with_header("x-http-method-override", "put")
+            .method(Method::POST)
+            .set_payload("data")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "POST PUT b\"data\"");
+
+        // method is not allowed
+        for method in ["CONNECT", "TRACE", "GET", "invalid method"] {
+            let req = TestRequest::with_header("x-http-method-override", method)
+                .method(Method::POST)
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(test::read_body(res).await, " POST b\"\"");
+        }
+
+        // only POST requests are overridden
+        let req = TestRequest::with_header("x-http-method-override", "PUT")
+            .uri("/get")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, " GET b\"\"");
+
+        // route guards and 405 see overridden method
+        let req = TestRequest::with_header("x-http-method-override", "DELETE")
+            .method(Method::POST)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // query is not trusted by default
+        let req = TestRequest::with_uri("/?_method=PUT")
+            .method(Method::POST)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, " POST b\"\"");
+    }
+
+    #[crate::rt_test]
+    async fn test_query_and_form() {
+        let mw = MethodOverride::new()
+            .disable_header()
+            .query()
+            .form()
+            .form_limit(32)
+            .methods([Method::PUT]);
+        assert!(format!("{:?}", mw).contains("form_limit: 32"));
+        let app = test::init_service(
+            App::new().wrap(mw).service(
+                web::resource("/")
+                    .route(web::put().to(handler))
+                    .route(web::post().to(handler)),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/?_method=put")
+            .method(Method::POST)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, "POST PUT b\"\"");
+
+        // header is disabled
+        let req = TestRequest::with_header("x-http-method-override", "put")
+            .method(Method::POST)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(test::read_body(res).await, " POST b\"\"");
+
+        // form body is passed to handler
+        let req = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .method(Method::POST)
+        .set_payload("name=test&_method=PUT")
+        .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            test::read_body(res).await,
+            "POST PUT b\"name=test&_method=PUT\""
+        );
+
+        // form is too large
+        let body = format!("name={}&_method=PUT", "a".repeat(32));
+        let req = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .method(Method::POST)
+        .set_payload(body.clone())
+        .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            test::read_body(res).await,
+            format!(" POST {:?}", Bytes::from(body))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Method override to CONNECT is not allowed")]
+    fn test_connect_not_allowed() {
+        let _ = MethodOverride::default().methods([Method::PUT, Method::CONNECT]);
+    }
+}
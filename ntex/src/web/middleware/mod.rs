@@ -8,5 +8,29 @@ pub use self::compress::Compress;
 mod logger;
 pub use self::logger::Logger;
 
+mod method_override;
+pub use self::method_override::{MethodOverride, OriginalMethod};
+
+mod normalize;
+pub use self::normalize::{NormalizePath, TrailingSlash};
+
+mod request_id;
+pub use self::request_id::RequestId;
+
+mod condition;
+pub use self::condition::Condition;
+
+mod cors;
+pub use self::cors::Cors;
+
 mod defaultheaders;
-pub use self::defaultheaders::DefaultHeaders;
+pub use self::defaultheaders::{ComputedHeaders, DefaultHeaders};
+
+mod errhandlers;
+pub use self::errhandlers::ErrorHandlers;
+
+mod timeout;
+pub use self::timeout::Timeout;
+
+mod wrap_fn;
+pub use self::wrap_fn::{Next, WrapFn};
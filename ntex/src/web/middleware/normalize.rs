@@ -0,0 +1,280 @@
+//! Middleware for normalizing request path
+use crate::http::header::LOCATION;
+use crate::http::{uri::PathAndQuery, Method, StatusCode, Uri};
+use crate::service::{Middleware, Service, ServiceCall, ServiceCtx};
+use crate::util::{Either, Ready};
+use crate::web::{HttpResponse, WebRequest, WebResponse};
+
+/// Trailing slash behavior of `NormalizePath` middleware
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Remove trailing slashes, root path is not changed
+    Trim,
+    /// Append trailing slash if it is missing
+    Always,
+    /// Keep trailing slash as is, only merge duplicate slashes
+    MergeOnly,
+}
+
+/// `Middleware` for normalizing request path.
+///
+/// Duplicate slashes are always merged, trailing slash is handled according
+/// to `TrailingSlash` setting. By default, path is rewritten in place and
+/// request is passed to the application. In redirect mode middleware responds
+/// with redirect to normalized path, query string is preserved.
+///
+/// Normalized path is not checked against application routes, normalized
+/// path is never changed again, so requests for unknown paths get `404`
+/// response without redirect loops.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::NormalizePath, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(NormalizePath::trim().redirect())
+///         .service(web::resource("/test").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct NormalizePath {
+    trailing: TrailingSlash,
+    redirect: bool,
+}
+
+impl Default for NormalizePath {
+    fn default() -> Self {
+        NormalizePath::new(TrailingSlash::Trim)
+    }
+}
+
+impl NormalizePath {
+    /// Create `NormalizePath` middleware with specified trailing slash behavior
+    pub fn new(trailing: TrailingSlash) -> Self {
+        NormalizePath {
+            trailing,
+            redirect: false,
+        }
+    }
+
+    /// Remove trailing slashes
+    pub fn trim() -> Self {
+        NormalizePath::new(TrailingSlash::Trim)
+    }
+
+    /// Append trailing slash
+    pub fn always() -> Self {
+        NormalizePath::new(TrailingSlash::Always)
+    }
+
+    /// Only merge duplicate slashes
+    pub fn merge_only() -> Self {
+        NormalizePath::new(TrailingSlash::MergeOnly)
+    }
+
+    /// Respond with redirect to normalized path instead of rewriting it.
+    ///
+    /// `GET` and `HEAD` requests get `301 Moved Permanently` response,
+    /// other requests get `308 Permanent Redirect`, so clients repeat
+    /// request with the same method and body.
+    pub fn redirect(mut self) -> Self {
+        self.redirect = true;
+        self
+    }
+
+    /// Normalize path, returns `None` if path is already normalized
+    fn normalize(&self, path: &str) -> Option<String> {
+        if !path.starts_with('/') {
+            return None;
+        }
+
+        let mut normalized = String::with_capacity(path.len() + 1);
+        for ch in path.chars() {
+            if !(ch == '/' && normalized.ends_with('/')) {
+                normalized.push(ch);
+            }
+        }
+        match self.trailing {
+            TrailingSlash::Trim => {
+                if normalized.len() > 1 && normalized.ends_with('/') {
+                    normalized.pop();
+                }
+            }
+            TrailingSlash::Always => {
+                if !normalized.ends_with('/') {
+                    normalized.push('/');
+                }
+            }
+            TrailingSlash::MergeOnly => (),
+        }
+
+        if normalized == path {
+            None
+        } else {
+            Some(normalized)
+        }
+    }
+}
+
+impl<S> Middleware<S> for NormalizePath {
+    type Service = NormalizePathMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        NormalizePathMiddleware {
+            service,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NormalizePathMiddleware<S> {
+    service: S,
+    config: NormalizePath,
+}
+
+impl<S, E> Service<WebRequest<E>> for NormalizePathMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> =
+        Either<ServiceCall<'f, S, WebRequest<E>>, Ready<WebResponse, S::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        mut req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        if let Some(path) = self.config.normalize(req.path()) {
+            let path = if let Some(query) = req.uri().query() {
+                format!("{}?{}", path, query)
+            } else {
+                path
+            };
+
+            if self.config.redirect {
+                let method = req.method();
+                let status = if method == Method::GET || method == Method::HEAD {
+                    StatusCode::MOVED_PERMANENTLY
+                } else {
+                    StatusCode::PERMANENT_REDIRECT
+                };
+                let res = HttpResponse::build(status).header(LOCATION, path).finish();
+                return Either::Right(Ready::Ok(req.into_response(res)));
+            }
+
+            if let Ok(pq) = PathAndQuery::try_from(path) {
+                let mut parts = req.uri().clone().into_parts();
+                parts.path_and_query = Some(pq);
+                if let Ok(uri) = Uri::from_parts(parts) {
+                    req.match_info_mut().set(uri.clone());
+                    req.head_mut().uri = uri;
+                }
+            }
+        }
+        Either::Left(ctx.call(&self.service, req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App, DefaultError, Error};
+
+    #[test]
+    fn test_normalize() {
+        let trim = NormalizePath::trim();
+        assert_eq!(trim.normalize("/"), None);
+        assert_eq!(trim.normalize("/test"), None);
+        assert_eq!(trim.normalize("//"), Some("/".to_string()));
+        assert_eq!(trim.normalize("/test/"), Some("/test".to_string()));
+        assert_eq!(trim.normalize("//a//b///c//"), Some("/a/b/c".to_string()));
+
+        let always = NormalizePath::always();
+        assert_eq!(always.normalize("/"), None);
+        assert_eq!(always.normalize("/test/"), None);
+        assert_eq!(always.normalize("/test"), Some("/test/".to_string()));
+        assert_eq!(always.normalize("/a//b//"), Some("/a/b/".to_string()));
+
+        let merge = NormalizePath::merge_only();
+        assert_eq!(merge.normalize("/test"), None);
+        assert_eq!(merge.normalize("/test/"), None);
+        assert_eq!(merge.normalize("/a//b//"), Some("/a/b/".to_string()));
+        assert_eq!(merge.normalize("*"), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_rewrite() {
+        let app = test::init_service(App::new().wrap(NormalizePath::default()).service(
+            web::resource("/v1/something").to(|req: web::HttpRequest| async move {
+                HttpResponse::Ok().body(req.uri().to_string())
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/v1//something////?q=1").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(test::read_body(res).await, "/v1/something?q=1");
+
+        let req = TestRequest::with_uri("/v1/something/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+
+        let req = TestRequest::with_uri("/v1/unknown/").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_merge_only() {
+        let app = test::init_service(
+            App::new()
+                .wrap(NormalizePath::merge_only())
+                .service(web::resource("/foo").to(|| async { HttpResponse::Ok() }))
+                .service(web::resource("/foo/").to(|| async { HttpResponse::Created() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("//foo").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/foo//").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    #[crate::rt_test]
+    async fn test_redirect() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::NotFound().finish()))
+        };
+        let mw = NormalizePath::always().redirect();
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let req = TestRequest::with_uri("/a//b?q=1&w=2").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "/a/b/?q=1&w=2");
+
+        let req = TestRequest::with_uri("/a")
+            .method(Method::POST)
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(res.headers().get(LOCATION).unwrap(), "/a/");
+
+        // normalized path is not redirected again
+        let req = TestRequest::with_uri("/a/b/?q=1&w=2").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,354 @@
+//! Middleware for request id propagation
+use std::{fmt, rc::Rc};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::error::HttpError;
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::util::BoxFuture;
+use crate::web::{types, ErrorRenderer, WebRequest, WebResponse};
+
+/// Default request id header name
+const X_REQUEST_ID: &str = "x-request-id";
+
+/// Default max length of incoming request id
+const MAX_LENGTH: usize = 64;
+
+/// `Middleware` for request id propagation.
+///
+/// Middleware reads request id from incoming `X-Request-Id` header,
+/// if header is missing or contains invalid value, new id is generated.
+/// Request id gets stored in request extensions, it is available with
+/// [`RequestId`](crate::web::types::RequestId) extractor and `%{request_id}xi`
+/// [`Logger`](super::Logger) format token, and is set on the response.
+///
+/// Incoming request id must consist of visible ascii characters and must not be
+/// longer than 64 characters. Default generator produces random UUID v4.
+///
+/// Errors returned by inner middlewares as `Err` are rendered with application
+/// error renderer, so error responses carry request id as well.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Logger::new("%{request_id}xi %r %s"))
+///         .wrap(middleware::RequestId::default())
+///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RequestId {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    header: HeaderName,
+    max_length: usize,
+    generator: Rc<dyn Fn() -> String>,
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId {
+            inner: Rc::new(Inner {
+                header: HeaderName::from_static(X_REQUEST_ID),
+                max_length: MAX_LENGTH,
+                generator: Rc::new(uuid),
+            }),
+        }
+    }
+}
+
+impl RequestId {
+    /// Construct `RequestId` middleware.
+    pub fn new() -> RequestId {
+        RequestId::default()
+    }
+
+    /// Set request id header name.
+    ///
+    /// By default `X-Request-Id` header is used.
+    pub fn header<K>(mut self, name: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+    {
+        let name = HeaderName::try_from(name)
+            .map_err(Into::into)
+            .expect("Cannot create header name");
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = name;
+        self
+    }
+
+    /// Set max length of incoming request id.
+    ///
+    /// By default max length is 64 characters.
+    pub fn max_length(mut self, len: usize) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .max_length = len;
+        self
+    }
+
+    /// Set request id generator.
+    ///
+    /// Generator is used if request does not contain valid request id.
+    /// Generated value must be a valid header value.
+    pub fn generator<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> String + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .generator = Rc::new(f);
+        self
+    }
+}
+
+impl Inner {
+    fn is_valid(&self, value: &HeaderValue) -> bool {
+        let value = value.as_bytes();
+        !value.is_empty()
+            && value.len() <= self.max_length
+            && value.iter().all(|b| b.is_ascii_graphic())
+    }
+
+    fn generate(&self) -> HeaderValue {
+        match HeaderValue::try_from((self.generator)()) {
+            Ok(value) => value,
+            Err(_) => {
+                log::error!("Request id generator produced invalid header value");
+                HeaderValue::try_from(uuid()).unwrap()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestId")
+            .field("header", &self.inner.header)
+            .field("max_length", &self.inner.max_length)
+            .finish()
+    }
+}
+
+/// Generate random UUID v4
+fn uuid() -> String {
+    let mut v = WyRand::new().generate::<u128>();
+    // version 4 and RFC 4122 variant
+    v = (v & !(0xf << 76)) | (0x4 << 76);
+    v = (v & !(0x3 << 62)) | (0x2 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        v >> 96,
+        (v >> 80) & 0xffff,
+        (v >> 64) & 0xffff,
+        (v >> 48) & 0xffff,
+        v & 0xffff_ffff_ffff
+    )
+}
+
+impl<S> Middleware<S> for RequestId {
+    type Service = RequestIdMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestIdMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for RequestIdMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestIdMiddleware")
+            .field("service", &self.service)
+            .field("header", &self.inner.header)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for RequestIdMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse, Error = E::Container>,
+    E: ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        let id = req
+            .headers()
+            .get(&self.inner.header)
+            .filter(|v| self.inner.is_valid(v))
+            .cloned()
+            .unwrap_or_else(|| self.inner.generate());
+        req.extensions_mut().insert(types::RequestId(id.clone()));
+
+        // render service error, error response carries request id as well
+        let hreq = req.http_request().detached();
+        Box::pin(async move {
+            let mut res = match ctx.call(&self.service, req).await {
+                Ok(res) => res,
+                Err(err) => WebResponse::from_err::<E, _>(err, hreq),
+            };
+            res.headers_mut().insert(self.inner.header.clone(), id);
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::{IntoService, Pipeline};
+    use crate::web::middleware::DefaultHeaders;
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App, DefaultError, Error, HttpResponse};
+
+    #[test]
+    fn test_uuid() {
+        let id = uuid();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(id, uuid());
+    }
+
+    #[crate::rt_test]
+    async fn test_request_id() {
+        let app = test::init_service(App::new().wrap(RequestId::default()).route(
+            "/",
+            web::get().to(|id: types::RequestId| async move { id.to_string() }),
+        ))
+        .await;
+
+        // propagate incoming id
+        let req = TestRequest::with_header("x-request-id", "abc-123").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "abc-123");
+        assert_eq!(test::read_body(res).await, "abc-123");
+
+        // generate id
+        let req = TestRequest::default().to_request();
+        let res = test::call_service(&app, req).await;
+        let id = res.headers().get("x-request-id").unwrap().clone();
+        assert_eq!(id.len(), 36);
+        assert_eq!(test::read_body(res).await, id.as_bytes());
+
+        // invalid incoming id
+        let long = "a".repeat(65);
+        for value in ["", "with space", long.as_str()] {
+            let req = TestRequest::with_header("x-request-id", value).to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.headers().get("x-request-id").unwrap().len(), 36);
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_config() {
+        let mw = RequestId::new()
+            .header("x-correlation-id")
+            .max_length(8)
+            .generator(|| "01ARZ3NDEKTSV4RRFFQ69G5FAV".to_string());
+        assert!(format!("{:?}", mw).contains("x-correlation-id"));
+
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let req = TestRequest::with_header("x-correlation-id", "12345678").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.headers().get("x-correlation-id").unwrap(), "12345678");
+
+        let req =
+            TestRequest::with_header("x-correlation-id", "123456789").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(
+            res.headers().get("x-correlation-id").unwrap(),
+            "01ARZ3NDEKTSV4RRFFQ69G5FAV"
+        );
+        assert!(!res.headers().contains_key("x-request-id"));
+    }
+
+    #[crate::rt_test]
+    async fn test_error_response() {
+        // inner middleware renders error response
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.error_response(web::error::ErrorForbidden("forbidden")))
+        };
+        let mw = RequestId::default();
+        let srv = Pipeline::new(Middleware::create(
+            &mw,
+            Middleware::create(&DefaultHeaders::new(), srv.into_service()),
+        ));
+
+        let req = TestRequest::with_header("x-request-id", "err-1").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "err-1");
+    }
+
+    #[crate::rt_test]
+    async fn test_service_error() {
+        // inner service returns error
+        let srv = |_: WebRequest<DefaultError>| async move {
+            Err::<WebResponse, _>(web::error::ErrorBadRequest("bad request").into())
+        };
+        let srv = Pipeline::new(Middleware::create(
+            &RequestId::default(),
+            srv.into_service(),
+        ));
+
+        let req = TestRequest::with_header("x-request-id", "err-2").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "err-2");
+
+        // handler error
+        let app =
+            test::init_service(App::new().wrap(RequestId::default()).route(
+                "/",
+                web::get().to(|| async {
+                    Err::<String, _>(web::error::ErrorConflict("conflict"))
+                }),
+            ))
+            .await;
+        let req = TestRequest::with_header("x-request-id", "err-3").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "err-3");
+    }
+
+    #[crate::rt_test]
+    async fn test_extractor_not_configured() {
+        let app = test::init_service(App::new().route(
+            "/",
+            web::get().to(|id: types::RequestId| async move { id.to_string() }),
+        ))
+        .await;
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,378 @@
+//! Middleware for request processing timeout
+use std::{fmt, future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::header::{HeaderValue, RETRY_AFTER};
+use crate::service::{Middleware, Service, ServiceCtx};
+use crate::time::{sleep, Millis, Sleep};
+use crate::util::{poll_fn, BoxFuture, BytesMut};
+use crate::web::{HttpRequest, HttpResponse, WebRequest, WebResponse};
+
+/// `Middleware` for request processing timeout.
+///
+/// Timeout covers time until response head is ready, streaming response
+/// body is not bound by timeout. With `buffer_body()` streaming body is
+/// collected within the same timeout. Timer starts when request is passed
+/// to the inner service, waiting for service readiness is not counted.
+///
+/// If timeout elapses, request processing is dropped and `503 Service
+/// Unavailable` response with `Retry-After: 1` header is returned.
+/// Timeout is disabled if it is set to 0.
+///
+/// Timeout response is built for a copy of the request head, request
+/// extensions are not available at that point.
+///
+/// ```rust
+/// use ntex::time::Seconds;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Timeout::new(Seconds(5)))
+///         .route("/", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Timeout {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    timeout: Millis,
+    buffer_body: bool,
+    response: Box<dyn Fn(&HttpRequest) -> HttpResponse>,
+}
+
+impl Timeout {
+    /// Construct `Timeout` middleware.
+    pub fn new<T: Into<Millis>>(timeout: T) -> Self {
+        Timeout {
+            inner: Rc::new(Inner {
+                timeout: timeout.into(),
+                buffer_body: false,
+                response: Box::new(|_| {
+                    HttpResponse::ServiceUnavailable()
+                        .header(RETRY_AFTER, HeaderValue::from_static("1"))
+                        .finish()
+                }),
+            }),
+        }
+    }
+
+    /// Collect streaming response body within timeout.
+    ///
+    /// Response body is buffered in memory, use it only for responses
+    /// of limited size.
+    pub fn buffer_body(mut self) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .buffer_body = true;
+        self
+    }
+
+    /// Set response for timed out requests.
+    pub fn response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .response = Box::new(f);
+        self
+    }
+}
+
+impl fmt::Debug for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("timeout", &self.inner.timeout)
+            .field("buffer_body", &self.inner.buffer_body)
+            .finish()
+    }
+}
+
+impl<S> Middleware<S> for Timeout {
+    type Service = TimeoutMiddleware<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        TimeoutMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// `Middleware` service for request processing timeout, see [`Timeout`].
+///
+/// Inner service owns the request, so request head is copied before the
+/// call to build timeout response.
+pub struct TimeoutMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for TimeoutMiddleware<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutMiddleware")
+            .field("service", &self.service)
+            .field("timeout", &self.inner.timeout)
+            .finish()
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for TimeoutMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    E: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future<'f> = BoxFuture<'f, Result<Self::Response, Self::Error>> where S: 'f, E: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<E>,
+        ctx: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        if self.inner.timeout.is_zero() {
+            return Box::pin(ctx.call(&self.service, req));
+        }
+
+        let hreq = req.http_request().detached();
+        Box::pin(async move {
+            let delay = sleep(self.inner.timeout);
+            let fut = ctx.call(&self.service, req);
+            let mut res = match timed(&delay, fut).await {
+                Some(res) => res?,
+                None => return Ok(self.timeout_response(hreq)),
+            };
+
+            if self.inner.buffer_body {
+                let mut body = res.take_body();
+                if let BodySize::Stream = body.size() {
+                    match timed(&delay, collect(&mut body)).await {
+                        Some(Ok(bytes)) => {
+                            res = res.map_body(|_, _| {
+                                ResponseBody::Body(Body::Bytes(bytes.freeze()))
+                            })
+                        }
+                        Some(Err(e)) => {
+                            log::error!("Cannot read response body: {}", e);
+                            let res = HttpResponse::InternalServerError().finish();
+                            return Ok(WebResponse::new(res, hreq));
+                        }
+                        None => return Ok(self.timeout_response(hreq)),
+                    }
+                } else {
+                    res = res.map_body(|_, _| body);
+                }
+            }
+            Ok(res)
+        })
+    }
+}
+
+impl<S> TimeoutMiddleware<S> {
+    fn timeout_response(&self, req: HttpRequest) -> WebResponse {
+        log::trace!("Request processing timeout {:?}", req.path());
+        let res = (self.inner.response)(&req);
+        WebResponse::new(res, req)
+    }
+}
+
+/// Poll future until it completes or delay elapses.
+///
+/// Future is polled first, so completed future wins over elapsed delay.
+async fn timed<F: Future>(delay: &Sleep, fut: F) -> Option<F::Output> {
+    let mut fut = std::pin::pin!(fut);
+    poll_fn(|cx| {
+        if let Poll::Ready(res) = Pin::new(&mut fut).poll(cx) {
+            Poll::Ready(Some(res))
+        } else if delay.poll_elapsed(cx).is_ready() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+async fn collect(
+    body: &mut ResponseBody<Body>,
+) -> Result<BytesMut, Box<dyn std::error::Error>> {
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+        bytes.extend_from_slice(&chunk?);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::{IntoService, Pipeline};
+    use crate::util::Bytes;
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App, DefaultError, Error};
+
+    #[crate::rt_test]
+    async fn test_timeout() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Timeout::new(Millis(50)))
+                .route("/fast", web::get().to(|| async { HttpResponse::Ok() }))
+                .service(web::scope("/scope").state(1usize).route(
+                    "/{id}",
+                    web::post().to(
+                        |_: web::types::Path<(usize,)>, body: Bytes| async move {
+                            HttpResponse::Ok().body(body)
+                        },
+                    ),
+                ))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        sleep(Millis(200)).await;
+                        HttpResponse::Ok()
+                    }),
+                ),
+        )
+        .await;
+
+        let res =
+            test::call_service(&app, TestRequest::with_uri("/fast").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // inner routing and extractors own the request
+        let req = TestRequest::post()
+            .uri("/scope/1")
+            .set_payload("body")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, Bytes::from_static(b"body"));
+
+        let res =
+            test::call_service(&app, TestRequest::with_uri("/slow").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "1");
+    }
+
+    #[crate::rt_test]
+    async fn test_custom_response() {
+        let mw = Timeout::new(Millis(10))
+            .response(|req| HttpResponse::GatewayTimeout().body(req.path().to_string()));
+        assert!(format!("{:?}", mw).contains("Timeout"));
+
+        let srv = |req: WebRequest<DefaultError>| async move {
+            sleep(Millis(100)).await;
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let srv = srv.into_service();
+        let res = Pipeline::new(Middleware::create(&mw, &srv))
+            .call(TestRequest::with_uri("/test").to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(test::read_body(res).await, Bytes::from_static(b"/test"));
+
+        // zero timeout disables middleware
+        let res = Pipeline::new(Middleware::create(&Timeout::new(Millis(0)), &srv))
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_completion_wins() {
+        // deadline passes while response future is polled
+        let srv = |req: WebRequest<DefaultError>| async move {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let mw = Timeout::new(Millis(10));
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_streaming_body() {
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, Error>>();
+        let rx = std::cell::RefCell::new(Some(rx));
+        let srv = move |req: WebRequest<DefaultError>| {
+            let rx = rx.borrow_mut().take();
+            async move {
+                let res = match rx {
+                    Some(rx) => HttpResponse::Ok().streaming(rx),
+                    None => HttpResponse::Ok().streaming(futures_util::stream::iter(vec![
+                        Ok::<_, Error>(Bytes::from_static(b"chunk-1")),
+                        Ok(Bytes::from_static(b"chunk-2")),
+                    ])),
+                };
+                Ok::<_, Error>(req.into_response(res))
+            }
+        };
+        let srv = srv.into_service();
+
+        // body is not covered by default
+        let default = Timeout::new(Millis(10));
+        let res = Middleware::create(&default, &srv);
+        let mut res = Pipeline::new(res)
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        sleep(Millis(50)).await;
+        tx.send(Ok(Bytes::from_static(b"late"))).unwrap();
+        drop(tx);
+        let mut body = res.take_body();
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk, Bytes::from_static(b"late"));
+
+        let buffered = Timeout::new(Millis(50)).buffer_body();
+        let res = Pipeline::new(Middleware::create(&buffered, &srv))
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.response().body().size(), BodySize::Sized(14));
+        assert_eq!(
+            test::read_body(res).await,
+            Bytes::from_static(b"chunk-1chunk-2")
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_buffered_body_timeout() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, Error>>();
+            tx.send(Ok(Bytes::from_static(b"chunk"))).unwrap();
+            crate::rt::spawn(async move {
+                sleep(Millis(200)).await;
+                drop(tx);
+            });
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().streaming(rx)))
+        };
+        let mw = Timeout::new(Millis(20)).buffer_body();
+        let srv = Pipeline::new(Middleware::create(&mw, srv.into_service()));
+
+        let res = srv
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
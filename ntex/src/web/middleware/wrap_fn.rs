@@ -0,0 +1,112 @@
+//! Middleware from async function
+use std::{fmt, future::Future, marker::PhantomData, rc::Rc};
+
+use crate::service::boxed::{self, BoxService};
+use crate::service::{Middleware, Pipeline, Service, ServiceCtx};
+use crate::web::{ErrorRenderer, WebRequest, WebResponse};
+
+/// Wrapped service passed to [`WrapFn`] function
+pub type Next<Err> =
+    Pipeline<BoxService<WebRequest<Err>, WebResponse, <Err as ErrorRenderer>::Container>>;
+
+/// `Middleware` from async function.
+///
+/// Function receives request and wrapped service, it could modify request
+/// before calling service and response after. Usually it is registered with
+/// `wrap_fn()` method of `App`, `Scope` or `Resource`.
+///
+/// ```rust
+/// use ntex::http::header::{HeaderValue, CACHE_CONTROL};
+/// use ntex::web::{self, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/static")
+///             .wrap_fn(|req, srv| async move {
+///                 let mut res = srv.call(req).await?;
+///                 res.headers_mut()
+///                     .insert(CACHE_CONTROL, HeaderValue::from_static("max-age=3600"));
+///                 Ok(res)
+///             })
+///             .to(|| async { HttpResponse::Ok() }),
+///     );
+/// }
+/// ```
+pub struct WrapFn<F, Err> {
+    f: Rc<F>,
+    _t: PhantomData<Err>,
+}
+
+impl<F, R, Err> WrapFn<F, Err>
+where
+    F: Fn(WebRequest<Err>, Next<Err>) -> R,
+    R: Future<Output = Result<WebResponse, Err::Container>>,
+    Err: ErrorRenderer,
+{
+    /// Construct middleware from async function.
+    pub fn new(f: F) -> Self {
+        WrapFn {
+            f: Rc::new(f),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, Err> fmt::Debug for WrapFn<F, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WrapFn")
+            .field("f", &std::any::type_name::<F>())
+            .finish()
+    }
+}
+
+impl<S, F, Err> Middleware<S> for WrapFn<F, Err>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse, Error = Err::Container> + 'static,
+    Err: ErrorRenderer,
+{
+    type Service = WrapFnMiddleware<F, Err>;
+
+    fn create(&self, service: S) -> Self::Service {
+        WrapFnMiddleware {
+            service: Pipeline::new(boxed::service(service)),
+            f: self.f.clone(),
+        }
+    }
+}
+
+pub struct WrapFnMiddleware<F, Err: ErrorRenderer> {
+    service: Next<Err>,
+    f: Rc<F>,
+}
+
+impl<F, Err: ErrorRenderer> fmt::Debug for WrapFnMiddleware<F, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WrapFnMiddleware")
+            .field("f", &std::any::type_name::<F>())
+            .finish()
+    }
+}
+
+impl<F, R, Err> Service<WebRequest<Err>> for WrapFnMiddleware<F, Err>
+where
+    F: Fn(WebRequest<Err>, Next<Err>) -> R,
+    R: Future<Output = Result<WebResponse, Err::Container>> + 'static,
+    Err: ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Future<'f> = R where Self: 'f;
+
+    crate::forward_poll_ready!(service);
+    crate::forward_poll_shutdown!(service);
+
+    #[inline]
+    fn call<'a>(
+        &'a self,
+        req: WebRequest<Err>,
+        _: ServiceCtx<'a, Self>,
+    ) -> Self::Future<'a> {
+        (self.f)(req, self.service.clone())
+    }
+}
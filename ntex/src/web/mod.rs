@@ -49,7 +49,7 @@
 //! * Streaming and pipelining
 //! * Keep-alive and slow requests handling
 //! * *WebSockets* server/client
-//! * Transparent content compression/decompression (br, gzip, deflate)
+//! * Transparent content compression/decompression (br, zstd, gzip, deflate)
 //! * Configurable request routing
 //! * SSL support with OpenSSL or `rustls`
 //! * Middlewares
@@ -59,15 +59,18 @@
 //!
 //! * `cookie` - enables http cookie support
 //! * `compress` - enables content encoding compression support
+//! * `zstd` - enables zstd content encoding support
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
 
 mod app;
 mod app_service;
+mod blocking;
 mod config;
 pub mod error;
 mod error_default;
 mod extract;
+mod fallback;
 pub mod guard;
 mod handler;
 mod httprequest;
@@ -82,6 +85,7 @@ mod route;
 mod scope;
 mod server;
 mod service;
+pub mod sse;
 pub mod test;
 pub mod types;
 mod util;
@@ -100,13 +104,16 @@ pub use ntex_macros::web_trace as trace;
 
 pub use crate::http::Response as HttpResponse;
 pub use crate::http::ResponseBuilder as HttpResponseBuilder;
+pub use crate::util::Either;
 
 pub use self::app::App;
+pub use self::blocking::BlockingPool;
 pub use self::config::ServiceConfig;
 pub use self::error::{
     DefaultError, Error, ErrorContainer, ErrorRenderer, WebResponseError,
 };
 pub use self::extract::FromRequest;
+pub use self::fallback::Fallback;
 pub use self::handler::Handler;
 pub use self::httprequest::HttpRequest;
 pub use self::request::WebRequest;
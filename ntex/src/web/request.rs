@@ -3,14 +3,14 @@ use std::{cell::Ref, cell::RefMut, fmt, marker::PhantomData, net, rc::Rc};
 use crate::http::{
     header, HeaderMap, HttpMessage, Method, Payload, RequestHead, Response, Uri, Version,
 };
-use crate::io::{types, IoRef};
+use crate::io::IoRef;
 use crate::router::{Path, Resource};
 use crate::util::Extensions;
 
 use super::config::AppConfig;
 use super::error::{ErrorRenderer, WebResponseError};
 use super::httprequest::HttpRequest;
-use super::info::ConnectionInfo;
+use super::info::{ConnectionInfo, TrustedProxies};
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::service::AppState;
@@ -86,6 +86,12 @@ impl<Err> WebRequest<Err> {
         WebResponse::new(res.into(), self.req)
     }
 
+    /// Reference to inner http request
+    #[inline]
+    pub(super) fn http_request(&self) -> &HttpRequest {
+        &self.req
+    }
+
     /// Io reference for current connection
     #[inline]
     pub fn io(&self) -> Option<&IoRef> {
@@ -160,17 +166,17 @@ impl<Err> WebRequest<Err> {
     /// To get client connection information `ConnectionInfo` should be used.
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.head()
-            .io
-            .as_ref()
-            .map(|io| io.query::<types::PeerAddr>().get().map(|addr| addr.0))
-            .unwrap_or(None)
+        self.head().peer_addr()
     }
 
     /// Get *ConnectionInfo* for the current request.
     #[inline]
     pub fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
-        ConnectionInfo::get(self.head(), self.app_config())
+        ConnectionInfo::get_with_proxies(
+            self.head(),
+            self.app_config(),
+            self.app_state::<TrustedProxies>(),
+        )
     }
 
     /// Get a reference to the Path parameters.
@@ -190,6 +196,12 @@ impl<Err> WebRequest<Err> {
         self.req.match_info_mut()
     }
 
+    #[inline]
+    /// Name of matched resource.
+    pub fn match_name(&self) -> Option<&str> {
+        self.req.match_name()
+    }
+
     #[inline]
     /// Get a reference to a `ResourceMap` of current application.
     pub fn resource_map(&self) -> &ResourceMap {
@@ -211,6 +223,12 @@ impl<Err> WebRequest<Err> {
         (self.req).0.app_state.get::<T>()
     }
 
+    #[inline]
+    /// Get an application state stored with `App::state_keyed()` method.
+    pub fn app_state_keyed<T: 'static>(&self, key: &str) -> Option<&T> {
+        (self.req).0.app_state.get_keyed::<T>(key)
+    }
+
     #[inline]
     /// Get request's payload
     pub fn take_payload(&mut self) -> Payload {
@@ -229,6 +247,10 @@ impl<Err> WebRequest<Err> {
         Rc::get_mut(&mut (self.req).0).unwrap().app_state = state;
     }
 
+    pub(super) fn set_match_name(&mut self, name: Rc<str>) {
+        Rc::get_mut(&mut (self.req).0).unwrap().match_name = Some(name);
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -1,6 +1,7 @@
-use std::{cell::RefCell, fmt, rc::Rc};
+use std::{cell::RefCell, fmt, future::Future, rc::Rc};
 
-use crate::http::Response;
+use crate::http::header::{self, HeaderValue};
+use crate::http::{Method, RequestHead, Response};
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::service::dev::{AndThen, ServiceChain, ServiceChainFactory};
@@ -12,11 +13,14 @@ use crate::util::{BoxFuture, Either, Extensions, Ready};
 
 use super::dev::{insert_slash, WebServiceConfig, WebServiceFactory};
 use super::extract::FromRequest;
+use super::guard::{Guard, ALLOW_METHODS};
 use super::handler::Handler;
+use super::middleware::{Next, WrapFn};
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::route::{IntoRoutes, Route, RouteService};
-use super::{app::Filter, error::ErrorRenderer, guard::Guard, service::AppState};
+use super::service::{insert_keyed_state, AppState};
+use super::{app::Filter, error::ErrorRenderer};
 
 type HttpService<Err: ErrorRenderer> =
     BoxService<WebRequest<Err>, WebResponse, Err::Container>;
@@ -47,7 +51,9 @@ type BoxResponse<'a, Err: ErrorRenderer> =
 /// }
 /// ```
 ///
-/// If no matching route could be found, *405* response code get returned.
+/// If no matching route could be found, *405* response code get returned,
+/// response contains `Allow` header with methods accepted by resource routes,
+/// `HEAD` method is listed if resource accepts `GET` requests.
 /// Default behavior could be overriden with `default_resource()` method.
 pub struct Resource<Err: ErrorRenderer, M = Identity, T = Filter<Err>> {
     middleware: M,
@@ -158,6 +164,14 @@ where
         self
     }
 
+    /// Set or override keyed application state.
+    ///
+    /// Keyed state of the resource shadows application state with the same key.
+    pub fn state_keyed<D: 'static>(mut self, key: &'static str, st: D) -> Self {
+        insert_keyed_state(self.state.get_or_insert_with(Extensions::new), key, st);
+        self
+    }
+
     /// Register a new route.
     ///
     /// ```rust
@@ -290,6 +304,19 @@ where
         }
     }
 
+    /// Register a resource middleware function.
+    ///
+    /// Function receives request and wrapped service, see
+    /// [`WrapFn`](super::middleware::WrapFn). Middleware functions get called
+    /// in the same order as middlewares.
+    pub fn wrap_fn<F, R>(self, f: F) -> Resource<Err, Stack<M, WrapFn<F, Err>>, T>
+    where
+        F: Fn(WebRequest<Err>, Next<Err>) -> R,
+        R: Future<Output = Result<WebResponse, Err::Container>>,
+    {
+        self.wrap(WrapFn::new(f))
+    }
+
     /// Default service to be used if no matching route could be found.
     /// By default *405* response get returned. Resource does not use
     /// default handler from `App` or `Scope`.
@@ -333,9 +360,11 @@ where
         } else {
             ResourceDef::new(self.rdef.clone())
         };
-        if let Some(ref name) = self.name {
+        let name = self.name.as_ref().map(|name| {
+            let name = format!("{}{}", config.name_prefix(), name);
             *rdef.name_mut() = name.clone();
-        }
+            Rc::from(name)
+        });
 
         let state = self.state.take().map(|state| {
             AppState::new(
@@ -347,6 +376,7 @@ where
 
         let router_factory = ResourceRouterFactory {
             state,
+            name,
             routes: self.routes,
             default: self.default.borrow_mut().take(),
         };
@@ -385,6 +415,7 @@ where
     ) -> ResourceServiceFactory<Err, M, ServiceChainFactory<F, WebRequest<Err>>> {
         let router_factory = ResourceRouterFactory {
             state: None,
+            name: None,
             routes: self.routes,
             default: self.default.borrow_mut().take(),
         };
@@ -435,6 +466,7 @@ struct ResourceRouterFactory<Err: ErrorRenderer> {
     routes: Vec<Route<Err>>,
     default: Option<Rc<HttpNewService<Err>>>,
     state: Option<AppState>,
+    name: Option<Rc<str>>,
 }
 
 impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ResourceRouterFactory<Err> {
@@ -454,17 +486,23 @@ impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ResourceRouterFacto
             Ok(ResourceRouter {
                 default,
                 state: self.state.clone(),
+                name: self.name.clone(),
                 routes: self.routes.iter().map(|route| route.service()).collect(),
             })
         })
     }
 }
 
+/// Automatic `OPTIONS` responses, configured with `App::auto_options()`
+#[derive(Copy, Clone, Debug)]
+pub(super) struct AutoOptions(pub(super) bool);
+
 type BoxResourceRouterResponse<'a, Err: ErrorRenderer> =
     ServiceCall<'a, RouteService<Err>, WebRequest<Err>>;
 
 pub struct ResourceRouter<Err: ErrorRenderer> {
     state: Option<AppState>,
+    name: Option<Rc<str>>,
     routes: Vec<RouteService<Err>>,
     default: Option<HttpService<Err>>,
 }
@@ -482,6 +520,9 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ResourceRouter<Err> {
         mut req: WebRequest<Err>,
         ctx: ServiceCtx<'a, Self>,
     ) -> Self::Future<'a> {
+        if let Some(ref name) = self.name {
+            req.set_match_name(name.clone());
+        }
         for route in self.routes.iter() {
             if route.check(&mut req) {
                 if let Some(ref state) = self.state {
@@ -490,24 +531,86 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ResourceRouter<Err> {
                 return Either::Left(ctx.call(route, req));
             }
         }
-        if let Some(ref default) = self.default {
+
+        let auto_options = req
+            .app_state::<AutoOptions>()
+            .map(|opts| opts.0)
+            .unwrap_or(false);
+        if auto_options && req.method() == Method::OPTIONS {
+            let allow = self
+                .allowed_methods(req.head(), true)
+                .unwrap_or_else(|| HeaderValue::from_static("OPTIONS"));
+            let res = Response::NoContent().header(header::ALLOW, allow).finish();
+            Either::Right(Either::Left(Ready::Ok(WebResponse::new(
+                res,
+                req.into_parts().0,
+            ))))
+        } else if let Some(ref default) = self.default {
             Either::Right(Either::Right(ctx.call(default, req)))
         } else {
+            // 405 only if request is rejected by method guards
+            let res = if let Some(allow) = self.allowed_methods(req.head(), auto_options) {
+                Response::MethodNotAllowed()
+                    .header(header::ALLOW, allow)
+                    .finish()
+            } else {
+                Response::NotFound().finish()
+            };
             Either::Right(Either::Left(Ready::Ok(WebResponse::new(
-                Response::MethodNotAllowed().finish(),
+                res,
                 req.into_parts().0,
             ))))
         }
     }
 }
 
+impl<Err: ErrorRenderer> ResourceRouter<Err> {
+    /// Methods which would be accepted by routes that rejected request method
+    ///
+    /// Methods are taken from route methods and method guards. Routes
+    /// that fail on other guards are skipped. Returns `None` if request
+    /// is not rejected by method of any route.
+    fn allowed_methods(&self, req: &RequestHead, options: bool) -> Option<HeaderValue> {
+        let declared: Vec<_> = self
+            .routes
+            .iter()
+            .filter_map(|r| match r.methods() {
+                Some(methods) if !methods.contains(&req.method) && r.check_guards(req) => {
+                    Some(methods)
+                }
+                _ => None,
+            })
+            .collect();
+        if declared.is_empty() {
+            return None;
+        }
+
+        let mut allowed: Vec<Method> = Vec::new();
+        let candidates = ALLOW_METHODS.iter().chain(declared.iter().flatten());
+        for m in candidates {
+            if !allowed.contains(m)
+                && (declared.iter().any(|methods| methods.contains(m))
+                    || (*m == Method::HEAD && allowed.contains(&Method::GET))
+                    || (*m == Method::OPTIONS && options))
+            {
+                allowed.push(m.clone());
+            }
+        }
+
+        let allowed: Vec<_> = allowed.iter().map(|m| m.as_str()).collect();
+        HeaderValue::try_from(allowed.join(", ")).ok()
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use crate::http::header::{self, HeaderValue};
     use crate::http::{Method, StatusCode};
     use crate::time::{sleep, Millis};
     use crate::web::middleware::DefaultHeaders;
-    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
     use crate::web::{self, guard, request::WebRequest, App, DefaultError, HttpResponse};
     use crate::{service::fn_service, util::Ready};
 
@@ -555,6 +658,46 @@ mod tests {
         );
     }
 
+    #[crate::rt_test]
+    async fn test_wrap_fn() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/test")
+                    .state(10usize)
+                    .guard(guard::Header("x-guard", "1"))
+                    .wrap_fn(|req, srv| async move {
+                        let mut res = srv.call(req).await?;
+                        res.headers_mut()
+                            .append(header::VARY, HeaderValue::from_static("inner"));
+                        Ok(res)
+                    })
+                    .wrap_fn(|req, srv| async move {
+                        let mut res = srv.call(req).await?;
+                        res.headers_mut()
+                            .append(header::VARY, HeaderValue::from_static("outer"));
+                        Ok(res)
+                    })
+                    .route(web::get().to(|st: web::types::State<usize>| async move {
+                        HttpResponse::Ok().body(st.to_string())
+                    })),
+            ),
+        )
+        .await;
+        let req = TestRequest::with_uri("/test")
+            .header("x-guard", "1")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let vary: Vec<_> = resp.headers().get_all(header::VARY).collect();
+        assert_eq!(vary, ["inner", "outer"]);
+        assert_eq!(read_body(resp).await, "10");
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key(header::VARY));
+    }
+
     #[crate::rt_test]
     async fn test_to() {
         let srv = init_service(App::new().service(web::resource("/test").to(|| async {
@@ -626,6 +769,136 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[crate::rt_test]
+    async fn test_allow_header() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/test")
+                        .route(
+                            web::route()
+                                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                                .to(|| async { HttpResponse::Ok() }),
+                        )
+                        .route(web::put().to(|| async { HttpResponse::Ok() })),
+                )
+                .service(
+                    web::resource("/not").route(
+                        web::route()
+                            .guard(guard::Not(guard::Any(guard::Get()).or(guard::Post())))
+                            .to(|| async { HttpResponse::Ok() }),
+                    ),
+                )
+                .service(
+                    web::resource("/header").route(
+                        web::post()
+                            .guard(guard::Header("content-type", "text/plain"))
+                            .to(|| async { HttpResponse::Ok() }),
+                    ),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD, PUT")
+        );
+
+        let req = TestRequest::with_uri("/not").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("HEAD, PUT, DELETE, CONNECT, OPTIONS, TRACE, PATCH")
+        );
+
+        // only non-method guard fails
+        let req = TestRequest::with_uri("/header")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key(header::ALLOW));
+
+        let req = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .uri("/header")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("POST")
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_allow_header_guards() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen2 = seen.clone();
+        let srv = init_service(
+            App::new().service(
+                web::resource("/test")
+                    .route(
+                        web::get()
+                            .guard(guard::fn_guard(move |head| {
+                                seen2.lock().unwrap().push(head.method.clone());
+                                !head.headers.contains_key("x-deny")
+                            }))
+                            .to(|| async { HttpResponse::Ok() }),
+                    )
+                    .route(
+                        web::route()
+                            .guard(guard::Put())
+                            .guard(guard::Header("content-type", "text/plain"))
+                            .to(|| async { HttpResponse::Ok() }),
+                    ),
+            ),
+        )
+        .await;
+
+        // put route fails on header guard
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD")
+        );
+
+        let req = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD, PUT")
+        );
+
+        // guards see actual request method
+        let req = TestRequest::with_header("x-deny", "1")
+            .uri("/test")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(!resp.headers().contains_key(header::ALLOW));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Method::POST, Method::POST, Method::GET]
+        );
+    }
+
     #[crate::rt_test]
     async fn test_resource_guards() {
         let srv = init_service(
@@ -693,4 +966,73 @@ mod tests {
         let resp = call_service(&srv, req).await;
         assert_eq!(resp.status(), StatusCode::OK);
     }
+
+    #[crate::rt_test]
+    async fn test_auto_options() {
+        let resources = || {
+            (
+                web::resource("/test")
+                    .route(web::get().to(|| async { HttpResponse::Ok() }))
+                    .route(web::post().to(|| async { HttpResponse::Ok() })),
+                web::resource("/options").route(
+                    web::route()
+                        .method(Method::OPTIONS)
+                        .to(|| async { HttpResponse::Ok() }),
+                ),
+            )
+        };
+        let srv = init_service(App::new().service(resources())).await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD, POST")
+        );
+
+        // auto options are disabled
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let srv = init_service(
+            App::new()
+                .auto_options(true)
+                .service(web::scope("/app").service(resources())),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/test")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD, POST, OPTIONS")
+        );
+        assert!(read_body(resp).await.is_empty());
+
+        let req = TestRequest::with_uri("/app/test")
+            .method(Method::DELETE)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, HEAD, POST, OPTIONS")
+        );
+
+        // explicit options route
+        let req = TestRequest::with_uri("/app/options")
+            .method(Method::OPTIONS)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
 }
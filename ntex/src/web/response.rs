@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::http::body::{Body, MessageBody, ResponseBody};
+use crate::http::body::{Body, ResponseBody};
 use crate::http::{HeaderMap, Response, ResponseHead, StatusCode};
 
 use super::error::{ErrorContainer, ErrorRenderer};
@@ -53,6 +53,11 @@ impl WebResponse {
         WebResponse::new(response, self.request)
     }
 
+    /// Deconstruct web response into parts
+    pub(super) fn into_parts(self) -> (HttpRequest, Response<Body>) {
+        (self.request, self.response)
+    }
+
     /// Get reference to original request
     #[inline]
     pub fn request(&self) -> &HttpRequest {
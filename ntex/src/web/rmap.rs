@@ -3,6 +3,8 @@ use std::{cell::RefCell, rc::Rc};
 #[cfg(feature = "url")]
 use url_pkg::Url;
 
+#[cfg(feature = "url")]
+use crate::router::BuildError;
 use crate::router::ResourceDef;
 use crate::util::HashMap;
 #[cfg(feature = "url")]
@@ -12,6 +14,7 @@ use crate::web::httprequest::HttpRequest;
 pub struct ResourceMap {
     #[allow(dead_code)]
     root: ResourceDef,
+    host: Option<(String, Option<u16>)>,
     parent: RefCell<Option<Rc<ResourceMap>>>,
     named: HashMap<String, ResourceDef>,
     patterns: Vec<(ResourceDef, Option<Rc<ResourceMap>>)>,
@@ -21,6 +24,7 @@ impl ResourceMap {
     pub fn new(root: ResourceDef) -> Self {
         ResourceMap {
             root,
+            host: None,
             parent: RefCell::new(None),
             named: HashMap::default(),
             patterns: Vec::new(),
@@ -36,6 +40,23 @@ impl ResourceMap {
         }
     }
 
+    /// Set virtual host of the map, host is used for url generation
+    pub(crate) fn set_host(&mut self, host: Option<(&str, Option<u16>)>) {
+        self.host = host.map(|(name, port)| (name.to_string(), port));
+    }
+
+    /// Virtual host of the map or of the closest parent map
+    #[cfg(feature = "url")]
+    fn host(&self) -> Option<(String, Option<u16>)> {
+        if self.host.is_some() {
+            self.host.clone()
+        } else if let Some(ref parent) = *self.parent.borrow() {
+            parent.host()
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn finish(&self, current: Rc<ResourceMap>) {
         for (_, nested) in &self.patterns {
             if let Some(ref nested) = nested {
@@ -44,6 +65,45 @@ impl ResourceMap {
             }
         }
     }
+
+    /// Check that resource names are unique across all nested maps
+    pub(crate) fn check_names(&self) -> Result<(), String> {
+        self.collect_names("", &mut HashMap::default())
+    }
+
+    fn collect_names(
+        &self,
+        prefix: &str,
+        names: &mut HashMap<String, String>,
+    ) -> Result<(), String> {
+        let prefix = format!("{}{}", prefix, self.root.pattern());
+        for (rdef, nested) in &self.patterns {
+            if let Some(ref nested) = nested {
+                nested.collect_names(&prefix, names)?;
+            } else if !rdef.name().is_empty() {
+                let pattern = if rdef.pattern().starts_with('/') {
+                    format!("{}{}", prefix, rdef.pattern())
+                } else {
+                    rdef.pattern().to_string()
+                };
+                match names.get(rdef.name()) {
+                    Some(existing) if *existing != pattern => {
+                        return Err(format!(
+                            "Duplicate resource name {:?}: {:?} and {:?}",
+                            rdef.name(),
+                            existing,
+                            pattern
+                        ));
+                    }
+                    Some(_) => (),
+                    None => {
+                        names.insert(rdef.name().to_string(), pattern);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "url")]
@@ -58,6 +118,99 @@ impl ResourceMap {
         name: &str,
         elements: U,
     ) -> Result<Url, super::error::UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+    {
+        self.url_for_query::<_, _, &str, &str>(req, name, elements, &[])
+    }
+
+    /// Generate url with query parameters for named resource
+    ///
+    /// Check [`HttpRequest::url_for_query()`](../struct.HttpRequest.html#method.
+    /// url_for_query) for detailed information.
+    pub fn url_for_query<U, I, K, V>(
+        &self,
+        req: &HttpRequest,
+        name: &str,
+        elements: U,
+        query: &[(K, V)],
+    ) -> Result<Url, super::error::UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut host = None;
+        let path = self.resource_path(name, elements, &mut host)?;
+        let mut url = if path.starts_with('/') {
+            let conn = req.connection_info();
+            let host = match host {
+                Some((name, Some(port))) => format!("{}:{}", name, port),
+                Some((name, None)) => {
+                    let current = conn.host().rsplit_once(':').map_or(conn.host(), |h| h.0);
+                    if current.eq_ignore_ascii_case(&name) {
+                        conn.host().to_string()
+                    } else {
+                        name
+                    }
+                }
+                None => conn.host().to_string(),
+            };
+            Url::parse(&format!("{}://{}{}", conn.scheme(), host, path))?
+        } else {
+            Url::parse(&path)?
+        };
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+
+    /// Generate relative url, path and query, for named resource
+    ///
+    /// Check [`HttpRequest::url_for_relative()`](../struct.HttpRequest.html#method.
+    /// url_for_relative) for detailed information.
+    pub fn url_for_relative<U, I, K, V>(
+        &self,
+        _: &HttpRequest,
+        name: &str,
+        elements: U,
+        query: &[(K, V)],
+    ) -> Result<String, super::error::UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut path = self.resource_path(name, elements, &mut None)?;
+        if path.starts_with('/') {
+            if !query.is_empty() {
+                path.push('?');
+                let start = path.len();
+                form_urlencoded::Serializer::for_suffix(&mut path, start)
+                    .extend_pairs(query);
+            }
+            Ok(path)
+        } else {
+            // external resources are always absolute
+            let mut url = Url::parse(&path)?;
+            if !query.is_empty() {
+                url.query_pairs_mut().extend_pairs(query);
+            }
+            Ok(url.into())
+        }
+    }
+
+    /// Generate path for named resource, all elements must be used
+    fn resource_path<U, I>(
+        &self,
+        name: &str,
+        elements: U,
+        host: &mut Option<(String, Option<u16>)>,
+    ) -> Result<String, super::error::UrlGenerationError>
     where
         U: IntoIterator<Item = I>,
         I: AsRef<str>,
@@ -65,17 +218,16 @@ impl ResourceMap {
         let mut path = String::new();
         let mut elements = elements.into_iter();
 
-        if self.patterns_for(name, &mut path, &mut elements)?.is_some() {
-            if path.starts_with('/') {
-                let conn = req.connection_info();
-                Ok(Url::parse(&format!(
-                    "{}://{}{}",
-                    conn.scheme(),
-                    conn.host(),
-                    path
-                ))?)
+        if self
+            .patterns_for(name, &mut path, &mut elements, host)?
+            .is_some()
+        {
+            if elements.next().is_some() {
+                Err(super::error::UrlGenerationError::TooManyElements(
+                    name.to_string(),
+                ))
             } else {
-                Ok(Url::parse(&path)?)
+                Ok(path)
             }
         } else {
             Err(super::error::UrlGenerationError::ResourceNotFound)
@@ -102,15 +254,16 @@ impl ResourceMap {
         name: &str,
         path: &mut String,
         elements: &mut U,
+        host: &mut Option<(String, Option<u16>)>,
     ) -> Result<Option<()>, super::error::UrlGenerationError>
     where
         U: Iterator<Item = I>,
         I: AsRef<str>,
     {
-        if self.pattern_for(name, path, elements)?.is_some() {
+        if self.pattern_for(name, path, elements, host)?.is_some() {
             Ok(Some(()))
         } else {
-            self.parent_pattern_for(name, path, elements)
+            self.parent_pattern_for(name, path, elements, host)
         }
     }
 
@@ -119,6 +272,7 @@ impl ResourceMap {
         name: &str,
         path: &mut String,
         elements: &mut U,
+        host: &mut Option<(String, Option<u16>)>,
     ) -> Result<Option<()>, super::error::UrlGenerationError>
     where
         U: Iterator<Item = I>,
@@ -126,17 +280,17 @@ impl ResourceMap {
     {
         if let Some(pattern) = self.named.get(name) {
             if pattern.pattern().starts_with('/') {
-                self.fill_root(path, elements)?;
-            }
-            if pattern.resource_path(path, elements) {
-                Ok(Some(()))
-            } else {
-                Err(super::error::UrlGenerationError::NotEnoughElements)
+                self.fill_root(name, path, elements)?;
             }
+            pattern
+                .build_path(path, elements)
+                .map_err(|e| build_error(name, e))?;
+            *host = self.host();
+            Ok(Some(()))
         } else {
             for (_, rmap) in &self.patterns {
                 if let Some(ref rmap) = rmap {
-                    if rmap.pattern_for(name, path, elements)?.is_some() {
+                    if rmap.pattern_for(name, path, elements, host)?.is_some() {
                         return Ok(Some(()));
                     }
                 }
@@ -147,6 +301,7 @@ impl ResourceMap {
 
     fn fill_root<U, I>(
         &self,
+        name: &str,
         path: &mut String,
         elements: &mut U,
     ) -> Result<(), super::error::UrlGenerationError>
@@ -155,13 +310,11 @@ impl ResourceMap {
         I: AsRef<str>,
     {
         if let Some(ref parent) = *self.parent.borrow() {
-            parent.fill_root(path, elements)?;
-        }
-        if self.root.resource_path(path, elements) {
-            Ok(())
-        } else {
-            Err(super::error::UrlGenerationError::NotEnoughElements)
+            parent.fill_root(name, path, elements)?;
         }
+        self.root
+            .build_path(path, elements)
+            .map_err(|e| build_error(name, e))
     }
 
     fn parent_pattern_for<U, I>(
@@ -169,6 +322,7 @@ impl ResourceMap {
         name: &str,
         path: &mut String,
         elements: &mut U,
+        host: &mut Option<(String, Option<u16>)>,
     ) -> Result<Option<()>, super::error::UrlGenerationError>
     where
         U: Iterator<Item = I>,
@@ -176,17 +330,28 @@ impl ResourceMap {
     {
         if let Some(ref parent) = *self.parent.borrow() {
             if let Some(pattern) = parent.named.get(name) {
-                self.fill_root(path, elements)?;
-                if pattern.resource_path(path, elements) {
-                    Ok(Some(()))
-                } else {
-                    Err(super::error::UrlGenerationError::NotEnoughElements)
-                }
+                self.fill_root(name, path, elements)?;
+                pattern
+                    .build_path(path, elements)
+                    .map_err(|e| build_error(name, e))?;
+                *host = parent.host();
+                Ok(Some(()))
             } else {
-                parent.parent_pattern_for(name, path, elements)
+                parent.parent_pattern_for(name, path, elements, host)
             }
         } else {
             Ok(None)
         }
     }
 }
+
+/// Convert path build error of resource
+#[cfg(feature = "url")]
+fn build_error(name: &str, err: BuildError) -> super::error::UrlGenerationError {
+    match err {
+        BuildError::MissingValue(param) => {
+            super::error::UrlGenerationError::MissingValue(name.to_string(), param)
+        }
+        err => super::error::UrlGenerationError::InvalidElement(name.to_string(), err),
+    }
+}
@@ -1,7 +1,8 @@
 use std::{fmt, mem, rc::Rc};
 
+use crate::http::{Method, RequestHead};
 use crate::util::{BoxFuture, Ready};
-use crate::{http::Method, service::Service, service::ServiceCtx, service::ServiceFactory};
+use crate::{service::Service, service::ServiceCtx, service::ServiceFactory};
 
 use super::error::ErrorRenderer;
 use super::error_default::DefaultError;
@@ -87,6 +88,29 @@ impl<Err: ErrorRenderer> RouteService<Err> {
 
         self.guards.check(req.head())
     }
+
+    /// Check route guards that do not restrict request method
+    pub(super) fn check_guards(&self, req: &RequestHead) -> bool {
+        self.guards.check_non_method(req)
+    }
+
+    /// Methods accepted by route methods and method guards
+    ///
+    /// Returns `None` if route does not restrict request method.
+    pub(super) fn methods(&self) -> Option<Vec<Method>> {
+        match self.guards.methods() {
+            Some(guards) if !self.methods.is_empty() => Some(
+                self.methods
+                    .iter()
+                    .filter(|m| guards.contains(m))
+                    .cloned()
+                    .collect(),
+            ),
+            Some(guards) => Some(guards),
+            None if !self.methods.is_empty() => Some(self.methods.clone()),
+            None => None,
+        }
+    }
 }
 
 impl<Err: ErrorRenderer> fmt::Debug for RouteService<Err> {
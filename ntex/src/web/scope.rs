@@ -16,12 +16,15 @@ use super::config::ServiceConfig;
 use super::dev::{WebServiceConfig, WebServiceFactory};
 use super::error::ErrorRenderer;
 use super::guard::Guard;
+use super::middleware::{Next, WrapFn};
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::route::Route;
-use super::service::{AppServiceFactory, AppState, ServiceFactoryWrapper};
+use super::service::{
+    insert_keyed_state, AppServiceFactory, AppState, ServiceFactoryWrapper,
+};
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpService<Err: ErrorRenderer> =
@@ -70,6 +73,7 @@ pub struct Scope<Err: ErrorRenderer, M = Identity, T = Filter<Err>> {
     default: Rc<RefCell<Option<Rc<HttpNewService<Err>>>>>,
     external: Vec<ResourceDef>,
     case_insensitive: bool,
+    name_prefix: String,
 }
 
 impl<Err: ErrorRenderer> Scope<Err> {
@@ -85,6 +89,7 @@ impl<Err: ErrorRenderer> Scope<Err> {
             default: Rc::new(RefCell::new(None)),
             external: Vec::new(),
             case_insensitive: false,
+            name_prefix: String::new(),
         }
     }
 }
@@ -101,6 +106,11 @@ where
 {
     /// Add match guard to a scope.
     ///
+    /// Guards are checked before any resource of the scope is considered.
+    /// If a guard does not match, the scope is skipped and matching continues
+    /// with next services, as if the scope is not registered. Guards of nested
+    /// scopes are checked after guards of outer scope.
+    ///
     /// ```rust
     /// use ntex::web::{self, guard, App, HttpRequest, HttpResponse};
     ///
@@ -158,14 +168,52 @@ where
         self
     }
 
+    /// Set or override keyed application state.
+    ///
+    /// Keyed state of the scope shadows application state with the same key.
+    pub fn state_keyed<D: 'static>(mut self, key: &'static str, st: D) -> Self {
+        insert_keyed_state(self.state.get_or_insert_with(Extensions::new), key, st);
+        self
+    }
+
     /// Use ascii case-insensitive routing.
     ///
-    /// Only static segments could be case-insensitive.
+    /// Static segments and static parts of dynamic segments are matched
+    /// case-insensitively, values of dynamic segments keep original case.
+    /// Scope uses case-insensitive routing if it is enabled for parent
+    /// scope or application.
     pub fn case_insensitive_routing(mut self) -> Self {
         self.case_insensitive = true;
         self
     }
 
+    /// Set prefix for names of resources registered in the scope.
+    ///
+    /// Prefix applies to named resources and external resources of the
+    /// scope and of all nested scopes. Prefixes of nested scopes are
+    /// concatenated.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpRequest, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::scope("/api")
+    ///             .name_prefix("api.")
+    ///             .service(web::resource("/users").name("users").to(
+    ///                 |req: HttpRequest| async move {
+    ///                     // resource name is "api.users"
+    ///                     HttpResponse::Ok().body(req.match_name().unwrap().to_string())
+    ///                 },
+    ///             )),
+    ///     );
+    /// }
+    /// ```
+    pub fn name_prefix(mut self, prefix: &str) -> Self {
+        self.name_prefix = prefix.to_string();
+        self
+    }
+
     /// Run external configuration as part of the scope building
     /// process
     ///
@@ -278,7 +326,10 @@ where
 
     /// Default service to be used if no matching route could be found.
     ///
-    /// If default resource is not registered, app's default resource is being used.
+    /// Default service is called if request path matches scope prefix, but
+    /// no resource of the scope matches. Request path is not modified and
+    /// scope state is available. If default resource is not registered,
+    /// default resource of the outer scope or app's default resource is being used.
     pub fn default_service<F, S>(mut self, f: F) -> Self
     where
         F: IntoServiceFactory<S, WebRequest<Err>>,
@@ -334,6 +385,7 @@ where
             default: self.default,
             external: self.external,
             case_insensitive: self.case_insensitive,
+            name_prefix: self.name_prefix,
         }
     }
 
@@ -358,8 +410,22 @@ where
             default: self.default,
             external: self.external,
             case_insensitive: self.case_insensitive,
+            name_prefix: self.name_prefix,
         }
     }
+
+    /// Register a scope middleware function.
+    ///
+    /// Function receives request and wrapped service, see
+    /// [`WrapFn`](super::middleware::WrapFn). Middleware functions get called
+    /// in the same order as middlewares.
+    pub fn wrap_fn<F, R>(self, f: F) -> Scope<Err, Stack<M, WrapFn<F, Err>>, T>
+    where
+        F: Fn(WebRequest<Err>, Next<Err>) -> R,
+        R: Future<Output = Result<WebResponse, Err::Container>>,
+    {
+        self.wrap(WrapFn::new(f))
+    }
 }
 
 impl<Err, M, T> WebServiceFactory<Err> for Scope<Err, M, T>
@@ -388,17 +454,24 @@ where
             )
         });
 
-        // register nested services
-        let mut cfg = config.clone_config(state.clone());
+        // register nested services, nested scopes use default service of this scope
+        let default = self.default.borrow().clone().unwrap();
+        let mut cfg = config.clone_config(state.clone(), default);
+        cfg.add_name_prefix(&self.name_prefix);
+        if self.case_insensitive {
+            cfg.set_case_insensitive();
+        }
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
 
         let slesh = self.rdef.iter().any(|s| s.ends_with('/'));
         let mut rmap = ResourceMap::new(ResourceDef::root_prefix(self.rdef.clone()));
+        rmap.set_host(self.guards.iter().find_map(|g| g.host()));
 
         // external resources
         for mut rdef in std::mem::take(&mut self.external) {
+            *rdef.name_mut() = format!("{}{}", cfg.name_prefix(), rdef.name());
             rmap.add(&mut rdef, None);
         }
 
@@ -406,7 +479,8 @@ where
         let router_factory = ScopeRouterFactory {
             state,
             default: self.default.borrow_mut().take(),
-            case_insensitive: self.case_insensitive,
+            case_insensitive: cfg.case_insensitive(),
+            rank_by_specificity: cfg.rank_by_specificity(),
             services: cfg
                 .into_services()
                 .into_iter()
@@ -562,6 +636,7 @@ struct ScopeRouterFactory<Err: ErrorRenderer> {
     services: Vec<(ResourceDef, HttpNewService<Err>, RefCell<Option<Guards>>)>,
     default: Option<Rc<HttpNewService<Err>>>,
     case_insensitive: bool,
+    rank_by_specificity: bool,
 }
 
 impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ScopeRouterFactory<Err> {
@@ -578,6 +653,9 @@ impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for ScopeRouterFactory<
             if self.case_insensitive {
                 router.case_insensitive();
             }
+            if self.rank_by_specificity {
+                router.rank_by_specificity();
+            }
             for (path, factory, guards) in &mut self.services.iter() {
                 let service = factory.create(()).await?;
                 router.rdef(path.clone(), service).2 = guards.borrow_mut().take();
@@ -631,6 +709,9 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ScopeRouter<Err> {
             }
             Either::Left(ctx.call(srv, req))
         } else if let Some(ref default) = self.default {
+            if let Some(ref state) = self.state {
+                req.set_state_container(state.clone());
+            }
             Either::Left(ctx.call(default, req))
         } else {
             let req = req.into_parts().0;
@@ -645,7 +726,7 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for ScopeRouter<Err> {
 #[cfg(test)]
 mod tests {
     use crate::http::body::{Body, ResponseBody};
-    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::header::{self, HeaderValue, CONTENT_TYPE};
     use crate::http::{Method, StatusCode};
     use crate::service::fn_service;
     use crate::util::{Bytes, Ready};
@@ -912,6 +993,152 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
     }
 
+    #[crate::rt_test]
+    async fn test_virtual_hosts() {
+        async fn index(req: HttpRequest) -> HttpResponse {
+            #[cfg(feature = "url")]
+            {
+                let a = req.url_for("a.index", [] as [&str; 0]).unwrap();
+                let b = req.url_for("b.index", [] as [&str; 0]).unwrap();
+                HttpResponse::Ok().body(format!("{} {}", a, b))
+            }
+            #[cfg(not(feature = "url"))]
+            HttpResponse::Ok().body(req.match_name().unwrap().to_string())
+        }
+
+        let srv = init_service(
+            App::new()
+                .trusted_proxies(["10.0.0.1"])
+                .service(
+                    web::scope("/app")
+                        .guard(guard::Host("a.example.com"))
+                        .name_prefix("a.")
+                        .service(web::resource("/index").name("index").to(index)),
+                )
+                .service(
+                    web::scope("/app")
+                        .guard(guard::Host("b.example.com:8443"))
+                        .name_prefix("b.")
+                        .service(web::resource("/index").name("index").to(index)),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/index")
+            .header(header::HOST, "a.example.com:8080")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        #[cfg(feature = "url")]
+        assert_eq!(
+            read_body(resp).await,
+            "http://a.example.com:8080/app/index http://b.example.com:8443/app/index"
+        );
+        #[cfg(not(feature = "url"))]
+        assert_eq!(read_body(resp).await, "a.index");
+
+        let req = TestRequest::with_uri("/app/index")
+            .header(header::HOST, "b.example.com:8443")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        #[cfg(feature = "url")]
+        assert_eq!(
+            read_body(resp).await,
+            "http://a.example.com/app/index http://b.example.com:8443/app/index"
+        );
+        #[cfg(not(feature = "url"))]
+        assert_eq!(read_body(resp).await, "b.index");
+
+        let req = TestRequest::with_uri("/app/index")
+            .header(header::HOST, "c.example.com")
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // forwarded host of trusted proxy
+        let req = TestRequest::with_uri("/app/index")
+            .header(header::HOST, "internal")
+            .header("x-forwarded-host", "a.example.com")
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // forwarded host is ignored for untrusted peer
+        let req = TestRequest::with_uri("/app/index")
+            .header(header::HOST, "internal")
+            .header("x-forwarded-host", "a.example.com")
+            .peer_addr("10.0.0.2:8080".parse().unwrap())
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[crate::rt_test]
+    async fn test_scope_guard_fallthrough() {
+        use crate::http::header;
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/admin")
+                        .guard(guard::Host("admin.example.com"))
+                        .guard(guard::Header("x-admin", "1"))
+                        .service(
+                            web::scope("/users")
+                                .guard(guard::Not(guard::Header("x-readonly", "1")))
+                                .route(
+                                    "",
+                                    web::post().to(|| async { HttpResponse::Created() }),
+                                ),
+                        )
+                        .route("/users", web::get().to(|| async { HttpResponse::Ok() })),
+                )
+                .service(
+                    web::resource("/admin/users")
+                        .route(web::delete().to(|| async { HttpResponse::NoContent() })),
+                ),
+        )
+        .await;
+
+        let admin = |method: Method| {
+            TestRequest::with_uri("/admin/users")
+                .method(method)
+                .header(header::HOST, "admin.example.com")
+                .header("x-admin", "1")
+        };
+
+        // outer and nested guards match
+        let resp = call_service(&srv, admin(Method::POST).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        // nested guard fails, outer scope resources are checked
+        let req = admin(Method::GET).header("x-readonly", "1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // outer guard fails, scope is transparent
+        let req = TestRequest::with_uri("/admin/users")
+            .method(Method::POST)
+            .header(header::HOST, "example.com")
+            .header("x-admin", "1")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("DELETE")
+        );
+
+        let req = TestRequest::with_uri("/admin/users")
+            .method(Method::DELETE)
+            .header(header::HOST, "admin.example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
     #[crate::rt_test]
     async fn test_scope_variable_segment() {
         let srv = init_service(App::new().service(web::scope("/ab-{project}").service(
@@ -1146,6 +1373,50 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
     }
 
+    #[crate::rt_test]
+    async fn test_nested_default_resource() {
+        async fn default(req: HttpRequest, st: web::types::State<&'static str>) -> String {
+            format!("{} {}", *st, req.path())
+        }
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .state("api")
+                        .service(
+                            web::scope("/v1")
+                                .route("/test", web::get().to(|| async { "ok" })),
+                        )
+                        .service(
+                            web::scope("/v2")
+                                .state("v2")
+                                .default_service(web::to(default)),
+                        )
+                        .default_service(web::to(default)),
+                )
+                .default_service(|r: WebRequest<DefaultError>| async move {
+                    Ok(r.into_response(HttpResponse::Ok().body("index")))
+                }),
+        )
+        .await;
+
+        let check = |path: &'static str, body: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = call_service(srv, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert_eq!(read_body(resp).await, Bytes::from_static(body.as_bytes()));
+            }
+        };
+        check("/api/v1/test", "ok").await;
+        check("/api/unknown", "api /api/unknown").await;
+        check("/api/v1/unknown", "api /api/v1/unknown").await;
+        check("/api/v2/unknown", "v2 /api/v2/unknown").await;
+        check("/unknown", "index").await;
+    }
+
     #[crate::rt_test]
     async fn test_filter() {
         let filter = std::rc::Rc::new(std::cell::Cell::new(false));
@@ -1167,6 +1438,29 @@ mod tests {
         assert!(filter.get());
     }
 
+    #[crate::rt_test]
+    async fn test_wrap_fn() {
+        let srv = init_service(
+            App::new().service(
+                web::scope("/app")
+                    .wrap_fn(|req, srv| async move {
+                        let path = req.path().to_string();
+                        let mut res = srv.call(req).await?;
+                        res.headers_mut()
+                            .insert(CONTENT_TYPE, HeaderValue::try_from(path).unwrap());
+                        Ok(res)
+                    })
+                    .service(web::resource("/test").to(|| async { HttpResponse::Ok() })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "/app/test");
+    }
+
     #[crate::rt_test]
     async fn test_middleware() {
         let srv = init_service(
@@ -1291,4 +1585,145 @@ mod tests {
             Bytes::from_static(b"http://localhost:8080/a/b/c/12345")
         );
     }
+
+    #[cfg(feature = "url")]
+    #[crate::rt_test]
+    async fn test_url_for_tail() {
+        let srv = init_service(
+            App::new().service(
+                web::scope("/static").service(
+                    web::resource("/{tail}*")
+                        .name("static")
+                        .route(web::get().to(
+                        |req: HttpRequest, path: web::types::Path<(String,)>| async move {
+                            HttpResponse::Ok().body(format!(
+                                "{} {}",
+                                path.into_inner().0,
+                                req.url_for("static", ["css/main.css"]).unwrap()
+                            ))
+                        },
+                    )),
+                ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/img/a%20b.png").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        assert_eq!(
+            body,
+            Bytes::from_static(b"img/a b.png http://localhost:8080/static/css/main.css")
+        );
+
+        let req = TestRequest::with_uri("/static/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "url")]
+    #[crate::rt_test]
+    async fn test_url_for_name_prefix() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api").name_prefix("api.").service(
+                        web::scope("/{version}")
+                            .name_prefix("v.")
+                            .configure(|cfg| {
+                                cfg.external_resource("docs", "https://docs.rs/{crate}");
+                            })
+                            .service(web::resource("/users/{id}").name("user").to(
+                                |req: HttpRequest| async move {
+                                    HttpResponse::Ok()
+                                        .body(req.match_name().unwrap().to_string())
+                                },
+                            )),
+                    ),
+                )
+                .service(web::resource("/links").name("links").to(
+                    |req: HttpRequest| async move {
+                        HttpResponse::Ok().body(format!(
+                            "{} {} {}",
+                            req.match_name().unwrap(),
+                            req.url_for("api.v.user", ["v1", "5"]).unwrap(),
+                            req.url_for("api.v.docs", ["ntex"]).unwrap(),
+                        ))
+                    },
+                )),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/links").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"links http://localhost:8080/api/v1/users/5 https://docs.rs/ntex"
+            )
+        );
+
+        let req = TestRequest::with_uri("/api/v2/users/1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"api.v.user"));
+    }
+
+    #[crate::rt_test]
+    async fn test_duplicate_names() {
+        use crate::service::ServiceFactory;
+        use crate::web::config::AppConfig;
+
+        let app = App::new()
+            .service(
+                web::scope("/a")
+                    .service(web::resource("/index").name("index").to(|| async { "a" })),
+            )
+            .service(
+                web::scope("/b")
+                    .service(web::resource("/index").name("index").to(|| async { "b" })),
+            );
+        assert!(app
+            .with_config(AppConfig::default())
+            .create(())
+            .await
+            .is_err());
+
+        // same pattern is not a collision
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/index")
+                        .name("index")
+                        .guard(guard::Get())
+                        .to(|| async { "get" }),
+                )
+                .service(web::resource("/index").name("index").to(|| async { "any" })),
+        )
+        .await;
+        let req = TestRequest::with_uri("/index").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"get"));
+
+        // name prefix makes names unique
+        let srv =
+            init_service(
+                App::new()
+                    .service(web::scope("/a").name_prefix("a.").service(
+                        web::resource("/index").name("index").to(|| async { "a" }),
+                    ))
+                    .service(web::scope("/b").name_prefix("b.").service(
+                        web::resource("/index").name("index").to(|| async { "b" }),
+                    )),
+            )
+            .await;
+        let req = TestRequest::with_uri("/b/index").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"b"));
+    }
 }
@@ -1,4 +1,4 @@
-use std::{fmt, io, marker::PhantomData, net, sync::Arc, sync::Mutex};
+use std::{fmt, future::Future, io, marker::PhantomData, net, sync::Arc, sync::Mutex};
 
 #[cfg(feature = "openssl")]
 use tls_openssl::ssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
@@ -6,9 +6,9 @@ use tls_openssl::ssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 use tls_rustls::ServerConfig as RustlsServerConfig;
 
 use crate::http::{
-    body::MessageBody, HttpService, KeepAlive, Request, Response, ResponseError,
+    body::MessageBody, h1, HttpService, KeepAlive, Request, Response, ResponseError,
 };
-use crate::server::{Server, ServerBuilder};
+use crate::server::{Server, ServerBuilder, ServerMetrics};
 use crate::service::{map_config, IntoServiceFactory, ServiceFactory};
 use crate::{time::Seconds, util::PoolId};
 
@@ -20,6 +20,10 @@ struct Config {
     client_timeout: Seconds,
     client_disconnect: Seconds,
     handshake_timeout: Seconds,
+    headers_max_size: usize,
+    headers_max_count: usize,
+    payload_max_size: usize,
+    pipeline_max: usize,
     pool: PoolId,
 }
 
@@ -53,6 +57,7 @@ where
     pub(super) factory: F,
     config: Arc<Mutex<Config>>,
     backlog: i32,
+    reuse_port: bool,
     builder: ServerBuilder,
     _t: PhantomData<(S, B)>,
 }
@@ -77,9 +82,14 @@ where
                 client_timeout: Seconds(5),
                 client_disconnect: Seconds(5),
                 handshake_timeout: Seconds(5),
+                headers_max_size: h1::MAX_BUFFER_SIZE,
+                headers_max_count: h1::MAX_HEADERS,
+                payload_max_size: 0,
+                pipeline_max: 1,
                 pool: PoolId::P0,
             })),
             backlog: 1024,
+            reuse_port: false,
             builder: ServerBuilder::default(),
             _t: PhantomData,
         }
@@ -94,6 +104,19 @@ where
         self
     }
 
+    /// Set number of workers for the last `bind*()` or `listen*()` call.
+    ///
+    /// Connections for last bound addresses are distributed only between
+    /// first `num` workers. See [`ServerBuilder::bind_workers()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num` is 0 or if method is called before any bind call.
+    pub fn bind_workers(mut self, num: usize) -> Self {
+        self.builder = self.builder.bind_workers(num);
+        self
+    }
+
     /// Set the maximum number of pending connections.
     ///
     /// This refers to the number of clients that can be waiting to be served.
@@ -110,28 +133,57 @@ where
         self
     }
 
+    /// Set `SO_REUSEPORT` option for bound sockets.
+    ///
+    /// Allows multiple independent processes to bind the same address.
+    /// On unsupported platforms warning is logged and option is ignored.
+    ///
+    /// This method should be called before `bind()` method call.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self.builder = self.builder.reuse_port(enabled);
+        self
+    }
+
     /// Sets the maximum per-worker number of concurrent connections.
     ///
-    /// All socket listeners will stop accepting connections when this limit is reached
-    /// for each worker.
+    /// Worker stops receiving new connections when this limit is reached. If
+    /// all workers are at the limit, listeners stop accepting connections and
+    /// pending connections wait in listen backlog until any worker gets
+    /// capacity.
     ///
     /// By default max connections is set to a 25k.
-    pub fn maxconn(mut self, num: usize) -> Self {
-        self.builder = self.builder.maxconn(num);
+    pub fn max_connections(mut self, num: usize) -> Self {
+        self.builder = self.builder.max_connections(num);
         self
     }
 
-    /// Sets the maximum per-worker concurrent connection establish process.
+    /// Sets the maximum per-worker number of concurrent tls handshakes.
     ///
-    /// All listeners will stop accepting connections when this limit is reached. It
-    /// can be used to limit the global SSL CPU usage.
+    /// Tls listeners stop accepting connections when this limit is reached,
+    /// plain listeners are not affected. It can be used to limit the global
+    /// SSL CPU usage. Limit is shared by all servers in the process.
     ///
-    /// By default max connections is set to a 256.
-    pub fn maxconnrate(self, num: usize) -> Self {
-        ntex_tls::max_concurrent_ssl_accept(num);
+    /// By default max connection rate is set to 256.
+    pub fn max_connection_rate(mut self, num: usize) -> Self {
+        self.builder = self.builder.max_connection_rate(num);
         self
     }
 
+    /// Sets the maximum per-worker number of concurrent connections.
+    ///
+    /// Same as [`HttpServer::max_connections()`].
+    pub fn maxconn(self, num: usize) -> Self {
+        self.max_connections(num)
+    }
+
+    /// Sets the maximum per-worker concurrent connection establish process.
+    ///
+    /// Same as [`HttpServer::max_connection_rate()`].
+    pub fn maxconnrate(self, num: usize) -> Self {
+        self.max_connection_rate(num)
+    }
+
     /// Set server keep-alive setting.
     ///
     /// By default keep alive is set to a 5 seconds.
@@ -167,6 +219,49 @@ where
         self
     }
 
+    /// Set max size of request head in bytes.
+    ///
+    /// Requests with larger head get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max size is set to 32Kb.
+    pub fn max_header_size(self, size: usize) -> Self {
+        self.config.lock().unwrap().headers_max_size = size;
+        self
+    }
+
+    /// Set max number of request headers.
+    ///
+    /// Requests with more headers get rejected with
+    /// the 431 (Request Header Fields Too Large) error.
+    ///
+    /// By default max number of headers is set to 96.
+    pub fn max_headers(self, count: usize) -> Self {
+        self.config.lock().unwrap().headers_max_count = count;
+        self
+    }
+
+    /// Set max size of request payload in bytes.
+    ///
+    /// Requests with larger declared content-length get rejected with
+    /// the 413 (Payload Too Large) error.
+    ///
+    /// By default payload size is not limited. To disable limit set value to 0.
+    pub fn max_payload_size(self, size: usize) -> Self {
+        self.config.lock().unwrap().payload_max_size = size;
+        self
+    }
+
+    /// Set max number of pipelined requests handled concurrently.
+    ///
+    /// Responses are always sent in the order of requests.
+    ///
+    /// By default requests are handled one by one.
+    pub fn max_pipelined_requests(self, max: usize) -> Self {
+        self.config.lock().unwrap().pipeline_max = max;
+        self
+    }
+
     /// Set server ssl handshake timeout in seconds.
     ///
     /// Defines a timeout for connection ssl handshake negotiation.
@@ -218,6 +313,63 @@ where
         self
     }
 
+    /// Register graceful shutdown hook.
+    ///
+    /// Hook is called on graceful shutdown before workers start draining
+    /// connections, server accepts new connections until returned future
+    /// resolves. Handlers could use `ShutdownSignal` extractor to get
+    /// notified when worker starts shutdown.
+    ///
+    /// ```rust,no_run
+    /// use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+    /// use ntex::{time::sleep, time::Millis, time::Seconds};
+    /// use ntex::web::{self, App, HttpResponse, HttpServer};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     let healthy = Arc::new(AtomicBool::new(true));
+    ///     let healthy2 = healthy.clone();
+    ///
+    ///     HttpServer::new(move || {
+    ///         let healthy = healthy.clone();
+    ///         App::new().route("/health", web::get().to(move || {
+    ///             let ok = healthy.load(Ordering::Relaxed);
+    ///             async move {
+    ///                 if ok { HttpResponse::Ok() } else { HttpResponse::ServiceUnavailable() }
+    ///             }
+    ///         }))
+    ///     })
+    ///     .shutdown_timeout(Seconds(60))
+    ///     .on_shutdown(move || async move {
+    ///         // fail health checks, give load balancer time to notice
+    ///         healthy2.store(false, Ordering::Relaxed);
+    ///         sleep(Millis(5_000)).await;
+    ///     })
+    ///     .bind("127.0.0.1:8080")?
+    ///     .run()
+    ///     .await
+    /// }
+    /// ```
+    pub fn on_shutdown<U, R>(mut self, f: U) -> Self
+    where
+        U: FnOnce() -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.builder = self.builder.on_shutdown(f);
+        self
+    }
+
+    /// Register server metrics hooks.
+    ///
+    /// See [`ServerBuilder::metrics()`].
+    pub fn metrics<M>(mut self, hooks: M) -> Self
+    where
+        M: ServerMetrics + 'static,
+    {
+        self.builder = self.builder.metrics(hooks);
+        self
+    }
+
     /// Set memory pool.
     ///
     /// Use specified memory pool for memory allocations.
@@ -250,6 +402,10 @@ where
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
                         .disconnect_timeout(c.client_disconnect)
+                        .max_header_size(c.headers_max_size)
+                        .max_headers(c.headers_max_count)
+                        .max_payload_size(c.payload_max_size)
+                        .max_pipelined_requests(c.pipeline_max)
                         .finish(map_config(factory(), move |_| cfg.clone()))
                 })?;
         Ok(self)
@@ -292,9 +448,13 @@ where
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
                         .disconnect_timeout(c.client_disconnect)
+                        .max_header_size(c.headers_max_size)
+                        .max_headers(c.headers_max_count)
+                        .max_payload_size(c.payload_max_size)
+                        .max_pipelined_requests(c.pipeline_max)
                         .ssl_handshake_timeout(c.handshake_timeout)
                         .finish(map_config(factory(), move |_| cfg.clone()))
-                        .openssl(acceptor.clone())
+                        .openssl_on_error(acceptor.clone(), r.handshake_error_hook())
                 })?;
         Ok(self)
     }
@@ -337,23 +497,37 @@ where
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
                     .disconnect_timeout(c.client_disconnect)
+                    .max_header_size(c.headers_max_size)
+                    .max_headers(c.headers_max_count)
+                    .max_payload_size(c.payload_max_size)
+                    .max_pipelined_requests(c.pipeline_max)
                     .ssl_handshake_timeout(c.handshake_timeout)
                     .finish(map_config(factory(), move |_| cfg.clone()))
-                    .rustls(config.clone())
+                    .rustls_on_error(config.clone(), r.handshake_error_hook())
             },
         )?;
         Ok(self)
     }
 
+    /// Get addresses of bound tcp listeners.
+    ///
+    /// Addresses of listeners added with `bind*()` and `listen*()` methods,
+    /// i.e. to get actual port of listener bound to port `0`.
+    pub fn addrs(&self) -> Vec<net::SocketAddr> {
+        self.builder.addrs()
+    }
+
     /// The socket address to bind
     ///
     /// To bind multiple addresses this method can be called multiple times.
     pub fn bind<A: net::ToSocketAddrs>(mut self, addr: A) -> io::Result<Self> {
         let sockets = self.bind2(addr)?;
 
+        let mark = self.builder.bind_mark();
         for lst in sockets {
             self = self.listen(lst)?;
         }
+        self.builder = self.builder.set_last_bind(mark);
 
         Ok(self)
     }
@@ -363,7 +537,7 @@ where
         let mut succ = false;
         let mut sockets = Vec::new();
         for addr in addr.to_socket_addrs()? {
-            match crate::server::create_tcp_listener(addr, self.backlog) {
+            match crate::server::create_tcp_listener(addr, self.backlog, self.reuse_port) {
                 Ok(lst) => {
                     succ = true;
                     sockets.push(lst);
@@ -401,9 +575,11 @@ where
         let sockets = self.bind2(addr)?;
         let acceptor = openssl_acceptor(builder)?;
 
+        let mark = self.builder.bind_mark();
         for lst in sockets {
             self = self.listen_ssl_inner(lst, acceptor.clone())?;
         }
+        self.builder = self.builder.set_last_bind(mark);
 
         Ok(self)
     }
@@ -418,16 +594,18 @@ where
         config: RustlsServerConfig,
     ) -> io::Result<Self> {
         let sockets = self.bind2(addr)?;
+        let mark = self.builder.bind_mark();
         for lst in sockets {
             self = self.listen_rustls_inner(lst, config.clone())?;
         }
+        self.builder = self.builder.set_last_bind(mark);
         Ok(self)
     }
 
     #[cfg(unix)]
     /// Start listening for unix domain connections on existing listener.
     ///
-    /// This method is available with `uds` feature.
+    /// This method is available on unix platforms only.
     pub fn listen_uds(mut self, lst: std::os::unix::net::UnixListener) -> io::Result<Self> {
         let cfg = self.config.clone();
         let factory = self.factory.clone();
@@ -448,6 +626,10 @@ where
             HttpService::build()
                 .keep_alive(c.keep_alive)
                 .client_timeout(c.client_timeout)
+                .max_header_size(c.headers_max_size)
+                .max_headers(c.headers_max_count)
+                .max_payload_size(c.payload_max_size)
+                .max_pipelined_requests(c.pipeline_max)
                 .finish(map_config(factory(), move |_| config.clone()))
         })?;
         Ok(self)
@@ -456,7 +638,7 @@ where
     #[cfg(unix)]
     /// Start listening for incoming unix domain connections.
     ///
-    /// This method is available with `uds` feature.
+    /// This method is available on unix platforms only.
     pub fn bind_uds<A>(mut self, addr: A) -> io::Result<Self>
     where
         A: AsRef<std::path::Path>,
@@ -481,6 +663,10 @@ where
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
+                    .max_header_size(c.headers_max_size)
+                    .max_headers(c.headers_max_count)
+                    .max_payload_size(c.payload_max_size)
+                    .max_pipelined_requests(c.pipeline_max)
                     .finish(map_config(factory(), move |_| config.clone()))
             },
         )?;
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::{boxed, IntoServiceFactory, ServiceFactory};
@@ -84,6 +84,21 @@ impl AppState {
         }
     }
 
+    pub(crate) fn get_keyed<T: 'static>(&self, key: &str) -> Option<&T> {
+        let result = self
+            .0
+            .ext
+            .get::<KeyedStates<T>>()
+            .and_then(|states| states.0.get(key));
+        if result.is_some() {
+            result
+        } else if let Some(parent) = self.0.parent.as_ref() {
+            parent.get_keyed::<T>(key)
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn contains<T: 'static>(&self) -> bool {
         if self.0.ext.contains::<T>() {
             true
@@ -95,10 +110,31 @@ impl AppState {
     }
 }
 
+/// Storage for keyed state values of type `T`
+struct KeyedStates<T>(HashMap<&'static str, T>);
+
+/// Store keyed state value, existing value with the same key gets replaced
+pub(crate) fn insert_keyed_state<T: 'static>(
+    ext: &mut Extensions,
+    key: &'static str,
+    state: T,
+) {
+    if let Some(states) = ext.get_mut::<KeyedStates<T>>() {
+        states.0.insert(key, state);
+    } else {
+        let mut states = HashMap::new();
+        states.insert(key, state);
+        ext.insert(KeyedStates(states));
+    }
+}
+
 /// Application service configuration
 pub struct WebServiceConfig<Err: ErrorRenderer> {
     state: AppState,
     root: bool,
+    name_prefix: String,
+    case_insensitive: bool,
+    rank_by_specificity: bool,
     default: Rc<HttpServiceFactory<Err>>,
     services: Vec<(
         ResourceDef,
@@ -115,6 +151,9 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             state,
             default,
             root: true,
+            name_prefix: String::new(),
+            case_insensitive: false,
+            rank_by_specificity: false,
             services: Vec::new(),
         }
     }
@@ -139,15 +178,51 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
         self.services
     }
 
-    pub(crate) fn clone_config(&self, state: Option<AppState>) -> Self {
+    pub(crate) fn clone_config(
+        &self,
+        state: Option<AppState>,
+        default: Rc<HttpServiceFactory<Err>>,
+    ) -> Self {
         WebServiceConfig {
             state: state.unwrap_or_else(|| self.state.clone()),
-            default: self.default.clone(),
+            default,
             services: Vec::new(),
             root: false,
+            name_prefix: self.name_prefix.clone(),
+            case_insensitive: self.case_insensitive,
+            rank_by_specificity: self.rank_by_specificity,
         }
     }
 
+    /// Prefix for names of registered resources.
+    ///
+    /// Prefix is a concatenation of name prefixes of all parent scopes.
+    pub fn name_prefix(&self) -> &str {
+        &self.name_prefix
+    }
+
+    pub(super) fn add_name_prefix(&mut self, prefix: &str) {
+        self.name_prefix.push_str(prefix);
+    }
+
+    /// Check if case-insensitive routing is enabled for application
+    pub(super) fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    pub(super) fn set_case_insensitive(&mut self) {
+        self.case_insensitive = true;
+    }
+
+    /// Check if routes are matched by specificity
+    pub(super) fn rank_by_specificity(&self) -> bool {
+        self.rank_by_specificity
+    }
+
+    pub(super) fn set_rank_by_specificity(&mut self) {
+        self.rank_by_specificity = true;
+    }
+
     /// Service configuration
     pub fn config(&self) -> &AppConfig {
         self.state.config()
@@ -0,0 +1,384 @@
+//! Server-Sent Events support
+//!
+//! ```rust
+//! use futures_util::stream;
+//! use ntex::time::Seconds;
+//! use ntex::web::{self, sse, App, Responder};
+//!
+//! async fn events(last_id: sse::LastEventId) -> impl Responder {
+//!     let start: u64 = last_id.as_str().and_then(|id| id.parse().ok()).unwrap_or(0);
+//!     let events = stream::iter((start..start + 3).map(|id| {
+//!         sse::Event::new(format!("message {}", id)).id(id.to_string())
+//!     }));
+//!     sse::SseStream::new(events).heartbeat(Seconds(15))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/events", web::get().to(events));
+//! }
+//! ```
+use std::{error::Error, fmt, pin::Pin, task::Context, task::Poll};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::{self, ContentEncoding, HeaderValue};
+use crate::http::{Payload, Response, StatusCode};
+use crate::time::{Millis, Sleep};
+use crate::util::{Bytes, BytesMut, Stream};
+use crate::web::error::ErrorRenderer;
+use crate::web::responder::{Ready, Responder};
+use crate::web::{BodyEncoding, FromRequest, HttpRequest};
+
+/// Server-sent event.
+///
+/// Multi-line data is sent as multiple `data` fields, line breaks in
+/// event name and id are removed.
+///
+/// ```rust
+/// use ntex::web::sse::Event;
+///
+/// let event = Event::new("first line\nsecond line").event("update").id("42");
+/// assert_eq!(
+///     event.to_bytes(),
+///     "event: update\nid: 42\ndata: first line\ndata: second line\n\n"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Millis>,
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Create event with data.
+    pub fn new<T: Into<String>>(data: T) -> Self {
+        Event {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create comment, comments are ignored by clients.
+    pub fn comment<T: Into<String>>(comment: T) -> Self {
+        Event {
+            comment: Some(comment.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set event name.
+    pub fn event<T: Into<String>>(mut self, event: T) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set event id.
+    ///
+    /// Client sends last received id with `Last-Event-ID` header on reconnect.
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set client reconnection time.
+    pub fn retry<T: Into<Millis>>(mut self, retry: T) -> Self {
+        self.retry = Some(retry.into());
+        self
+    }
+
+    /// Encode event to wire format.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        self.encode(&mut buf);
+        buf.freeze()
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        if let Some(ref comment) = self.comment {
+            for line in lines(comment) {
+                write_field(buf, "", line);
+            }
+        }
+        if let Some(ref event) = self.event {
+            write_field(buf, "event", &single_line(event));
+        }
+        if let Some(ref id) = self.id {
+            write_field(buf, "id", &single_line(id));
+        }
+        if let Some(retry) = self.retry {
+            write_field(buf, "retry", &retry.0.to_string());
+        }
+        if let Some(ref data) = self.data {
+            for line in lines(data) {
+                write_field(buf, "data", line);
+            }
+        }
+        buf.extend_from_slice(b"\n");
+    }
+}
+
+/// Split text to lines, any of `\r\n`, `\n` or `\r` ends line
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], "")
+}
+
+fn write_field(buf: &mut BytesMut, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(b": ");
+    buf.extend_from_slice(value.as_bytes());
+    buf.extend_from_slice(b"\n");
+}
+
+/// Server-sent events responder.
+///
+/// Wraps stream of events into streaming response with
+/// `text/event-stream` content type. Response is not compressed and not
+/// cached, `X-Accel-Buffering: no` header disables buffering in nginx.
+///
+/// Stream is dropped as soon as response body is dropped, i.e. when client
+/// disconnects. Heartbeats help to detect disconnected clients on idle
+/// streams and keep proxies from closing connection.
+pub struct SseStream<S> {
+    stream: S,
+    heartbeat: Option<Millis>,
+}
+
+impl<S> SseStream<S>
+where
+    S: Stream<Item = Event> + 'static,
+{
+    /// Create responder for stream of events.
+    pub fn new(stream: S) -> Self {
+        SseStream {
+            stream,
+            heartbeat: None,
+        }
+    }
+
+    /// Send comment if no events were sent for specified period.
+    ///
+    /// By default heartbeats are disabled.
+    pub fn heartbeat<T: Into<Millis>>(mut self, period: T) -> Self {
+        self.heartbeat = Some(period.into());
+        self
+    }
+}
+
+impl<S> fmt::Debug for SseStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseStream")
+            .field("heartbeat", &self.heartbeat)
+            .finish()
+    }
+}
+
+impl<S, Err> Responder<Err> for SseStream<S>
+where
+    S: Stream<Item = Event> + 'static,
+    Err: ErrorRenderer,
+{
+    type Future = Ready<Response>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        let body = SseBody {
+            stream: Box::pin(self.stream),
+            heartbeat: self.heartbeat.map(|period| (period, Sleep::new(period))),
+        };
+        let mut res = Response::build(StatusCode::OK);
+        res.content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+            .header("x-accel-buffering", HeaderValue::from_static("no"))
+            .encoding(ContentEncoding::Identity);
+        res.body(Body::from_message(body)).into()
+    }
+}
+
+struct SseBody<S> {
+    stream: Pin<Box<S>>,
+    heartbeat: Option<(Millis, Sleep)>,
+}
+
+impl<S> MessageBody for SseBody<S>
+where
+    S: Stream<Item = Event> + 'static,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match self.stream.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                if let Some((period, ref sleep)) = self.heartbeat {
+                    sleep.reset(period);
+                }
+                Poll::Ready(Some(Ok(event.to_bytes())))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                if let Some((period, ref sleep)) = self.heartbeat {
+                    if sleep.poll_elapsed(cx).is_ready() {
+                        sleep.reset(period);
+                        return Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n"))));
+                    }
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Extractor for `Last-Event-ID` request header.
+///
+/// Clients send id of last received event on reconnect, value is `None`
+/// for new connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastEventId(Option<String>);
+
+impl LastEventId {
+    /// Get last event id
+    pub fn as_str(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for LastEventId {
+    type Error = Err::Container;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req
+            .headers()
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        Ready::from(Ok(LastEventId(id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+    use crate::time::sleep;
+    use crate::util::{lazy, stream_recv};
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App};
+
+    #[test]
+    fn test_event() {
+        assert_eq!(Event::new("data").to_bytes(), "data: data\n\n");
+        assert_eq!(
+            Event::new("a\r\nb\rc\n")
+                .event("na\nme")
+                .id("1\r\n")
+                .retry(Millis(500))
+                .to_bytes(),
+            "event: name\nid: 1\nretry: 500\ndata: a\ndata: b\ndata: c\ndata: \n\n"
+        );
+        assert_eq!(Event::comment("ping").to_bytes(), ": ping\n\n");
+        assert_eq!(Event::new("").to_bytes(), "data: \n\n");
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let app = test::init_service(App::new().route(
+            "/",
+            web::get().to(|id: LastEventId| async move {
+                let start: usize = id.as_str().and_then(|id| id.parse().ok()).unwrap_or(0);
+                SseStream::new(futures_util::stream::iter(
+                    (start..start + 2)
+                        .map(|i| Event::new(format!("msg{}", i)).id(i.to_string())),
+                ))
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            res.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        assert_eq!(res.headers().get("x-accel-buffering").unwrap(), "no");
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+        assert_eq!(
+            res.response().get_encoding(),
+            Some(ContentEncoding::Identity)
+        );
+        assert_eq!(
+            test::read_body(res).await,
+            "id: 0\ndata: msg0\n\nid: 1\ndata: msg1\n\n"
+        );
+
+        let req = TestRequest::with_header("last-event-id", "5").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            test::read_body(res).await,
+            "id: 5\ndata: msg5\n\nid: 6\ndata: msg6\n\n"
+        );
+    }
+
+    struct Pending(Rc<Cell<bool>>);
+
+    impl Stream for Pending {
+        type Item = Event;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Event>> {
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Pending {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_heartbeat() {
+        let dropped = Rc::new(Cell::new(false));
+        let req = TestRequest::default().to_http_request();
+        let mut res = Responder::<crate::web::DefaultError>::respond_to(
+            SseStream::new(Pending(dropped.clone())).heartbeat(Millis(50)),
+            &req,
+        )
+        .await;
+        let mut body = res.take_body();
+        assert_eq!(body.size(), BodySize::Stream);
+        assert!(lazy(|cx| body.poll_next_chunk(cx)).await.is_pending());
+
+        sleep(Millis(100)).await;
+        let chunk = stream_recv(&mut body).await.unwrap().unwrap();
+        assert_eq!(chunk, Bytes::from_static(b":\n\n"));
+        assert!(lazy(|cx| body.poll_next_chunk(cx)).await.is_pending());
+
+        // client is gone
+        assert!(!dropped.get());
+        drop(body);
+        assert!(dropped.get());
+    }
+}
@@ -1,29 +1,38 @@
 //! Various helpers for ntex applications to use during testing.
-use std::{fmt, net, net::SocketAddr, rc::Rc, sync::mpsc, thread};
+use std::task::{Context, Poll};
+use std::{any::Any, sync::mpsc, thread};
+use std::{collections::VecDeque, fmt, net, net::SocketAddr, pin::Pin, rc::Rc};
 
 #[cfg(feature = "cookie")]
 use coo_kie::Cookie;
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::http::body::MessageBody;
+use crate::http::body::{Body, MessageBody, ResponseBody};
+#[cfg(feature = "openssl")]
+use crate::http::client::Certificate;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::http::client::TlsConfig;
 use crate::http::client::{Client, ClientRequest, ClientResponse, Connector};
 use crate::http::error::{HttpError, PayloadError, ResponseError};
-use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
 use crate::http::test::TestRequest as HttpTestRequest;
 use crate::http::{HttpService, Method, Payload, Request, StatusCode, Uri, Version};
+use crate::io::{Base, Io, Sealed};
 use crate::router::{Path, ResourceDef};
 use crate::service::{
     map_config, IntoService, IntoServiceFactory, Pipeline, Service, ServiceFactory,
 };
-use crate::time::{sleep, Millis, Seconds};
-use crate::util::{stream_recv, Bytes, BytesMut, Extensions, Ready, Stream};
-use crate::ws::{error::WsClientError, WsClient, WsConnection};
-use crate::{io::Sealed, rt::System, server::Server};
+use crate::time::{sleep, Millis, Seconds, Sleep};
+use crate::util::{stream_recv, ByteString, Bytes, BytesMut, Extensions, Ready, Stream};
+use crate::ws::{self, error::WsClientError, WsClient, WsClientBuilder, WsConnection};
+use crate::{rt::System, server::Server};
 
+use crate::web::config::AppConfig;
 use crate::web::error::{DefaultError, ErrorRenderer};
 use crate::web::httprequest::{HttpRequest, HttpRequestPool};
 use crate::web::rmap::ResourceMap;
-use crate::web::{config::AppConfig, service::AppState};
+use crate::web::service::{insert_keyed_state, AppState};
+use crate::web::types::StateKey;
 use crate::web::{FromRequest, HttpResponse, Responder, WebRequest, WebResponse};
 
 /// Create service that always responds with `HttpResponse::Ok()`
@@ -79,8 +88,14 @@ where
     srv.pipeline(AppConfig::default()).await.unwrap()
 }
 
+/// Default timeout of `call_service()`
+const CALL_TIMEOUT: Millis = Millis(30_000);
+
 /// Calls service and waits for response future completion.
 ///
+/// Panics if handler does not complete within 30 seconds, use
+/// [`call_service_timeout()`] for slow handlers.
+///
 /// ```rust
 /// use ntex::http::StatusCode;
 /// use ntex::web::{self, test, App, HttpResponse};
@@ -107,7 +122,46 @@ where
     S: Service<R, Response = WebResponse, Error = E>,
     E: std::fmt::Debug,
 {
-    app.call(req).await.unwrap()
+    call_service_timeout(app, req, CALL_TIMEOUT).await
+}
+
+/// Calls service and waits for response future completion with specified timeout.
+///
+/// Panics if handler does not complete in time.
+///
+/// ```rust
+/// use ntex::{http::StatusCode, time::Seconds};
+/// use ntex::web::{self, test, App, HttpResponse};
+///
+/// #[ntex::test]
+/// async fn test_slow_response() {
+///     let app = test::init_service(
+///         App::new().service(web::resource("/test").to(|| async {
+///             ntex::time::sleep(ntex::time::Millis(100)).await;
+///             HttpResponse::Ok()
+///         }))
+///     ).await;
+///
+///     let req = test::TestRequest::with_uri("/test").to_request();
+///     let resp = test::call_service_timeout(&app, req, Seconds(1)).await;
+///     assert_eq!(resp.status(), StatusCode::OK);
+/// }
+/// ```
+pub async fn call_service_timeout<S, R, E, T>(
+    app: &Pipeline<S>,
+    req: R,
+    timeout: T,
+) -> S::Response
+where
+    S: Service<R, Response = WebResponse, Error = E>,
+    E: std::fmt::Debug,
+    T: Into<Millis>,
+{
+    let timeout = timeout.into();
+    match crate::time::timeout(timeout, app.call(req)).await {
+        Ok(res) => res.unwrap(),
+        Err(_) => panic!("handler did not complete within {}ms", timeout.0),
+    }
 }
 
 /// Helper function that returns a response body of a TestRequest
@@ -144,12 +198,7 @@ where
         .await
         .unwrap_or_else(|_| panic!("read_response failed at application call"));
 
-    let mut body = resp.take_body();
-    let mut bytes = BytesMut::new();
-    while let Some(item) = stream_recv(&mut body).await {
-        bytes.extend_from_slice(&item.unwrap());
-    }
-    bytes.freeze()
+    collect_body(resp.take_body()).await
 }
 
 /// Helper function that returns a response body of a WebResponse.
@@ -179,14 +228,39 @@ where
 /// }
 /// ```
 pub async fn read_body(mut res: WebResponse) -> Bytes {
-    let mut body = res.take_body();
+    collect_body(res.take_body()).await
+}
+
+/// Helper function that returns a deserialized response body of a WebResponse.
+pub async fn read_body_json<T: DeserializeOwned>(res: WebResponse) -> T {
+    deserialize_body("read_body_json", read_body(res).await)
+}
+
+/// Max size of response body that is read by test helpers
+const MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
+
+async fn collect_body(mut body: ResponseBody<Body>) -> Bytes {
     let mut bytes = BytesMut::new();
     while let Some(item) = stream_recv(&mut body).await {
         bytes.extend_from_slice(&item.unwrap());
+        if bytes.len() > MAX_BODY_SIZE {
+            panic!("Response body is larger than {} bytes", MAX_BODY_SIZE);
+        }
     }
     bytes.freeze()
 }
 
+fn deserialize_body<T: DeserializeOwned>(name: &str, body: Bytes) -> T {
+    serde_json::from_slice(&body).unwrap_or_else(|e| {
+        panic!(
+            "{} failed during deserialization: {}, body: {:?}",
+            name,
+            e,
+            String::from_utf8_lossy(&body)
+        )
+    })
+}
+
 /// Reads response's body and combines it to a Bytes objects
 pub async fn load_stream<S, E>(mut stream: S) -> Result<Bytes, E>
 where
@@ -199,6 +273,100 @@ where
     Ok(data.freeze())
 }
 
+/// Create payload stream from chunks for `TestRequest::set_payload_stream()`
+///
+/// ```rust
+/// use ntex::{time::Millis, web::test};
+///
+/// let req = test::TestRequest::post()
+///     .set_payload_stream(test::chunks(["hello", " world"]).delay(Millis(10)))
+///     .to_http_request();
+/// ```
+pub fn chunks<I, B>(chunks: I) -> Chunks
+where
+    I: IntoIterator<Item = B>,
+    B: Into<Bytes>,
+{
+    Chunks {
+        chunks: chunks.into_iter().map(Into::into).collect(),
+        delay: Millis::ZERO,
+        sleep: None,
+        error: None,
+        pending: false,
+    }
+}
+
+/// Payload stream of test request
+pub struct Chunks {
+    chunks: VecDeque<Bytes>,
+    delay: Millis,
+    sleep: Option<Sleep>,
+    error: Option<PayloadError>,
+    pending: bool,
+}
+
+impl Chunks {
+    /// Wait before each chunk
+    pub fn delay(mut self, delay: Millis) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Fail with error after all chunks are delivered
+    pub fn error(mut self, err: PayloadError) -> Self {
+        self.error = Some(err);
+        self
+    }
+
+    /// Never finish stream after all chunks are delivered,
+    /// simulates client that stops sending body
+    pub fn pending(mut self) -> Self {
+        self.pending = true;
+        self
+    }
+}
+
+impl fmt::Debug for Chunks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chunks")
+            .field("chunks", &self.chunks.len())
+            .field("delay", &self.delay)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl Stream for Chunks {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        if this.chunks.is_empty() {
+            return if let Some(err) = this.error.take() {
+                Poll::Ready(Some(Err(err)))
+            } else if this.pending {
+                Poll::Pending
+            } else {
+                Poll::Ready(None)
+            };
+        }
+
+        if this.delay.non_zero() {
+            let delay = this.delay;
+            let sleep = this.sleep.get_or_insert_with(|| Sleep::new(delay));
+            if sleep.poll_elapsed(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.sleep = None;
+        }
+        Poll::Ready(this.chunks.pop_front().map(Ok))
+    }
+}
+
 /// Helper function that returns a deserialized response body of a TestRequest
 ///
 /// ```rust
@@ -239,10 +407,7 @@ where
     S: Service<Request, Response = WebResponse>,
     T: DeserializeOwned,
 {
-    let body = read_response::<S>(app, req).await;
-
-    serde_json::from_slice(&body)
-        .unwrap_or_else(|_| panic!("read_response_json failed during deserialization"))
+    deserialize_body("read_response_json", read_response::<S>(app, req).await)
 }
 
 /// Helper method for extractors testing
@@ -301,7 +466,6 @@ pub struct TestRequest {
     rmap: ResourceMap,
     config: AppConfig,
     path: Path<Uri>,
-    peer_addr: Option<SocketAddr>,
     app_state: Extensions,
 }
 
@@ -312,7 +476,6 @@ impl Default for TestRequest {
             rmap: ResourceMap::new(ResourceDef::new("")),
             config: AppConfig::default(),
             path: Path::new(Uri::default()),
-            peer_addr: None,
             app_state: Extensions::new(),
         }
     }
@@ -404,7 +567,7 @@ impl TestRequest {
 
     /// Set peer addr
     pub fn peer_addr(mut self, addr: SocketAddr) -> Self {
-        self.peer_addr = Some(addr);
+        self.req.peer_addr(addr);
         self
     }
 
@@ -414,6 +577,18 @@ impl TestRequest {
         self
     }
 
+    /// Set streaming request payload.
+    ///
+    /// Payload is delivered chunk by chunk, like body of chunked request.
+    /// See [`chunks()`] for simple payload streams.
+    pub fn set_payload_stream<S>(mut self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        self.req.set_payload_stream(stream);
+        self
+    }
+
     /// Serialize `data` to a URL encoded form and set it as the request payload. The `Content-Type`
     /// header is set to `application/x-www-form-urlencoded`.
     pub fn set_form<T: Serialize>(mut self, data: &T) -> Self {
@@ -442,6 +617,28 @@ impl TestRequest {
         self
     }
 
+    /// Set keyed application state. This is equivalent of `App::state_keyed()`
+    /// method for testing purpose, state is stored with `K::KEY` key.
+    ///
+    /// ```rust
+    /// use ntex::web::{test, types::StateKey};
+    ///
+    /// struct Replica;
+    ///
+    /// impl StateKey for Replica {
+    ///     const KEY: &'static str = "replica";
+    /// }
+    ///
+    /// let req = test::TestRequest::default()
+    ///     .state_keyed::<Replica>("db2".to_string())
+    ///     .to_http_request();
+    /// assert_eq!(req.app_state_keyed::<String>("replica").unwrap(), "db2");
+    /// ```
+    pub fn state_keyed<K: StateKey>(mut self, data: impl Any) -> Self {
+        insert_keyed_state(&mut self.app_state, K::KEY, data);
+        self
+    }
+
     #[cfg(test)]
     /// Set request config
     pub(crate) fn rmap(mut self, rmap: ResourceMap) -> Self {
@@ -586,6 +783,11 @@ where
 {
     let (tx, rx) = mpsc::channel();
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    let mut cfg = cfg;
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    let tls = cfg.tls_config();
+
     let ssl = match cfg.stream {
         StreamType::Tcp => false,
         #[cfg(feature = "openssl")]
@@ -594,6 +796,8 @@ where
         StreamType::Rustls(_) => true,
     };
 
+    let request_timeout = cfg.request_timeout;
+
     // run server in separate thread
     thread::spawn(move || {
         let sys = System::new("ntex-test-server");
@@ -698,24 +902,18 @@ where
 
     let client = {
         let connector = {
-            #[cfg(feature = "openssl")]
+            #[cfg(any(feature = "openssl", feature = "rustls"))]
             {
-                use tls_openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-
-                let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
-                builder.set_verify(SslVerifyMode::NONE);
-                let _ = builder
-                    .set_alpn_protos(b"\x02h2\x08http/1.1")
-                    .map_err(|e| log::error!("Cannot set alpn protocol: {:?}", e));
                 Connector::default()
                     .lifetime(Seconds::ZERO)
                     .keep_alive(Seconds(30))
                     .timeout(Millis(30_000))
                     .disconnect_timeout(Millis(5_000))
-                    .openssl(builder.build())
+                    .tls_config(tls)
+                    .expect("Invalid test client tls settings")
                     .finish()
             }
-            #[cfg(not(feature = "openssl"))]
+            #[cfg(not(any(feature = "openssl", feature = "rustls")))]
             {
                 Connector::default()
                     .lifetime(Seconds::ZERO)
@@ -726,7 +924,7 @@ where
 
         Client::build()
             .connector(connector)
-            .timeout(Seconds(30))
+            .timeout(request_timeout)
             .finish()
     };
 
@@ -745,6 +943,9 @@ pub struct TestServerConfig {
     tp: HttpVer,
     stream: StreamType,
     client_timeout: Seconds,
+    request_timeout: Millis,
+    #[cfg(feature = "openssl")]
+    self_signed: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -793,6 +994,9 @@ impl TestServerConfig {
             tp: HttpVer::Both,
             stream: StreamType::Tcp,
             client_timeout: Seconds(5),
+            request_timeout: Millis(30_000),
+            #[cfg(feature = "openssl")]
+            self_signed: false,
         }
     }
 
@@ -815,6 +1019,17 @@ impl TestServerConfig {
         self
     }
 
+    /// Start openssl server with generated self-signed certificate.
+    ///
+    /// Certificate is valid for `localhost` and `127.0.0.1`, test server
+    /// client trusts it. `h2` protocol is negotiated with ALPN unless
+    /// server is configured with `h1()`.
+    #[cfg(feature = "openssl")]
+    pub fn openssl_self_signed(mut self) -> Self {
+        self.self_signed = true;
+        self
+    }
+
     /// Start rustls server
     #[cfg(feature = "rustls")]
     pub fn rustls(mut self, config: tls_rustls::ServerConfig) -> Self {
@@ -827,6 +1042,84 @@ impl TestServerConfig {
         self.client_timeout = val;
         self
     }
+
+    /// Set timeout of test client requests.
+    ///
+    /// Request fails with timeout error if server does not respond in time.
+    /// Could be overridden per request with `ClientRequest::timeout()`.
+    /// By default timeout is 30 seconds.
+    pub fn request_timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.request_timeout = timeout.into();
+        self
+    }
+
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Tls settings of test client, creates self-signed acceptor if needed
+    fn tls_config(&mut self) -> TlsConfig {
+        #[cfg(feature = "openssl")]
+        if self.self_signed {
+            let (acceptor, cert) = self_signed_acceptor(&self.tp);
+            self.stream = StreamType::Openssl(acceptor);
+            return TlsConfig::new().add_root_certificate(cert);
+        }
+        TlsConfig::new().danger_accept_invalid_certs(true)
+    }
+}
+
+#[cfg(feature = "openssl")]
+/// Create openssl acceptor with throwaway self-signed certificate
+fn self_signed_acceptor(tp: &HttpVer) -> (tls_openssl::ssl::SslAcceptor, Certificate) {
+    use tls_openssl::asn1::Asn1Time;
+    use tls_openssl::bn::{BigNum, MsbOption};
+    use tls_openssl::ec::{EcGroup, EcKey};
+    use tls_openssl::ssl::{self, AlpnError, SslAcceptor, SslMethod};
+    use tls_openssl::x509::{extension::SubjectAlternativeName, X509NameBuilder, X509};
+    use tls_openssl::{hash::MessageDigest, nid::Nid, pkey::PKey};
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", "localhost").unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&cert.x509v3_context(None, None))
+        .unwrap();
+    cert.append_extension(san).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    let cert = cert.build();
+
+    let protos: &'static [u8] = match tp {
+        HttpVer::Http1 => b"\x08http/1.1",
+        HttpVer::Http2 => b"\x02h2",
+        HttpVer::Both => b"\x02h2\x08http/1.1",
+    };
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder.set_private_key(&key).unwrap();
+    builder.set_certificate(&cert).unwrap();
+    builder.set_alpn_select_callback(move |_, client| {
+        ssl::select_next_proto(protos, client).ok_or(AlpnError::NOACK)
+    });
+
+    let der = Certificate::from_der(&cert.to_der().unwrap());
+    (builder.build(), der)
 }
 
 #[derive(Debug)]
@@ -906,6 +1199,99 @@ impl TestServer {
 
     /// Connect to websocket server at a given path
     pub async fn ws_at(&self, path: &str) -> Result<WsConnection<Sealed>, WsClientError> {
+        self.ws_builder(path).connect_raw().await
+    }
+
+    /// Create websocket handshake builder for a given path
+    pub fn ws_builder(&self, path: &str) -> WsTestBuilder {
+        let mut builder = WsClient::build(self.url(path));
+        builder.address(self.addr).timeout(Seconds(30));
+
+        WsTestBuilder {
+            builder,
+            ssl: self.ssl,
+            timeout: Millis(30_000),
+        }
+    }
+
+    /// Connect to a websocket server
+    pub async fn ws(&self) -> Result<WsConnection<Sealed>, WsClientError> {
+        self.ws_at("/").await
+    }
+
+    /// Gracefully stop http server
+    pub async fn stop(self) {
+        self.server.stop(true).await;
+        self.system.stop();
+        sleep(Millis(100)).await;
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.system.stop()
+    }
+}
+
+/// Websocket handshake builder of test server
+pub struct WsTestBuilder {
+    builder: WsClientBuilder<Base, crate::connect::Connector<Uri>>,
+    ssl: bool,
+    timeout: Millis,
+}
+
+impl WsTestBuilder {
+    /// Set websocket protocols offered to server
+    pub fn protocols<U, V>(mut self, protos: U) -> Self
+    where
+        U: IntoIterator<Item = V>,
+        V: AsRef<str>,
+    {
+        self.builder.protocols(protos);
+        self
+    }
+
+    /// Append handshake request header
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        HeaderValue: TryFrom<V>,
+        <HeaderName as TryFrom<K>>::Error: Into<HttpError>,
+        <HeaderValue as TryFrom<V>>::Error: Into<HttpError>,
+    {
+        self.builder.header(key, value);
+        self
+    }
+
+    #[cfg(feature = "cookie")]
+    /// Set handshake request cookie
+    pub fn cookie(mut self, cookie: Cookie<'_>) -> Self {
+        self.builder.cookie(cookie);
+        self
+    }
+
+    /// Set handshake and frame timeout.
+    ///
+    /// By default timeout is 30 seconds.
+    pub fn timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.timeout = timeout.into();
+        self.builder.timeout(self.timeout);
+        self
+    }
+
+    /// Complete handshake
+    pub async fn connect(self) -> Result<WsTestConnection, WsClientError> {
+        let timeout = self.timeout;
+        let (io, codec, res) = self.connect_raw().await?.into_inner();
+        Ok(WsTestConnection {
+            io,
+            codec,
+            res,
+            timeout,
+        })
+    }
+
+    async fn connect_raw(mut self) -> Result<WsConnection<Sealed>, WsClientError> {
         if self.ssl {
             #[cfg(feature = "openssl")]
             {
@@ -917,11 +1303,8 @@ impl TestServer {
                     .set_alpn_protos(b"\x08http/1.1")
                     .map_err(|e| log::error!("Cannot set alpn protocol: {:?}", e));
 
-                WsClient::build(self.url(path))
-                    .address(self.addr)
-                    .timeout(Seconds(30))
+                self.builder
                     .openssl(builder.build())
-                    .take()
                     .finish()
                     .unwrap()
                     .connect()
@@ -933,9 +1316,7 @@ impl TestServer {
                 panic!("openssl feature is required")
             }
         } else {
-            WsClient::build(self.url(path))
-                .address(self.addr)
-                .timeout(Seconds(30))
+            self.builder
                 .finish()
                 .unwrap()
                 .connect()
@@ -943,23 +1324,159 @@ impl TestServer {
                 .map(|ws| ws.seal())
         }
     }
+}
 
-    /// Connect to a websocket server
-    pub async fn ws(&self) -> Result<WsConnection<Sealed>, WsClientError> {
-        self.ws_at("/").await
+impl fmt::Debug for WsTestBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsTestBuilder")
+            .field("builder", &self.builder)
+            .field("ssl", &self.ssl)
+            .field("timeout", &self.timeout)
+            .finish()
     }
+}
 
-    /// Gracefully stop http server
-    pub async fn stop(self) {
-        self.server.stop(true).await;
-        self.system.stop();
-        sleep(Millis(100)).await;
-    }
+#[derive(Debug)]
+/// Websocket connection of test server.
+///
+/// Helper methods panic on io errors, unexpected frames and timeouts.
+///
+/// ```rust
+/// use ntex::service::{fn_factory_with_config, fn_service};
+/// use ntex::web::{self, test, ws, App, HttpRequest};
+///
+/// #[ntex::test]
+/// async fn test_ws() {
+///     let srv = test::server(|| {
+///         App::new().service(web::resource("/").to(|req: HttpRequest| async move {
+///             ws::start::<_, _, web::Error>(
+///                 req,
+///                 fn_factory_with_config(|_| async {
+///                     Ok::<_, web::Error>(fn_service(|frame| async move {
+///                         Ok::<_, std::io::Error>(match frame {
+///                             ws::Frame::Text(text) => Some(ws::Message::Binary(text)),
+///                             _ => None,
+///                         })
+///                     }))
+///                 }),
+///             )
+///             .await
+///         }))
+///     });
+///
+///     let conn = srv.ws_builder("/").connect().await.unwrap();
+///     conn.send_text("text").await;
+///     assert_eq!(conn.expect_binary().await, "text");
+/// }
+/// ```
+pub struct WsTestConnection {
+    io: Io<Sealed>,
+    codec: ws::Codec,
+    res: ClientResponse,
+    timeout: Millis,
 }
 
-impl Drop for TestServer {
-    fn drop(&mut self) {
-        self.system.stop()
+impl WsTestConnection {
+    /// Get handshake response
+    pub fn response(&self) -> &ClientResponse {
+        &self.res
+    }
+
+    /// Get handshake response status
+    pub fn status(&self) -> StatusCode {
+        self.res.status()
+    }
+
+    /// Get handshake response headers
+    pub fn headers(&self) -> &HeaderMap {
+        self.res.headers()
+    }
+
+    /// Get sub-protocol selected by server
+    pub fn protocol(&self) -> Option<&str> {
+        self.res
+            .headers()
+            .get(&header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|hdr| hdr.to_str().ok())
+            .map(|proto| proto.trim())
+    }
+
+    /// Get io stream
+    pub fn io(&self) -> &Io<Sealed> {
+        &self.io
+    }
+
+    /// Get codec reference
+    pub fn codec(&self) -> &ws::Codec {
+        &self.codec
+    }
+
+    /// Get io stream, codec and handshake response
+    pub fn into_inner(self) -> (Io<Sealed>, ws::Codec, ClientResponse) {
+        (self.io, self.codec, self.res)
+    }
+
+    /// Send websocket message
+    pub async fn send(&self, msg: ws::Message) {
+        self.io
+            .send(msg, &self.codec)
+            .await
+            .unwrap_or_else(|e| panic!("Cannot send websocket message: {:?}", e));
+    }
+
+    /// Send text message
+    pub async fn send_text<T: Into<ByteString>>(&self, text: T) {
+        self.send(ws::Message::Text(text.into())).await
+    }
+
+    /// Send binary message
+    pub async fn send_binary<T: Into<Bytes>>(&self, data: T) {
+        self.send(ws::Message::Binary(data.into())).await
+    }
+
+    /// Receive next frame
+    pub async fn recv(&self) -> ws::Frame {
+        match crate::time::timeout(self.timeout, self.io.recv(&self.codec)).await {
+            Ok(Ok(Some(frame))) => frame,
+            Ok(Ok(None)) => panic!("Websocket connection is closed"),
+            Ok(Err(e)) => panic!("Cannot read websocket frame: {:?}", e),
+            Err(_) => panic!("No websocket frame within {}ms", self.timeout.0),
+        }
+    }
+
+    /// Receive next data or close frame, ping and pong frames are skipped
+    async fn recv_data(&self) -> ws::Frame {
+        loop {
+            match self.recv().await {
+                ws::Frame::Ping(_) | ws::Frame::Pong(_) => continue,
+                frame => return frame,
+            }
+        }
+    }
+
+    /// Receive text message, ping and pong frames are skipped
+    pub async fn expect_text(&self) -> String {
+        match self.recv_data().await {
+            ws::Frame::Text(text) => String::from_utf8(text.to_vec())
+                .unwrap_or_else(|e| panic!("Text frame is not utf-8: {:?}", e)),
+            frame => panic!("Expected text frame, got {:?}", frame),
+        }
+    }
+
+    /// Receive binary message, ping and pong frames are skipped
+    pub async fn expect_binary(&self) -> Bytes {
+        match self.recv_data().await {
+            ws::Frame::Binary(data) => data,
+            frame => panic!("Expected binary frame, got {:?}", frame),
+        }
+    }
+
+    /// Receive close frame with specified code, ping and pong frames are skipped
+    pub async fn expect_close(&self, code: ws::CloseCode) {
+        match self.recv_data().await {
+            ws::Frame::Close(Some(reason)) if reason.code == code => (),
+            frame => panic!("Expected close frame with {:?} code, got {:?}", code, frame),
+        }
     }
 }
 
@@ -984,7 +1501,7 @@ mod tests {
             .to_http_request();
         assert!(req.headers().contains_key(header::CONTENT_TYPE));
         assert!(req.headers().contains_key(header::DATE));
-        assert_eq!(req.peer_addr(), None);
+        assert_eq!(req.peer_addr(), Some("127.0.0.1:8081".parse().unwrap()));
         assert_eq!(&req.match_info()["test"], "123");
         assert_eq!(req.version(), Version::HTTP_2);
         let data = req.app_state::<u64>().unwrap();
@@ -1131,6 +1648,46 @@ mod tests {
         assert_eq!(&result.name, "User name");
     }
 
+    #[crate::rt_test]
+    #[should_panic(expected = "read_response_json failed during deserialization: \
+                               expected ident at line 1 column 2, body: \"not json\"")]
+    async fn test_read_body_json() {
+        let app = init_service(
+            App::new()
+                .service(web::resource("/people").to(|| async {
+                    let body = futures_util::stream::iter(
+                        [&b"{\"id\":\"12345\","[..], b"\"name\":\"User name\"}"]
+                            .map(|b| Ok::<_, Infallible>(Bytes::from_static(b))),
+                    );
+                    HttpResponse::Ok().streaming(body)
+                }))
+                .service(web::resource("/text").to(|| async { "not json" })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/people").to_request();
+        let result: Person = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(&result.id, "12345");
+        assert_eq!(&result.name, "User name");
+
+        let req = TestRequest::with_uri("/text").to_request();
+        let _: Person = read_response_json(&app, req).await;
+    }
+
+    #[crate::rt_test]
+    #[should_panic(expected = "Response body is larger than")]
+    async fn test_read_body_limit() {
+        let app = init_service(App::new().service(web::resource("/").to(|| async {
+            let body = futures_util::stream::repeat_with(|| {
+                Ok::<_, Infallible>(Bytes::from(vec![0u8; 64 * 1024]))
+            });
+            HttpResponse::Ok().streaming(body)
+        })))
+        .await;
+
+        read_response(&app, TestRequest::default().to_request()).await;
+    }
+
     #[crate::rt_test]
     async fn test_async_with_block() {
         async fn async_with_block() -> Result<HttpResponse, Infallible> {
@@ -1155,6 +1712,44 @@ mod tests {
         assert!(res.status().is_success());
     }
 
+    #[cfg(feature = "cookie")]
+    #[crate::rt_test]
+    async fn test_request_parts() {
+        use crate::web::types::{KeyedState, State, StateKey};
+
+        struct Replica;
+        impl StateKey for Replica {
+            const KEY: &'static str = "replica";
+        }
+
+        let (req, mut pl) = TestRequest::default()
+            .peer_addr("192.168.0.1:8080".parse().unwrap())
+            .header(header::COOKIE, "session=abc")
+            .cookie(Cookie::new("name", "value"))
+            .cookie(Cookie::new("lang", "en"))
+            .state(10usize)
+            .state_keyed::<Replica>("db2".to_string())
+            .to_http_parts();
+
+        assert_eq!(req.peer_addr(), Some("192.168.0.1:8080".parse().unwrap()));
+        assert_eq!(req.connection_info().remote(), Some("192.168.0.1:8080"));
+        assert_eq!(req.headers().get_all(header::COOKIE).count(), 1);
+        assert_eq!(req.cookie("session").unwrap().value(), "abc");
+        assert_eq!(req.cookie("name").unwrap().value(), "value");
+        assert_eq!(req.cookie("lang").unwrap().value(), "en");
+
+        let st = <State<usize> as FromRequest<DefaultError>>::from_request(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(*st, 10);
+        let st = <KeyedState<Replica, String> as FromRequest<DefaultError>>::from_request(
+            &req, &mut pl,
+        )
+        .await
+        .unwrap();
+        assert_eq!(st.as_str(), "db2");
+    }
+
     #[crate::rt_test]
     async fn test_server_state() {
         async fn handler(data: web::types::State<usize>) -> crate::http::ResponseBuilder {
@@ -1172,6 +1767,117 @@ mod tests {
         assert!(res.status().is_success());
     }
 
+    #[crate::rt_test]
+    async fn test_payload_stream() {
+        use crate::web::types::PayloadConfig;
+
+        async fn extract(
+            req: TestRequest,
+        ) -> Result<Bytes, crate::web::error::PayloadError> {
+            let (req, mut pl) = req.to_http_parts();
+            <Bytes as FromRequest<DefaultError>>::from_request(&req, &mut pl).await
+        }
+
+        let req = TestRequest::post()
+            .set_payload_stream(chunks(["hello", " ", "world"]).delay(Millis(10)));
+        assert!(format!("{:?}", chunks(["a"])).contains("Chunks"));
+        let (r, _) = req.to_http_parts();
+        assert_eq!(
+            r.headers().get(header::TRANSFER_ENCODING).unwrap(),
+            "chunked"
+        );
+
+        let req = TestRequest::post()
+            .set_payload_stream(chunks(["hello", " ", "world"]).delay(Millis(10)));
+        assert_eq!(
+            extract(req).await.unwrap(),
+            Bytes::from_static(b"hello world")
+        );
+
+        // limit applies to payload without content-length
+        let req = TestRequest::post()
+            .state(PayloadConfig::new(8))
+            .set_payload_stream(chunks(["hello", " ", "world"]));
+        assert!(matches!(
+            extract(req).await,
+            Err(crate::web::error::PayloadError::Payload(
+                PayloadError::LimitExceeded { limit: 8, size: 11 }
+            ))
+        ));
+
+        // mid-body error
+        let req = TestRequest::post()
+            .set_payload_stream(chunks(["hello"]).error(PayloadError::Incomplete(None)));
+        assert!(matches!(
+            extract(req).await,
+            Err(crate::web::error::PayloadError::Payload(
+                PayloadError::Incomplete(None)
+            ))
+        ));
+
+        // client never finishes body
+        let req = TestRequest::post().set_payload_stream(chunks(["hello"]).pending());
+        let res = crate::time::timeout(Millis(50), extract(req)).await;
+        assert!(res.is_err());
+    }
+
+    #[crate::rt_test]
+    #[should_panic(expected = "handler did not complete within 50ms")]
+    async fn test_call_service_timeout() {
+        let app = init_service(App::new().service(web::resource("/").to(|| async {
+            sleep(Millis(200)).await;
+            HttpResponse::Ok()
+        })))
+        .await;
+
+        let res =
+            call_service_timeout(&app, TestRequest::default().to_request(), Seconds(1))
+                .await;
+        assert!(res.status().is_success());
+        call_service_timeout(&app, TestRequest::default().to_request(), Millis(50)).await;
+    }
+
+    #[crate::rt_test]
+    async fn test_server_request_timeout() {
+        use crate::http::client::error::SendRequestError;
+
+        let srv = server_with(config().request_timeout(Millis(100)), || {
+            App::new().service(web::resource("/").to(|| async {
+                sleep(Millis(500)).await;
+                HttpResponse::Ok()
+            }))
+        });
+
+        let res = srv.get("/").send().await;
+        assert!(matches!(res, Err(SendRequestError::Timeout)));
+
+        // per request override
+        let res = srv.get("/").timeout(Seconds(5)).send().await.unwrap();
+        assert!(res.status().is_success());
+    }
+
+    #[cfg(feature = "openssl")]
+    #[crate::rt_test]
+    async fn test_openssl_self_signed() {
+        let factory = || {
+            App::new().service(web::resource("/").to(|req: HttpRequest| async move {
+                req.connection_info().scheme().to_string()
+            }))
+        };
+
+        let srv = server_with(config().openssl_self_signed(), factory);
+        assert!(srv.url("/").starts_with("https://localhost:"));
+        let mut res = srv.get("/").send().await.unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.version(), Version::HTTP_2);
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"https"));
+
+        let srv = server_with(config().h1().openssl_self_signed(), factory);
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.version(), Version::HTTP_11);
+        assert_eq!(res.body().await.unwrap(), Bytes::from_static(b"https"));
+    }
+
     #[crate::rt_test]
     async fn test_test_methods() {
         let srv = server(|| {
@@ -0,0 +1,103 @@
+//! Tls client certificate extractor
+use std::ops;
+
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::ErrorRenderer;
+use crate::web::{FromRequest, HttpRequest};
+
+/// Tls peer certificate
+#[derive(Clone, Debug)]
+pub enum Certificate {
+    /// Certificate of openssl connection
+    #[cfg(feature = "openssl")]
+    Openssl(tls_openssl::x509::X509),
+    /// Certificate of rustls connection
+    #[cfg(feature = "rustls")]
+    Rustls(tls_rustls::Certificate),
+}
+
+impl Certificate {
+    /// Get DER encoded certificate
+    pub fn to_der(&self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "openssl")]
+            Certificate::Openssl(cert) => cert.to_der().unwrap_or_default(),
+            #[cfg(feature = "rustls")]
+            Certificate::Rustls(cert) => cert.0.clone(),
+        }
+    }
+}
+
+/// Client certificate extractor
+///
+/// Certificate is provided by tls layer of the connection, so it is available
+/// for all requests of the connection, including http/2 connections. Server
+/// must request client certificate, i.e. with `SslAcceptorBuilder::set_verify()`
+/// for openssl or with client cert verifier for rustls.
+///
+/// Extractor never fails, value is `None` for plain connections or if client
+/// did not provide certificate.
+///
+/// ```rust
+/// use ntex::web::{self, types::ClientCert, App, HttpResponse};
+///
+/// async fn index(cert: ClientCert) -> HttpResponse {
+///     match cert.get() {
+///         Some(cert) => HttpResponse::Ok().body(cert.to_der()),
+///         None => HttpResponse::Unauthorized().finish(),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientCert(Option<Certificate>);
+
+impl ClientCert {
+    /// Get client certificate
+    pub fn get(&self) -> Option<&Certificate> {
+        self.0.as_ref()
+    }
+
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> Option<Certificate> {
+        self.0
+    }
+}
+
+impl ops::Deref for ClientCert {
+    type Target = Option<Certificate>;
+
+    fn deref(&self) -> &Option<Certificate> {
+        &self.0
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for ClientCert {
+    type Error = Err::Container;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        Ready::Ok(ClientCert(req.io().and_then(peer_cert)))
+    }
+}
+
+fn peer_cert(io: &crate::io::IoRef) -> Option<Certificate> {
+    #[cfg(feature = "openssl")]
+    {
+        if let Some(cert) = io.query::<crate::tls::openssl::PeerCert>().as_ref() {
+            return Some(Certificate::Openssl(cert.0.clone()));
+        }
+    }
+    #[cfg(feature = "rustls")]
+    {
+        if let Some(cert) = io.query::<crate::tls::rustls::PeerCert>().as_ref() {
+            return Some(Certificate::Rustls(cert.0.clone()));
+        }
+    }
+    None
+}
@@ -2,6 +2,7 @@
 use std::{fmt, future::Future, ops, pin::Pin, task::Context, task::Poll};
 
 use encoding_rs::{Encoding, UTF_8};
+use percent_encoding::percent_decode;
 use serde::{de::DeserializeOwned, Serialize};
 
 #[cfg(feature = "compress")]
@@ -13,6 +14,8 @@ use crate::web::error::{ErrorRenderer, UrlencodedError, WebResponseError};
 use crate::web::responder::{Ready, Responder};
 use crate::web::{FromRequest, HttpRequest};
 
+use super::helpers::ErrorHandler;
+
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
 /// Can be use to extract url-encoded data from the request body,
@@ -105,12 +108,12 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let limit = req
+        let (limit, lossy) = req
             .app_state::<FormConfig>()
-            .map(|c| c.limit)
-            .unwrap_or(16384);
+            .map(|c| (c.limit, c.lossy))
+            .unwrap_or((16384, false));
 
-        let fut = UrlEncoded::new(req, payload).limit(limit);
+        let fut = UrlEncoded::new(req, payload).limit(limit).lossy(lossy);
         Box::pin(async move {
             match fut.await {
                 Err(e) => Err(e),
@@ -118,6 +121,20 @@ where
             }
         })
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        Some(
+            req.app_state::<FormConfig>()
+                .map(|c| c.limit)
+                .unwrap_or(16384),
+        )
+    }
+
+    fn error_response(err: &UrlencodedError, req: &HttpRequest) -> Option<Response> {
+        req.app_state::<FormConfig>()
+            .and_then(|cfg| cfg.err_handler.call(err, req))
+    }
 }
 
 impl<T: fmt::Debug> fmt::Debug for Form<T> {
@@ -178,9 +195,11 @@ where
 ///     );
 /// }
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    lossy: bool,
+    err_handler: ErrorHandler<UrlencodedError>,
 }
 
 impl FormConfig {
@@ -189,11 +208,46 @@ impl FormConfig {
         self.limit = limit;
         self
     }
+
+    /// Replace invalid utf-8 sequences with `U+FFFD` instead of rejecting payload
+    ///
+    /// By default, percent-encoded values must be valid utf-8. Lossy mode
+    /// allows to accept forms from legacy clients that send latin-1 data.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Set custom error handler
+    ///
+    /// Error handler creates response for form extractor errors,
+    /// it is used with any error renderer.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&UrlencodedError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = ErrorHandler::new(handler);
+        self
+    }
 }
 
 impl Default for FormConfig {
     fn default() -> Self {
-        FormConfig { limit: 16384 }
+        FormConfig {
+            limit: 16384,
+            lossy: false,
+            err_handler: ErrorHandler::default(),
+        }
+    }
+}
+
+impl fmt::Debug for FormConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormConfig")
+            .field("limit", &self.limit)
+            .field("lossy", &self.lossy)
+            .field("error_handler", &self.err_handler)
+            .finish()
     }
 }
 
@@ -214,6 +268,7 @@ struct UrlEncoded<U> {
     #[cfg(not(feature = "compress"))]
     stream: Option<Payload>,
     limit: usize,
+    lossy: bool,
     length: Option<usize>,
     encoding: &'static Encoding,
     err: Option<UrlencodedError>,
@@ -254,6 +309,7 @@ impl<U> UrlEncoded<U> {
             encoding,
             stream: Some(payload),
             limit: 32_768,
+            lossy: false,
             length: len,
             fut: None,
             err: None,
@@ -264,6 +320,7 @@ impl<U> UrlEncoded<U> {
         UrlEncoded {
             stream: None,
             limit: 32_768,
+            lossy: false,
             fut: None,
             err: Some(e),
             length: None,
@@ -276,6 +333,12 @@ impl<U> UrlEncoded<U> {
         self.limit = limit;
         self
     }
+
+    /// Accept invalid utf-8 sequences
+    fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
 }
 
 impl<U> Future for UrlEncoded<U>
@@ -303,6 +366,7 @@ where
 
         // future
         let encoding = self.encoding;
+        let lossy = self.lossy;
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(Box::pin(async move {
@@ -321,6 +385,12 @@ where
             }
 
             if encoding == UTF_8 {
+                if !lossy {
+                    let decoded: Vec<u8> = percent_decode(&body).collect();
+                    if std::str::from_utf8(&decoded).is_err() {
+                        return Err(UrlencodedError::Parse);
+                    }
+                }
                 serde_urlencoded::from_bytes::<U>(&body).map_err(|_| UrlencodedError::Parse)
             } else {
                 let body = encoding
@@ -501,4 +571,103 @@ mod tests {
 
         assert_eq!(resp.body().get_ref(), b"hello=world&counter=123");
     }
+
+    #[crate::rt_test]
+    async fn test_utf8_validation() {
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=caf%C3%A9&counter=1"))
+                .to_http_parts();
+        let Form(s) = from_request::<Form<Info>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.hello, "caf\u{e9}");
+
+        // latin-1 encoded value
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=caf%E9&counter=1"))
+                .to_http_parts();
+        let res = from_request::<Form<Info>>(&req, &mut pl).await;
+        assert!(matches!(res.err().unwrap(), UrlencodedError::Parse));
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=caf%E9&counter=1"))
+                .state(FormConfig::default().lossy(true))
+                .to_http_parts();
+        let Form(s) = from_request::<Form<Info>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.hello, "caf\u{fffd}");
+    }
+
+    #[crate::rt_test]
+    async fn test_form_config() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App, HttpResponse};
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/upload")
+                        .state(FormConfig::default().limit(1024 * 1024).error_handler(
+                            |err, _| {
+                                HttpResponse::build(err.status_code()).body("custom error")
+                            },
+                        ))
+                        .route(web::post().to(|f: Form<Info>| async move {
+                            HttpResponse::Ok().body(f.into_inner().hello)
+                        })),
+                )
+                .route(
+                    "/",
+                    web::post().to(|f: Form<Info>| async move {
+                        HttpResponse::Ok().body(f.into_inner().hello)
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/")
+            .header(
+                CONTENT_TYPE,
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "world");
+
+        // multi-megabyte form
+        let mut body = b"counter=1&hello=".to_vec();
+        body.resize(body.len() + 3 * 1024 * 1024, b'a');
+        let req = TestRequest::post()
+            .uri("/upload")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from(body.clone()))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(read_body(resp).await, "custom error");
+
+        body.truncate(1024 * 1024);
+        let req = TestRequest::post()
+            .uri("/upload")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from(body))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // default renderer
+        let req = TestRequest::post()
+            .uri("/")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from(vec![b'a'; 32 * 1024]))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(read_body(resp)
+            .await
+            .starts_with(b"Urlencoded payload size"));
+    }
 }
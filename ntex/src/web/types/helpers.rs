@@ -0,0 +1,366 @@
+//! Shared extractor helpers
+use std::{fmt, sync::Arc};
+
+use serde::de;
+
+use crate::http::Response;
+use crate::web::HttpRequest;
+
+/// Custom error handler of extractor configuration
+pub(super) struct ErrorHandler<E>(
+    Option<Arc<dyn Fn(&E, &HttpRequest) -> Response + Send + Sync>>,
+);
+
+impl<E> ErrorHandler<E> {
+    pub(super) fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&E, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        ErrorHandler(Some(Arc::new(handler)))
+    }
+
+    /// Create error response with custom error handler
+    pub(super) fn call(&self, err: &E, req: &HttpRequest) -> Option<Response> {
+        self.0.as_ref().map(|handler| handler(err, req))
+    }
+}
+
+impl<E> Clone for ErrorHandler<E> {
+    fn clone(&self) -> Self {
+        ErrorHandler(self.0.clone())
+    }
+}
+
+impl<E> Default for ErrorHandler<E> {
+    fn default() -> Self {
+        ErrorHandler(None)
+    }
+}
+
+impl<E> fmt::Debug for ErrorHandler<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.is_some().fmt(f)
+    }
+}
+
+/// Deserialization state, updated by [`Track`] wrapper
+pub(super) trait Tracker {
+    /// Single value is being deserialized
+    fn single(&mut self) {}
+
+    /// Sequence of `len` values is being deserialized
+    fn len(&mut self, _: usize) {}
+
+    /// Struct with `fields` is being deserialized
+    fn fields(&mut self, _: &'static [&'static str]) {}
+
+    /// Map key is deserialized
+    fn key(&mut self, _: &str) {}
+
+    /// Sequence element or map entry at `index` is being deserialized
+    fn element(&mut self, _: usize) {}
+
+    /// Sequence element or map entry is deserialized
+    fn done(&mut self) {}
+}
+
+/// Deserializer and visitor wrapper, tracks deserialization state
+pub(super) struct Track<'a, D, S> {
+    inner: D,
+    state: &'a mut S,
+    key: bool,
+}
+
+impl<'a, D, S: Tracker> Track<'a, D, S> {
+    pub(super) fn new(inner: D, state: &'a mut S) -> Self {
+        Track {
+            inner,
+            state,
+            key: false,
+        }
+    }
+
+    fn visitor<V>(self, inner: V) -> (D, Track<'a, V, S>) {
+        let visitor = Track {
+            inner,
+            state: self.state,
+            key: self.key,
+        };
+        (self.inner, visitor)
+    }
+
+    /// Map keys are not tracked as values
+    fn state(&mut self) -> Option<&mut S> {
+        if self.key {
+            None
+        } else {
+            Some(self.state)
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: de::Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                let (de, visitor) = self.visitor(visitor);
+                de.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! forward_single {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: de::Visitor<'de>>(
+                mut self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                if let Some(state) = self.state() {
+                    state.single();
+                }
+                let (de, visitor) = self.visitor(visitor);
+                de.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de, D, S> de::Deserializer<'de> for Track<'a, D, S>
+where
+    D: de::Deserializer<'de>,
+    S: Tracker,
+{
+    type Error = D::Error;
+
+    forward_single! {
+        deserialize_bool(), deserialize_i8(), deserialize_i16(), deserialize_i32(),
+        deserialize_i64(), deserialize_u8(), deserialize_u16(), deserialize_u32(),
+        deserialize_u64(), deserialize_f32(), deserialize_f64(), deserialize_char(),
+        deserialize_str(), deserialize_string(), deserialize_byte_buf(),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    }
+
+    forward_deserialize! {
+        deserialize_any(), deserialize_bytes(), deserialize_option(), deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(), deserialize_map(), deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(
+        mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(state) = self.state() {
+            state.len(len);
+        }
+        let (de, visitor) = self.visitor(visitor);
+        de.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(state) = self.state() {
+            state.len(len);
+        }
+        let (de, visitor) = self.visitor(visitor);
+        de.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(state) = self.state() {
+            state.fields(fields);
+        }
+        let (de, visitor) = self.visitor(visitor);
+        de.deserialize_struct(name, fields, visitor)
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de, V, S> de::Visitor<'de> for Track<'a, V, S>
+where
+    V: de::Visitor<'de>,
+    S: Tracker,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool), visit_i8(i8), visit_i16(i16), visit_i32(i32), visit_i64(i64),
+        visit_u8(u8), visit_u16(u16), visit_u32(u32), visit_u64(u64), visit_f32(f32),
+        visit_f64(f64), visit_char(char), visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]), visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if self.key {
+            self.state.key(v);
+        }
+        self.inner.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        if self.key {
+            self.state.key(v);
+        }
+        self.inner.visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        if self.key {
+            self.state.key(&v);
+        }
+        self.inner.visit_string(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        self.inner.visit_some(d)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        d: D,
+    ) -> Result<Self::Value, D::Error> {
+        let (visitor, de) = self.visitor(d);
+        visitor.visit_newtype_struct(de)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_seq(Elements {
+            inner: seq,
+            state: self.state,
+            index: 0,
+        })
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_map(Elements {
+            inner: map,
+            state: self.state,
+            index: 0,
+        })
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'a, 'de, T, S> de::DeserializeSeed<'de> for Track<'a, T, S>
+where
+    T: de::DeserializeSeed<'de>,
+    S: Tracker,
+{
+    type Value = T::Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> Result<T::Value, D::Error> {
+        let (seed, de) = self.visitor(d);
+        seed.deserialize(de)
+    }
+}
+
+/// Sequence and map access wrapper, counts deserialized elements
+struct Elements<'a, A, S> {
+    inner: A,
+    state: &'a mut S,
+    index: usize,
+}
+
+impl<'a, 'de, A, S> de::SeqAccess<'de> for Elements<'a, A, S>
+where
+    A: de::SeqAccess<'de>,
+    S: Tracker,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.state.element(self.index);
+        let item = self.inner.next_element_seed(seed)?;
+        self.state.done();
+        self.index += 1;
+        Ok(item)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A, S> de::MapAccess<'de> for Elements<'a, A, S>
+where
+    A: de::MapAccess<'de>,
+    S: Tracker,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.state.element(self.index);
+        let key = self.inner.next_key_seed(Track {
+            inner: seed,
+            state: &mut *self.state,
+            key: true,
+        })?;
+        if key.is_none() {
+            self.state.done();
+        }
+        Ok(key)
+    }
+
+    fn next_value_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let value = self.inner.next_value_seed(seed)?;
+        self.state.done();
+        self.index += 1;
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
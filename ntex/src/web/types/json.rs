@@ -12,6 +12,8 @@ use crate::web::error::{ErrorRenderer, JsonError, JsonPayloadError, WebResponseE
 use crate::web::responder::{Ready, Responder};
 use crate::web::{FromRequest, HttpRequest};
 
+use super::helpers::ErrorHandler;
+
 /// Json helper
 ///
 /// Json can be used for two different purpose. First is for json response
@@ -188,6 +190,20 @@ where
             }
         })
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        Some(
+            req.app_state::<JsonConfig>()
+                .map(|c| c.limit)
+                .unwrap_or(32768),
+        )
+    }
+
+    fn error_response(err: &JsonPayloadError, req: &HttpRequest) -> Option<Response> {
+        req.app_state::<JsonConfig>()
+            .and_then(|cfg| cfg.err_handler.call(err, req))
+    }
 }
 
 /// Json extractor configuration
@@ -225,6 +241,7 @@ where
 pub struct JsonConfig {
     limit: usize,
     content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    err_handler: ErrorHandler<JsonPayloadError>,
 }
 
 impl JsonConfig {
@@ -242,6 +259,28 @@ impl JsonConfig {
         self.content_type = Some(Arc::new(predicate));
         self
     }
+
+    /// Set custom error handler
+    ///
+    /// Error handler creates response for json extractor errors,
+    /// it is used with any error renderer.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, error::JsonPayloadError, types::JsonConfig, HttpResponse};
+    ///
+    /// let config = JsonConfig::default().error_handler(|err, _| match err {
+    ///     JsonPayloadError::Deserialize(e) => HttpResponse::BadRequest()
+    ///         .json(&serde_json::json!({"line": e.line(), "message": e.to_string()})),
+    ///     _ => HttpResponse::BadRequest().body(err.to_string()),
+    /// });
+    /// ```
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&JsonPayloadError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = ErrorHandler::new(handler);
+        self
+    }
 }
 
 impl Default for JsonConfig {
@@ -249,6 +288,7 @@ impl Default for JsonConfig {
         JsonConfig {
             limit: 32768,
             content_type: None,
+            err_handler: ErrorHandler::default(),
         }
     }
 }
@@ -264,6 +304,7 @@ impl fmt::Debug for JsonConfig {
                     .as_ref()
                     .map(|_| "Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>"),
             )
+            .field("error_handler", &self.err_handler)
             .finish()
     }
 }
@@ -602,4 +643,129 @@ mod tests {
         let s = from_request::<Json<MyObject>>(&req, &mut pl).await;
         assert!(s.is_err())
     }
+
+    #[crate::rt_test]
+    async fn test_scope_config_error_handler() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App, HttpResponse};
+
+        let srv = init_service(
+            App::new()
+                .state(JsonConfig::default().limit(8))
+                .service(
+                    web::scope("/reports")
+                        .state(
+                            JsonConfig::default()
+                                .limit(1024)
+                                .content_type(|mime| mime.subtype() == "csp-report")
+                                .error_handler(|err, _| {
+                                    HttpResponse::UnprocessableEntity().json(
+                                        &serde_json::json!({"error": err.to_string()}),
+                                    )
+                                }),
+                        )
+                        .route(
+                            "/",
+                            web::post()
+                                .to(|_: Json<MyObject>| async { HttpResponse::Ok() }),
+                        ),
+                )
+                .route(
+                    "/",
+                    web::post().to(|_: Json<MyObject>| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/reports/")
+            .header(header::CONTENT_TYPE, "application/csp-report")
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/reports/")
+            .header(header::CONTENT_TYPE, "application/csp-report")
+            .set_payload(Bytes::from_static(b"{\"name\": 1}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = read_body(resp).await;
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("Json deserialize error"));
+
+        // app level config
+        let req = TestRequest::post()
+            .uri("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = read_body(resp).await;
+        assert_eq!(body, "Json payload size is bigger than allowed");
+    }
+
+    #[crate::rt_test]
+    async fn test_error_handler_custom_renderer() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App, ErrorContainer, HttpResponse};
+
+        #[derive(thiserror::Error, Debug)]
+        #[error("Container")]
+        struct Container;
+
+        impl crate::http::ResponseError for Container {}
+
+        impl ErrorContainer for Container {
+            fn error_response(&self, _: &HttpRequest) -> HttpResponse {
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+
+        impl From<JsonPayloadError> for Container {
+            fn from(_: JsonPayloadError) -> Container {
+                Container
+            }
+        }
+
+        struct Renderer;
+
+        impl ErrorRenderer for Renderer {
+            type Container = Container;
+        }
+
+        let srv = init_service(
+            App::with(Renderer)
+                .state(JsonConfig::default().error_handler(|err, _| {
+                    HttpResponse::UnprocessableEntity().body(err.to_string())
+                }))
+                .route(
+                    "/",
+                    web::post().to(|_: Json<MyObject>| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"name\": 1}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("Json deserialize error"));
+    }
 }
@@ -1,18 +1,29 @@
 //! Extractor types
 
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod client_cert;
 pub(in crate::web) mod form;
+mod helpers;
 pub(in crate::web) mod json;
 mod path;
 pub(in crate::web) mod payload;
 mod query;
+mod real_ip;
+mod req_data;
+mod request_id;
 pub(in crate::web) mod state;
 
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::client_cert::{Certificate, ClientCert};
 pub use self::form::{Form, FormConfig};
 pub use self::json::{Json, JsonConfig};
-pub use self::path::Path;
+pub use self::path::{Path, PathConfig};
 pub use self::payload::{Payload, PayloadConfig};
-pub use self::query::Query;
-pub use self::state::State;
+pub use self::query::{Query, QueryConfig};
+pub use self::real_ip::RealIp;
+pub use self::req_data::ReqData;
+pub use self::request_id::RequestId;
+pub use self::state::{KeyedState, State, StateKey};
 
 #[deprecated]
 #[doc(hidden)]
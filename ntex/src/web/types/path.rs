@@ -3,9 +3,13 @@ use std::{fmt, ops};
 
 use serde::de;
 
+use crate::http::{Payload, Response};
+use crate::router::{Path as MatchInfo, PathDeserializer, ResourcePath};
+use crate::util::Ready;
 use crate::web::error::{ErrorRenderer, PathError};
 use crate::web::{FromRequest, HttpRequest};
-use crate::{http::Payload, router::PathDeserializer, util::Ready};
+
+use super::helpers::{ErrorHandler, Track, Tracker};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 /// Extract typed information from the request's path.
@@ -158,18 +162,166 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        Ready::from(
-            de::Deserialize::deserialize(PathDeserializer::new(req.match_info()))
-                .map(|inner| Path { inner })
-                .map_err(move |e| {
-                    log::debug!(
-                        "Failed during Path extractor deserialization. \
-                         Request path: {:?}",
-                        req.path()
-                    );
-                    PathError::from(e)
-                }),
-        )
+        let insensitive =
+            matches!(req.app_state::<PathConfig>(), Some(cfg) if cfg.insensitive);
+        let result = deserialize(req.match_info(), insensitive);
+        if result.is_err() {
+            log::debug!(
+                "Failed during Path extractor deserialization. \
+                 Request path: {:?}",
+                req.path()
+            );
+        }
+        Ready::from(result)
+    }
+
+    fn error_response(err: &PathError, req: &HttpRequest) -> Option<Response> {
+        req.app_state::<PathConfig>()
+            .and_then(|cfg| cfg.err_handler.call(err, req))
+    }
+}
+
+/// Path extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::PathConfig, App, HttpResponse};
+///
+/// async fn index(id: web::types::Path<u32>) -> String {
+///     format!("User {}", id)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/users/{id}")
+///             .state(PathConfig::default().error_handler(|err, _| {
+///                 HttpResponse::BadRequest().json(&serde_json::json!({
+///                     "segment": err.name(),
+///                     "error": err.to_string(),
+///                 }))
+///             }))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct PathConfig {
+    err_handler: ErrorHandler<PathError>,
+    insensitive: bool,
+}
+
+impl PathConfig {
+    /// Set custom error handler
+    ///
+    /// Error handler creates response for path extractor errors,
+    /// it is used with any error renderer.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&PathError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = ErrorHandler::new(handler);
+        self
+    }
+
+    /// Match enum variant names ascii case-insensitively
+    ///
+    /// By default variant names are case-sensitive.
+    pub fn case_insensitive_variants(mut self) -> Self {
+        self.insensitive = true;
+        self
+    }
+}
+
+impl fmt::Debug for PathConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PathConfig")
+            .field("error_handler", &self.err_handler)
+            .field("case_insensitive_variants", &self.insensitive)
+            .finish()
+    }
+}
+
+/// Deserialize path parameters, keep track of the failed segment
+fn deserialize<T, P>(path: &MatchInfo<P>, insensitive: bool) -> Result<Path<T>, PathError>
+where
+    T: de::DeserializeOwned,
+    P: ResourcePath,
+{
+    let mut state = State {
+        segments: path.len(),
+        ..Default::default()
+    };
+    let mut inner = PathDeserializer::new(path);
+    if insensitive {
+        inner = inner.case_insensitive_variants();
+    }
+    let error = match T::deserialize(Track::new(inner, &mut state)) {
+        Ok(inner) => return Ok(Path { inner }),
+        Err(error) => error,
+    };
+
+    if let Some(index) = state.segment {
+        if let Some((name, _)) = path.iter().nth(index) {
+            return Err(PathError::Segment {
+                index,
+                name: name.to_string(),
+                error,
+            });
+        }
+    }
+    if let Some(fields) = state.fields {
+        if let Some(name) = fields.iter().find(|name| path.get(name).is_none()) {
+            return Err(PathError::Missing {
+                index: None,
+                name: Some(name.to_string()),
+            });
+        }
+    } else if let Some(len) = state.len {
+        if path.len() < len {
+            return Err(PathError::Missing {
+                index: Some(path.len()),
+                name: None,
+            });
+        }
+    }
+    Err(PathError::Deserialize(error))
+}
+
+#[derive(Default)]
+struct State {
+    /// Number of matched segments
+    segments: usize,
+    /// Segment that is being deserialized
+    segment: Option<usize>,
+    /// Expected struct fields
+    fields: Option<&'static [&'static str]>,
+    /// Expected number of segments
+    len: Option<usize>,
+}
+
+/// Path segment being deserialized
+impl Tracker for State {
+    /// Single value gets deserialized from the first segment
+    fn single(&mut self) {
+        if self.segments == 1 {
+            self.segment = Some(0);
+        }
+        self.len = Some(1);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.len = Some(len);
+    }
+
+    fn fields(&mut self, fields: &'static [&'static str]) {
+        self.fields = Some(fields);
+    }
+
+    fn element(&mut self, index: usize) {
+        self.segment = Some(index);
+    }
+
+    fn done(&mut self) {
+        self.segment = None;
     }
 }
 
@@ -186,7 +338,7 @@ mod tests {
         value: String,
     }
 
-    #[derive(serde::Deserialize)]
+    #[derive(serde::Deserialize, Debug)]
     struct Test2 {
         key: String,
         value: u32,
@@ -282,4 +434,159 @@ mod tests {
         assert_eq!(res[0], "name".to_owned());
         assert_eq!(res[1], "32".to_owned());
     }
+
+    #[crate::rt_test]
+    async fn test_path_error() {
+        let mut router = Router::<usize>::build();
+        router.path("/{id}/{name}/", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/abc/user1/").to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+
+        let err = from_request::<Path<(u32, String)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PathError::Segment { .. }));
+        assert_eq!(err.index(), Some(0));
+        assert_eq!(err.name(), Some("id"));
+
+        let err = from_request::<Path<(String, String, String)>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PathError::Missing { .. }));
+        assert_eq!(err.index(), Some(2));
+        assert_eq!(err.to_string(), "Path segment 2 is missing");
+
+        let err = from_request::<Path<Test2>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PathError::Missing { .. }));
+        assert_eq!(err.name(), Some("key"));
+
+        let mut router = Router::<usize>::build();
+        router.path("/{key}/{value}/", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/name/abc/").to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+
+        let err = from_request::<Path<Test2>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PathError::Segment { .. }));
+        assert_eq!(err.index(), Some(1));
+        assert_eq!(err.name(), Some("value"));
+        assert!(err.to_string().starts_with("Path segment `value`"));
+    }
+
+    #[crate::rt_test]
+    async fn test_optional_segment() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Order {
+            Asc,
+            Desc,
+        }
+
+        #[derive(serde::Deserialize, Debug)]
+        struct Archive {
+            year: u16,
+            order: Option<Order>,
+        }
+
+        let mut router = Router::<usize>::build();
+        router.path("/archive/{year}/{order?}", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/archive/2023/desc").to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+        let s = from_request::<Path<Archive>>(&req, &mut pl).await.unwrap();
+        assert_eq!((s.year, &s.order), (2023, &Some(Order::Desc)));
+        let s = from_request::<Path<(u16, Option<Order>)>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(s.into_inner(), (2023, Some(Order::Desc)));
+
+        let mut req = TestRequest::with_uri("/archive/2023").to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+        let s = from_request::<Path<Archive>>(&req, &mut pl).await.unwrap();
+        assert_eq!((s.year, &s.order), (2023, &None));
+        let s = from_request::<Path<(u16, Option<Order>)>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(s.into_inner(), (2023, None));
+
+        // variant names
+        let mut req = TestRequest::with_uri("/archive/2023/ASC").to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+        let err = from_request::<Path<Archive>>(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(err.name(), Some("order"));
+        assert!(err.to_string().contains("expected one of `asc`, `desc`"));
+
+        let mut req = TestRequest::with_uri("/archive/2023/ASC")
+            .state(PathConfig::default().case_insensitive_variants())
+            .to_srv_request();
+        router.recognize(req.match_info_mut());
+        let (req, mut pl) = req.into_parts();
+        let s = from_request::<Path<Archive>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.order, Some(Order::Asc));
+    }
+
+    #[crate::rt_test]
+    async fn test_error_handler() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App, HttpResponse};
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .state(PathConfig::default().error_handler(|err, _| {
+                            HttpResponse::BadRequest().body(format!(
+                                "{:?} {:?}",
+                                err.index(),
+                                err.name()
+                            ))
+                        }))
+                        .route(
+                            "/users/{id}/{name}",
+                            web::get().to(|p: Path<(u32, String)>| async move {
+                                HttpResponse::Ok().body(p.into_inner().1)
+                            }),
+                        ),
+                )
+                .route(
+                    "/users/{id}",
+                    web::get().to(|p: Path<u32>| async move {
+                        HttpResponse::Ok().body(p.to_string())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api/users/1/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "test");
+
+        let req = TestRequest::with_uri("/api/users/abc/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), crate::http::StatusCode::BAD_REQUEST);
+        assert_eq!(read_body(resp).await, "Some(0) Some(\"id\")");
+
+        // default response
+        let req = TestRequest::with_uri("/users/abc").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), crate::http::StatusCode::NOT_FOUND);
+        assert!(read_body(resp)
+            .await
+            .starts_with(b"Path segment `id` deserialize error"));
+    }
 }
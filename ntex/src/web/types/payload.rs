@@ -161,6 +161,15 @@ impl<Err: ErrorRenderer> FromRequest<Err> for Bytes {
         let limit = cfg.limit;
         Either::Left(Box::pin(HttpMessageBody::new(req, payload).limit(limit)))
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        Some(
+            req.app_state::<PayloadConfig>()
+                .map(|c| c.limit)
+                .unwrap_or(262_144),
+        )
+    }
 }
 
 /// Extract text information from a request's body.
@@ -233,12 +242,44 @@ impl<Err: ErrorRenderer> FromRequest<Err> for String {
             }
         }))
     }
+
+    #[inline]
+    fn payload_limit(req: &HttpRequest) -> Option<usize> {
+        Some(
+            req.app_state::<PayloadConfig>()
+                .map(|c| c.limit)
+                .unwrap_or(262_144),
+        )
+    }
 }
 /// Payload configuration for request's payload.
+///
+/// Configuration could be set for application, scope or resource with
+/// `state()` method, configuration of innermost scope or resource is used.
+///
+/// ```rust
+/// use ntex::web::{self, types::PayloadConfig, App};
+///
+/// fn main() {
+///     let app = App::new()
+///         .state(PayloadConfig::new(4096))
+///         .service(
+///             web::resource("/upload")
+///                 .state(
+///                     PayloadConfig::new(16 * 1024 * 1024)
+///                         .mimetype(mime::IMAGE_PNG)
+///                         .mimetype(mime::IMAGE_JPEG),
+///                 )
+///                 .route(web::post().to(|body: ntex::util::Bytes| async move {
+///                     format!("{} bytes", body.len())
+///                 })),
+///         );
+/// }
+/// ```
 #[derive(Clone, Debug)]
 pub struct PayloadConfig {
-    limit: usize,
-    mimetype: Option<Mime>,
+    pub(crate) limit: usize,
+    mimetype: Vec<Mime>,
 }
 
 impl PayloadConfig {
@@ -256,19 +297,27 @@ impl PayloadConfig {
         self
     }
 
-    /// Set required mime-type of the request. By default mime type is not
-    /// enforced.
+    /// Add allowed mime-type of the request.
+    ///
+    /// Could be called multiple times to allow several mime types. Request
+    /// with other content type is rejected with `415 Unsupported Media Type`
+    /// response before payload is read. Mime type parameters are ignored.
+    /// By default mime type is not enforced.
     pub fn mimetype(mut self, mt: Mime) -> Self {
-        self.mimetype = Some(mt);
+        self.mimetype.push(mt);
         self
     }
 
     fn check_mimetype(&self, req: &HttpRequest) -> Result<(), PayloadError> {
         // check content-type
-        if let Some(ref mt) = self.mimetype {
+        if !self.mimetype.is_empty() {
             match req.mime_type() {
                 Ok(Some(ref req_mt)) => {
-                    if mt != req_mt {
+                    if !self
+                        .mimetype
+                        .iter()
+                        .any(|mt| mt.essence_str() == req_mt.essence_str())
+                    {
                         return Err(PayloadError::from(
                             error::ContentTypeError::Unexpected,
                         ));
@@ -290,7 +339,7 @@ impl Default for PayloadConfig {
     fn default() -> Self {
         PayloadConfig {
             limit: 262_144,
-            mimetype: None,
+            mimetype: Vec::new(),
         }
     }
 }
@@ -300,7 +349,7 @@ impl Default for PayloadConfig {
 /// Load http message body.
 ///
 /// By default only 256Kb payload reads to a memory, then
-/// `PayloadError::Overflow` get returned. Use `MessageBody::limit()`
+/// `PayloadError::LimitExceeded` get returned. Use `MessageBody::limit()`
 /// method to change upper limit.
 struct HttpMessageBody {
     limit: usize,
@@ -381,7 +430,12 @@ impl Future for HttpMessageBody {
 
         if let Some(len) = self.length.take() {
             if len > self.limit {
-                return Poll::Ready(Err(PayloadError::from(error::PayloadError::Overflow)));
+                return Poll::Ready(Err(PayloadError::from(
+                    error::PayloadError::LimitExceeded {
+                        limit: self.limit,
+                        size: len,
+                    },
+                )));
             }
         }
 
@@ -394,7 +448,10 @@ impl Future for HttpMessageBody {
             while let Some(item) = stream_recv(&mut stream).await {
                 let chunk = item?;
                 if body.len() + chunk.len() > limit {
-                    return Err(PayloadError::from(error::PayloadError::Overflow));
+                    return Err(PayloadError::from(error::PayloadError::LimitExceeded {
+                        limit,
+                        size: body.len() + chunk.len(),
+                    }));
                 } else {
                     body.extend_from_slice(&chunk);
                 }
@@ -430,6 +487,73 @@ mod tests {
         let req = TestRequest::with_header(header::CONTENT_TYPE, "application/json")
             .to_http_request();
         assert!(cfg.check_mimetype(&req).is_ok());
+
+        // allow-list, parameters are ignored
+        let cfg = cfg.mimetype(mime::TEXT_PLAIN);
+        let req =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .to_http_request();
+        assert!(cfg.check_mimetype(&req).is_ok());
+        let req =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/html").to_http_request();
+        assert!(cfg.check_mimetype(&req).is_err());
+    }
+
+    #[crate::rt_test]
+    async fn test_payload_config_nested() {
+        use crate::http::{Method, StatusCode};
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App};
+
+        let srv = init_service(
+            App::new()
+                .state(PayloadConfig::new(4))
+                .route("/", web::post().to(|body: Bytes| async move { body }))
+                .service(
+                    web::scope("/scope")
+                        .state(PayloadConfig::new(8))
+                        .route("/", web::post().to(|body: String| async move { body }))
+                        .service(
+                            web::resource("/upload")
+                                .state(
+                                    PayloadConfig::new(16)
+                                        .mimetype(mime::IMAGE_PNG)
+                                        .mimetype(mime::IMAGE_JPEG),
+                                )
+                                .route(web::post().to(|body: Bytes| async move { body })),
+                        ),
+                ),
+        )
+        .await;
+
+        let req = |uri: &str, ct: &str, body: &'static [u8]| {
+            TestRequest::with_uri(uri)
+                .method(Method::POST)
+                .header(header::CONTENT_TYPE, ct)
+                .header(header::CONTENT_LENGTH, body.len().to_string())
+                .set_payload(Bytes::from_static(body))
+                .to_request()
+        };
+
+        let resp = call_service(&srv, req("/", "text/plain", b"1234567890")).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = read_body(resp).await;
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("exceeds limit of 4 bytes"));
+
+        let resp = call_service(&srv, req("/scope/", "text/plain", b"12345678")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&srv, req("/scope/", "text/plain", b"123456789")).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let resp =
+            call_service(&srv, req("/scope/upload", "image/png", b"1234567890")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"1234567890"));
+        let resp =
+            call_service(&srv, req("/scope/upload", "image/gif", b"1234567890")).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
 
     #[crate::rt_test]
@@ -470,6 +594,27 @@ mod tests {
         assert!(from_request::<Bytes>(&req, &mut pl).await.is_err());
     }
 
+    #[crate::rt_test]
+    async fn test_string_charset() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/plain; charset=latin1")
+                .set_payload(Bytes::from_static(b"caf\xe9"))
+                .to_http_parts();
+        let s = from_request::<String>(&req, &mut pl).await.unwrap();
+        assert_eq!(s, "caf\u{e9}");
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/plain; charset=unknown")
+                .set_payload(Bytes::from_static(b"test"))
+                .to_http_parts();
+        assert!(matches!(
+            from_request::<String>(&req, &mut pl).await,
+            Err(PayloadError::ContentType(
+                error::ContentTypeError::UnknownEncoding
+            ))
+        ));
+    }
+
     #[crate::rt_test]
     async fn test_string() {
         let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
@@ -509,7 +654,10 @@ mod tests {
             .into_parts();
         let res = HttpMessageBody::new(&req, &mut pl).await;
         match res.err().unwrap() {
-            PayloadError::Payload(error::PayloadError::Overflow) => (),
+            PayloadError::Payload(error::PayloadError::LimitExceeded {
+                limit: 262_144,
+                size: 1_000_000,
+            }) => (),
             _ => unreachable!("error"),
         }
 
@@ -524,7 +672,10 @@ mod tests {
             .to_http_parts();
         let res = HttpMessageBody::new(&req, &mut pl).limit(5).await;
         match res.err().unwrap() {
-            PayloadError::Payload(error::PayloadError::Overflow) => (),
+            PayloadError::Payload(error::PayloadError::LimitExceeded {
+                limit: 5,
+                size: 14,
+            }) => (),
             _ => unreachable!("error"),
         }
     }
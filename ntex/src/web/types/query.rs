@@ -3,9 +3,12 @@ use std::{fmt, ops};
 
 use serde::de;
 
+use crate::http::{Payload, Response};
+use crate::util::Ready;
 use crate::web::error::{ErrorRenderer, QueryPayloadError};
 use crate::web::{FromRequest, HttpRequest};
-use crate::{http::Payload, util::Ready};
+
+use super::helpers::{ErrorHandler, Track, Tracker};
 
 /// Extract typed information from the request's query.
 ///
@@ -58,9 +61,16 @@ impl<T> Query<T> {
     where
         T: de::DeserializeOwned,
     {
-        serde_urlencoded::from_str::<T>(query_str)
-            .map(|val| Ok(Query(val)))
-            .unwrap_or_else(move |e| Err(QueryPayloadError::Deserialize(e)))
+        let mut field = None;
+        let de = serde_urlencoded::Deserializer::new(form_urlencoded::parse(
+            query_str.as_bytes(),
+        ));
+        T::deserialize(Track::new(de, &mut field))
+            .map(Query)
+            .map_err(|error| match field {
+                Some(field) => QueryPayloadError::Field { field, error },
+                None => QueryPayloadError::Deserialize(error),
+            })
     }
 }
 
@@ -132,11 +142,9 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        serde_urlencoded::from_str::<T>(req.query_string())
-            .map(|val| Ready::Ok(Query(val)))
+        Query::from_query(req.query_string())
+            .map(Ready::Ok)
             .unwrap_or_else(move |e| {
-                let e = QueryPayloadError::Deserialize(e);
-
                 log::debug!(
                     "Failed during Query extractor deserialization. \
                      Request path: {:?}",
@@ -145,6 +153,76 @@ where
                 Ready::Err(e)
             })
     }
+
+    fn error_response(err: &QueryPayloadError, req: &HttpRequest) -> Option<Response> {
+        req.app_state::<QueryConfig>()
+            .and_then(|cfg| cfg.err_handler.call(err, req))
+    }
+}
+
+/// Query extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::QueryConfig, App, HttpResponse};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     page: u32,
+/// }
+///
+/// async fn index(info: web::types::Query<Info>) -> String {
+///     format!("Page {}", info.page)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .state(QueryConfig::default().error_handler(|err, _| {
+///                 HttpResponse::BadRequest().json(&serde_json::json!({
+///                     "field": err.field(),
+///                     "error": err.to_string(),
+///                 }))
+///             }))
+///             .route(web::get().to(index)),
+///     );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct QueryConfig {
+    err_handler: ErrorHandler<QueryPayloadError>,
+}
+
+impl QueryConfig {
+    /// Set custom error handler
+    ///
+    /// Error handler creates response for query extractor errors,
+    /// it is used with any error renderer.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&QueryPayloadError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = ErrorHandler::new(handler);
+        self
+    }
+}
+
+impl fmt::Debug for QueryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryConfig")
+            .field("error_handler", &self.err_handler)
+            .finish()
+    }
+}
+
+/// Name of the query parameter being deserialized
+impl Tracker for Option<String> {
+    fn key(&mut self, name: &str) {
+        *self = Some(name.to_string());
+    }
+
+    fn done(&mut self) {
+        *self = None;
+    }
 }
 
 #[cfg(test)]
@@ -192,4 +270,80 @@ mod tests {
         let s = s.into_inner();
         assert_eq!(s.id, "test1");
     }
+
+    #[derive(serde::Deserialize, Debug)]
+    struct Params {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Optional {
+        page: Option<u32>,
+        sort: Option<String>,
+    }
+
+    #[test]
+    fn test_error_field() {
+        let err = Query::<Params>::from_query("name=test&id=abc").unwrap_err();
+        assert_eq!(err.field(), Some("id"));
+        assert!(err.to_string().contains("field `id`"));
+
+        let err = Query::<Params>::from_query("id=1").unwrap_err();
+        assert_eq!(err.field(), None);
+        assert!(err.to_string().contains("missing field `name`"));
+
+        let params = Query::<Params>::from_query("id=1&name=test%20name").unwrap();
+        assert_eq!(params.id, 1);
+        assert_eq!(params.name, "test name");
+
+        let params =
+            Query::<std::collections::HashMap<u32, String>>::from_query("1=a&2=b").unwrap();
+        assert_eq!(params[&2], "b");
+    }
+
+    #[test]
+    fn test_empty_query() {
+        let s = Query::<Optional>::from_query("").unwrap();
+        assert_eq!(
+            s.into_inner(),
+            Optional {
+                page: None,
+                sort: None
+            }
+        );
+
+        let s = Query::<Optional>::from_query("page=2").unwrap();
+        assert_eq!(s.page, Some(2));
+        assert_eq!(s.sort, None);
+    }
+
+    #[crate::rt_test]
+    async fn test_error_handler() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App, HttpResponse};
+
+        let srv = init_service(
+            App::new()
+                .state(QueryConfig::default().error_handler(|err, _| {
+                    HttpResponse::BadRequest().body(format!("invalid {:?}", err.field()))
+                }))
+                .route(
+                    "/",
+                    web::get().to(|p: Query<Params>| async move {
+                        HttpResponse::Ok().body(p.into_inner().name)
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/?id=10&name=test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "test");
+
+        let req = TestRequest::with_uri("/?id=-10&name=test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), crate::http::StatusCode::BAD_REQUEST);
+        assert_eq!(read_body(resp).await, "invalid Some(\"id\")");
+    }
 }
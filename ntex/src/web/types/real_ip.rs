@@ -0,0 +1,69 @@
+//! Real ip extractor
+use std::{fmt, net::IpAddr, ops};
+
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, RealIpError};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Real ip address of the client
+///
+/// Client address is resolved with `ConnectionInfo::real_ip()`, forwarded
+/// headers are used only if request comes from trusted proxy configured with
+/// `App::trusted_proxies()`. Extraction fails if peer address is unknown.
+///
+/// ```rust
+/// use ntex::web::{self, types::RealIp, App};
+///
+/// async fn index(ip: RealIp) -> String {
+///     format!("Your ip: {}", ip)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .trusted_proxies(["127.0.0.1", "10.0.0.0/8"])
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RealIp(pub IpAddr);
+
+impl RealIp {
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> IpAddr {
+        self.0
+    }
+}
+
+impl ops::Deref for RealIp {
+    type Target = IpAddr;
+
+    fn deref(&self) -> &IpAddr {
+        &self.0
+    }
+}
+
+impl fmt::Display for RealIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for RealIp {
+    type Error = RealIpError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(ip) = req.connection_info().real_ip() {
+            Ready::Ok(RealIp(ip))
+        } else {
+            log::debug!(
+                "Failed to construct RealIp extractor, peer address is unknown. \
+                 Request path: {:?}",
+                req.path()
+            );
+            Ready::Err(RealIpError::Unknown)
+        }
+    }
+}
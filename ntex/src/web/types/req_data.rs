@@ -0,0 +1,157 @@
+//! Request data extractor
+use std::{any::type_name, fmt, ops};
+
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, ReqDataError};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Request data extractor
+///
+/// Extracts value of type `T` from request extensions. Middlewares could use
+/// request extensions to pass per-request context, like authenticated user or
+/// tenant, to handlers. Value is cloned, use `Rc` for expensive values.
+///
+/// If value is not set, extraction fails with *Internal Server Error*
+/// response.
+///
+/// ```rust
+/// use ntex::web::{self, types::ReqData, App, WebRequest};
+/// use ntex::service::fn_service;
+///
+/// #[derive(Clone)]
+/// struct User(String);
+///
+/// async fn index(user: ReqData<User>) -> String {
+///     format!("Hello, {}", user.0)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .filter(fn_service(|req: WebRequest<_>| async move {
+///             req.extensions_mut().insert(User("admin".to_string()));
+///             Ok(req)
+///         }))
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct ReqData<T>(T);
+
+impl<T> ReqData<T> {
+    /// Unwrap into inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for ReqData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for ReqData<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReqData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReqData").field(&self.0).finish()
+    }
+}
+
+impl<T: Clone + 'static, Err: ErrorRenderer> FromRequest<Err> for ReqData<T> {
+    type Error = ReqDataError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(value) = req.extensions().get::<T>() {
+            Ready::Ok(ReqData(value.clone()))
+        } else {
+            log::debug!(
+                "Failed to construct ReqData<{}> extractor. \
+                 Request path: {:?}",
+                type_name::<T>(),
+                req.path()
+            );
+            Ready::Err(ReqDataError::NotFound(type_name::<T>()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::service::fn_service;
+    use crate::web::test::{self, TestRequest};
+    use crate::web::{self, App, DefaultError, WebRequest};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Tenant(&'static str);
+
+    #[crate::rt_test]
+    async fn test_req_data() {
+        let app = test::init_service(
+            App::new()
+                .filter(fn_service(|req: WebRequest<_>| async move {
+                    if let Some(tenant) = req.headers().get("x-tenant") {
+                        let tenant = if tenant == "a" { "a" } else { "other" };
+                        req.extensions_mut().insert(Tenant(tenant));
+                        req.extensions_mut().insert(Rc::new(10usize));
+                    }
+                    Ok(req)
+                }))
+                .route(
+                    "/",
+                    web::get().to(|t: ReqData<Tenant>, n: ReqData<Rc<usize>>| async move {
+                        format!("{} {}", t.0 .0, **n)
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_header("x-tenant", "a").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "a 10");
+
+        let req = TestRequest::default().to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = test::read_body(res).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains("Tenant"));
+    }
+
+    #[crate::rt_test]
+    async fn test_req_data_inner() {
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(Tenant("a"));
+        let mut data = <ReqData<Tenant> as FromRequest<DefaultError>>::from_request(
+            &req,
+            &mut Payload::None,
+        )
+        .await
+        .unwrap();
+        data.0 = Tenant("b");
+        assert_eq!(format!("{:?}", data), "ReqData(Tenant(\"b\"))");
+        assert_eq!(data.into_inner(), Tenant("b"));
+
+        let req = TestRequest::default().to_http_request();
+        let err = <ReqData<Tenant> as FromRequest<DefaultError>>::from_request(
+            &req,
+            &mut Payload::None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, ReqDataError::NotFound(type_name::<Tenant>()));
+    }
+}
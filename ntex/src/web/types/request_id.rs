@@ -0,0 +1,80 @@
+//! Request id extractor
+use std::{fmt, ops};
+
+use crate::http::{header::HeaderValue, Payload};
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, RequestIdError};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Request id extractor
+///
+/// Request id is set by [`RequestId`](crate::web::middleware::RequestId)
+/// middleware, extraction fails if middleware is not configured.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, types::RequestId, App};
+///
+/// async fn index(id: RequestId) -> String {
+///     format!("Request id: {}", id)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::RequestId::default())
+///         .route("/", web::get().to(index));
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(pub(crate) HeaderValue);
+
+impl RequestId {
+    /// Get request id as a string
+    pub fn as_str(&self) -> &str {
+        // request id is validated by middleware
+        self.0.to_str().unwrap_or_default()
+    }
+
+    /// Get request id as a header value
+    pub fn as_header(&self) -> &HeaderValue {
+        &self.0
+    }
+}
+
+impl ops::Deref for RequestId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequestId").field(&self.as_str()).finish()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for RequestId {
+    type Error = RequestIdError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(id) = req.extensions().get::<RequestId>() {
+            Ready::Ok(id.clone())
+        } else {
+            log::debug!(
+                "Failed to construct RequestId extractor. \
+                 Request path: {:?}",
+                req.path()
+            );
+            Ready::Err(RequestIdError::NotConfigured)
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{marker::PhantomData, ops::Deref};
+use std::{fmt, marker::PhantomData, ops::Deref};
 
 use crate::web::error::{ErrorRenderer, StateExtractorError};
 use crate::web::extract::FromRequest;
@@ -86,11 +86,113 @@ impl<T: 'static, E: ErrorRenderer> FromRequest<E> for State<T> {
             Ready::Ok(Self(req.0.app_state.clone(), PhantomData))
         } else {
             log::debug!(
-                "Failed to construct App-level State extractor. \
+                "Failed to construct App-level State<{}> extractor. \
                  Request path: {:?}",
+                std::any::type_name::<T>(),
                 req.path()
             );
-            Ready::Err(StateExtractorError::NotConfigured)
+            Ready::Err(StateExtractorError::NotConfigured(
+                std::any::type_name::<T>(),
+            ))
+        }
+    }
+}
+
+/// Key of keyed application state.
+///
+/// Key type is used by `KeyedState<K, T>` extractor to select state value.
+pub trait StateKey: 'static {
+    /// Key of the state value
+    const KEY: &'static str;
+}
+
+/// Keyed application state.
+///
+/// Keyed state allows to store multiple values of the same type. Values
+/// are stored with `App::state_keyed()`, `Scope::state_keyed()` or
+/// `Resource::state_keyed()` methods and could be accessed with
+/// `KeyedState<K, T>` extractor, where `K` is a type that implements
+/// `StateKey` trait. Keyed state of scope or resource shadows app state
+/// with the same key.
+///
+/// If state is not set for a key, using `KeyedState<K, T>` extractor
+/// would cause *Internal Server Error* response.
+///
+/// ```rust
+/// use ntex::web::{self, types::KeyedState, types::StateKey, App};
+///
+/// #[derive(Clone)]
+/// struct Pool(&'static str);
+///
+/// struct Primary;
+/// impl StateKey for Primary {
+///     const KEY: &'static str = "primary";
+/// }
+///
+/// struct Replica;
+/// impl StateKey for Replica {
+///     const KEY: &'static str = "replica";
+/// }
+///
+/// async fn index(
+///     primary: KeyedState<Primary, Pool>,
+///     replica: KeyedState<Replica, Pool>,
+/// ) -> String {
+///     format!("{} {}", primary.get_ref().0, replica.get_ref().0)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .state_keyed("primary", Pool("db1"))
+///         .state_keyed("replica", Pool("db2"))
+///         .service(web::resource("/index.html").to(index));
+/// }
+/// ```
+pub struct KeyedState<K, T>(AppState, PhantomData<(K, T)>);
+
+impl<K: StateKey, T: 'static> KeyedState<K, T> {
+    /// Get reference to inner app data.
+    pub fn get_ref(&self) -> &T {
+        self.0.get_keyed::<T>(K::KEY).expect("Unexpected state")
+    }
+}
+
+impl<K: StateKey, T: 'static> Deref for KeyedState<K, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get_ref()
+    }
+}
+
+impl<K, T> Clone for KeyedState<K, T> {
+    fn clone(&self) -> KeyedState<K, T> {
+        KeyedState(self.0.clone(), PhantomData)
+    }
+}
+
+impl<K: StateKey, T> fmt::Debug for KeyedState<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("KeyedState").field(&K::KEY).finish()
+    }
+}
+
+impl<K: StateKey, T: 'static, E: ErrorRenderer> FromRequest<E> for KeyedState<K, T> {
+    type Error = StateExtractorError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if req.0.app_state.get_keyed::<T>(K::KEY).is_some() {
+            Ready::Ok(Self(req.0.app_state.clone(), PhantomData))
+        } else {
+            log::debug!(
+                "Failed to construct App-level KeyedState extractor for key {:?}. \
+                 Request path: {:?}",
+                K::KEY,
+                req.path()
+            );
+            Ready::Err(StateExtractorError::KeyNotConfigured(K::KEY))
         }
     }
 }
@@ -101,8 +203,8 @@ mod tests {
 
     use super::*;
     use crate::http::StatusCode;
-    use crate::web::test::{self, init_service, TestRequest};
-    use crate::web::{self, App, HttpResponse};
+    use crate::web::test::{self, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest, HttpResponse};
 
     #[crate::rt_test]
     async fn test_state_extractor() {
@@ -128,6 +230,10 @@ mod tests {
         let req = TestRequest::default().to_request();
         let res = srv.call(req).await.unwrap();
         assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            read_body(res).await,
+            "App state of type usize is not configured, to configure use App::state()"
+        );
     }
 
     #[cfg(feature = "tokio")]
@@ -216,4 +322,119 @@ mod tests {
         let resp = srv.call(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
     }
+
+    #[crate::rt_test]
+    async fn test_state_shadowing() {
+        async fn handler(req: HttpRequest, data: web::types::State<usize>) -> String {
+            format!("{} {}", *data, req.app_state::<&'static str>().unwrap())
+        }
+
+        let srv = init_service(
+            App::new()
+                .state(1usize)
+                .state("app")
+                .route("/app", web::get().to(handler))
+                .service(
+                    web::scope("/scope")
+                        .state(2usize)
+                        .state("scope")
+                        .route("/", web::get().to(handler))
+                        .service(
+                            web::resource("/res")
+                                .state(3usize)
+                                .state("res")
+                                .route(web::get().to(handler)),
+                        ),
+                ),
+        )
+        .await;
+
+        for (path, expected) in [
+            ("/app", "1 app"),
+            ("/scope/", "2 scope"),
+            ("/scope/res", "3 res"),
+        ] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = srv.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(read_body(resp).await, expected);
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_keyed_state() {
+        #[derive(Debug)]
+        struct Pool(&'static str);
+
+        struct Primary;
+        impl StateKey for Primary {
+            const KEY: &'static str = "primary";
+        }
+        struct Replica;
+        impl StateKey for Replica {
+            const KEY: &'static str = "replica";
+        }
+        struct Missing;
+        impl StateKey for Missing {
+            const KEY: &'static str = "missing";
+        }
+
+        async fn index(
+            primary: KeyedState<Primary, Pool>,
+            replica: KeyedState<Replica, Pool>,
+        ) -> String {
+            let _ = primary.clone();
+            format!("{} {}", primary.get_ref().0, replica.get_ref().0)
+        }
+
+        let srv = init_service(
+            App::new()
+                .state_keyed("primary", Pool("db1"))
+                .state_keyed("replica", Pool("db2"))
+                .service(web::resource("/").to(index))
+                .service(
+                    web::scope("/scope")
+                        .state_keyed("replica", Pool("db3"))
+                        .service(web::resource("/").to(index))
+                        .service(
+                            web::resource("/res")
+                                .state_keyed("primary", Pool("db4"))
+                                .to(index),
+                        ),
+                )
+                .service(
+                    web::resource("/missing")
+                        .to(|_: KeyedState<Missing, Pool>| async { HttpResponse::Ok() }),
+                )
+                .service(web::resource("/req").to(
+                    |req: crate::web::HttpRequest| async move {
+                        assert!(req.app_state::<Pool>().is_none());
+                        assert!(req.app_state_keyed::<Pool>("missing").is_none());
+                        assert!(req.app_state_keyed::<usize>("primary").is_none());
+                        req.app_state_keyed::<Pool>("primary").unwrap().0
+                    },
+                )),
+        )
+        .await;
+
+        let check = |path: &'static str, body: &'static str| {
+            let srv = &srv;
+            async move {
+                let req = TestRequest::with_uri(path).to_request();
+                let resp = srv.call(req).await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert_eq!(test::read_body(resp).await, body.as_bytes());
+            }
+        };
+        check("/", "db1 db2").await;
+        check("/scope/", "db1 db3").await;
+        check("/scope/res", "db4 db3").await;
+        check("/req", "db1").await;
+
+        let req = TestRequest::with_uri("/missing").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = test::read_body(resp).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains("\"missing\""));
+    }
 }
@@ -12,8 +12,11 @@ use crate::service::{IntoServiceFactory, ServiceFactory};
 use super::config::AppConfig;
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
+use super::fallback::Fallback;
 use super::handler::Handler;
+use super::request::WebRequest;
 use super::resource::Resource;
+use super::response::WebResponse;
 use super::route::Route;
 use super::scope::Scope;
 use super::server::HttpServer;
@@ -232,6 +235,40 @@ where
     Route::new().to(handler)
 }
 
+/// Create chain of services tried in registration order.
+///
+/// Service signals that request is not handled with *404 Not Found*
+/// response, then request is passed to the next service. Chain is
+/// useful as default service.
+///
+/// ```rust
+/// use ntex::web::{self, App, HttpRequest, HttpResponse};
+///
+/// async fn assets(req: HttpRequest) -> HttpResponse {
+///     if req.path() == "/app.js" {
+///         HttpResponse::Ok().body("console.log(1)")
+///     } else {
+///         HttpResponse::NotFound().finish()
+///     }
+/// }
+///
+/// let app = App::new().default_service(
+///     web::fallback(web::get().to(assets))
+///         .or(web::get().to(|| async { HttpResponse::Ok().body("index") }))
+///         .or(web::to(|| async { HttpResponse::NotFound().json(&"not found") })),
+/// );
+/// ```
+pub fn fallback<F, U, Err>(f: F) -> Fallback<Err>
+where
+    F: IntoServiceFactory<U, WebRequest<Err>>,
+    U: ServiceFactory<WebRequest<Err>, Response = WebResponse, Error = Err::Container>
+        + 'static,
+    U::InitError: fmt::Debug,
+    Err: ErrorRenderer,
+{
+    Fallback::new(f)
+}
+
 /// Create service adapter for a specific path.
 ///
 /// ```rust
@@ -1,18 +1,225 @@
 //! WebSockets protocol support
-use std::{fmt, rc::Rc};
+use std::{cell::Cell, fmt, rc::Rc, time::Duration, time::Instant};
 
-pub use crate::ws::{CloseCode, CloseReason, Frame, Message, WsSink};
+pub use crate::ws::{CloseCode, CloseReason, Frame, Message, Protocols, WsSink};
 
 use crate::http::{body::BodySize, h1, StatusCode};
 use crate::service::{
     apply_fn, fn_factory_with_config, IntoServiceFactory, ServiceFactory,
 };
+use crate::time::{now, sleep, Seconds};
+use crate::util::{select, Bytes, Either, Ready};
 use crate::web::{HttpRequest, HttpResponse};
-use crate::ws::{self, error::HandshakeError, error::WsError, handshake};
-use crate::{io::DispatchItem, rt, time::Seconds, util::Either, util::Ready};
+use crate::ws::error::{HandshakeError, ProtocolError, WsError};
+use crate::ws::{self, handshake_with_protocols};
+use crate::{io::DispatchItem, rt};
+
+/// Websocket service configuration
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    protocols: Protocols,
+    aggregate: bool,
+    max_message_size: usize,
+    write_limit: ws::WriteLimit,
+    heartbeat: Heartbeat,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        WsConfig {
+            protocols: Protocols::default(),
+            aggregate: true,
+            max_message_size: 1_048_576,
+            write_limit: ws::WriteLimit::default(),
+            heartbeat: Heartbeat::default(),
+        }
+    }
+}
+
+impl WsConfig {
+    /// Set supported sub-protocols
+    pub fn protocols(mut self, protocols: Protocols) -> Self {
+        self.protocols = protocols;
+        self
+    }
+
+    /// Reassemble fragmented messages.
+    ///
+    /// Service receives single `Frame::Text` or `Frame::Binary` frame once
+    /// final fragment arrives. If disabled, service receives raw
+    /// `Frame::Continuation` frames. By default aggregation is enabled.
+    pub fn aggregate_continuation(mut self, enabled: bool) -> Self {
+        self.aggregate = enabled;
+        self
+    }
+
+    /// Set max size of reassembled message.
+    ///
+    /// If message exceeds this size, connection gets closed with
+    /// `1009` close code. By default max message size is set to 1mb
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Set high-water mark of [`WsSink`] write buffer.
+    ///
+    /// If size of unsent data exceeds the limit, messages sent via sink
+    /// are discarded and connection gets closed with `1013` close code.
+    /// Close messages are always sent. Limit does not apply to messages
+    /// returned by websocket service. By default write buffer is not limited.
+    pub fn write_buffer_limit(mut self, size: usize) -> Self {
+        self.write_limit.size = size;
+        self
+    }
+
+    /// Call function for messages that exceed write buffer limit.
+    ///
+    /// Function receives sink and discarded message, connection is
+    /// not closed. Takes effect only if write buffer limit is set.
+    pub fn on_write_buffer_overflow<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WsSink, Message) + 'static,
+    {
+        self.write_limit.on_overflow = Some(Rc::new(f));
+        self
+    }
+
+    /// Send heartbeat pings and close unresponsive connections.
+    ///
+    /// `Ping` message is sent every `interval`, any incoming frame
+    /// confirms connection liveness. If no frames are received during
+    /// `timeout`, connection gets closed with `1001` close code.
+    /// Service receives client's `Pong` frames. By default heartbeat
+    /// is disabled.
+    pub fn heartbeat(mut self, interval: Seconds, timeout: Seconds) -> Self {
+        self.heartbeat.interval = interval;
+        self.heartbeat.timeout = timeout;
+        self
+    }
+
+    /// Call function when connection gets closed by heartbeat timeout
+    pub fn on_heartbeat_timeout<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WsSink) + 'static,
+    {
+        self.heartbeat.on_timeout = Some(Rc::new(f));
+        self
+    }
+
+    fn codec(&self) -> ws::Codec {
+        let codec = ws::Codec::new().max_message_size(self.max_message_size);
+        if self.aggregate {
+            codec.aggregate_continuation()
+        } else {
+            codec
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Heartbeat {
+    interval: Seconds,
+    timeout: Seconds,
+    on_timeout: Option<Rc<dyn Fn(&WsSink)>>,
+}
+
+impl fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("interval", &self.interval)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Heartbeat {
+    async fn run(self, sink: WsSink, alive: Rc<Cell<Instant>>) {
+        let timeout = Duration::from_secs(self.timeout.seconds());
+        let mut on_disconnect = sink.on_disconnect();
+
+        loop {
+            if let Either::Right(_) = select(sleep(self.interval), &mut on_disconnect).await
+            {
+                return;
+            }
+
+            if now().duration_since(alive.get()) >= timeout {
+                log::trace!("Websocket heartbeat timeout, closing connection");
+                if let Some(ref f) = self.on_timeout {
+                    f(&sink);
+                }
+                let _ = sink
+                    .send(Message::Close(Some(CloseCode::Away.into())))
+                    .await;
+                sink.io().close();
+                return;
+            }
+            if sink.send(Message::Ping(Bytes::new())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
 
 /// Do websocket handshake and start websockets service.
+///
+/// Fragmented messages are reassembled, see [`WsConfig`] for details.
 pub async fn start<T, F, Err>(req: HttpRequest, factory: F) -> Result<HttpResponse, Err>
+where
+    T: ServiceFactory<Frame, WsSink, Response = Option<Message>> + 'static,
+    T::Error: fmt::Debug,
+    F: IntoServiceFactory<T, Frame, WsSink>,
+    Err: From<T::InitError> + From<HandshakeError>,
+{
+    start_with_config(req, &WsConfig::default(), factory).await
+}
+
+/// Do websocket handshake, negotiate sub-protocol and start websockets service.
+///
+/// Selected protocol is available via [`WsSink::protocol()`] method.
+///
+/// ```rust
+/// use ntex::service::{fn_factory_with_config, fn_service};
+/// use ntex::web::{self, ws};
+///
+/// async fn ws_index(req: web::HttpRequest) -> Result<web::HttpResponse, web::Error> {
+///     let protocols = ws::Protocols::new(["graphql-transport-ws", "graphql-ws"]).required();
+///
+///     ws::start_with_protocols(
+///         req,
+///         &protocols,
+///         fn_factory_with_config(|sink: ws::WsSink| async move {
+///             println!("Selected protocol: {:?}", sink.protocol());
+///             Ok::<_, web::Error>(fn_service(|_: ws::Frame| async {
+///                 Ok::<_, web::Error>(None)
+///             }))
+///         }),
+///     )
+///     .await
+/// }
+/// ```
+pub async fn start_with_protocols<T, F, Err>(
+    req: HttpRequest,
+    protocols: &Protocols,
+    factory: F,
+) -> Result<HttpResponse, Err>
+where
+    T: ServiceFactory<Frame, WsSink, Response = Option<Message>> + 'static,
+    T::Error: fmt::Debug,
+    F: IntoServiceFactory<T, Frame, WsSink>,
+    Err: From<T::InitError> + From<HandshakeError>,
+{
+    let config = WsConfig::default().protocols(protocols.clone());
+    start_with_config(req, &config, factory).await
+}
+
+/// Do websocket handshake and start websockets service with custom configuration.
+pub async fn start_with_config<T, F, Err>(
+    req: HttpRequest,
+    config: &WsConfig,
+    factory: F,
+) -> Result<HttpResponse, Err>
 where
     T: ServiceFactory<Frame, WsSink, Response = Option<Message>> + 'static,
     T::Error: fmt::Debug,
@@ -20,16 +227,29 @@ where
     Err: From<T::InitError> + From<HandshakeError>,
 {
     let inner_factory = Rc::new(factory.chain().map_err(WsError::Service));
+    let heartbeat = config.heartbeat.clone();
 
     let factory = fn_factory_with_config(move |sink: WsSink| {
         let factory = inner_factory.clone();
+        let heartbeat = heartbeat.clone();
 
         async move {
             let srv = factory.create(sink.clone()).await?;
             let sink = sink.clone();
 
+            let alive = if heartbeat.interval.non_zero() {
+                let alive = Rc::new(Cell::new(now()));
+                rt::spawn(heartbeat.run(sink.clone(), alive.clone()));
+                Some(alive)
+            } else {
+                None
+            };
+
             Ok::<_, T::InitError>(apply_fn(srv, move |req, srv| match req {
                 DispatchItem::<ws::Codec>::Item(item) => {
+                    if let Some(ref alive) = alive {
+                        alive.set(now());
+                    }
                     let s = if matches!(item, Frame::Close(_)) {
                         Some(sink.clone())
                     } else {
@@ -48,6 +268,12 @@ where
                 DispatchItem::KeepAliveTimeout => {
                     Either::Right(Ready::Err(WsError::KeepAlive))
                 }
+                DispatchItem::DecoderError(ProtocolError::Overflow) => {
+                    // message is too big, close connection with 1009 code
+                    Either::Right(Ready::Ok(Some(Message::Close(Some(
+                        CloseCode::Size.into(),
+                    )))))
+                }
                 DispatchItem::DecoderError(e) | DispatchItem::EncoderError(e) => {
                     Either::Right(Ready::Err(WsError::Protocol(e)))
                 }
@@ -58,7 +284,14 @@ where
         }
     });
 
-    start_with(req, factory).await
+    upgrade(
+        req,
+        &config.protocols,
+        config.codec(),
+        &config.write_limit,
+        factory,
+    )
+    .await
 }
 
 /// Do websocket handshake and start websockets service.
@@ -66,6 +299,31 @@ pub async fn start_with<T, F, Err>(
     req: HttpRequest,
     factory: F,
 ) -> Result<HttpResponse, Err>
+where
+    T: ServiceFactory<DispatchItem<ws::Codec>, WsSink, Response = Option<Message>>
+        + 'static,
+    T::Error: fmt::Debug,
+    F: IntoServiceFactory<T, DispatchItem<ws::Codec>, WsSink>,
+    Err: From<T::InitError> + From<HandshakeError>,
+{
+    let limit = ws::WriteLimit::default();
+    upgrade(
+        req,
+        &Protocols::default(),
+        ws::Codec::new(),
+        &limit,
+        factory,
+    )
+    .await
+}
+
+async fn upgrade<T, F, Err>(
+    req: HttpRequest,
+    protocols: &Protocols,
+    codec: ws::Codec,
+    limit: &ws::WriteLimit,
+    factory: F,
+) -> Result<HttpResponse, Err>
 where
     T: ServiceFactory<DispatchItem<ws::Codec>, WsSink, Response = Option<Message>>
         + 'static,
@@ -76,7 +334,8 @@ where
     log::trace!("Start ws handshake verification for {:?}", req.path());
 
     // ws handshake
-    let res = handshake(req.head())?.finish().into_parts().0;
+    let (mut res, protocol) = handshake_with_protocols(req.head(), protocols)?;
+    let res = res.finish().into_parts().0;
 
     // extract io
     let item = req
@@ -84,15 +343,13 @@ where
         .take_io()
         .ok_or(HandshakeError::NoWebsocketUpgrade)?;
     let io = item.0;
-    let codec = item.1;
 
-    io.encode(h1::Message::Item((res, BodySize::Empty)), &codec)
+    io.encode(h1::Message::Item((res, BodySize::Empty)), &item.1)
         .map_err(|_| HandshakeError::NoWebsocketUpgrade)?;
     log::trace!("Ws handshake verification completed for {:?}", req.path());
 
     // create sink
-    let codec = ws::Codec::new();
-    let sink = WsSink::new(io.get_ref(), codec.clone());
+    let sink = WsSink::new(io.get_ref(), codec.clone(), protocol).write_limit(limit);
 
     // create ws service
     let srv = factory.into_factory().create(sink.clone()).await?;
@@ -6,6 +6,10 @@ use crate::connect::openssl;
 #[cfg(feature = "rustls")]
 use crate::connect::rustls;
 #[cfg(feature = "cookie")]
+use crate::http::client::CookieStore;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use crate::http::client::TlsConfig;
+#[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
@@ -20,7 +24,7 @@ use crate::service::{apply_fn, into_service, IntoService, Pipeline, Service};
 use crate::time::{timeout, Millis, Seconds};
 use crate::{channel::mpsc, rt, util::Ready, ws};
 
-use super::error::{WsClientBuilderError, WsClientError, WsError};
+use super::error::{ProtocolError, WsClientBuilderError, WsClientError, WsError};
 use super::transport::WsTransport;
 
 /// `WebSocket` client builder
@@ -29,21 +33,30 @@ pub struct WsClient<F, T> {
     head: Rc<RequestHead>,
     addr: Option<net::SocketAddr>,
     max_size: usize,
+    max_message_size: usize,
     server_mode: bool,
     timeout: Millis,
     keepalive_timeout: Seconds,
     extra_headers: RefCell<Option<HeaderMap>>,
+    #[cfg(feature = "cookie")]
+    cookie_store: Option<CookieStore>,
+    #[cfg(feature = "compress")]
+    deflate: Option<ws::DeflateConfig>,
     _t: marker::PhantomData<F>,
 }
 
 /// `WebSocket` client builder
 pub struct WsClientBuilder<F, T> {
     inner: Option<Inner<F, T>>,
-    err: Option<HttpError>,
+    err: Option<WsClientBuilderError>,
     protocols: Option<String>,
     origin: Option<HeaderValue>,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
+    #[cfg(feature = "cookie")]
+    cookie_store: Option<CookieStore>,
+    #[cfg(feature = "compress")]
+    deflate: Option<ws::DeflateConfig>,
 }
 
 struct Inner<F, T> {
@@ -51,6 +64,7 @@ struct Inner<F, T> {
     pub(crate) head: RequestHead,
     addr: Option<net::SocketAddr>,
     max_size: usize,
+    max_message_size: usize,
     server_mode: bool,
     timeout: Millis,
     keepalive_timeout: Seconds,
@@ -134,6 +148,7 @@ where
     pub async fn connect(&self) -> Result<WsConnection<F>, WsClientError> {
         let head = self.head.clone();
         let max_size = self.max_size;
+        let max_message_size = self.max_message_size;
         let server_mode = self.server_mode;
         let to = self.timeout;
         let keepalive_timeout = self.keepalive_timeout;
@@ -155,6 +170,16 @@ where
             HeaderValue::try_from(key.as_str()).unwrap(),
         );
 
+        #[cfg(feature = "cookie")]
+        if let Some(ref store) = self.cookie_store {
+            let existing = headers
+                .get(header::COOKIE)
+                .or_else(|| head.headers.get(header::COOKIE));
+            if let Some(value) = store.header(&head.uri, existing) {
+                headers.insert(header::COOKIE, value);
+            }
+        }
+
         let msg = Connect::new(head.uri.clone()).set_addr(self.addr);
         log::trace!("Open ws connection to {:?} addr: {:?}", head.uri, self.addr);
 
@@ -188,6 +213,11 @@ where
         };
         log::trace!("Ws handshake response is received {:?}", response);
 
+        #[cfg(feature = "cookie")]
+        if let Some(ref store) = self.cookie_store {
+            store.store(&self.head.uri, &response.headers);
+        }
+
         // verify response
         if response.status != StatusCode::SWITCHING_PROTOCOLS {
             return Err(WsClientError::InvalidResponseStatus(response.status));
@@ -230,7 +260,7 @@ where
                 log::trace!(
                     "Invalid challenge response: expected: {} received: {:?}",
                     encoded,
-                    key
+                    hdr_key
                 );
                 return Err(WsClientError::InvalidChallengeResponse(
                     encoded,
@@ -241,17 +271,50 @@ where
             log::trace!("Missing SEC-WEBSOCKET-ACCEPT header");
             return Err(WsClientError::MissingWebSocketAcceptHeader);
         };
+
+        // selected sub-protocol must be one of offered protocols
+        if let Some(hdr) = response.headers.get(&header::SEC_WEBSOCKET_PROTOCOL) {
+            let offered = self
+                .head
+                .headers
+                .get(&header::SEC_WEBSOCKET_PROTOCOL)
+                .and_then(|protos| protos.to_str().ok())
+                .map(|protos| protos.split(',').map(|p| p.trim()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if !hdr
+                .to_str()
+                .map(|proto| offered.contains(&proto.trim()))
+                .unwrap_or(false)
+            {
+                log::trace!("Invalid SEC-WEBSOCKET-PROTOCOL header: {:?}", hdr);
+                return Err(WsClientError::InvalidProtocolHeader(hdr.clone()));
+            }
+        }
+
+        let mut codec = ws::Codec::new()
+            .max_size(max_size)
+            .max_message_size(max_message_size);
+        if !server_mode {
+            codec = codec.client_mode();
+        }
+
+        // negotiated extensions
+        #[cfg(feature = "compress")]
+        match ws::DeflateConfig::accept(self.deflate, &response.headers) {
+            Ok(Some(cfg)) => codec = codec.deflate(cfg),
+            Ok(None) => (),
+            Err(hdr) => {
+                log::trace!("Invalid SEC-WEBSOCKET-EXTENSIONS header: {:?}", hdr);
+                return Err(WsClientError::InvalidExtensionsHeader(hdr));
+            }
+        }
         log::trace!("Ws handshake response verification is completed");
 
         // response and ws io
         Ok(WsConnection::new(
             io,
             ClientResponse::with_empty_payload(response),
-            if server_mode {
-                ws::Codec::new().max_size(max_size)
-            } else {
-                ws::Codec::new().max_size(max_size).client_mode()
-            },
+            codec,
             keepalive_timeout,
         ))
     }
@@ -283,7 +346,10 @@ impl WsClientBuilder<Base, ()> {
                 },
                 None,
             ),
-            Err(e) => (Default::default(), Some(e.into())),
+            Err(e) => (
+                Default::default(),
+                Some(WsClientBuilderError::Http(e.into())),
+            ),
         };
 
         WsClientBuilder {
@@ -295,6 +361,7 @@ impl WsClientBuilder<Base, ()> {
                 connector: Connector::<Uri>::default(),
                 addr: None,
                 max_size: 65_536,
+                max_message_size: 1_048_576,
                 server_mode: false,
                 timeout: Millis(5_000),
                 keepalive_timeout: Seconds(600),
@@ -302,6 +369,10 @@ impl WsClientBuilder<Base, ()> {
             }),
             #[cfg(feature = "cookie")]
             cookies: None,
+            #[cfg(feature = "cookie")]
+            cookie_store: None,
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 }
@@ -348,6 +419,17 @@ where
         self
     }
 
+    #[cfg(feature = "cookie")]
+    /// Use cookie store
+    ///
+    /// Matching cookies from the store are sent with handshake request,
+    /// cookies from handshake response are stored. Client's store is
+    /// available via `Client::cookie_store()`.
+    pub fn cookie_store(&mut self, store: CookieStore) -> &mut Self {
+        self.cookie_store = Some(store);
+        self
+    }
+
     /// Set request Origin
     pub fn origin<V, E>(&mut self, origin: V) -> &mut Self
     where
@@ -356,13 +438,14 @@ where
     {
         match HeaderValue::try_from(origin) {
             Ok(value) => self.origin = Some(value),
-            Err(e) => self.err = Some(e.into()),
+            Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
         }
         self
     }
 
     /// Set max frame size
     ///
+    /// Limit applies to payload length from the frame header.
     /// By default max size is set to 64kb
     pub fn max_frame_size(&mut self, size: usize) -> &mut Self {
         if let Some(parts) = parts(&mut self.inner, &self.err) {
@@ -371,6 +454,26 @@ where
         self
     }
 
+    /// Set max message size
+    ///
+    /// Limits size of reassembled and decompressed messages.
+    /// By default max message size is set to 1mb
+    pub fn max_message_size(&mut self, size: usize) -> &mut Self {
+        if let Some(parts) = parts(&mut self.inner, &self.err) {
+            parts.max_message_size = size;
+        }
+        self
+    }
+
+    #[cfg(feature = "compress")]
+    /// Request `permessage-deflate` extension
+    ///
+    /// If server accepts extension, connection's codec compresses messages.
+    pub fn deflate(&mut self, cfg: ws::DeflateConfig) -> &mut Self {
+        self.deflate = Some(cfg);
+        self
+    }
+
     /// Disable payload masking. By default ws client masks frame payload.
     pub fn server_mode(&mut self) -> &mut Self {
         if let Some(parts) = parts(&mut self.inner, &self.err) {
@@ -396,9 +499,9 @@ where
                     Ok(value) => {
                         parts.head.headers.append(key, value);
                     }
-                    Err(e) => self.err = Some(e.into()),
+                    Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
                 },
-                Err(e) => self.err = Some(e.into()),
+                Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
             }
         }
         self
@@ -418,9 +521,9 @@ where
                     Ok(value) => {
                         parts.head.headers.insert(key, value);
                     }
-                    Err(e) => self.err = Some(e.into()),
+                    Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
                 },
-                Err(e) => self.err = Some(e.into()),
+                Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
             }
         }
         self
@@ -442,17 +545,17 @@ where
                             Ok(value) => {
                                 parts.head.headers.insert(key, value);
                             }
-                            Err(e) => self.err = Some(e.into()),
+                            Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
                         }
                     }
                 }
-                Err(e) => self.err = Some(e.into()),
+                Err(e) => self.err = Some(WsClientBuilderError::Http(e.into())),
             }
         }
         self
     }
 
-    /// Set HTTP basic authorization header
+    /// Set HTTP basic authorization header, replaces existing header.
     pub fn basic_auth<U>(&mut self, username: U, password: Option<&str>) -> &mut Self
     where
         U: fmt::Display,
@@ -461,15 +564,15 @@ where
             Some(password) => format!("{}:{}", username, password),
             None => format!("{}:", username),
         };
-        self.header(AUTHORIZATION, format!("Basic {}", base64.encode(auth)))
+        self.set_header(AUTHORIZATION, format!("Basic {}", base64.encode(auth)))
     }
 
-    /// Set HTTP bearer authentication header
+    /// Set HTTP bearer authentication header, replaces existing header.
     pub fn bearer_auth<U>(&mut self, token: U) -> &mut Self
     where
         U: fmt::Display,
     {
-        self.header(AUTHORIZATION, format!("Bearer {}", token))
+        self.set_header(AUTHORIZATION, format!("Bearer {}", token))
     }
 
     /// Set request timeout.
@@ -509,6 +612,7 @@ where
                 head: inner.head,
                 addr: inner.addr,
                 max_size: inner.max_size,
+                max_message_size: inner.max_message_size,
                 server_mode: inner.server_mode,
                 timeout: inner.timeout,
                 keepalive_timeout: inner.keepalive_timeout,
@@ -519,6 +623,10 @@ where
             origin: self.origin.take(),
             #[cfg(feature = "cookie")]
             cookies: self.cookies.take(),
+            #[cfg(feature = "cookie")]
+            cookie_store: self.cookie_store.take(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
         }
     }
 
@@ -540,6 +648,38 @@ where
         self.connector(rustls::Connector::from(config))
     }
 
+    #[cfg(feature = "openssl")]
+    /// Use openssl connector configured with http client tls settings.
+    ///
+    /// Invalid settings are reported by `finish()` method.
+    pub fn tls_config(
+        &mut self,
+        config: TlsConfig,
+    ) -> WsClientBuilder<Layer<openssl::SslFilter>, openssl::Connector<Uri>> {
+        let alpn = b"\x08http/1.1";
+        let connector = config.openssl(alpn).unwrap_or_else(|e| {
+            self.err.get_or_insert(WsClientBuilderError::Tls(e));
+            TlsConfig::default().openssl(alpn).unwrap()
+        });
+        self.openssl(connector)
+    }
+
+    #[cfg(all(not(feature = "openssl"), feature = "rustls"))]
+    /// Use rustls connector configured with http client tls settings.
+    ///
+    /// Invalid settings are reported by `finish()` method.
+    pub fn tls_config(
+        &mut self,
+        config: TlsConfig,
+    ) -> WsClientBuilder<Layer<rustls::TlsFilter>, rustls::Connector<Uri>> {
+        let alpn = || vec![b"http/1.1".to_vec()];
+        let config = config.rustls(alpn()).unwrap_or_else(|e| {
+            self.err.get_or_insert(WsClientBuilderError::Tls(e));
+            TlsConfig::default().rustls(alpn()).unwrap()
+        });
+        self.rustls(std::sync::Arc::new(config))
+    }
+
     /// This method construct new `WsClientBuilder`
     pub fn take(&mut self) -> WsClientBuilder<F, T> {
         WsClientBuilder {
@@ -549,13 +689,17 @@ where
             protocols: self.protocols.take(),
             #[cfg(feature = "cookie")]
             cookies: self.cookies.take(),
+            #[cfg(feature = "cookie")]
+            cookie_store: self.cookie_store.take(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
         }
     }
 
     /// Complete building process and construct websockets client.
     pub fn finish(&mut self) -> Result<WsClient<F, T>, WsClientBuilderError> {
         if let Some(e) = self.err.take() {
-            return Err(WsClientBuilderError::Http(e));
+            return Err(e);
         }
 
         // #[allow(unused_mut)]
@@ -629,15 +773,28 @@ where
             );
         }
 
+        #[cfg(feature = "compress")]
+        if let Some(ref cfg) = self.deflate {
+            inner
+                .head
+                .headers
+                .insert(header::SEC_WEBSOCKET_EXTENSIONS, cfg.header_value());
+        }
+
         Ok(WsClient {
             connector: inner.connector.into(),
             head: Rc::new(inner.head),
             addr: inner.addr,
             max_size: inner.max_size,
+            max_message_size: inner.max_message_size,
             server_mode: inner.server_mode,
             timeout: inner.timeout,
             keepalive_timeout: inner.keepalive_timeout,
             extra_headers: RefCell::new(None),
+            #[cfg(feature = "cookie")]
+            cookie_store: self.cookie_store.take(),
+            #[cfg(feature = "compress")]
+            deflate: self.deflate.take(),
             _t: marker::PhantomData,
         })
     }
@@ -646,7 +803,7 @@ where
 #[inline]
 fn parts<'a, F, T>(
     parts: &'a mut Option<Inner<F, T>>,
-    err: &Option<HttpError>,
+    err: &Option<WsClientBuilderError>,
 ) -> Option<&'a mut Inner<F, T>> {
     if err.is_some() {
         return None;
@@ -704,12 +861,25 @@ impl<F> WsConnection<F> {
     pub fn response(&self) -> &ClientResponse {
         &self.res
     }
+
+    /// Get sub-protocol selected by server
+    pub fn protocol(&self) -> Option<&str> {
+        self.res
+            .headers()
+            .get(&header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|hdr| hdr.to_str().ok())
+            .map(|proto| proto.trim())
+    }
 }
 
 impl<F> WsConnection<F> {
     /// Get ws sink
     pub fn sink(&self) -> ws::WsSink {
-        ws::WsSink::new(self.io.get_ref(), self.codec.clone())
+        ws::WsSink::new(
+            self.io.get_ref(),
+            self.codec.clone(),
+            self.protocol().map(|p| p.to_string()),
+        )
     }
 
     /// Consumes the `WsConnection`, returning it'as underlying I/O stream object
@@ -761,6 +931,10 @@ impl WsConnection<Sealed> {
                     DispatchItem::WBackPressureEnabled
                     | DispatchItem::WBackPressureDisabled => Ok(None),
                     DispatchItem::KeepAliveTimeout => Err(WsError::KeepAlive),
+                    DispatchItem::DecoderError(ProtocolError::Overflow) => {
+                        // message is too big, close connection with 1009 code
+                        Ok(Some(ws::Message::Close(Some(ws::CloseCode::Size.into()))))
+                    }
                     DispatchItem::DecoderError(e) | DispatchItem::EncoderError(e) => {
                         Err(WsError::Protocol(e))
                     }
@@ -838,6 +1012,26 @@ mod tests {
         );
     }
 
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    #[crate::rt_test]
+    async fn tls_config() {
+        let config = TlsConfig::new().danger_accept_invalid_certs(true);
+        let client = WsClient::build("wss://localhost")
+            .tls_config(config)
+            .finish()
+            .unwrap();
+        assert!(format!("{:?}", client).contains("wss://localhost"));
+
+        let config = TlsConfig::new()
+            .add_root_certificate(crate::http::client::Certificate::from_der(b"cert"));
+        let err = WsClient::build("wss://localhost")
+            .tls_config(config)
+            .finish()
+            .err()
+            .unwrap();
+        assert!(matches!(err, WsClientBuilderError::Tls(_)));
+    }
+
     #[test]
     fn basic_errs() {
         let err = WsClient::build("localhost").finish().err().unwrap();
@@ -930,6 +1124,14 @@ mod tests {
         );
 
         let _ = client.connect();
+
+        let client = WsClient::build("http://localhost")
+            .basic_auth("username", None)
+            .bearer_auth("token")
+            .finish()
+            .unwrap();
+        let values: Vec<_> = client.head.headers.get_all(header::AUTHORIZATION).collect();
+        assert_eq!(values, vec!["Bearer token"]);
     }
 
     #[cfg(feature = "cookie")]
@@ -938,6 +1140,7 @@ mod tests {
         let mut builder = WsClient::build("http://localhost/")
             .origin("test-origin")
             .max_frame_size(100)
+            .max_message_size(200)
             .server_mode()
             .protocols(["v1", "v2"])
             .set_header_if_none(header::CONTENT_TYPE, "json")
@@ -949,6 +1152,7 @@ mod tests {
             "test-origin"
         );
         assert_eq!(builder.inner.as_ref().unwrap().max_size, 100);
+        assert_eq!(builder.inner.as_ref().unwrap().max_message_size, 200);
         assert!(builder.inner.as_ref().unwrap().server_mode);
         assert_eq!(builder.protocols, Some("v1,v2".to_string()));
 
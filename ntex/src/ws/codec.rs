@@ -1,8 +1,12 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "compress")]
+use std::rc::Rc;
 
 use crate::codec::{Decoder, Encoder};
 use crate::util::{ByteString, Bytes, BytesMut};
 
+#[cfg(feature = "compress")]
+use super::deflate::{DeflateConfig, DeflateContext};
 use super::error::ProtocolError;
 use super::frame::Parser;
 use super::proto::{CloseReason, OpCode};
@@ -55,6 +59,11 @@ pub enum Item {
 pub struct Codec {
     flags: Cell<Flags>,
     max_size: usize,
+    max_message_size: usize,
+    fragment_size: usize,
+    partial: RefCell<Option<(OpCode, BytesMut)>>,
+    #[cfg(feature = "compress")]
+    deflate: Option<Rc<RefCell<DeflateContext>>>,
 }
 
 bitflags::bitflags! {
@@ -63,6 +72,8 @@ bitflags::bitflags! {
         const R_CONTINUATION = 0b0000_0010;
         const W_CONTINUATION = 0b0000_0100;
         const CLOSED         = 0b0000_1000;
+        const AGGREGATE      = 0b0001_0000;
+        const R_DEFLATE      = 0b0010_0000;
     }
 }
 
@@ -71,18 +82,67 @@ impl Codec {
     pub fn new() -> Codec {
         Codec {
             max_size: 65_536,
+            max_message_size: 1_048_576,
+            fragment_size: 0,
             flags: Cell::new(Flags::SERVER),
+            partial: RefCell::new(None),
+            #[cfg(feature = "compress")]
+            deflate: None,
         }
     }
 
     /// Set max frame size
     ///
-    /// By default max size is set to 64kb
+    /// Limit applies to unmasked payload length from the frame header,
+    /// frame gets rejected with `ProtocolError::Overflow` error before
+    /// payload is buffered. By default max size is set to 64kb
     pub fn max_size(mut self, size: usize) -> Self {
         self.max_size = size;
         self
     }
 
+    /// Reassemble continuation frames.
+    ///
+    /// Fragmented messages get collected and returned as a single
+    /// `Frame::Text` or `Frame::Binary` frame. Control frames could be
+    /// interleaved with fragments and returned as usual.
+    ///
+    /// By default decoder returns `Frame::Continuation` frames.
+    pub fn aggregate_continuation(self) -> Self {
+        self.insert_flags(Flags::AGGREGATE);
+        self
+    }
+
+    /// Set max size of reassembled message
+    ///
+    /// If message exceeds this size, decoder returns `ProtocolError::Overflow` error.
+    /// Limit also applies to decompressed payload of `permessage-deflate` messages.
+    /// By default max message size is set to 1mb
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Fragment outgoing messages
+    ///
+    /// Text and binary messages larger than `size` are sent as a sequence of
+    /// continuation frames. By default outgoing messages are not fragmented.
+    pub fn fragment_size(mut self, size: usize) -> Self {
+        self.fragment_size = size;
+        self
+    }
+
+    #[cfg(feature = "compress")]
+    /// Enable `permessage-deflate` extension
+    ///
+    /// Configuration must be negotiated with the peer during handshake.
+    /// Outgoing text and binary messages get compressed, continuation
+    /// items are sent uncompressed. Cloned codecs share deflate state.
+    pub fn deflate(mut self, cfg: DeflateConfig) -> Self {
+        self.deflate = Some(Rc::new(RefCell::new(DeflateContext::new(cfg))));
+        self
+    }
+
     /// Set decoder to client mode.
     ///
     /// By default decoder works in server mode.
@@ -107,6 +167,160 @@ impl Codec {
         flags.remove(f);
         self.flags.set(flags);
     }
+
+    fn write_data(
+        &self,
+        dst: &mut BytesMut,
+        data: &[u8],
+        op: OpCode,
+    ) -> Result<(), ProtocolError> {
+        #[cfg(feature = "compress")]
+        {
+            if let Some(ref deflate) = self.deflate {
+                let server = self.flags.get().contains(Flags::SERVER);
+                let data = deflate.borrow_mut().compress(data, server)?;
+                self.write_frames(dst, &data, op, true);
+                return Ok(());
+            }
+        }
+        self.write_frames(dst, data, op, false);
+        Ok(())
+    }
+
+    fn write_frames(
+        &self,
+        dst: &mut BytesMut,
+        mut data: &[u8],
+        mut op: OpCode,
+        rsv1: bool,
+    ) {
+        let mask = !self.flags.get().contains(Flags::SERVER);
+        let pos = dst.len();
+
+        if self.fragment_size != 0 && !self.flags.get().contains(Flags::W_CONTINUATION) {
+            while data.len() > self.fragment_size {
+                let (chunk, rest) = data.split_at(self.fragment_size);
+                Parser::write_message(dst, chunk, op, false, mask);
+                data = rest;
+                op = OpCode::Continue;
+            }
+        }
+        Parser::write_message(dst, data, op, true, mask);
+
+        // compressed message, rsv1 is set on first frame
+        if rsv1 {
+            dst[pos] |= 0x40;
+        }
+    }
+
+    #[cfg(feature = "compress")]
+    fn decompress(
+        &self,
+        finished: bool,
+        rsv1: bool,
+        opcode: OpCode,
+        payload: Option<Bytes>,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        let deflate = if let Some(ref deflate) = self.deflate {
+            deflate
+        } else if rsv1 {
+            return Err(ProtocolError::ReservedBits);
+        } else {
+            return Ok(payload);
+        };
+
+        let compressed = match opcode {
+            OpCode::Text | OpCode::Binary => {
+                if rsv1 && !finished {
+                    self.insert_flags(Flags::R_DEFLATE);
+                }
+                rsv1
+            }
+            OpCode::Continue if !rsv1 => {
+                let compressed = self.flags.get().contains(Flags::R_DEFLATE);
+                if finished {
+                    self.remove_flags(Flags::R_DEFLATE);
+                }
+                compressed
+            }
+            _ if rsv1 => return Err(ProtocolError::ReservedBits),
+            _ => false,
+        };
+
+        if compressed {
+            let server = self.flags.get().contains(Flags::SERVER);
+            let payload = payload.unwrap_or_default();
+            Ok(Some(deflate.borrow_mut().decompress(
+                &payload,
+                finished,
+                server,
+                self.max_message_size,
+            )?))
+        } else {
+            Ok(payload)
+        }
+    }
+
+    #[cfg(not(feature = "compress"))]
+    fn decompress(
+        &self,
+        _: bool,
+        rsv1: bool,
+        _: OpCode,
+        payload: Option<Bytes>,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        if rsv1 {
+            Err(ProtocolError::ReservedBits)
+        } else {
+            Ok(payload)
+        }
+    }
+
+    fn aggregate(
+        &self,
+        finished: bool,
+        opcode: OpCode,
+        payload: Option<Bytes>,
+    ) -> Result<Option<Frame>, ProtocolError> {
+        let payload = payload.unwrap_or_default();
+        let mut partial = self.partial.borrow_mut();
+
+        if opcode == OpCode::Continue {
+            if let Some((_, ref mut buf)) = *partial {
+                if buf.len() + payload.len() > self.max_message_size {
+                    *partial = None;
+                    return Err(ProtocolError::Overflow);
+                }
+                buf.extend_from_slice(&payload);
+            } else {
+                return Err(ProtocolError::ContinuationNotStarted);
+            }
+
+            if finished {
+                let (op, buf) = partial.take().unwrap();
+                if op == OpCode::Text {
+                    Ok(Some(Frame::Text(buf.freeze())))
+                } else {
+                    Ok(Some(Frame::Binary(buf.freeze())))
+                }
+            } else {
+                Ok(None)
+            }
+        } else if partial.is_some() {
+            Err(ProtocolError::ContinuationStarted)
+        } else if finished {
+            if opcode == OpCode::Text {
+                Ok(Some(Frame::Text(payload)))
+            } else {
+                Ok(Some(Frame::Binary(payload)))
+            }
+        } else if payload.len() > self.max_message_size {
+            Err(ProtocolError::Overflow)
+        } else {
+            *partial = Some((opcode, BytesMut::from(&payload[..])));
+            Ok(None)
+        }
+    }
 }
 
 impl Default for Codec {
@@ -121,20 +335,8 @@ impl Encoder for Codec {
 
     fn encode(&self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         match item {
-            Message::Text(txt) => Parser::write_message(
-                dst,
-                txt.as_ref(),
-                OpCode::Text,
-                true,
-                !self.flags.get().contains(Flags::SERVER),
-            ),
-            Message::Binary(bin) => Parser::write_message(
-                dst,
-                bin,
-                OpCode::Binary,
-                true,
-                !self.flags.get().contains(Flags::SERVER),
-            ),
+            Message::Text(txt) => self.write_data(dst, txt.as_bytes(), OpCode::Text)?,
+            Message::Binary(bin) => self.write_data(dst, &bin, OpCode::Binary)?,
             Message::Ping(txt) => Parser::write_message(
                 dst,
                 txt,
@@ -220,90 +422,389 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.get().contains(Flags::SERVER), self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
-                // handle continuation
-                if !finished {
-                    match opcode {
-                        OpCode::Continue => {
-                            if self.flags.get().contains(Flags::R_CONTINUATION) {
-                                Ok(Some(Frame::Continuation(Item::Continue(
-                                    payload.unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationNotStarted)
-                            }
-                        }
-                        OpCode::Binary => {
-                            if !self.flags.get().contains(Flags::R_CONTINUATION) {
-                                self.insert_flags(Flags::R_CONTINUATION);
-                                Ok(Some(Frame::Continuation(Item::FirstBinary(
-                                    payload.unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationStarted)
-                            }
-                        }
-                        OpCode::Text => {
-                            if !self.flags.get().contains(Flags::R_CONTINUATION) {
-                                self.insert_flags(Flags::R_CONTINUATION);
-                                Ok(Some(Frame::Continuation(Item::FirstText(
-                                    payload.unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationStarted)
-                            }
-                        }
-                        OpCode::Ping => {
-                            Ok(Some(Frame::Ping(payload.unwrap_or_else(Bytes::new))))
-                        }
-                        OpCode::Pong => {
-                            Ok(Some(Frame::Pong(payload.unwrap_or_else(Bytes::new))))
-                        }
-                        OpCode::Bad => Err(ProtocolError::BadOpCode),
-                        _ => {
-                            error!("Unfinished fragment {:?}", opcode);
-                            Err(ProtocolError::ContinuationFragment(opcode))
+        loop {
+            let (finished, rsv1, opcode, payload) = match Parser::parse_frame(
+                src,
+                self.flags.get().contains(Flags::SERVER),
+                self.max_size,
+            )? {
+                Some(item) => item,
+                None => return Ok(None),
+            };
+            let payload = self.decompress(finished, rsv1, opcode, payload)?;
+
+            // reassemble fragmented messages
+            if self.flags.get().contains(Flags::AGGREGATE)
+                && matches!(opcode, OpCode::Continue | OpCode::Text | OpCode::Binary)
+            {
+                if let Some(frame) = self.aggregate(finished, opcode, payload)? {
+                    return Ok(Some(frame));
+                }
+                continue;
+            }
+
+            // handle continuation
+            return if !finished {
+                match opcode {
+                    OpCode::Continue => {
+                        if self.flags.get().contains(Flags::R_CONTINUATION) {
+                            Ok(Some(Frame::Continuation(Item::Continue(
+                                payload.unwrap_or_else(Bytes::new),
+                            ))))
+                        } else {
+                            Err(ProtocolError::ContinuationNotStarted)
                         }
                     }
-                } else {
-                    match opcode {
-                        OpCode::Continue => {
-                            if self.flags.get().contains(Flags::R_CONTINUATION) {
-                                self.remove_flags(Flags::R_CONTINUATION);
-                                Ok(Some(Frame::Continuation(Item::Last(
-                                    payload.unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationNotStarted)
-                            }
-                        }
-                        OpCode::Bad => Err(ProtocolError::BadOpCode),
-                        OpCode::Close => {
-                            if let Some(ref pl) = payload {
-                                let close_reason = Parser::parse_close_payload(pl);
-                                Ok(Some(Frame::Close(close_reason)))
-                            } else {
-                                Ok(Some(Frame::Close(None)))
-                            }
+                    OpCode::Binary => {
+                        if !self.flags.get().contains(Flags::R_CONTINUATION) {
+                            self.insert_flags(Flags::R_CONTINUATION);
+                            Ok(Some(Frame::Continuation(Item::FirstBinary(
+                                payload.unwrap_or_else(Bytes::new),
+                            ))))
+                        } else {
+                            Err(ProtocolError::ContinuationStarted)
                         }
-                        OpCode::Ping => {
-                            Ok(Some(Frame::Ping(payload.unwrap_or_else(Bytes::new))))
-                        }
-                        OpCode::Pong => {
-                            Ok(Some(Frame::Pong(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Text => {
+                        if !self.flags.get().contains(Flags::R_CONTINUATION) {
+                            self.insert_flags(Flags::R_CONTINUATION);
+                            Ok(Some(Frame::Continuation(Item::FirstText(
+                                payload.unwrap_or_else(Bytes::new),
+                            ))))
+                        } else {
+                            Err(ProtocolError::ContinuationStarted)
                         }
-                        OpCode::Binary => {
-                            Ok(Some(Frame::Binary(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Ping => {
+                        Ok(Some(Frame::Ping(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Pong => {
+                        Ok(Some(Frame::Pong(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Bad => Err(ProtocolError::BadOpCode),
+                    _ => {
+                        error!("Unfinished fragment {:?}", opcode);
+                        Err(ProtocolError::ContinuationFragment(opcode))
+                    }
+                }
+            } else {
+                match opcode {
+                    OpCode::Continue => {
+                        if self.flags.get().contains(Flags::R_CONTINUATION) {
+                            self.remove_flags(Flags::R_CONTINUATION);
+                            Ok(Some(Frame::Continuation(Item::Last(
+                                payload.unwrap_or_else(Bytes::new),
+                            ))))
+                        } else {
+                            Err(ProtocolError::ContinuationNotStarted)
                         }
-                        OpCode::Text => {
-                            Ok(Some(Frame::Text(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Bad => Err(ProtocolError::BadOpCode),
+                    OpCode::Close => {
+                        if let Some(ref pl) = payload {
+                            let close_reason = Parser::parse_close_payload(pl);
+                            Ok(Some(Frame::Close(close_reason)))
+                        } else {
+                            Ok(Some(Frame::Close(None)))
                         }
                     }
+                    OpCode::Ping => {
+                        Ok(Some(Frame::Ping(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Pong => {
+                        Ok(Some(Frame::Pong(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    // data frames could not be interleaved with fragments
+                    OpCode::Binary | OpCode::Text
+                        if self.flags.get().contains(Flags::R_CONTINUATION) =>
+                    {
+                        Err(ProtocolError::ContinuationStarted)
+                    }
+                    OpCode::Binary => {
+                        Ok(Some(Frame::Binary(payload.unwrap_or_else(Bytes::new))))
+                    }
+                    OpCode::Text => {
+                        Ok(Some(Frame::Text(payload.unwrap_or_else(Bytes::new))))
+                    }
                 }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::CloseCode;
+
+    fn write(buf: &mut BytesMut, data: &[u8], op: OpCode, fin: bool) {
+        Parser::write_message(buf, data, op, fin, false);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let codec = Codec::new().client_mode().aggregate_continuation();
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"hello", OpCode::Text, false);
+        write(&mut buf, b" ", OpCode::Continue, false);
+        write(&mut buf, b"world", OpCode::Continue, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from_static(b"hello world")))
+        );
+        assert!(buf.is_empty());
+
+        // partial frames
+        write(&mut buf, b"hello", OpCode::Binary, false);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        write(&mut buf, b"world", OpCode::Continue, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::from_static(b"helloworld")))
+        );
+
+        // not fragmented
+        write(&mut buf, b"data", OpCode::Binary, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::from_static(b"data")))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_empty_fragments() {
+        let codec = Codec::new().client_mode().aggregate_continuation();
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"", OpCode::Text, false);
+        write(&mut buf, b"", OpCode::Continue, false);
+        write(&mut buf, b"abc", OpCode::Continue, false);
+        write(&mut buf, b"", OpCode::Continue, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from_static(b"abc")))
+        );
+
+        write(&mut buf, b"", OpCode::Binary, false);
+        write(&mut buf, b"", OpCode::Continue, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::new()))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_control_frames() {
+        let codec = Codec::new().client_mode().aggregate_continuation();
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"hello", OpCode::Text, false);
+        write(&mut buf, b"ping", OpCode::Ping, true);
+        write(&mut buf, b"world", OpCode::Continue, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Ping(Bytes::from_static(b"ping")))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from_static(b"helloworld")))
+        );
+
+        // close frame in the middle of fragmented message
+        write(&mut buf, b"hello", OpCode::Text, false);
+        Parser::write_close(&mut buf, Some(CloseCode::Normal.into()), false);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Close(Some(CloseCode::Normal.into())))
+        );
+
+        // data frames cannot be interleaved
+        write(&mut buf, b"world", OpCode::Binary, true);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::ContinuationStarted)
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_overflow() {
+        let codec = Codec::new()
+            .client_mode()
+            .aggregate_continuation()
+            .max_message_size(8);
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"hello", OpCode::Text, false);
+        write(&mut buf, b"world", OpCode::Continue, true);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::Overflow)
+        ));
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"hello world", OpCode::Binary, false);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_continuation_ordering() {
+        let codec = Codec::new().client_mode();
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"world", OpCode::Continue, true);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::ContinuationNotStarted)
+        ));
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"hello", OpCode::Text, false);
+        write(&mut buf, b"world", OpCode::Text, true);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::FirstText(Bytes::from_static(
+                b"hello"
+            ))))
+        );
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::ContinuationStarted)
+        ));
+    }
+
+    #[test]
+    fn test_fragment_size() {
+        let codec = Codec::new().fragment_size(4);
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Message::Text(ByteString::from_static("hello world")),
+                &mut buf,
+            )
+            .unwrap();
+        codec
+            .encode(Message::Binary(Bytes::from_static(b"data")), &mut buf)
+            .unwrap();
+
+        let decoder = Codec::new().client_mode();
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::FirstText(Bytes::from_static(
+                b"hell"
+            ))))
+        );
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Continue(Bytes::from_static(
+                b"o wo"
+            ))))
+        );
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Continuation(Item::Last(Bytes::from_static(b"rld"))))
+        );
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::from_static(b"data")))
+        );
+
+        let decoder = Codec::new().client_mode().aggregate_continuation();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Message::Binary(Bytes::from(vec![1u8; 10])), &mut buf)
+            .unwrap();
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::from(vec![1u8; 10])))
+        );
+    }
+
+    #[test]
+    fn test_reserved_bits() {
+        let codec = Codec::new().client_mode();
+
+        let mut buf = BytesMut::new();
+        write(&mut buf, b"data", OpCode::Text, true);
+        buf[0] |= 0x40;
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::ReservedBits)
+        ));
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_deflate() {
+        let cfg = DeflateConfig::new();
+        let codec = Codec::new().deflate(cfg).fragment_size(16);
+        let text = "hello world ".repeat(100);
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Message::Text(ByteString::from(text.clone())), &mut buf)
+            .unwrap();
+        assert_eq!(buf[0] & 0x40, 0x40);
+        let mut buf2 = buf.clone();
+
+        let decoder = Codec::new()
+            .client_mode()
+            .aggregate_continuation()
+            .deflate(cfg);
+        assert_eq!(
+            decoder.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from(text.clone())))
+        );
+
+        // decompress continuation items
+        let decoder = Codec::new().client_mode().deflate(cfg);
+        let mut data = BytesMut::new();
+        while let Some(frame) = decoder.decode(&mut buf2).unwrap() {
+            match frame {
+                Frame::Continuation(Item::FirstText(b))
+                | Frame::Continuation(Item::Continue(b))
+                | Frame::Continuation(Item::Last(b)) => data.extend_from_slice(&b),
+                _ => panic!(),
             }
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
         }
+        assert_eq!(&data[..], text.as_bytes());
+
+        // control frames are not compressed
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Message::Ping(Bytes::from_static(b"ping")), &mut buf)
+            .unwrap();
+        assert_eq!(buf[0] & 0x40, 0);
+        buf[0] |= 0x40;
+        assert!(matches!(
+            decoder.decode(&mut buf),
+            Err(ProtocolError::ReservedBits)
+        ));
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_deflate_limit() {
+        let cfg = DeflateConfig::new();
+        let codec = Codec::new().deflate(cfg);
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Message::Binary(Bytes::from(vec![0u8; 1024])), &mut buf)
+            .unwrap();
+        assert!(buf.len() < 100);
+
+        let decoder = Codec::new()
+            .client_mode()
+            .max_message_size(512)
+            .deflate(cfg);
+        assert!(matches!(
+            decoder.decode(&mut buf),
+            Err(ProtocolError::Overflow)
+        ));
     }
 }
@@ -0,0 +1,454 @@
+//! `permessage-deflate` extension support (RFC 7692)
+use std::fmt;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::http::header::{HeaderMap, HeaderValue, SEC_WEBSOCKET_EXTENSIONS};
+use crate::util::Bytes;
+
+use super::error::ProtocolError;
+
+const EXTENSION: &str = "permessage-deflate";
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+const MAX_WINDOW_BITS: &str = "15";
+
+/// Configuration of `permessage-deflate` extension
+///
+/// Compression always uses max window size (15 bits), so offers that
+/// require smaller server window get declined.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: bool,
+}
+
+impl DeflateConfig {
+    /// Create default `permessage-deflate` configuration
+    ///
+    /// By default both peers retain deflate state across messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Server resets deflate state after each message
+    pub fn server_no_context_takeover(mut self) -> Self {
+        self.server_no_context_takeover = true;
+        self
+    }
+
+    /// Client resets deflate state after each message
+    pub fn client_no_context_takeover(mut self) -> Self {
+        self.client_no_context_takeover = true;
+        self
+    }
+
+    /// Check if server resets deflate state after each message
+    pub fn is_server_no_context_takeover(&self) -> bool {
+        self.server_no_context_takeover
+    }
+
+    /// Check if client resets deflate state after each message
+    pub fn is_client_no_context_takeover(&self) -> bool {
+        self.client_no_context_takeover
+    }
+
+    /// Negotiate extension with client's offers
+    ///
+    /// Returns accepted configuration, its `header_value()` must be sent
+    /// back to the client in `Sec-WebSocket-Extensions` header.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<DeflateConfig> {
+        for hdr in headers.get_all(SEC_WEBSOCKET_EXTENSIONS) {
+            let hdr = if let Ok(hdr) = hdr.to_str() {
+                hdr
+            } else {
+                continue;
+            };
+
+            'offers: for offer in hdr.split(',') {
+                let mut params = offer.split(';').map(|p| p.trim());
+                if params.next() != Some(EXTENSION) {
+                    continue;
+                }
+
+                let mut cfg = *self;
+                for param in params {
+                    match parse_param(param) {
+                        ("server_no_context_takeover", None) => {
+                            cfg.server_no_context_takeover = true
+                        }
+                        ("client_no_context_takeover", None) => {
+                            cfg.client_no_context_takeover = true
+                        }
+                        ("server_max_window_bits", Some(MAX_WINDOW_BITS)) => {
+                            cfg.server_max_window_bits = true
+                        }
+                        // client could use any window size
+                        ("client_max_window_bits", _) => (),
+                        _ => continue 'offers,
+                    }
+                }
+                return Some(cfg);
+            }
+        }
+        None
+    }
+
+    /// Accept server's negotiation response
+    ///
+    /// Extensions other than `permessage-deflate` are ignored. Returns
+    /// offending header value if `permessage-deflate` is not requested,
+    /// its parameters are invalid or it is accepted more than once.
+    pub(super) fn accept(
+        requested: Option<DeflateConfig>,
+        headers: &HeaderMap,
+    ) -> Result<Option<DeflateConfig>, HeaderValue> {
+        let mut accepted = None;
+        for hdr in headers.get_all(SEC_WEBSOCKET_EXTENSIONS) {
+            let val = if let Ok(val) = hdr.to_str() {
+                val
+            } else {
+                continue;
+            };
+
+            for ext in val.split(',') {
+                let mut params = ext.split(';').map(|p| p.trim());
+                if params.next() != Some(EXTENSION) {
+                    continue;
+                }
+                match requested {
+                    Some(cfg) if accepted.is_none() => {
+                        accepted =
+                            Some(cfg.accept_params(params).ok_or_else(|| hdr.clone())?);
+                    }
+                    _ => return Err(hdr.clone()),
+                }
+            }
+        }
+        Ok(accepted)
+    }
+
+    fn accept_params<'a>(mut self, params: impl Iterator<Item = &'a str>) -> Option<Self> {
+        for param in params {
+            match parse_param(param) {
+                ("server_no_context_takeover", None) => {
+                    self.server_no_context_takeover = true
+                }
+                ("client_no_context_takeover", None) => {
+                    self.client_no_context_takeover = true
+                }
+                ("server_max_window_bits", Some(bits)) => match bits.parse::<u8>() {
+                    Ok(8..=15) => (),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        Some(self)
+    }
+
+    /// `Sec-WebSocket-Extensions` header value for current configuration
+    pub fn header_value(&self) -> HeaderValue {
+        let mut val = EXTENSION.to_string();
+        if self.server_no_context_takeover {
+            val.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            val.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits {
+            val.push_str("; server_max_window_bits=");
+            val.push_str(MAX_WINDOW_BITS);
+        }
+        HeaderValue::try_from(val).unwrap()
+    }
+}
+
+fn parse_param(param: &str) -> (&str, Option<&str>) {
+    if let Some((name, val)) = param.split_once('=') {
+        (name.trim(), Some(val.trim().trim_matches('"')))
+    } else {
+        (param, None)
+    }
+}
+
+/// Deflate state of websocket connection
+pub(super) struct DeflateContext {
+    cfg: DeflateConfig,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl DeflateContext {
+    pub(super) fn new(cfg: DeflateConfig) -> Self {
+        Self {
+            cfg,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Compress message payload
+    pub(super) fn compress(
+        &mut self,
+        data: &[u8],
+        server: bool,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity());
+            }
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|_| ProtocolError::Deflate)?;
+
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if out.ends_with(&TAIL) {
+            out.truncate(out.len() - TAIL.len());
+        }
+        if (server && self.cfg.server_no_context_takeover)
+            || (!server && self.cfg.client_no_context_takeover)
+        {
+            self.compress.reset();
+        }
+        Ok(Bytes::from(out))
+    }
+
+    /// Decompress message fragment
+    ///
+    /// Decompressed data is limited by `limit` bytes.
+    pub(super) fn decompress(
+        &mut self,
+        data: &[u8],
+        finished: bool,
+        server: bool,
+        limit: usize,
+    ) -> Result<Bytes, ProtocolError> {
+        let mut out = Vec::new();
+        self.inflate(data, &mut out, limit)?;
+
+        if finished {
+            self.inflate(&TAIL, &mut out, limit)?;
+
+            if (server && self.cfg.client_no_context_takeover)
+                || (!server && self.cfg.server_no_context_takeover)
+            {
+                self.decompress.reset(false);
+            }
+        }
+        Ok(Bytes::from(out))
+    }
+
+    fn inflate(
+        &mut self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> Result<(), ProtocolError> {
+        let start = self.decompress.total_in();
+
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if out.len() == out.capacity() {
+                out.reserve(std::cmp::max(data.len() * 2, 256));
+            }
+            let status = self
+                .decompress
+                .decompress_vec(&data[consumed..], out, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::Deflate)?;
+
+            if out.len() > limit {
+                return Err(ProtocolError::Overflow);
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            match status {
+                Status::StreamEnd => {
+                    // peer finished deflate stream, start new one
+                    self.decompress.reset(false);
+                    if consumed == data.len() {
+                        return Ok(());
+                    }
+                }
+                Status::BufError if consumed == data.len() => return Ok(()),
+                _ => {
+                    if consumed == data.len() && out.len() < out.capacity() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for DeflateContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeflateContext")
+            .field("cfg", &self.cfg)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+
+    fn offer(val: &'static str) -> HeaderMap {
+        let mut hdrs = HeaderMap::new();
+        hdrs.insert(SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_static(val));
+        hdrs
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let cfg = DeflateConfig::new();
+        assert_eq!(cfg.negotiate(&HeaderMap::new()), None);
+        assert_eq!(cfg.negotiate(&offer("x-webkit-deflate-frame")), None);
+
+        let res = cfg.negotiate(&offer("permessage-deflate")).unwrap();
+        assert_eq!(res, cfg);
+        assert_eq!(res.header_value(), "permessage-deflate");
+
+        let res = cfg
+            .negotiate(&offer(
+                "permessage-deflate; client_max_window_bits; server_no_context_takeover",
+            ))
+            .unwrap();
+        assert!(res.is_server_no_context_takeover());
+        assert!(!res.is_client_no_context_takeover());
+        assert_eq!(
+            res.header_value(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+
+        // smaller server window is not supported
+        let res = cfg
+            .negotiate(&offer(
+                "permessage-deflate; server_max_window_bits=10, permessage-deflate; server_max_window_bits=\"15\"",
+            ))
+            .unwrap();
+        assert_eq!(
+            res.header_value(),
+            "permessage-deflate; server_max_window_bits=15"
+        );
+        assert_eq!(
+            cfg.negotiate(&offer("permessage-deflate; server_max_window_bits=10")),
+            None
+        );
+        assert_eq!(cfg.negotiate(&offer("permessage-deflate; unknown")), None);
+
+        let res = DeflateConfig::new()
+            .client_no_context_takeover()
+            .negotiate(&offer("permessage-deflate"))
+            .unwrap();
+        assert_eq!(
+            res.header_value(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+    }
+
+    #[test]
+    fn test_accept() {
+        let cfg = DeflateConfig::new();
+        let accept = |val| DeflateConfig::accept(Some(cfg), &offer(val));
+        assert_eq!(
+            DeflateConfig::accept(Some(cfg), &HeaderMap::new()),
+            Ok(None)
+        );
+        assert_eq!(accept("permessage-deflate"), Ok(Some(cfg)));
+        let res = accept(
+            "permessage-deflate; client_no_context_takeover; server_max_window_bits=10",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(res.is_client_no_context_takeover());
+        assert_eq!(
+            accept("permessage-deflate; client_max_window_bits=10"),
+            Err(HeaderValue::from_static(
+                "permessage-deflate; client_max_window_bits=10"
+            ))
+        );
+        assert!(accept("permessage-deflate, permessage-deflate").is_err());
+
+        // other extensions are ignored
+        assert_eq!(accept("deflate"), Ok(None));
+        assert_eq!(
+            accept("x-custom; level=1, permessage-deflate"),
+            Ok(Some(cfg))
+        );
+
+        // deflate is not requested
+        assert_eq!(DeflateConfig::accept(None, &offer("x-custom")), Ok(None));
+        assert!(DeflateConfig::accept(None, &offer("permessage-deflate")).is_err());
+    }
+
+    #[test]
+    fn test_context_takeover() {
+        let data = b"Hello, hello, hello, hello";
+
+        // rfc7692 7.2.3.1
+        let mut ctx = DeflateContext::new(DeflateConfig::new());
+        let msg = ctx.compress(b"Hello", true).unwrap();
+        assert_eq!(&msg[..], &[0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00][..]);
+
+        let mut ctx = DeflateContext::new(DeflateConfig::new());
+        let mut client = DeflateContext::new(DeflateConfig::new());
+        let msg1 = ctx.compress(data, true).unwrap();
+        let msg2 = ctx.compress(data, true).unwrap();
+        // second message refers to the first one
+        assert!(msg2.len() < msg1.len());
+        assert_eq!(
+            &client.decompress(&msg1, true, false, 1024).unwrap()[..],
+            &data[..]
+        );
+        assert_eq!(
+            &client.decompress(&msg2, true, false, 1024).unwrap()[..],
+            &data[..]
+        );
+
+        let cfg = DeflateConfig::new().server_no_context_takeover();
+        let mut ctx = DeflateContext::new(cfg);
+        let mut client = DeflateContext::new(cfg);
+        let msg1 = ctx.compress(data, true).unwrap();
+        let msg2 = ctx.compress(data, true).unwrap();
+        assert_eq!(msg1, msg2);
+        assert_eq!(
+            &client.decompress(&msg1, true, false, 1024).unwrap()[..],
+            &data[..]
+        );
+        assert_eq!(
+            &client.decompress(&msg2, true, false, 1024).unwrap()[..],
+            &data[..]
+        );
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let data = vec![b'a'; 4096];
+        let mut ctx = DeflateContext::new(DeflateConfig::new());
+        let msg = ctx.compress(&data, true).unwrap();
+        assert!(msg.len() < 100);
+
+        let mut client = DeflateContext::new(DeflateConfig::new());
+        assert!(matches!(
+            client.decompress(&msg, true, false, 1024),
+            Err(ProtocolError::Overflow)
+        ));
+
+        let mut client = DeflateContext::new(DeflateConfig::new());
+        assert!(matches!(
+            client.decompress(b"\xff\xff\xff", true, false, 1024),
+            Err(ProtocolError::Deflate)
+        ));
+    }
+}
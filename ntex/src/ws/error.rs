@@ -55,6 +55,12 @@ pub enum ProtocolError {
     /// Unknown continuation fragment
     #[error("Unknown continuation fragment {0}")]
     ContinuationFragment(OpCode),
+    /// Reserved bits are set without negotiated extension
+    #[error("Reserved bits are set without negotiated extension")]
+    ReservedBits,
+    /// Failed to compress or decompress message payload
+    #[error("Deflate stream error")]
+    Deflate,
 }
 
 /// Websocket client error
@@ -68,6 +74,8 @@ pub enum WsClientBuilderError {
     MissingHost,
     #[error("Url parse error: {0}")]
     Http(#[from] HttpError),
+    #[error("Tls settings error: {0}")]
+    Tls(io::Error),
 }
 
 /// Websocket client error
@@ -94,6 +102,12 @@ pub enum WsClientError {
     /// Invalid challenge response
     #[error("Invalid challenge response")]
     InvalidChallengeResponse(String, HeaderValue),
+    /// Invalid SEC-WEBSOCKET-EXTENSIONS header
+    #[error("Invalid SEC-WEBSOCKET-EXTENSIONS header")]
+    InvalidExtensionsHeader(HeaderValue),
+    /// Server selected sub-protocol that was not offered
+    #[error("Invalid SEC-WEBSOCKET-PROTOCOL header")]
+    InvalidProtocolHeader(HeaderValue),
     /// Protocol error
     #[error("{0}")]
     Protocol(#[from] ProtocolError),
@@ -144,6 +158,9 @@ pub enum HandshakeError {
     /// Websocket key is not set or wrong
     #[error("Unknown websocket key")]
     BadWebsocketKey,
+    /// Client does not offer any supported sub-protocol
+    #[error("Unsupported websocket protocol")]
+    UnsupportedProtocol,
 }
 
 impl ResponseError for HandshakeError {
@@ -164,6 +181,9 @@ impl ResponseError for HandshakeError {
             HandshakeError::UnsupportedVersion => Response::BadRequest()
                 .reason("Unsupported version")
                 .finish(),
+            HandshakeError::UnsupportedProtocol => Response::BadRequest()
+                .reason("No supported websocket protocol")
+                .finish(),
             HandshakeError::BadWebsocketKey => {
                 Response::BadRequest().reason("Handshake error").finish()
             }
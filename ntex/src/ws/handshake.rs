@@ -1,10 +1,12 @@
 //! Websockets protocol helpers
-use crate::http::{header, Method, StatusCode};
+use crate::http::{header, HeaderMap, Method, StatusCode};
 use crate::http::{RequestHead, Response, ResponseBuilder};
 
+#[cfg(feature = "compress")]
+use super::deflate::DeflateConfig;
 use super::error::HandshakeError;
 
-/// Verify `WebSocket` handshake request and create handshake reponse.
+/// Verify `WebSocket` handshake request and create handshake response.
 // /// `protocols` is a sequence of known protocols. On successful handshake,
 // /// the returned response headers contain the first protocol in this list
 // /// which the server also knows.
@@ -13,6 +15,89 @@ pub fn handshake(req: &RequestHead) -> Result<ResponseBuilder, HandshakeError> {
     Ok(handshake_response(req))
 }
 
+#[cfg(feature = "compress")]
+/// Verify `WebSocket` handshake request, negotiate `permessage-deflate`
+/// extension and create handshake response.
+///
+/// Returns negotiated configuration, it should be used for `Codec::deflate()`.
+pub fn handshake_deflate(
+    req: &RequestHead,
+    cfg: &DeflateConfig,
+) -> Result<(ResponseBuilder, Option<DeflateConfig>), HandshakeError> {
+    verify_handshake(req)?;
+    let mut res = handshake_response(req);
+    let cfg = cfg.negotiate(req.headers());
+    if let Some(ref cfg) = cfg {
+        res.header(header::SEC_WEBSOCKET_EXTENSIONS, cfg.header_value());
+    }
+    Ok((res, cfg))
+}
+
+/// Websocket sub-protocols supported by server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Protocols {
+    protocols: Vec<String>,
+    required: bool,
+}
+
+impl Protocols {
+    /// Create sub-protocols configuration
+    pub fn new<U, V>(protocols: U) -> Self
+    where
+        U: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        Protocols {
+            protocols: protocols.into_iter().map(Into::into).collect(),
+            required: false,
+        }
+    }
+
+    /// Reject handshake if client does not offer any supported protocol
+    ///
+    /// By default handshake proceeds without protocol.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Select first protocol offered by client, which is supported by server
+    pub fn negotiate(&self, headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .filter_map(|hdr| hdr.to_str().ok())
+            .flat_map(|hdr| hdr.split(','))
+            .map(|proto| proto.trim())
+            .find_map(|proto| {
+                self.protocols
+                    .iter()
+                    .find(|p| p.as_str() == proto)
+                    .map(|p| p.as_str())
+            })
+    }
+}
+
+/// Verify `WebSocket` handshake request, negotiate sub-protocol and
+/// create handshake response.
+///
+/// Selected protocol is set to `Sec-WebSocket-Protocol` response header.
+pub fn handshake_with_protocols(
+    req: &RequestHead,
+    protocols: &Protocols,
+) -> Result<(ResponseBuilder, Option<String>), HandshakeError> {
+    verify_handshake(req)?;
+    let protocol = protocols.negotiate(req.headers()).map(|p| p.to_string());
+    if protocol.is_none() && protocols.required {
+        return Err(HandshakeError::UnsupportedProtocol);
+    }
+
+    let mut res = handshake_response(req);
+    if let Some(ref protocol) = protocol {
+        res.header(header::SEC_WEBSOCKET_PROTOCOL, protocol.as_str());
+    }
+    Ok((res, protocol))
+}
+
 /// Verify `WebSocket` handshake request.
 // /// `protocols` is a sequence of known protocols. On successful handshake,
 // /// the returned response headers contain the first protocol in this list
@@ -195,6 +280,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handshake_protocols() {
+        let req = TestRequest::default()
+            .header(header::UPGRADE, "websocket")
+            .header(header::CONNECTION, "upgrade")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "13")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "chat, graphql-ws")
+            .header(header::SEC_WEBSOCKET_PROTOCOL, "mqtt")
+            .finish();
+
+        let protocols = Protocols::new(["graphql-ws", "mqtt", "chat"]);
+        assert_eq!(protocols.negotiate(req.headers()), Some("chat"));
+        let protocols = Protocols::new(["mqtt", "graphql-ws"]);
+        assert_eq!(protocols.negotiate(req.headers()), Some("graphql-ws"));
+
+        let (mut res, protocol) = handshake_with_protocols(req.head(), &protocols).unwrap();
+        assert_eq!(protocol.as_deref(), Some("graphql-ws"));
+        let res = res.finish();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            res.headers().get(header::SEC_WEBSOCKET_PROTOCOL).unwrap(),
+            "graphql-ws"
+        );
+
+        // no supported protocols
+        let protocols = Protocols::new(["wamp"]);
+        let (mut res, protocol) = handshake_with_protocols(req.head(), &protocols).unwrap();
+        assert_eq!(protocol, None);
+        assert!(!res
+            .finish()
+            .headers()
+            .contains_key(header::SEC_WEBSOCKET_PROTOCOL));
+
+        assert_eq!(
+            handshake_with_protocols(req.head(), &protocols.required())
+                .err()
+                .unwrap(),
+            HandshakeError::UnsupportedProtocol
+        );
+    }
+
     #[test]
     fn test_wserror_http_response() {
         let resp: Response = HandshakeError::GetMethodRequired.error_response();
@@ -209,5 +336,7 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
         let resp: Response = HandshakeError::BadWebsocketKey.error_response();
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp: Response = HandshakeError::UnsupportedProtocol.error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 }
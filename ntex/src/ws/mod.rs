@@ -5,6 +5,8 @@
 //! communicate with the peer.
 mod client;
 mod codec;
+#[cfg(feature = "compress")]
+mod deflate;
 mod frame;
 mod handshake;
 mod mask;
@@ -16,8 +18,15 @@ pub mod error;
 
 pub use self::client::{WsClient, WsClientBuilder, WsConnection};
 pub use self::codec::{Codec, Frame, Item, Message};
+#[cfg(feature = "compress")]
+pub use self::deflate::DeflateConfig;
 pub use self::frame::Parser;
-pub use self::handshake::{handshake, handshake_response, verify_handshake};
+#[cfg(feature = "compress")]
+pub use self::handshake::handshake_deflate;
+pub use self::handshake::{
+    handshake, handshake_response, handshake_with_protocols, verify_handshake, Protocols,
+};
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
+pub(crate) use self::sink::WriteLimit;
 pub use self::sink::WsSink;
 pub use self::transport::{WsTransport, WsTransportFactory};
@@ -1,4 +1,4 @@
-use std::{future::Future, rc::Rc};
+use std::{fmt, future::Future, rc::Rc};
 
 use crate::io::{IoRef, OnDisconnect};
 use crate::ws;
@@ -10,11 +10,43 @@ pub struct WsSink(Rc<WsSinkInner>);
 struct WsSinkInner {
     io: IoRef,
     codec: ws::Codec,
+    protocol: Option<String>,
+    limit: Option<WriteLimit>,
+}
+
+/// Write buffer limit of `WsSink`
+#[derive(Clone, Default)]
+pub(crate) struct WriteLimit {
+    pub(crate) size: usize,
+    pub(crate) on_overflow: Option<Rc<dyn Fn(&WsSink, ws::Message)>>,
+}
+
+impl fmt::Debug for WriteLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteLimit")
+            .field("size", &self.size)
+            .field("on_overflow", &self.on_overflow.is_some())
+            .finish()
+    }
 }
 
 impl WsSink {
-    pub(crate) fn new(io: IoRef, codec: ws::Codec) -> Self {
-        Self(Rc::new(WsSinkInner { io, codec }))
+    pub(crate) fn new(io: IoRef, codec: ws::Codec, protocol: Option<String>) -> Self {
+        Self(Rc::new(WsSinkInner {
+            io,
+            codec,
+            protocol,
+            limit: None,
+        }))
+    }
+
+    pub(crate) fn write_limit(mut self, limit: &WriteLimit) -> Self {
+        if limit.size > 0 {
+            Rc::get_mut(&mut self.0)
+                .expect("Multiple copies exist")
+                .limit = Some(limit.clone());
+        }
+        self
     }
 
     /// Io reference
@@ -22,17 +54,39 @@ impl WsSink {
         &self.0.io
     }
 
+    /// Negotiated websocket sub-protocol
+    pub fn protocol(&self) -> Option<&str> {
+        self.0.protocol.as_deref()
+    }
+
+    /// Size of data in write buffer, that is not yet sent to the peer
+    pub fn buffered_bytes(&self) -> usize {
+        self.0.io.write_buf_size()
+    }
+
     /// Endcode and send message to the peer.
+    ///
+    /// If write buffer limit is configured and buffered data exceeds
+    /// the limit, message is not sent. Close messages are always sent.
     pub fn send(
         &self,
         item: ws::Message,
     ) -> impl Future<Output = Result<(), ws::error::ProtocolError>> {
-        let inner = self.0.clone();
+        let sink = self.clone();
 
         async move {
+            let inner = &sink.0;
             let close = match item {
                 ws::Message::Close(_) => inner.codec.is_closed(),
-                _ => false,
+                _ => {
+                    if let Some(ref limit) = inner.limit {
+                        if inner.io.write_buf_size() >= limit.size {
+                            sink.overflow(limit, item)?;
+                            return Ok(());
+                        }
+                    }
+                    false
+                }
             };
 
             inner.io.encode(item, &inner.codec)?;
@@ -47,4 +101,25 @@ impl WsSink {
     pub fn on_disconnect(&self) -> OnDisconnect {
         self.0.io.on_disconnect()
     }
+
+    fn overflow(
+        &self,
+        limit: &WriteLimit,
+        item: ws::Message,
+    ) -> Result<(), ws::error::ProtocolError> {
+        if let Some(ref f) = limit.on_overflow {
+            f(self, item);
+        } else if !self.0.io.is_closed() {
+            log::trace!(
+                "Write buffer limit is exceeded ({}), closing connection",
+                self.0.io.write_buf_size()
+            );
+            self.0.io.encode(
+                ws::Message::Close(Some(ws::CloseCode::Again.into())),
+                &self.0.codec,
+            )?;
+            self.0.io.close();
+        }
+        Ok(())
+    }
 }
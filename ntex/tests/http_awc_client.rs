@@ -6,13 +6,17 @@ use std::sync::Arc;
 use brotli2::write::BrotliEncoder;
 use coo_kie::Cookie;
 use flate2::{read::GzDecoder, write::GzEncoder, write::ZlibEncoder, Compression};
-use futures_util::stream::once;
+use futures_util::stream::{once, StreamExt};
 use rand::Rng;
 
-use ntex::http::client::error::{JsonPayloadError, SendRequestError};
-use ntex::http::client::{Client, Connector};
+use ntex::http::client::error::{
+    ConnectError, DownloadError, JsonPayloadError, SendRequestError,
+};
+use ntex::http::client::{Client, Connector, RetryPolicy};
 use ntex::http::test::server as test_server;
-use ntex::http::{header, HttpMessage, HttpService, Method};
+use ntex::http::{
+    error::ParseError, error::PayloadError, header, HttpMessage, HttpService, Method,
+};
 use ntex::service::{chain_factory, map_config};
 use ntex::web::dev::AppConfig;
 use ntex::web::middleware::Compress;
@@ -158,6 +162,196 @@ async fn test_form() {
     assert!(response.status().is_success());
 }
 
+#[ntex::test]
+async fn test_multipart() {
+    use ntex::http::client::multipart::Form;
+
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| async move {
+                let hdr = |name| {
+                    req.headers()
+                        .get(name)
+                        .map(|v| v.to_str().unwrap().to_string())
+                        .unwrap_or_default()
+                };
+                HttpResponse::Ok()
+                    .header("x-content-type", hdr(header::CONTENT_TYPE))
+                    .header("x-content-length", hdr(header::CONTENT_LENGTH))
+                    .header("x-transfer-encoding", hdr(header::TRANSFER_ENCODING))
+                    .body(body)
+            },
+        )))
+    });
+
+    // split body to parts, each part is a pair of headers and content
+    fn parts(body: &[u8], boundary: &str) -> Vec<(String, Vec<u8>)> {
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let body = body.strip_suffix(&format!("--{}--\r\n", boundary)).unwrap();
+        body.split(&format!("--{}\r\n", boundary))
+            .skip(1)
+            .map(|part| {
+                let (head, content) = part.split_once("\r\n\r\n").unwrap();
+                let content = content.strip_suffix("\r\n").unwrap();
+                (head.to_string(), content.as_bytes().to_vec())
+            })
+            .collect()
+    }
+
+    let form = Form::new().text("name", "value").bytes(
+        "file",
+        "résumé \"1\".txt",
+        mime::TEXT_PLAIN,
+        Bytes::from_static(b"file data"),
+    );
+    let boundary = form.boundary().to_string();
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get("x-content-type").unwrap(),
+        &format!("multipart/form-data; boundary={}", boundary)
+    );
+    assert_eq!(response.headers().get("x-transfer-encoding").unwrap(), "");
+
+    let body = response.body().await.unwrap();
+    assert_eq!(
+        response.headers().get("x-content-length").unwrap(),
+        &body.len().to_string()
+    );
+    assert_eq!(
+        parts(&body, &boundary),
+        vec![
+            (
+                "content-disposition: form-data; name=\"name\"".to_string(),
+                b"value".to_vec()
+            ),
+            (
+                "content-disposition: form-data; name=\"file\"; \
+                 filename=\"résumé %221%22.txt\"\r\ncontent-type: text/plain"
+                    .to_string(),
+                b"file data".to_vec()
+            ),
+        ]
+    );
+
+    // streaming part uses chunked encoding
+    let form = Form::new().text("name", "value").stream(
+        "file",
+        "data.bin",
+        mime::APPLICATION_OCTET_STREAM,
+        Box::pin(once(Ready::Ok::<_, JsonPayloadError>(Bytes::from_static(
+            b"streamed",
+        )))),
+    );
+    let boundary = form.boundary().to_string();
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert_eq!(response.headers().get("x-content-length").unwrap(), "");
+    assert_eq!(
+        response.headers().get("x-transfer-encoding").unwrap(),
+        "chunked"
+    );
+    let body = response.body().await.unwrap();
+    let parts = parts(&body, &boundary);
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[1].1, b"streamed".to_vec());
+}
+
+#[ntex::test]
+async fn test_send_stream() {
+    let srv = test::server(|| {
+        App::new()
+            .service(web::resource("/chunked").route(web::to(|| async {
+                HttpResponse::Ok().streaming(Box::pin(
+                    once(async { Ok::<_, Error>(Bytes::from_static(b"hello")) }).chain(
+                        once(async { Ok::<_, Error>(Bytes::from_static(b" world")) }),
+                    ),
+                ))
+            })))
+            .service(
+                web::resource("/sized")
+                    .route(web::to(|| async { HttpResponse::Ok().body(STR) })),
+            )
+            .service(web::resource("/sink").route(web::to(
+                |req: HttpRequest, body: Bytes| async move {
+                    let hdr = |name| {
+                        req.headers()
+                            .get(name)
+                            .map(|v| v.to_str().unwrap().to_string())
+                            .unwrap_or_default()
+                    };
+                    HttpResponse::Ok().body(format!(
+                        "{};{};{}",
+                        hdr(header::CONTENT_LENGTH),
+                        hdr(header::TRANSFER_ENCODING),
+                        String::from_utf8_lossy(&body)
+                    ))
+                },
+            )))
+    });
+
+    // stream server response to the other request
+    let upstream = srv.get("/chunked").send().await.unwrap();
+    let mut response = srv.post("/sink").send_stream(upstream).await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b";chunked;hello world"));
+
+    // known size
+    let upstream = srv.get("/sized").send().await.unwrap();
+    let len = upstream
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .unwrap()
+        .clone();
+    let mut response = srv
+        .post("/sink")
+        .header(header::CONTENT_LENGTH, len)
+        .send_stream(upstream)
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, format!("{};;{}", STR.len(), STR));
+
+    let request = srv.post("/sink").content_length(5).freeze().unwrap();
+    let mut response = request
+        .send_stream(once(Ready::Ok::<_, Error>(Bytes::from_static(b"hello"))))
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"5;;hello"));
+
+    // stream error aborts request
+    let res = srv
+        .post("/sink")
+        .send_stream(Box::pin(
+            once(async { Ok(Bytes::from_static(b"hello")) }).chain(once(async {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "stream error",
+                ))
+            })),
+        ))
+        .await;
+    match res {
+        Err(SendRequestError::Stream(e)) => assert_eq!(e.to_string(), "stream error"),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+
+    // stream length must match content length
+    let res = srv
+        .post("/sink")
+        .content_length(10)
+        .send_stream(once(Ready::Ok::<_, Error>(Bytes::from_static(b"short"))))
+        .await;
+    assert!(matches!(res, Err(SendRequestError::Stream(_))));
+    let res = srv
+        .post("/sink")
+        .content_length(2)
+        .send_stream(once(Ready::Ok::<_, Error>(Bytes::from_static(b"long"))))
+        .await;
+    assert!(matches!(res, Err(SendRequestError::Stream(_))));
+}
+
 #[ntex::test]
 async fn test_timeout() {
     let srv = test::server(|| {
@@ -201,6 +395,589 @@ async fn test_timeout_override() {
     }
 }
 
+#[ntex::test]
+async fn test_body_timeout() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            HttpResponse::Ok().streaming(Box::pin(
+                once(async { Ok::<_, Error>(Bytes::from_static(b"hello")) }).chain(once(
+                    async {
+                        sleep(Millis(1500)).await;
+                        Ok::<_, Error>(Bytes::from_static(b" world"))
+                    },
+                )),
+            ))
+        })))
+    });
+
+    let client = Client::build().body_timeout(Millis(500)).finish();
+
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    match response.body().await {
+        Err(PayloadError::Timeout) => (),
+        res => panic!("{:?}", res),
+    }
+
+    // per-request override
+    let mut response = client
+        .get(srv.url("/"))
+        .disable_body_timeout()
+        .send()
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"hello world"));
+}
+
+#[ntex::test]
+async fn test_redirects() {
+    async fn echo(req: HttpRequest, body: Bytes) -> HttpResponse {
+        HttpResponse::Ok().body(format!(
+            "{} {} {}",
+            req.method(),
+            String::from_utf8_lossy(&body),
+            req.headers().contains_key(header::AUTHORIZATION)
+        ))
+    }
+
+    let srv2 = test::server(|| App::new().service(web::resource("/echo").to(echo)));
+    let other = srv2.url("/echo");
+
+    let srv = test::server(move || {
+        let other = other.clone();
+        App::new()
+            .service(web::resource("/echo").to(echo))
+            .service(web::resource("/302").to(|_: Bytes| async {
+                HttpResponse::Found()
+                    .header(header::LOCATION, "/echo")
+                    .finish()
+            }))
+            .service(web::resource("/307").to(|_: Bytes| async {
+                HttpResponse::TemporaryRedirect()
+                    .header(header::LOCATION, "echo")
+                    .finish()
+            }))
+            .service(web::resource("/other").to(move || {
+                let other = other.clone();
+                async move {
+                    HttpResponse::Found()
+                        .header(header::LOCATION, other)
+                        .finish()
+                }
+            }))
+            .service(web::resource("/loop").to(|| async {
+                HttpResponse::Found()
+                    .header(header::LOCATION, "/loop")
+                    .finish()
+            }))
+    });
+
+    // redirects are not followed by default
+    let response = srv.post("/302").send().await.unwrap();
+    assert_eq!(response.status(), 302);
+
+    let client = Client::build().max_redirects(3).finish();
+
+    // POST switches to GET on 302
+    let mut response = client
+        .post(srv.url("/302"))
+        .bearer_auth("token")
+        .send_body("data")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.url().unwrap().path(), "/echo");
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"GET  true"));
+
+    // method and body are preserved on 307
+    let mut response = client
+        .post(srv.url("/307"))
+        .send_body("data")
+        .await
+        .unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"POST data false"));
+
+    // authorization is not sent to other origins
+    let mut response = client
+        .get(srv.url("/other"))
+        .bearer_auth("token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.url().unwrap().to_string(), srv2.url("/echo"));
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"GET  false"));
+
+    // last redirect is returned after max redirects
+    let response = client.get(srv.url("/loop")).send().await.unwrap();
+    assert_eq!(response.status(), 302);
+
+    // streaming body cannot be sent again
+    let res = client
+        .post(srv.url("/307"))
+        .send_stream(Box::pin(once(async {
+            Ok::<_, std::io::Error>(Bytes::from_static(b"data"))
+        })))
+        .await;
+    assert!(matches!(res, Err(SendRequestError::RedirectStream)));
+}
+
+#[ntex::test]
+async fn test_retry() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter2 = counter.clone();
+
+    // first two requests fail
+    let srv = test::server(move || {
+        let counter = counter2.clone();
+        App::new().service(web::resource("/flaky").to(move |body: Bytes| {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::Relaxed) >= 2 {
+                    HttpResponse::Ok().body(body)
+                } else {
+                    HttpResponse::ServiceUnavailable().finish()
+                }
+            }
+        }))
+    });
+
+    let policy = RetryPolicy::new(3).backoff(Millis(1), Millis(10));
+    let client = Client::build().retry(policy.clone()).finish();
+
+    // requests are not retried by default
+    let response = srv.get("/flaky").send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.attempts(), 1);
+
+    counter.store(0, Ordering::Relaxed);
+    let response = client.get(srv.url("/flaky")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.attempts(), 3);
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+
+    // body is sent again
+    counter.store(0, Ordering::Relaxed);
+    let mut response = client
+        .put(srv.url("/flaky"))
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(response.attempts(), 3);
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"data"));
+
+    // per request override
+    counter.store(0, Ordering::Relaxed);
+    let response = client
+        .get(srv.url("/flaky"))
+        .no_retry()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.attempts(), 1);
+
+    // non-idempotent requests are not retried
+    counter.store(0, Ordering::Relaxed);
+    let response = client.post(srv.url("/flaky")).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+    let client = Client::build().retry(policy.all_methods()).finish();
+    counter.store(0, Ordering::Relaxed);
+    let response = client.post(srv.url("/flaky")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.attempts(), 3);
+
+    // last response is returned after max attempts
+    let client = Client::build()
+        .retry(RetryPolicy::new(2).backoff(Millis(1), Millis(10)))
+        .finish();
+    counter.store(0, Ordering::Relaxed);
+    let response = client.get(srv.url("/flaky")).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.attempts(), 2);
+
+    // streaming body cannot be sent again
+    counter.store(0, Ordering::Relaxed);
+    let res = client
+        .put(srv.url("/flaky"))
+        .send_stream(Box::pin(once(async {
+            Ok::<_, std::io::Error>(Bytes::from_static(b"data"))
+        })))
+        .await;
+    assert!(matches!(res, Err(SendRequestError::RetryStream)));
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+}
+
+#[ntex::test]
+async fn test_retry_timeout() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter2 = counter.clone();
+
+    // first request does not respond in time
+    let srv = test::server(move || {
+        let counter = counter2.clone();
+        App::new().service(web::resource("/slow").to(move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::Relaxed) == 0 {
+                    ntex::time::sleep(Millis(2_000)).await;
+                }
+                HttpResponse::Ok().finish()
+            }
+        }))
+    });
+
+    // timeout is applied to each attempt
+    let client = Client::build()
+        .timeout(Millis(500))
+        .retry(RetryPolicy::new(3).backoff(Millis(200), Millis(200)))
+        .finish();
+    let response = client.get(srv.url("/slow")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.attempts(), 2);
+
+    // non-idempotent requests are not retried after timeout
+    counter.store(0, Ordering::Relaxed);
+    let client = Client::build()
+        .timeout(Millis(500))
+        .retry(RetryPolicy::new(3).all_methods())
+        .finish();
+    let res = client.post(srv.url("/slow")).send().await;
+    assert!(matches!(res, Err(SendRequestError::Timeout)));
+    assert_eq!(counter.load(Ordering::Relaxed), 1);
+}
+
+#[ntex::test]
+async fn test_resolver() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").to(|req: HttpRequest| async move {
+            HttpResponse::Ok().body(req.connection_info().host().to_string())
+        }))
+    });
+    let port = srv.addr().port();
+
+    // static host addresses
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .resolve_host("test.fake", ["127.0.0.1:0".parse().unwrap()])
+                .prefer_ipv4()
+                .finish(),
+        )
+        .finish();
+    let mut response = client
+        .get(format!("http://test.fake:{}/", port))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(format!("test.fake:{}", port)));
+
+    // custom resolver service
+    let addr = srv.addr();
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .resolver(ntex::service::fn_service(
+                    move |req: ntex::connect::Connect<ntex::http::Uri>| async move {
+                        if req.host() == "service.fake" {
+                            Ok(req.set_addr(Some(addr)))
+                        } else {
+                            Err(ntex::connect::ConnectError::Unresolved)
+                        }
+                    },
+                ))
+                .finish(),
+        )
+        .finish();
+    let response = client
+        .get(format!("http://service.fake:{}/", port))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let res = client.get("http://other.fake/").send().await;
+    assert!(matches!(
+        res,
+        Err(SendRequestError::Connect(ConnectError::Unresolved))
+    ));
+}
+
+#[ntex::test]
+async fn test_local_address() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").to(|req: HttpRequest| async move {
+            HttpResponse::Ok().body(req.peer_addr().unwrap().ip().to_string())
+        }))
+    });
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .local_address("127.0.0.1".parse().unwrap())
+                .finish(),
+        )
+        .finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"127.0.0.1"));
+
+    // whole loopback network is local on linux
+    #[cfg(target_os = "linux")]
+    {
+        let client = Client::build()
+            .connector(
+                Connector::default()
+                    .local_address("127.0.0.2".parse().unwrap())
+                    .finish(),
+            )
+            .finish();
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"127.0.0.2"));
+    }
+
+    // address does not belong to the host
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .local_address("192.0.2.1".parse().unwrap())
+                .finish(),
+        )
+        .finish();
+    let res = client.get(srv.url("/")).send().await;
+    assert!(matches!(
+        res,
+        Err(SendRequestError::Connect(ConnectError::Bind(addr, _))) if addr.to_string() == "192.0.2.1"
+    ));
+}
+
+#[ntex::test]
+async fn test_proxy() {
+    let srv = test::server(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            let auth = req
+                .headers()
+                .get(header::PROXY_AUTHORIZATION)
+                .map(|val| val.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            HttpResponse::Ok().body(format!("{} {}", req.uri(), auth))
+        }))
+    });
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .proxy(srv.url("/").replace("localhost", "user:pass@localhost"))
+                .finish(),
+        )
+        .finish();
+
+    // request target is sent in absolute form
+    let mut response = client
+        .get("http://example.invalid/path?q=1")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(
+        bytes,
+        Bytes::from_static(b"http://example.invalid/path?q=1 Basic dXNlcjpwYXNz")
+    );
+
+    // connection to the proxy is reused
+    let mut response = client.get("http://example.invalid/").send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(
+        bytes,
+        Bytes::from_static(b"http://example.invalid/ Basic dXNlcjpwYXNz")
+    );
+
+    // excluded hosts are connected directly
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .proxy("http://127.0.0.1:1")
+                .no_proxy(["localhost"])
+                .finish(),
+        )
+        .finish();
+    let mut response = client.get(srv.url("/direct")).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/direct "));
+}
+
+#[cfg(feature = "socks")]
+/// Socks5 proxy that records requested targets
+fn socks_proxy(
+    auth: Option<(&'static str, &'static str)>,
+) -> (std::net::SocketAddr, Arc<std::sync::Mutex<Vec<String>>>) {
+    use std::net::{TcpListener, TcpStream};
+
+    fn read(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    let targets = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    let tgts = targets.clone();
+    std::thread::spawn(move || {
+        for mut stream in lst.incoming().flatten() {
+            let targets = tgts.clone();
+            std::thread::spawn(move || {
+                let n = read(&mut stream, 2)[1] as usize;
+                let methods = read(&mut stream, n);
+                let method = if auth.is_some() { 2 } else { 0 };
+                if !methods.contains(&method) {
+                    stream.write_all(&[5, 0xff]).unwrap();
+                    return;
+                }
+                stream.write_all(&[5, method]).unwrap();
+
+                if let Some((user, pass)) = auth {
+                    let n = read(&mut stream, 2)[1] as usize;
+                    let u = read(&mut stream, n);
+                    let n = read(&mut stream, 1)[0] as usize;
+                    let p = read(&mut stream, n);
+                    if u != user.as_bytes() || p != pass.as_bytes() {
+                        stream.write_all(&[1, 1]).unwrap();
+                        return;
+                    }
+                    stream.write_all(&[1, 0]).unwrap();
+                }
+
+                let req = read(&mut stream, 5);
+                assert_eq!(req[3], 3, "host name must be resolved by proxy");
+                let host = String::from_utf8(read(&mut stream, req[4] as usize)).unwrap();
+                let port = read(&mut stream, 2);
+                let port = u16::from_be_bytes([port[0], port[1]]);
+                targets.lock().unwrap().push(format!("{}:{}", host, port));
+
+                let mut upstream = if let Ok(upstream) = TcpStream::connect((host, port)) {
+                    upstream
+                } else {
+                    stream.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+                    return;
+                };
+                stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+
+                let mut rd = stream.try_clone().unwrap();
+                let mut wr = upstream.try_clone().unwrap();
+                std::thread::spawn(move || {
+                    let _ = std::io::copy(&mut wr, &mut stream);
+                });
+                let _ = std::io::copy(&mut rd, &mut upstream);
+            });
+        }
+    });
+    (addr, targets)
+}
+
+#[cfg(feature = "socks")]
+#[ntex::test]
+async fn test_socks_proxy() {
+    use ntex::http::client::error::ConnectError;
+
+    let srv = test::server(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            HttpResponse::Ok().body(req.uri().to_string())
+        }))
+    });
+    let client =
+        |connector: Connector| Client::build().connector(connector.finish()).finish();
+
+    let (addr, targets) = socks_proxy(None);
+    let cl = client(Connector::default().proxy(format!("socks5h://{}", addr)));
+
+    // request is sent through the tunnel in origin form
+    let mut response = cl.get(srv.url("/path?q=1")).send().await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/path?q=1"));
+
+    // tunnel is reused
+    let mut response = cl.get(srv.url("/")).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/"));
+    assert_eq!(
+        *targets.lock().unwrap(),
+        vec![format!("localhost:{}", srv.addr().port())]
+    );
+
+    // target host refused connection
+    match cl.get("http://localhost:1/").send().await {
+        Err(SendRequestError::Connect(ConnectError::SocksConnectionRefused)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+
+    // username and password authentication
+    let (addr, _) = socks_proxy(Some(("user", "pass")));
+    let cl = client(Connector::default().proxy(format!("socks5://user:pass@{}", addr)));
+    let mut response = cl.get(srv.url("/auth")).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"/auth"));
+
+    let cl = client(
+        Connector::default()
+            .proxy(format!("socks5://{}", addr))
+            .proxy_basic_auth("user", Some("wrong")),
+    );
+    match cl.get(srv.url("/")).send().await {
+        Err(SendRequestError::Connect(ConnectError::SocksAuth)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+
+    // proxy does not accept offered methods
+    let cl = client(Connector::default().proxy(format!("socks5://{}", addr)));
+    match cl.get(srv.url("/")).send().await {
+        Err(SendRequestError::Connect(ConnectError::SocksMethod)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+}
+
+#[ntex::test]
+async fn test_headers_limits() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            HttpResponse::Ok()
+                .header("x-1", "1")
+                .header("x-2", "2")
+                .body(STR)
+        })))
+    });
+
+    let client = Client::build().max_headers(2).finish();
+    let request = client.get(srv.url("/")).send();
+    match request.await {
+        Err(SendRequestError::Response(ParseError::TooLarge)) => (),
+        _ => panic!(),
+    }
+
+    let client = Client::build().max_header_size(32).finish();
+    let request = client.get(srv.url("/")).send();
+    match request.await {
+        Err(SendRequestError::Response(ParseError::TooLarge)) => (),
+        _ => panic!(),
+    }
+
+    let client = Client::build().max_headers(10).finish();
+    let response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
 #[ntex::test]
 async fn test_connection_reuse() {
     let num = Arc::new(AtomicUsize::new(0));
@@ -793,6 +1570,76 @@ async fn test_client_cookie_handling() {
     assert_eq!(c2, cookie2);
 }
 
+#[ntex::test]
+async fn test_client_cookie_store() {
+    async fn echo(req: HttpRequest) -> HttpResponse {
+        let cookies = req
+            .headers()
+            .get(header::COOKIE)
+            .map(|val| val.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        HttpResponse::Ok().body(cookies)
+    }
+
+    let srv = test::server(|| {
+        App::new()
+            .service(web::resource("/login").to(|| async {
+                HttpResponse::Ok()
+                    .header(header::SET_COOKIE, "session=abc; Path=/")
+                    .header(header::SET_COOKIE, "admin=1; Path=/admin")
+                    .header(header::SET_COOKIE, "secure=1; Secure")
+                    .finish()
+            }))
+            .service(web::resource("/logout").to(|| async {
+                HttpResponse::Ok()
+                    .header(header::SET_COOKIE, "session=; Path=/; Max-Age=0")
+                    .finish()
+            }))
+            .service(web::resource("/echo").to(echo))
+            .service(web::resource("/admin/echo").to(echo))
+    });
+
+    // cookies are not stored by default
+    let client = Client::new();
+    client.get(srv.url("/login")).send().await.unwrap();
+    let mut response = client.get(srv.url("/echo")).send().await.unwrap();
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b""));
+    assert!(client.cookie_store().is_none());
+
+    let client = Client::build().cookie_store(true).finish();
+    client.get(srv.url("/login")).send().await.unwrap();
+    assert_eq!(client.cookies_for(srv.url("/")).len(), 1);
+
+    let mut response = client.get(srv.url("/echo")).send().await.unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"session=abc")
+    );
+
+    // request cookies are sent with stored cookies
+    let mut response = client
+        .get(srv.url("/admin/echo"))
+        .cookie(Cookie::new("extra", "2"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"extra=2; admin=1; session=abc")
+    );
+
+    // expired cookie is removed
+    client.get(srv.url("/logout")).send().await.unwrap();
+    let mut response = client.get(srv.url("/admin/echo")).send().await.unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"admin=1")
+    );
+
+    client.clear_cookies();
+    assert!(client.cookies_for(srv.url("/admin/echo")).is_empty());
+}
+
 #[ntex::test]
 async fn client_read_until_eof() {
     let addr = ntex::server::TestServer::unused_addr();
@@ -826,6 +1673,129 @@ async fn client_read_until_eof() {
     assert_eq!(bytes, Bytes::from_static(b"welcome!"));
 }
 
+#[ntex::test]
+async fn test_range() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").to(|req: HttpRequest| async move {
+            HttpResponse::Ok().body(
+                req.headers()
+                    .get(header::RANGE)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            )
+        }))
+    });
+
+    let mut response = srv.get("/").range(5, None).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"bytes=5-"));
+
+    let mut response = srv.get("/").range(0, Some(99)).send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"bytes=0-99"));
+}
+
+/// Raw http server, responds with result of `f(connection_index, range_start)`
+fn download_server<F>(f: F) -> String
+where
+    F: Fn(usize, Option<String>) -> &'static str + Send + 'static,
+{
+    let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for (idx, mut stream) in lst.incoming().flatten().enumerate() {
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            let req = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+            let range = req
+                .lines()
+                .find_map(|line| line.strip_prefix("range: bytes="))
+                .map(|start| start.trim_end_matches('-').to_string());
+            let _ = stream.write_all(f(idx, range).as_bytes());
+        }
+    });
+    format!("http://{}/", addr)
+}
+
+const INTERRUPTED: &str = "HTTP/1.1 200 OK\r\naccept-ranges: bytes\r\netag: \"v1\"\r\ncontent-length: 10\r\n\r\n01234";
+
+#[ntex::test]
+async fn test_download_to() {
+    // resume from current offset
+    let url = download_server(|idx, range| match idx {
+        0 => {
+            assert_eq!(range, None);
+            INTERRUPTED
+        }
+        _ => {
+            assert_eq!(range.as_deref(), Some("5"));
+            "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 5-9/10\r\ncontent-length: 5\r\n\r\n56789"
+        }
+    });
+    let mut buf = Vec::new();
+    let res = Client::new().get(&url).download_to(&mut buf).await;
+    assert_eq!(res.unwrap(), 10);
+    assert_eq!(buf, b"0123456789");
+
+    // server ignores range, download restarts
+    let url = download_server(|idx, _| match idx {
+        0 => INTERRUPTED,
+        _ => "HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nabcdefghij",
+    });
+    let mut buf = Vec::new();
+    let res = Client::new().get(&url).download_to(&mut buf).await;
+    assert_eq!(res.unwrap(), 10);
+    assert_eq!(buf, b"abcdefghij");
+
+    // resource changed
+    let url = download_server(|idx, _| {
+        match idx {
+        0 => INTERRUPTED,
+        _ => "HTTP/1.1 206 Partial Content\r\netag: \"v2\"\r\ncontent-range: bytes 5-9/10\r\ncontent-length: 5\r\n\r\n56789",
+    }
+    });
+    let res = Client::new().get(&url).download_to(Vec::new()).await;
+    assert!(matches!(res, Err(DownloadError::Modified)));
+
+    // unexpected range
+    let url = download_server(|idx, _| {
+        match idx {
+        0 => INTERRUPTED,
+        _ => "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 0-9/10\r\ncontent-length: 10\r\n\r\n0123456789",
+    }
+    });
+    let res = Client::new().get(&url).download_to(Vec::new()).await;
+    assert!(matches!(res, Err(DownloadError::InvalidRange)));
+
+    // server does not support ranges
+    let url = download_server(|_, _| {
+        "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 10\r\n\r\n01234"
+    });
+    let res = Client::new().get(&url).download_to(Vec::new()).await;
+    assert!(matches!(
+        res,
+        Err(DownloadError::Payload(PayloadError::Incomplete(_)))
+    ));
+
+    // every attempt is interrupted
+    let url = download_server(|_, range| {
+        match range.as_deref() {
+        None => INTERRUPTED,
+        Some("5") => "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 5-9/10\r\ncontent-length: 5\r\n\r\n56",
+        _ => "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 7-9/10\r\ncontent-length: 3\r\n\r\n7",
+    }
+    });
+    let mut buf = Vec::new();
+    let res = Client::new().get(&url).download_to(&mut buf).await;
+    assert!(matches!(
+        res,
+        Err(DownloadError::Payload(PayloadError::Incomplete(_)))
+    ));
+    assert_eq!(buf, b"01234567");
+}
+
 #[ntex::test]
 async fn client_basic_auth() {
     let srv = test::server(|| {
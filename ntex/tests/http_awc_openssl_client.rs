@@ -5,7 +5,7 @@ use tls_openssl::ssl::{
     AlpnError, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
 };
 
-use ntex::http::client::{Client, Connector};
+use ntex::http::client::{Certificate, Client, Connector, TlsConfig};
 use ntex::http::test::server as test_server;
 use ntex::http::{HttpService, Version};
 use ntex::service::{chain_factory, map_config, ServiceFactory};
@@ -87,3 +87,66 @@ async fn test_connection_reuse_h2() {
     // one connection
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[ntex::test]
+async fn test_tls_config() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(map_config(
+                App::new().service(
+                    web::resource("/").route(web::to(|| async { HttpResponse::Ok() })),
+                ),
+                |_| AppConfig::default(),
+            ))
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+    let cert = Certificate::from_pem(&std::fs::read("./tests/cert.pem").unwrap()).unwrap();
+    let url = format!("https://www.example.com:{}/", srv.addr().port());
+    let client =
+        |connector: Connector| Client::build().connector(connector.finish()).finish();
+
+    // server certificate is verified by default
+    let res = client(Connector::default()).get(srv.surl("/")).send().await;
+    assert!(res.is_err());
+
+    // trusted self-signed certificate
+    let connector = Connector::default()
+        .tls_config(TlsConfig::new().add_root_certificate(cert.clone()))
+        .unwrap()
+        .resolve_host("www.example.com", ["127.0.0.1:0".parse().unwrap()]);
+    let response = client(connector).get(&url).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // host name does not match certificate
+    let connector = Connector::default()
+        .tls_config(TlsConfig::new().add_root_certificate(cert.clone()))
+        .unwrap();
+    let res = client(connector).get(srv.surl("/")).send().await;
+    assert!(res.is_err());
+
+    let config = TlsConfig::new()
+        .add_root_certificate(cert.clone())
+        .danger_accept_invalid_hostnames(true);
+    let connector = Connector::default().tls_config(config).unwrap();
+    let response = client(connector).get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // untrusted certificate
+    let config = TlsConfig::new().danger_accept_invalid_certs(true);
+    let connector = Connector::default().tls_config(config).unwrap();
+    let response = client(connector).get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // invalid settings are reported
+    let config = TlsConfig::new().add_root_certificate(Certificate::from_der(b"cert"));
+    assert!(Connector::default().tls_config(config).is_err());
+
+    // client builder settings
+    let config = TlsConfig::new()
+        .add_root_certificate(cert)
+        .danger_accept_invalid_hostnames(true);
+    let client = Client::build().tls_config(config).unwrap().finish();
+    let response = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+}
@@ -1,5 +1,7 @@
 use std::io;
 
+use futures_util::stream::once;
+
 use ntex::http::test::server as test_server;
 use ntex::http::{HttpService, Method, Request, Response};
 use ntex::service::ServiceFactory;
@@ -87,3 +89,36 @@ async fn test_with_query_parameter() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[ntex::test]
+async fn test_h1_trailers() {
+    let srv = test_server(move || {
+        HttpService::build().finish(|_| {
+            Ready::Ok::<_, io::Error>(
+                Response::Ok()
+                    .trailer("x-checksum", "1234")
+                    .streaming(once(Ready::Ok::<_, io::Error>(Bytes::from(STR)))),
+            )
+        })
+    });
+
+    let mut response = srv.request(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.trailers().is_none());
+
+    // trailers are available after payload eof
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+    assert_eq!(
+        response.trailers().unwrap().get("x-checksum").unwrap(),
+        "1234"
+    );
+
+    // response without trailers
+    let srv = test_server(move || {
+        HttpService::build().finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().body(STR)))
+    });
+    let mut response = srv.request(Method::GET, "/").send().await.unwrap();
+    let _ = response.body().await.unwrap();
+    assert!(response.trailers().is_none());
+}
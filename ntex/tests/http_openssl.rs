@@ -5,7 +5,7 @@ use futures_util::stream::{once, Stream, StreamExt};
 use tls_openssl::ssl::{AlpnError, SslAcceptor, SslFiletype, SslMethod};
 
 use ntex::codec::BytesCodec;
-use ntex::http::error::PayloadError;
+use ntex::http::error::{H2SettingsError, PayloadError};
 use ntex::http::header::{self, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
 use ntex::http::{body, h1, HttpService, Method, Request, Response, StatusCode, Version};
@@ -137,6 +137,250 @@ async fn test_h2_body() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_h2_trailers() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(|_| {
+                Ready::Ok::<_, io::Error>(
+                    Response::Ok()
+                        .trailer("x-checksum", "1234")
+                        .streaming(once(Ready::Ok::<_, io::Error>(Bytes::from("test")))),
+                )
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"test"));
+    assert_eq!(
+        response.trailers().unwrap().get("x-checksum").unwrap(),
+        "1234"
+    );
+}
+
+#[ntex::test]
+async fn test_h2_flow_control() -> io::Result<()> {
+    let data = "HELLOWORLD".to_owned().repeat(64 * 1024);
+    let mut srv = test_server(move || {
+        HttpService::build()
+            .initial_window_size(1024 * 1024)
+            .unwrap()
+            .initial_connection_window_size(4 * 1024 * 1024)
+            .unwrap()
+            .max_concurrent_streams(10)
+            .max_frame_size(64 * 1024)
+            .unwrap()
+            .h2(|mut req: Request| async move {
+                let body = load_body(req.take_payload())
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                Ok::<_, io::Error>(Response::Ok().body(body))
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv
+        .srequest(Method::GET, "/")
+        .send_body(data.clone())
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let body = srv.load_body(response).await.unwrap();
+    assert_eq!(&body, data.as_bytes());
+    Ok(())
+}
+
+/// Read http/2 settings and connection window increment sent by peer
+fn read_h2_settings<S: io::Read>(stream: &mut S) -> (Vec<(u16, u32)>, u32) {
+    let (mut settings, mut window) = (None, None);
+    while settings.is_none() || window.is_none() {
+        let mut head = [0u8; 9];
+        stream.read_exact(&mut head).unwrap();
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).unwrap();
+        match head[3] {
+            // SETTINGS frame, not ACK
+            0x4 if head[4] & 0x1 == 0 => {
+                settings = Some(
+                    payload
+                        .chunks(6)
+                        .map(|c| {
+                            let id = u16::from_be_bytes([c[0], c[1]]);
+                            (id, u32::from_be_bytes([c[2], c[3], c[4], c[5]]))
+                        })
+                        .collect(),
+                );
+            }
+            // connection level WINDOW_UPDATE frame
+            0x8 if head[5..] == [0; 4] => {
+                let inc =
+                    u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                window = Some(inc & 0x7fff_ffff);
+            }
+            _ => (),
+        }
+    }
+    (settings.unwrap(), window.unwrap())
+}
+
+#[ntex::test]
+async fn test_h2_settings() {
+    use std::io::Write;
+    use tls_openssl::ssl::{SslConnector, SslVerifyMode};
+
+    type Builder = ntex::http::HttpServiceBuilder<ntex::io::Base, h1::ExpectHandler>;
+    let err = Builder::new().max_frame_size(1024).err().unwrap();
+    assert_eq!(err, H2SettingsError::MaxFrameSize(1024));
+    let err = Builder::new().initial_window_size(1 << 31).err().unwrap();
+    assert_eq!(err, H2SettingsError::WindowSize(1 << 31));
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .initial_window_size(1024 * 1024)
+            .unwrap()
+            .initial_connection_window_size(4 * 1024 * 1024)
+            .unwrap()
+            .max_concurrent_streams(10)
+            .max_frame_size(64 * 1024)
+            .unwrap()
+            .h2(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+    let conn = std::net::TcpStream::connect(srv.addr()).unwrap();
+    conn.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let mut stream = builder.build().connect("localhost", conn).unwrap();
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+        .unwrap();
+    stream.write_all(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0]).unwrap();
+
+    let (settings, window) = read_h2_settings(&mut stream);
+    assert!(settings.contains(&(0x3, 10)));
+    assert!(settings.contains(&(0x4, 1024 * 1024)));
+    assert!(settings.contains(&(0x5, 64 * 1024)));
+    assert_eq!(window, 4 * 1024 * 1024 - 65_535);
+}
+
+#[ntex::test]
+async fn test_h2_client_settings() {
+    use ntex::http::client::{Client, Connector};
+    use std::io::Read;
+    use tls_openssl::ssl::{SslConnector, SslVerifyMode};
+
+    let acceptor = ssl_acceptor();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let srv = std::thread::spawn(move || {
+        let (conn, _) = listener.accept().unwrap();
+        conn.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let mut stream = acceptor.accept(conn).unwrap();
+        let mut preface = [0u8; 24];
+        stream.read_exact(&mut preface).unwrap();
+        assert_eq!(&preface[..], b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+        read_h2_settings(&mut stream)
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+    let connector = Connector::default()
+        .openssl(builder.build())
+        .initial_window_size(512 * 1024)
+        .unwrap()
+        .initial_connection_window_size(2 * 1024 * 1024)
+        .unwrap()
+        .max_concurrent_streams(20)
+        .max_frame_size(32 * 1024)
+        .unwrap()
+        .finish();
+    let client = Client::build().connector(connector).finish();
+    let req = client
+        .get(format!("https://localhost:{}/", addr.port()))
+        .send();
+    let _ = timeout(Millis(1000), req).await;
+
+    let (settings, window) = srv.join().unwrap();
+    assert!(settings.contains(&(0x3, 20)));
+    assert!(settings.contains(&(0x4, 512 * 1024)));
+    assert!(settings.contains(&(0x5, 32 * 1024)));
+    assert_eq!(window, 2 * 1024 * 1024 - 65_535);
+}
+
+#[ntex::test]
+async fn test_h2_payload_overflow() {
+    use std::io::{Read, Write};
+    use tls_openssl::ssl::{SslConnector, SslVerifyMode};
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .max_payload_size(1024)
+            .h2(|mut req: Request| async move {
+                let res = load_body(req.take_payload()).await;
+                assert!(matches!(res, Err(PayloadError::Overflow)));
+                Ok::<_, io::Error>(Response::Ok().finish())
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+    let conn = std::net::TcpStream::connect(srv.addr()).unwrap();
+    conn.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let mut stream = builder.build().connect("localhost", conn).unwrap();
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+        .unwrap();
+    stream.write_all(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0]).unwrap();
+
+    // POST request headers for stream 1, without END_STREAM flag
+    let mut headers = vec![0x83, 0x87, 0x84, 0x01, 0x09];
+    headers.extend_from_slice(b"localhost");
+    stream
+        .write_all(&[0, 0, headers.len() as u8, 0x1, 0x4, 0, 0, 0, 1])
+        .unwrap();
+    stream.write_all(&headers).unwrap();
+
+    // two DATA frames, 1600 bytes in total
+    for _ in 0..2 {
+        stream
+            .write_all(&[0, 0x3, 0x20, 0x0, 0, 0, 0, 0, 1])
+            .unwrap();
+        stream.write_all(&[b'x'; 800]).unwrap();
+    }
+
+    // server resets stream with CANCEL error code
+    loop {
+        let mut head = [0u8; 9];
+        stream.read_exact(&mut head).unwrap();
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).unwrap();
+        if head[3] == 0x3 {
+            assert_eq!(head[5..], [0, 0, 0, 1]);
+            assert_eq!(payload, [0, 0, 0, 0x8]);
+            break;
+        }
+    }
+}
+
 #[ntex::test]
 async fn test_h2_content_length() {
     let srv = test_server(move || {
@@ -528,3 +772,120 @@ async fn test_ws_transport() {
         }))
     );
 }
+
+/// Minimal `CONNECT` proxy
+fn tunnel_proxy(auth: Option<&'static str>) -> std::net::SocketAddr {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in lst.incoming().flatten() {
+            std::thread::spawn(move || {
+                let mut head = Vec::new();
+                let mut b = [0; 1];
+                while !head.ends_with(b"\r\n\r\n") {
+                    if stream.read(&mut b).unwrap() == 0 {
+                        return;
+                    }
+                    head.push(b[0]);
+                }
+                let head = String::from_utf8(head).unwrap();
+                if let Some(auth) = auth {
+                    if !head.contains(&format!("proxy-authorization: {}\r\n", auth)) {
+                        let _ = stream.write_all(
+                            b"HTTP/1.1 407 Proxy Authentication Required\r\ncontent-length: 0\r\n\r\n",
+                        );
+                        return;
+                    }
+                }
+                assert!(head.starts_with("CONNECT "));
+                let target = head.split_whitespace().nth(1).unwrap();
+                let mut upstream = TcpStream::connect(target).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .unwrap();
+
+                let mut rd = stream.try_clone().unwrap();
+                let mut wr = upstream.try_clone().unwrap();
+                std::thread::spawn(move || {
+                    let _ = std::io::copy(&mut wr, &mut stream);
+                });
+                let _ = std::io::copy(&mut rd, &mut upstream);
+            });
+        }
+    });
+    addr
+}
+
+#[ntex::test]
+async fn test_proxy_tunnel() {
+    use ntex::http::client::{error::ConnectError, error::SendRequestError};
+    use ntex::http::client::{Client, Connector, TlsConfig};
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .finish(|req: Request| {
+                Ready::Ok::<_, io::Error>(Response::Ok().body(req.path().to_string()))
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+    // test server uses self-signed certificate
+    let client = |connector: Connector| {
+        Client::build()
+            .connector(
+                connector
+                    .tls_config(TlsConfig::new().danger_accept_invalid_certs(true))
+                    .unwrap()
+                    .finish(),
+            )
+            .finish()
+    };
+
+    let proxy = format!("http://{}", tunnel_proxy(None));
+    let mut response = client(Connector::default().proxy(proxy.as_str()))
+        .get(srv.surl("/test"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"/test"));
+
+    // proxy authentication
+    let proxy = format!("http://{}", tunnel_proxy(Some("Basic dXNlcjpwYXNz")));
+    let res = client(Connector::default().proxy(proxy.as_str()))
+        .get(srv.surl("/"))
+        .send()
+        .await;
+    assert!(matches!(
+        res,
+        Err(SendRequestError::Connect(ConnectError::ProxyTunnel(
+            StatusCode::PROXY_AUTHENTICATION_REQUIRED
+        )))
+    ));
+
+    let response = client(
+        Connector::default()
+            .proxy(proxy.as_str())
+            .proxy_basic_auth("user", Some("pass")),
+    )
+    .get(srv.surl("/"))
+    .send()
+    .await
+    .unwrap();
+    assert!(response.status().is_success());
+
+    // excluded hosts are connected directly
+    let response = client(
+        Connector::default()
+            .proxy("http://127.0.0.1:1")
+            .no_proxy(["localhost"]),
+    )
+    .get(srv.surl("/"))
+    .send()
+    .await
+    .unwrap();
+    assert!(response.status().is_success());
+}
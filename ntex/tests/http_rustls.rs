@@ -0,0 +1,120 @@
+#![cfg(feature = "rustls")]
+use std::{io, io::Read, sync::Arc, time::Duration};
+
+use tls_rustls::{ClientConfig, ServerConfig};
+
+use ntex::http::client::{Client, Connector};
+use ntex::http::test::server as test_server;
+use ntex::http::{HttpService, Method, Response, Version};
+use ntex::service::ServiceFactory;
+use ntex::{time::Seconds, util::Ready};
+
+fn tls_config() -> ServerConfig {
+    use rustls_pemfile::{certs, pkcs8_private_keys};
+    use std::fs::File;
+    use std::io::BufReader;
+    use tls_rustls::{Certificate, PrivateKey};
+
+    let cert_file = &mut BufReader::new(File::open("tests/cert.pem").unwrap());
+    let key_file = &mut BufReader::new(File::open("tests/key.pem").unwrap());
+    let cert_chain = certs(cert_file)
+        .unwrap()
+        .iter()
+        .map(|c| Certificate(c.to_vec()))
+        .collect();
+    let key = PrivateKey(pkcs8_private_keys(key_file).unwrap().remove(0));
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .unwrap()
+}
+
+mod danger {
+    use std::time::SystemTime;
+    use tls_rustls::{Certificate, ServerName};
+
+    pub struct NoCertificateVerification {}
+
+    impl tls_rustls::client::ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<tls_rustls::client::ServerCertVerified, tls_rustls::Error> {
+            Ok(tls_rustls::client::ServerCertVerified::assertion())
+        }
+    }
+}
+
+fn client(protos: &[&[u8]]) -> Client {
+    let mut config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(danger::NoCertificateVerification {}))
+        .with_no_client_auth();
+    config.alpn_protocols = protos.iter().map(|p| p.to_vec()).collect();
+
+    Client::build()
+        .connector(Connector::default().rustls(config).finish())
+        .finish()
+}
+
+#[ntex::test]
+async fn test_h1() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+            .rustls(tls_config())
+            .map_err(|_| ())
+    });
+
+    let response = client(&[b"http/1.1"])
+        .request(Method::GET, srv.surl("/"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_11);
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+            .rustls(tls_config())
+            .map_err(|_| ())
+    });
+
+    let response = client(&[b"h2", b"http/1.1"])
+        .request(Method::GET, srv.surl("/"))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_handshake_timeout() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .ssl_handshake_timeout(Seconds(1))
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+            .rustls(tls_config())
+            .map_err(|_| ())
+    });
+
+    // client never starts handshake, server must close connection
+    let mut stream = std::net::TcpStream::connect(srv.addr())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut buf = [0; 64];
+    assert_eq!(stream.read(&mut buf)?, 0);
+    Ok(())
+}
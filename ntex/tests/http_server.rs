@@ -5,13 +5,15 @@ use futures_util::future::{self, FutureExt};
 use futures_util::stream::{once, StreamExt};
 use regex::Regex;
 
+use ntex::codec::BytesCodec;
 use ntex::http::header::{self, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
 use ntex::http::{
-    body, HttpService, KeepAlive, Method, Request, Response, StatusCode, Version,
+    body, h1, HttpService, KeepAlive, Method, Request, Response, StatusCode, Version,
 };
+use ntex::io::Io;
 use ntex::time::{sleep, timeout, Millis, Seconds};
-use ntex::{service::fn_service, util::Bytes, util::Ready, web::error};
+use ntex::{service::fn_service, util::Bytes, util::Either, util::Ready, web::error};
 
 #[ntex::test]
 async fn test_h1() {
@@ -94,6 +96,34 @@ async fn test_expect_continue() {
     assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
 }
 
+#[ntex::test]
+async fn test_expect_either() {
+    let srv = test_server(|| {
+        let expect = if std::env::var("NTEX_TEST_DEFAULT_EXPECT").is_ok() {
+            Either::Left(fn_service(|req: Request| async move {
+                Ok::<_, error::InternalError<&'static str>>(req)
+            }))
+        } else {
+            Either::Right(fn_service(|_: Request| async move {
+                Err(error::InternalError::default(
+                    "error",
+                    StatusCode::PRECONDITION_FAILED,
+                ))
+            }))
+        };
+        HttpService::build()
+            .expect(expect)
+            .keep_alive(KeepAlive::Disabled)
+            .h1(|_| async { Ok::<_, io::Error>(Response::Ok().finish()) })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nexpect: 100-continue\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+}
+
 #[ntex::test]
 async fn test_chunked_payload() {
     let chunk_sizes = vec![32768, 32, 32768];
@@ -145,6 +175,38 @@ async fn test_chunked_payload() {
     assert_eq!(returned_size, total_size);
 }
 
+#[ntex::test]
+async fn test_chunked_payload_trailers() {
+    let srv = test_server(|| {
+        HttpService::build().h1(fn_service(|mut req: Request| async move {
+            let mut pl = req.take_payload();
+            let mut body = Vec::new();
+            while let Some(chunk) = pl.next().await {
+                body.extend_from_slice(&chunk.unwrap());
+            }
+            let checksum = pl.trailers().unwrap().get("x-checksum").unwrap().clone();
+
+            Ok::<_, io::Error>(
+                Response::Ok()
+                    .trailer("x-checksum", checksum)
+                    .streaming(once(Ready::Ok::<_, io::Error>(Bytes::from(body)))),
+            )
+        }))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nconnection: close\r\n\
+          transfer-encoding: chunked\r\n\r\n\
+          4\r\ndata\r\n0\r\nx-checksum: 1234\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("transfer-encoding: chunked\r\n"));
+    assert!(data.ends_with("\r\n\r\n4\r\ndata\r\n0\r\nx-checksum: 1234\r\n\r\n"));
+}
+
 #[ntex::test]
 async fn test_slow_request() {
     let srv = test_server(|| {
@@ -160,6 +222,74 @@ async fn test_slow_request() {
     assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
 }
 
+#[ntex::test]
+async fn test_slow_request_no_data() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .client_timeout(Seconds(1))
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    // nothing is sent, connection get dropped without response
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.is_empty());
+}
+
+#[ntex::test]
+async fn test_slow_request_keepalive() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(30)
+            .client_timeout(Seconds(1))
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    // partial head of next request is bound by client timeout
+    let start = std::time::Instant::now();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[ntex::test]
+async fn test_slow_request_payload() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(30)
+            .disconnect_timeout(Seconds(1))
+            .finish(|mut req: Request| {
+                let pl = req.take_payload();
+                ntex::rt::spawn(async move {
+                    sleep(Millis(30_000)).await;
+                    drop(pl);
+                });
+                Ready::Ok::<_, io::Error>(Response::Ok().finish())
+            })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\ncontent-length: 100\r\n\r\n1234");
+    let mut data = vec![0; 1024];
+    let _ = stream.read(&mut data);
+    assert_eq!(&data[..17], b"HTTP/1.1 200 OK\r\n");
+
+    // rest of payload is bound by disconnect timeout
+    let start = std::time::Instant::now();
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+}
+
 #[ntex::test]
 async fn test_http1_malformed_request() {
     let srv = test_server(|| {
@@ -296,6 +426,51 @@ async fn test_http10_keepalive() {
     assert_eq!(res, 0);
 }
 
+#[ntex::test]
+async fn test_http10_keepalive_reuse() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Os)
+            .h1(|_| Ready::Ok::<_, io::Error>(Response::Ok().body("test")))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    for _ in 0..2 {
+        let _ = stream.write_all(b"GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        let mut data = vec![0; 1024];
+        let size = stream.read(&mut data).unwrap();
+        let data = String::from_utf8_lossy(&data[..size]);
+        assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(data.contains("connection: keep-alive\r\n"));
+        assert!(data.contains("content-length: 4\r\n"));
+        assert!(data.ends_with("\r\n\r\ntest"));
+    }
+}
+
+#[ntex::test]
+async fn test_http10_streaming_body() {
+    let srv = test_server(|| {
+        HttpService::build().h1(|_| {
+            Ready::Ok::<_, io::Error>(Response::Ok().streaming(once(Ready::Ok::<
+                _,
+                io::Error,
+            >(
+                Bytes::from_static(b"test body"),
+            ))))
+        })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    let data = String::from_utf8(data).unwrap();
+    assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(!data.contains("transfer-encoding"));
+    assert!(!data.contains("connection: keep-alive"));
+    assert!(data.ends_with("\r\n\r\ntest body"));
+}
+
 #[ntex::test]
 async fn test_http1_keepalive_disabled() {
     let srv = test_server(|| {
@@ -636,3 +811,236 @@ async fn test_h1_client_drop() -> io::Result<()> {
     assert_eq!(count.load(Ordering::Relaxed), 1);
     Ok(())
 }
+
+fn read_head(stream: &mut net::TcpStream) -> String {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 1];
+    while !data.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut buf).unwrap();
+        data.push(buf[0]);
+    }
+    String::from_utf8(data).unwrap()
+}
+
+#[ntex::test]
+async fn test_h1_connect_tunnel() {
+    // upstream echo server
+    let upstream = ntex::server::test_server(|| {
+        fn_service(|io: Io| async move {
+            while let Some(item) = io.recv(&BytesCodec).await.map_err(|e| e.into_inner())? {
+                io.send(item.freeze(), &BytesCodec)
+                    .await
+                    .map_err(|e| e.into_inner())?;
+            }
+            Ok::<_, io::Error>(())
+        })
+    });
+    let addr = upstream.addr();
+
+    let srv = test_server(move || {
+        HttpService::build().h1(move |req: Request| async move {
+            assert_eq!(*req.method(), Method::CONNECT);
+            if req.uri().host() != Some("upstream") {
+                return Ok::<_, io::Error>(Response::Forbidden().finish());
+            }
+
+            let (io, codec) = *req.head().take_io().unwrap();
+            let upstream = ntex::connect::connect::<String, _>(addr.to_string())
+                .await
+                .unwrap();
+            io.encode(
+                h1::Message::Item((
+                    Response::Ok().finish().drop_body(),
+                    body::BodySize::None,
+                )),
+                &codec,
+            )?;
+
+            // splice bytes between client and upstream
+            let client_ref = io.get_ref();
+            let upstream_ref = upstream.get_ref();
+            ntex::rt::spawn(async move {
+                while let Ok(Some(item)) = upstream.recv(&BytesCodec).await {
+                    let _ = client_ref.encode(item.freeze(), &BytesCodec);
+                }
+                client_ref.close();
+            });
+            ntex::rt::spawn(async move {
+                while let Ok(Some(item)) = io.recv(&BytesCodec).await {
+                    let _ = upstream_ref.encode(item.freeze(), &BytesCodec);
+                }
+                upstream_ref.close();
+            });
+            Ok(Response::Ok().finish())
+        })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+
+    // rejected tunnel keeps connection usable
+    let _ = stream.write_all(b"CONNECT denied:443 HTTP/1.1\r\nhost: denied:443\r\n\r\n");
+    let head = read_head(&mut stream);
+    assert!(head.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    assert!(head.contains("content-length: 0\r\n"));
+
+    let _ = stream.write_all(b"CONNECT upstream:80 HTTP/1.1\r\nhost: upstream:80\r\n\r\n");
+    let head = read_head(&mut stream);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!head.contains("content-length"));
+    assert!(!head.contains("transfer-encoding"));
+
+    let data = "0123456789".repeat(512);
+    let mut buf = vec![0u8; data.len()];
+    for _ in 0..2 {
+        let _ = stream.write_all(data.as_bytes());
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], data.as_bytes());
+    }
+}
+
+#[ntex::test]
+async fn test_h1_informational() {
+    let srv = test_server(|| {
+        HttpService::build().h1(|req: Request| async move {
+            let early_hints = StatusCode::from_u16(103).unwrap();
+            let mut hdrs = header::HeaderMap::new();
+            hdrs.insert(
+                header::LINK,
+                HeaderValue::from_static("</style.css>; rel=preload; as=style"),
+            );
+            req.send_informational(early_hints, &hdrs)?;
+            req.send_informational(early_hints, &hdrs)?;
+            assert!(req
+                .send_informational(StatusCode::OK, &header::HeaderMap::new())
+                .is_err());
+
+            let body = once(Box::pin(async move {
+                // final response is started
+                assert!(req.send_informational(early_hints, &hdrs).is_err());
+                Ok::<_, io::Error>(Bytes::from_static(b"test"))
+            }));
+            Ok::<_, io::Error>(
+                Response::Ok()
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .streaming(body),
+            )
+        })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    let data = String::from_utf8(data).unwrap();
+    let hint =
+        "HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload; as=style\r\n\r\n";
+    assert!(data.starts_with(&format!("{}{}HTTP/1.1 200 OK\r\n", hint, hint)));
+    let final_head = &data[hint.len() * 2..];
+    assert!(final_head.contains("content-type: text/plain\r\n"));
+    assert!(!final_head.contains("link:"));
+
+    // interim responses are skipped for http/1.0 peers
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test HTTP/1.0\r\n\r\n");
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    assert!(String::from_utf8(data)
+        .unwrap()
+        .starts_with("HTTP/1.0 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_h2_informational() {
+    use ntex_h2::frame::{self, Frame, PseudoHeaders, Settings, StreamId};
+
+    let srv = test_server(|| {
+        HttpService::build().h2(|req: Request| async move {
+            let early_hints = StatusCode::from_u16(103).unwrap();
+            let mut hdrs = header::HeaderMap::new();
+            hdrs.insert(
+                header::LINK,
+                HeaderValue::from_static("</style.css>; rel=preload; as=style"),
+            );
+            req.send_informational(early_hints, &hdrs)?;
+            assert!(req
+                .send_informational(StatusCode::OK, &header::HeaderMap::new())
+                .is_err());
+
+            let body = once(Box::pin(async move {
+                // final response is started
+                assert!(req.send_informational(early_hints, &hdrs).is_err());
+                Ok::<_, io::Error>(Bytes::from_static(b"test"))
+            }));
+            Ok::<_, io::Error>(Response::Ok().streaming(body))
+        })
+    });
+
+    let io = ntex::connect::connect(srv.addr()).await.unwrap();
+    let codec = ntex_h2::Codec::default();
+    io.write(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").unwrap();
+    io.send(Frame::Settings(Settings::default()), &codec)
+        .await
+        .unwrap();
+    let uri = format!("http://{}/test", srv.addr()).parse().unwrap();
+    let pseudo = PseudoHeaders::request(Method::GET, uri, None);
+    let mut req = frame::Headers::new(StreamId::from(1), pseudo, Default::default(), true);
+    req.set_end_headers();
+    io.send(Frame::Headers(req), &codec).await.unwrap();
+
+    let mut responses = Vec::new();
+    while responses.len() < 2 {
+        if let Frame::Headers(hdrs) = io.recv(&codec).await.unwrap().unwrap() {
+            responses.push(hdrs);
+        }
+    }
+    assert_eq!(responses[0].pseudo().status, StatusCode::from_u16(103).ok());
+    assert_eq!(
+        responses[0].fields().get(header::LINK).unwrap(),
+        "</style.css>; rel=preload; as=style"
+    );
+    assert!(!responses[0].is_end_stream());
+    assert_eq!(responses[1].pseudo().status, Some(StatusCode::OK));
+    assert!(responses[1].fields().get(header::LINK).is_none());
+}
+
+#[ntex::test]
+async fn test_h1_date_header() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .date_header(false)
+            .h1(|req: Request| async move {
+                let mut res = Response::Ok();
+                if req.path() == "/date" {
+                    res.header(header::DATE, "Sun, 06 Nov 1994 08:49:37 GMT");
+                }
+                Ok::<_, io::Error>(res.finish())
+            })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!data.contains("date: "));
+
+    // date set by the service is sent once
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /date HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert_eq!(data.matches("date: ").count(), 1);
+    assert!(data.contains("date: Sun, 06 Nov 1994 08:49:37 GMT\r\n"));
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .date_source(|| std::time::SystemTime::UNIX_EPOCH)
+            .h1(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.contains("date: Thu, 01 Jan 1970 00:00:00 GMT\r\n"));
+}
@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, sync::Arc, sync::Mutex};
 
 use ntex::codec::BytesCodec;
 use ntex::http::test::server as test_server;
@@ -7,7 +7,7 @@ use ntex::io::{DispatchItem, Dispatcher, Io};
 use ntex::service::{fn_factory_with_config, fn_service};
 use ntex::web::{self, App, HttpRequest};
 use ntex::ws::{self, handshake_response};
-use ntex::{time::Seconds, util::ByteString, util::Bytes, util::Ready};
+use ntex::{time::Millis, time::Seconds, util::ByteString, util::Bytes, util::Ready};
 
 async fn ws_service(
     msg: DispatchItem<ws::Codec>,
@@ -77,6 +77,85 @@ async fn test_simple() {
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Normal.into())));
 }
 
+#[cfg(feature = "compress")]
+#[ntex::test]
+async fn test_deflate() {
+    use ntex::http::header;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, io, codec): (Request, Io, h1::Codec)| {
+                async move {
+                    let (mut res, cfg) = ws::handshake_deflate(
+                        req.head(),
+                        &ws::DeflateConfig::new().server_no_context_takeover(),
+                    )
+                    .unwrap();
+                    let res = res.finish();
+
+                    // send handshake respone
+                    io.encode(h1::Message::Item((res.drop_body(), BodySize::None)), &codec)
+                        .unwrap();
+
+                    // start websocket service
+                    let codec = ws::Codec::default()
+                        .aggregate_continuation()
+                        .deflate(cfg.unwrap());
+                    Dispatcher::new(io.seal(), codec, ws_service).await
+                }
+            })
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::NotFound()))
+    });
+
+    let conn = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .deflate(ws::DeflateConfig::new())
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(
+        conn.response()
+            .headers()
+            .get(header::SEC_WEBSOCKET_EXTENSIONS)
+            .unwrap(),
+        "permessage-deflate; server_no_context_takeover"
+    );
+
+    let (io, codec, _) = conn.into_inner();
+    let text = "text ".repeat(1000);
+    for _ in 0..2 {
+        io.send(ws::Message::Text(ByteString::from(text.clone())), &codec)
+            .await
+            .unwrap();
+        let item = io.recv(&codec).await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Text(Bytes::from(text.clone())));
+    }
+
+    io.send(ws::Message::Binary(Bytes::from_static(b"binary")), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(Bytes::from_static(b"binary")));
+
+    // uncompressed continuation
+    io.send(
+        ws::Message::Continuation(ws::Item::FirstText(Bytes::from_static(b"con"))),
+        &codec,
+    )
+    .await
+    .unwrap();
+    io.send(
+        ws::Message::Continuation(ws::Item::Last(Bytes::from_static(b"tinuation"))),
+        &codec,
+    )
+    .await
+    .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"continuation")));
+}
+
 #[ntex::test]
 async fn test_transport() {
     let mut srv = test_server(|| {
@@ -151,6 +230,64 @@ async fn test_keepalive_timeout() {
     assert!(item.is_none());
 }
 
+#[ntex::test]
+async fn test_max_frame_size() {
+    let closed = Arc::new(Mutex::new(None));
+    let closed2 = closed.clone();
+
+    let srv = test_server(move || {
+        let closed = closed2.clone();
+        HttpService::build()
+            .upgrade(move |(req, io, codec): (Request, Io, h1::Codec)| {
+                let closed = closed.clone();
+                async move {
+                    let res = handshake_response(req.head()).finish();
+
+                    // send handshake respone
+                    io.encode(h1::Message::Item((res.drop_body(), BodySize::None)), &codec)
+                        .unwrap();
+
+                    // start websocket service
+                    let srv = fn_service(move |msg: DispatchItem<ws::Codec>| {
+                        if let DispatchItem::Item(ws::Frame::Close(ref reason)) = msg {
+                            *closed.lock().unwrap() = reason.clone();
+                        }
+                        ws_service(msg)
+                    });
+                    Dispatcher::new(io.seal(), ws::Codec::default(), srv).await
+                }
+            })
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::NotFound()))
+    });
+
+    let con = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .max_frame_size(10)
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+        .seal();
+    let tx = con.sink();
+    let rx = con.receiver();
+
+    tx.send(ws::Message::Binary(Bytes::from_static(b"text")))
+        .await
+        .unwrap();
+    let item = rx.recv().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(Bytes::from_static(b"text")));
+
+    // response is larger than max frame size
+    tx.send(ws::Message::Binary(Bytes::from_static(b"large response")))
+        .await
+        .unwrap();
+    assert!(rx.recv().await.is_none());
+
+    ntex::time::sleep(Millis(100)).await;
+    assert_eq!(*closed.lock().unwrap(), Some(ws::CloseCode::Size.into()));
+}
+
 #[ntex::test]
 async fn test_upgrade_handler_with_await() {
     async fn service(_: ws::Frame) -> Result<Option<ws::Message>, io::Error> {
@@ -184,3 +321,136 @@ async fn test_upgrade_handler_with_await() {
         .await
         .unwrap();
 }
+
+#[ntex::test]
+async fn test_handshake() {
+    use ntex::http::header;
+    use ntex::ws::error::WsClientError;
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, io, codec): (Request, Io, h1::Codec)| async move {
+                let mut res = handshake_response(req.head());
+                res.header(header::SEC_WEBSOCKET_PROTOCOL, "v2");
+                for name in [header::ORIGIN, header::AUTHORIZATION] {
+                    if let Some(val) = req.headers().get(&name) {
+                        res.header(format!("x-{}", name), val.clone());
+                    }
+                }
+                if req.path() == "/invalid-accept" {
+                    res.set_header(header::SEC_WEBSOCKET_ACCEPT, "invalid");
+                }
+                io.encode(
+                    h1::Message::Item((res.finish().drop_body(), BodySize::None)),
+                    &codec,
+                )
+                .unwrap();
+                Dispatcher::new(io.seal(), ws::Codec::default(), ws_service).await
+            })
+            .finish(|_| Ready::Ok::<_, io::Error>(Response::NotFound()))
+    });
+
+    let con = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .protocols(["v1", "v2"])
+        .origin("http://example.com")
+        .header(header::AUTHORIZATION, "Bearer token")
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(con.protocol(), Some("v2"));
+    let headers = con.response().headers();
+    assert_eq!(headers.get("x-origin").unwrap(), "http://example.com");
+    assert_eq!(headers.get("x-authorization").unwrap(), "Bearer token");
+
+    // server selected protocol that was not offered
+    let res = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .protocols(["v1"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await;
+    assert!(matches!(res, Err(WsClientError::InvalidProtocolHeader(_))));
+
+    let res = ws::WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .finish()
+        .unwrap()
+        .connect()
+        .await;
+    assert!(matches!(res, Err(WsClientError::InvalidProtocolHeader(_))));
+
+    let res = ws::WsClient::build(srv.url("/invalid-accept"))
+        .address(srv.addr())
+        .protocols(["v2"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await;
+    assert!(matches!(
+        res,
+        Err(WsClientError::InvalidChallengeResponse(_, _))
+    ));
+}
+
+#[cfg(feature = "cookie")]
+#[ntex::test]
+async fn test_cookie_store() {
+    use ntex::http::{client::CookieStore, header, StatusCode};
+
+    let srv = test_server(|| {
+        HttpService::build()
+            .upgrade(|(req, io, codec): (Request, Io, h1::Codec)| async move {
+                let cookie = req.headers().get(header::COOKIE);
+                let res = if cookie.map(|v| v == "session=abc").unwrap_or(false) {
+                    handshake_response(req.head())
+                        .header(header::SET_COOKIE, "ws=1")
+                        .finish()
+                } else {
+                    Response::Forbidden().finish()
+                };
+                io.encode(h1::Message::Item((res.drop_body(), BodySize::None)), &codec)
+                    .unwrap();
+                Dispatcher::new(io.seal(), ws::Codec::default(), ws_service).await
+            })
+            .finish(|_| {
+                Ready::Ok::<_, io::Error>(
+                    Response::Ok()
+                        .header(header::SET_COOKIE, "session=abc")
+                        .finish(),
+                )
+            })
+    });
+
+    let connect = |store: CookieStore| {
+        ws::WsClient::build(srv.url("/"))
+            .address(srv.addr())
+            .cookie_store(store)
+            .finish()
+            .unwrap()
+    };
+
+    let store = CookieStore::new();
+    let res = connect(store.clone()).connect().await;
+    assert!(matches!(
+        res,
+        Err(ws::error::WsClientError::InvalidResponseStatus(
+            StatusCode::FORBIDDEN
+        ))
+    ));
+
+    // cookies are received with plain http request
+    let client = ntex::http::client::Client::build()
+        .cookie_store(true)
+        .finish();
+    let res = client.get(srv.url("/login")).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    let store = client.cookie_store().unwrap().clone();
+    let _con = connect(store.clone()).connect().await.unwrap();
+    assert_eq!(store.len(), 2);
+    assert_eq!(client.cookies_for(srv.url("/")).len(), 2);
+}
@@ -1,9 +1,9 @@
 use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
-use std::{io, io::Read, net, sync::mpsc, sync::Arc, thread, time};
+use std::{io, io::Read, io::Write, net, sync::mpsc, sync::Arc, thread, time};
 
 use ntex::codec::BytesCodec;
 use ntex::io::Io;
-use ntex::server::{Server, TestServer};
+use ntex::server::{Server, ServerMetrics, TestServer};
 use ntex::service::fn_service;
 use ntex::util::{Bytes, Ready};
 
@@ -299,3 +299,838 @@ fn test_panic_in_worker() {
     sys.stop();
     let _ = h.join();
 }
+
+#[ntex::test]
+async fn test_bind_workers() {
+    let addr1 = TestServer::unused_addr();
+    let addr2 = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let main = Arc::new(AtomicUsize::new(0));
+    let admin = Arc::new(AtomicUsize::new(0));
+    let main2 = main.clone();
+    let admin2 = admin.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("main", addr1, move |_| {
+                    let _ = main2.fetch_add(1, Relaxed);
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"main"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind("admin", addr2, move |_| {
+                    let _ = admin2.fetch_add(1, Relaxed);
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"admn"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .bind_workers(1)
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    // admin service runs on first worker only
+    assert_eq!(main.load(Relaxed), 2);
+    assert_eq!(admin.load(Relaxed), 1);
+    assert_eq!(srv.workers().await, 2);
+
+    for _ in 0..4 {
+        let mut buf = [0u8; 4];
+        let mut conn = net::TcpStream::connect(addr2).unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"admn"[..]);
+
+        let mut conn = net::TcpStream::connect(addr1).unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"main"[..]);
+    }
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+async fn test_resize_workers() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move |_| {
+                    let _ = num2.fetch_add(1, Relaxed);
+                    fn_service(|io: Io| async move {
+                        while let Ok(Some(item)) = io.recv(&BytesCodec).await {
+                            io.send(item.freeze(), &BytesCodec).await.unwrap();
+                        }
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(srv.workers().await, 1);
+
+    srv.resize_workers(3).await;
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(srv.workers().await, 3);
+    assert_eq!(srv.connections().await, vec![0, 0, 0]);
+    assert_eq!(num.load(Relaxed), 3);
+
+    let mut conns: Vec<_> = (0..3)
+        .map(|_| {
+            let mut conn = net::TcpStream::connect(addr).unwrap();
+            let mut buf = [0u8; 4];
+            conn.write_all(b"ping").unwrap();
+            conn.read_exact(&mut buf).unwrap();
+            conn
+        })
+        .collect();
+    assert_eq!(srv.connections().await.iter().sum::<usize>(), 3);
+
+    // drained workers keep open connections
+    srv.resize_workers(1).await;
+    thread::sleep(time::Duration::from_millis(100));
+    assert_eq!(srv.workers().await, 1);
+    assert_eq!(srv.connections().await.len(), 1);
+    for conn in &mut conns {
+        let mut buf = [0u8; 4];
+        conn.write_all(b"pong").unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"pong"[..]);
+    }
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"test").unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+    assert_eq!(num.load(Relaxed), 3);
+
+    drop(conns);
+    drop(conn);
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+async fn test_metrics() {
+    #[derive(Default)]
+    struct Hooks {
+        accepted: AtomicUsize,
+        closed: AtomicUsize,
+        failed: AtomicUsize,
+    }
+
+    impl ServerMetrics for Hooks {
+        fn accepted(&self, id: usize, name: &str) {
+            assert_eq!((id, name), (0, "test"));
+            let _ = self.accepted.fetch_add(1, Relaxed);
+        }
+
+        fn closed(&self, _: usize, _: &str, _: time::Duration, failed: bool) {
+            let _ = self.closed.fetch_add(1, Relaxed);
+            if failed {
+                let _ = self.failed.fetch_add(1, Relaxed);
+            }
+        }
+    }
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let hooks = Arc::new(Hooks::default());
+    let hooks2 = hooks.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .disable_signals()
+                .metrics(hooks2)
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        match io.recv(&BytesCodec).await {
+                            Ok(Some(item)) if &item[..] == b"fail" => Err(()),
+                            _ => Ok(()),
+                        }
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    let m = srv.metrics().await;
+    assert_eq!(m.listeners.len(), 1);
+    assert_eq!(m.listeners[0].name, "test");
+    assert_eq!((m.listeners[0].accepted, m.listeners[0].active), (1, 1));
+
+    conn.write_all(b"ok").unwrap();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"fail").unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    let m = srv.metrics().await;
+    let l = &m.listeners[0];
+    assert_eq!((l.accepted, l.active, l.failed), (2, 0, 1));
+    assert!(!m.paused);
+    assert_eq!(hooks.accepted.load(Relaxed), 2);
+    assert_eq!(hooks.closed.load(Relaxed), 2);
+    assert_eq!(hooks.failed.load(Relaxed), 1);
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+async fn test_pause_resume() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .max_connections(1)
+                .disable_signals()
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"test"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        let _ = io.recv(&BytesCodec).await;
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    // worker is at capacity, connection waits in listen backlog
+    let mut conn2 = net::TcpStream::connect(addr).unwrap();
+    conn2
+        .set_read_timeout(Some(time::Duration::from_millis(200)))
+        .unwrap();
+    assert!(conn2.read_exact(&mut buf).is_err());
+    assert_eq!(srv.metrics().await.listeners[0].accepted, 1);
+
+    // worker becomes available, but server is paused
+    srv.pause().await;
+    drop(conn);
+    thread::sleep(time::Duration::from_millis(200));
+    assert!(conn2.read_exact(&mut buf).is_err());
+    assert_eq!(srv.metrics().await.listeners[0].accepted, 1);
+
+    // pending connection is accepted after resume
+    srv.resume().await;
+    conn2
+        .set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn2.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+    assert_eq!(srv.metrics().await.listeners[0].accepted, 2);
+    drop(conn2);
+
+    // pause does not close listening socket
+    srv.pause().await;
+    srv.resume().await;
+    thread::sleep(time::Duration::from_millis(100));
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+    drop(conn);
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+#[cfg(feature = "openssl")]
+async fn test_max_connection_rate() {
+    use ntex::service::chain_factory;
+    use ntex::tls::openssl::Acceptor;
+    use tls_openssl::ssl::{self, HandshakeError, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut builder = ssl::SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    let acceptor = builder.build();
+
+    let addr1 = TestServer::unused_addr();
+    let addr2 = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .max_connection_rate(1)
+                .disable_signals()
+                .bind("tls", addr1, move |_| {
+                    chain_factory(Acceptor::new(acceptor.clone()))
+                        .map_err(|_| ())
+                        .and_then(fn_service(|io: Io<_>| async move {
+                            io.send(Bytes::from_static(b"tls"), &BytesCodec)
+                                .await
+                                .unwrap();
+                            Ok::<_, ()>(())
+                        }))
+                })
+                .unwrap()
+                .bind("plain", addr2, move |_| {
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"plain"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // stalled handshake holds the only handshake slot, next connection
+    // could be queued to worker before service readiness gets updated
+    let stalled = net::TcpStream::connect(addr1).unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+    let queued = net::TcpStream::connect(addr1).unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    let mut connector = ssl::SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    let connector = connector.build();
+    let conn = net::TcpStream::connect(addr1).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(300)))
+        .unwrap();
+    let mut mid = match connector.connect("localhost", conn) {
+        Err(HandshakeError::WouldBlock(mid)) => mid,
+        _ => panic!("handshake must be delayed"),
+    };
+
+    // plain listener is not affected
+    let mut buf = [0u8; 5];
+    let mut plain = net::TcpStream::connect(addr2).unwrap();
+    plain
+        .set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    plain.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"plain"[..]);
+
+    // delayed connection waits in listen backlog
+    let m = srv.metrics().await;
+    assert_eq!(m.listeners[0].accepted, 2);
+    assert_eq!(m.listeners[1].accepted, 1);
+    assert!(!m.paused);
+
+    // handshake slot is released
+    drop(stalled);
+    drop(queued);
+    let mut stream = loop {
+        match mid.handshake() {
+            Ok(stream) => break stream,
+            Err(HandshakeError::WouldBlock(m)) => mid = m,
+            Err(e) => panic!("handshake failed: {:?}", e),
+        }
+    };
+    let mut buf = [0u8; 3];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"tls"[..]);
+    assert_eq!(srv.metrics().await.listeners[0].accepted, 3);
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+#[ntex::test]
+#[cfg(feature = "openssl")]
+async fn test_tls_handshake_timeout() {
+    use std::sync::Mutex;
+
+    use ntex::service::chain_factory;
+    use ntex::time::{sleep, Millis};
+    use ntex::tls::{openssl::Acceptor, HandshakeError};
+    use tls_openssl::ssl::{self, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut builder = ssl::SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    let acceptor = builder.build();
+
+    #[derive(Default)]
+    struct Hooks(Mutex<Vec<String>>);
+
+    impl ServerMetrics for Hooks {
+        fn handshake_error(&self, id: usize, name: &str, err: &HandshakeError) {
+            let err = format!("{} {} {}", id, name, err);
+            self.0.lock().unwrap().push(err);
+        }
+    }
+
+    let hooks = Arc::new(Hooks::default());
+    let hooks2 = hooks.clone();
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors2 = errors.clone();
+    let addr1 = TestServer::unused_addr();
+    let addr2 = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .max_connection_rate(1)
+                .disable_signals()
+                .metrics(hooks2)
+                .bind("tls", addr1, move |cfg| {
+                    let errors = errors2.clone();
+                    let metrics = cfg.handshake_error_hook();
+                    let acceptor = Acceptor::new(acceptor.clone())
+                        .timeout(Millis(500))
+                        .on_error(move |err| {
+                            metrics(err);
+                            let err = match err {
+                                HandshakeError::Timeout => "timeout",
+                                HandshakeError::Io(_) => "io",
+                                _ => "tls",
+                            };
+                            errors.lock().unwrap().push(err);
+                        });
+                    chain_factory(acceptor).map_err(|_| ()).and_then(fn_service(
+                        |io: Io<_>| async move {
+                            io.send(Bytes::from_static(b"tls"), &BytesCodec)
+                                .await
+                                .unwrap();
+                            sleep(Millis(500)).await;
+                            io.send(Bytes::from_static(b"late"), &BytesCodec)
+                                .await
+                                .unwrap();
+                            Ok::<_, ()>(())
+                        },
+                    ))
+                })
+                .unwrap()
+                .bind("plain", addr2, move |_| {
+                    fn_service(|io: Io| async move {
+                        sleep(Millis(700)).await;
+                        io.send(Bytes::from_static(b"plain"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut connector = ssl::SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    let connector = connector.build();
+    let read = |stream: &mut dyn Read, size| {
+        let mut buf = vec![0u8; size];
+        stream.read_exact(&mut buf).map(|_| buf)
+    };
+
+    // handshake completes just before deadline, connection outlives deadline
+    let conn = net::TcpStream::connect(addr1).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(400));
+    let mut stream = connector.connect("localhost", conn).unwrap();
+    assert_eq!(read(&mut stream, 3).unwrap(), b"tls");
+    assert_eq!(read(&mut stream, 4).unwrap(), b"late");
+    drop(stream);
+
+    // slow client sends one byte of tls record per 100 millis
+    let slow = thread::spawn(move || {
+        let mut conn = net::TcpStream::connect(addr1).unwrap();
+        let record = [0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xfc, 0x03];
+        let start = time::Instant::now();
+        for b in record.iter().chain([0u8; 10].iter()) {
+            thread::sleep(time::Duration::from_millis(100));
+            if conn.write_all(&[*b]).is_err() {
+                break;
+            }
+        }
+        let mut buf = [0u8; 1];
+        assert!(!matches!(conn.read(&mut buf), Ok(n) if n > 0));
+        start.elapsed()
+    });
+    thread::sleep(time::Duration::from_millis(100));
+
+    // stalled handshake holds the only handshake slot until timeout
+    let start = time::Instant::now();
+    let conn = net::TcpStream::connect(addr1).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    let mut stream = connector.connect("localhost", conn).unwrap();
+    assert_eq!(read(&mut stream, 3).unwrap(), b"tls");
+    assert!(start.elapsed() >= time::Duration::from_millis(300));
+    assert!(slow.join().unwrap() < time::Duration::from_millis(1500));
+    assert_eq!(read(&mut stream, 4).unwrap(), b"late");
+    drop(stream);
+
+    // plain listener is not affected
+    let mut plain = net::TcpStream::connect(addr2).unwrap();
+    plain
+        .set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    assert_eq!(read(&mut plain, 5).unwrap(), b"plain");
+
+    assert_eq!(*errors.lock().unwrap(), vec!["timeout"]);
+    assert_eq!(
+        *hooks.0.lock().unwrap(),
+        vec!["0 tls tls handshake timeout"]
+    );
+    let m = srv.metrics().await;
+    let (tls, plain) = (&m.listeners[0], &m.listeners[1]);
+    assert_eq!((tls.accepted, tls.failed, tls.handshake_errors), (3, 1, 1));
+    assert_eq!((plain.failed, plain.handshake_errors), (0, 0));
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
+
+/// Connect with untrusted client certificate, then send plain text
+#[cfg(feature = "openssl")]
+fn tls_handshake_errors(addr: net::SocketAddr) {
+    use tls_openssl::ssl::{self, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut connector = ssl::SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    connector
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    let connector = connector.build();
+
+    let conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    if let Ok(mut stream) = connector.connect("localhost", conn) {
+        let mut buf = [0u8; 1];
+        assert!(!matches!(stream.read(&mut buf), Ok(n) if n > 0));
+    }
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    conn.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut buf = [0u8; 1];
+    assert!(!matches!(conn.read(&mut buf), Ok(n) if n > 0));
+    thread::sleep(time::Duration::from_millis(100));
+}
+
+#[cfg(feature = "openssl")]
+fn handshake_error_kind(err: &ntex::tls::HandshakeError) -> &'static str {
+    use ntex::tls::HandshakeError;
+
+    match err {
+        HandshakeError::Timeout => "timeout",
+        HandshakeError::CertRejected(_) => "cert",
+        HandshakeError::Protocol(_) => "protocol",
+        HandshakeError::Io(_) => "io",
+        _ => "other",
+    }
+}
+
+#[ntex::test]
+#[cfg(feature = "openssl")]
+async fn test_openssl_handshake_errors() {
+    use std::sync::Mutex;
+
+    use ntex::service::chain_factory;
+    use ntex::{server::test_server, tls::openssl::Acceptor};
+    use tls_openssl::ssl::{self, SslFiletype, SslMethod, SslVerifyMode};
+
+    let mut builder = ssl::SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    let acceptor = builder.build();
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors2 = errors.clone();
+    let srv = test_server(move || {
+        let errors = errors2.clone();
+        let acceptor = Acceptor::new(acceptor.clone()).on_error(move |err| {
+            errors.lock().unwrap().push(handshake_error_kind(err));
+        });
+        chain_factory(acceptor)
+            .map_err(|_| ())
+            .and_then(fn_service(|_: Io<_>| Ready::Ok::<_, ()>(())))
+    });
+
+    tls_handshake_errors(srv.addr());
+    assert_eq!(*errors.lock().unwrap(), vec!["cert", "protocol"]);
+}
+
+#[ntex::test]
+#[cfg(all(feature = "openssl", feature = "rustls"))]
+async fn test_rustls_handshake_errors() {
+    use std::{fs::File, io::BufReader, sync::Mutex};
+
+    use ntex::service::chain_factory;
+    use ntex::{server::test_server, tls::rustls::Acceptor};
+    use rustls_pemfile::{certs, pkcs8_private_keys};
+    use tls_rustls::server::AllowAnyAuthenticatedClient;
+    use tls_rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+
+    let cert_file = &mut BufReader::new(File::open("tests/cert.pem").unwrap());
+    let key_file = &mut BufReader::new(File::open("tests/key.pem").unwrap());
+    let cert_chain = certs(cert_file)
+        .unwrap()
+        .iter()
+        .map(|c| Certificate(c.to_vec()))
+        .collect();
+    let key = PrivateKey(pkcs8_private_keys(key_file).unwrap().remove(0));
+    let verifier = AllowAnyAuthenticatedClient::new(RootCertStore::empty()).boxed();
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(cert_chain, key)
+        .unwrap();
+    let config = Arc::new(config);
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors2 = errors.clone();
+    let srv = test_server(move || {
+        let errors = errors2.clone();
+        let acceptor = Acceptor::new(config.clone()).on_error(move |err| {
+            errors.lock().unwrap().push(handshake_error_kind(err));
+        });
+        chain_factory(acceptor)
+            .map_err(|_| ())
+            .and_then(fn_service(|_: Io<_>| Ready::Ok::<_, ()>(())))
+    });
+
+    tls_handshake_errors(srv.addr());
+    assert_eq!(*errors.lock().unwrap(), vec!["cert", "protocol"]);
+}
+
+#[ntex::test]
+#[cfg(all(unix, feature = "systemd"))]
+async fn test_systemd_notify() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path =
+        std::env::temp_dir().join(format!("ntex-notify-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sock = UnixDatagram::bind(&path).unwrap();
+    let recv = |timeout| {
+        let mut buf = [0u8; 64];
+        sock.set_read_timeout(Some(time::Duration::from_millis(timeout)))
+            .unwrap();
+        sock.recv(&mut buf)
+            .map(|n| String::from_utf8_lossy(&buf[..n]).to_string())
+    };
+
+    // watchdog is notified every 100 millis
+    std::env::set_var("NOTIFY_SOCKET", &path);
+    std::env::set_var("WATCHDOG_USEC", "200000");
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .disable_signals()
+                .systemd_notify(true)
+                .bind("test", addr, move |_| {
+                    fn_service(|_: Io| async move {
+                        // block worker's event loop
+                        thread::sleep(time::Duration::from_millis(800));
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    std::env::remove_var("NOTIFY_SOCKET");
+    std::env::remove_var("WATCHDOG_USEC");
+
+    assert_eq!(recv(1000).unwrap(), "READY=1");
+    assert_eq!(recv(1000).unwrap(), "WATCHDOG=1");
+
+    // blocked worker stops watchdog notifications
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(250));
+    while recv(1).is_ok() {}
+    assert!(recv(250).is_err());
+
+    // worker is responsive again
+    assert_eq!(recv(1000).unwrap(), "WATCHDOG=1");
+
+    srv.stop(true).await;
+    loop {
+        let msg = recv(1000).unwrap();
+        if msg != "WATCHDOG=1" {
+            assert_eq!(msg, "STOPPING=1");
+            break;
+        }
+    }
+
+    sys.stop();
+    let _ = h.join();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[ntex::test]
+async fn test_multiplex() {
+    use ntex::http::{HttpService, Request, Response};
+    use ntex::server::Multiplex;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.run(move || {
+            let srv = Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_multiplexed("test", addr, move |_| {
+                    Multiplex::new()
+                        .peek_size(7)
+                        .timeout(time::Duration::from_millis(200))
+                        .service(
+                            |buf, _| buf == b"METRICS",
+                            fn_service(|io: Io| async move {
+                                // peeked bytes are replayed
+                                if let Ok(Some(item)) = io.recv(&BytesCodec).await {
+                                    io.send(item.freeze(), &BytesCodec).await.unwrap();
+                                }
+                                Ok::<_, ()>(())
+                            }),
+                        )
+                        .service(
+                            |buf, _| buf.starts_with(b"GET "),
+                            HttpService::build().h1(|_: Request| async {
+                                Ok::<_, io::Error>(Response::Ok().body("http"))
+                            }),
+                        )
+                })
+                .unwrap()
+                .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"METRICS cpu=1").unwrap();
+    let mut buf = [0u8; 13];
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"METRICS cpu=1"[..]);
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert!(res.starts_with("HTTP/1.1 200 OK"));
+    assert!(res.ends_with("http"));
+
+    // no service matches
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(100)))
+        .unwrap();
+    conn.write_all(b"UNKNOWN PROTOCOL").unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    // timeout
+    let start = time::Instant::now();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn.write_all(b"GET").unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+    assert!(start.elapsed() >= time::Duration::from_millis(200));
+
+    srv.stop(true).await;
+    sys.stop();
+    let _ = h.join();
+}
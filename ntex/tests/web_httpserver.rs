@@ -3,7 +3,7 @@ use std::{sync::mpsc, thread, time::Duration};
 #[cfg(feature = "openssl")]
 use tls_openssl::ssl::SslAcceptorBuilder;
 
-use ntex::web::{self, App, HttpResponse, HttpServer};
+use ntex::web::{self, App, HttpRequest, HttpResponse, HttpServer};
 use ntex::{rt, server::TestServer, time::sleep, time::Seconds};
 
 #[cfg(unix)]
@@ -213,11 +213,21 @@ async fn test_bind_uds() {
         let sys = ntex::rt::System::new("test");
 
         sys.run(move || {
+            // stale socket file is removed on bind
+            let _ = std::fs::write("/tmp/uds-test", b"");
+
             let srv = HttpServer::new(|| {
-                App::new().service(
-                    web::resource("/")
-                        .route(web::to(|| async { HttpResponse::Ok().body("test") })),
-                )
+                App::new().service(web::resource("/").route(web::to(
+                    |req: HttpRequest| async move {
+                        let info = req.connection_info();
+                        HttpResponse::Ok().body(format!(
+                            "{:?} {:?} {}",
+                            req.peer_addr(),
+                            info.remote(),
+                            info.host()
+                        ))
+                    },
+                )))
             })
             .workers(1)
             .shutdown_timeout(Seconds(1))
@@ -243,9 +253,13 @@ async fn test_bind_uds() {
                 .finish(),
         )
         .finish();
-    let response = client.get("http://localhost").send().await.unwrap();
+    let mut response = client.get("http://localhost").send().await.unwrap();
     assert!(response.status().is_success());
 
+    // peer address is not available for unix domain sockets
+    let body = response.body().await.unwrap();
+    assert_eq!(body, "None None localhost");
+
     // stop
     let _ = srv.stop(false);
 
@@ -304,3 +318,236 @@ async fn test_listen_uds() {
     sleep(Duration::from_millis(100)).await;
     sys.stop();
 }
+
+#[ntex::test]
+#[cfg(all(unix, feature = "openssl"))]
+async fn test_listen_multiple() {
+    use std::os::unix::io::IntoRawFd;
+
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let builder = ssl_acceptor().unwrap();
+
+        sys.run(move || {
+            let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let ssl_lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            // pre-bound socket, i.e. passed by parent process
+            let ssl_lst = unsafe { ntex::server::from_fd(ssl_lst.into_raw_fd()) }.unwrap();
+            let addrs = vec![lst.local_addr().unwrap(), ssl_lst.local_addr().unwrap()];
+
+            let srv = HttpServer::new(|| {
+                App::new().service(web::resource("/").route(web::to(
+                    |req: HttpRequest| async move {
+                        if req.app_config().secure() {
+                            HttpResponse::Ok().body("secure")
+                        } else {
+                            HttpResponse::Ok().body("plain")
+                        }
+                    },
+                )))
+            })
+            .workers(1)
+            .shutdown_timeout(Seconds(1))
+            .stop_runtime()
+            .disable_signals()
+            .listen(lst)
+            .unwrap()
+            .listen_openssl(ssl_lst, builder)
+            .unwrap();
+            assert_eq!(srv.addrs(), addrs);
+
+            let srv = srv.run();
+            let _ = tx.send((srv, addrs, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, addrs, sys) = rx.recv().unwrap();
+
+    let client = client();
+    let mut response = client
+        .get(format!("http://{}", addrs[0]))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.body().await.unwrap(), "plain");
+
+    let mut response = client
+        .get(format!("https://{}", addrs[1]))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.body().await.unwrap(), "secure");
+
+    // stop
+    let _ = srv.stop(false);
+
+    sleep(Duration::from_millis(100)).await;
+    sys.stop();
+}
+
+#[ntex::test]
+async fn test_graceful_shutdown() {
+    use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+    use std::time::Instant;
+
+    use ntex::http::{client, header};
+    use ntex::server::ShutdownSignal;
+    use ntex::time::Millis;
+
+    let addr = TestServer::unused_addr();
+    let hook_called = Arc::new(AtomicBool::new(false));
+    let hook_called2 = hook_called.clone();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+
+        sys.run(move || {
+            let srv = HttpServer::new(|| {
+                App::new()
+                    .route(
+                        "/slow",
+                        web::get().to(|| async {
+                            sleep(Millis(500)).await;
+                            HttpResponse::Ok().body("slow")
+                        }),
+                    )
+                    .route(
+                        "/hang",
+                        web::get().to(|| async {
+                            sleep(Seconds(60)).await;
+                            HttpResponse::Ok().body("hang")
+                        }),
+                    )
+                    .route(
+                        "/signal",
+                        web::get().to(|signal: ShutdownSignal| async move {
+                            signal.await;
+                            HttpResponse::Ok().body("stopped")
+                        }),
+                    )
+            })
+            .workers(1)
+            .shutdown_timeout(Seconds(2))
+            .on_shutdown(move || async move {
+                hook_called2.store(true, Ordering::Relaxed);
+            })
+            .disable_signals()
+            .bind(format!("{}", addr))
+            .unwrap()
+            .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let client = client::Client::build()
+        .timeout(Seconds(30))
+        .connector(client::Connector::default().timeout(Seconds(30)).finish())
+        .finish();
+    let url = |path| format!("http://{}{}", addr, path);
+
+    // keep-alive connection
+    let response = client.get(url("/signal")).send();
+    let slow = ntex::rt::spawn(client.get(url("/slow")).send());
+    let hang = ntex::rt::spawn(client.get(url("/hang")).send());
+    let signal = ntex::rt::spawn(response);
+    sleep(Millis(200)).await;
+
+    let start = Instant::now();
+    let stop = ntex::rt::spawn(srv.stop(true));
+
+    // handler finished within shutdown timeout
+    let mut response = slow.await.unwrap().unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(header::CONNECTION).unwrap(), "close");
+    assert_eq!(response.body().await.unwrap(), "slow");
+    assert!(hook_called.load(Ordering::Relaxed));
+
+    // handler is notified about shutdown
+    let mut response = signal.await.unwrap().unwrap();
+    assert_eq!(response.body().await.unwrap(), "stopped");
+
+    // handler exceeding shutdown timeout is dropped
+    assert!(hang.await.unwrap().is_err());
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(2), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(10), "{:?}", elapsed);
+
+    stop.await.unwrap();
+    sys.stop();
+}
+
+#[ntex::test]
+async fn test_max_connections() {
+    use std::io::{Read, Write};
+    use std::time::Instant;
+
+    use ntex::time::Millis;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+
+        sys.run(move || {
+            let srv = HttpServer::new(|| {
+                App::new().route(
+                    "/",
+                    web::get().to(|| async {
+                        sleep(Millis(1000)).await;
+                        HttpResponse::Ok().body("test")
+                    }),
+                )
+            })
+            .workers(1)
+            .max_connections(2)
+            .max_connection_rate(2)
+            .disable_signals()
+            .bind(format!("{}", addr))
+            .unwrap()
+            .run();
+            let _ = tx.send((srv, ntex::rt::System::current()));
+            Ok(())
+        })
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let start = Instant::now();
+    let clients: Vec<_> = (0..3)
+        .map(|_| {
+            thread::spawn(move || {
+                let mut conn = std::net::TcpStream::connect(addr).unwrap();
+                conn.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+                    .unwrap();
+                let mut data = String::new();
+                conn.read_to_string(&mut data).unwrap();
+                (data, start.elapsed())
+            })
+        })
+        .collect();
+
+    sleep(Millis(300)).await;
+    assert_eq!(srv.connections().await, vec![2]);
+
+    let mut results: Vec<_> = clients.into_iter().map(|h| h.join().unwrap()).collect();
+    results.sort_by_key(|(_, elapsed)| *elapsed);
+
+    // (n+1)th connection waits for capacity instead of failing
+    for (data, _) in &results {
+        assert!(data.starts_with("HTTP/1.1 200 OK"), "{}", data);
+        assert!(data.ends_with("test"));
+    }
+    assert!(results[1].1 < Duration::from_millis(1900));
+    assert!(results[2].1 >= Duration::from_millis(1900));
+    assert_eq!(srv.connections().await, vec![0]);
+
+    srv.stop(false).await;
+    sys.stop();
+}
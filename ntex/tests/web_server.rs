@@ -9,7 +9,7 @@ use thiserror::Error;
 
 use ntex::http::header::{
     ContentEncoding, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
-    TRANSFER_ENCODING,
+    TRANSFER_ENCODING, VARY,
 };
 use ntex::http::{body::Body, client, ConnectionType, Method, StatusCode};
 use ntex::time::{sleep, Millis, Seconds, Sleep};
@@ -448,6 +448,115 @@ async fn test_body_brotli() {
     assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_body_brotli_level() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new().wrap(Compress::default().level(11)).service(
+            web::resource("/")
+                .route(web::to(move || async { HttpResponse::Ok().body(STR) })),
+        )
+    });
+
+    // client request
+    let mut response = srv
+        .get("/")
+        .header(ACCEPT_ENCODING, "gzip;q=0.5, br")
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "br");
+    assert_eq!(response.headers().get(VARY).unwrap(), "accept-encoding");
+
+    // read response
+    let bytes = response.body().await.unwrap();
+
+    // decode brotli
+    let mut e = BrotliDecoder::new(Vec::with_capacity(2048));
+    e.write_all(bytes.as_ref()).unwrap();
+    let dec = e.finish().unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+}
+
+#[cfg(feature = "zstd")]
+#[ntex::test]
+async fn test_body_zstd() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new().wrap(Compress::default()).service(
+            web::resource("/")
+                .route(web::to(move || async { HttpResponse::Ok().body(STR) })),
+        )
+    });
+
+    // client request
+    let mut response = srv
+        .get("/")
+        .header(ACCEPT_ENCODING, "gzip;q=0.5, zstd")
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+
+    // decode zstd
+    let bytes = response.body().await.unwrap();
+    let dec = zstd::stream::decode_all(bytes.as_ref()).unwrap();
+    assert_eq!(Bytes::from(dec), Bytes::from_static(STR.as_ref()));
+
+    // client decompress
+    let mut response = srv
+        .get("/")
+        .header(ACCEPT_ENCODING, "zstd")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn test_body_vary() {
+    let srv = test::server_with(test::config().h1(), || {
+        App::new()
+            .wrap(Compress::default())
+            .service(web::resource("/").route(web::to(move || async {
+                HttpResponse::Ok().header(VARY, "origin").body(STR)
+            })))
+            .service(web::resource("/vary").route(web::to(move || async {
+                HttpResponse::Ok()
+                    .header(VARY, "Origin, Accept-Encoding")
+                    .body(STR)
+            })))
+    });
+
+    // vary header is appended
+    let response = srv
+        .get("/")
+        .header(ACCEPT_ENCODING, "gzip")
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let vary: Vec<_> = response.headers().get_all(VARY).collect();
+    assert_eq!(vary, vec!["origin", "accept-encoding"]);
+
+    // vary header already contains accept-encoding
+    let response = srv
+        .get("/vary")
+        .header(ACCEPT_ENCODING, "gzip")
+        .no_decompress()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let vary: Vec<_> = response.headers().get_all(VARY).collect();
+    assert_eq!(vary, vec!["Origin, Accept-Encoding"]);
+}
+
 #[ntex::test]
 async fn test_encoding() {
     let srv = test::server_with(test::config().h1(), || {
@@ -641,6 +750,52 @@ async fn test_reading_deflate_encoding_large_random() {
     assert_eq!(bytes, Bytes::from(data));
 }
 
+#[cfg(feature = "zstd")]
+#[ntex::test]
+async fn test_zstd_encoding() {
+    let data = STR.repeat(10);
+    let srv = test::server_with(test::config().h1(), || {
+        App::new().service(web::resource("/").route(web::to(move |body: Bytes| async {
+            HttpResponse::Ok().body(body)
+        })))
+    });
+
+    let enc = zstd::stream::encode_all(data.as_bytes(), 5).unwrap();
+
+    // client request
+    let request = srv
+        .post("/")
+        .header(CONTENT_ENCODING, "zstd")
+        .send_body(enc);
+    let mut response = request.await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(data));
+
+    // single segment frame with one raw block, as defined by RFC 8878
+    let frame = b"\x28\xb5\x2f\xfd\x20\x05\x29\x00\x00hello";
+    let request = srv
+        .post("/")
+        .header(CONTENT_ENCODING, "zstd")
+        .send_body(Bytes::from_static(frame));
+    let mut response = request.await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"hello"));
+
+    // frame produced by zstd cli, `zstd -19` of `STR.repeat(100)`
+    let request = srv
+        .post("/")
+        .header(CONTENT_ENCODING, "zstd")
+        .send_body(Bytes::from_static(include_bytes!("test.zst")));
+    let mut response = request.await.unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(STR.repeat(100)));
+}
+
 #[ntex::test]
 async fn test_brotli_encoding() {
     let srv = test::server_with(test::config().h1(), || {
@@ -839,6 +994,78 @@ async fn test_brotli_encoding_large_openssl_h2() {
     assert_eq!(bytes, Bytes::from(data));
 }
 
+#[cfg(feature = "openssl")]
+#[ntex::test]
+async fn test_client_cert_openssl_h2() {
+    use ntex::web::types::ClientCert;
+    use tls_openssl::ssl::{
+        AlpnError, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+    };
+    use tls_openssl::x509::X509;
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    // request optional client certificate, accept self-signed certificates
+    builder.set_verify_callback(SslVerifyMode::PEER, |_, _| true);
+    builder.set_alpn_select_callback(|_, protos| {
+        const H2: &[u8] = b"\x02h2";
+        if protos.windows(3).any(|window| window == H2) {
+            Ok(b"h2")
+        } else {
+            Err(AlpnError::NOACK)
+        }
+    });
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+
+    let srv = test::server_with(test::config().openssl(builder.build()).h2(), || {
+        App::new().service(web::resource("/").route(web::to(
+            |cert: ClientCert| async move {
+                match cert.get() {
+                    Some(cert) => HttpResponse::Ok().body(cert.to_der()),
+                    None => HttpResponse::Unauthorized().finish(),
+                }
+            },
+        )))
+    });
+
+    // client without certificate
+    let response = srv.get("/").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // client with self-signed certificate
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder
+        .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+        .unwrap();
+    builder
+        .set_certificate_chain_file("./tests/cert.pem")
+        .unwrap();
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+    let client = client::Client::build()
+        .connector(
+            client::Connector::default()
+                .openssl(builder.build())
+                .finish(),
+        )
+        .finish();
+
+    let cert = X509::from_pem(include_bytes!("cert.pem")).unwrap();
+    // requests share one http/2 connection
+    for _ in 0..2 {
+        let mut response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.version(), ntex::http::Version::HTTP_2);
+        let bytes = response.body().await.unwrap();
+        assert_eq!(bytes, cert.to_der().unwrap());
+    }
+}
+
 #[cfg(all(feature = "rustls", feature = "openssl"))]
 #[ntex::test]
 async fn test_reading_deflate_encoding_large_random_rustls() {
@@ -1078,13 +1305,29 @@ async fn test_slow_request() {
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
     let mut data = String::new();
     let _ = stream.read_to_string(&mut data);
-    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(data.is_empty());
 
     let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
     let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
     let mut data = String::new();
     let _ = stream.read_to_string(&mut data);
     assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+
+    // idle keep-alive connection is closed without response
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!data.contains("408"));
+
+    // partial head of next keep-alive request
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("HTTP/1.1 408 Request Timeout"));
 }
 
 #[ntex::test]
@@ -1236,3 +1479,160 @@ async fn web_no_ws_payload() {
     let body = response.body().await.unwrap();
     assert_eq!(body, STR);
 }
+
+#[ntex::test]
+async fn test_sse() {
+    use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+    use std::{net, time::Duration};
+
+    use ntex::web::sse::{Event, SseStream};
+
+    struct Events(Option<Event>, Arc<AtomicBool>);
+
+    impl Stream for Events {
+        type Item = Event;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Event>> {
+            match self.0.take() {
+                Some(ev) => Poll::Ready(Some(ev)),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    impl Drop for Events {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::Relaxed);
+        }
+    }
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let dropped2 = dropped.clone();
+    let srv = test::server_with(test::config().h1(), move || {
+        let dropped = dropped2.clone();
+        App::new()
+            .wrap(Compress::default())
+            .service(web::resource("/").route(web::get().to(move || {
+                let events =
+                    Events(Some(Event::new("hello\nworld").id("1")), dropped.clone());
+                async move { SseStream::new(events).heartbeat(Millis(100)) }
+            })))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nAccept-Encoding: gzip\r\n\r\n");
+
+    // read response head, first event and heartbeat
+    let mut data = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&data).contains(":\n\n") {
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0);
+        data.extend_from_slice(&buf[..n]);
+    }
+    let data = String::from_utf8(data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("content-type: text/event-stream\r\n"));
+    assert!(data.contains("transfer-encoding: chunked\r\n"));
+    assert!(!data.contains("content-encoding"));
+    assert!(!data.contains("content-length"));
+    assert!(data.contains("id: 1\ndata: hello\ndata: world\n\n"));
+    assert!(!dropped.load(Ordering::Relaxed));
+
+    // client disconnects
+    drop(stream);
+    let mut n = 0;
+    while !dropped.load(Ordering::Relaxed) {
+        n += 1;
+        assert!(n < 50, "stream is not dropped");
+        sleep(Millis(100)).await;
+    }
+}
+
+#[ntex::test]
+async fn test_streaming_reader() {
+    use futures_io::AsyncRead;
+    use sha1::{Digest, Sha1};
+
+    const SIZE: usize = 50 * 1024 * 1024;
+
+    /// Reader of pseudo-random data, every other read is pending
+    struct Reader {
+        remaining: usize,
+        state: u64,
+        pending: bool,
+    }
+
+    impl Reader {
+        fn new() -> Self {
+            Reader {
+                remaining: SIZE,
+                state: 0x2545_f491_4f6c_dd1d,
+                pending: false,
+            }
+        }
+    }
+
+    impl AsyncRead for Reader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(self.remaining).min(7001);
+            for chunk in buf[..n].chunks_mut(8) {
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 7;
+                self.state ^= self.state << 17;
+                chunk.copy_from_slice(&self.state.to_le_bytes()[..chunk.len()]);
+            }
+            self.remaining -= n;
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    let mut expected = Sha1::new();
+    let mut reader = Reader::new();
+    let mut buf = vec![0; 65536];
+    loop {
+        let n = ntex::util::poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf))
+            .await
+            .unwrap();
+        if n == 0 {
+            break;
+        }
+        expected.update(&buf[..n]);
+    }
+
+    let srv = test::server_with(test::config().h1(), || {
+        App::new().service(web::resource("/").route(web::get().to(|| async {
+            HttpResponse::Ok().streaming_reader(Reader::new(), 32 * 1024)
+        })))
+    });
+
+    let mut response = srv.get("/").send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get(TRANSFER_ENCODING).unwrap(),
+        &b"chunked"[..]
+    );
+
+    // hash response body chunks as they arrive
+    let mut received = Sha1::new();
+    let mut size = 0;
+    while let Some(chunk) = ntex::util::stream_recv(&mut response).await {
+        let chunk = chunk.unwrap();
+        size += chunk.len();
+        received.update(&chunk);
+    }
+    assert_eq!(size, SIZE);
+    assert_eq!(received.finalize(), expected.finalize());
+}
@@ -1,10 +1,13 @@
-use std::io;
+use std::io::{self, Read, Write};
+use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
+use std::{net, time};
 
-use ntex::http::StatusCode;
+use ntex::http::{header, StatusCode};
 use ntex::service::{fn_factory_with_config, fn_service};
+use ntex::time::Seconds;
 use ntex::util::{ByteString, Bytes};
 use ntex::web::{self, test, ws, App, HttpRequest, HttpResponse};
-use ntex::ws::error::WsClientError;
+use ntex::ws::{error::WsClientError, Item, WsClient};
 
 async fn service(msg: ws::Frame) -> Result<Option<ws::Message>, io::Error> {
     let msg = match msg {
@@ -66,6 +69,74 @@ async fn web_ws() {
     assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Away.into())));
 }
 
+#[ntex::test]
+async fn web_ws_first_frame_with_handshake() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest| async move {
+                ws::start::<_, _, web::Error>(
+                    req,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(service))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    // first client frame arrives in the same packet as handshake request
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(time::Duration::from_secs(5)))
+        .unwrap();
+    let mut data = b"GET / HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        Sec-WebSocket-Version: 13\r\n\r\n"
+        .to_vec();
+    // masked text frame with zero mask
+    data.extend_from_slice(&[0x81, 0x84, 0, 0, 0, 0]);
+    data.extend_from_slice(b"text");
+    stream.write_all(&data).unwrap();
+
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buf.ends_with(b"\r\n\r\n\x81\x04text") {
+        let n = stream.read(&mut chunk).unwrap();
+        assert!(n > 0, "connection is closed: {:?}", buf);
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    assert!(buf.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+}
+
+#[ntex::test]
+async fn web_ws_overflow() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest| async move {
+                ws::start::<_, _, web::Error>(
+                    req,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(service))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    // frame is larger than default max size
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    io.send(ws::Message::Binary(Bytes::from(vec![0u8; 70_000])), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Size.into())));
+}
+
 #[ntex::test]
 async fn web_no_ws() {
     let srv = test::server(|| {
@@ -137,3 +208,375 @@ async fn web_ws_client() {
     // TODO fix
     on_disconnect.await
 }
+
+#[ntex::test]
+async fn web_ws_protocols() {
+    let srv = test::server(|| {
+        App::new()
+            .service(
+                web::resource("/").route(web::to(|req: HttpRequest| async move {
+                    let protocols = ws::Protocols::new(["graphql-ws", "chat"]);
+                    ws::start_with_protocols::<_, _, web::Error>(
+                        req,
+                        &protocols,
+                        fn_factory_with_config(|sink: ws::WsSink| async move {
+                            let proto = sink.protocol().unwrap_or("none").to_string();
+                            Ok::<_, web::Error>(fn_service(move |_: ws::Frame| {
+                                let msg = ws::Message::Text(proto.clone().into());
+                                async move { Ok::<_, io::Error>(Some(msg)) }
+                            }))
+                        }),
+                    )
+                    .await
+                })),
+            )
+            .service(web::resource("/required").route(web::to(
+                |req: HttpRequest| async move {
+                    let protocols = ws::Protocols::new(["chat"]).required();
+                    ws::start_with_protocols::<_, _, web::Error>(
+                        req,
+                        &protocols,
+                        fn_factory_with_config(|_| async {
+                            Ok::<_, web::Error>(fn_service(service))
+                        }),
+                    )
+                    .await
+                },
+            )))
+    });
+
+    // first supported protocol offered by client is selected
+    let conn = WsClient::build(srv.url("/"))
+        .address(srv.addr())
+        .protocols(["mqtt", "chat", "graphql-ws"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(conn.protocol(), Some("chat"));
+    assert_eq!(conn.sink().protocol(), Some("chat"));
+
+    let (io, codec, _) = conn.into_inner();
+    io.send(ws::Message::Text(ByteString::from_static("text")), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"chat")));
+
+    // no protocol
+    let (io, codec, res) = srv.ws().await.unwrap().into_inner();
+    assert!(!res.headers().contains_key(header::SEC_WEBSOCKET_PROTOCOL));
+    io.send(ws::Message::Text(ByteString::from_static("text")), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"none")));
+
+    // protocol is required
+    assert!(matches!(
+        srv.ws_at("/required").await.err().unwrap(),
+        WsClientError::InvalidResponseStatus(StatusCode::BAD_REQUEST)
+    ));
+    let conn = WsClient::build(srv.url("/required"))
+        .address(srv.addr())
+        .protocols(["chat"])
+        .finish()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(conn.protocol(), Some("chat"));
+}
+
+#[ntex::test]
+async fn web_ws_test_builder() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest| async move {
+                let value = req
+                    .headers()
+                    .get("x-test")
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+                ws::start_with_protocols::<_, _, web::Error>(
+                    req,
+                    &ws::Protocols::new(["chat"]),
+                    fn_factory_with_config(move |_| {
+                        let value = value.clone();
+                        async move {
+                            Ok::<_, web::Error>(fn_service(move |frame: ws::Frame| {
+                                let value = value.clone();
+                                async move {
+                                    match frame {
+                                        ws::Frame::Text(_) => {
+                                            Ok(Some(ws::Message::Text(value.into())))
+                                        }
+                                        frame => service(frame).await,
+                                    }
+                                }
+                            }))
+                        }
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    let conn = srv
+        .ws_builder("/")
+        .protocols(["mqtt", "chat"])
+        .header("x-test", "value")
+        .timeout(Seconds(5))
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(conn.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(conn.protocol(), Some("chat"));
+    assert!(conn.headers().contains_key(header::SEC_WEBSOCKET_ACCEPT));
+
+    conn.send_text("text").await;
+    assert_eq!(conn.expect_text().await, "value");
+    conn.send_binary("bin").await;
+    assert_eq!(conn.expect_binary().await, Bytes::from_static(b"bin"));
+    conn.send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await;
+    conn.expect_close(ws::CloseCode::Away).await;
+
+    let conn = srv.ws_builder("/").connect().await.unwrap();
+    assert_eq!(conn.protocol(), None);
+}
+
+#[ntex::test]
+async fn web_ws_continuation() {
+    let srv = test::server(|| {
+        App::new()
+            .service(
+                web::resource("/").route(web::to(|req: HttpRequest| async move {
+                    let config = ws::WsConfig::default().max_message_size(10);
+                    ws::start_with_config::<_, _, web::Error>(
+                        req,
+                        &config,
+                        fn_factory_with_config(|_| async {
+                            Ok::<_, web::Error>(fn_service(service))
+                        }),
+                    )
+                    .await
+                })),
+            )
+            .service(
+                web::resource("/raw").route(web::to(|req: HttpRequest| async move {
+                    let config = ws::WsConfig::default().aggregate_continuation(false);
+                    ws::start_with_config::<_, _, web::Error>(
+                        req,
+                        &config,
+                        fn_factory_with_config(|_| async {
+                            Ok::<_, web::Error>(fn_service(|msg: ws::Frame| async move {
+                                match msg {
+                                    ws::Frame::Continuation(item) => Ok::<_, io::Error>(
+                                        Some(ws::Message::Continuation(item)),
+                                    ),
+                                    _ => panic!(),
+                                }
+                            }))
+                        }),
+                    )
+                    .await
+                })),
+            )
+    });
+
+    // fragments are delivered as one message
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    for item in [
+        Item::FirstText(Bytes::from_static(b"Hel")),
+        Item::Continue(Bytes::from_static(b"lo ")),
+        Item::Last(Bytes::from_static(b"wo")),
+    ] {
+        io.send(ws::Message::Continuation(item), &codec)
+            .await
+            .unwrap();
+    }
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"Hello wo")));
+
+    // message is too big
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    for item in [
+        Item::FirstBinary(Bytes::from_static(b"012345")),
+        Item::Last(Bytes::from_static(b"6789ab")),
+    ] {
+        io.send(ws::Message::Continuation(item), &codec)
+            .await
+            .unwrap();
+    }
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Size.into())));
+
+    // raw fragments
+    let (io, codec, _) = srv.ws_at("/raw").await.unwrap().into_inner();
+    io.send(
+        ws::Message::Continuation(Item::FirstText(Bytes::from_static(b"Hel"))),
+        &codec,
+    )
+    .await
+    .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(
+        item,
+        ws::Frame::Continuation(Item::FirstText(Bytes::from_static(b"Hel")))
+    );
+}
+
+#[ntex::test]
+async fn web_ws_write_limit() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let dropped2 = dropped.clone();
+
+    let srv = test::server(move || {
+        let dropped = dropped2.clone();
+
+        App::new()
+            .service(web::resource("/").route(web::to(move |req: HttpRequest| {
+                let dropped = dropped.clone();
+                async move {
+                    let config = ws::WsConfig::default()
+                        .write_buffer_limit(16 * 1024)
+                        .on_write_buffer_overflow(move |_, _| {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        });
+                    ws::start_with_config::<_, _, web::Error>(
+                        req,
+                        &config,
+                        fn_factory_with_config(|sink: ws::WsSink| async move {
+                            Ok::<_, web::Error>(fn_service(move |_: ws::Frame| {
+                                let sink = sink.clone();
+                                async move { Ok::<_, io::Error>(produce(&sink).await) }
+                            }))
+                        }),
+                    )
+                    .await
+                }
+            })))
+            .service(web::resource("/close").route(web::to(
+                |req: HttpRequest| async move {
+                    let config = ws::WsConfig::default().write_buffer_limit(16 * 1024);
+                    ws::start_with_config::<_, _, web::Error>(
+                        req,
+                        &config,
+                        fn_factory_with_config(|sink: ws::WsSink| async move {
+                            Ok::<_, web::Error>(fn_service(move |_: ws::Frame| {
+                                let sink = sink.clone();
+                                async move {
+                                    produce(&sink).await;
+                                    Ok::<_, io::Error>(None)
+                                }
+                            }))
+                        }),
+                    )
+                    .await
+                },
+            )))
+    });
+
+    // write 64kb without yielding to write task, buffer size is bounded
+    async fn produce(sink: &ws::WsSink) -> Option<ws::Message> {
+        let mut max = 0;
+        for _ in 0..64 {
+            sink.send(ws::Message::Binary(Bytes::from(vec![0u8; 1024])))
+                .await
+                .unwrap();
+            max = std::cmp::max(max, sink.buffered_bytes());
+        }
+        Some(ws::Message::Text(max.to_string().into()))
+    }
+
+    // extra messages are discarded
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    io.send(ws::Message::Text(ByteString::from_static("go")), &codec)
+        .await
+        .unwrap();
+    let mut received = 0;
+    let max: usize = loop {
+        match io.recv(&codec).await.unwrap().unwrap() {
+            ws::Frame::Binary(_) => received += 1,
+            ws::Frame::Text(max) => break String::from_utf8_lossy(&max).parse().unwrap(),
+            _ => panic!(),
+        }
+    };
+    assert!(max < 17 * 1024 + 64);
+    assert!(received < 64);
+    assert_eq!(received + dropped.load(Ordering::Relaxed), 64);
+
+    // connection is closed with 1013 code
+    let (io, codec, _) = srv.ws_at("/close").await.unwrap().into_inner();
+    io.send(ws::Message::Text(ByteString::from_static("go")), &codec)
+        .await
+        .unwrap();
+    let item = loop {
+        match io.recv(&codec).await.unwrap().unwrap() {
+            ws::Frame::Binary(_) => (),
+            item => break item,
+        }
+    };
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Again.into())));
+}
+
+#[ntex::test]
+async fn web_ws_heartbeat() {
+    let timeouts = Arc::new(AtomicUsize::new(0));
+    let timeouts2 = timeouts.clone();
+
+    let srv = test::server(move || {
+        let timeouts = timeouts2.clone();
+
+        App::new().service(web::resource("/").route(web::to(move |req: HttpRequest| {
+            let timeouts = timeouts.clone();
+            async move {
+                let config = ws::WsConfig::default()
+                    .heartbeat(Seconds(1), Seconds(2))
+                    .on_heartbeat_timeout(move |_| {
+                        timeouts.fetch_add(1, Ordering::Relaxed);
+                    });
+                ws::start_with_config::<_, _, web::Error>(
+                    req,
+                    &config,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(|_: ws::Frame| async {
+                            Ok::<_, io::Error>(None)
+                        }))
+                    }),
+                )
+                .await
+            }
+        })))
+    });
+
+    // any frame confirms liveness
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    for _ in 0..3 {
+        let item = io.recv(&codec).await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Ping(Bytes::new()));
+        io.send(ws::Message::Text(ByteString::from_static("text")), &codec)
+            .await
+            .unwrap();
+    }
+    assert_eq!(timeouts.load(Ordering::Relaxed), 0);
+
+    // heartbeat stops on disconnect
+    drop(io);
+    ntex::time::sleep(Seconds(3)).await;
+    assert_eq!(timeouts.load(Ordering::Relaxed), 0);
+
+    // silent client
+    let (io, codec, _) = srv.ws().await.unwrap().into_inner();
+    let item = loop {
+        match io.recv(&codec).await.unwrap().unwrap() {
+            ws::Frame::Ping(_) => (),
+            item => break item,
+        }
+    };
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Away.into())));
+    assert_eq!(timeouts.load(Ordering::Relaxed), 1);
+}